@@ -4,7 +4,7 @@ use std::time::Duration;
 
 use criterion::{criterion_group, criterion_main, Criterion, Throughput};
 
-use beancount_parser::BeancountFile;
+use beancount_parser::{parse_with_options, BeancountFile, Date, ParseOptions};
 
 const SAMPLE: &str = include_str!("../tests/samples/official.beancount");
 
@@ -14,8 +14,24 @@ pub fn run_bench(c: &mut Criterion) {
     group.throughput(Throughput::Bytes(SAMPLE.len() as u64));
     group.measurement_time(Duration::from_secs(10));
     group.bench_function("parse", |b| b.iter(|| SAMPLE.parse::<BeancountFile<f64>>()));
+    let one_month =
+        ParseOptions::default().date_range(Date::new(2023, 3, 1), Date::new(2023, 4, 1));
+    group.bench_function("parse one month", |b| {
+        b.iter(|| parse_with_options::<f64>(SAMPLE, &one_month));
+    });
     group.finish();
 }
 
-criterion_group!(benches, run_bench);
+pub fn clone_bench(c: &mut Criterion) {
+    let Ok(file) = SAMPLE.parse::<BeancountFile<f64>>() else {
+        return;
+    };
+    let mut group = c.benchmark_group("clone official example");
+    group.bench_function("clone file", |b| b.iter(|| file.clone()));
+    let snapshot = file.clone().into_snapshot();
+    group.bench_function("clone snapshot", |b| b.iter(|| snapshot.clone()));
+    group.finish();
+}
+
+criterion_group!(benches, run_bench, clone_bench);
 criterion_main!(benches);
@@ -9,15 +9,14 @@ use std::{
 use nom::{
     bytes::complete::take_while,
     character::complete::{char, satisfy, space0, space1},
-    combinator::{all_consuming, cut, iterator, opt, recognize},
+    combinator::{cut, iterator, opt, recognize},
     multi::many1_count,
-    sequence::{delimited, preceded},
-    Finish,
+    sequence::{delimited, preceded, tuple},
 };
 
 use crate::{
     amount::{self, Amount, Currency},
-    Decimal, Span,
+    unicode, Code, Number, Span,
 };
 
 use super::IResult;
@@ -34,15 +33,75 @@ use super::IResult;
 /// let DirectiveContent::Open(open) = &beancount.directives[0].content else { unreachable!() };
 /// assert_eq!(open.account.as_str(), "Assets:Bank:Checking");
 /// ```
+///
+/// # Equality, ordering and hashing
+///
+/// Equality, ordering and hashing are defined by the string content only.
+/// Two accounts with the same name are equal and hash identically
+/// no matter how, or by which parse, they were created.
+/// The ordering is the lexicographic ordering of the name.
+///
+/// This makes it safe to use accounts as keys of maps that are persisted and reloaded.
+///
+/// # Serde
+///
+/// With the `serde` feature, the account is (de)serialized as a plain string.
+/// Deserialization fails if the string is not a valid account name.
 #[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
 pub struct Account(Arc<str>);
 
 impl Account {
+    /// Create an account from its name, validating it like the parser does
+    ///
+    /// The name must have at least two components separated by `:`,
+    /// each starting with an uppercase letter or a digit, and containing only letters, digits and `-`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error`](crate::Error) with the code [`Code::InvalidAccount`] if the name is not a valid account name
+    ///
+    /// # Example
+    /// ```
+    /// use beancount_parser::{Account, Code};
+    /// assert_eq!(Account::try_new("Assets:Bank").unwrap().as_str(), "Assets:Bank");
+    /// let error = Account::try_new("Assets:Bank:").unwrap_err();
+    /// assert_eq!(error.code(), Code::InvalidAccount);
+    /// assert_eq!(error.to_string(), "error[E005]: Invalid account name at line: 1 (empty account component)");
+    /// ```
+    pub fn try_new(name: &str) -> Result<Self, crate::Error> {
+        match invalid_reason(name) {
+            None => Ok(Self(Arc::from(name))),
+            Some((offset, reason)) => {
+                Err(crate::Error::at(name, offset, 1, Code::InvalidAccount).with_hint(reason))
+            }
+        }
+    }
+
+    /// Create an account from a name known to be valid
+    pub(crate) fn new_unchecked(name: &str) -> Self {
+        Self(name.into())
+    }
+
     /// Returns underlying string representation
     #[must_use]
     pub fn as_str(&self) -> &str {
         &self.0
     }
+
+    /// Returns the parent account (`Assets:Bank` for `Assets:Bank:Checking`)
+    ///
+    /// The parent of a two-components account is the root (like `Assets`),
+    /// and a root has no parent.
+    pub(crate) fn parent(&self) -> Option<Account> {
+        self.0
+            .rfind(':')
+            .map(|index| Account(Arc::from(&self.0[..index])))
+    }
+
+    /// Returns the last component of the account name (`Checking` for `Assets:Bank:Checking`)
+    pub(crate) fn leaf_name(&self) -> &str {
+        self.0.rsplit(':').next().unwrap_or(&self.0)
+    }
 }
 
 impl Display for Account {
@@ -67,14 +126,23 @@ impl FromStr for Account {
     type Err = crate::Error;
 
     fn from_str(input: &str) -> Result<Self, Self::Err> {
-        let spanned = Span::new(input);
-        match all_consuming(parse)(spanned).finish() {
-            Ok((_, account)) => Ok(account),
-            Err(err) => {
-                println!("{err:?}");
-                Err(Self::Err::new(input, spanned))
-            }
-        }
+        Self::try_new(input)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Account {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Account {
+    fn deserialize<De: serde::Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse()
+            .map_err(|_| serde::de::Error::custom(format!("invalid account name: {name}")))
     }
 }
 
@@ -89,6 +157,11 @@ impl FromStr for Account {
 /// assert_eq!(open.account.as_str(), "Assets:Bank:Checking");
 /// assert_eq!(open.currencies.iter().next().unwrap().as_str(), "CHF");
 /// ```
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
 #[derive(Debug, Clone, PartialEq)]
 #[non_exhaustive]
 pub struct Open {
@@ -127,6 +200,20 @@ impl From<&str> for BookingMethod {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for BookingMethod {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_ref())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for BookingMethod {
+    fn deserialize<De: serde::Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+        String::deserialize(deserializer).map(|name| name.as_str().into())
+    }
+}
+
 /// Close account directive
 ///
 /// # Example
@@ -137,6 +224,11 @@ impl From<&str> for BookingMethod {
 /// let DirectiveContent::Close(close) = &beancount.directives[0].content else { unreachable!() };
 /// assert_eq!(close.account.as_str(), "Assets:Bank:Checking");
 /// ```
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
 #[derive(Debug, Clone, PartialEq)]
 #[non_exhaustive]
 pub struct Close {
@@ -156,6 +248,11 @@ pub struct Close {
 /// assert_eq!(balance.amount.value, 10.0);
 /// assert_eq!(balance.amount.currency.as_str(), "CHF");
 /// ```
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
 #[derive(Debug, Clone, PartialEq)]
 #[non_exhaustive]
 pub struct Balance<D> {
@@ -180,6 +277,11 @@ pub struct Balance<D> {
 /// assert_eq!(pad.account.as_str(), "Assets:BofA:Checking");
 /// assert_eq!(pad.source_account.as_str(), "Equity:Opening-Balances");
 /// ```
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
 #[derive(Debug, Clone, PartialEq)]
 #[non_exhaustive]
 pub struct Pad {
@@ -189,23 +291,64 @@ pub struct Pad {
     pub source_account: Account,
 }
 
+/// Parse an account name
+///
+/// Empty components (like in `Assets::Cash`, `Assets:Cash:` or `:Assets:Cash`) are accepted here,
+/// so that they can be reported with a specific error (see [`invalid_reason`]) instead of a syntax error.
 pub(super) fn parse(input: Span<'_>) -> IResult<'_, Account> {
-    let (input, name) = recognize(preceded(
-        preceded(
-            satisfy(|c: char| c.is_uppercase() || c.is_ascii_digit()),
-            take_while(|c: char| c.is_alphanumeric() || c == '-'),
-        ),
-        cut(many1_count(preceded(
-            char(':'),
-            preceded(
-                satisfy(|c: char| c.is_uppercase() || c.is_ascii_digit()),
-                take_while(|c: char| c.is_alphanumeric() || c == '-'),
-            ),
-        ))),
-    ))(input)?;
+    let (input, name) = recognize(tuple((
+        opt(char(':')),
+        component,
+        cut(many1_count(preceded(char(':'), opt(component)))),
+    )))(input)?;
     Ok((input, Account(Arc::from(*name.fragment()))))
 }
 
+fn component(input: Span<'_>) -> IResult<'_, Span<'_>> {
+    recognize(preceded(
+        satisfy(|c: char| c.is_uppercase() || c.is_ascii_digit()),
+        take_while(is_account_char),
+    ))(input)
+}
+
+/// Returns true if the character may follow the first character of an account component
+///
+/// Combining marks are accepted, so that the names written in decomposed form (NFD) can be parsed.
+fn is_account_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || unicode::is_combining_mark(c)
+}
+
+/// Returns the offset and the reason of the first problem of the account name, if any
+pub(crate) fn invalid_reason(name: &str) -> Option<(usize, &'static str)> {
+    if name.is_empty() {
+        return Some((0, "empty account name"));
+    }
+    let mut offset = 0;
+    let mut components = 0;
+    for component in name.split(':') {
+        components += 1;
+        let mut chars = component.chars();
+        match chars.next() {
+            None => return Some((offset, "empty account component")),
+            Some(c) if !(c.is_uppercase() || c.is_ascii_digit()) => {
+                return Some((
+                    offset,
+                    "account component must start with an uppercase letter or a digit",
+                ));
+            }
+            Some(_) => (),
+        }
+        if let Some(index) = component.find(|c: char| !is_account_char(c)) {
+            return Some((offset + index, "invalid character in account name"));
+        }
+        offset += component.len() + 1;
+    }
+    (components < 2).then_some((
+        name.len(),
+        "missing account component after the account type",
+    ))
+}
+
 pub(super) fn open(input: Span<'_>) -> IResult<'_, Open> {
     let (input, account) = parse(input)?;
     let (input, currencies) = opt(preceded(space1, currencies))(input)?;
@@ -236,15 +379,16 @@ pub(super) fn close(input: Span<'_>) -> IResult<'_, Close> {
     Ok((input, Close { account }))
 }
 
-pub(super) fn balance<D: Decimal>(input: Span<'_>) -> IResult<'_, Balance<D>> {
+pub(super) fn balance<D: Number>(input: Span<'_>) -> IResult<'_, Balance<D>> {
     let (input, account) = parse(input)?;
     let (input, _) = space1(input)?;
     let (input, value) = amount::expression(input)?;
+    let decimals = amount::decimals(input);
     let (input, tolerance) = opt(preceded(space0, tolerance))(input)?;
     let (input, _) = space1(input)?;
     let (input, currency) = amount::currency(input)?;
     Ok((
-        input,
+        amount::with_amount(input, decimals),
         Balance {
             account,
             amount: Amount { value, currency },
@@ -253,7 +397,7 @@ pub(super) fn balance<D: Decimal>(input: Span<'_>) -> IResult<'_, Balance<D>> {
     ))
 }
 
-fn tolerance<D: Decimal>(input: Span<'_>) -> IResult<'_, D> {
+fn tolerance<D: Number>(input: Span<'_>) -> IResult<'_, D> {
     let (input, _) = char('~')(input)?;
     let (input, _) = space0(input)?;
     let (input, tolerance) = amount::expression(input)?;
@@ -0,0 +1,213 @@
+use std::collections::{btree_map, BTreeMap};
+
+use crate::{Account, Date, Directive, DirectiveContent, Evaluate, Inventory};
+
+/// Hierarchy of the accounts found in a beancount file
+///
+/// Use [`BeancountFile::account_tree`](crate::BeancountFile::account_tree) to get an instance of this.
+///
+/// Every component of an account name is a node of the tree.
+/// The parent accounts that are only implied by their children (like `Assets:Bank` implied by `Assets:Bank:Checking`)
+/// are also represented as nodes, but are flagged as implicit (see [`AccountNode::is_implicit`]).
+///
+/// # Example
+///
+/// ```
+/// use beancount_parser::BeancountFile;
+/// let input = r#"
+/// 2023-01-01 open Assets:Bank:Checking
+/// 2023-01-01 open Assets:Bank:Savings
+/// 2023-01-01 open Assets:Cash
+/// 2023-12-31 close Assets:Cash
+/// "#;
+/// let beancount: BeancountFile<f64> = input.parse().unwrap();
+/// let tree = beancount.account_tree();
+///
+/// let bank = tree.get("Assets:Bank").unwrap();
+/// assert!(bank.is_implicit());
+/// let children: Vec<&str> = bank.children().map(|node| node.name()).collect();
+/// assert_eq!(children, ["Checking", "Savings"]);
+///
+/// let cash = tree.get("Assets:Cash").unwrap();
+/// assert!(!cash.is_implicit());
+/// assert_eq!(cash.close, Some("2023-12-31".parse().unwrap()));
+///
+/// let accounts: Vec<&str> = tree.iter().map(|node| node.account.as_str()).collect();
+/// assert_eq!(
+///     accounts,
+///     ["Assets", "Assets:Bank", "Assets:Bank:Checking", "Assets:Bank:Savings", "Assets:Cash"]
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct AccountTree {
+    roots: BTreeMap<String, AccountNode>,
+}
+
+/// A node of the [`AccountTree`]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct AccountNode {
+    /// Full name of the account
+    pub account: Account,
+    /// Date of the `open` directive, if any
+    pub open: Option<Date>,
+    /// Date of the `close` directive, if any
+    pub close: Option<Date>,
+    children: BTreeMap<String, AccountNode>,
+}
+
+impl AccountTree {
+    /// Returns the node of the given account, if it exists in the tree
+    ///
+    /// The account may be a root (like `Assets`) or any implied parent account.
+    #[must_use]
+    pub fn get(&self, account: &str) -> Option<&AccountNode> {
+        let mut components = account.split(':');
+        let mut node = self.roots.get(components.next()?)?;
+        for component in components {
+            node = node.children.get(component)?;
+        }
+        Some(node)
+    }
+
+    /// Returns the root nodes (like `Assets`, `Income`, etc.) sorted by name
+    pub fn roots(&self) -> impl Iterator<Item = &AccountNode> {
+        self.roots.values()
+    }
+
+    /// Returns an iterator over all the nodes, depth-first
+    ///
+    /// Each node is visited before its children, and the children are visited in order of their name.
+    pub fn iter(&self) -> impl Iterator<Item = &AccountNode> {
+        DepthFirst {
+            stack: vec![self.roots.values()],
+        }
+    }
+
+    /// Returns the total of every node of the tree: its own balance plus the balances of all its descendants
+    ///
+    /// The `balances` are typically obtained with [`BeancountFile::balances`](crate::BeancountFile::balances).
+    /// Every node of the tree (including implicit ones) is present in the result, sorted by account name.
+    /// Balances of accounts that are not in the tree are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::{Amount, BeancountFile};
+    /// let input = r#"
+    /// 2023-01-01 * "Shopping"
+    ///   Expenses:Food:Groceries   10 CHF
+    ///   Expenses:Food:Restaurant  20 CHF
+    ///   Expenses:Transport         5 EUR
+    ///   Assets:Cash              -30 CHF
+    ///   Assets:Card               -5 EUR
+    /// "#;
+    /// let beancount: BeancountFile<f64> = input.parse().unwrap();
+    /// let totals = beancount.account_tree().rollup(&beancount.balances(None));
+    /// assert_eq!(totals["Expenses:Food"].get("CHF"), Some(&30.0));
+    /// let expenses: Vec<Amount<f64>> = totals["Expenses"].clone().into();
+    /// assert_eq!(expenses.len(), 2);
+    /// assert!(totals["Assets"].get("CHF").is_some());
+    /// ```
+    #[must_use]
+    pub fn rollup<D: Evaluate>(
+        &self,
+        balances: &BTreeMap<Account, Inventory<D>>,
+    ) -> BTreeMap<Account, Inventory<D>> {
+        let mut totals = BTreeMap::new();
+        for root in self.roots.values() {
+            root.rollup(balances, &mut totals);
+        }
+        totals
+    }
+
+    fn node_mut(&mut self, account: &Account) -> &mut AccountNode {
+        let siblings = match account.parent() {
+            Some(parent) => &mut self.node_mut(&parent).children,
+            None => &mut self.roots,
+        };
+        siblings
+            .entry(account.leaf_name().to_owned())
+            .or_insert_with(|| AccountNode {
+                account: account.clone(),
+                open: None,
+                close: None,
+                children: BTreeMap::new(),
+            })
+    }
+}
+
+impl AccountNode {
+    /// Returns the name of the last component of the account (`Checking` for `Assets:Bank:Checking`)
+    #[must_use]
+    pub fn name(&self) -> &str {
+        self.account.leaf_name()
+    }
+
+    /// Returns true if there is no `open` directive for this account
+    ///
+    /// This is the case of the parent accounts that only exist because of their children.
+    #[must_use]
+    pub fn is_implicit(&self) -> bool {
+        self.open.is_none()
+    }
+
+    /// Returns the children nodes sorted by name
+    pub fn children(&self) -> impl Iterator<Item = &AccountNode> {
+        self.children.values()
+    }
+
+    fn rollup<'a, D: Evaluate>(
+        &self,
+        balances: &BTreeMap<Account, Inventory<D>>,
+        totals: &'a mut BTreeMap<Account, Inventory<D>>,
+    ) -> &'a Inventory<D> {
+        let mut total = balances.get(&self.account).cloned().unwrap_or_default();
+        for child in self.children.values() {
+            total.merge(child.rollup(balances, totals));
+        }
+        totals.entry(self.account.clone()).or_insert(total)
+    }
+}
+
+struct DepthFirst<'a> {
+    stack: Vec<btree_map::Values<'a, String, AccountNode>>,
+}
+
+impl<'a> Iterator for DepthFirst<'a> {
+    type Item = &'a AccountNode;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let siblings = self.stack.last_mut()?;
+            if let Some(node) = siblings.next() {
+                self.stack.push(node.children.values());
+                return Some(node);
+            }
+            self.stack.pop();
+        }
+    }
+}
+
+pub(crate) fn build<'a, D: 'a>(
+    directives: impl IntoIterator<Item = &'a Directive<D>>,
+) -> AccountTree {
+    let mut tree = AccountTree::default();
+    for directive in directives {
+        match &directive.content {
+            DirectiveContent::Open(open) => {
+                let node = tree.node_mut(&open.account);
+                node.open = node.open.or(Some(directive.date));
+            }
+            DirectiveContent::Close(close) => {
+                let node = tree.node_mut(&close.account);
+                node.close = node.close.or(Some(directive.date));
+            }
+            content => content.for_each_account(|account| {
+                tree.node_mut(account);
+            }),
+        }
+    }
+    tree
+}
@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+
+use crate::{Account, BeancountFile};
+
+/// Prefix trie of the accounts referenced in a beancount file, to complete account names as they are typed
+///
+/// Use [`BeancountFile::account_trie`] to get an instance of this (requires the `trie` feature).
+///
+/// An account matches a query if each component of the query (separated by `:`) is a prefix of the component
+/// of the account at the same position. So both a full prefix (`Expenses:Fo`) and an abbreviation of each component
+/// (`Ex:Fo:Gr`) match `Expenses:Food:Groceries`. The descendants of the matched accounts also match
+/// (`Ex:Fo` matches `Expenses:Food` and `Expenses:Food:Groceries`).
+///
+/// The components of the query are compared with "smart case":
+/// a component written in lowercase matches regardless of the case (`ex:fo` matches `Expenses:Food`),
+/// while a component containing an uppercase letter must match exactly (`EX` does not match `Expenses`).
+///
+/// # Example
+///
+/// ```
+/// use beancount_parser::BeancountFile;
+/// let input = r#"
+/// 2023-01-01 open Expenses:Food:Groceries
+/// 2023-01-01 open Expenses:Food:Restaurant
+/// 2023-01-01 open Expenses:Fuel
+/// 2023-01-01 open Assets:Cash
+/// 2023-01-02 * "Lunch"
+///   Expenses:Food:Restaurant  20 CHF
+///   Assets:Cash
+/// "#;
+/// let beancount: BeancountFile<f64> = input.parse().unwrap();
+/// let trie = beancount.account_trie();
+///
+/// let completions: Vec<&str> = trie.complete("Ex:F", 3).iter().map(|a| a.as_str()).collect();
+/// assert_eq!(completions, ["Expenses:Food:Restaurant", "Expenses:Food:Groceries", "Expenses:Fuel"]);
+/// ```
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct AccountTrie {
+    root: Node,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Node {
+    /// The account ending at this node, with the number of times it is referenced
+    account: Option<(Account, usize)>,
+    children: BTreeMap<String, Node>,
+}
+
+impl AccountTrie {
+    /// Returns at most `limit` accounts matching the query (see [`AccountTrie`] for the matching rules)
+    ///
+    /// The accounts are ordered by the number of directives and postings referencing them (most referenced first),
+    /// then by name. An empty query matches all the accounts.
+    #[must_use]
+    pub fn complete(&self, query: &str, limit: usize) -> Vec<&Account> {
+        let query: Vec<&str> = query.split(':').collect();
+        let mut matches = Vec::new();
+        self.root.collect_matches(&query, &mut matches);
+        matches.sort_by(|(account_a, uses_a), (account_b, uses_b)| {
+            uses_b.cmp(uses_a).then_with(|| account_a.cmp(account_b))
+        });
+        matches
+            .into_iter()
+            .take(limit)
+            .map(|(account, _)| account)
+            .collect()
+    }
+
+    /// Number of accounts in the trie
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.root.len()
+    }
+
+    /// Returns true if the trie has no account
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.root.children.is_empty()
+    }
+
+    fn insert(&mut self, account: &Account) {
+        let node = account
+            .as_str()
+            .split(':')
+            .fold(&mut self.root, |node, component| {
+                node.children.entry(component.to_owned()).or_default()
+            });
+        node.account.get_or_insert_with(|| (account.clone(), 0)).1 += 1;
+    }
+}
+
+impl Node {
+    fn collect_matches<'a>(&'a self, query: &[&str], matches: &mut Vec<(&'a Account, usize)>) {
+        let Some((component, rest)) = query.split_first() else {
+            if let Some((account, uses)) = &self.account {
+                matches.push((account, *uses));
+            }
+            for child in self.children.values() {
+                child.collect_matches(query, matches);
+            }
+            return;
+        };
+        for (name, child) in &self.children {
+            if component_matches(component, name) {
+                child.collect_matches(rest, matches);
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        usize::from(self.account.is_some()) + self.children.values().map(Node::len).sum::<usize>()
+    }
+}
+
+/// Returns true if the component of a query is a prefix of the account component, using "smart case"
+fn component_matches(query: &str, component: &str) -> bool {
+    if query.chars().any(char::is_uppercase) {
+        return component.starts_with(query);
+    }
+    let mut component = component.chars().flat_map(char::to_lowercase);
+    query.chars().all(|c| component.next() == Some(c))
+}
+
+pub(crate) fn build<D>(file: &BeancountFile<D>) -> AccountTrie {
+    let mut trie = AccountTrie::default();
+    for directive in &file.directives {
+        directive
+            .content
+            .for_each_account(|account| trie.insert(account));
+    }
+    trie
+}
@@ -1,6 +1,6 @@
 use std::{
     borrow::Borrow,
-    fmt::{Debug, Display, Formatter},
+    fmt::{Debug, Display, Formatter, Write},
     ops::{Add, Div, Mul, Neg, Sub},
     str::FromStr,
     sync::Arc,
@@ -8,14 +8,76 @@ use std::{
 
 use nom::{
     branch::alt,
-    bytes::complete::{take_while, take_while1},
-    character::complete::{char, one_of, satisfy, space0, space1},
-    combinator::{all_consuming, iterator, map_res, opt, recognize, verify},
+    bytes::complete::take_while,
+    character::complete::{char, digit1, one_of, satisfy, space0, space1},
+    combinator::{all_consuming, map_res, opt, recognize, verify},
+    error::ErrorKind,
     sequence::{delimited, preceded, terminated, tuple},
     Finish,
 };
 
-use crate::{IResult, Span};
+use crate::{
+    display::{self, Decimals},
+    AmountDisplay, IResult, ParseOptions, Span,
+};
+
+/// Error kind of the parser failing on a number in scientific notation (see [`Code::ScientificNotation`](crate::Code::ScientificNotation))
+pub(crate) const SCIENTIFIC_NOTATION: ErrorKind = ErrorKind::Float;
+
+/// Error kind of the parser failing on a number too precise for the number type (see [`Code::ExcessPrecision`](crate::Code::ExcessPrecision))
+pub(crate) const EXCESS_PRECISION: ErrorKind = ErrorKind::TooLarge;
+
+/// Error kind of the parser failing on an expression the number type cannot evaluate (see [`Number::evaluate`])
+pub(crate) const UNSUPPORTED_EXPRESSION: ErrorKind = ErrorKind::Permutation;
+
+/// State of the number literals, carried by the [`Span`]
+///
+/// The literals are parsed deep in the grammar, so instead of being passed to every parser,
+/// the options of [`ParseOptions`] are carried by the span (see `entry_with_options`).
+/// So are the decimals of the amounts parsed so far: as each parser returns them with the rest of its input,
+/// the amounts of an alternative that failed are forgotten with it.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Literals {
+    allow_scientific_notation: bool,
+    round_excess_precision: bool,
+    /// Largest number of decimals among the literals of the last expression
+    expression_decimals: usize,
+    /// Number of decimals of each amount of the directive being parsed
+    pub(crate) amounts: Decimals,
+}
+
+impl From<&ParseOptions> for Literals {
+    fn from(options: &ParseOptions) -> Self {
+        Self {
+            allow_scientific_notation: options.allow_scientific_notation,
+            round_excess_precision: options.round_excess_precision,
+            ..Self::default()
+        }
+    }
+}
+
+impl Literals {
+    /// Forget the decimals of the amounts, before parsing a directive
+    pub(crate) fn without_amounts(self) -> Self {
+        Self {
+            amounts: Decimals::default(),
+            ..self
+        }
+    }
+}
+
+/// Returns the number of decimals of the last expression parsed before `input`
+pub(crate) fn decimals(input: Span<'_>) -> usize {
+    input.extra.expression_decimals
+}
+
+/// Record an amount whose value has the given number of decimals
+pub(crate) fn with_amount(input: Span<'_>, decimals: usize) -> Span<'_> {
+    input.map_extra(|literals| Literals {
+        amounts: literals.amounts.push(decimals),
+        ..literals
+    })
+}
 
 /// Price directive
 ///
@@ -30,6 +92,11 @@ use crate::{IResult, Span};
 /// assert_eq!(price.amount.value, 4.0);
 /// assert_eq!(price.amount.currency.as_str(), "PLN");
 /// ```
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
 #[derive(Debug, Clone, PartialEq)]
 pub struct Price<D> {
     /// Currency
@@ -43,6 +110,11 @@ pub struct Price<D> {
 /// Where `D` is the decimal type (like `f64` or `rust_decimal::Decimal`)
 ///
 /// For an example, look at the [`Price`] directive
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
 #[derive(Debug, Clone, PartialEq)]
 pub struct Amount<D> {
     /// The value (decimal) part
@@ -51,15 +123,35 @@ pub struct Amount<D> {
     pub currency: Currency,
 }
 
+impl<D: Evaluate + Display> Amount<D> {
+    /// Render the amount for a human-facing report
+    ///
+    /// The result is not beancount syntax, and must not be written to a beancount file.
+    ///
+    /// See [`AmountDisplay`] for an example.
+    #[must_use]
+    pub fn display_with(&self, display: &AmountDisplay) -> String {
+        display::render(self, display)
+    }
+}
+
 /// Currency
 ///
 /// One may use [`Currency::as_str`] to get the string representation of the currency
 ///
 /// For an example, look at the [`Price`] directive
+///
+/// With the `serde` feature, the currency is (de)serialized as a plain string.
+/// Deserialization fails if the string is not a valid currency.
 #[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
 pub struct Currency(Arc<str>);
 
 impl Currency {
+    /// Create a currency from a name known to be valid
+    pub(crate) fn new_unchecked(name: &str) -> Self {
+        Self(name.into())
+    }
+
     /// Returns underlying string representation
     #[must_use]
     pub fn as_str(&self) -> &str {
@@ -95,7 +187,7 @@ impl<'a> TryFrom<&'a str> for Currency {
 impl FromStr for Currency {
     type Err = crate::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let span = Span::new(s);
+        let span = crate::span(s);
         match all_consuming(currency)(span).finish() {
             Ok((_, currency)) => Ok(currency),
             Err(_) => Err(crate::Error::new(s, span)),
@@ -103,52 +195,86 @@ impl FromStr for Currency {
     }
 }
 
-pub(crate) fn parse<D: Decimal>(input: Span<'_>) -> IResult<'_, Amount<D>> {
+#[cfg(feature = "serde")]
+impl serde::Serialize for Currency {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Currency {
+    fn deserialize<De: serde::Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse()
+            .map_err(|_| serde::de::Error::custom(format!("invalid currency: {name}")))
+    }
+}
+
+pub(crate) fn parse<D: Number>(input: Span<'_>) -> IResult<'_, Amount<D>> {
     let (input, value) = expression(input)?;
+    let decimals = decimals(input);
     let (input, _) = space1(input)?;
     let (input, currency) = currency(input)?;
-    Ok((input, Amount { value, currency }))
+    Ok((with_amount(input, decimals), Amount { value, currency }))
 }
 
-pub(crate) fn expression<D: Decimal>(input: Span<'_>) -> IResult<'_, D> {
+pub(crate) fn expression<D: Number>(input: Span<'_>) -> IResult<'_, D> {
+    let input = input.map_extra(|literals| Literals {
+        expression_decimals: 0,
+        ..literals
+    });
     alt((negation, sum))(input)
 }
 
-fn sum<D: Decimal>(input: Span<'_>) -> IResult<'_, D> {
-    let (input, value) = product(input)?;
-    let mut iter = iterator(
-        input,
-        tuple((delimited(space0, one_of("+-"), space0), product)),
-    );
-    let value = iter.fold(value, |a, (op, b)| match op {
-        '+' => a + b,
-        '-' => a - b,
-        op => unreachable!("unsupported operator: {}", op),
-    });
-    let (input, ()) = iter.finish()?;
-    Ok((input, value))
-}
-
-fn product<D: Decimal>(input: Span<'_>) -> IResult<'_, D> {
-    let (input, value) = atom(input)?;
-    let mut iter = iterator(
-        input,
-        tuple((delimited(space0, one_of("*/"), space0), atom)),
-    );
-    let value = iter.fold(value, |a, (op, b)| match op {
-        '*' => a * b,
-        '/' => a / b,
-        op => unreachable!("unsupported operator: {}", op),
-    });
-    let (input, ()) = iter.finish()?;
-    Ok((input, value))
+fn sum<D: Number>(start: Span<'_>) -> IResult<'_, D> {
+    let (mut input, mut value) = product(start)?;
+    loop {
+        let (rest, next) = opt(tuple((delimited(space0, one_of("+-"), space0), product)))(input)?;
+        let Some((op, b)) = next else {
+            return Ok((input, value));
+        };
+        let operation = match op {
+            '+' => Operation::Add(value, b),
+            '-' => Operation::Sub(value, b),
+            op => unreachable!("unsupported operator: {}", op),
+        };
+        value = evaluate(start, operation)?;
+        input = rest;
+    }
+}
+
+fn product<D: Number>(start: Span<'_>) -> IResult<'_, D> {
+    let (mut input, mut value) = atom(start)?;
+    loop {
+        let (rest, next) = opt(tuple((delimited(space0, one_of("*/"), space0), atom)))(input)?;
+        let Some((op, b)) = next else {
+            return Ok((input, value));
+        };
+        let operation = match op {
+            '*' => Operation::Mul(value, b),
+            '/' => Operation::Div(value, b),
+            op => unreachable!("unsupported operator: {}", op),
+        };
+        value = evaluate(start, operation)?;
+        input = rest;
+    }
+}
+
+/// Evaluate the operation of the expression starting at `input`, failing if the number type does not support expressions
+fn evaluate<D: Number>(
+    input: Span<'_>,
+    operation: Operation<D>,
+) -> Result<D, nom::Err<nom::error::Error<Span<'_>>>> {
+    D::evaluate(operation)
+        .ok_or_else(|| nom::Err::Failure(nom::error::Error::new(input, UNSUPPORTED_EXPRESSION)))
 }
 
-fn atom<D: Decimal>(input: Span<'_>) -> IResult<'_, D> {
+fn atom<D: Number>(input: Span<'_>) -> IResult<'_, D> {
     alt((literal, group))(input)
 }
 
-fn group<D: Decimal>(input: Span<'_>) -> IResult<'_, D> {
+fn group<D: Number>(input: Span<'_>) -> IResult<'_, D> {
     delimited(
         terminated(char('('), space0),
         expression,
@@ -156,25 +282,179 @@ fn group<D: Decimal>(input: Span<'_>) -> IResult<'_, D> {
     )(input)
 }
 
-fn negation<D: Decimal>(input: Span<'_>) -> IResult<'_, D> {
-    let (input, _) = char('-')(input)?;
-    let (input, _) = space0(input)?;
-    let (input, expr) = group::<D>(input)?;
-    Ok((input, -expr))
+fn negation<D: Number>(input: Span<'_>) -> IResult<'_, D> {
+    let (rest, _) = char('-')(input)?;
+    let (rest, _) = space0(rest)?;
+    let (rest, expr) = group::<D>(rest)?;
+    Ok((rest, evaluate(input, Operation::Neg(expr))?))
 }
 
-fn literal<D: Decimal>(input: Span<'_>) -> IResult<'_, D> {
-    map_res(
+fn literal<D: Number>(input: Span<'_>) -> IResult<'_, D> {
+    let (rest, sign) = opt(terminated(char('-'), space0))(input)?;
+    let (rest, digits) = verify(
         recognize(tuple((
-            opt(char('-')),
-            space0,
-            take_while1(|c: char| c.is_numeric() || c == '.' || c == ','),
+            take_while(|c: char| c.is_ascii_digit() || c == ','),
+            opt(preceded(
+                char('.'),
+                take_while(|c: char| c.is_ascii_digit()),
+            )),
         ))),
-        |s: Span<'_>| s.fragment().replace([',', ' '], "").parse(),
+        |s: &Span<'_>| s.fragment().chars().any(|c| c.is_ascii_digit()),
+    )(rest)?;
+    let (rest, exponent) = opt(exponent)(rest)?;
+    let options = input.extra;
+    if exponent.is_some() && !options.allow_scientific_notation {
+        return Err(nom::Err::Failure(nom::error::Error::new(
+            digits,
+            SCIENTIFIC_NOTATION,
+        )));
+    }
+    let (integer, fraction) = digits
+        .fragment()
+        .split_once('.')
+        .unwrap_or((digits.fragment(), ""));
+    let stripped;
+    let integer = if integer.contains(',') {
+        stripped = integer.replace(',', "");
+        &stripped
+    } else {
+        integer
+    };
+    let shifted;
+    let (integer, fraction) = match exponent {
+        Some(exponent) => {
+            shifted = shift_point(integer, fraction, exponent)
+                .ok_or_else(|| nom::Err::Error(nom::error::Error::new(input, ErrorKind::MapRes)))?;
+            (shifted.0.as_str(), shifted.1.as_str())
+        }
+        None => (integer, fraction),
+    };
+    let literal = Literal {
+        negative: sign.is_some(),
+        integer,
+        fraction,
+    };
+    let value = D::from_literal(literal)
+        .map_err(|_| nom::Err::Error(nom::error::Error::new(input, ErrorKind::MapRes)))?;
+    if !options.round_excess_precision && D::exceeds_precision(literal, &value) {
+        return Err(nom::Err::Failure(nom::error::Error::new(
+            digits,
+            EXCESS_PRECISION,
+        )));
+    }
+    let rest = rest.map_extra(|literals| Literals {
+        expression_decimals: literals.expression_decimals.max(literal.fraction.len()),
+        ..literals
+    });
+    Ok((rest, value))
+}
+
+/// Exponent of a number in scientific notation (like the `E-7` of `2.5E-7`), returned without the `E`
+fn exponent(input: Span<'_>) -> IResult<'_, i32> {
+    preceded(
+        one_of("eE"),
+        map_res(
+            recognize(tuple((opt(one_of("+-")), digit1))),
+            |e: Span<'_>| e.fragment().parse::<i32>(),
+        ),
     )(input)
 }
 
-pub(crate) fn price<D: Decimal>(input: Span<'_>) -> IResult<'_, Price<D>> {
+/// Largest exponent accepted in scientific notation, to bound the length of the plain decimal
+const MAX_EXPONENT: i32 = 1000;
+
+/// Returns the digits before and after the decimal point of `integer.fraction` multiplied by `10^exponent`
+///
+/// Returns `None` if the exponent is larger than [`MAX_EXPONENT`].
+fn shift_point(integer: &str, fraction: &str, exponent: i32) -> Option<(String, String)> {
+    if exponent.abs() > MAX_EXPONENT {
+        return None;
+    }
+    let digits = format!("{integer}{fraction}");
+    let point = i64::try_from(integer.len()).ok()? + i64::from(exponent);
+    let (integer, fraction) = match usize::try_from(point) {
+        Err(_) => {
+            let zeros = usize::try_from(point.unsigned_abs()).ok()?;
+            (String::new(), format!("{}{digits}", "0".repeat(zeros)))
+        }
+        Ok(point) if point >= digits.len() => (
+            format!("{digits}{}", "0".repeat(point - digits.len())),
+            String::new(),
+        ),
+        Ok(point) => (digits[..point].to_owned(), digits[point..].to_owned()),
+    };
+    let integer = integer.trim_start_matches('0');
+    let fraction = fraction.trim_end_matches('0');
+    Some((
+        if integer.is_empty() { "0" } else { integer }.to_owned(),
+        fraction.to_owned(),
+    ))
+}
+
+/// Returns the number in scientific notation at the start of `text` written as a plain decimal (like `0.00000025` for `2.5E-7`)
+pub(crate) fn plain_decimal(text: &str) -> Option<String> {
+    let end = text
+        .find(|c: char| !(c.is_ascii_digit() || matches!(c, ',' | '.' | 'e' | 'E' | '+' | '-')))
+        .unwrap_or(text.len());
+    let (mantissa, exponent) = text[..end].split_once(['e', 'E'])?;
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let (integer, fraction) =
+        shift_point(&integer.replace(',', ""), fraction, exponent.parse().ok()?)?;
+    let literal = Literal {
+        negative: false,
+        integer: &integer,
+        fraction: &fraction,
+    };
+    Some(literal.to_string())
+}
+
+/// Returns true if the number type does not distinguish the literal from the one whose last non-zero digit is changed by one
+///
+/// That is, if the last digits of the literal are lost.
+fn probe_precision<D: FromLiteral + PartialEq>(literal: Literal<'_>, value: &D) -> bool {
+    let fraction = literal.fraction.trim_end_matches('0');
+    with_buffer(literal.integer.len() + fraction.len(), |buffer| {
+        let (integer_digits, fraction_digits) = buffer.split_at_mut(literal.integer.len());
+        integer_digits.copy_from_slice(literal.integer.as_bytes());
+        fraction_digits.copy_from_slice(fraction.as_bytes());
+        let digits = if fraction.is_empty() {
+            &mut *integer_digits
+        } else {
+            &mut *fraction_digits
+        };
+        let Some(position) = digits.iter().rposition(|&c| c != b'0') else {
+            return false;
+        };
+        let digit = digits[position];
+        digits[position] = if digit == b'9' { b'8' } else { digit + 1 };
+        let (Ok(integer), Ok(fraction)) = (
+            std::str::from_utf8(integer_digits),
+            std::str::from_utf8(fraction_digits),
+        ) else {
+            return false;
+        };
+        let neighbour = Literal {
+            negative: literal.negative,
+            integer,
+            fraction,
+        };
+        D::from_literal(neighbour).is_ok_and(|neighbour| neighbour == *value)
+    })
+}
+
+/// Length of the literals converted without allocating (longer literals use a buffer on the heap)
+const INLINE_LITERAL_LEN: usize = 64;
+
+/// Calls `f` with a zero-filled buffer of `len` bytes, which is on the stack unless `len` exceeds [`INLINE_LITERAL_LEN`]
+fn with_buffer<R>(len: usize, f: impl FnOnce(&mut [u8]) -> R) -> R {
+    let mut inline = [0; INLINE_LITERAL_LEN];
+    match inline.get_mut(..len) {
+        Some(buffer) => f(buffer),
+        None => f(&mut vec![0; len]),
+    }
+}
+
+pub(crate) fn price<D: Number>(input: Span<'_>) -> IResult<'_, Price<D>> {
     let (input, currency) = currency(input)?;
     let (input, _) = space1(input)?;
     let (input, amount) = parse(input)?;
@@ -199,7 +479,9 @@ pub(crate) fn currency(input: Span<'_>) -> IResult<'_, Currency> {
     Ok((input, Currency(Arc::from(*currency.fragment()))))
 }
 
-/// Decimal type to which amount values and expressions will be parsed into.
+/// Decimal type implementing [`FromStr`] and the arithmetic operators
+///
+/// The parser requires a [`Number`] instead, which is implemented for the notable decimals below.
 ///
 /// # Notable implementations
 ///
@@ -238,6 +520,329 @@ impl<D> Decimal for D where
 {
 }
 
+/// Number type to which amount values and expressions will be parsed into.
+///
+/// Number literals are converted with [`FromLiteral`].
+/// Expressions (like `10 / 3`) are evaluated with [`Number::evaluate`],
+/// and the computations of balances (like [`crate::BeancountFile::balances`]) require [`Evaluate`].
+///
+/// It is implemented for `f64`, `f32`, the signed integers,
+/// and `Decimal` of the crate [rust_decimal] (with the `rust_decimal` feature).
+///
+/// [rust_decimal]: https://docs.rs/rust_decimal
+///
+/// For an example, look at [`FromLiteral`]
+pub trait Number: FromLiteral + Default + Clone + Debug + PartialEq + PartialOrd {
+    /// Returns the result of an operation of an expression,
+    /// or `None` if this type does not support expressions
+    ///
+    /// By default expressions are not supported, and an expression in place of a number is a syntax error.
+    /// Types implementing the arithmetic operators may support them with [`Operation::evaluate`].
+    fn evaluate(operation: Operation<Self>) -> Option<Self> {
+        let _ = operation;
+        None
+    }
+}
+
+/// Arithmetic of a [`Number`], required to evaluate expressions and to compute balances
+///
+/// It is implemented for every [`Number`] implementing the arithmetic operators.
+pub trait Evaluate:
+    Number
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+{
+}
+
+impl<D> Evaluate for D where
+    D: Number
+        + Add<Output = Self>
+        + Sub<Output = Self>
+        + Mul<Output = Self>
+        + Div<Output = Self>
+        + Neg<Output = Self>
+{
+}
+
+/// Operation of an expression (like `10 / 3`), see [`Number::evaluate`]
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Operation<D> {
+    /// Sum (`a + b`)
+    Add(D, D),
+    /// Difference (`a - b`)
+    Sub(D, D),
+    /// Product (`a * b`)
+    Mul(D, D),
+    /// Quotient (`a / b`)
+    Div(D, D),
+    /// Negation (`-(a)`)
+    Neg(D),
+}
+
+impl<D: Evaluate> Operation<D> {
+    /// Returns the result of the operation
+    #[must_use]
+    pub fn evaluate(self) -> D {
+        match self {
+            Self::Add(a, b) => a + b,
+            Self::Sub(a, b) => a - b,
+            Self::Mul(a, b) => a * b,
+            Self::Div(a, b) => a / b,
+            Self::Neg(a) => -a,
+        }
+    }
+}
+
+/// Parts of a number literal found in the beancount file
+///
+/// The thousands separators (`,`) are already removed.
+///
+/// For an example, look at [`FromLiteral`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Literal<'a> {
+    /// True if the literal starts with a minus sign
+    pub negative: bool,
+    /// Digits before the decimal point (may be empty, like in `.5`)
+    pub integer: &'a str,
+    /// Digits after the decimal point (may be empty, like in `5`)
+    pub fraction: &'a str,
+}
+
+impl Display for Literal<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.negative {
+            f.write_char('-')?;
+        }
+        f.write_str(self.integer)?;
+        if !self.fraction.is_empty() {
+            write!(f, ".{}", self.fraction)?;
+        }
+        Ok(())
+    }
+}
+
+/// Conversion from a number literal
+///
+/// It is implemented for `f64`, `f32`, the signed integers (for literals without decimals),
+/// and `Decimal` of the crate [rust_decimal] (with the `rust_decimal` feature).
+///
+/// Other number types may implement it to get the digits directly,
+/// which is convenient for exact integer-based types.
+///
+/// [rust_decimal]: https://docs.rs/rust_decimal
+///
+/// # Example
+///
+/// ```
+/// use beancount_parser::{BeancountFile, FromLiteral, Literal, Number};
+///
+/// /// Fixed-point number with 4 implied decimals
+/// #[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd)]
+/// struct Fixed(i128);
+///
+/// impl FromLiteral for Fixed {
+///     type Err = &'static str;
+///     fn from_literal(literal: Literal<'_>) -> Result<Self, Self::Err> {
+///         if literal.fraction.len() > 4 {
+///             return Err("too many decimals");
+///         }
+///         let digits = format!("{}{:0<4}", literal.integer, literal.fraction);
+///         let value: i128 = digits.parse().map_err(|_| "invalid number")?;
+///         Ok(Fixed(if literal.negative { -value } else { value }))
+///     }
+/// }
+///
+/// // Without arithmetic, expressions (like `10 / 3`) are rejected
+/// impl Number for Fixed {}
+///
+/// let input = "2023-05-27 price CHF  1,234.5 PLN";
+/// let beancount: BeancountFile<Fixed> = input.parse().unwrap();
+/// # let beancount_parser::DirectiveContent::Price(price) = &beancount.directives[0].content else { unreachable!() };
+/// # assert_eq!(price.amount.value, Fixed(12_345_000));
+/// ```
+pub trait FromLiteral: Sized {
+    /// Error returned if the literal cannot be converted
+    type Err;
+
+    /// Convert the literal
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the literal cannot be represented by this type
+    fn from_literal(literal: Literal<'_>) -> Result<Self, Self::Err>;
+
+    /// Returns true if the `value` converted from the `literal` lost some of its digits
+    ///
+    /// Such literals are rejected unless [`ParseOptions::round_excess_precision`] is set.
+    ///
+    /// By default, the value is compared with the conversion of the literal whose last non-zero digit is changed.
+    /// Types that never round the literals (like the signed integers) or can tell it from the value
+    /// should override it to spare the second conversion.
+    fn exceeds_precision(literal: Literal<'_>, value: &Self) -> bool
+    where
+        Self: PartialEq,
+    {
+        probe_precision(literal, value)
+    }
+}
+
+/// Returns the digits of the literal as an integer (ignoring the sign and the decimal point), or `None` if it overflows
+fn mantissa(literal: Literal<'_>) -> Option<u128> {
+    literal
+        .integer
+        .bytes()
+        .chain(literal.fraction.bytes())
+        .try_fold(0_u128, |mantissa, digit| {
+            mantissa
+                .checked_mul(10)?
+                .checked_add(u128::from(digit - b'0'))
+        })
+}
+
+/// Implements [`FromLiteral`] and [`Number`] for a float type
+///
+/// The literals with at most `$digits` significant digits and `$powers` decimals are converted exactly,
+/// as both the digits and the power of ten are exact floats, and the division is correctly rounded.
+/// The other literals are parsed with [`FromStr`].
+macro_rules! float {
+    ($float:ty, $digits:expr, $powers:expr) => {
+        impl FromLiteral for $float {
+            type Err = std::num::ParseFloatError;
+            fn from_literal(literal: Literal<'_>) -> Result<Self, Self::Err> {
+                match exact_float(literal, $digits, $powers) {
+                    Some((mantissa, power)) => {
+                        #[allow(clippy::cast_precision_loss)]
+                        let value = mantissa as $float / <$float>::from(10_u8).powi(power);
+                        Ok(if literal.negative { -value } else { value })
+                    }
+                    None => parse_literal(literal),
+                }
+            }
+
+            fn exceeds_precision(literal: Literal<'_>, value: &Self) -> bool {
+                exact_float(literal, $digits, $powers).is_none() && probe_precision(literal, value)
+            }
+        }
+
+        impl Number for $float {
+            fn evaluate(operation: Operation<Self>) -> Option<Self> {
+                Some(operation.evaluate())
+            }
+        }
+    };
+}
+
+/// Returns the mantissa and the number of decimals of the literal,
+/// if it has at most `digits` significant digits and `powers` decimals
+fn exact_float(literal: Literal<'_>, digits: u32, powers: i32) -> Option<(u128, i32)> {
+    let mantissa = mantissa(literal).filter(|&mantissa| mantissa < 10_u128.pow(digits))?;
+    let power = i32::try_from(literal.fraction.len())
+        .ok()
+        .filter(|&power| power <= powers)?;
+    Some((mantissa, power))
+}
+
+float!(f64, 15, 22);
+float!(f32, 7, 10);
+
+/// Implements [`FromLiteral`] and [`Number`] for a signed integer type
+///
+/// The literals with non-zero decimals cannot be converted.
+macro_rules! integer {
+    ($($integer:ty),*) => {$(
+        impl FromLiteral for $integer {
+            type Err = crate::ConversionError;
+            fn from_literal(literal: Literal<'_>) -> Result<Self, Self::Err> {
+                if literal.fraction.bytes().any(|digit| digit != b'0') {
+                    return Err(crate::ConversionError);
+                }
+                let integer = Literal {
+                    integer: if literal.integer.is_empty() { "0" } else { literal.integer },
+                    fraction: "",
+                    ..literal
+                };
+                parse_literal(integer).map_err(|_| crate::ConversionError)
+            }
+
+            fn exceeds_precision(_: Literal<'_>, _: &Self) -> bool {
+                false
+            }
+        }
+
+        impl Number for $integer {
+            fn evaluate(operation: Operation<Self>) -> Option<Self> {
+                Some(operation.evaluate())
+            }
+        }
+    )*};
+}
+
+integer!(i8, i16, i32, i64, i128, isize);
+
+/// Builds the decimal from the digits and the scale (the number of decimals)
+///
+/// The literals exceeding the precision of the decimal are parsed with [`FromStr`], which rounds them.
+#[cfg(feature = "rust_decimal")]
+impl FromLiteral for rust_decimal::Decimal {
+    type Err = rust_decimal::Error;
+    fn from_literal(literal: Literal<'_>) -> Result<Self, Self::Err> {
+        let mantissa = mantissa(literal).and_then(|mantissa| i128::try_from(mantissa).ok());
+        let scale = u32::try_from(literal.fraction.len()).ok();
+        let exact = mantissa.zip(scale).and_then(|(mantissa, scale)| {
+            let mantissa = if literal.negative {
+                -mantissa
+            } else {
+                mantissa
+            };
+            Self::try_from_i128_with_scale(mantissa, scale).ok()
+        });
+        match exact {
+            Some(value) => Ok(value),
+            None => parse_literal(literal),
+        }
+    }
+
+    /// The decimals lost by the rounding are the ones beyond the scale of the value
+    fn exceeds_precision(literal: Literal<'_>, value: &Self) -> bool {
+        let decimals = literal.fraction.trim_end_matches('0').len();
+        usize::try_from(value.scale()).is_ok_and(|scale| decimals > scale)
+    }
+}
+
+#[cfg(feature = "rust_decimal")]
+impl Number for rust_decimal::Decimal {
+    fn evaluate(operation: Operation<Self>) -> Option<Self> {
+        Some(operation.evaluate())
+    }
+}
+
+/// Parses the literal written without thousands separators (like `-1000.5`)
+fn parse_literal<T: FromStr>(literal: Literal<'_>) -> Result<T, T::Err> {
+    if !literal.negative && literal.fraction.is_empty() {
+        return literal.integer.parse();
+    }
+    let sign = usize::from(literal.negative);
+    let point = usize::from(!literal.fraction.is_empty());
+    let len = sign + literal.integer.len() + point + literal.fraction.len();
+    with_buffer(len, |buffer| {
+        let (sign_and_integer, point_and_fraction) =
+            buffer.split_at_mut(sign + literal.integer.len());
+        sign_and_integer[..sign].fill(b'-');
+        sign_and_integer[sign..].copy_from_slice(literal.integer.as_bytes());
+        point_and_fraction[..point].fill(b'.');
+        point_and_fraction[point..].copy_from_slice(literal.fraction.as_bytes());
+        match std::str::from_utf8(buffer) {
+            Ok(text) => text.parse(),
+            Err(_) => literal.to_string().parse(),
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
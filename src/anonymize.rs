@@ -0,0 +1,534 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    metadata, Account, Amount, BeancountFile, Currency, CustomValue, Date, DirectiveContent,
+    Evaluate, Link, PostingPrice, Tag,
+};
+
+/// Options of [`BeancountFile::pseudonymize`]
+///
+/// By default, every text that may identify someone is replaced: the payees, narrations, accounts, tags, links,
+/// string metadata values, and the texts of the notes, documents, events and queries. The currencies are kept.
+///
+/// What is always kept: the dates, the amounts, the metadata keys, the types of the events, the names of the queries,
+/// and the names and string values of the `custom` directives (they are keywords, like `"budget"` for fava).
+///
+/// # Example
+/// ```
+/// # use beancount_parser::PseudonymizeOptions;
+/// let options = PseudonymizeOptions::default()
+///     .narrations(false)
+///     .currencies(true);
+/// ```
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct PseudonymizeOptions {
+    pub(crate) payees: bool,
+    pub(crate) narrations: bool,
+    pub(crate) accounts: bool,
+    pub(crate) currencies: bool,
+    pub(crate) tags_and_links: bool,
+    pub(crate) metadata: bool,
+    pub(crate) notes: bool,
+    pub(crate) documents: bool,
+    pub(crate) events: bool,
+    pub(crate) queries: bool,
+}
+
+impl Default for PseudonymizeOptions {
+    fn default() -> Self {
+        Self {
+            payees: true,
+            narrations: true,
+            accounts: true,
+            currencies: false,
+            tags_and_links: true,
+            metadata: true,
+            notes: true,
+            documents: true,
+            events: true,
+            queries: true,
+        }
+    }
+}
+
+impl PseudonymizeOptions {
+    /// Replace the payees of the transactions (enabled by default)
+    #[must_use]
+    pub fn payees(mut self, replace: bool) -> Self {
+        self.payees = replace;
+        self
+    }
+
+    /// Replace the narrations of the transactions (enabled by default)
+    #[must_use]
+    pub fn narrations(mut self, replace: bool) -> Self {
+        self.narrations = replace;
+        self
+    }
+
+    /// Replace the components of the accounts, except their root (enabled by default)
+    ///
+    /// The accounts keep their depth, and the sub-accounts of an account stay its sub-accounts.
+    /// The options naming an account (like `account_rounding`) are replaced too.
+    #[must_use]
+    pub fn accounts(mut self, replace: bool) -> Self {
+        self.accounts = replace;
+        self
+    }
+
+    /// Replace the currencies (disabled by default)
+    ///
+    /// The `operating_currency` options are replaced too.
+    #[must_use]
+    pub fn currencies(mut self, replace: bool) -> Self {
+        self.currencies = replace;
+        self
+    }
+
+    /// Replace the tags and the links (enabled by default)
+    ///
+    /// They often name an invoice or a person. The tags pushed with `pushtag` are replaced too.
+    #[must_use]
+    pub fn tags_and_links(mut self, replace: bool) -> Self {
+        self.tags_and_links = replace;
+        self
+    }
+
+    /// Replace the string values of the metadata (enabled by default)
+    ///
+    /// The keys are kept. The metadata pushed with `pushmeta` are replaced too.
+    #[must_use]
+    pub fn metadata(mut self, replace: bool) -> Self {
+        self.metadata = replace;
+        self
+    }
+
+    /// Replace the descriptions of the `note` directives (enabled by default)
+    #[must_use]
+    pub fn notes(mut self, replace: bool) -> Self {
+        self.notes = replace;
+        self
+    }
+
+    /// Replace the paths of the `document` directives (enabled by default)
+    ///
+    /// Each component of the path is replaced, except the extension of the file, the `.` and `..` components,
+    /// and the roots of the accounts (like `Assets`).
+    /// A directory named like a component of an account gets the same pseudonym as that component,
+    /// so that a path like `documents/Assets/Bank/statement.pdf` still follows the account.
+    #[must_use]
+    pub fn documents(mut self, replace: bool) -> Self {
+        self.documents = replace;
+        self
+    }
+
+    /// Replace the values of the `event` directives (enabled by default)
+    ///
+    /// The types of the events (like `"location"`) are kept.
+    #[must_use]
+    pub fn events(mut self, replace: bool) -> Self {
+        self.events = replace;
+        self
+    }
+
+    /// Replace the query strings of the `query` directives (enabled by default)
+    ///
+    /// The replaced query is not a valid query anymore. The names of the queries are kept.
+    #[must_use]
+    pub fn queries(mut self, replace: bool) -> Self {
+        self.queries = replace;
+        self
+    }
+}
+
+pub(crate) fn shift_dates<D>(file: &mut BeancountFile<D>, days: i32) {
+    let shift = |date: &mut Date| {
+        let shifted = date.to_epoch_days().saturating_add(days);
+        *date = Date::from_epoch_days(shifted).unwrap_or(if days < 0 {
+            Date::new(0, 1, 1)
+        } else {
+            Date::new(u16::MAX, 12, 31)
+        });
+    };
+    for directive in &mut file.directives {
+        shift(&mut directive.date);
+        match &mut directive.content {
+            DirectiveContent::Transaction(trx) => trx
+                .postings
+                .iter_mut()
+                .filter_map(|posting| posting.cost.as_mut()?.date.as_mut())
+                .for_each(shift),
+            DirectiveContent::Custom(custom) => {
+                for value in &mut custom.values {
+                    if let CustomValue::Date(date) = value {
+                        shift(date);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+pub(crate) fn scale_amounts<D: Evaluate>(file: &mut BeancountFile<D>, factor: &D) {
+    let scale = |value: &mut D| *value = value.clone() * factor.clone();
+    for directive in &mut file.directives {
+        match &mut directive.content {
+            DirectiveContent::Transaction(trx) => {
+                for posting in &mut trx.postings {
+                    if let Some(amount) = &mut posting.amount {
+                        scale(&mut amount.value);
+                    }
+                    if let Some(PostingPrice::Total(total)) = &mut posting.price {
+                        scale(&mut total.value);
+                    }
+                }
+            }
+            DirectiveContent::Balance(balance) => {
+                scale(&mut balance.amount.value);
+                balance.tolerance.iter_mut().for_each(scale);
+            }
+            _ => (),
+        }
+    }
+}
+
+pub(crate) fn pseudonymize<D>(
+    file: &mut BeancountFile<D>,
+    seed: u64,
+    options: &PseudonymizeOptions,
+) {
+    let mut pseudonyms = Pseudonyms::new(seed, options);
+    for option in &mut file.options {
+        if options.accounts && option.name.starts_with("account_") {
+            if let Ok(mut account) = option.value.parse() {
+                pseudonyms.account(&mut account);
+                option.value = account.to_string();
+            }
+        }
+        if options.currencies && option.name == "operating_currency" {
+            if let Ok(mut currency) = option.value.parse() {
+                pseudonyms.currency(&mut currency);
+                option.value = currency.to_string();
+            }
+        }
+    }
+    pseudonyms.tags(&mut file.state.pushed.tags);
+    for (_, value) in &mut file.state.pushed.metadata {
+        pseudonyms.metadata_value(value);
+    }
+    for directive in &mut file.directives {
+        pseudonyms.metadata(&mut directive.metadata, &mut directive.metadata_duplicates);
+        pseudonyms.content(&mut directive.content);
+    }
+}
+
+/// Kind of pseudonym, each kind having its own format
+///
+/// The kinds are part of the hash: a new kind must be added last, so that the pseudonyms of the others do not change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Kind {
+    /// Payee, narration or another text (like `Dorapikemu`)
+    Text,
+    /// Component of an account or of a path (like `Dorapikemu`)
+    AccountComponent,
+    /// Currency (like `KVBE`)
+    Currency,
+    /// Tag (like `dorapikemu`)
+    Tag,
+    /// Link (like `dorapikemu`)
+    Link,
+}
+
+/// Pseudonyms given to the names, so that the same name is always replaced by the same pseudonym
+///
+/// The pseudonym of a name only depends on the seed and the name, unless another name of the file already got it.
+/// In that (unlikely) case, the name met last gets the pseudonym of the next attempt, which depends on the order of the names.
+struct Pseudonyms {
+    seed: u64,
+    options: PseudonymizeOptions,
+    names: HashMap<(Kind, String), String>,
+    /// Pseudonyms already given, to give distinct pseudonyms to distinct names
+    used: HashSet<(Kind, String)>,
+    /// Replaced accounts and currencies, so that the replacements share their allocation
+    replaced_accounts: HashMap<Account, Account>,
+    replaced_currencies: HashMap<Currency, Currency>,
+}
+
+impl Pseudonyms {
+    fn new(seed: u64, options: &PseudonymizeOptions) -> Self {
+        Self {
+            seed,
+            options: options.clone(),
+            names: HashMap::new(),
+            used: HashSet::new(),
+            replaced_accounts: HashMap::new(),
+            replaced_currencies: HashMap::new(),
+        }
+    }
+
+    fn content<D>(&mut self, content: &mut DirectiveContent<D>) {
+        match content {
+            DirectiveContent::Transaction(trx) => {
+                if self.options.payees {
+                    trx.payee.iter_mut().for_each(|payee| self.text(payee));
+                }
+                if self.options.narrations {
+                    trx.narration
+                        .iter_mut()
+                        .for_each(|narration| self.text(narration));
+                }
+                self.tags(&mut trx.tags);
+                self.links(&mut trx.links);
+                for posting in &mut trx.postings {
+                    self.account(&mut posting.account);
+                    self.metadata(&mut posting.metadata, &mut posting.metadata_duplicates);
+                    let cost = posting.cost.as_mut().and_then(|c| c.amount.as_mut());
+                    let price = posting.price.as_mut().map(|price| match price {
+                        PostingPrice::Unit(amount) | PostingPrice::Total(amount) => amount,
+                    });
+                    for amount in posting.amount.iter_mut().chain(cost).chain(price) {
+                        self.amount(amount);
+                    }
+                }
+            }
+            DirectiveContent::Price(price) => {
+                self.currency(&mut price.currency);
+                self.amount(&mut price.amount);
+            }
+            DirectiveContent::Balance(balance) => {
+                self.account(&mut balance.account);
+                self.amount(&mut balance.amount);
+            }
+            DirectiveContent::Open(open) => {
+                self.account(&mut open.account);
+                if self.options.currencies {
+                    open.currencies = std::mem::take(&mut open.currencies)
+                        .into_iter()
+                        .map(|mut currency| {
+                            self.currency(&mut currency);
+                            currency
+                        })
+                        .collect();
+                }
+            }
+            DirectiveContent::Close(close) => self.account(&mut close.account),
+            DirectiveContent::Pad(pad) => {
+                self.account(&mut pad.account);
+                self.account(&mut pad.source_account);
+            }
+            DirectiveContent::Commodity(currency) => self.currency(currency),
+            DirectiveContent::Note(note) => {
+                self.account(&mut note.account);
+                if self.options.notes {
+                    self.text(&mut note.description);
+                }
+                self.tags(&mut note.tags);
+                self.links(&mut note.links);
+            }
+            DirectiveContent::Document(document) => {
+                self.account(&mut document.account);
+                if self.options.documents {
+                    self.path(&mut document.path);
+                }
+                self.tags(&mut document.tags);
+                self.links(&mut document.links);
+            }
+            DirectiveContent::Custom(custom) => {
+                for value in &mut custom.values {
+                    match value {
+                        CustomValue::Account(account) => self.account(account),
+                        CustomValue::Amount(amount) => self.amount(amount),
+                        _ => (),
+                    }
+                }
+            }
+            DirectiveContent::Event(event) => {
+                if self.options.events {
+                    self.text(&mut event.value);
+                }
+            }
+            DirectiveContent::Query(query) => {
+                if self.options.queries {
+                    self.text(&mut query.query_string);
+                }
+            }
+        }
+    }
+
+    fn text(&mut self, text: &mut String) {
+        if !text.is_empty() {
+            *text = self.pseudonym(Kind::Text, text);
+        }
+    }
+
+    /// Replace each component of the path, keeping the separators and the extension of the components
+    fn path(&mut self, path: &mut String) {
+        let mut replaced = String::with_capacity(path.len());
+        for component in path.split_inclusive(['/', '\\']) {
+            let name = component.trim_end_matches(['/', '\\']);
+            let (stem, extension) = match name.rsplit_once('.') {
+                Some((stem, extension)) if !stem.is_empty() => (stem, Some(extension)),
+                _ => (name, None),
+            };
+            if stem.is_empty() || [".", ".."].contains(&stem) || ACCOUNT_ROOTS.contains(&stem) {
+                replaced.push_str(stem);
+            } else {
+                replaced.push_str(&self.pseudonym(Kind::AccountComponent, stem));
+            }
+            if let Some(extension) = extension {
+                replaced.push('.');
+                replaced.push_str(extension);
+            }
+            replaced.push_str(&component[name.len()..]);
+        }
+        *path = replaced;
+    }
+
+    fn account(&mut self, account: &mut Account) {
+        if !self.options.accounts {
+            return;
+        }
+        if let Some(replaced) = self.replaced_accounts.get(account) {
+            *account = replaced.clone();
+            return;
+        }
+        let mut components = account.as_str().split(':');
+        let mut name = components.next().unwrap_or_default().to_owned();
+        for component in components {
+            name.push(':');
+            name.push_str(&self.pseudonym(Kind::AccountComponent, component));
+        }
+        let replaced = Account::new_unchecked(&name);
+        self.replaced_accounts
+            .insert(account.clone(), replaced.clone());
+        *account = replaced;
+    }
+
+    fn currency(&mut self, currency: &mut Currency) {
+        if !self.options.currencies {
+            return;
+        }
+        if let Some(replaced) = self.replaced_currencies.get(currency) {
+            *currency = replaced.clone();
+            return;
+        }
+        let replaced = Currency::new_unchecked(&self.pseudonym(Kind::Currency, currency.as_str()));
+        self.replaced_currencies
+            .insert(currency.clone(), replaced.clone());
+        *currency = replaced;
+    }
+
+    fn amount<D>(&mut self, amount: &mut Amount<D>) {
+        self.currency(&mut amount.currency);
+    }
+
+    fn tags(&mut self, tags: &mut HashSet<Tag>) {
+        if self.options.tags_and_links && !tags.is_empty() {
+            *tags = std::mem::take(tags)
+                .iter()
+                .map(|tag| Tag::new_unchecked(&self.pseudonym(Kind::Tag, tag.as_str())))
+                .collect();
+        }
+    }
+
+    fn links(&mut self, links: &mut HashSet<Link>) {
+        if self.options.tags_and_links && !links.is_empty() {
+            *links = std::mem::take(links)
+                .iter()
+                .map(|link| Link::new_unchecked(&self.pseudonym(Kind::Link, link.as_str())))
+                .collect();
+        }
+    }
+
+    fn metadata<D>(
+        &mut self,
+        map: &mut metadata::Map<D>,
+        duplicates: &mut metadata::Duplicates<D>,
+    ) {
+        let duplicates = duplicates.as_mut_vec().into_iter().flatten();
+        for value in map.values_mut().chain(duplicates.map(|d| &mut d.value)) {
+            self.metadata_value(value);
+        }
+    }
+
+    fn metadata_value<D>(&mut self, value: &mut metadata::Value<D>) {
+        match value {
+            metadata::Value::Currency(currency) => self.currency(currency),
+            metadata::Value::String(text) if self.options.metadata => self.text(text),
+            _ => (),
+        }
+    }
+
+    /// Returns the pseudonym of the name, which is derived from the seed and the name (unless it is already taken)
+    fn pseudonym(&mut self, kind: Kind, name: &str) -> String {
+        let key = (kind, name.to_owned());
+        if let Some(pseudonym) = self.names.get(&key) {
+            return pseudonym.clone();
+        }
+        // A file has far fewer names than there are pseudonyms, so a free one is found after a few attempts
+        let pseudonym = (0..u32::MAX)
+            .map(|attempt| format(kind, hash(self.seed, kind, attempt, name)))
+            .find(|pseudonym| !self.used.contains(&(kind, pseudonym.clone())))
+            .unwrap_or_default();
+        self.used.insert((kind, pseudonym.clone()));
+        self.names.insert(key, pseudonym.clone());
+        pseudonym
+    }
+}
+
+/// Roots of the accounts, which are kept in the paths (they are kept in the accounts too)
+const ACCOUNT_ROOTS: [&str; 5] = ["Assets", "Liabilities", "Equity", "Income", "Expenses"];
+
+const CONSONANTS: &[u8] = b"bcdfghjklmnprstv";
+const VOWELS: &[u8] = b"aeiou";
+const UPPERCASE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// Returns the pseudonym of the hash in the format of the kind
+///
+/// The names are made of syllables (a consonant and a vowel), capitalized (except for the tags and links),
+/// which is a valid account component.
+/// The currencies are made of four uppercase letters.
+fn format(kind: Kind, mut hash: u64) -> String {
+    // Each letter consumes the digits of the hash in the base of its alphabet
+    let mut letter = |alphabet: &[u8]| {
+        let base = u64::try_from(alphabet.len()).unwrap_or(u64::MAX);
+        let index = usize::try_from(hash % base).unwrap_or_default();
+        hash /= base;
+        char::from(alphabet[index])
+    };
+    match kind {
+        Kind::Text | Kind::AccountComponent | Kind::Tag | Kind::Link => {
+            let mut name = String::with_capacity(10);
+            for _ in 0..5 {
+                name.push(letter(CONSONANTS));
+                name.push(letter(VOWELS));
+            }
+            if !matches!(kind, Kind::Tag | Kind::Link) {
+                name[..1].make_ascii_uppercase();
+            }
+            name
+        }
+        Kind::Currency => (0..4).map(|_| letter(UPPERCASE)).collect(),
+    }
+}
+
+/// Keyed hash of the name, that is the same on every platform and for every run (FNV-1a of the seed, kind, attempt and name)
+fn hash(seed: u64, kind: Kind, attempt: u32, name: &str) -> u64 {
+    let kind = kind as u8;
+    let bytes = seed
+        .to_le_bytes()
+        .into_iter()
+        .chain([kind])
+        .chain(attempt.to_le_bytes())
+        .chain(name.bytes());
+    let hash = bytes.fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    // Final mix of SplitMix64, so that names differing by their last byte do not get close hashes
+    let hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    let hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^ (hash >> 31)
+}
@@ -0,0 +1,121 @@
+//! Documents (like receipts) attached to directives with metadata
+//!
+//! Like in fava, the path of the first document of a directive is the value of the [`DOCUMENT_KEY`] metadata key,
+//! and the paths of the following ones are the values of the numbered keys `document-2`, `document-3`, etc.
+//! (see [`document_key`]).
+//!
+//! # Example
+//!
+//! ```
+//! use beancount_parser::{BeancountFile, Directive};
+//! let input = r#"
+//! 2023-05-20 * "Hardware store" "Drill"
+//!   document: "receipts/drill.pdf"
+//!   Expenses:Tools  80 CHF
+//!   Assets:Cash
+//! "#;
+//! let mut beancount: BeancountFile<f64> = input.parse().unwrap();
+//! let directive = &mut beancount.directives[0];
+//! directive.attach_document("warranties/drill.pdf");
+//! assert_eq!(directive.attached_documents(), ["receipts/drill.pdf", "warranties/drill.pdf"]);
+//! assert!(directive.metadata.contains_key("document-2"));
+//! ```
+
+use crate::{
+    index,
+    metadata::{Key, Value},
+    BeancountFile, Directive, DirectiveId,
+};
+
+/// Metadata key of the first document attached to a directive
+pub const DOCUMENT_KEY: &str = "document";
+
+/// Returns the metadata key of the `n`th document attached to a directive (starting at 1)
+///
+/// That is [`DOCUMENT_KEY`] for the first document, and `document-{n}` for the following ones.
+///
+/// # Example
+/// ```
+/// # use beancount_parser::attachment::document_key;
+/// assert_eq!(document_key(1), "document");
+/// assert_eq!(document_key(3), "document-3");
+/// ```
+#[must_use]
+pub fn document_key(n: usize) -> String {
+    if n <= 1 {
+        DOCUMENT_KEY.into()
+    } else {
+        format!("{DOCUMENT_KEY}-{n}")
+    }
+}
+
+/// Returns the position (starting at 1) of the document whose path is the value of the metadata key
+///
+/// Returns `None` if the key is not one returned by [`document_key`] (like `document-1` or `documents`).
+///
+/// # Example
+/// ```
+/// # use beancount_parser::attachment::document_index;
+/// assert_eq!(document_index("document"), Some(1));
+/// assert_eq!(document_index("document-2"), Some(2));
+/// assert_eq!(document_index("document-02"), None);
+/// ```
+#[must_use]
+pub fn document_index(key: &str) -> Option<usize> {
+    if key == DOCUMENT_KEY {
+        return Some(1);
+    }
+    let n: usize = key
+        .strip_prefix(DOCUMENT_KEY)?
+        .strip_prefix('-')?
+        .parse()
+        .ok()?;
+    (n > 1 && document_key(n) == key).then_some(n)
+}
+
+pub(crate) fn attach<D>(directive: &mut Directive<D>, path: &str) {
+    // One of the first `len + 1` keys is free
+    let n = (1..=directive.metadata.len() + 1)
+        .find(|n| !directive.metadata.contains_key(document_key(*n).as_str()))
+        .unwrap_or_default();
+    directive.metadata.insert(
+        Key::new_unchecked(&document_key(n)),
+        Value::String(path.into()),
+    );
+}
+
+pub(crate) fn documents<D>(directive: &Directive<D>) -> Vec<&str> {
+    let mut documents: Vec<(usize, &str)> = directive
+        .metadata
+        .iter()
+        .filter_map(|(key, value)| match value {
+            Value::String(path) => Some((document_index(key.as_ref())?, path.as_str())),
+            _ => None,
+        })
+        .collect();
+    documents.sort_unstable();
+    documents.into_iter().map(|(_, path)| path).collect()
+}
+
+pub(crate) fn documents_for<D>(file: &BeancountFile<D>, id: DirectiveId) -> Vec<&str> {
+    let Some(directive) = file.directive(id) else {
+        return Vec::new();
+    };
+    let mut result = documents(directive);
+    let Some((_, links)) = index::tags_and_links(&directive.content) else {
+        return result;
+    };
+    let linked = file.directives.iter().enumerate().filter(|(index, other)| {
+        *index != id.index()
+            && index::tags_and_links(&other.content)
+                .is_some_and(|(_, other_links)| !other_links.is_disjoint(links))
+    });
+    for (_, other) in linked {
+        for path in documents(other) {
+            if !result.contains(&path) {
+                result.push(path);
+            }
+        }
+    }
+    result
+}
@@ -0,0 +1,495 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    ops::RangeBounds,
+    sync::Arc,
+};
+
+use crate::{
+    Account, Amount, Balance, BeancountFile, Code, Cost, Currency, Date, Directive,
+    DirectiveContent, Error, Evaluate, Inventory, Pad, Posting, PostingPrice, Provenance,
+    Transaction, Warning, WarningKind,
+};
+
+/// Returns the directives in the order they take effect
+///
+/// Directives are sorted by date. On the same day, `open` comes first, then `balance` (that asserts the balance
+/// at the beginning of the day), then the other directives, and `close` comes last.
+/// Directives of the same day and kind keep the order in which they were declared.
+pub(crate) fn chronological<D>(directives: &[Directive<D>]) -> Vec<&Directive<D>> {
+    let mut sorted: Vec<&Directive<D>> = directives.iter().collect();
+    sorted.sort_by_key(|directive| (directive.date, kind_order(&directive.content)));
+    sorted
+}
+
+pub(crate) fn kind_order<D>(content: &DirectiveContent<D>) -> i8 {
+    match content {
+        DirectiveContent::Open(_) => -2,
+        DirectiveContent::Balance(_) => -1,
+        DirectiveContent::Close(_) => 1,
+        _ => 0,
+    }
+}
+
+/// Returns true if the posting adds units at a cost without amount (like `10 HOOL {}` or `10 HOOL {2023-05-20}`)
+fn has_missing_cost<D: Evaluate>(posting: &Posting<D>) -> bool {
+    matches!(&posting.cost, Some(Cost { amount: None, .. }))
+        && posting
+            .amount
+            .as_ref()
+            .is_some_and(|amount| amount.value > D::default())
+}
+
+/// Returns the amount by which the posting contributes to the balance of the transaction
+///
+/// That is the total cost if there is a cost, otherwise the total price if there is a price,
+/// and otherwise the amount itself.
+pub(crate) fn weight<D: Evaluate>(posting: &Posting<D>) -> Option<Amount<D>> {
+    let amount = posting.amount.as_ref()?;
+    if let Some(Cost {
+        amount: Some(cost), ..
+    }) = &posting.cost
+    {
+        return Some(Amount {
+            value: amount.value.clone() * cost.value.clone(),
+            currency: cost.currency.clone(),
+        });
+    }
+    Some(match &posting.price {
+        Some(PostingPrice::Unit(price)) => Amount {
+            value: amount.value.clone() * price.value.clone(),
+            currency: price.currency.clone(),
+        },
+        Some(PostingPrice::Total(total)) if amount.value < D::default() => Amount {
+            value: -total.value.clone(),
+            currency: total.currency.clone(),
+        },
+        Some(PostingPrice::Total(total)) => total.clone(),
+        None => amount.clone(),
+    })
+}
+
+/// Amounts added to the accounts by a transaction
+pub(crate) struct Booked<'a, D> {
+    /// Amount added to each account
+    ///
+    /// The postings with an amount come first, in declaration order. They are followed by the interpolated
+    /// posting without amount, that appears once per currency needed to balance the transaction.
+    pub(crate) amounts: Vec<(&'a Account, Amount<D>)>,
+    /// Positions of the postings without amount
+    pub(crate) missing: Vec<usize>,
+    /// Interpolated cost per unit of the posting whose cost has no amount (like `10 HOOL {}`), and its position
+    pub(crate) cost: Option<(usize, Amount<D>)>,
+    /// Positions of the postings whose cost has no amount, and could not be interpolated
+    ///
+    /// Their weight is the units, as if they had no cost.
+    pub(crate) missing_costs: Vec<usize>,
+    /// Sum of the weights that remain unbalanced
+    ///
+    /// It is empty if the transaction is balanced. It cannot be interpolated if the transaction has
+    /// more than one posting without amount.
+    pub(crate) residual: Inventory<D>,
+}
+
+/// Returns the amounts of the postings, interpolating the amount of the posting without amount (if any)
+///
+/// The cost amount of a posting adding units at an unknown cost (like `10 HOOL {}`) is interpolated too,
+/// if it is the only missing number of the transaction and the other postings weigh a single currency.
+pub(crate) fn book<D: Evaluate>(transaction: &Transaction<D>) -> Booked<'_, D> {
+    let mut amounts = Vec::with_capacity(transaction.postings.len());
+    let mut residual = Inventory::new();
+    let mut missing = Vec::new();
+    let mut missing_costs = Vec::new();
+    for (position, posting) in transaction.postings.iter().enumerate() {
+        match (&posting.amount, weight(posting)) {
+            (Some(amount), _) if has_missing_cost(posting) => {
+                amounts.push((&posting.account, amount.clone()));
+                missing_costs.push(position);
+            }
+            (Some(amount), Some(weight)) => {
+                amounts.push((&posting.account, amount.clone()));
+                residual.add(weight);
+            }
+            _ => missing.push(position),
+        }
+    }
+    let mut cost = None;
+    if let ([], [position], [total]) =
+        (&missing[..], &missing_costs[..], &residual.to_amounts()[..])
+    {
+        if let Some(units) = &transaction.postings[*position].amount {
+            let per_unit = Amount {
+                value: -total.value.clone() / units.value.clone(),
+                currency: total.currency.clone(),
+            };
+            cost = Some((*position, per_unit));
+            missing_costs.clear();
+            residual = Inventory::new();
+        }
+    }
+    for &position in &missing_costs {
+        if let Some(units) = &transaction.postings[position].amount {
+            residual.add(units.clone());
+        }
+    }
+    if let [position] = missing[..] {
+        let account = &transaction.postings[position].account;
+        for amount in residual.to_amounts() {
+            amounts.push((
+                account,
+                Amount {
+                    value: -amount.value,
+                    currency: amount.currency,
+                },
+            ));
+        }
+        residual = Inventory::new();
+    }
+    Booked {
+        amounts,
+        missing,
+        cost,
+        missing_costs,
+        residual,
+    }
+}
+
+/// Amount moved by a `pad` directive from the source account to the padded account
+pub(crate) struct Padding<D> {
+    /// Date of the `pad` directive
+    pub(crate) date: Date,
+    /// Line number of the `pad` directive
+    pub(crate) line_number: u32,
+    pub(crate) provenance: Option<Arc<Provenance>>,
+    pub(crate) account: Account,
+    pub(crate) source_account: Account,
+    pub(crate) amount: Amount<D>,
+}
+
+struct ActivePad {
+    date: Date,
+    line_number: u32,
+    provenance: Option<Arc<Provenance>>,
+    source_account: Account,
+    padded: HashSet<Currency>,
+}
+
+/// Running balances of the accounts, updated one directive at a time in chronological order
+///
+/// It is the single implementation of the beancount rules on balances
+/// (interpolation, pads, rounding), so that every computation agrees on them.
+pub(crate) struct Tracker<D> {
+    pub(crate) balances: BTreeMap<Account, Inventory<D>>,
+    pads: HashMap<Account, ActivePad>,
+    /// Pads replaced by another pad of the account before being used
+    replaced_pads: Vec<(Account, ActivePad)>,
+    rounding_account: Option<Account>,
+    /// Balance assertions cover the sub-accounts (see [`ParseOptions::aggregate_balance_assertions`](crate::ParseOptions::aggregate_balance_assertions))
+    aggregate_balance_assertions: bool,
+}
+
+impl<D: Evaluate> Tracker<D> {
+    /// Create a tracker using the options (like `account_rounding`) of the file
+    pub(crate) fn new(file: &BeancountFile<D>) -> Self {
+        Self {
+            balances: BTreeMap::new(),
+            pads: HashMap::new(),
+            replaced_pads: Vec::new(),
+            rounding_account: rounding_account(file),
+            aggregate_balance_assertions: file.state.options.aggregate_balance_assertions,
+        }
+    }
+
+    /// Update the balances with the directive
+    ///
+    /// Returns the amount padded if the directive is a balance assertion resolving a pad.
+    pub(crate) fn apply(&mut self, directive: &Directive<D>) -> Option<Padding<D>> {
+        match &directive.content {
+            DirectiveContent::Transaction(trx) => {
+                self.apply_transaction(trx);
+                None
+            }
+            DirectiveContent::Pad(pad) => {
+                self.apply_pad(directive, pad);
+                None
+            }
+            DirectiveContent::Balance(balance) => self.apply_balance(balance),
+            _ => None,
+        }
+    }
+
+    /// Returns true if a `pad` dated before `date` may still move amounts
+    pub(crate) fn has_active_pad_before(&self, date: Date) -> bool {
+        self.pads.values().any(|pad| pad.date < date)
+    }
+
+    /// Returns the balance of the account including all its sub-accounts, for the given currency
+    pub(crate) fn subtree_balance(&self, account: &Account, currency: &Currency) -> D {
+        let prefix = format!("{account}:");
+        self.balances
+            .iter()
+            .filter(|(a, _)| *a == account || a.as_str().starts_with(&prefix))
+            .filter_map(|(_, inventory)| inventory.get(currency))
+            .fold(D::default(), |sum, value| sum + value.clone())
+    }
+
+    /// Returns the balance checked by a `balance` assertion of the account, for the given currency
+    ///
+    /// That is the balance of the account itself, or of its subtree with aggregate balance assertions.
+    pub(crate) fn asserted_balance(&self, account: &Account, currency: &Currency) -> D {
+        if self.aggregate_balance_assertions {
+            self.subtree_balance(account, currency)
+        } else {
+            self.balances
+                .get(account)
+                .and_then(|inventory| inventory.get(currency))
+                .cloned()
+                .unwrap_or_default()
+        }
+    }
+
+    /// Returns the difference between the asserted amount and the actual balance, if it exceeds the tolerance
+    fn balance_error(&self, balance: &Balance<D>) -> Option<(D, D)> {
+        let actual = self.asserted_balance(&balance.account, &balance.amount.currency);
+        let difference = balance.amount.value.clone() - actual.clone();
+        exceeds(&difference, balance.tolerance.as_ref()).then_some((actual, difference))
+    }
+
+    fn apply_transaction(&mut self, transaction: &Transaction<D>) {
+        let booked = book(transaction);
+        for (account, amount) in booked.amounts {
+            add(&mut self.balances, account, amount);
+        }
+        if let Some(rounding_account) = &self.rounding_account {
+            for amount in booked.residual.to_amounts() {
+                add(
+                    &mut self.balances,
+                    rounding_account,
+                    Amount {
+                        value: -amount.value,
+                        currency: amount.currency,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Returns a warning for each pad that did not move any amount, sorted by line number
+    pub(crate) fn unused_pads(self) -> Vec<Warning> {
+        let mut warnings: Vec<Warning> = self
+            .replaced_pads
+            .into_iter()
+            .chain(self.pads)
+            .filter(|(_, pad)| pad.padded.is_empty())
+            .map(|(account, pad)| Warning::new(WarningKind::UnusedPad(account), pad.line_number))
+            .collect();
+        warnings.sort_by_key(Warning::line_number);
+        warnings
+    }
+
+    fn apply_pad(&mut self, directive: &Directive<D>, pad: &Pad) {
+        let previous = self.pads.insert(
+            pad.account.clone(),
+            ActivePad {
+                date: directive.date,
+                line_number: directive.line_number,
+                provenance: directive.provenance.clone(),
+                source_account: pad.source_account.clone(),
+                padded: HashSet::new(),
+            },
+        );
+        if let Some(previous) = previous {
+            self.replaced_pads.push((pad.account.clone(), previous));
+        }
+    }
+
+    fn apply_balance(&mut self, balance: &Balance<D>) -> Option<Padding<D>> {
+        let pad = self.pads.get(&balance.account)?;
+        let currency = &balance.amount.currency;
+        if pad.padded.contains(currency) {
+            return None;
+        }
+        let (_, difference) = self.balance_error(balance)?;
+        let pad = self.pads.get_mut(&balance.account)?;
+        pad.padded.insert(currency.clone());
+        let padding = Padding {
+            date: pad.date,
+            line_number: pad.line_number,
+            provenance: pad.provenance.clone(),
+            account: balance.account.clone(),
+            source_account: pad.source_account.clone(),
+            amount: Amount {
+                value: difference,
+                currency: currency.clone(),
+            },
+        };
+        padding.apply_to(&mut self.balances);
+        Some(padding)
+    }
+}
+
+impl<D: Evaluate> Padding<D> {
+    pub(crate) fn apply_to(&self, balances: &mut BTreeMap<Account, Inventory<D>>) {
+        add(balances, &self.account, self.amount.clone());
+        add(
+            balances,
+            &self.source_account,
+            Amount {
+                value: -self.amount.value.clone(),
+                currency: self.amount.currency.clone(),
+            },
+        );
+    }
+}
+
+/// Returns true if the difference is larger (in absolute value) than the tolerance
+///
+/// Without explicit tolerance, any non-zero difference exceeds it.
+pub(crate) fn exceeds<D: Evaluate>(difference: &D, tolerance: Option<&D>) -> bool {
+    let zero = D::default();
+    let tolerance = tolerance.cloned().unwrap_or_default();
+    if *difference < zero {
+        -difference.clone() > tolerance
+    } else {
+        *difference > tolerance
+    }
+}
+
+fn add<D: Evaluate>(
+    balances: &mut BTreeMap<Account, Inventory<D>>,
+    account: &Account,
+    amount: Amount<D>,
+) {
+    balances.entry(account.clone()).or_default().add(amount);
+}
+
+/// Balances of all accounts at the beginning of `as_of` (or after all directives if `None`)
+pub(crate) fn balances<D: Evaluate>(
+    file: &BeancountFile<D>,
+    as_of: Option<Date>,
+) -> BTreeMap<Account, Inventory<D>> {
+    let mut tracker = Tracker::new(file);
+    let mut snapshot: Option<BTreeMap<Account, Inventory<D>>> = None;
+    for directive in chronological(&file.directives) {
+        if let Some(as_of) = as_of {
+            if snapshot.is_none() && directive.date >= as_of {
+                snapshot = Some(tracker.balances.clone());
+            }
+            if snapshot.is_some() && !tracker.has_active_pad_before(as_of) {
+                break;
+            }
+        }
+        let padding = tracker.apply(directive);
+        // A pad takes effect at its own date, even if the assertion determining its amount comes later
+        if let (Some(snapshot), Some(padding)) = (&mut snapshot, padding) {
+            if as_of.is_some_and(|as_of| padding.date < as_of) {
+                padding.apply_to(snapshot);
+            }
+        }
+    }
+    snapshot.unwrap_or(tracker.balances)
+}
+
+/// Balance checked by a `balance` assertion of the account dated `date`, for the currency
+///
+/// Returns `None` if no directive dated before `date` changes the balance of the account
+/// (or of its sub-accounts, with aggregate balance assertions).
+pub(crate) fn asserted_balance<D: Evaluate>(
+    file: &BeancountFile<D>,
+    account: &Account,
+    currency: &Currency,
+    date: Date,
+) -> Option<D> {
+    let mut tracker = Tracker::new(file);
+    tracker.balances = balances(file, Some(date));
+    let prefix = format!("{account}:");
+    let used = tracker.balances.keys().any(|a| {
+        a == account || (tracker.aggregate_balance_assertions && a.as_str().starts_with(&prefix))
+    });
+    used.then(|| tracker.asserted_balance(account, currency))
+}
+
+/// Returns the account of the option `account_rounding`, if it is set to a valid account
+fn rounding_account<D>(file: &BeancountFile<D>) -> Option<Account> {
+    file.option("account_rounding")
+        .and_then(|name| name.parse().ok())
+}
+
+/// Accounts whose balance may be changed by the directives dated in the range
+pub(crate) fn accounts_touched<D: Evaluate>(
+    file: &BeancountFile<D>,
+    range: &impl RangeBounds<Date>,
+) -> HashSet<Account> {
+    let mut touched = HashSet::new();
+    let mut unbalanced = false;
+    // Dates and source accounts of the pads of each account, and the balance assertions in the range,
+    // to find the pads before the range resolved by an assertion in the range
+    let mut pads: HashMap<&Account, Vec<(Date, &Account)>> = HashMap::new();
+    let mut assertions = Vec::new();
+    for directive in &file.directives {
+        let in_range = range.contains(&directive.date);
+        match &directive.content {
+            DirectiveContent::Pad(pad) => {
+                pads.entry(&pad.account)
+                    .or_default()
+                    .push((directive.date, &pad.source_account));
+                if in_range {
+                    touched.extend([pad.account.clone(), pad.source_account.clone()]);
+                }
+            }
+            DirectiveContent::Transaction(trx) if in_range => {
+                touched.extend(trx.postings.iter().map(|p| p.account.clone()));
+                unbalanced = unbalanced || !book(trx).residual.is_empty();
+            }
+            DirectiveContent::Balance(balance) if in_range => {
+                touched.insert(balance.account.clone());
+                assertions.push((directive.date, &balance.account));
+            }
+            DirectiveContent::Open(open) if in_range => {
+                touched.insert(open.account.clone());
+            }
+            DirectiveContent::Close(close) if in_range => {
+                touched.insert(close.account.clone());
+            }
+            _ => (),
+        }
+    }
+    for (date, account) in assertions {
+        let source = pads.get(account).and_then(|pads| {
+            pads.iter()
+                .filter(|(pad_date, _)| *pad_date < date)
+                .max_by_key(|(pad_date, _)| *pad_date)
+        });
+        if let Some((_, source)) = source {
+            touched.insert((*source).clone());
+        }
+    }
+    if unbalanced {
+        touched.extend(rounding_account(file));
+    }
+    touched
+}
+
+/// Check the `balance` assertions of the file, and return an error for each one that fails
+pub(crate) fn verify_balances<D: Evaluate>(file: &BeancountFile<D>) -> Vec<Error> {
+    let mut tracker = Tracker::new(file);
+    let mut errors = Vec::new();
+    for directive in chronological(&file.directives) {
+        tracker.apply(directive);
+        let DirectiveContent::Balance(balance) = &directive.content else {
+            continue;
+        };
+        if let Some((actual, _)) = tracker.balance_error(balance) {
+            errors.push(
+                Error::at("", 0, directive.line_number, Code::BalanceMismatch)
+                    .with_hint(format!(
+                        "expected {:?} {currency} in {}, found {actual:?} {currency}",
+                        balance.amount.value,
+                        balance.account,
+                        currency = balance.amount.currency,
+                    ))
+                    .with_provenance(directive.provenance.clone()),
+            );
+        }
+    }
+    errors
+}
@@ -0,0 +1,288 @@
+use nom::combinator::iterator;
+
+use crate::{
+    entry_with_options,
+    iterator::{shift_lines, Iter, Pushed},
+    span, Code, Entry, Error, Number, ParseOptions,
+};
+
+/// Minimum size of the chunks validated and parsed at once
+pub(crate) const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Part of the input containing only complete entries
+struct Chunk<'a> {
+    bytes: &'a [u8],
+    /// Offset of the chunk in the input
+    offset: usize,
+    /// Number of lines before the chunk
+    previous_lines: u32,
+}
+
+/// Split the input into chunks of complete entries
+///
+/// A chunk ends at the first line after `min_size` bytes that starts a new entry.
+/// That is a line that does not start with a whitespace, and that is not inside a (multi-line) string.
+struct Chunks<'a> {
+    rest: &'a [u8],
+    offset: usize,
+    lines: u32,
+    min_size: usize,
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = Chunk<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rest.is_empty() {
+            return None;
+        }
+        let (bytes, rest) = self.rest.split_at(split_point(self.rest, self.min_size));
+        let chunk = Chunk {
+            bytes,
+            offset: self.offset,
+            previous_lines: self.lines,
+        };
+        self.rest = rest;
+        self.offset += bytes.len();
+        self.lines += count_lines(bytes);
+        Some(chunk)
+    }
+}
+
+fn split_point(bytes: &[u8], min_size: usize) -> usize {
+    let mut in_string = false;
+    let mut in_comment = false;
+    let mut escaped = false;
+    for (index, &byte) in bytes.iter().enumerate() {
+        match byte {
+            b'\n' => {
+                in_comment = false;
+                let starts_entry = bytes
+                    .get(index + 1)
+                    .is_some_and(|next| !next.is_ascii_whitespace());
+                if index + 1 >= min_size && !in_string && starts_entry {
+                    return index + 1;
+                }
+            }
+            _ if in_comment => (),
+            b'\\' if in_string => {
+                escaped = !escaped;
+                continue;
+            }
+            b'"' if !escaped => in_string = !in_string,
+            b';' if !in_string => in_comment = true,
+            _ => (),
+        }
+        escaped = false;
+    }
+    bytes.len()
+}
+
+fn count_lines(bytes: &[u8]) -> u32 {
+    let lines = bytes.split(|&b| b == b'\n').count() - 1;
+    u32::try_from(lines).unwrap_or(u32::MAX)
+}
+
+/// Parse the input one chunk at a time, validating each chunk as UTF-8 just before parsing it
+pub(crate) fn parse<'a, D: Number + 'a>(
+    input: &'a [u8],
+    options: &ParseOptions,
+    min_chunk_size: usize,
+) -> impl Iterator<Item = Result<Entry<D>, Error>> + 'a {
+    let options = options.clone();
+    BytesIter {
+        chunks: Chunks {
+            rest: input,
+            offset: 0,
+            lines: 0,
+            min_size: min_chunk_size,
+        },
+        make_iter: move |source: &'a str| {
+            Iter::new(
+                source,
+                iterator(span(source), entry_with_options::<D>(&options)),
+                options.clone(),
+            )
+        },
+        current: None,
+        pushed: Pushed::default(),
+        failed: false,
+    }
+}
+
+struct BytesIter<'a, D, F, M> {
+    chunks: Chunks<'a>,
+    /// Create the iterator over the entries of a chunk
+    make_iter: M,
+    /// Iterator over the entries of the current chunk, and the number of lines before the chunk
+    current: Option<(Iter<'a, D, F>, u32)>,
+    /// Tags and metadata pushed in the previous chunks
+    pushed: Pushed<D>,
+    failed: bool,
+}
+
+impl<'a, D, F, M> Iterator for BytesIter<'a, D, F, M>
+where
+    M: FnMut(&'a str) -> Iter<'a, D, F>,
+    Iter<'a, D, F>: Iterator<Item = Result<Entry<D>, Error>>,
+{
+    type Item = Result<Entry<D>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        loop {
+            if let Some((iter, previous_lines)) = &mut self.current {
+                if let Some(item) = iter.next() {
+                    self.failed = item.is_err();
+                    return Some(shift_lines(item, *previous_lines));
+                }
+                self.pushed = iter.take_pushed();
+                self.current = None;
+            }
+            let chunk = self.chunks.next()?;
+            match std::str::from_utf8(chunk.bytes) {
+                Ok(source) => {
+                    let iter =
+                        (self.make_iter)(source).with_pushed(std::mem::take(&mut self.pushed));
+                    self.current = Some((iter, chunk.previous_lines));
+                }
+                Err(err) => {
+                    self.failed = true;
+                    let valid = err.valid_up_to();
+                    let line_number = chunk.previous_lines + count_lines(&chunk.bytes[..valid]) + 1;
+                    let error = Error::at(
+                        &String::from_utf8_lossy(chunk.bytes),
+                        valid,
+                        line_number,
+                        Code::InvalidUtf8,
+                    );
+                    return Some(Err(error.with_hint(format!(
+                        "invalid byte sequence at offset {}",
+                        chunk.offset + valid
+                    ))));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::{parse, BeancountFile, DirectiveContent};
+
+    const SIMPLE: &str = include_str!("../tests/samples/simple.beancount");
+    const COMMENTS: &str = include_str!("../tests/samples/comments.beancount");
+    const OFFICIAL: &str = include_str!("../tests/samples/official.beancount");
+
+    fn parse_chunked(input: &[u8], min_chunk_size: usize) -> Result<BeancountFile<f64>, Error> {
+        super::parse(input, &ParseOptions::default(), min_chunk_size).collect()
+    }
+
+    #[rstest]
+    fn should_give_same_result_as_parsing_str(
+        #[values(SIMPLE, COMMENTS, OFFICIAL)] input: &str,
+        #[values(1, 100, CHUNK_SIZE)] min_chunk_size: usize,
+    ) {
+        let expected = parse::<f64>(input).unwrap();
+        let actual = parse_chunked(input.as_bytes(), min_chunk_size).unwrap();
+        assert_eq!(actual.directives, expected.directives);
+        assert_eq!(actual.warnings, expected.warnings);
+    }
+
+    #[rstest]
+    fn posting_lines_should_be_counted_from_the_start_of_the_input(
+        #[values(1, 100, CHUNK_SIZE)] min_chunk_size: usize,
+    ) {
+        let posting_lines = |file: BeancountFile<f64>| -> Vec<u32> {
+            file.directives
+                .iter()
+                .filter_map(|d| match &d.content {
+                    DirectiveContent::Transaction(trx) => {
+                        Some(trx.postings.iter().map(|p| p.line_number))
+                    }
+                    _ => None,
+                })
+                .flatten()
+                .collect()
+        };
+        let expected = posting_lines(parse::<f64>(OFFICIAL).unwrap());
+        let actual = posting_lines(parse_chunked(OFFICIAL.as_bytes(), min_chunk_size).unwrap());
+        assert_eq!(actual, expected);
+    }
+
+    #[rstest]
+    #[case::indented_lines("2023-01-01 * \"a\"\n  Assets:Cash  1 CHF\n  Assets:Bank\n2023-01-02 open A:B", &[52, 71])]
+    #[case::multi_line_string("2023-01-01 * \"a\n2023\"\n2023-01-02 open A:B", &[22, 41])]
+    #[case::escaped_quote("2023-01-01 * \"\\\"\n2023\"\n2023-01-02 open A:B", &[23, 42])]
+    #[case::quote_in_comment("; \"\n2023-01-02 open A:B", &[4, 23])]
+    fn should_split_at_the_start_of_entries(#[case] input: &str, #[case] expected: &[usize]) {
+        let chunks: Vec<usize> = Chunks {
+            rest: input.as_bytes(),
+            offset: 0,
+            lines: 0,
+            min_size: 1,
+        }
+        .map(|chunk| chunk.offset + chunk.bytes.len())
+        .collect();
+        assert_eq!(chunks, expected);
+    }
+
+    #[rstest]
+    fn tag_stack_should_be_carried_over_chunks() {
+        let input = "pushtag #trip\n2023-01-01 * \"a\"\n2023-01-02 * \"b\"\npoptag #trip\n2023-01-03 * \"c\"";
+        let tags: Vec<usize> = parse_chunked(input.as_bytes(), 1)
+            .unwrap()
+            .directives
+            .iter()
+            .map(|d| match &d.content {
+                DirectiveContent::Transaction(trx) => trx.tags.len(),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(tags, [1, 1, 0]);
+    }
+
+    #[rstest]
+    fn pushed_metadata_should_be_carried_over_chunks() {
+        let input =
+            "pushmeta trip: \"Paris\"\n2023-01-01 open A:B\npopmeta trip:\n2023-01-02 open A:C";
+        let trips: Vec<bool> = parse_chunked(input.as_bytes(), 1)
+            .unwrap()
+            .directives
+            .iter()
+            .map(|d| d.metadata.contains_key("trip"))
+            .collect();
+        assert_eq!(trips, [true, false]);
+    }
+
+    #[rstest]
+    fn syntax_error_should_have_the_line_number_in_the_whole_input(
+        #[values(1, CHUNK_SIZE)] min_chunk_size: usize,
+    ) {
+        let input = b"2023-01-01 open A:B\n2023-01-02 open A:B\n2023-01-03 open oops";
+        let error = parse_chunked(input, min_chunk_size).unwrap_err();
+        assert_eq!(error.code(), Code::InvalidSyntax);
+        assert_eq!(error.line_number(), 3);
+    }
+
+    #[rstest]
+    fn invalid_utf8_should_be_reported_with_line_and_offset(
+        #[values(1, CHUNK_SIZE)] min_chunk_size: usize,
+    ) {
+        let input = b"2023-01-01 open A:B\n2023-01-02 open A:B\n2023-01-03 * \"\xff\"";
+        let error = parse_chunked(input, min_chunk_size).unwrap_err();
+        assert_eq!(error.code(), Code::InvalidUtf8);
+        assert_eq!(error.line_number(), 3);
+        assert!(
+            error
+                .to_string()
+                .ends_with("(invalid byte sequence at offset 54)"),
+            "{error}"
+        );
+    }
+}
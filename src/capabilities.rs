@@ -0,0 +1,111 @@
+use crate::{lint::DirectiveKind, ParseOptions};
+
+/// What this version of the crate supports
+///
+/// See [`capabilities`](crate::capabilities)
+///
+/// With the `serde` feature, it is serialized as a map of lists of strings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Capabilities {
+    /// Version of the crate (like `2.2.0`)
+    pub version: &'static str,
+    /// Kinds of directive that are parsed
+    pub directives: Vec<DirectiveKind>,
+    /// Identifiers of the [`ParseOptions`], which are the names of their builder methods (like `strict_metadata`)
+    pub options: Vec<&'static str>,
+    /// Cargo features enabled in this build (like `serde`)
+    pub features: Vec<&'static str>,
+}
+
+impl Capabilities {
+    /// Returns true if the directives of this kind are parsed
+    #[must_use]
+    pub fn supports_directive(&self, kind: DirectiveKind) -> bool {
+        self.directives.contains(&kind)
+    }
+
+    /// Returns true if the [`ParseOptions`] has the option (like `strict_metadata`)
+    #[must_use]
+    pub fn supports_option(&self, name: &str) -> bool {
+        self.options.contains(&name)
+    }
+
+    /// Returns true if the cargo feature (like `serde`) is enabled
+    #[must_use]
+    pub fn has_feature(&self, name: &str) -> bool {
+        self.features.contains(&name)
+    }
+}
+
+pub(crate) fn capabilities() -> Capabilities {
+    let features = [
+        ("import", cfg!(feature = "import")),
+        ("rust_decimal", cfg!(feature = "rust_decimal")),
+        ("miette", cfg!(feature = "miette")),
+        ("serde", cfg!(feature = "serde")),
+        ("trie", cfg!(feature = "trie")),
+        (
+            "unicode-normalization",
+            cfg!(feature = "unicode-normalization"),
+        ),
+    ];
+    Capabilities {
+        version: env!("CARGO_PKG_VERSION"),
+        directives: DirectiveKind::ALL.to_vec(),
+        options: ParseOptions::NAMES.to_vec(),
+        features: features
+            .into_iter()
+            .filter_map(|(name, enabled)| enabled.then_some(name))
+            .collect(),
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Capabilities {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut s = serializer.serialize_struct("Capabilities", 4)?;
+        s.serialize_field("version", self.version)?;
+        s.serialize_field("directives", &self.directives)?;
+        s.serialize_field("options", &self.options)?;
+        s.serialize_field("features", &self.features)?;
+        s.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::Directive;
+
+    fn example(kind: DirectiveKind) -> &'static str {
+        match kind {
+            DirectiveKind::Transaction => "2023-05-20 *",
+            DirectiveKind::Price => "2023-05-20 price HOOL 10 USD",
+            DirectiveKind::Balance => "2023-05-20 balance Assets:Cash 10 USD",
+            DirectiveKind::Open => "2023-05-20 open Assets:Cash",
+            DirectiveKind::Close => "2023-05-20 close Assets:Cash",
+            DirectiveKind::Pad => "2023-05-20 pad Assets:Cash Equity:Opening",
+            DirectiveKind::Commodity => "2023-05-20 commodity USD",
+            DirectiveKind::Event => "2023-05-20 event \"location\" \"Paris\"",
+            DirectiveKind::Note => "2023-05-20 note Assets:Cash \"Visited Paris\"",
+            DirectiveKind::Document => "2023-05-20 document Assets:Cash \"receipts/paris.pdf\"",
+            DirectiveKind::Query => "2023-05-20 query \"cash\" \"SELECT account\"",
+            DirectiveKind::Custom => "2023-05-20 custom \"budget\"",
+        }
+    }
+
+    #[test]
+    fn every_listed_directive_kind_is_parsed() {
+        let directives = capabilities().directives;
+        let unique: HashSet<DirectiveKind> = directives.iter().copied().collect();
+        assert_eq!(unique.len(), directives.len());
+        for kind in directives {
+            let directive: Directive<f64> = example(kind).parse().unwrap();
+            assert_eq!(DirectiveKind::of(&directive.content), kind);
+        }
+    }
+}
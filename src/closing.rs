@@ -0,0 +1,71 @@
+use std::collections::HashSet;
+
+use crate::{
+    merge::transfer_posting, Account, Amount, BeancountFile, Close, Date, Directive,
+    DirectiveContent, Evaluate, Transaction,
+};
+
+pub(crate) fn closing_entries<D: Evaluate>(
+    file: &BeancountFile<D>,
+    as_of: Date,
+    target: &Account,
+    close_accounts: bool,
+) -> Vec<Directive<D>> {
+    // The entries take effect on the last day accounted in the balances
+    let date = Date::from_epoch_days(as_of.to_epoch_days() - 1).unwrap_or(as_of);
+    let roots = [
+        file.option("name_income").unwrap_or("Income"),
+        file.option("name_expenses").unwrap_or("Expenses"),
+    ];
+    let closed: Vec<(Account, Vec<Amount<D>>)> = file
+        .balances(Some(as_of))
+        .into_iter()
+        .filter(|(account, inventory)| {
+            let root = account.as_str().split(':').next().unwrap_or_default();
+            roots.contains(&root) && !inventory.is_empty()
+        })
+        .map(|(account, inventory)| (account, inventory.to_amounts()))
+        .collect();
+    let transactions = closed.iter().map(|(account, amounts)| {
+        let postings = amounts
+            .iter()
+            .map(|amount| {
+                transfer_posting(
+                    account,
+                    Amount {
+                        value: -amount.value.clone(),
+                        currency: amount.currency.clone(),
+                    },
+                )
+            })
+            .chain(
+                amounts
+                    .iter()
+                    .map(|amount| transfer_posting(target, amount.clone())),
+            )
+            .collect();
+        let transaction = Transaction {
+            flag: Some('*'),
+            payee: None,
+            narration: Some("Closing entry".into()),
+            tags: HashSet::new(),
+            links: HashSet::new(),
+            postings,
+            indent: None,
+        };
+        directive(date, DirectiveContent::Transaction(transaction))
+    });
+    let mut entries: Vec<Directive<D>> = transactions.collect();
+    if close_accounts {
+        entries.extend(
+            closed
+                .into_iter()
+                .map(|(account, _)| directive(date, DirectiveContent::Close(Close { account }))),
+        );
+    }
+    entries
+}
+
+fn directive<D>(date: Date, content: DirectiveContent<D>) -> Directive<D> {
+    Directive::new(date, content).with_provenance("closing entries")
+}
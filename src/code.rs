@@ -0,0 +1,257 @@
+use std::fmt::{Display, Formatter};
+
+/// Stable identifier of a kind of [`Error`](crate::Error) or [`Warning`](crate::Warning)
+///
+/// The letter is the default severity: codes starting with `E` are errors, and codes starting with `W` are warnings.
+/// A problem keeps its code when an option changes its severity: a warning turned into an error
+/// (like with [`ParseOptions::strict_metadata`](crate::ParseOptions::strict_metadata)),
+/// or an error relaxed into a warning (like with [`ParseOptions::skip_invalid_lines`](crate::ParseOptions::skip_invalid_lines)).
+/// The severity a problem is reported with shows in its message (`error[E014]` or `warning[E014]`),
+/// and in its diagnostic (see `diagnostic::Severity`, with the `serde` feature).
+///
+/// The codes are stable: a code is never reused for another kind of problem.
+///
+/// # Example
+///
+/// ```
+/// use beancount_parser::{BeancountFile, Code};
+/// let error = "2023-05-27 open oops".parse::<BeancountFile<f64>>().unwrap_err();
+/// assert_eq!(error.code(), Code::InvalidSyntax);
+/// assert_eq!(error.code().as_str(), "E001");
+/// assert_eq!(error.to_string(), "error[E001]: Invalid beancount syntax at line: 1");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum Code {
+    /// `E001`: The input is not valid beancount syntax
+    InvalidSyntax,
+    /// `W001`: A metadata key is declared more than once on the same directive or posting
+    ///
+    /// See [`WarningKind::DuplicateMetadataKey`](crate::WarningKind::DuplicateMetadataKey)
+    DuplicateMetadataKey,
+    /// `E002`: The date of a directive is followed by a time (like `2023-05-22 12:30:00 * "Coffee"`)
+    ///
+    /// Beancount dates cannot include a time.
+    /// With [`ParseOptions::strip_times`](crate::ParseOptions::strip_times), the time is removed instead,
+    /// and a warning with this code is emitted.
+    DateWithTime,
+    /// `E003`: The input is not valid UTF-8
+    ///
+    /// Only reported when parsing bytes (see [`parse_bytes`](crate::parse_bytes)).
+    InvalidUtf8,
+    /// `W002`: A `custom "budget"` directive does not follow the fava convention
+    ///
+    /// Only reported by [`fava::budgets`](crate::fava::budgets).
+    MalformedBudget,
+    /// `W003`: The cost of a posting is in the currency of its units (like `10 HOOL {10 HOOL}`)
+    ///
+    /// See [`CostIssue::SameCurrency`](crate::CostIssue::SameCurrency)
+    CostInUnitsCurrency,
+    /// `E004`: A transaction has more than one posting without amount
+    ///
+    /// The amounts cannot be interpolated. Only reported by [`BeancountFile::resolve`](crate::BeancountFile::resolve).
+    TooManyMissingAmounts,
+    /// `W004`: A directive is dated after today
+    ///
+    /// Only reported by the [`lint::FutureDatedDirective`](crate::lint::FutureDatedDirective) lint.
+    FutureDatedDirective,
+    /// `W005`: A transaction has been flagged `!` for too long
+    ///
+    /// Only reported by the [`lint::StaleIncompleteFlag`](crate::lint::StaleIncompleteFlag) lint.
+    StaleIncompleteFlag,
+    /// `E005`: An account name is not valid (like `Assets:Bank:` or `Assets::Bank`)
+    ///
+    /// With [`ParseOptions::lenient_accounts`](crate::ParseOptions::lenient_accounts),
+    /// the directive is kept with the account as written, and a warning with this code is emitted.
+    InvalidAccount,
+    /// `E006`: A `balance` assertion does not match the balance of the account
+    ///
+    /// Only reported by [`BeancountFile::verify_balances`](crate::BeancountFile::verify_balances).
+    BalanceMismatch,
+    /// `E007`: A posting line ends with a tag or a link (like `Expenses:Food  10 USD #lunch`)
+    ///
+    /// Beancount only allows tags and links in the transaction header.
+    /// With [`ParseOptions::strip_posting_tags`](crate::ParseOptions::strip_posting_tags),
+    /// they are removed instead, and a warning with this code is emitted.
+    TagOnPosting,
+    /// `E008`: An `include` directive names a file that does not exist
+    ///
+    /// Only reported by [`read_files`](crate::read_files).
+    IncludeNotFound,
+    /// `W006`: The glob pattern of an `include` directive (like `include "2023/*.bean"`) matches no file
+    ///
+    /// Only reported by [`read_files`](crate::read_files).
+    EmptyIncludeGlob,
+    /// `W007`: A `pad` directive is not used by any `balance` assertion
+    ///
+    /// Either no assertion of the padded account follows it, or another `pad` of the account
+    /// comes before the next assertion (the last pad is used then).
+    /// Only reported by [`BeancountFile::resolve`](crate::BeancountFile::resolve).
+    UnusedPad,
+    /// `E009`: The keyword of a directive is misspelled (like `Open` or `blance`)
+    ///
+    /// The error has a hint with the keyword it likely is.
+    /// With [`ParseOptions::case_insensitive_keywords`](crate::ParseOptions::case_insensitive_keywords),
+    /// the keywords only differing by their case (like `Open` or `TXN`) are accepted instead,
+    /// and a warning with this code is emitted.
+    MisspelledKeyword,
+    /// `E010`: A posting line has a token that is not part of the posting (like the `CHF` of `10 USD CHF`)
+    ///
+    /// With [`ParseOptions::drop_stray_tokens`](crate::ParseOptions::drop_stray_tokens),
+    /// the token is removed instead, and a warning with this code is emitted.
+    StrayPostingToken,
+    /// `E011`: The cost of a posting without amount (like `10 HOOL {}`) cannot be interpolated
+    ///
+    /// The other postings must tell the cost: they must all have an amount, and weigh a single currency.
+    /// Only reported by [`BeancountFile::resolve`](crate::BeancountFile::resolve).
+    UnresolvableCost,
+    /// `E012`: A number is written in scientific notation (like `2.5E-7 BTC`)
+    ///
+    /// Beancount numbers are plain decimals. The hint gives the number written as such.
+    /// With [`ParseOptions::allow_scientific_notation`](crate::ParseOptions::allow_scientific_notation),
+    /// the number is accepted instead.
+    ScientificNotation,
+    /// `E013`: A number has more digits than the number type can represent (like 30 decimals for `f64`)
+    ///
+    /// With [`ParseOptions::round_excess_precision`](crate::ParseOptions::round_excess_precision),
+    /// the number is rounded instead.
+    ExcessPrecision,
+    /// `E014`: An indented line of a directive is neither metadata nor a posting (like a posting with an invalid flag)
+    ///
+    /// The parsing does not stop at such a line, so that the lines following it are not silently ignored.
+    /// With [`ParseOptions::skip_invalid_lines`](crate::ParseOptions::skip_invalid_lines),
+    /// the line is skipped instead, and a warning with this code is emitted.
+    InvalidIndentedLine,
+    /// `W008`: Two accounts only differ by their Unicode normalization (like `ü` written composed and decomposed)
+    ///
+    /// Only reported by the `lint::SimilarAccounts` lint (requires the `unicode-normalization` feature).
+    SimilarAccounts,
+    /// `W009`: A `poptag` pops a tag that is not pushed (like a misspelled tag)
+    ///
+    /// The `poptag` has no effect. See [`WarningKind::UnpushedTagPopped`](crate::WarningKind::UnpushedTagPopped)
+    UnbalancedTag,
+    /// `W010`: A line outside of the directives is not recognized, and is ignored (like `Expenses:Food 10 CHF`)
+    ///
+    /// See [`LineKind::Unrecognized`](crate::LineKind::Unrecognized)
+    UnrecognizedLine,
+    /// `E015`: The cost or the price of a posting uses a syntax that is not supported
+    /// (like the lot label of `{2 USD, "lot"}`, the total cost `{{20 PLN}}` or the compound cost `{2 # 3 PLN}`)
+    ///
+    /// Unlike a [stray token](Code::StrayPostingToken), it cannot be dropped: the posting would silently lose its cost.
+    UnsupportedCostSyntax,
+}
+
+impl Code {
+    /// Returns the code as a string (like `E001`)
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::InvalidSyntax => "E001",
+            Self::DuplicateMetadataKey => "W001",
+            Self::DateWithTime => "E002",
+            Self::InvalidUtf8 => "E003",
+            Self::MalformedBudget => "W002",
+            Self::CostInUnitsCurrency => "W003",
+            Self::TooManyMissingAmounts => "E004",
+            Self::FutureDatedDirective => "W004",
+            Self::StaleIncompleteFlag => "W005",
+            Self::InvalidAccount => "E005",
+            Self::BalanceMismatch => "E006",
+            Self::TagOnPosting => "E007",
+            Self::IncludeNotFound => "E008",
+            Self::EmptyIncludeGlob => "W006",
+            Self::UnusedPad => "W007",
+            Self::MisspelledKeyword => "E009",
+            Self::StrayPostingToken => "E010",
+            Self::UnresolvableCost => "E011",
+            Self::ScientificNotation => "E012",
+            Self::ExcessPrecision => "E013",
+            Self::InvalidIndentedLine => "E014",
+            Self::SimilarAccounts => "W008",
+            Self::UnbalancedTag => "W009",
+            Self::UnrecognizedLine => "W010",
+            Self::UnsupportedCostSyntax => "E015",
+        }
+    }
+
+    pub(crate) fn summary(self) -> &'static str {
+        match self {
+            Self::InvalidSyntax => "Invalid beancount syntax",
+            Self::DuplicateMetadataKey => "Duplicate metadata key",
+            Self::DateWithTime => "Beancount dates cannot include a time",
+            Self::InvalidUtf8 => "Invalid UTF-8",
+            Self::MalformedBudget => "Malformed budget",
+            Self::CostInUnitsCurrency => "Cost in the currency of the units",
+            Self::TooManyMissingAmounts => "More than one posting without amount",
+            Self::FutureDatedDirective => "Directive dated in the future",
+            Self::StaleIncompleteFlag => "Stale incomplete flag",
+            Self::InvalidAccount => "Invalid account name",
+            Self::BalanceMismatch => "Balance assertion failed",
+            Self::TagOnPosting => "Tag or link on a posting",
+            Self::IncludeNotFound => "Included file not found",
+            Self::EmptyIncludeGlob => "Include pattern matching no file",
+            Self::UnusedPad => "Unused pad",
+            Self::MisspelledKeyword => "Misspelled directive keyword",
+            Self::StrayPostingToken => "Unexpected token in a posting",
+            Self::UnresolvableCost => "Cost cannot be interpolated",
+            Self::ScientificNotation => "Number in scientific notation",
+            Self::ExcessPrecision => "Number too precise for the number type",
+            Self::InvalidIndentedLine => "Invalid line in a directive",
+            Self::SimilarAccounts => "Accounts only differing by their Unicode normalization",
+            Self::UnbalancedTag => "Tag popped without being pushed",
+            Self::UnrecognizedLine => "Unrecognized line",
+            Self::UnsupportedCostSyntax => "Unsupported cost or price syntax",
+        }
+    }
+}
+
+impl Display for Code {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    const ALL: [Code; 25] = [
+        Code::InvalidSyntax,
+        Code::DuplicateMetadataKey,
+        Code::DateWithTime,
+        Code::InvalidUtf8,
+        Code::MalformedBudget,
+        Code::CostInUnitsCurrency,
+        Code::TooManyMissingAmounts,
+        Code::FutureDatedDirective,
+        Code::StaleIncompleteFlag,
+        Code::InvalidAccount,
+        Code::BalanceMismatch,
+        Code::TagOnPosting,
+        Code::IncludeNotFound,
+        Code::EmptyIncludeGlob,
+        Code::UnusedPad,
+        Code::MisspelledKeyword,
+        Code::StrayPostingToken,
+        Code::UnresolvableCost,
+        Code::ScientificNotation,
+        Code::ExcessPrecision,
+        Code::InvalidIndentedLine,
+        Code::SimilarAccounts,
+        Code::UnbalancedTag,
+        Code::UnrecognizedLine,
+        Code::UnsupportedCostSyntax,
+    ];
+
+    #[test]
+    fn codes_are_unique_and_well_formed() {
+        let codes: HashSet<&str> = ALL.iter().map(|code| code.as_str()).collect();
+        assert_eq!(codes.len(), ALL.len());
+        for code in codes {
+            assert!(code.starts_with('E') || code.starts_with('W'), "{code}");
+            assert_eq!(code.len(), 4, "{code}");
+        }
+    }
+}
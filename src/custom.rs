@@ -0,0 +1,91 @@
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::{satisfy, space1},
+    combinator::{map, not, value},
+    multi::many0,
+    sequence::{preceded, terminated},
+};
+
+use crate::{account, amount, date, string, Account, Amount, Date, IResult, Number, Span};
+
+/// A custom directive
+///
+/// It is used by plugins and tools (like fava) to declare their own kind of directive.
+/// The meaning of the values depends on the name of the directive.
+///
+/// See the [`fava`](crate::fava) module for a typed view of the directives used by fava.
+///
+/// # Example
+/// ```
+/// # use beancount_parser::{BeancountFile, CustomValue, DirectiveContent};
+/// let input = r#"2014-07-09 custom "budget" Expenses:Food "monthly" 45.30 USD TRUE"#;
+/// let beancount: BeancountFile<f64> = input.parse().unwrap();
+/// let DirectiveContent::Custom(ref custom) = beancount.directives[0].content else { unreachable!() };
+/// assert_eq!(custom.name, "budget");
+/// assert!(matches!(&custom.values[0], CustomValue::Account(account) if account.as_str() == "Expenses:Food"));
+/// assert!(matches!(&custom.values[1], CustomValue::String(s) if s == "monthly"));
+/// assert!(matches!(&custom.values[2], CustomValue::Amount(amount) if amount.value == 45.3));
+/// assert!(matches!(custom.values[3], CustomValue::Bool(true)));
+/// ```
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Custom<D> {
+    /// Name (type) of the custom directive
+    pub name: String,
+    /// Values following the name
+    pub values: Vec<CustomValue<D>>,
+}
+
+/// A value of a [`Custom`] directive
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum CustomValue<D> {
+    /// A quoted string
+    String(String),
+    /// A date
+    Date(Date),
+    /// A boolean (`TRUE` or `FALSE`)
+    Bool(bool),
+    /// An amount (number and currency)
+    Amount(Amount<D>),
+    /// A number without currency
+    Number(D),
+    /// An account
+    Account(Account),
+}
+
+pub(super) fn parse<D: Number>(input: Span<'_>) -> IResult<'_, Custom<D>> {
+    let (input, name) = string(input)?;
+    let (input, values) = many0(preceded(space1, custom_value))(input)?;
+    Ok((input, Custom { name, values }))
+}
+
+fn custom_value<D: Number>(input: Span<'_>) -> IResult<'_, CustomValue<D>> {
+    alt((
+        map(string, CustomValue::String),
+        map(date::parse, CustomValue::Date),
+        value(CustomValue::Bool(true), tag("TRUE")),
+        value(CustomValue::Bool(false), tag("FALSE")),
+        // An account following a number must not be read as the currency of an amount
+        map(
+            terminated(
+                amount::parse,
+                not(satisfy(|c: char| c.is_alphanumeric() || c == ':')),
+            ),
+            CustomValue::Amount,
+        ),
+        map(amount::expression, CustomValue::Number),
+        map(account::parse, CustomValue::Account),
+    ))(input)
+}
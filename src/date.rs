@@ -1,4 +1,8 @@
-use std::{cmp::Ordering, str::FromStr};
+use std::{
+    cmp::Ordering,
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
 
 use nom::{
     bytes::complete::take,
@@ -17,6 +21,9 @@ use super::{IResult, Span};
 ///
 /// If that is important to you, you should use a date-time library to verify the validity.
 ///
+/// With the `serde` feature, the date is (de)serialized as a `YYYY-MM-DD` string.
+/// Unlike the parser, deserialization fails if it is not an actual date (like `2023-02-30`).
+///
 /// # Example
 ///
 /// ```
@@ -44,6 +51,74 @@ impl Date {
     pub fn new(year: u16, month: u8, day: u8) -> Self {
         Self { year, month, day }
     }
+
+    /// Returns the number of days since 1970-01-01, in the proleptic Gregorian calendar
+    ///
+    /// It is a single integer that can be compared, stored or subtracted to get the number of days between two dates.
+    ///
+    /// The date is not validated: a day past the end of its month counts as a day of the next month.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use beancount_parser::Date;
+    /// assert_eq!(Date::new(1970, 1, 1).to_epoch_days(), 0);
+    /// assert_eq!(Date::new(2024, 3, 1).to_epoch_days() - Date::new(2024, 2, 28).to_epoch_days(), 2);
+    /// ```
+    #[must_use]
+    pub fn to_epoch_days(self) -> i32 {
+        let month = i32::from(self.month);
+        let year = i32::from(self.year) - i32::from(month <= 2);
+        let era = year.div_euclid(400);
+        let year_of_era = year.rem_euclid(400);
+        let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5
+            + i32::from(self.day)
+            - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146_097 + day_of_era - 719_468
+    }
+
+    /// Create the date from the number of days since 1970-01-01 (see [`Date::to_epoch_days`])
+    ///
+    /// The returned date is always a valid date.
+    /// Returns `None` if the year is not in the range of years supported by [`Date`] (`0` to `65535`).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use beancount_parser::Date;
+    /// assert_eq!(Date::from_epoch_days(19_417), Some(Date::new(2023, 3, 1)));
+    /// assert_eq!(Date::from_epoch_days(-800_000), None);
+    /// ```
+    #[must_use]
+    pub fn from_epoch_days(days: i32) -> Option<Self> {
+        let days = days.checked_add(719_468)?;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        let year = era * 400 + year_of_era + i32::from(month <= 2);
+        Some(Self {
+            year: u16::try_from(year).ok()?,
+            month: u8::try_from(month).ok()?,
+            day: u8::try_from(day).ok()?,
+        })
+    }
+}
+
+impl Display for Date {
+    /// Formats the date as `YYYY-MM-DD`
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
 }
 
 impl PartialOrd for Date {
@@ -53,11 +128,16 @@ impl PartialOrd for Date {
 }
 
 impl Ord for Date {
+    /// Compares the dates by their number of days since the epoch (see [`Date::to_epoch_days`])
+    ///
+    /// Invalid dates designating the same day as another date (like `2023-02-31` and `2023-03-03`)
+    /// are not equal, and are ordered by year, month and day.
     fn cmp(&self, other: &Self) -> Ordering {
-        self.year
-            .cmp(&other.year)
-            .then_with(|| self.month.cmp(&other.month))
-            .then_with(|| self.day.cmp(&other.day))
+        self.to_epoch_days()
+            .cmp(&other.to_epoch_days())
+            .then_with(|| {
+                (self.year, self.month, self.day).cmp(&(other.year, other.month, other.day))
+            })
     }
 }
 
@@ -65,7 +145,7 @@ impl FromStr for Date {
     type Err = crate::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let span = Span::new(s);
+        let span = crate::span(s);
         match all_consuming(parse)(span).finish() {
             Ok((_, date)) => Ok(date),
             Err(_) => Err(crate::Error::new(s, span)),
@@ -73,6 +153,25 @@ impl FromStr for Date {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for Date {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Date {
+    fn deserialize<De: serde::Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+        let text = String::deserialize(deserializer)?;
+        match text.parse::<Date>() {
+            // A date past the end of its month does not survive the round-trip through the epoch days
+            Ok(date) if Date::from_epoch_days(date.to_epoch_days()) == Some(date) => Ok(date),
+            _ => Err(serde::de::Error::custom(format!("invalid date: {text}"))),
+        }
+    }
+}
+
 pub(super) fn parse(input: Span<'_>) -> IResult<'_, Date> {
     let (input, _) = peek(tuple((digit1, char('-'), digit1, char('-'), digit1)))(input)?;
     cut(do_parse)(input)
@@ -107,8 +206,82 @@ fn day(input: Span<'_>) -> IResult<'_, u8> {
 
 #[cfg(test)]
 mod tests {
+    use rstest::rstest;
+
     use super::*;
 
+    fn days_in_month(year: u16, month: u8) -> u8 {
+        let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+        match month {
+            2 if leap => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        }
+    }
+
+    #[rstest]
+    #[case::epoch(Date::new(1970, 1, 1), 0)]
+    #[case::before_epoch(Date::new(1969, 12, 31), -1)]
+    #[case::first_leap_day_after_epoch(Date::new(1972, 2, 29), 789)]
+    #[case::century_leap_year(Date::new(2000, 2, 29), 11_016)]
+    #[case::after_century_leap_day(Date::new(2000, 3, 1), 11_017)]
+    #[case::century_not_leap(Date::new(1900, 3, 1), -25_508)]
+    #[case::before_century_not_leap(Date::new(1900, 2, 28), -25_509)]
+    #[case::y2k38(Date::new(2038, 1, 19), 24_855)]
+    #[case::end_of_year(Date::new(2023, 12, 31), 19_722)]
+    #[case::start_of_year(Date::new(2024, 1, 1), 19_723)]
+    #[case::year_zero(Date::new(0, 1, 1), -719_528)]
+    fn epoch_days_of_known_dates(#[case] date: Date, #[case] days: i32) {
+        assert_eq!(date.to_epoch_days(), days);
+        assert_eq!(Date::from_epoch_days(days), Some(date));
+    }
+
+    #[test]
+    fn epoch_days_should_round_trip_every_day_over_two_centuries() {
+        let start = Date::new(1900, 1, 1).to_epoch_days();
+        let end = Date::new(2100, 12, 31).to_epoch_days();
+        let mut previous = Date::from_epoch_days(start - 1).unwrap();
+        for days in start..=end {
+            let date = Date::from_epoch_days(days).unwrap();
+            assert!((1..=12).contains(&date.month), "{date}");
+            assert!(
+                (1..=days_in_month(date.year, date.month)).contains(&date.day),
+                "{date}"
+            );
+            assert_eq!(date.to_epoch_days(), days, "{date}");
+            let expected_next = if previous.day < days_in_month(previous.year, previous.month) {
+                Date::new(previous.year, previous.month, previous.day + 1)
+            } else if previous.month < 12 {
+                Date::new(previous.year, previous.month + 1, 1)
+            } else {
+                Date::new(previous.year + 1, 1, 1)
+            };
+            assert_eq!(date, expected_next);
+            assert!(previous < date);
+            previous = date;
+        }
+    }
+
+    #[rstest]
+    #[case::before_year_zero(-719_529)]
+    #[case::min(i32::MIN)]
+    #[case::after_year_65535(Date::new(u16::MAX, 12, 31).to_epoch_days() + 1)]
+    #[case::max(i32::MAX)]
+    fn from_epoch_days_should_reject_years_out_of_range(#[case] days: i32) {
+        assert_eq!(Date::from_epoch_days(days), None);
+    }
+
+    #[test]
+    fn ord_should_be_consistent_with_eq_for_invalid_dates() {
+        let invalid = Date::new(2023, 2, 31);
+        let valid = Date::new(2023, 3, 3);
+        assert_eq!(invalid.to_epoch_days(), valid.to_epoch_days());
+        assert_ne!(invalid.cmp(&valid), Ordering::Equal);
+        assert!(invalid < Date::new(2023, 3, 4));
+        assert!(invalid > Date::new(2023, 3, 2));
+    }
+
     #[test]
     fn date_from_str_should_parse_valid_date() {
         let date: Date = "2023-03-12".parse().unwrap();
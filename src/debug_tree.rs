@@ -0,0 +1,188 @@
+use std::{
+    collections::HashSet,
+    fmt::{Display, Formatter, Result},
+};
+
+use crate::{
+    metadata,
+    syntax::{AmountText, CostText, CustomValueText, MetadataValueText},
+    BeancountFile, Currency, Directive, DirectiveContent, Link, Posting, PostingPrice, Quoted, Tag,
+};
+
+/// Display adapter writing a [`Directive`] as an indented tree
+pub(crate) struct DirectiveTree<'a, D>(pub(crate) &'a Directive<D>);
+
+/// Display adapter writing a [`BeancountFile`] as an indented tree
+pub(crate) struct FileTree<'a, D>(pub(crate) &'a BeancountFile<D>);
+
+impl<D: Display> Display for DirectiveTree<'_, D> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        directive(f, self.0)
+    }
+}
+
+impl<D: Display> Display for FileTree<'_, D> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let file = self.0;
+        for option in &file.options {
+            writeln!(f, "option {} {}", option.name, Quoted(&option.value))?;
+        }
+        for path in &file.includes {
+            writeln!(f, "include {}", Quoted(&path.to_string_lossy()))?;
+        }
+        for plugin in &file.plugins {
+            write!(f, "plugin {}", Quoted(&plugin.name))?;
+            if let Some(config) = &plugin.config {
+                write!(f, " {}", Quoted(config))?;
+            }
+            writeln!(f)?;
+        }
+        for d in &file.directives {
+            directive(f, d)?;
+        }
+        for warning in &file.warnings {
+            writeln!(f, "{warning}")?;
+        }
+        Ok(())
+    }
+}
+
+fn directive<D: Display>(f: &mut Formatter<'_>, directive: &Directive<D>) -> Result {
+    let field = |f: &mut Formatter<'_>, name: &str, value: &dyn Display| -> Result {
+        writeln!(f, "  {name}: {value}")
+    };
+    match &directive.content {
+        DirectiveContent::Transaction(trx) => {
+            writeln!(f, "{} transaction", directive.date)?;
+            if let Some(flag) = trx.flag {
+                field(f, "flag", &flag)?;
+            }
+            if let Some(payee) = &trx.payee {
+                field(f, "payee", &Quoted(payee))?;
+            }
+            if let Some(narration) = &trx.narration {
+                field(f, "narration", &Quoted(narration))?;
+            }
+            tags_and_links(f, &trx.tags, &trx.links)?;
+            metadata(f, 1, &directive.metadata)?;
+            for p in &trx.postings {
+                posting(f, p)?;
+            }
+            return Ok(());
+        }
+        DirectiveContent::Price(price) => {
+            writeln!(f, "{} price", directive.date)?;
+            field(f, "currency", &price.currency)?;
+            field(f, "amount", &AmountText(&price.amount))?;
+        }
+        DirectiveContent::Balance(balance) => {
+            writeln!(f, "{} balance", directive.date)?;
+            field(f, "account", &balance.account)?;
+            field(f, "amount", &AmountText(&balance.amount))?;
+            if let Some(tolerance) = &balance.tolerance {
+                field(f, "tolerance", tolerance)?;
+            }
+        }
+        DirectiveContent::Open(open) => {
+            writeln!(f, "{} open", directive.date)?;
+            field(f, "account", &open.account)?;
+            if !open.currencies.is_empty() {
+                let mut currencies: Vec<&str> =
+                    open.currencies.iter().map(Currency::as_str).collect();
+                currencies.sort_unstable();
+                field(f, "currencies", &currencies.join(","))?;
+            }
+            if let Some(booking_method) = &open.booking_method {
+                field(f, "booking_method", booking_method)?;
+            }
+        }
+        DirectiveContent::Close(close) => {
+            writeln!(f, "{} close", directive.date)?;
+            field(f, "account", &close.account)?;
+        }
+        DirectiveContent::Pad(pad) => {
+            writeln!(f, "{} pad", directive.date)?;
+            field(f, "account", &pad.account)?;
+            field(f, "source_account", &pad.source_account)?;
+        }
+        DirectiveContent::Commodity(currency) => {
+            writeln!(f, "{} commodity", directive.date)?;
+            field(f, "currency", currency)?;
+        }
+        DirectiveContent::Event(event) => {
+            writeln!(f, "{} event", directive.date)?;
+            field(f, "name", &Quoted(&event.name))?;
+            field(f, "value", &Quoted(&event.value))?;
+        }
+        DirectiveContent::Note(note) => {
+            writeln!(f, "{} note", directive.date)?;
+            field(f, "account", &note.account)?;
+            field(f, "description", &Quoted(&note.description))?;
+            tags_and_links(f, &note.tags, &note.links)?;
+        }
+        DirectiveContent::Document(document) => {
+            writeln!(f, "{} document", directive.date)?;
+            field(f, "account", &document.account)?;
+            field(f, "path", &Quoted(&document.path))?;
+            tags_and_links(f, &document.tags, &document.links)?;
+        }
+        DirectiveContent::Query(query) => {
+            writeln!(f, "{} query", directive.date)?;
+            field(f, "name", &Quoted(&query.name))?;
+            field(f, "query_string", &Quoted(&query.query_string))?;
+        }
+        DirectiveContent::Custom(custom) => {
+            writeln!(f, "{} custom", directive.date)?;
+            field(f, "name", &Quoted(&custom.name))?;
+            for value in &custom.values {
+                field(f, "value", &CustomValueText(value))?;
+            }
+        }
+    }
+    metadata(f, 1, &directive.metadata)
+}
+
+/// Write the tags and the links (if any), sorted by name
+fn tags_and_links(f: &mut Formatter<'_>, tags: &HashSet<Tag>, links: &HashSet<Link>) -> Result {
+    if !tags.is_empty() {
+        let mut tags: Vec<String> = tags.iter().map(|tag| format!("#{tag}")).collect();
+        tags.sort();
+        writeln!(f, "  tags: {}", tags.join(" "))?;
+    }
+    if !links.is_empty() {
+        let mut links: Vec<String> = links.iter().map(|link| format!("^{link}")).collect();
+        links.sort();
+        writeln!(f, "  links: {}", links.join(" "))?;
+    }
+    Ok(())
+}
+
+fn posting<D: Display>(f: &mut Formatter<'_>, posting: &Posting<D>) -> Result {
+    writeln!(f, "  posting {}", posting.account)?;
+    if let Some(flag) = posting.flag {
+        writeln!(f, "    flag: {flag}")?;
+    }
+    if let Some(amount) = &posting.amount {
+        writeln!(f, "    amount: {}", AmountText(amount))?;
+    }
+    if let Some(cost) = &posting.cost {
+        writeln!(f, "    cost: {}", CostText(cost))?;
+    }
+    match &posting.price {
+        Some(PostingPrice::Unit(price)) => writeln!(f, "    price: @ {}", AmountText(price))?,
+        Some(PostingPrice::Total(price)) => writeln!(f, "    price: @@ {}", AmountText(price))?,
+        None => (),
+    }
+    metadata(f, 2, &posting.metadata)
+}
+
+/// Write the metadata entries sorted by key
+fn metadata<D: Display>(f: &mut Formatter<'_>, depth: usize, map: &metadata::Map<D>) -> Result {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_by(|(a, _), (b, _)| a.as_ref().cmp(b.as_ref()));
+    for (key, value) in entries {
+        let indent = "  ".repeat(depth);
+        writeln!(f, "{indent}{key}: {}", MetadataValueText(value))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,311 @@
+//! Machine-readable diagnostics, for tools like continuous integration (requires the `serde` feature)
+//!
+//! The errors, the warnings and the findings of the [lints](crate::lint) all convert into [`Diagnostic`]s
+//! with [`diagnostics`]. A diagnostic serializes as a flat JSON object, so writing one diagnostic per line
+//! gives [JSON lines](https://jsonlines.org).
+//! The [`sarif`] function turns them into a [SARIF 2.1.0](https://sarifweb.azurewebsites.net) log,
+//! with the subset of the format accepted by the GitHub code scanning.
+//!
+//! # Example
+//!
+//! ```
+//! use beancount_parser::{diagnostic, lint::{FixedClock, LintSet, StaleIncompleteFlag}, parse_report, Date, ParseOptions, ParseReport};
+//! let input = r#"
+//! 2023-05-20 open Assets:Cash
+//!   id: 1
+//!   id: 2
+//! 2023-05-20 ! "Coffee"
+//!   Expenses:Food  3 CHF
+//!   Assets:Cash
+//! "#;
+//! let report: ParseReport<f64> = parse_report(input, &ParseOptions::default());
+//! let lints = LintSet::new(FixedClock::new(Date::new(2023, 12, 1)))
+//!     .stale_incomplete_flag(StaleIncompleteFlag::new(30))
+//!     .check(&report.file);
+//! let diagnostics = diagnostic::diagnostics(
+//!     &report.sources,
+//!     &report.errors,
+//!     report.warnings.iter().chain(&lints),
+//! );
+//! assert_eq!(diagnostics.len(), 2);
+//!
+//! let json_lines: Vec<String> = diagnostics
+//!     .iter()
+//!     .map(|d| serde_json::to_string(d).unwrap())
+//!     .collect();
+//! assert!(json_lines[0].starts_with(r#"{"code":"W001","severity":"warning","#));
+//!
+//! let sarif = serde_json::to_value(diagnostic::sarif(&diagnostics)).unwrap();
+//! assert_eq!(sarif["runs"][0]["results"][1]["ruleId"], "W005");
+//! ```
+
+use std::{
+    fmt::{Display, Formatter},
+    path::{Path, PathBuf},
+};
+
+use serde::{Serialize, Serializer};
+
+use crate::{Code, Error, Locate, SourceMap, Warning};
+
+/// An error or a warning, with everything needed to report it outside of the process
+///
+/// See the [module documentation](self) for an example.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct Diagnostic {
+    /// Code identifying the kind of problem (serialized as its string, like `"E001"`)
+    #[serde(serialize_with = "serialize_code")]
+    pub code: Code,
+    /// Whether the problem is an error or a warning
+    pub severity: Severity,
+    /// Description of the problem, as displayed after the code (like `Invalid beancount syntax at line: 3`)
+    pub message: String,
+    /// Path of the file of the problem, as declared in the [`SourceMap`]
+    pub file: PathBuf,
+    /// Line number (starting at 1)
+    pub line: u32,
+    /// Column (in characters, starting at 1), if known
+    pub column: Option<u32>,
+    /// How to fix the problem, if known
+    pub suggestion: Option<String>,
+}
+
+/// Severity of a [`Diagnostic`]
+///
+/// It is the severity with which the problem was reported, which is also the SARIF level.
+/// It may differ from the letter of the code: a warning turned into an error by an option
+/// (like [`ParseOptions::strict_metadata`](crate::ParseOptions::strict_metadata)) is an error with a `W` code,
+/// and an error relaxed into a warning (like with [`ParseOptions::skip_invalid_lines`](crate::ParseOptions::skip_invalid_lines))
+/// is a warning with an `E` code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Severity {
+    /// An [`Error`]
+    Error,
+    /// A [`Warning`]
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+        })
+    }
+}
+
+/// Convert the errors and the warnings (including the findings of the [lints](crate::lint)) into diagnostics
+///
+/// The errors come first, then the warnings, each in the given order.
+/// The paths of the files are taken from `sources`.
+#[must_use]
+pub fn diagnostics<'a>(
+    sources: &SourceMap,
+    errors: impl IntoIterator<Item = &'a Error>,
+    warnings: impl IntoIterator<Item = &'a Warning>,
+) -> Vec<Diagnostic> {
+    let errors = errors.into_iter().map(|error| {
+        Diagnostic::new(
+            sources,
+            error,
+            error.code(),
+            Severity::Error,
+            error.hint().map(Into::into),
+        )
+    });
+    let warnings = warnings
+        .into_iter()
+        .map(|warning| Diagnostic::new(sources, warning, warning.code(), Severity::Warning, None));
+    errors.chain(warnings).collect()
+}
+
+impl Diagnostic {
+    fn new(
+        sources: &SourceMap,
+        problem: &impl Locate,
+        code: Code,
+        severity: Severity,
+        suggestion: Option<String>,
+    ) -> Self {
+        let location = problem.location();
+        let text = problem.to_string();
+        let message = text
+            .strip_prefix(&format!("{severity}[{code}]: "))
+            .map_or_else(|| text.clone(), Into::into);
+        Self {
+            code,
+            severity,
+            message,
+            file: sources
+                .path(location.file_id)
+                .unwrap_or(Path::new("<unknown>"))
+                .to_path_buf(),
+            line: location.line_number,
+            column: location.column,
+            suggestion,
+        }
+    }
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)] // Signature required by `serialize_with`
+fn serialize_code<S: Serializer>(code: &Code, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(code.as_str())
+}
+
+/// Returns a [SARIF 2.1.0](https://sarifweb.azurewebsites.net) log of the diagnostics, to serialize with serde
+///
+/// The log has a single run, with a rule for each code of the diagnostics and a result for each diagnostic.
+/// It only uses the subset of the format accepted by the GitHub code scanning.
+///
+/// See the [module documentation](self) for an example.
+#[must_use]
+pub fn sarif(diagnostics: &[Diagnostic]) -> SarifLog<'_> {
+    SarifLog { diagnostics }
+}
+
+/// [SARIF](https://sarifweb.azurewebsites.net) log of diagnostics, returned by [`sarif`]
+#[derive(Debug, Clone, Copy)]
+pub struct SarifLog<'a> {
+    diagnostics: &'a [Diagnostic],
+}
+
+impl Serialize for SarifLog<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut codes: Vec<Code> = self.diagnostics.iter().map(|d| d.code).collect();
+        codes.sort_unstable();
+        codes.dedup();
+        let rules = codes
+            .into_iter()
+            .map(|code| sarif::Rule {
+                id: code.as_str(),
+                short_description: sarif::Message {
+                    text: code.summary().into(),
+                },
+            })
+            .collect();
+        let results = self
+            .diagnostics
+            .iter()
+            .map(|diagnostic| sarif::Result {
+                rule_id: diagnostic.code.as_str(),
+                level: match diagnostic.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                },
+                message: sarif::Message {
+                    text: diagnostic.message.clone(),
+                },
+                locations: [sarif::Location {
+                    physical_location: sarif::PhysicalLocation {
+                        artifact_location: sarif::ArtifactLocation {
+                            uri: diagnostic.file.to_string_lossy().replace('\\', "/"),
+                        },
+                        // Regions start at line 1, so a diagnostic without line has none
+                        region: (diagnostic.line > 0).then_some(sarif::Region {
+                            start_line: diagnostic.line,
+                            start_column: diagnostic.column,
+                        }),
+                    },
+                }],
+            })
+            .collect();
+        sarif::Log {
+            schema: "https://json.schemastore.org/sarif-2.1.0.json",
+            version: "2.1.0",
+            runs: [sarif::Run {
+                tool: sarif::Tool {
+                    driver: sarif::Driver {
+                        name: env!("CARGO_PKG_NAME"),
+                        information_uri: env!("CARGO_PKG_REPOSITORY"),
+                        rules,
+                    },
+                },
+                results,
+            }],
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Objects of the SARIF format
+mod sarif {
+    use serde::Serialize;
+
+    #[derive(Serialize)]
+    pub(super) struct Log {
+        #[serde(rename = "$schema")]
+        pub(super) schema: &'static str,
+        pub(super) version: &'static str,
+        pub(super) runs: [Run; 1],
+    }
+
+    #[derive(Serialize)]
+    pub(super) struct Run {
+        pub(super) tool: Tool,
+        pub(super) results: Vec<Result>,
+    }
+
+    #[derive(Serialize)]
+    pub(super) struct Tool {
+        pub(super) driver: Driver,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(super) struct Driver {
+        pub(super) name: &'static str,
+        pub(super) information_uri: &'static str,
+        pub(super) rules: Vec<Rule>,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(super) struct Rule {
+        pub(super) id: &'static str,
+        pub(super) short_description: Message,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(super) struct Result {
+        pub(super) rule_id: &'static str,
+        pub(super) level: &'static str,
+        pub(super) message: Message,
+        pub(super) locations: [Location; 1],
+    }
+
+    #[derive(Serialize)]
+    pub(super) struct Message {
+        pub(super) text: String,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(super) struct Location {
+        pub(super) physical_location: PhysicalLocation,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(super) struct PhysicalLocation {
+        pub(super) artifact_location: ArtifactLocation,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub(super) region: Option<Region>,
+    }
+
+    #[derive(Serialize)]
+    pub(super) struct ArtifactLocation {
+        pub(super) uri: String,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(super) struct Region {
+        pub(super) start_line: u32,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub(super) start_column: Option<u32>,
+    }
+}
@@ -0,0 +1,323 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::{Amount, BeancountFile, Currency, Evaluate};
+
+/// Number of decimals with which the amounts of each currency are displayed
+///
+/// [`BeancountFile::display_context`] infers it from the file, like beancount does:
+/// the precision of a currency is the most common number of decimals among the amounts in that currency,
+/// as written in the file.
+///
+/// # Example
+/// ```
+/// use beancount_parser::{BeancountFile, DisplayContext};
+/// let input = r#"
+/// 2023-05-20 price CHF 1.12 USD
+/// 2023-05-21 price CHF 1.125 USD
+/// 2023-05-22 price CHF 1.13 USD
+/// "#;
+/// let beancount: BeancountFile<f64> = input.parse().unwrap();
+/// let context = beancount.display_context();
+/// assert_eq!(context.precision("USD"), Some(2));
+/// assert_eq!(context.precision("CHF"), None);
+///
+/// let context = context.with_precision("CHF".parse().unwrap(), 4);
+/// assert_eq!(context.precision("CHF"), Some(4));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DisplayContext {
+    precisions: HashMap<Currency, usize>,
+}
+
+impl DisplayContext {
+    /// Returns the number of decimals of the currency, if known
+    #[must_use]
+    pub fn precision(&self, currency: &str) -> Option<usize> {
+        self.precisions.get(currency).copied()
+    }
+
+    /// Set the number of decimals of the currency
+    #[must_use]
+    pub fn with_precision(mut self, currency: Currency, precision: usize) -> Self {
+        self.precisions.insert(currency, precision);
+        self
+    }
+}
+
+/// Number of decimals of the literals of each amount of a directive, in the order of `DirectiveContent::for_each_amount`
+///
+/// The numbers are packed on [`DECIMAL_BITS`] bits each, so that parsing a directive does not allocate.
+/// The amounts beyond the capacity are counted but their decimals are unknown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Decimals {
+    packed: u128,
+    len: usize,
+}
+
+/// Number of bits of each number of decimals in [`Decimals`] (larger numbers are saturated)
+const DECIMAL_BITS: usize = 5;
+
+/// Number of amounts whose decimals fit in [`Decimals`]
+const DECIMALS_CAPACITY: usize = 128 / DECIMAL_BITS;
+
+impl Decimals {
+    /// Append the number of decimals of the next amount
+    #[must_use]
+    pub(crate) fn push(self, decimals: usize) -> Self {
+        let max = (1 << DECIMAL_BITS) - 1;
+        let packed = if self.len < DECIMALS_CAPACITY {
+            self.packed | (decimals.min(max) as u128) << (self.len * DECIMAL_BITS)
+        } else {
+            self.packed
+        };
+        Self {
+            packed,
+            len: self.len + 1,
+        }
+    }
+
+    /// Returns the number of decimals of the amount at `index`, if known
+    fn get(self, index: usize) -> Option<usize> {
+        (index < self.len.min(DECIMALS_CAPACITY)).then(|| {
+            let max = (1 << DECIMAL_BITS) - 1;
+            ((self.packed >> (index * DECIMAL_BITS)) & max) as usize
+        })
+    }
+}
+
+pub(crate) fn display_context<D>(file: &BeancountFile<D>) -> DisplayContext {
+    let mut counts: HashMap<&Currency, HashMap<usize, usize>> = HashMap::new();
+    for directive in &file.directives {
+        let mut amounts = 0;
+        directive.content.for_each_amount(|_| amounts += 1);
+        // The directives built by a program, or changed since they were parsed, do not tell their decimals
+        if amounts != directive.decimals.len {
+            continue;
+        }
+        let mut index = 0;
+        directive.content.for_each_amount(|amount| {
+            if let Some(decimals) = directive.decimals.get(index) {
+                *counts
+                    .entry(&amount.currency)
+                    .or_default()
+                    .entry(decimals)
+                    .or_default() += 1;
+            }
+            index += 1;
+        });
+    }
+    let precisions = counts
+        .into_iter()
+        .filter_map(|(currency, decimals)| {
+            // On a tie, the larger precision wins so that no decimal is hidden
+            let (precision, _) = decimals
+                .into_iter()
+                .max_by_key(|&(decimals, count)| (count, decimals))?;
+            Some((currency.clone(), precision))
+        })
+        .collect();
+    DisplayContext { precisions }
+}
+
+/// Options of [`Amount::display_with`], to render amounts in human-facing reports
+///
+/// The rendering is not beancount syntax (the currency may be replaced by a symbol, the decimal separator may be a comma, etc.),
+/// it must not be written to a beancount file.
+///
+/// [`BeancountFile::amount_display`] returns the options of a file,
+/// which can then be overridden.
+///
+/// # Example
+/// ```
+/// use beancount_parser::{AmountDisplay, BeancountFile, DirectiveContent};
+/// let input = r#"
+/// option "render_commas" "TRUE"
+/// 2023-05-20 balance Assets:Cash -1234.5 USD
+/// "#;
+/// let beancount: BeancountFile<f64> = input.parse().unwrap();
+/// let DirectiveContent::Balance(balance) = &beancount.directives[0].content else { unreachable!() };
+/// let display = beancount
+///     .amount_display()
+///     .symbol("USD".parse().unwrap(), "$")
+///     .symbol_before(true)
+///     .accounting_negatives(true);
+/// assert_eq!(balance.amount.display_with(&display), "($1,234.5)");
+/// assert_eq!(balance.amount.display_with(&AmountDisplay::default()), "-1234.5 USD");
+/// ```
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+#[allow(clippy::struct_excessive_bools)]
+pub struct AmountDisplay {
+    context: DisplayContext,
+    symbols: HashMap<Currency, String>,
+    symbol_before: bool,
+    accounting_negatives: bool,
+    render_commas: bool,
+    decimal_comma: bool,
+}
+
+impl AmountDisplay {
+    /// Number of decimals of each currency (the amounts of other currencies are rendered with all their decimals)
+    #[must_use]
+    pub fn context(mut self, context: DisplayContext) -> Self {
+        self.context = context;
+        self
+    }
+
+    /// Render the currency as the given symbol (like `$` for `USD`)
+    #[must_use]
+    pub fn symbol(mut self, currency: Currency, symbol: impl Into<String>) -> Self {
+        self.symbols.insert(currency, symbol.into());
+        self
+    }
+
+    /// Render the currency before the number (disabled by default)
+    ///
+    /// A symbol is written right before the number (`$12.50`), a currency is separated by a space (`USD 12.50`).
+    #[must_use]
+    pub fn symbol_before(mut self, before: bool) -> Self {
+        self.symbol_before = before;
+        self
+    }
+
+    /// Render the negative amounts in parentheses, like in accounting (`(12.50 USD)` instead of `-12.50 USD`, disabled by default)
+    #[must_use]
+    pub fn accounting_negatives(mut self, accounting: bool) -> Self {
+        self.accounting_negatives = accounting;
+        self
+    }
+
+    /// Separate the thousands (disabled by default, like the beancount option `render_commas`)
+    #[must_use]
+    pub fn render_commas(mut self, render_commas: bool) -> Self {
+        self.render_commas = render_commas;
+        self
+    }
+
+    /// Use a comma as decimal separator, and a dot as thousands separator (disabled by default)
+    #[must_use]
+    pub fn decimal_comma(mut self, decimal_comma: bool) -> Self {
+        self.decimal_comma = decimal_comma;
+        self
+    }
+}
+
+pub(crate) fn amount_display<D>(file: &BeancountFile<D>) -> AmountDisplay {
+    // Like beancount, which accepts `TRUE`, `on` or `1` (in any case) as true
+    let render_commas = file.option("render_commas").is_some_and(|value| {
+        value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("on") || value == "1"
+    });
+    AmountDisplay::default()
+        .context(display_context(file))
+        .render_commas(render_commas)
+}
+
+pub(crate) fn render<D: Evaluate + Display>(amount: &Amount<D>, display: &AmountDisplay) -> String {
+    let negative = amount.value < D::default();
+    let magnitude = if negative {
+        -amount.value.clone()
+    } else {
+        amount.value.clone()
+    };
+    let digits = magnitude.to_string();
+    let (integer, fraction) = digits.split_once('.').unwrap_or((&digits, ""));
+    let (integer, fraction) = match display.context.precision(amount.currency.as_str()) {
+        Some(precision) => rounded(integer, fraction, precision),
+        None => (integer.to_owned(), fraction.to_owned()),
+    };
+    // An amount rounded to zero is not negative
+    let negative = negative && integer.chars().chain(fraction.chars()).any(|c| c != '0');
+    let (thousands, decimal) = if display.decimal_comma {
+        ('.', ',')
+    } else {
+        (',', '.')
+    };
+    let mut number = if display.render_commas {
+        grouped(&integer, thousands)
+    } else {
+        integer
+    };
+    if !fraction.is_empty() {
+        number.push(decimal);
+        number.push_str(&fraction);
+    }
+    let symbol = display.symbols.get(&amount.currency);
+    let rendered = match (symbol, display.symbol_before) {
+        (Some(symbol), true) => format!("{symbol}{number}"),
+        (None, true) => format!("{} {number}", amount.currency),
+        (symbol, false) => format!(
+            "{number} {}",
+            symbol.map_or(amount.currency.as_str(), String::as_str)
+        ),
+    };
+    match (negative, display.accounting_negatives) {
+        (true, true) => format!("({rendered})"),
+        (true, false) => format!("-{rendered}"),
+        (false, _) => rendered,
+    }
+}
+
+/// Round the digits half away from zero to `precision` decimals
+fn rounded(integer: &str, fraction: &str, precision: usize) -> (String, String) {
+    if fraction.len() <= precision {
+        return (integer.to_owned(), format!("{fraction:0<precision$}"));
+    }
+    let mut digits: Vec<u8> = integer
+        .bytes()
+        .chain(fraction.bytes().take(precision))
+        .collect();
+    if fraction.as_bytes()[precision] >= b'5' {
+        let carry = digits.iter_mut().rev().all(|digit| {
+            if *digit == b'9' {
+                *digit = b'0';
+                true
+            } else {
+                *digit += 1;
+                false
+            }
+        });
+        if carry {
+            digits.insert(0, b'1');
+        }
+    }
+    let fraction = digits.split_off(digits.len() - precision);
+    (
+        String::from_utf8_lossy(&digits).into_owned(),
+        String::from_utf8_lossy(&fraction).into_owned(),
+    )
+}
+
+fn grouped(integer: &str, separator: char) -> String {
+    let mut result = String::with_capacity(integer.len() + integer.len() / 3);
+    for (index, digit) in integer.chars().enumerate() {
+        if index > 0 && (integer.len() - index) % 3 == 0 {
+            result.push(separator);
+        }
+        result.push(digit);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(include_str!("../tests/samples/official.beancount"))]
+    #[case(include_str!("../tests/samples/simple.beancount"))]
+    #[case(include_str!("../tests/samples/comments.beancount"))]
+    #[case(include_str!("../tests/samples/diagnostics.beancount"))]
+    fn decimals_should_be_recorded_for_every_amount(#[case] input: &str) {
+        let (file, _) = crate::parse_lenient::<f64>(input);
+        for directive in &file.directives {
+            let mut amounts = 0;
+            directive.content.for_each_amount(|_| amounts += 1);
+            assert_eq!(
+                directive.decimals.len, amounts,
+                "line {}",
+                directive.line_number
+            );
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use std::collections::HashSet;
+
+use nom::character::complete::space1;
+
+use crate::{account, string, transaction, Account, IResult, Link, Span, Tag};
+
+/// A document (like a statement) attached to an account
+///
+/// Tags and links may follow the path.
+///
+/// # Example
+/// ```
+/// # use beancount_parser::{BeancountFile, DirectiveContent};
+/// let input = r#"2023-04-01 document Assets:Cash "/home/user/statements/april 2023.pdf""#;
+/// let beancount: BeancountFile<f64> = input.parse().unwrap();
+/// let DirectiveContent::Document(ref document) = beancount.directives[0].content else { unreachable!() };
+/// assert_eq!(document.account.as_str(), "Assets:Cash");
+/// assert_eq!(document.path, "/home/user/statements/april 2023.pdf");
+/// ```
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Document {
+    /// Account of the document
+    pub account: Account,
+    /// Path of the document, as written in the file (relative or absolute)
+    pub path: String,
+    /// Tags following the path
+    pub tags: HashSet<Tag>,
+    /// Links following the path
+    pub links: HashSet<Link>,
+}
+
+pub(super) fn parse(input: Span<'_>) -> IResult<'_, Document> {
+    let (input, account) = account::parse(input)?;
+    let (input, _) = space1(input)?;
+    let (input, path) = string(input)?;
+    let (input, (tags, links)) = transaction::tags_and_links(input)?;
+    Ok((
+        input,
+        Document {
+            account,
+            path,
+            tags,
+            links,
+        },
+    ))
+}
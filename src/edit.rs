@@ -0,0 +1,615 @@
+use std::{
+    collections::HashSet,
+    fmt::{Display, Formatter, Result},
+    ops::Range,
+};
+
+use crate::{
+    metadata,
+    syntax::{AmountText, CostText, CustomValueText, MetadataValueText, TagsAndLinks},
+    BeancountFile, Directive, DirectiveContent, DirectiveId, Posting, PostingPrice, Quoted, Tag,
+};
+
+/// Replacement of a range of text, to apply a change of the model to the source it was parsed from
+///
+/// It converts directly to an LSP `TextEdit` (the offsets are in bytes).
+/// See [`BeancountFile::edit`] for an example.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TextEdit {
+    /// Byte range of the source to replace
+    pub range: Range<usize>,
+    /// Text replacing the range
+    pub replacement: String,
+}
+
+impl TextEdit {
+    /// Apply the edit to the text
+    ///
+    /// To apply several edits (like the ones of [`BeancountFile::edit_batch`]),
+    /// apply them from the last one to the first, so that the ranges remain valid.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is not in the text, or not on character boundaries.
+    pub fn apply(&self, text: &mut String) {
+        text.replace_range(self.range.clone(), &self.replacement);
+    }
+}
+
+pub(crate) fn edit<D: Display>(
+    file: &mut BeancountFile<D>,
+    source: &str,
+    ids: impl IntoIterator<Item = DirectiveId>,
+    mut f: impl FnMut(&mut Directive<D>),
+) -> Vec<TextEdit> {
+    let mut indices: Vec<usize> = ids.into_iter().map(DirectiveId::index).collect();
+    indices.sort_unstable();
+    indices.dedup();
+    // Line number, and number of lines added (or removed) by each edit
+    let mut shifts: Vec<(u32, i64)> = Vec::new();
+    let mut edits = Vec::new();
+    for index in indices {
+        let Some(directive) = file.directives.get_mut(index) else {
+            continue;
+        };
+        let line_number = directive.line_number;
+        let Some(range) = lines_of(source, line_number) else {
+            continue;
+        };
+        let original = &source[range.clone()];
+        let lines: Vec<&str> = original.lines().collect();
+        let inherited = Inherited::new(directive, &lines);
+        let before = DirectiveLines::new(directive, &inherited);
+        f(directive);
+        let after = DirectiveLines::new(directive, &inherited);
+        let newline = if original.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        };
+        let indent = indent_of(directive, original);
+        let (replacement, posting_lines) = rewrite(&lines, line_number, &before, &after, &indent);
+        if let DirectiveContent::Transaction(trx) = &mut directive.content {
+            for (posting, line_number) in trx.postings.iter_mut().zip(posting_lines) {
+                posting.line_number = line_number;
+            }
+        }
+        if replacement
+            .iter()
+            .map(String::as_str)
+            .eq(lines.iter().copied())
+        {
+            continue;
+        }
+        let delta = line_count(replacement.len()) - line_count(lines.len());
+        shifts.push((line_number, delta));
+        edits.push(TextEdit {
+            range,
+            replacement: replacement.join(newline),
+        });
+    }
+    for directive in &mut file.directives {
+        if directive.line_number == 0 {
+            continue;
+        }
+        let shift: i64 = shifts
+            .iter()
+            .filter(|(line_number, _)| *line_number < directive.line_number)
+            .map(|(_, delta)| delta)
+            .sum();
+        directive.line_number = shift_line(directive.line_number, shift);
+        if let DirectiveContent::Transaction(trx) = &mut directive.content {
+            for posting in &mut trx.postings {
+                posting.line_number = shift_line(posting.line_number, shift);
+            }
+        }
+    }
+    let total = shifts.iter().map(|(_, delta)| delta).sum();
+    file.state.lines = shift_line(file.state.lines, total);
+    edits.sort_by_key(|edit| edit.range.start);
+    edits
+}
+
+/// Write the lines of a changed directive, keeping the original text of the lines the change did not touch
+///
+/// `lines` are the original lines of the directive (starting at `first_line`),
+/// `before` and `after` the directive written before and after the change.
+/// The postings are written in their new order, each followed by the original lines following it
+/// (its metadata and comments, up to the next posting). A removed posting is removed with these lines.
+///
+/// Returns the lines, and the line number of every posting.
+fn rewrite(
+    lines: &[&str],
+    first_line: u32,
+    before: &DirectiveLines,
+    after: &DirectiveLines,
+    indent: &str,
+) -> (Vec<String>, Vec<u32>) {
+    let starts: Vec<Option<usize>> = before
+        .postings
+        .iter()
+        .map(|posting| {
+            let offset = usize::try_from(posting.line_number.checked_sub(first_line)?).ok()?;
+            (1..lines.len()).contains(&offset).then_some(offset)
+        })
+        .collect();
+    let mut sorted_starts: Vec<usize> = starts.iter().flatten().copied().collect();
+    sorted_starts.sort_unstable();
+    let block = |start: usize| {
+        let end = sorted_starts
+            .iter()
+            .find(|next| **next > start)
+            .copied()
+            .unwrap_or(lines.len());
+        start..end
+    };
+    let posting_metadata_indent = before
+        .postings
+        .iter()
+        .zip(&starts)
+        .find_map(|(posting, start)| {
+            let range = block((*start)?);
+            lines[range.start + 1..range.end]
+                .iter()
+                .find(|line| metadata_key(line, &posting.metadata).is_some())
+                .map(|line| indentation(line).to_owned())
+        })
+        .unwrap_or_else(|| format!("{indent}{indent}"));
+
+    let mut out = vec![changed_line(lines[0], &before.header, &after.header)];
+    let head_end = sorted_starts.first().copied().unwrap_or(lines.len());
+    section(
+        &mut out,
+        &lines[1..head_end],
+        &before.metadata,
+        &after.metadata,
+        indent,
+    );
+    let mut used = vec![false; before.postings.len()];
+    let mut posting_lines = Vec::with_capacity(after.postings.len());
+    for posting in &after.postings {
+        posting_lines.push(first_line.saturating_add(u32::try_from(out.len()).unwrap_or(u32::MAX)));
+        let original = (0..before.postings.len()).find(|&i| {
+            !used[i] && starts[i].is_some() && before.postings[i].line_number == posting.line_number
+        });
+        if let Some(i) = original {
+            used[i] = true;
+            let old = &before.postings[i];
+            let range = block(starts[i].unwrap_or_default());
+            out.push(changed_posting_line(lines[range.start], old, posting));
+            section(
+                &mut out,
+                &lines[range.start + 1..range.end],
+                &old.metadata,
+                &posting.metadata,
+                &posting_metadata_indent,
+            );
+        } else {
+            out.push(format!("{indent}{}", posting.text(None)));
+            section(
+                &mut out,
+                &[],
+                &[],
+                &posting.metadata,
+                &posting_metadata_indent,
+            );
+        }
+    }
+    (out, posting_lines)
+}
+
+/// Write the lines following a directive (or a posting) line
+///
+/// The metadata lines are kept, rewritten or removed (following the change of their entry),
+/// the other lines (like comments) are kept as they are,
+/// and the added entries are written after the last metadata line, with its indentation.
+fn section<'a>(
+    out: &mut Vec<String>,
+    lines: &[&'a str],
+    before: &[Entry],
+    after: &[Entry],
+    mut indent: &'a str,
+) {
+    let mut written = vec![false; after.len()];
+    let mut seen: Vec<&str> = Vec::new();
+    let mut insert_at = out.len();
+    for line in lines {
+        let Some(key) = metadata_key(line, before) else {
+            out.push((*line).to_owned());
+            continue;
+        };
+        // The n-th line of a key is the n-th entry of that key (the following ones being the duplicates)
+        let nth = seen.iter().filter(|seen| **seen == key).count();
+        seen.push(key);
+        let old = before.iter().filter(|entry| entry.key == key).nth(nth);
+        let new = after
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.key == key)
+            .nth(nth);
+        if let (Some(old), Some((i, new))) = (old, new) {
+            written[i] = true;
+            out.push(changed_line(line, &old.text, &new.text));
+        }
+        insert_at = out.len();
+        indent = indentation(line);
+    }
+    let added = after
+        .iter()
+        .zip(written)
+        .filter(|(_, written)| !written)
+        .map(|(entry, _)| format!("{indent}{}", entry.text));
+    out.splice(insert_at..insert_at, added);
+}
+
+/// Key of a metadata line, if it is one of the `entries`
+fn metadata_key<'a>(line: &'a str, entries: &[Entry]) -> Option<&'a str> {
+    let (key, _) = line.trim_start().split_once(':')?;
+    entries.iter().any(|entry| entry.key == key).then_some(key)
+}
+
+/// The original line if its text did not change, or else the new text with the indentation and comment of the line
+fn changed_line(line: &str, old: &str, new: &str) -> String {
+    if old == new {
+        line.to_owned()
+    } else {
+        format!("{}{new}{}", indentation(line), comment_of(line))
+    }
+}
+
+/// Same as [`changed_line`] for a posting, keeping the column of the amount
+fn changed_posting_line(line: &str, old: &PostingLine, new: &PostingLine) -> String {
+    if old.head == new.head && old.tail == new.tail {
+        return line.to_owned();
+    }
+    let indent = indentation(line);
+    let comment = comment_of(line);
+    let column = line[indent.len()..line.len() - comment.len()]
+        .strip_prefix(old.head.as_str())
+        .filter(|_| !old.tail.is_empty())
+        .map(|rest| old.head.chars().count() + rest.len() - rest.trim_start().len());
+    format!("{indent}{}{comment}", new.text(column))
+}
+
+/// Whitespace at the start of the line
+fn indentation(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// Comment at the end of the line (with the whitespace before it), or an empty string
+fn comment_of(line: &str) -> &str {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ';' if !quoted => return &line[line[..i].trim_end().len()..],
+            _ => (),
+        }
+    }
+    ""
+}
+
+fn shift_line(line_number: u32, shift: i64) -> u32 {
+    u32::try_from(i64::from(line_number) + shift).unwrap_or(0)
+}
+
+fn line_count(lines: usize) -> i64 {
+    i64::try_from(lines).unwrap_or(i64::MAX)
+}
+
+/// Indentation of the postings of a transaction, or else of the first indented line
+fn indent_of<D>(directive: &Directive<D>, original: &str) -> String {
+    let posting_indent = match &directive.content {
+        DirectiveContent::Transaction(trx) => trx.indent.map(|indent| indent.to_string()),
+        _ => None,
+    };
+    posting_indent.unwrap_or_else(|| {
+        original
+            .lines()
+            .skip(1)
+            .map(indentation)
+            .find(|indent| !indent.is_empty())
+            .unwrap_or("  ")
+            .to_owned()
+    })
+}
+
+/// Byte range of the lines of the directive starting at `line_number`
+///
+/// That is the line of the date and the indented lines following it,
+/// without the comments and blank lines at the end (they are not part of the directive).
+fn lines_of(source: &str, line_number: u32) -> Option<Range<usize>> {
+    let mut lines = source.split_inclusive('\n');
+    let start: usize = lines
+        .by_ref()
+        .take(line_number.checked_sub(1)? as usize)
+        .map(str::len)
+        .sum();
+    let first = lines.next()?;
+    let mut end = start + first.trim_end_matches(['\r', '\n']).len();
+    let mut offset = start + first.len();
+    for line in lines {
+        if !line.starts_with([' ', '\t']) {
+            break;
+        }
+        let content = line.trim();
+        if !content.is_empty() && !content.starts_with(';') {
+            end = offset + line.trim_end_matches(['\r', '\n']).len();
+        }
+        offset += line.len();
+    }
+    Some(start..end)
+}
+
+/// Lines of a directive written in the beancount syntax, to find the ones changed by an edit
+struct DirectiveLines {
+    header: String,
+    metadata: Vec<Entry>,
+    postings: Vec<PostingLine>,
+}
+
+impl DirectiveLines {
+    fn new<D: Display>(directive: &Directive<D>, inherited: &Inherited) -> Self {
+        let postings = match &directive.content {
+            DirectiveContent::Transaction(trx) => {
+                trx.postings.iter().map(PostingLine::new).collect()
+            }
+            _ => Vec::new(),
+        };
+        Self {
+            header: HeaderText {
+                directive,
+                hidden_tags: &inherited.tags,
+            }
+            .to_string(),
+            metadata: entries(&directive.metadata, &directive.metadata_duplicates)
+                .into_iter()
+                .filter(|entry| !inherited.metadata.contains(entry))
+                .collect(),
+            postings,
+        }
+    }
+}
+
+/// Tags and metadata of a directive that are not written in its lines (they are added by `pushtag` and `pushmeta`)
+///
+/// They are left out of the lines of the directive, unless they are changed.
+struct Inherited {
+    tags: HashSet<Tag>,
+    metadata: Vec<Entry>,
+}
+
+impl Inherited {
+    fn new<D: Display>(directive: &Directive<D>, lines: &[&str]) -> Self {
+        let mut head_end = lines.len();
+        let mut tags = HashSet::new();
+        if let DirectiveContent::Transaction(trx) = &directive.content {
+            let written = written_tags(lines[0]);
+            tags = trx
+                .tags
+                .iter()
+                .filter(|tag| !written.contains(tag.as_str()))
+                .cloned()
+                .collect();
+            head_end = trx
+                .postings
+                .iter()
+                .filter_map(|posting| posting.line_number.checked_sub(directive.line_number))
+                .filter_map(|offset| usize::try_from(offset).ok())
+                .min()
+                .map_or(head_end, |offset| offset.clamp(1, head_end));
+        }
+        let metadata_lines = &lines[1..head_end];
+        let metadata = directive
+            .metadata
+            .iter()
+            .filter(|(key, _)| {
+                !metadata_lines.iter().any(|line| {
+                    line.trim_start()
+                        .split_once(':')
+                        .is_some_and(|(written, _)| written == key.as_ref())
+                })
+            })
+            .map(|(key, value)| Entry::new(key, value))
+            .collect();
+        Self { tags, metadata }
+    }
+}
+
+/// Tags written in a header line (outside of its strings and comment)
+fn written_tags(header: &str) -> HashSet<String> {
+    let mut unquoted = String::new();
+    let mut quoted = false;
+    let mut escaped = false;
+    for c in header[..header.len() - comment_of(header).len()].chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => {
+                quoted = !quoted;
+                unquoted.push(' ');
+            }
+            _ if quoted => (),
+            c => unquoted.push(c),
+        }
+    }
+    unquoted
+        .split_whitespace()
+        .filter_map(|word| word.strip_prefix('#'))
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+/// Metadata entry written as `key: value`
+#[derive(PartialEq)]
+struct Entry {
+    key: String,
+    text: String,
+}
+
+impl Entry {
+    fn new<D: Display>(key: &metadata::Key, value: &metadata::Value<D>) -> Self {
+        Self {
+            key: key.to_string(),
+            text: format!("{key}: {}", MetadataValueText(value)),
+        }
+    }
+}
+
+/// Metadata entries sorted by key, followed by the duplicate keys
+fn entries<D: Display>(map: &metadata::Map<D>, duplicates: &metadata::Duplicates<D>) -> Vec<Entry> {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_by(|(a, _), (b, _)| a.as_ref().cmp(b.as_ref()));
+    let duplicates = duplicates
+        .iter()
+        .map(|duplicate| (&duplicate.key, &duplicate.value));
+    entries
+        .into_iter()
+        .chain(duplicates)
+        .map(|(key, value)| Entry::new(key, value))
+        .collect()
+}
+
+struct PostingLine {
+    line_number: u32,
+    /// Flag and account
+    head: String,
+    /// Amount, cost and price (empty if there are none)
+    tail: String,
+    metadata: Vec<Entry>,
+}
+
+impl PostingLine {
+    fn new<D: Display>(posting: &Posting<D>) -> Self {
+        let mut head = String::new();
+        if let Some(flag) = posting.flag {
+            head = format!("{flag} ");
+        }
+        head.push_str(posting.account.as_str());
+        let mut tail = Vec::new();
+        if let Some(amount) = &posting.amount {
+            tail.push(AmountText(amount).to_string());
+        }
+        if let Some(cost) = &posting.cost {
+            tail.push(CostText(cost).to_string());
+        }
+        match &posting.price {
+            Some(PostingPrice::Unit(price)) => tail.push(format!("@ {}", AmountText(price))),
+            Some(PostingPrice::Total(price)) => tail.push(format!("@@ {}", AmountText(price))),
+            None => (),
+        }
+        Self {
+            line_number: posting.line_number,
+            head,
+            tail: tail.join(" "),
+            metadata: entries(&posting.metadata, &posting.metadata_duplicates),
+        }
+    }
+
+    /// Text of the line, starting the amount at `column` (in characters) if the account is short enough
+    fn text(&self, column: Option<usize>) -> String {
+        if self.tail.is_empty() {
+            return self.head.clone();
+        }
+        let spaces = column
+            .map_or(0, |column| column.saturating_sub(self.head.chars().count()))
+            .max(2);
+        format!("{}{:spaces$}{}", self.head, "", self.tail)
+    }
+}
+
+/// Display adapter writing the first line of a [`Directive`] in the beancount syntax, without the hidden tags
+struct HeaderText<'a, D> {
+    directive: &'a Directive<D>,
+    hidden_tags: &'a HashSet<Tag>,
+}
+
+impl<D: Display> Display for HeaderText<'_, D> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let directive = self.directive;
+        let date = directive.date;
+        match &directive.content {
+            DirectiveContent::Transaction(trx) => {
+                write!(f, "{}", trx.header(date).without_tags(self.hidden_tags))
+            }
+            DirectiveContent::Price(price) => write!(
+                f,
+                "{date} price {} {}",
+                price.currency,
+                AmountText(&price.amount)
+            ),
+            DirectiveContent::Balance(balance) => {
+                write!(
+                    f,
+                    "{date} balance {} {}",
+                    balance.account, balance.amount.value
+                )?;
+                if let Some(tolerance) = &balance.tolerance {
+                    write!(f, " ~ {tolerance}")?;
+                }
+                write!(f, " {}", balance.amount.currency)
+            }
+            DirectiveContent::Open(open) => {
+                write!(f, "{date} open {}", open.account)?;
+                let mut currencies: Vec<_> = open.currencies.iter().collect();
+                currencies.sort();
+                for (i, currency) in currencies.into_iter().enumerate() {
+                    let separator = if i == 0 { ' ' } else { ',' };
+                    write!(f, "{separator}{currency}")?;
+                }
+                if let Some(booking_method) = &open.booking_method {
+                    write!(f, " {}", Quoted(booking_method.as_ref()))?;
+                }
+                Ok(())
+            }
+            DirectiveContent::Close(close) => write!(f, "{date} close {}", close.account),
+            DirectiveContent::Pad(pad) => {
+                write!(f, "{date} pad {} {}", pad.account, pad.source_account)
+            }
+            DirectiveContent::Commodity(currency) => write!(f, "{date} commodity {currency}"),
+            DirectiveContent::Event(event) => write!(
+                f,
+                "{date} event {} {}",
+                Quoted(&event.name),
+                Quoted(&event.value)
+            ),
+            DirectiveContent::Note(note) => write!(
+                f,
+                "{date} note {} {}{}",
+                note.account,
+                Quoted(&note.description),
+                TagsAndLinks {
+                    tags: &note.tags,
+                    links: &note.links,
+                }
+            ),
+            DirectiveContent::Document(document) => write!(
+                f,
+                "{date} document {} {}{}",
+                document.account,
+                Quoted(&document.path),
+                TagsAndLinks {
+                    tags: &document.tags,
+                    links: &document.links,
+                }
+            ),
+            DirectiveContent::Query(query) => write!(
+                f,
+                "{date} query {} {}",
+                Quoted(&query.name),
+                Quoted(&query.query_string)
+            ),
+            DirectiveContent::Custom(custom) => {
+                write!(f, "{date} custom {}", Quoted(&custom.name))?;
+                for value in &custom.values {
+                    write!(f, " {}", CustomValueText(value))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
@@ -1,26 +1,71 @@
 #![allow(clippy::module_name_repetitions)]
 
-use std::fmt::Debug;
+use std::{
+    fmt::{Debug, Display, Formatter},
+    sync::Arc,
+};
 
 #[cfg(feature = "miette")]
 use miette::{Diagnostic, SourceSpan};
 use thiserror::Error;
 
-use crate::Span;
+use crate::{amount, Code, FileId, Provenance, Span};
 
 /// Error returned in case of invalid beancount syntax found
 ///
 /// # Example
 /// ```
 /// # use beancount_parser::BeancountFile;
-/// let result: Result<BeancountFile<f64>, beancount_parser::Error> = "2022-05-21 oops".parse();
+/// let result: Result<BeancountFile<f64>, beancount_parser::Error> = "2022-05-21 open oops".parse();
 /// assert!(result.is_err());
 /// let error = result.unwrap_err();
 /// assert_eq!(error.line_number(), 1);
+/// assert_eq!(error.column(), Some(17));
 /// ```
-#[derive(Debug, Clone, Error)]
+///
+/// The error owns its data (the position, and the [snippet](Self::snippet) of the line): it does not borrow the input,
+/// and can be returned after the input is dropped.
+/// It implements [`std::error::Error`], so that it can be propagated with `?` as a `Box<dyn Error>`
+/// (or an `anyhow::Error`):
+///
+/// ```
+/// # use beancount_parser::BeancountFile;
+/// fn count_directives(input: &str) -> Result<usize, Box<dyn std::error::Error>> {
+///     let beancount: BeancountFile<f64> = input.parse()?;
+///     Ok(beancount.directives.len())
+/// }
+/// let error = count_directives("2022-05-21 oops").unwrap_err();
+/// assert_eq!(error.to_string(), "error[E001]: Invalid beancount syntax at line: 1");
+/// ```
+///
+/// # Debug output
+///
+/// The `Debug` output shows where the error is and the line of the input it is on (the [snippet](Self::snippet)),
+/// but never the rest of the input. The snippet is truncated to 120 characters,
+/// or to the precision of the format (`{error:.40?}` shows at most 40 characters).
+/// Use [`Error::redacted`] to keep the content of the input out of the logs.
+///
+/// # Display output
+///
+/// The `Display` output is a single line with the code, the line number and the hint (if any).
+/// The alternate form (`{error:#}`) adds the line of the input, with a caret under the column of the error,
+/// unless the error is [redacted](Error::redacted).
+///
+/// ```
+/// # use beancount_parser::BeancountFile;
+/// let input = "2023-05-20 open Assets:Cash\n2023-05-21 open oops";
+/// let error = input.parse::<BeancountFile<f64>>().unwrap_err();
+/// assert_eq!(error.to_string(), "error[E001]: Invalid beancount syntax at line: 2");
+/// assert_eq!(
+///     format!("{error:#}"),
+///     "error[E001]: Invalid beancount syntax at line: 2
+///   |
+/// 2 | 2023-05-21 open oops
+///   |                 ^"
+/// );
+/// ```
+#[derive(Clone, Error)]
 #[cfg_attr(feature = "miette", derive(Diagnostic))]
-#[error("Invalid beancount syntax at line: {line_number}")]
 pub struct Error {
     #[cfg(feature = "miette")]
     #[source_code]
@@ -28,31 +73,280 @@ pub struct Error {
     #[cfg(feature = "miette")]
     #[label]
     span: SourceSpan,
+    file_id: FileId,
     line_number: u32,
+    column: Option<u32>,
+    code: Code,
+    hint: Option<String>,
+    provenance: Option<Arc<Provenance>>,
+    snippet: Option<Snippet>,
+}
+
+/// Line of the input on which an error is
+#[derive(Clone)]
+enum Snippet {
+    Text(String),
+    /// Number of characters of a redacted line
+    Redacted(usize),
 }
 
+/// Number of characters of the snippet shown by the `Debug` output of an [`Error`], unless a precision is given
+const SNIPPET_LEN: usize = 120;
+
 impl Error {
-    #[cfg(not(feature = "miette"))]
-    pub(crate) fn new(_: impl Into<String>, span: Span<'_>) -> Self {
-        Self {
-            line_number: span.location_line(),
+    pub(crate) fn new(src: &str, span: Span<'_>) -> Self {
+        Self::at(
+            src,
+            span.location_offset(),
+            span.location_line(),
+            Code::InvalidSyntax,
+        )
+    }
+
+    /// Convert an error of the parser, using the code of the failures of the number literals
+    pub(crate) fn from_nom(src: &str, error: &nom::error::Error<Span<'_>>) -> Self {
+        let span = error.input;
+        let code = match error.code {
+            amount::SCIENTIFIC_NOTATION => Code::ScientificNotation,
+            amount::EXCESS_PRECISION => Code::ExcessPrecision,
+            amount::UNSUPPORTED_EXPRESSION => {
+                return Self::new(src, span)
+                    .with_hint("the number type does not support expressions, write the result");
+            }
+            _ => return Self::new(src, span),
+        };
+        let error = Self::at(src, span.location_offset(), span.location_line(), code);
+        match src
+            .get(span.location_offset()..)
+            .and_then(amount::plain_decimal)
+        {
+            Some(plain) if code == Code::ScientificNotation => {
+                error.with_hint(format!("write `{plain}`"))
+            }
+            _ => error,
         }
     }
 
-    #[cfg(feature = "miette")]
-    pub(crate) fn new(src: impl Into<String>, span: Span<'_>) -> Self {
+    pub(crate) fn at(src: &str, offset: usize, line_number: u32, code: Code) -> Self {
         Self {
+            #[cfg(feature = "miette")]
             src: src.into(),
-            span: span.location_offset().into(),
-            line_number: span.location_line(),
+            #[cfg(feature = "miette")]
+            span: offset.into(),
+            file_id: FileId::MAIN,
+            line_number,
+            column: column(src, offset),
+            code,
+            hint: None,
+            provenance: None,
+            snippet: line_at(src, offset).map(|line| Snippet::Text(line.into())),
         }
     }
 
+    /// Attribute the error to another file
+    pub(crate) fn in_file(mut self, file_id: FileId) -> Self {
+        self.file_id = file_id;
+        self
+    }
+
+    /// Add `lines` to the line number, for an error found in a part of a larger input
+    pub(crate) fn shift_lines(mut self, lines: u32) -> Self {
+        self.line_number += lines;
+        self
+    }
+
+    /// Add a hint on how to fix the error, displayed after the message
+    pub(crate) fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    /// Attribute the error to a directive built by a program, reported instead of the line number
+    pub(crate) fn with_provenance(mut self, provenance: Option<Arc<Provenance>>) -> Self {
+        self.provenance = provenance;
+        self
+    }
+
+    /// Code identifying the kind of error
+    ///
+    /// See [`Code`] for the list of codes.
+    #[must_use]
+    pub fn code(&self) -> Code {
+        self.code
+    }
+
     /// Line number at which the error was found in the input
     #[must_use]
     pub fn line_number(&self) -> u32 {
         self.line_number
     }
+
+    /// Column (in characters, starting at 1) at which the error was found in the input, if known
+    #[must_use]
+    pub fn column(&self) -> Option<u32> {
+        self.column
+    }
+
+    /// File in which the error was found
+    ///
+    /// See [`SourceMap`](crate::SourceMap) to get its path.
+    #[must_use]
+    pub fn file_id(&self) -> FileId {
+        self.file_id
+    }
+
+    /// Hint on how to fix the error, if any (displayed after the message)
+    #[must_use]
+    pub fn hint(&self) -> Option<&str> {
+        self.hint.as_deref()
+    }
+
+    /// Line of the input on which the error was found, if known and the error is not [redacted](Self::redacted)
+    ///
+    /// # Example
+    /// ```
+    /// # use beancount_parser::BeancountFile;
+    /// let input = "2023-05-20 open Assets:Cash\n2023-05-21 open oops\n2023-05-22 close Assets:Cash";
+    /// let error = input.parse::<BeancountFile<f64>>().unwrap_err();
+    /// assert_eq!(error.snippet(), Some("2023-05-21 open oops"));
+    /// assert_eq!(error.clone().redacted().snippet(), None);
+    /// ```
+    #[must_use]
+    pub fn snippet(&self) -> Option<&str> {
+        match &self.snippet {
+            Some(Snippet::Text(text)) => Some(text),
+            Some(Snippet::Redacted(_)) | None => None,
+        }
+    }
+
+    /// Remove the content of the input from the error, for the deployments where it must not be logged
+    ///
+    /// The `Debug` output shows the length of the [snippet](Self::snippet) instead of its text.
+    /// With the `miette` feature, the source code is removed as well, so the reports have no code excerpt.
+    ///
+    /// # Example
+    /// ```
+    /// # use beancount_parser::BeancountFile;
+    /// let error = "2023-05-21 oops".parse::<BeancountFile<f64>>().unwrap_err().redacted();
+    /// let debug = format!("{error:?}");
+    /// assert!(!debug.contains("oops"));
+    /// assert!(debug.contains("<redacted: 15 chars>"));
+    /// ```
+    #[must_use]
+    pub fn redacted(mut self) -> Self {
+        if let Some(Snippet::Text(text)) = &self.snippet {
+            self.snippet = Some(Snippet::Redacted(text.chars().count()));
+        }
+        #[cfg(feature = "miette")]
+        {
+            self.src = String::new();
+            self.span = 0.into();
+        }
+        self
+    }
+
+    /// Origin of the directive the error is about, if it was built by a program
+    ///
+    /// See [`Directive::with_provenance`](crate::Directive::with_provenance)
+    #[must_use]
+    pub fn provenance(&self) -> Option<&Provenance> {
+        self.provenance.as_deref()
+    }
+}
+
+impl Debug for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let len = f.precision().unwrap_or(SNIPPET_LEN);
+        f.debug_struct("Error")
+            .field("code", &self.code)
+            .field("file_id", &self.file_id)
+            .field("line_number", &self.line_number)
+            .field("column", &self.column)
+            .field("hint", &self.hint)
+            .field("provenance", &self.provenance)
+            .field(
+                "snippet",
+                &self
+                    .snippet
+                    .as_ref()
+                    .map(|snippet| SnippetDebug(snippet, len)),
+            )
+            .finish_non_exhaustive()
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "error[{}]: {} ", self.code, self.code.summary())?;
+        match &self.provenance {
+            Some(provenance) => write!(f, "in {provenance}")?,
+            None => write!(f, "at line: {}", self.line_number)?,
+        }
+        if let Some(hint) = &self.hint {
+            write!(f, " ({hint})")?;
+        }
+        match &self.snippet {
+            Some(Snippet::Text(line)) if f.alternate() && self.provenance.is_none() => {
+                self.fmt_excerpt(f, line)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Error {
+    /// Write the line of the error below the message, with a caret under the column
+    fn fmt_excerpt(&self, f: &mut Formatter<'_>, line: &str) -> std::fmt::Result {
+        let number = self.line_number.to_string();
+        let gutter = " ".repeat(number.len());
+        write!(f, "\n{gutter} |\n{number} | {line}")?;
+        if let Some(column) = self.column {
+            // Tabs are kept so that the caret is aligned whatever their width
+            let padding: String = line
+                .chars()
+                .take(column.saturating_sub(1) as usize)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            write!(f, "\n{gutter} | {padding}^")?;
+        }
+        Ok(())
+    }
+}
+
+/// Debug adapter of a snippet, truncated to a number of characters
+struct SnippetDebug<'a>(&'a Snippet, usize);
+
+impl Debug for SnippetDebug<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Snippet::Text(text) => match text.char_indices().nth(self.1) {
+                Some((end, _)) => write!(f, "{:?}", format!("{}…", &text[..end])),
+                None => write!(f, "{text:?}"),
+            },
+            Snippet::Redacted(len) => write!(f, "<redacted: {len} chars>"),
+        }
+    }
+}
+
+/// Line of the source containing the offset, if the offset is in the source
+fn line_at(src: &str, offset: usize) -> Option<&str> {
+    if src.is_empty() {
+        return None;
+    }
+    let start = src.get(..offset)?.rfind('\n').map_or(0, |index| index + 1);
+    let end = src[offset..]
+        .find('\n')
+        .map_or(src.len(), |index| offset + index);
+    Some(src[start..end].trim_end_matches('\r'))
+}
+
+/// Column (in characters, starting at 1) of the offset in the source, if the offset is in the source
+fn column(src: &str, offset: usize) -> Option<u32> {
+    if src.is_empty() {
+        return None;
+    }
+    let line = src.get(..offset)?.rsplit('\n').next()?;
+    u32::try_from(line.chars().count() + 1).ok()
 }
 
 /// Error returned when reading a beancount file from disk
@@ -66,6 +360,17 @@ pub enum ReadFileError {
     Syntax(#[from] Error),
 }
 
+/// Error returned by [`Transaction::merge`](crate::Transaction::merge) when the transactions cannot be merged
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum MergeError {
+    /// The transactions have different payees (the payee of the first transaction, then of the second one)
+    ///
+    /// Use [`MergePolicy::force`](crate::MergePolicy::force) to keep the payee of the first transaction instead.
+    #[error("Cannot merge transactions of different payees ({0:?} and {1:?})")]
+    ConflictingPayees(String, String),
+}
+
 /// Error that may be returned by the various `TryFrom`/`TryInto` implementation
 /// to signify that the value cannot be converted to the desired type
 #[derive(Debug, Clone, Error)]
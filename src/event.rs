@@ -13,6 +13,11 @@ use crate::{string, IResult, Span};
 /// assert_eq!(event.name, "Location");
 /// assert_eq!(event.value, "Switzerland");
 /// ```
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
 #[derive(Debug, Clone, PartialEq)]
 #[non_exhaustive]
 pub struct Event {
@@ -0,0 +1,143 @@
+//! Interpretation of the [`Custom`] directives defined by [fava](https://beancount.github.io/fava/)
+//!
+//! The parser keeps `custom` directives generic. The functions of this module are opt-in,
+//! and give a typed view of the directives following the fava conventions.
+
+use std::fmt::{Display, Formatter};
+
+use crate::{
+    Account, Amount, BeancountFile, ConversionError, Custom, CustomValue, Date, DirectiveContent,
+    Warning, WarningKind,
+};
+
+/// A budget (`custom "budget" <account> <interval> <amount>`)
+///
+/// The budget applies to the account, starting at the date, for each period of the interval.
+///
+/// See [`budgets`]
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Budget<D> {
+    /// Date from which the budget applies
+    pub date: Date,
+    /// Account the budget applies to
+    pub account: Account,
+    /// Period of the budget
+    pub interval: Interval,
+    /// Amount budgeted for each period
+    pub amount: Amount<D>,
+}
+
+/// Period of a [`Budget`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Interval {
+    /// `daily`
+    Daily,
+    /// `weekly`
+    Weekly,
+    /// `monthly`
+    Monthly,
+    /// `quarterly`
+    Quarterly,
+    /// `yearly`
+    Yearly,
+}
+
+impl Interval {
+    /// Returns the name of the interval, as written in the budget directive (like `monthly`)
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Daily => "daily",
+            Self::Weekly => "weekly",
+            Self::Monthly => "monthly",
+            Self::Quarterly => "quarterly",
+            Self::Yearly => "yearly",
+        }
+    }
+}
+
+impl Display for Interval {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl<'a> TryFrom<&'a str> for Interval {
+    type Error = ConversionError;
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        match value {
+            "daily" => Ok(Self::Daily),
+            "weekly" => Ok(Self::Weekly),
+            "monthly" => Ok(Self::Monthly),
+            "quarterly" => Ok(Self::Quarterly),
+            "yearly" => Ok(Self::Yearly),
+            _ => Err(ConversionError),
+        }
+    }
+}
+
+/// Returns the budgets declared in the file, and the warnings for the malformed budget directives
+///
+/// Every `custom "budget"` directive is expected to be followed by an account,
+/// an interval (see [`Interval`]) and an amount. The directives that don't follow
+/// this convention are skipped with a [`WarningKind::MalformedBudget`] warning.
+///
+/// # Example
+/// ```
+/// # use beancount_parser::{BeancountFile, fava::{self, Interval}};
+/// let input = r#"
+/// 2023-01-01 custom "budget" Expenses:Food "monthly" 400 CHF
+/// 2023-01-01 custom "budget" Expenses:Food "sometimes" 400 CHF
+/// "#;
+/// let beancount: BeancountFile<f64> = input.parse().unwrap();
+/// let (budgets, warnings) = fava::budgets(&beancount);
+/// assert_eq!(budgets.len(), 1);
+/// assert_eq!(budgets[0].account.as_str(), "Expenses:Food");
+/// assert_eq!(budgets[0].interval, Interval::Monthly);
+/// assert_eq!(budgets[0].amount.value, 400.0);
+/// assert_eq!(warnings[0].line_number(), 3);
+/// ```
+#[must_use]
+pub fn budgets<D: Clone>(file: &BeancountFile<D>) -> (Vec<Budget<D>>, Vec<Warning>) {
+    let mut budgets = Vec::new();
+    let mut warnings = Vec::new();
+    for directive in &file.directives {
+        let DirectiveContent::Custom(custom) = &directive.content else {
+            continue;
+        };
+        if custom.name != "budget" {
+            continue;
+        }
+        match budget(directive.date, custom) {
+            Ok(budget) => budgets.push(budget),
+            Err(reason) => warnings.push(Warning::new(
+                WarningKind::MalformedBudget(reason),
+                directive.line_number,
+            )),
+        }
+    }
+    (budgets, warnings)
+}
+
+fn budget<D: Clone>(date: Date, custom: &Custom<D>) -> Result<Budget<D>, String> {
+    let mut values = custom.values.iter();
+    let Some(CustomValue::Account(account)) = values.next() else {
+        return Err("expected an account".into());
+    };
+    let Some(CustomValue::String(interval)) = values.next() else {
+        return Err("expected an interval".into());
+    };
+    let interval = Interval::try_from(interval.as_str())
+        .map_err(|_| format!("unknown interval `{interval}`"))?;
+    let Some(CustomValue::Amount(amount)) = values.next() else {
+        return Err("expected an amount".into());
+    };
+    Ok(Budget {
+        date,
+        account: account.clone(),
+        interval,
+        amount: amount.clone(),
+    })
+}
@@ -0,0 +1,524 @@
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+    mem::size_of,
+    path::PathBuf,
+};
+
+use crate::transaction::{MisplacedTag, StrayToken, UnsupportedCost};
+use crate::{
+    account::BookingMethod, metadata, Account, Amount, Balance, BeanOption, BeancountFile, Close,
+    Cost, Currency, Custom, CustomValue, Directive, DirectiveContent, Document, Event, Link, Note,
+    Open, Pad, Plugin, Posting, PostingPrice, Price, Query, Tag, Transaction, Warning, WarningKind,
+};
+
+/// Pointers of the shared string allocations that have already been counted
+pub(crate) type Seen = HashSet<*const u8>;
+
+/// Memory footprint accounting implemented by every type of the parsed tree
+///
+/// Both operations are implemented by the same trait so that adding a new type (or variant)
+/// can't be forgotten in one of them.
+pub(crate) trait HeapSize {
+    /// Release the excess capacity of all owned collections
+    fn shrink_to_fit(&mut self);
+
+    /// Approximated number of bytes owned on the heap (excluding `size_of::<Self>()`)
+    ///
+    /// Shared allocations are only counted the first time they are seen.
+    fn heap_size(&self, seen: &mut Seen) -> usize;
+}
+
+/// Approximated overhead of a hash table entry (control byte of the swiss table)
+const HASH_TABLE_ENTRY_OVERHEAD: usize = 1;
+
+impl<D> HeapSize for BeancountFile<D> {
+    fn shrink_to_fit(&mut self) {
+        self.options.shrink_to_fit();
+        self.options.iter_mut().for_each(HeapSize::shrink_to_fit);
+        self.includes.shrink_to_fit();
+        self.includes.iter_mut().for_each(PathBuf::shrink_to_fit);
+        self.plugins.shrink_to_fit();
+        self.plugins.iter_mut().for_each(HeapSize::shrink_to_fit);
+        self.directives.shrink_to_fit();
+        self.directives.iter_mut().for_each(HeapSize::shrink_to_fit);
+        self.warnings.shrink_to_fit();
+    }
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        vec_heap_size(&self.options, seen)
+            + self.includes.capacity() * size_of::<PathBuf>()
+            + self.includes.iter().map(PathBuf::capacity).sum::<usize>()
+            + vec_heap_size(&self.plugins, seen)
+            + vec_heap_size(&self.directives, seen)
+            + vec_heap_size(&self.warnings, seen)
+            + self.state.pushed.tags.capacity() * size_of::<Tag>()
+            + self.state.pushed.metadata.capacity()
+                * size_of::<(metadata::Key, metadata::Value<D>)>()
+    }
+}
+
+impl HeapSize for Plugin {
+    fn shrink_to_fit(&mut self) {
+        self.name.shrink_to_fit();
+        if let Some(config) = &mut self.config {
+            config.shrink_to_fit();
+        }
+    }
+
+    fn heap_size(&self, _: &mut Seen) -> usize {
+        self.name.capacity() + self.config.as_ref().map_or(0, String::capacity)
+    }
+}
+
+impl HeapSize for BeanOption {
+    fn shrink_to_fit(&mut self) {
+        self.name.shrink_to_fit();
+        self.value.shrink_to_fit();
+    }
+
+    fn heap_size(&self, _: &mut Seen) -> usize {
+        self.name.capacity() + self.value.capacity()
+    }
+}
+
+impl<D> HeapSize for Directive<D> {
+    fn shrink_to_fit(&mut self) {
+        self.content.shrink_to_fit();
+        self.metadata.shrink_to_fit();
+        self.metadata_duplicates.shrink_to_fit();
+    }
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        self.content.heap_size(seen)
+            + self.metadata.heap_size(seen)
+            + self.metadata_duplicates.heap_size(seen)
+            + self.misspelled_keyword.as_ref().map_or(0, |keyword| {
+                size_of::<crate::keyword::MisspelledKeyword>() + keyword.text.capacity()
+            })
+            + self.invalid_lines.as_ref().map_or(0, |lines| {
+                lines
+                    .iter()
+                    .map(|line| size_of::<crate::InvalidLine>() + line.text.capacity())
+                    .sum()
+            })
+            + self.provenance.as_ref().map_or(0, |provenance| {
+                size_of::<crate::Provenance>() + provenance.label.capacity()
+            })
+    }
+}
+
+impl<D> HeapSize for DirectiveContent<D> {
+    fn shrink_to_fit(&mut self) {
+        match self {
+            DirectiveContent::Transaction(trx) => trx.shrink_to_fit(),
+            DirectiveContent::Price(price) => price.shrink_to_fit(),
+            DirectiveContent::Balance(balance) => balance.shrink_to_fit(),
+            DirectiveContent::Open(open) => open.shrink_to_fit(),
+            DirectiveContent::Close(close) => close.shrink_to_fit(),
+            DirectiveContent::Pad(pad) => pad.shrink_to_fit(),
+            DirectiveContent::Commodity(currency) => currency.shrink_to_fit(),
+            DirectiveContent::Event(event) => event.shrink_to_fit(),
+            DirectiveContent::Note(note) => note.shrink_to_fit(),
+            DirectiveContent::Document(document) => document.shrink_to_fit(),
+            DirectiveContent::Query(query) => query.shrink_to_fit(),
+            DirectiveContent::Custom(custom) => custom.shrink_to_fit(),
+        }
+    }
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        match self {
+            DirectiveContent::Transaction(trx) => trx.heap_size(seen),
+            DirectiveContent::Price(price) => price.heap_size(seen),
+            DirectiveContent::Balance(balance) => balance.heap_size(seen),
+            DirectiveContent::Open(open) => open.heap_size(seen),
+            DirectiveContent::Close(close) => close.heap_size(seen),
+            DirectiveContent::Pad(pad) => pad.heap_size(seen),
+            DirectiveContent::Commodity(currency) => currency.heap_size(seen),
+            DirectiveContent::Event(event) => event.heap_size(seen),
+            DirectiveContent::Note(note) => note.heap_size(seen),
+            DirectiveContent::Document(document) => document.heap_size(seen),
+            DirectiveContent::Query(query) => query.heap_size(seen),
+            DirectiveContent::Custom(custom) => custom.heap_size(seen),
+        }
+    }
+}
+
+impl<D> HeapSize for Transaction<D> {
+    fn shrink_to_fit(&mut self) {
+        self.payee.shrink_to_fit();
+        self.narration.shrink_to_fit();
+        self.tags.shrink_to_fit();
+        self.links.shrink_to_fit();
+        self.postings.shrink_to_fit();
+        self.postings.iter_mut().for_each(HeapSize::shrink_to_fit);
+    }
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        self.payee.heap_size(seen)
+            + self.narration.heap_size(seen)
+            + self.tags.heap_size(seen)
+            + self.links.heap_size(seen)
+            + vec_heap_size(&self.postings, seen)
+    }
+}
+
+impl<D> HeapSize for Posting<D> {
+    fn shrink_to_fit(&mut self) {
+        self.metadata.shrink_to_fit();
+        self.metadata_duplicates.shrink_to_fit();
+    }
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        self.account.heap_size(seen)
+            + self.amount.heap_size(seen)
+            + self.cost.heap_size(seen)
+            + self.price.heap_size(seen)
+            + self.metadata.heap_size(seen)
+            + self.metadata_duplicates.heap_size(seen)
+            + self.misplaced_tags.as_ref().map_or(0, |tags| {
+                tags.iter()
+                    .map(|tag| size_of::<MisplacedTag>() + tag.text.heap_size(seen))
+                    .sum()
+            })
+            + self.stray_tokens.as_ref().map_or(0, |tokens| {
+                tokens
+                    .iter()
+                    .map(|token| size_of::<StrayToken>() + token.text.heap_size(seen))
+                    .sum()
+            })
+            + self.unsupported_cost.as_ref().map_or(0, |cost| {
+                size_of::<UnsupportedCost>() + cost.text.heap_size(seen)
+            })
+    }
+}
+
+impl<D> HeapSize for Cost<D> {
+    fn shrink_to_fit(&mut self) {}
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        self.amount.heap_size(seen)
+    }
+}
+
+impl<D> HeapSize for PostingPrice<D> {
+    fn shrink_to_fit(&mut self) {}
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        match self {
+            PostingPrice::Unit(amount) | PostingPrice::Total(amount) => amount.heap_size(seen),
+        }
+    }
+}
+
+impl<D> HeapSize for Price<D> {
+    fn shrink_to_fit(&mut self) {}
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        self.currency.heap_size(seen) + self.amount.heap_size(seen)
+    }
+}
+
+impl<D> HeapSize for Balance<D> {
+    fn shrink_to_fit(&mut self) {}
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        self.account.heap_size(seen) + self.amount.heap_size(seen)
+    }
+}
+
+impl<D> HeapSize for Amount<D> {
+    fn shrink_to_fit(&mut self) {}
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        self.currency.heap_size(seen)
+    }
+}
+
+impl HeapSize for Open {
+    fn shrink_to_fit(&mut self) {
+        self.currencies.shrink_to_fit();
+    }
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        self.account.heap_size(seen)
+            + self.currencies.heap_size(seen)
+            + self.booking_method.heap_size(seen)
+    }
+}
+
+impl HeapSize for Close {
+    fn shrink_to_fit(&mut self) {}
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        self.account.heap_size(seen)
+    }
+}
+
+impl HeapSize for Pad {
+    fn shrink_to_fit(&mut self) {}
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        self.account.heap_size(seen) + self.source_account.heap_size(seen)
+    }
+}
+
+impl HeapSize for Event {
+    fn shrink_to_fit(&mut self) {
+        self.name.shrink_to_fit();
+        self.value.shrink_to_fit();
+    }
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        self.name.heap_size(seen) + self.value.heap_size(seen)
+    }
+}
+
+impl HeapSize for Note {
+    fn shrink_to_fit(&mut self) {
+        self.description.shrink_to_fit();
+        self.tags.shrink_to_fit();
+        self.links.shrink_to_fit();
+    }
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        self.account.heap_size(seen)
+            + self.description.heap_size(seen)
+            + self.tags.heap_size(seen)
+            + self.links.heap_size(seen)
+    }
+}
+
+impl HeapSize for Query {
+    fn shrink_to_fit(&mut self) {
+        self.name.shrink_to_fit();
+        self.query_string.shrink_to_fit();
+    }
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        self.name.heap_size(seen) + self.query_string.heap_size(seen)
+    }
+}
+
+impl HeapSize for Document {
+    fn shrink_to_fit(&mut self) {
+        self.path.shrink_to_fit();
+        self.tags.shrink_to_fit();
+        self.links.shrink_to_fit();
+    }
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        self.account.heap_size(seen)
+            + self.path.heap_size(seen)
+            + self.tags.heap_size(seen)
+            + self.links.heap_size(seen)
+    }
+}
+
+impl<D> HeapSize for Custom<D> {
+    fn shrink_to_fit(&mut self) {
+        self.name.shrink_to_fit();
+        self.values.shrink_to_fit();
+        self.values.iter_mut().for_each(HeapSize::shrink_to_fit);
+    }
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        self.name.heap_size(seen) + vec_heap_size(&self.values, seen)
+    }
+}
+
+impl<D> HeapSize for CustomValue<D> {
+    fn shrink_to_fit(&mut self) {
+        if let CustomValue::String(s) = self {
+            s.shrink_to_fit();
+        }
+    }
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        match self {
+            CustomValue::String(s) => s.heap_size(seen),
+            CustomValue::Amount(amount) => amount.heap_size(seen),
+            CustomValue::Account(account) => account.heap_size(seen),
+            CustomValue::Date(_) | CustomValue::Bool(_) | CustomValue::Number(_) => 0,
+        }
+    }
+}
+
+impl<D> HeapSize for metadata::Duplicate<D> {
+    fn shrink_to_fit(&mut self) {
+        self.value.shrink_to_fit();
+    }
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        self.key.heap_size(seen) + self.value.heap_size(seen)
+    }
+}
+
+impl<D> HeapSize for metadata::Duplicates<D> {
+    fn shrink_to_fit(&mut self) {
+        if let Some(duplicates) = self.as_mut_vec() {
+            duplicates.shrink_to_fit();
+            duplicates.iter_mut().for_each(HeapSize::shrink_to_fit);
+        }
+    }
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        self.as_vec().map_or(0, |duplicates| {
+            size_of::<Vec<metadata::Duplicate<D>>>() + vec_heap_size(duplicates, seen)
+        })
+    }
+}
+
+impl HeapSize for Warning {
+    fn shrink_to_fit(&mut self) {}
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        match self.kind() {
+            WarningKind::DuplicateMetadataKey(key) => key.heap_size(seen),
+            WarningKind::TimeRemoved(time) => time.heap_size(seen),
+            WarningKind::MalformedBudget(reason) => reason.heap_size(seen),
+            WarningKind::InvalidAccount(account) | WarningKind::UnusedPad(account) => {
+                account.heap_size(seen)
+            }
+            WarningKind::SimilarAccounts(account, other) => {
+                account.heap_size(seen) + other.heap_size(seen)
+            }
+            WarningKind::PostingTagRemoved(tag) => tag.heap_size(seen),
+            WarningKind::UnpushedTagPopped(tag) => tag.heap_size(seen),
+            WarningKind::EmptyIncludeGlob(text)
+            | WarningKind::KeywordCase(text)
+            | WarningKind::StrayTokenDropped(text)
+            | WarningKind::InvalidLineSkipped(text)
+            | WarningKind::UnrecognizedLine(text) => text.heap_size(seen),
+            WarningKind::InvalidCost(_)
+            | WarningKind::FutureDated(_)
+            | WarningKind::StaleIncompleteFlag(_) => 0,
+        }
+    }
+}
+
+impl<D> HeapSize for metadata::Value<D> {
+    fn shrink_to_fit(&mut self) {
+        match self {
+            metadata::Value::String(s) => s.shrink_to_fit(),
+            metadata::Value::Number(_) | metadata::Value::Currency(_) => (),
+        }
+    }
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        match self {
+            metadata::Value::String(s) => s.heap_size(seen),
+            metadata::Value::Number(_) => 0,
+            metadata::Value::Currency(currency) => currency.heap_size(seen),
+        }
+    }
+}
+
+impl HeapSize for Account {
+    fn shrink_to_fit(&mut self) {}
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        shared_str_heap_size(self.as_ref(), seen)
+    }
+}
+
+impl HeapSize for Currency {
+    fn shrink_to_fit(&mut self) {}
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        shared_str_heap_size(self.as_ref(), seen)
+    }
+}
+
+impl HeapSize for Tag {
+    fn shrink_to_fit(&mut self) {}
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        shared_str_heap_size(self.as_ref(), seen)
+    }
+}
+
+impl HeapSize for Link {
+    fn shrink_to_fit(&mut self) {}
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        shared_str_heap_size(self.as_ref(), seen)
+    }
+}
+
+impl HeapSize for metadata::Key {
+    fn shrink_to_fit(&mut self) {}
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        shared_str_heap_size(self.as_ref(), seen)
+    }
+}
+
+impl HeapSize for BookingMethod {
+    fn shrink_to_fit(&mut self) {}
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        shared_str_heap_size(self.as_ref(), seen)
+    }
+}
+
+impl HeapSize for String {
+    fn shrink_to_fit(&mut self) {
+        String::shrink_to_fit(self);
+    }
+
+    fn heap_size(&self, _: &mut Seen) -> usize {
+        self.capacity()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Option<T> {
+    fn shrink_to_fit(&mut self) {
+        if let Some(value) = self {
+            value.shrink_to_fit();
+        }
+    }
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        self.as_ref().map_or(0, |value| value.heap_size(seen))
+    }
+}
+
+impl<T: HeapSize + Eq + Hash> HeapSize for HashSet<T> {
+    fn shrink_to_fit(&mut self) {
+        HashSet::shrink_to_fit(self);
+    }
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        self.capacity() * (size_of::<T>() + HASH_TABLE_ENTRY_OVERHEAD)
+            + self.iter().map(|v| v.heap_size(seen)).sum::<usize>()
+    }
+}
+
+impl<K: HeapSize + Eq + Hash, V: HeapSize> HeapSize for HashMap<K, V> {
+    fn shrink_to_fit(&mut self) {
+        HashMap::shrink_to_fit(self);
+        self.values_mut().for_each(HeapSize::shrink_to_fit);
+    }
+
+    fn heap_size(&self, seen: &mut Seen) -> usize {
+        self.capacity() * (size_of::<(K, V)>() + HASH_TABLE_ENTRY_OVERHEAD)
+            + self
+                .iter()
+                .map(|(k, v)| k.heap_size(seen) + v.heap_size(seen))
+                .sum::<usize>()
+    }
+}
+
+fn vec_heap_size<T: HeapSize>(vec: &Vec<T>, seen: &mut Seen) -> usize {
+    vec.capacity() * size_of::<T>() + vec.iter().map(|v| v.heap_size(seen)).sum::<usize>()
+}
+
+/// Size of the shared `Arc<str>` allocation: the reference counters followed by the string
+fn shared_str_heap_size(s: &str, seen: &mut Seen) -> usize {
+    if seen.insert(s.as_ptr()) {
+        2 * size_of::<usize>() + s.len()
+    } else {
+        0
+    }
+}
@@ -0,0 +1,203 @@
+//! Import of directives from external files (like bank statements)
+//!
+//! An [`Importer`] recognizes the files it can read, and extracts [`Directive`]s from their content.
+//! The [`ImporterRegistry`] runs a set of importers over a set of files, and [`merge`] adds
+//! the extracted directives to an existing ledger, skipping the ones it already contains.
+//!
+//! The importers work on bytes, the reading (and writing) of the files is left to the caller.
+//!
+//! Requires the `import` feature.
+//!
+//! # Example
+//!
+//! An importer of a CSV export with the columns `date,description,amount`.
+//!
+//! ```
+//! use beancount_parser::{
+//!     import::{self, ImportError, Importer, ImporterRegistry},
+//!     Account, BeancountFile, Directive,
+//! };
+//!
+//! struct BankCsv {
+//!     account: Account,
+//! }
+//!
+//! impl Importer<f64> for BankCsv {
+//!     fn identify(&self, input: &[u8], name: &str) -> bool {
+//!         name.ends_with(".csv") && input.starts_with(b"date,description,amount")
+//!     }
+//!
+//!     fn extract(&self, input: &[u8]) -> Result<Vec<Directive<f64>>, ImportError> {
+//!         let input = std::str::from_utf8(input).map_err(|err| ImportError::invalid(err.to_string()))?;
+//!         input
+//!             .lines()
+//!             .skip(1)
+//!             .map(|line| {
+//!                 let [date, description, amount] = line.split(',').collect::<Vec<_>>()[..] else {
+//!                     return Err(ImportError::invalid(format!("expected 3 columns in `{line}`")));
+//!                 };
+//!                 let trx = format!(
+//!                     "{date} * \"{description}\"\n  {}  {amount} CHF\n  Expenses:Unknown",
+//!                     self.account
+//!                 );
+//!                 Ok(trx.parse()?)
+//!             })
+//!             .collect()
+//!     }
+//!
+//!     fn account(&self) -> Account {
+//!         self.account.clone()
+//!     }
+//! }
+//!
+//! let registry = ImporterRegistry::new().with(BankCsv {
+//!     account: "Assets:Bank".parse().unwrap(),
+//! });
+//! let csv = b"date,description,amount\n2023-05-20,Coffee,-3\n2023-05-21,Groceries,-30";
+//! let extracted = registry.extract([("statement.csv", &csv[..]), ("notes.txt", b"hello")]);
+//! assert_eq!(extracted.len(), 1);
+//! assert_eq!(extracted[0].account.as_str(), "Assets:Bank");
+//! let directives = extracted.into_iter().next().unwrap().directives.unwrap();
+//!
+//! let mut ledger: BeancountFile<f64> = r#"
+//! 2023-05-20 * "Coffee"
+//!   Assets:Bank  -3 CHF
+//!   Expenses:Unknown
+//! "#
+//! .parse()
+//! .unwrap();
+//! let duplicates = import::merge(&mut ledger, directives);
+//! assert_eq!(duplicates.len(), 1);
+//! assert_eq!(ledger.directives.len(), 2);
+//! ```
+
+use crate::{Account, BeancountFile, Directive};
+
+/// Reader of a kind of external file, producing directives
+///
+/// See the [module documentation](self) for an example.
+pub trait Importer<D> {
+    /// Returns true if the importer can extract the file
+    ///
+    /// `name` is the name of the file (like `statement.csv`), and `input` its content.
+    fn identify(&self, input: &[u8], name: &str) -> bool;
+
+    /// Extract the directives of the file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the content cannot be interpreted
+    fn extract(&self, input: &[u8]) -> Result<Vec<Directive<D>>, ImportError>;
+
+    /// Account the imported files belong to (like `Assets:Bank:Checking`)
+    fn account(&self) -> Account;
+}
+
+/// Error returned by [`Importer::extract`]
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum ImportError {
+    /// The content of the file cannot be interpreted (the value is the reason)
+    #[error("Invalid input: {0}")]
+    Invalid(String),
+    /// A directive produced by the importer is not valid beancount syntax
+    #[error(transparent)]
+    Syntax(#[from] crate::Error),
+}
+
+impl ImportError {
+    /// Create an [`ImportError::Invalid`] error with the given reason
+    pub fn invalid(reason: impl Into<String>) -> Self {
+        Self::Invalid(reason.into())
+    }
+}
+
+/// Set of [`Importer`]s, run over a set of files
+///
+/// See the [module documentation](self) for an example.
+pub struct ImporterRegistry<D> {
+    importers: Vec<Box<dyn Importer<D>>>,
+}
+
+impl<D> Default for ImporterRegistry<D> {
+    fn default() -> Self {
+        Self {
+            importers: Vec::new(),
+        }
+    }
+}
+
+/// Result of the extraction of a file by an [`ImporterRegistry`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct Extracted<D> {
+    /// Name of the file
+    pub name: String,
+    /// Account of the importer that identified the file
+    pub account: Account,
+    /// Directives extracted from the file
+    pub directives: Result<Vec<Directive<D>>, ImportError>,
+}
+
+impl<D> ImporterRegistry<D> {
+    /// Create an empty registry
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an importer to the registry
+    ///
+    /// The importers are tried in the order they were added.
+    #[must_use]
+    pub fn with(mut self, importer: impl Importer<D> + 'static) -> Self {
+        self.importers.push(Box::new(importer));
+        self
+    }
+
+    /// Extract each file with the first importer identifying it
+    ///
+    /// The files (given as pairs of name and content) that no importer identifies are skipped.
+    pub fn extract<'a>(
+        &self,
+        files: impl IntoIterator<Item = (&'a str, &'a [u8])>,
+    ) -> Vec<Extracted<D>> {
+        files
+            .into_iter()
+            .filter_map(|(name, input)| {
+                let importer = self
+                    .importers
+                    .iter()
+                    .find(|importer| importer.identify(input, name))?;
+                Some(Extracted {
+                    name: name.into(),
+                    account: importer.account(),
+                    directives: importer.extract(input),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Add the directives to the ledger, except the ones it already contains
+///
+/// A directive is a duplicate if the ledger has a directive with the same date and the same content
+/// (the metadata and line number are ignored). Returns the duplicates, that were not added.
+pub fn merge<D: PartialEq>(
+    ledger: &mut BeancountFile<D>,
+    directives: impl IntoIterator<Item = Directive<D>>,
+) -> Vec<Directive<D>> {
+    let mut duplicates = Vec::new();
+    for directive in directives {
+        let is_duplicate = ledger
+            .directives
+            .iter()
+            .any(|d| d.date == directive.date && d.content == directive.content);
+        if is_duplicate {
+            duplicates.push(directive);
+        } else {
+            ledger.directives.push(directive);
+        }
+    }
+    duplicates
+}
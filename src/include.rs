@@ -0,0 +1,138 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Returns true if the include contains glob characters (`*`, `?` or `[`)
+pub(crate) fn is_glob(include: &str) -> bool {
+    include.contains(['*', '?', '['])
+}
+
+/// Returns the files matching the include, relative to `dir` (the directory of the including file)
+///
+/// Both `/` and `\` are path separators, on all platforms.
+/// The components of the include can be glob patterns:
+///
+/// * `*` matches any sequence of characters,
+/// * `?` matches any character,
+/// * `[abc]`, `[a-z]` and `[!abc]` match a set of characters,
+/// * `**` (as a whole component) matches any number of directories.
+///
+/// Like in Python's `glob` (used by beancount), the wildcards do not match the names starting with a `.`.
+/// The files are returned sorted lexicographically.
+pub(crate) fn expand(dir: &Path, include: &str) -> io::Result<Vec<PathBuf>> {
+    let normalized = include.replace('\\', "/");
+    let (root, relative) = match normalized.strip_prefix('/') {
+        Some(relative) => (PathBuf::from("/"), relative),
+        None => match drive(&normalized) {
+            Some((drive, relative)) => (PathBuf::from(format!("{drive}/")), relative),
+            None => (dir.to_path_buf(), normalized.as_str()),
+        },
+    };
+    let components: Vec<&str> = relative.split('/').filter(|c| !c.is_empty()).collect();
+    let mut files = Vec::new();
+    walk(root, &components, &mut files)?;
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// Returns the drive (like `C:`) and the rest of an absolute Windows path
+fn drive(path: &str) -> Option<(&str, &str)> {
+    let bytes = path.as_bytes();
+    if bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && bytes[2] == b'/' {
+        Some((&path[..2], &path[3..]))
+    } else {
+        None
+    }
+}
+
+fn walk(dir: PathBuf, components: &[&str], files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let Some((&first, rest)) = components.split_first() else {
+        if dir.is_file() {
+            files.push(dir);
+        }
+        return Ok(());
+    };
+    if !is_glob(first) {
+        return walk(dir.join(first), rest, files);
+    }
+    if first == "**" {
+        walk(dir.clone(), rest, files)?;
+    }
+    let entries = match dir.read_dir() {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound || !dir.is_dir() => return Ok(()),
+        Err(err) => return Err(err),
+    };
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+        if first == "**" {
+            if !name.starts_with('.') && entry.path().is_dir() {
+                walk(entry.path(), components, files)?;
+            }
+        } else if matches(first, name) {
+            walk(entry.path(), rest, files)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns true if the file name matches the glob pattern
+fn matches(pattern: &str, name: &str) -> bool {
+    if name.starts_with('.') && !pattern.starts_with('.') {
+        return false;
+    }
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    matches_chars(&pattern, &name)
+}
+
+fn matches_chars(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|skip| matches_chars(rest, &name[skip..])),
+        Some(('?', rest)) => !name.is_empty() && matches_chars(rest, &name[1..]),
+        Some(('[', rest)) => match (set(rest), name.split_first()) {
+            (Some((matched, rest)), Some((&c, name))) => matched(c) && matches_chars(rest, name),
+            (Some(_), None) => false,
+            // An unclosed `[` is a literal character
+            (None, _) => name.first() == Some(&'[') && matches_chars(rest, &name[1..]),
+        },
+        Some((&c, rest)) => name.first() == Some(&c) && matches_chars(rest, &name[1..]),
+    }
+}
+
+/// Parse the set following a `[`, returning a predicate and the rest of the pattern
+fn set(pattern: &[char]) -> Option<(impl Fn(char) -> bool + '_, &[char])> {
+    let (negated, pattern) = match pattern.first() {
+        Some('!' | '^') => (true, &pattern[1..]),
+        _ => (false, pattern),
+    };
+    // A `]` just after the `[` is part of the set
+    let end = pattern
+        .iter()
+        .skip(1)
+        .position(|&c| c == ']')
+        .map(|position| position + 1)?;
+    let items = &pattern[..end];
+    let matched = move |c: char| {
+        let mut i = 0;
+        let mut found = false;
+        while i < items.len() {
+            if i + 2 < items.len() && items[i + 1] == '-' {
+                found |= (items[i]..=items[i + 2]).contains(&c);
+                i += 3;
+            } else {
+                found |= items[i] == c;
+                i += 1;
+            }
+        }
+        found != negated
+    };
+    Some((matched, &pattern[end + 1..]))
+}
@@ -0,0 +1,70 @@
+use std::collections::{BTreeMap, HashSet};
+
+use crate::{Directive, DirectiveContent, Link, Tag};
+
+/// Identifier of a directive in a [`BeancountFile`](crate::BeancountFile)
+///
+/// It is the position of the directive in [`BeancountFile::directives`](crate::BeancountFile::directives).
+/// Use [`BeancountFile::directive`](crate::BeancountFile::directive) to get the directive back.
+///
+/// For an example, look at [`BeancountFile::links_index`](crate::BeancountFile::links_index)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DirectiveId(usize);
+
+impl DirectiveId {
+    pub(crate) fn new(index: usize) -> Self {
+        Self(index)
+    }
+
+    /// Returns the position of the directive in [`BeancountFile::directives`](crate::BeancountFile::directives)
+    #[must_use]
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// Returns the tags and links of the directive content
+///
+/// Every directive kind that can carry tags or links must be listed here,
+/// so that it appears in the indexes.
+pub(crate) fn tags_and_links<D>(
+    content: &DirectiveContent<D>,
+) -> Option<(&HashSet<Tag>, &HashSet<Link>)> {
+    match content {
+        DirectiveContent::Transaction(trx) => Some((&trx.tags, &trx.links)),
+        DirectiveContent::Note(note) => Some((&note.tags, &note.links)),
+        DirectiveContent::Document(document) => Some((&document.tags, &document.links)),
+        _ => None,
+    }
+}
+
+pub(crate) fn links<'a, D: 'a>(
+    directives: impl IntoIterator<Item = &'a Directive<D>>,
+) -> BTreeMap<Link, Vec<DirectiveId>> {
+    build(directives, |(_, links)| links)
+}
+
+pub(crate) fn tags<'a, D: 'a>(
+    directives: impl IntoIterator<Item = &'a Directive<D>>,
+) -> BTreeMap<Tag, Vec<DirectiveId>> {
+    build(directives, |(tags, _)| tags)
+}
+
+fn build<'a, D: 'a, K: Clone + Ord + 'a>(
+    directives: impl IntoIterator<Item = &'a Directive<D>>,
+    keys: impl Fn((&'a HashSet<Tag>, &'a HashSet<Link>)) -> &'a HashSet<K>,
+) -> BTreeMap<K, Vec<DirectiveId>> {
+    let mut index: BTreeMap<K, Vec<DirectiveId>> = BTreeMap::new();
+    for (position, directive) in directives.into_iter().enumerate() {
+        let Some(keys) = tags_and_links(&directive.content).map(&keys) else {
+            continue;
+        };
+        for key in keys {
+            index
+                .entry(key.clone())
+                .or_default()
+                .push(DirectiveId(position));
+        }
+    }
+    index
+}
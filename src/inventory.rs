@@ -0,0 +1,151 @@
+use std::{borrow::Borrow, collections::BTreeMap};
+
+use crate::{Amount, Currency, Evaluate};
+
+/// Quantities held per currency
+///
+/// It is typically the content of an account at a given point in time.
+/// Currencies are iterated in order of their name.
+///
+/// # Example
+///
+/// ```
+/// use beancount_parser::{Amount, Inventory};
+/// let mut inventory = Inventory::<f64>::new();
+/// inventory.add(Amount { value: 10.0, currency: "CHF".parse().unwrap() });
+/// inventory.add(Amount { value: 2.0, currency: "EUR".parse().unwrap() });
+/// inventory.add(Amount { value: 5.0, currency: "CHF".parse().unwrap() });
+/// assert_eq!(inventory.get("CHF"), Some(&15.0));
+/// assert_eq!(inventory.get("EUR"), Some(&2.0));
+/// assert_eq!(inventory.get("PLN"), None);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Inventory<D> {
+    amounts: BTreeMap<Currency, D>,
+}
+
+impl<D> Default for Inventory<D> {
+    fn default() -> Self {
+        Self {
+            amounts: BTreeMap::new(),
+        }
+    }
+}
+
+impl<D: Evaluate> Inventory<D> {
+    /// Create an empty inventory
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the amount to the inventory
+    ///
+    /// A currency for which the quantity becomes zero is removed from the inventory.
+    pub fn add(&mut self, amount: Amount<D>) {
+        let value = self.amounts.remove(&amount.currency).unwrap_or_default() + amount.value;
+        if value != D::default() {
+            self.amounts.insert(amount.currency, value);
+        }
+    }
+
+    /// Add all the quantities of the other inventory to this one
+    pub fn merge(&mut self, other: &Inventory<D>) {
+        for (currency, value) in &other.amounts {
+            self.add(Amount {
+                value: value.clone(),
+                currency: currency.clone(),
+            });
+        }
+    }
+
+    /// Returns the quantity held for the currency, if any
+    #[must_use]
+    pub fn get<Q>(&self, currency: &Q) -> Option<&D>
+    where
+        Currency: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.amounts.get(currency)
+    }
+
+    /// Returns an iterator over the currencies and their quantities, ordered by currency
+    pub fn iter(&self) -> impl Iterator<Item = (&Currency, &D)> {
+        self.amounts.iter()
+    }
+
+    /// Returns true if the inventory holds nothing
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.amounts.is_empty()
+    }
+
+    /// Returns the list of amounts held, ordered by currency
+    #[must_use]
+    pub fn to_amounts(&self) -> Vec<Amount<D>> {
+        self.amounts
+            .iter()
+            .map(|(currency, value)| Amount {
+                value: value.clone(),
+                currency: currency.clone(),
+            })
+            .collect()
+    }
+}
+
+impl<D> From<Inventory<D>> for Vec<Amount<D>> {
+    fn from(inventory: Inventory<D>) -> Self {
+        inventory
+            .amounts
+            .into_iter()
+            .map(|(currency, value)| Amount { value, currency })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn amount(value: i32, currency: &str) -> Amount<i32> {
+        Amount {
+            value,
+            currency: currency.parse().unwrap(),
+        }
+    }
+
+    #[rstest]
+    fn zero_quantities_are_removed() {
+        let mut inventory = Inventory::new();
+        inventory.add(amount(10, "CHF"));
+        inventory.add(amount(-10, "CHF"));
+        assert!(inventory.is_empty(), "{inventory:?}");
+    }
+
+    #[rstest]
+    fn amounts_are_ordered_by_currency() {
+        let mut inventory = Inventory::new();
+        inventory.add(amount(1, "USD"));
+        inventory.add(amount(2, "CHF"));
+        inventory.add(amount(3, "EUR"));
+        let amounts: Vec<Amount<i32>> = inventory.into();
+        assert_eq!(
+            amounts,
+            vec![amount(2, "CHF"), amount(3, "EUR"), amount(1, "USD")]
+        );
+    }
+
+    #[rstest]
+    fn merge_adds_quantities() {
+        let mut first = Inventory::new();
+        first.add(amount(1, "CHF"));
+        first.add(amount(2, "EUR"));
+        let mut second = Inventory::new();
+        second.add(amount(3, "CHF"));
+        second.add(amount(-2, "EUR"));
+        first.merge(&second);
+        assert_eq!(first.to_amounts(), vec![amount(4, "CHF")]);
+    }
+}
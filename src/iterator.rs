@@ -1,62 +1,569 @@
-use std::collections::HashSet;
+use std::{
+    collections::{HashSet, VecDeque},
+    ops::Range,
+};
+
+use nom::combinator::iterator;
 
 use nom::{combinator::ParserIterator, Finish};
 
-use crate::{DirectiveContent, Entry, Error, RawEntry, Span, Tag};
+use crate::unicode::Normalizer;
+use crate::{
+    account, entry_with_options, metadata, span,
+    transaction::{MisplacedTag, StrayToken, UnsupportedCost},
+    Account, BeancountFile, Code, CostIssue, Directive, DirectiveContent, Entry, Error,
+    IgnoredLine, InvalidLine, LineKind, Number, ParseOptions, RawEntry, Span, Tag, Warning,
+    WarningKind,
+};
 
 type InnerIter<'i, F> = ParserIterator<Span<'i>, nom::error::Error<Span<'i>>, F>;
 
-pub(crate) struct Iter<'i, F> {
+pub(crate) struct Iter<'i, D, F> {
     source: &'i str,
     inner: Option<InnerIter<'i, F>>,
-    tag_stack: HashSet<Tag>,
+    pushed: Pushed<D>,
+    options: ParseOptions,
+    /// Entries to emit before continuing the parsing
+    pending: VecDeque<Entry<D>>,
+    /// Offset and line number of the last `include` directive emitted
+    last_include: (usize, u32),
+    /// Normalizer of the names, if they are normalized (see `ParseOptions::normalize_nfc`)
+    normalizer: Option<Normalizer>,
 }
 
-impl<'i, F> Iter<'i, F> {
-    pub(crate) fn new(source: &'i str, value: InnerIter<'i, F>) -> Self {
+impl<'i, D, F> Iter<'i, D, F> {
+    pub(crate) fn new(source: &'i str, value: InnerIter<'i, F>, options: ParseOptions) -> Self {
         Self {
             source,
             inner: Some(value),
-            tag_stack: HashSet::new(),
+            pushed: Pushed::default(),
+            normalizer: Normalizer::from_options(&options),
+            options,
+            pending: VecDeque::new(),
+            last_include: (0, 0),
+        }
+    }
+
+    /// Start with the tags and metadata pushed (with `pushtag` and `pushmeta`) by a previous input
+    pub(crate) fn with_pushed(mut self, pushed: Pushed<D>) -> Self {
+        self.pushed = pushed;
+        self
+    }
+
+    /// Returns the offset and line number of the last [`Entry::Include`] emitted
+    pub(crate) fn last_include(&self) -> (usize, u32) {
+        self.last_include
+    }
+
+    /// Returns the tags and metadata pushed and not yet popped, leaving them empty
+    pub(crate) fn take_pushed(&mut self) -> Pushed<D> {
+        std::mem::take(&mut self.pushed)
+    }
+}
+
+impl<D: Clone, F> Iter<'_, D, F> {
+    /// Stop the parsing with the error of the first problem, unless the problems are `accepted` by the options
+    fn reject_first<T>(
+        &mut self,
+        problems: &[T],
+        accepted: bool,
+        error: impl FnOnce(&T, &str) -> Error,
+    ) -> Result<(), Error> {
+        match problems.first() {
+            Some(problem) if !accepted => {
+                self.inner = None;
+                Err(error(problem, self.source))
+            }
+            _ => Ok(()),
         }
     }
+
+    /// Complete the directive with the pushed tags and metadata, and queue its warnings
+    fn directive(
+        &mut self,
+        mut d: Directive<D>,
+        warning: Option<Warning>,
+    ) -> Result<Entry<D>, Error> {
+        if let Some(normalizer) = &mut self.normalizer {
+            normalizer.directive(&mut d);
+        }
+        let misspelled_keyword = d.misspelled_keyword.take();
+        if let Some(keyword) = &misspelled_keyword {
+            if !(self.options.case_insensitive_keywords && keyword.only_case()) {
+                self.inner = None;
+                return Err(Error::at(
+                    self.source,
+                    keyword.offset,
+                    keyword.line_number,
+                    Code::MisspelledKeyword,
+                )
+                .with_hint(keyword.hint()));
+            }
+        }
+        if let Some(duplicate) = d.duplicates().next() {
+            if self.options.strict_metadata {
+                self.inner = None;
+                return Err(Error::at(
+                    self.source,
+                    duplicate.offset,
+                    duplicate.line_number,
+                    Code::DuplicateMetadataKey,
+                ));
+            }
+        }
+        self.reject_first(&d.take_unsupported_costs(), false, UnsupportedCost::error)?;
+        let misplaced_tags = d.take_misplaced_tags();
+        let stray_tokens = d.take_stray_tokens();
+        let invalid_lines = d.take_invalid_lines();
+        self.reject_first(
+            &misplaced_tags,
+            self.options.strip_posting_tags,
+            MisplacedTag::error,
+        )?;
+        self.reject_first(
+            &stray_tokens,
+            self.options.drop_stray_tokens,
+            StrayToken::error,
+        )?;
+        self.reject_first(
+            &invalid_lines,
+            self.options.skip_invalid_lines,
+            InvalidLine::error,
+        )?;
+        let invalid_account = invalid_account(&d);
+        if let Some((account, offset, reason)) = &invalid_account {
+            if !self.options.lenient_accounts {
+                self.inner = None;
+                let (offset, line_number) =
+                    locate(self.source, d.line_number, account.as_str(), *offset);
+                return Err(
+                    Error::at(self.source, offset, line_number, Code::InvalidAccount)
+                        .with_hint(*reason),
+                );
+            }
+        }
+        let suppressed = &self.options.suppressed;
+        self.pending.extend(
+            warning
+                .into_iter()
+                .chain(misspelled_keyword.map(|keyword| {
+                    Warning::new(WarningKind::KeywordCase(keyword.text), keyword.line_number)
+                }))
+                .chain(d.duplicates().map(|duplicate| {
+                    Warning::new(
+                        WarningKind::DuplicateMetadataKey(duplicate.key.clone()),
+                        duplicate.line_number,
+                    )
+                }))
+                .chain(
+                    cost_issues(&d)
+                        .map(|issue| Warning::new(WarningKind::InvalidCost(issue), d.line_number)),
+                )
+                .chain(misplaced_tags.into_iter().map(|tag| {
+                    Warning::new(WarningKind::PostingTagRemoved(tag.text), tag.line_number)
+                }))
+                .chain(stray_tokens.iter().map(|token| {
+                    Warning::new(
+                        WarningKind::StrayTokenDropped(token.hint()),
+                        token.line_number,
+                    )
+                }))
+                .chain(invalid_lines.into_iter().map(|line| {
+                    Warning::new(WarningKind::InvalidLineSkipped(line.text), line.line_number)
+                }))
+                .chain(invalid_account.map(|(account, _, _)| {
+                    Warning::new(WarningKind::InvalidAccount(account), d.line_number)
+                }))
+                .filter(|warning| !suppressed.contains(&warning.code()))
+                .map(Entry::Warning),
+        );
+        self.pushed.apply(&mut d);
+        Ok(Entry::Directive(d))
+    }
+}
+
+/// Returns the first invalid account of the directive, with the offset (in the name) and the reason of the problem
+fn invalid_account<D>(directive: &Directive<D>) -> Option<(Account, usize, &'static str)> {
+    let mut invalid = None;
+    directive.content.for_each_account(|account| {
+        if invalid.is_none() {
+            invalid = account::invalid_reason(account.as_str())
+                .map(|(offset, reason)| (account.clone(), offset, reason));
+        }
+    });
+    invalid
+}
+
+/// Returns the offset in the source and the line number of the account of the directive at `line_number`
+fn locate(source: &str, line_number: u32, account: &str, offset: usize) -> (usize, u32) {
+    let start = source
+        .split_inclusive('\n')
+        .take(line_number.saturating_sub(1) as usize)
+        .map(str::len)
+        .sum::<usize>();
+    let Some(position) = source[start..].find(account) else {
+        return (start, line_number);
+    };
+    let lines = source[start..start + position].matches('\n').count();
+    (
+        start + position + offset,
+        line_number + u32::try_from(lines).unwrap_or(u32::MAX),
+    )
 }
 
-impl<'i, D, F> Iterator for Iter<'i, F>
+/// Returns the issues of the costs of the postings (see [`Cost::validate`](crate::Cost::validate))
+fn cost_issues<D>(directive: &Directive<D>) -> impl Iterator<Item = CostIssue> + '_ {
+    let postings = match &directive.content {
+        DirectiveContent::Transaction(trx) => &trx.postings[..],
+        _ => &[],
+    };
+    postings
+        .iter()
+        .flat_map(|posting| match (&posting.amount, &posting.cost) {
+            (Some(amount), Some(cost)) => cost.validate(&amount.currency),
+            _ => Vec::new(),
+        })
+}
+
+impl<'i, D: Clone, F> Iterator for Iter<'i, D, F>
 where
     for<'a> &'a mut InnerIter<'i, F>: Iterator<Item = RawEntry<D>>,
 {
     type Item = Result<Entry<D>, Error>;
     fn next(&mut self) -> Option<Self::Item> {
+        if let Some(entry) = self.pending.pop_front() {
+            return Some(Ok(entry));
+        }
         let inner = self.inner.as_mut()?;
         for entry in inner {
             match entry {
-                RawEntry::Directive(mut d) => {
-                    if let DirectiveContent::Transaction(trx) = &mut d.content {
-                        trx.tags.extend(self.tag_stack.iter().cloned());
+                RawEntry::Directive(d) => return Some(self.directive(d, None)),
+                RawEntry::TimedDirective(d, time) => {
+                    if !self.options.strip_times {
+                        self.inner = None;
+                        return Some(Err(Error::at(
+                            self.source,
+                            time.offset,
+                            time.line_number,
+                            Code::DateWithTime,
+                        )
+                        .with_hint(format!("remove `{}`", time.text))));
                     }
-                    return Some(Ok(Entry::Directive(d)));
+                    let warning = Warning::new(
+                        WarningKind::TimeRemoved(time.text.trim_start().into()),
+                        time.line_number,
+                    );
+                    return Some(self.directive(d, Some(warning)));
                 }
                 RawEntry::Option(o) => {
                     return Some(Ok(Entry::Option(o)));
                 }
-                RawEntry::Include(path) => {
+                RawEntry::Include(path, offset, line_number) => {
+                    self.last_include = (offset, line_number);
                     return Some(Ok(Entry::Include(path)));
                 }
-                RawEntry::PushTag(tag) => {
-                    self.tag_stack.insert(tag);
+                RawEntry::Plugin(p) => {
+                    return Some(Ok(Entry::Plugin(p)));
                 }
-                RawEntry::PopTag(tag) => {
-                    self.tag_stack.remove(&tag);
+                RawEntry::PushTag(mut tag) => {
+                    if let Some(normalizer) = &mut self.normalizer {
+                        normalizer.tag(&mut tag);
+                    }
+                    self.pushed.tags.insert(tag.clone());
+                    return Some(Ok(Entry::PushTag(tag)));
+                }
+                RawEntry::PopTag(mut tag, line_number) => {
+                    if let Some(normalizer) = &mut self.normalizer {
+                        normalizer.tag(&mut tag);
+                    }
+                    if !self.pushed.tags.remove(&tag)
+                        && !self.options.suppressed.contains(&Code::UnbalancedTag)
+                    {
+                        let warning =
+                            Warning::new(WarningKind::UnpushedTagPopped(tag.clone()), line_number);
+                        self.pending.push_back(Entry::Warning(warning));
+                    }
+                    return Some(Ok(Entry::PopTag(tag)));
+                }
+                RawEntry::PushMeta(mut key, mut value) => {
+                    if let Some(normalizer) = &mut self.normalizer {
+                        normalizer.key(&mut key);
+                        normalizer.value(&mut value);
+                    }
+                    self.pushed.metadata.push((key.clone(), value.clone()));
+                    return Some(Ok(Entry::PushMeta(key, value)));
+                }
+                RawEntry::PopMeta(mut key) => {
+                    if let Some(normalizer) = &mut self.normalizer {
+                        normalizer.key(&mut key);
+                    }
+                    let metadata = &mut self.pushed.metadata;
+                    if let Some(position) = metadata.iter().rposition(|(k, _)| *k == key) {
+                        metadata.remove(position);
+                    }
+                    return Some(Ok(Entry::PopMeta(key)));
+                }
+                RawEntry::Ignored(kind, offset, line_number) => {
+                    let line = (kind, ignored_text(self.source, offset), line_number);
+                    self.pending.extend(ignored_entries(&self.options, line));
+                    if let Some(entry) = self.pending.pop_front() {
+                        return Some(Ok(entry));
+                    }
                 }
                 RawEntry::Comment => (),
             }
         }
         match self.inner.take().unwrap().finish().finish() {
             Ok((rest, ())) if rest.fragment().is_empty() => None,
-            Ok((input, ())) | Err(nom::error::Error { input, .. }) => {
-                Some(Err(Error::new(self.source, input)))
+            Ok((input, ())) => Some(Err(Error::new(self.source, input))),
+            Err(error) => Some(Err(Error::from_nom(self.source, &error))),
+        }
+    }
+}
+
+/// Returns the line starting at `offset`, without the line ending
+fn ignored_text(source: &str, offset: usize) -> &str {
+    let line = &source[offset..];
+    line[..line.find('\n').unwrap_or(line.len())].trim_end_matches('\r')
+}
+
+/// Returns the entries emitted for a line ignored by the parser (given with its kind and line number):
+/// the line if it is captured, and the warning if it is not recognized
+fn ignored_entries<D>(
+    options: &ParseOptions,
+    (kind, text, line_number): (LineKind, &str, u32),
+) -> impl Iterator<Item = Entry<D>> {
+    let captured = (options.capture_ignored_lines && kind != LineKind::Blank).then(|| {
+        Entry::Ignored(IgnoredLine {
+            text: text.into(),
+            kind,
+            line_number,
+        })
+    });
+    let warning = (kind == LineKind::Unrecognized
+        && !options.suppressed.contains(&Code::UnrecognizedLine))
+    .then(|| {
+        Entry::Warning(Warning::new(
+            WarningKind::UnrecognizedLine(text.into()),
+            line_number,
+        ))
+    });
+    captured.into_iter().chain(warning)
+}
+
+/// Tags and metadata pushed (with `pushtag` and `pushmeta`) and not yet popped
+#[derive(Debug, Clone)]
+pub(crate) struct Pushed<D> {
+    pub(crate) tags: HashSet<Tag>,
+    /// Metadata in the order it was pushed: `popmeta` removes the last value pushed for the key
+    pub(crate) metadata: Vec<(metadata::Key, metadata::Value<D>)>,
+}
+
+impl<D> Default for Pushed<D> {
+    fn default() -> Self {
+        Self {
+            tags: HashSet::new(),
+            metadata: Vec::new(),
+        }
+    }
+}
+
+impl<D: Clone> Pushed<D> {
+    /// Add the pushed tags to a transaction and the pushed metadata to a directive
+    fn apply(&self, d: &mut Directive<D>) {
+        if let DirectiveContent::Transaction(trx) = &mut d.content {
+            trx.tags.extend(self.tags.iter().cloned());
+        }
+        // The last value pushed for a key is applied, unless the directive has its own value
+        for (key, value) in self.metadata.iter().rev() {
+            d.metadata
+                .entry(key.clone())
+                .or_insert_with(|| value.clone());
+        }
+    }
+}
+
+/// State of the parser at the end of an input, needed to parse text appended to it
+#[derive(Debug, Clone)]
+pub(crate) struct ParseState<D> {
+    pub(crate) options: ParseOptions,
+    /// Tags and metadata pushed and not yet popped
+    pub(crate) pushed: Pushed<D>,
+    /// Number of lines already parsed
+    pub(crate) lines: u32,
+}
+
+impl<D> Default for ParseState<D> {
+    fn default() -> Self {
+        Self {
+            options: ParseOptions::default(),
+            pushed: Pushed::default(),
+            lines: 0,
+        }
+    }
+}
+
+/// Parse the input, continuing from the given state
+pub(crate) fn parse_from_state<D: Number>(
+    input: &str,
+    state: ParseState<D>,
+) -> Result<BeancountFile<D>, Error> {
+    let mut iter = Iter::new(
+        input,
+        iterator(span(input), entry_with_options::<D>(&state.options)),
+        state.options.clone(),
+    )
+    .with_pushed(state.pushed);
+    let mut file = BeancountFile::default();
+    for entry in iter.by_ref() {
+        file.extend(Some(shift_lines(entry, state.lines)?));
+    }
+    let lines = u32::try_from(input.lines().count()).unwrap_or(u32::MAX);
+    file.state = ParseState {
+        options: state.options,
+        pushed: iter.take_pushed(),
+        lines: state.lines.saturating_add(lines),
+    };
+    Ok(file)
+}
+
+/// Parse the input, skipping the entries on which an error is found, and return the errors
+///
+/// After an error, the parsing restarts at the next line that is neither indented nor blank,
+/// with the tags and metadata pushed before the error.
+/// When the error is on an indented line separated from its directive by a blank line,
+/// the directive ends at the blank line and is kept.
+pub(crate) fn parse_lenient<D: Number>(
+    input: &str,
+    options: &ParseOptions,
+) -> (BeancountFile<D>, Vec<Error>) {
+    let mut file = BeancountFile::default();
+    let mut errors = Vec::new();
+    let mut pushed = Pushed::default();
+    // Offset and line number at which the remaining input starts
+    let (mut offset, mut lines) = (0, 0);
+    loop {
+        let rest = &input[offset..];
+        let error;
+        (pushed, error) = parse_until_error(&mut file, rest, pushed, options, lines);
+        let Some(error) = error else {
+            break;
+        };
+        let error_line = error.line_number().saturating_sub(lines);
+        if let Some((range, header)) = directive_before_blank_line(rest, error_line) {
+            // The directive is complete: it cannot change the pushed tags and metadata
+            let _ = parse_until_error(
+                &mut file,
+                &rest[range],
+                pushed.clone(),
+                options,
+                lines + header,
+            );
+        }
+        let restart = next_entry(rest, error_line);
+        errors.push(error);
+        let Some(restart) = restart else {
+            break;
+        };
+        lines += u32::try_from(rest[..restart].matches('\n').count()).unwrap_or(u32::MAX);
+        offset += restart;
+    }
+    file.state = ParseState {
+        options: options.clone(),
+        pushed,
+        lines: u32::try_from(input.lines().count()).unwrap_or(u32::MAX),
+    };
+    (file, errors)
+}
+
+/// Add the entries of the input to the file, until the first error
+///
+/// Returns the pushed tags and metadata at the end, and the error if any.
+fn parse_until_error<D: Number>(
+    file: &mut BeancountFile<D>,
+    input: &str,
+    pushed: Pushed<D>,
+    options: &ParseOptions,
+    lines: u32,
+) -> (Pushed<D>, Option<Error>) {
+    let mut iter = Iter::new(
+        input,
+        iterator(span(input), entry_with_options::<D>(options)),
+        options.clone(),
+    )
+    .with_pushed(pushed);
+    let mut error = None;
+    for entry in iter.by_ref() {
+        match shift_lines(entry, lines) {
+            Ok(entry) => file.extend(Some(entry)),
+            Err(err) => {
+                error = Some(err);
+                break;
+            }
+        }
+    }
+    (iter.take_pushed(), error)
+}
+
+/// Returns the byte range and the line index of the directive containing the indented line at `line_number`,
+/// if a blank line separates them, ending the directive at that blank line
+fn directive_before_blank_line(input: &str, line_number: u32) -> Option<(Range<usize>, u32)> {
+    let index = line_number.checked_sub(1)?;
+    if !input.lines().nth(index as usize)?.starts_with([' ', '\t']) {
+        return None;
+    }
+    let mut header = None;
+    let mut blank = None;
+    let mut offset = 0;
+    for (index, line) in (0..index).zip(input.split_inclusive('\n')) {
+        if line.trim().is_empty() {
+            blank = blank.or(Some(offset));
+        } else if !line.starts_with([' ', '\t', ';']) {
+            header = Some((offset, index));
+            blank = None;
+        }
+        offset += line.len();
+    }
+    let (start, index) = header?;
+    Some((start..blank?, index))
+}
+
+/// Returns the offset of the first line after `line_number` that is neither indented nor blank
+fn next_entry(input: &str, line_number: u32) -> Option<usize> {
+    let mut offset = 0;
+    for (index, line) in input.split_inclusive('\n').enumerate() {
+        if index >= line_number as usize
+            && !line.starts_with([' ', '\t'])
+            && !line.trim().is_empty()
+        {
+            return Some(offset);
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// Add `lines` to the line numbers of the entry (or error) found in a part of a larger input
+pub(crate) fn shift_lines<D>(item: Result<Entry<D>, Error>, lines: u32) -> Result<Entry<D>, Error> {
+    match item {
+        Ok(Entry::Directive(mut directive)) => {
+            directive.line_number += lines;
+            if let DirectiveContent::Transaction(trx) = &mut directive.content {
+                for posting in &mut trx.postings {
+                    posting.line_number += lines;
+                }
             }
+            Ok(Entry::Directive(directive))
+        }
+        Ok(Entry::Warning(mut warning)) => {
+            warning.shift_lines(lines);
+            Ok(Entry::Warning(warning))
+        }
+        Ok(Entry::Ignored(mut line)) => {
+            line.line_number += lines;
+            Ok(Entry::Ignored(line))
         }
+        Ok(entry) => Ok(entry),
+        Err(error) => Err(error.shift_lines(lines)),
     }
 }
@@ -0,0 +1,95 @@
+use nom::{
+    branch::alt,
+    character::complete::{alpha1, line_ending, space1},
+    combinator::{eof, peek, verify},
+};
+use nom_locate::position;
+
+use crate::{IResult, Span};
+
+/// Keywords of the directives following a date
+pub(crate) const KEYWORDS: [&str; 12] = [
+    "txn",
+    "price",
+    "balance",
+    "open",
+    "close",
+    "pad",
+    "commodity",
+    "event",
+    "note",
+    "document",
+    "query",
+    "custom",
+];
+
+/// Word found in place of a directive keyword, that is likely a misspelling of it (like `Open` or `blance`)
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct MisspelledKeyword {
+    pub(crate) text: String,
+    pub(crate) keyword: &'static str,
+    pub(crate) offset: usize,
+    pub(crate) line_number: u32,
+}
+
+impl MisspelledKeyword {
+    /// Returns true if the word only differs from the keyword by its case (like `Open` or `TXN`)
+    pub(crate) fn only_case(&self) -> bool {
+        self.text.eq_ignore_ascii_case(self.keyword)
+    }
+
+    pub(crate) fn hint(&self) -> String {
+        format!("did you mean `{}`?", self.keyword)
+    }
+}
+
+/// Returns the keyword that `word` is likely a misspelling of
+///
+/// That is a keyword equal to the word ignoring the case, or at one edit (insertion, deletion or substitution) of it.
+pub(crate) fn suggest(word: &str) -> Option<&'static str> {
+    let word = word.to_ascii_lowercase();
+    KEYWORDS
+        .into_iter()
+        .find(|keyword| *keyword == word)
+        .or_else(|| {
+            KEYWORDS
+                .into_iter()
+                .find(|keyword| levenshtein(&word, keyword) == 1)
+        })
+}
+
+/// Parse a word that is not a keyword, but is likely a misspelling of one
+///
+/// It fails (without consuming input) for keywords, flags, and words without suggestion.
+pub(crate) fn misspelled(input: Span<'_>) -> IResult<'_, MisspelledKeyword> {
+    let (input, position) = position(input)?;
+    let (input, word) = verify(alpha1, |word: &Span<'_>| {
+        word.len() > 1 && !KEYWORDS.contains(word.fragment()) && suggest(word.fragment()).is_some()
+    })(input)?;
+    let (input, _) = peek(alt((space1, line_ending, eof)))(input)?;
+    let keyword = suggest(word.fragment()).unwrap_or_default();
+    Ok((
+        input,
+        MisspelledKeyword {
+            text: (*word.fragment()).into(),
+            keyword,
+            offset: position.location_offset(),
+            line_number: position.location_line(),
+        },
+    ))
+}
+
+/// Number of edits (insertions, deletions or substitutions) to turn `a` into `b`
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
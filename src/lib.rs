@@ -7,7 +7,8 @@
 //! Use [`parse`] to get an instance of [`BeancountFile`].
 //!
 //! This is generic over the decimal type. The examples use `f64` as a decimal type.
-//! You may also use `Decimal` from the [rust_decimal crate].
+//! You may also use `Decimal` from the [rust_decimal crate] (with the `rust_decimal` feature),
+//! or your own number type by implementing [`FromLiteral`] and [`Number`].
 //!
 //! [rust_decimal crate]: https://docs.rs/rust_decimal
 //!
@@ -40,27 +41,112 @@
 //! assert_eq!(trx.postings[1].amount, None);
 //! # Ok(()) }
 //! ```
+//!
+//! # Line numbers
+//!
+//! Every [`Directive`] and [`Posting`] has the line number where it starts in the input,
+//! so that tools built on this crate (like a linter) can point at the problems they find.
+//!
+//! ```
+//! use beancount_parser::{BeancountFile, DirectiveContent};
+//!
+//! let input = r#"
+//! 2023-05-20 * "Refund"
+//!   Expenses:Groceries  -10 CHF
+//!   Assets:Checking
+//! "#;
+//! let beancount: BeancountFile<f64> = input.parse().unwrap();
+//! let mut errors = Vec::new();
+//! for directive in &beancount.directives {
+//!     let DirectiveContent::Transaction(trx) = &directive.content else { continue };
+//!     for posting in &trx.postings {
+//!         let negative = posting.amount.as_ref().is_some_and(|amount| amount.value < 0.0);
+//!         if negative && posting.account.as_str().starts_with("Expenses:") {
+//!             errors.push(format!("error at line {}: negative expense", posting.line_number));
+//!         }
+//!     }
+//! }
+//! assert_eq!(errors, ["error at line 3: negative expense"]);
+//! ```
+//!
+//! # Ordering
+//!
+//! Every aggregate computed from a file has a deterministic order, that does not depend on hashing:
+//! accounts are sorted by name, currencies by code, tags and links by name, and dates ascending
+//! (ties keep the order of the directives in the file).
+//! Counts, when ranked, are sorted descending with the name as tiebreak.
+//!
+//! # Allocations
+//!
+//! Parsing a transaction with a narration and two postings (without metadata, tags or links)
+//! makes at most 6 heap allocations: the narration, the list of postings, and one per account and currency.
+//! This budget is checked by the tests (see `tests/allocation_budget.txt`).
 
-use std::{collections::HashSet, fs::File, io::Read, path::PathBuf, str::FromStr};
+use std::{
+    collections::{BTreeMap, HashSet, VecDeque},
+    fmt::{Display, Formatter, Write},
+    fs::File,
+    io::Read,
+    ops::{Range, RangeBounds},
+    path::PathBuf,
+    str::FromStr,
+    sync::Arc,
+};
 
 use nom::{
     branch::alt,
-    bytes::complete::{tag, take_while},
-    character::complete::{char, line_ending, not_line_ending, space0, space1},
-    combinator::{all_consuming, cut, eof, iterator, map, not, opt, value},
-    sequence::{delimited, preceded, terminated, tuple},
-    Finish, Parser,
+    bytes::complete::{tag, take_while, take_while_m_n},
+    character::complete::{alpha1, char, digit1, line_ending, not_line_ending, space0, space1},
+    combinator::{
+        all_consuming, cut, eof, iterator, map, map_res, not, opt, peek, recognize, value, verify,
+    },
+    multi::many0_count,
+    sequence::{delimited, pair, preceded, terminated, tuple},
+    Finish, InputTake, Parser,
 };
 use nom_locate::position;
 
-use crate::iterator::Iter;
+#[cfg(feature = "trie")]
+pub use crate::account_trie::AccountTrie;
 pub use crate::{
     account::{Account, Balance, Close, Open, Pad},
-    amount::{Amount, Currency, Decimal, Price},
+    account_tree::{AccountNode, AccountTree},
+    amount::{Amount, Currency, Decimal, Evaluate, FromLiteral, Literal, Number, Operation, Price},
+    anonymize::PseudonymizeOptions,
+    capabilities::Capabilities,
+    code::Code,
+    custom::{Custom, CustomValue},
     date::Date,
-    error::{ConversionError, Error, ReadFileError},
+    display::{AmountDisplay, DisplayContext},
+    document::Document,
+    edit::TextEdit,
+    error::{ConversionError, Error, MergeError, ReadFileError},
     event::Event,
-    transaction::{Cost, Link, Posting, PostingPrice, Tag, Transaction},
+    index::DirectiveId,
+    inventory::Inventory,
+    line::{IgnoredLine, LineKind},
+    merge::MergePolicy,
+    normalize::NormalizeOptions,
+    note::Note,
+    parse_options::ParseOptions,
+    provenance::Provenance,
+    query::Query,
+    report::{ParseReport, Stats},
+    resolve::{ResolveOptions, ResolvedLedger, ResolvedPosting},
+    sink::DirectiveSink,
+    snapshot::Snapshot,
+    source::{FileId, Locate, Located, Location, SourceMap},
+    suggestion::{BalanceAdjustment, BalanceSuggestion},
+    transaction::{
+        Cost, CostIssue, Indent, Link, Posting, PostingPrice, Tag, Transaction, TransactionHeader,
+    },
+    warning::{Warning, WarningKind},
+};
+use crate::{
+    heap_size::HeapSize,
+    iterator::{Iter, ParseState},
+    keyword::MisspelledKeyword,
+    period::{ActivityStats, Period, PeriodKey},
 };
 
 #[deprecated(note = "use `metadata::Value` instead", since = "1.0.0-beta.3")]
@@ -68,157 +154,1255 @@ pub use crate::{
 pub type MetadataValue<D> = metadata::Value<D>;
 
 mod account;
+mod account_tree;
+#[cfg(feature = "trie")]
+mod account_trie;
 mod amount;
+mod anonymize;
+pub mod attachment;
+mod booking;
+mod bytes;
+mod capabilities;
+mod closing;
+mod code;
+mod custom;
 mod date;
+mod debug_tree;
+#[cfg(feature = "serde")]
+pub mod diagnostic;
+mod display;
+mod document;
+mod edit;
 mod error;
 mod event;
+pub mod fava;
+mod heap_size;
+#[cfg(feature = "import")]
+pub mod import;
+mod include;
+mod index;
+mod inventory;
 mod iterator;
+mod keyword;
+mod line;
+pub mod lint;
+mod merge;
 pub mod metadata;
+mod normalize;
+mod note;
+mod parse_options;
+pub mod period;
+mod provenance;
+mod query;
+mod report;
+mod resolve;
+mod sink;
+mod snapshot;
+mod source;
+mod suggestion;
+mod syntax;
 mod transaction;
+mod unicode;
+mod warning;
 
 /// Parse the input beancount file and return an instance of [`BeancountFile`] on success
 ///
-/// It is generic over the [`Decimal`] type `D`.
+/// It is generic over the [`Number`] type `D`.
 ///
 /// See the root crate documentation for an example.
 ///
 /// # Errors
 ///
 /// Returns an [`Error`] in case of invalid beancount syntax found.
-pub fn parse<D: Decimal>(input: &str) -> Result<BeancountFile<D>, Error> {
+pub fn parse<D: Number>(input: &str) -> Result<BeancountFile<D>, Error> {
     input.parse()
 }
 
+/// Parse the input beancount file with the given [`ParseOptions`]
+///
+/// See [`parse`]
+///
+/// # Errors
+///
+/// Returns an [`Error`] in case of invalid beancount syntax found,
+/// or in case of input rejected by the options.
+pub fn parse_with_options<D: Number>(
+    input: &str,
+    options: &ParseOptions,
+) -> Result<BeancountFile<D>, Error> {
+    iterator::parse_from_state(
+        input,
+        ParseState {
+            options: options.clone(),
+            ..ParseState::default()
+        },
+    )
+}
+
+/// Parse the input beancount file, skipping the invalid entries, and return the file with all the errors found
+///
+/// Unlike [`parse`], the parsing does not stop at the first error. The entry on which an error is found is skipped
+/// (up to the next line that is neither indented nor blank), the error is recorded, and the parsing continues.
+/// An invalid indented line separated from its directive by a blank line does not skip the directive:
+/// the directive ends at the blank line.
+/// This is useful for editors and linters, which need the valid directives and all the errors at once.
+///
+/// The file is the same as the one returned by [`parse`] if there is no error.
+///
+/// # Example
+///
+/// ```
+/// use beancount_parser::{parse_lenient, BeancountFile};
+/// let input = "2023-05-27 open Assets:Cash\n2023-05-28 open oops\n  id: 1\n2023-05-29 close Assets:Cash\n2023-05-30 close oops\n";
+/// let (beancount, errors): (BeancountFile<f64>, _) = parse_lenient(input);
+/// let lines: Vec<u32> = beancount.directives.iter().map(|d| d.line_number).collect();
+/// assert_eq!(lines, [1, 4]);
+/// let lines: Vec<u32> = errors.iter().map(|e| e.line_number()).collect();
+/// assert_eq!(lines, [2, 5]);
+/// ```
+#[must_use]
+pub fn parse_lenient<D: Number>(input: &str) -> (BeancountFile<D>, Vec<Error>) {
+    parse_lenient_with_options(input, &ParseOptions::default())
+}
+
+/// Parse the input beancount file with the given [`ParseOptions`], skipping the invalid entries
+///
+/// The entries rejected by the options are skipped as well, and their errors are returned.
+///
+/// See [`parse_lenient`]
+#[must_use]
+pub fn parse_lenient_with_options<D: Number>(
+    input: &str,
+    options: &ParseOptions,
+) -> (BeancountFile<D>, Vec<Error>) {
+    iterator::parse_lenient(input, options)
+}
+
 /// Parse the beancount file and return an iterator over `Result<Entry<D>, Result>`
 ///
-/// It is generic over the [`Decimal`] type `D`.
+/// It is generic over the [`Number`] type `D`.
 ///
 /// See [`Entry`]
 ///
 /// # Errors
 ///
 /// The iterator will emit an [`Error`] in case of invalid beancount syntax found.
-pub fn parse_iter<'a, D: Decimal + 'a>(
+pub fn parse_iter<'a, D: Number + 'a>(
     input: &'a str,
 ) -> impl Iterator<Item = Result<Entry<D>, Error>> + 'a {
-    Iter::new(input, iterator(Span::new(input), entry::<D>))
+    parse_iter_with_options(input, &ParseOptions::default())
 }
 
-impl<D: Decimal> FromStr for BeancountFile<D> {
+/// Parse the beancount file with the given [`ParseOptions`] and return an iterator over `Result<Entry<D>, Result>`
+///
+/// See [`parse_iter`]
+///
+/// # Errors
+///
+/// The iterator will emit an [`Error`] in case of invalid beancount syntax found,
+/// or in case of input rejected by the options.
+pub fn parse_iter_with_options<'a, D: Number + 'a>(
+    input: &'a str,
+    options: &ParseOptions,
+) -> impl Iterator<Item = Result<Entry<D>, Error>> + 'a {
+    Iter::new(
+        input,
+        iterator(span(input), entry_with_options::<D>(options)),
+        options.clone(),
+    )
+}
+
+/// Parse the beancount file from bytes and return an instance of [`BeancountFile`] on success
+///
+/// The input is validated as UTF-8 progressively, as it is parsed, so that there is no need
+/// to validate (or convert) the whole input upfront, like with [`parse`].
+/// This is useful for large memory-mapped files.
+///
+/// # Errors
+///
+/// Returns an [`Error`] in case of invalid beancount syntax found,
+/// or in case of invalid UTF-8 (with the code [`Code::InvalidUtf8`]).
+///
+/// # Example
+///
+/// ```
+/// use beancount_parser::{parse_bytes, BeancountFile, Code};
+/// let beancount: BeancountFile<f64> = parse_bytes(b"2023-05-27 open Assets:Cash").unwrap();
+/// assert_eq!(beancount.directives.len(), 1);
+///
+/// let error = parse_bytes::<f64>(b"2023-05-27 open Assets:Cash\n2023-05-28 * \"\xff\"").unwrap_err();
+/// assert_eq!(error.code(), Code::InvalidUtf8);
+/// assert_eq!(error.line_number(), 2);
+/// ```
+pub fn parse_bytes<D: Number>(input: &[u8]) -> Result<BeancountFile<D>, Error> {
+    parse_bytes_iter(input).collect()
+}
+
+/// Parse the beancount file from bytes and return an iterator over `Result<Entry<D>, Result>`
+///
+/// See [`parse_bytes`] and [`parse_iter`]
+///
+/// # Errors
+///
+/// The iterator will emit an [`Error`] in case of invalid beancount syntax found,
+/// or in case of invalid UTF-8.
+pub fn parse_bytes_iter<'a, D: Number + 'a>(
+    input: &'a [u8],
+) -> impl Iterator<Item = Result<Entry<D>, Error>> + 'a {
+    parse_bytes_iter_with_options(input, &ParseOptions::default())
+}
+
+/// Parse the beancount file from bytes with the given [`ParseOptions`]
+///
+/// See [`parse_bytes`] and [`parse_iter_with_options`]
+///
+/// # Errors
+///
+/// The iterator will emit an [`Error`] in case of invalid beancount syntax found,
+/// in case of invalid UTF-8, or in case of input rejected by the options.
+pub fn parse_bytes_iter_with_options<'a, D: Number + 'a>(
+    input: &'a [u8],
+    options: &ParseOptions,
+) -> impl Iterator<Item = Result<Entry<D>, Error>> + 'a {
+    bytes::parse(input, options, bytes::CHUNK_SIZE)
+}
+
+/// Parse the beancount file with the given [`ParseOptions`], and return a [`ParseReport`]
+///
+/// The report contains the parsed file with its errors, warnings, statistics and the time spent parsing.
+/// It is meant for tools presenting the result of the parsing to a user.
+/// See [`parse`] for getting only the file.
+#[must_use]
+pub fn parse_report<D: Number>(input: &str, options: &ParseOptions) -> ParseReport<D> {
+    report::parse(input, options)
+}
+
+/// Parse the beancount file with the given [`ParseOptions`], and push every entry to the `sink`
+///
+/// This is the push-style counterpart of [`parse_iter_with_options`] (on which it is built),
+/// for pipelines processing the directives one by one without building a [`BeancountFile`].
+/// The parsing stops early if a hook of the sink returns [`ControlFlow::Break`](std::ops::ControlFlow::Break).
+///
+/// See [`DirectiveSink`] for an example.
+///
+/// # Errors
+///
+/// Returns an [`Error`] in case of invalid beancount syntax found,
+/// or in case of input rejected by the options.
+/// The entries found before the error are pushed to the sink.
+pub fn parse_with_sink<D: Number>(
+    input: &str,
+    options: &ParseOptions,
+    sink: &mut dyn DirectiveSink<D>,
+) -> Result<(), Error> {
+    sink::parse(input, options, sink)
+}
+
+/// Returns what this version of the crate supports: directive kinds, parse options and enabled cargo features
+///
+/// This allows tools supporting several versions of the crate to check for a feature at runtime.
+///
+/// # Example
+/// ```
+/// use beancount_parser::{capabilities, lint::DirectiveKind};
+/// let capabilities = capabilities();
+/// assert!(capabilities.supports_directive(DirectiveKind::Custom));
+/// assert!(capabilities.supports_option("strict_metadata"));
+/// assert!(!capabilities.supports_option("no_such_option"));
+/// assert_eq!(capabilities.has_feature("serde"), cfg!(feature = "serde"));
+/// ```
+#[must_use]
+pub fn capabilities() -> Capabilities {
+    capabilities::capabilities()
+}
+
+impl<D: Number> FromStr for BeancountFile<D> {
     type Err = Error;
     fn from_str(input: &str) -> Result<Self, Self::Err> {
-        parse_iter(input).collect()
+        parse_with_options(input, &ParseOptions::default())
     }
 }
 
 /// Read the files from disk and parse their content.
 ///
-/// It follows the `include` directives found.
+/// It follows the `include` directives found, relative to the directory of the including file.
+/// An include can be a glob pattern (like `include "2023/*.bean"`), whose matches are loaded in lexicographic order.
+/// Both `/` and `\` are accepted as path separators, and each file is loaded only once.
+///
+/// # Errors
+///
+/// Returns an error if any file could not be read (IO error),
+/// if there is a beancount syntax error in any file read,
+/// or if an included file does not exist (with code [`Code::IncludeNotFound`]).
+/// A glob pattern matching no file is only a warning (with code [`Code::EmptyIncludeGlob`]).
+pub fn read_files<D: Number, F: FnMut(Entry<D>)>(
+    files: impl IntoIterator<Item = PathBuf>,
+    on_entry: F,
+) -> Result<(), ReadFileError> {
+    read_files_with_sources(files, &mut SourceMap::default(), on_entry)
+}
+
+/// Read the files from disk and parse their content, recording their paths in `sources`
+///
+/// Like [`read_files`], but the warnings and errors are attributed to the file they were found in:
+/// their [`FileId`] can be resolved to the path of the file with [`SourceMap::resolve`].
 ///
 /// # Errors
 ///
 /// Returns an error if any file could not be read (IO error)
 /// or if there is a beancount syntax error in any file read
-pub fn read_files<D: Decimal, F: FnMut(Entry<D>)>(
+pub fn read_files_with_sources<D: Number, F: FnMut(Entry<D>)>(
     files: impl IntoIterator<Item = PathBuf>,
+    sources: &mut SourceMap,
     mut on_entry: F,
 ) -> Result<(), ReadFileError> {
     let mut loaded: HashSet<PathBuf> = HashSet::new();
-    let mut pending: Vec<PathBuf> = files
+    let mut pending: VecDeque<PathBuf> = files
         .into_iter()
         .map(|p| p.canonicalize())
         .collect::<Result<_, _>>()?;
+    let options = ParseOptions::default();
     let mut buffer = String::new();
-    while let Some(path) = pending.pop() {
-        if loaded.contains(&path) {
+    while let Some(path) = pending.pop_front() {
+        if !loaded.insert(path.clone()) {
             continue;
         }
-        loaded.insert(path.clone());
+        let file_id = sources.insert(path.clone());
         buffer.clear();
         File::open(&path)?.read_to_string(&mut buffer)?;
-        for result in parse_iter::<D>(&buffer) {
-            let entry = result?;
+        let Some(dir) = path.parent() else {
+            unreachable!("there must be a parent if the file was valid")
+        };
+        let mut entries = Iter::new(
+            &buffer,
+            iterator(span(&buffer), entry_with_options::<D>(&options)),
+            options.clone(),
+        );
+        while let Some(result) = entries.next() {
+            let entry = result.map_err(|error| error.in_file(file_id))?;
             match entry {
                 Entry::Include(include) => {
-                    let path = if include.is_relative() {
-                        let Some(parent) = path.parent() else {
-                            unreachable!("there must be a parent if the file was valid")
-                        };
-                        parent.join(include)
-                    } else {
-                        include
-                    };
-                    let path = path.canonicalize()?;
-                    if !loaded.contains(&path) {
-                        pending.push(path);
+                    let include = include.to_string_lossy();
+                    let files = include::expand(dir, &include)?;
+                    if files.is_empty() {
+                        let (offset, line_number) = entries.last_include();
+                        if !include::is_glob(&include) {
+                            let error =
+                                Error::at(&buffer, offset, line_number, Code::IncludeNotFound)
+                                    .with_hint(format!(
+                                        "no file at {}",
+                                        dir.join(&*include).display()
+                                    ));
+                            return Err(error.in_file(file_id).into());
+                        }
+                        let kind = WarningKind::EmptyIncludeGlob(include.into_owned());
+                        on_entry(Entry::Warning(
+                            Warning::new(kind, line_number).in_file(file_id),
+                        ));
+                    }
+                    for file in files {
+                        let file = file.canonicalize()?;
+                        if !loaded.contains(&file) {
+                            pending.push_back(file);
+                        }
                     }
                 }
+                Entry::Warning(warning) => on_entry(Entry::Warning(warning.in_file(file_id))),
                 entry => on_entry(entry),
             }
         }
     }
-    Ok(())
-}
+    Ok(())
+}
+
+/// Main struct representing a parsed beancount file.
+///
+/// To get an instance of this, use [`parse`].
+///
+/// For an example, look at the root crate documentation.
+///
+/// # Serde
+///
+/// With the `serde` feature, the file and all the types it contains can be serialized and deserialized
+/// (the [`warnings`](Self::warnings) are not).
+/// Deserialization rejects unknown fields, and validates the accounts, currencies, tags, links, metadata keys and dates,
+/// so that it only produces values the parser could have produced.
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct BeancountFile<D> {
+    /// List of beancount options
+    ///
+    /// See: <https://beancount.github.io/docs/beancount_language_syntax.html#options>
+    pub options: Vec<BeanOption>,
+    /// Paths of include directives
+    ///
+    /// The paths are kept as written in the file: parsing never reads the file system,
+    /// so they are neither resolved nor checked to exist, and glob patterns are not expanded.
+    /// Use [`read_files`] to load the included files, or implement your own loading on top of these paths.
+    ///
+    /// See: <https://beancount.github.io/docs/beancount_language_syntax.html#includes>
+    pub includes: Vec<PathBuf>,
+    /// List of plugins to run on the directives, in the order they are declared
+    ///
+    /// See: <https://beancount.github.io/docs/beancount_language_syntax.html#plugins>
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub plugins: Vec<Plugin>,
+    /// List of [`Directive`] found in the file
+    pub directives: Vec<Directive<D>>,
+    /// List of [`Warning`] emitted while parsing the file
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub warnings: Vec<Warning>,
+    /// State of the parser at the end of the input, used by [`Self::extend_from_str`]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    state: ParseState<D>,
+}
+
+impl<D> Default for BeancountFile<D> {
+    fn default() -> Self {
+        Self {
+            options: Vec::new(),
+            includes: Vec::new(),
+            plugins: Vec::new(),
+            directives: Vec::new(),
+            warnings: Vec::new(),
+            state: ParseState::default(),
+        }
+    }
+}
+
+impl<D> BeancountFile<D> {
+    /// Returns the first value found for the option
+    ///
+    /// If the option is declared multiple times, this function returns the first one found.
+    ///
+    /// See [`Self::options`] to get all declared options.
+    ///
+    /// Syntax: <https://beancount.github.io/docs/beancount_language_syntax.html#options>
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::BeancountFile;
+    /// let input = r#"
+    /// option "favorite_color" "blue"
+    /// option "operating_currency" "CHF"
+    /// option "operating_currency" "PLN"
+    /// "#;
+    /// let beancount: BeancountFile<f64> = input.parse().unwrap();
+    /// assert_eq!(beancount.option("favorite_color"), Some("blue"));
+    /// assert_eq!(beancount.option("operating_currency"), Some("CHF"));
+    /// assert_eq!(beancount.option("foo"), None);
+    /// ```
+    #[must_use]
+    pub fn option(&self, key: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .find(|opt| opt.name == key)
+            .map(|opt| &opt.value[..])
+    }
+
+    /// Returns all the values found for the option, in the order they are declared
+    ///
+    /// This is useful for the options that may be declared multiple times, like `operating_currency`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::BeancountFile;
+    /// let input = r#"
+    /// option "operating_currency" "CHF"
+    /// option "title" "My ledger"
+    /// option "operating_currency" "PLN"
+    /// "#;
+    /// let beancount: BeancountFile<f64> = input.parse().unwrap();
+    /// let currencies: Vec<&str> = beancount.option_values("operating_currency").collect();
+    /// assert_eq!(currencies, ["CHF", "PLN"]);
+    /// assert_eq!(beancount.option_values("foo").count(), 0);
+    /// ```
+    pub fn option_values<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.options
+            .iter()
+            .filter(move |opt| opt.name == key)
+            .map(|opt| &opt.value[..])
+    }
+
+    /// Returns the first plugin declared with the given name (the python module implementing it)
+    ///
+    /// This tells whether the file relies on a plugin, and with which configuration.
+    /// See [`Self::plugins`] to get all declared plugins.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::BeancountFile;
+    /// let input = r#"
+    /// plugin "beancount.plugins.auto_accounts"
+    /// plugin "beancount.plugins.check_commodity" "strict"
+    /// "#;
+    /// let beancount: BeancountFile<f64> = input.parse().unwrap();
+    /// assert!(beancount.plugin("beancount.plugins.auto_accounts").is_some());
+    /// let plugin = beancount.plugin("beancount.plugins.check_commodity").unwrap();
+    /// assert_eq!(plugin.config.as_deref(), Some("strict"));
+    /// assert!(beancount.plugin("beancount.plugins.leafonly").is_none());
+    /// ```
+    #[must_use]
+    pub fn plugin(&self, name: &str) -> Option<&Plugin> {
+        self.plugins.iter().find(|plugin| plugin.name == name)
+    }
+
+    /// Release the excess capacity of all the collections and strings owned by the file
+    ///
+    /// This is useful for long-running processes keeping parsed files in memory.
+    pub fn shrink_to_fit(&mut self) {
+        HeapSize::shrink_to_fit(self);
+    }
+
+    /// Returns an estimation of the number of bytes allocated on the heap by this file
+    ///
+    /// It accounts for the capacity of collections and strings,
+    /// and counts only once the names (account, currency, tag, etc.) that are shared between values.
+    ///
+    /// The decimal type `D` is assumed to not allocate on the heap.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::BeancountFile;
+    /// let input = "2023-05-27 open Assets:Cash CHF";
+    /// let mut beancount: BeancountFile<f64> = input.parse().unwrap();
+    /// let size = beancount.estimated_heap_size();
+    /// beancount.shrink_to_fit();
+    /// assert!(beancount.estimated_heap_size() <= size);
+    /// ```
+    #[must_use]
+    pub fn estimated_heap_size(&self) -> usize {
+        self.heap_size(&mut heap_size::Seen::new())
+    }
+
+    /// Parse text appended to the input of this file, and add its entries to this file
+    ///
+    /// This is faster than parsing the whole input again when only entries were appended.
+    /// The appended text is parsed with the state at the end of the original input:
+    /// the same [`ParseOptions`], the tags and metadata pushed (with `pushtag` and `pushmeta`) and not popped yet,
+    /// and the line numbers continue after the last line of the original input.
+    ///
+    /// The state is only known if the file was created with [`parse`], [`parse_with_options`],
+    /// [`str::parse`] or a previous call to this method. Otherwise, the appended text is parsed
+    /// as if it was the beginning of the input.
+    ///
+    /// The [`DirectiveId`] of the existing directives are not changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error`] in case of invalid beancount syntax found in the appended text.
+    /// In that case, the file is left unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::{BeancountFile, DirectiveContent};
+    /// let mut beancount: BeancountFile<f64> = "pushtag #trip\n2023-05-27 open Assets:Cash\n".parse().unwrap();
+    /// beancount.extend_from_str("2023-05-28 * \"Hotel\"\n").unwrap();
+    /// let directive = &beancount.directives[1];
+    /// assert_eq!(directive.line_number, 3);
+    /// let DirectiveContent::Transaction(trx) = &directive.content else { unreachable!() };
+    /// assert!(trx.tags.contains("trip"));
+    /// ```
+    pub fn extend_from_str(&mut self, appended: &str) -> Result<(), Error>
+    where
+        D: Number,
+    {
+        let mut staging = iterator::parse_from_state(appended, self.state.clone())?;
+        self.options.append(&mut staging.options);
+        self.includes.append(&mut staging.includes);
+        self.plugins.append(&mut staging.plugins);
+        self.directives.append(&mut staging.directives);
+        self.warnings.append(&mut staging.warnings);
+        self.state = staging.state;
+        Ok(())
+    }
+
+    /// Returns the tags pushed (with `pushtag`) and not popped at the end of the input, in no particular order
+    ///
+    /// Beancount expects every `pushtag` to be popped before the end of the file, but the parser accepts
+    /// an unbalanced `pushtag`: the tag is added to all the transactions until the end of the input
+    /// (and of the text appended with [`BeancountFile::extend_from_str`]).
+    /// This method allows to detect them.
+    ///
+    /// The tags are only known if the file was created with [`parse`], [`parse_with_options`],
+    /// [`str::parse`] or [`BeancountFile::extend_from_str`]. Otherwise, none are returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::BeancountFile;
+    /// let input = "pushtag #trip\npushtag #work\n2023-05-27 * \"Hotel\"\npoptag #work\n";
+    /// let beancount: BeancountFile<f64> = input.parse().unwrap();
+    /// let tags: Vec<&str> = beancount.pushed_tags().map(|tag| tag.as_str()).collect();
+    /// assert_eq!(tags, ["trip"]);
+    /// ```
+    pub fn pushed_tags(&self) -> impl Iterator<Item = &Tag> {
+        self.state.pushed.tags.iter()
+    }
+
+    /// Returns the hierarchy of all the accounts referenced in the directives
+    ///
+    /// See [`AccountTree`] for an example.
+    #[must_use]
+    pub fn account_tree(&self) -> AccountTree {
+        account_tree::build(&self.directives)
+    }
+
+    /// Returns a prefix trie of all the accounts referenced in the directives, to complete account names
+    ///
+    /// See [`AccountTrie`] for an example.
+    #[cfg(feature = "trie")]
+    #[must_use]
+    pub fn account_trie(&self) -> AccountTrie {
+        account_trie::build(self)
+    }
+
+    /// Convert into a [`Snapshot`], which is cheap to clone
+    ///
+    /// The directives are moved, not copied. See [`Snapshot`] for an example.
+    #[must_use]
+    pub fn into_snapshot(self) -> Snapshot<D> {
+        self.into()
+    }
+
+    /// Returns the ids of all the directives, in the order of [`Self::directives`]
+    pub fn directive_ids(&self) -> impl Iterator<Item = DirectiveId> {
+        (0..self.directives.len()).map(DirectiveId::new)
+    }
+
+    /// Returns the directive identified by `id`
+    ///
+    /// Returns `None` if there is no such directive (for example if `id` comes from another file).
+    #[must_use]
+    pub fn directive(&self, id: DirectiveId) -> Option<&Directive<D>> {
+        self.directives.get(id.index())
+    }
+
+    /// Change the directive identified by `id`, and return the edit of `source` writing the changed directive
+    ///
+    /// `source` must be the input this file was parsed from (and the result of the previous edits),
+    /// so that the edit replaces the lines of the directive and only them.
+    /// The rest of the text, including the comments and blank lines around the directive, is kept.
+    /// Only the lines changed by `f` are written again (in the beancount syntax, with the indentation of the original line):
+    /// the other lines of the directive, including its comments, are kept as written.
+    /// Added metadata entries and postings are written after the existing ones, and a removed posting
+    /// is removed with the lines following it (its metadata and comments).
+    /// The tags and metadata added by `pushtag` and `pushmeta` are not written (unless `f` changes their value).
+    ///
+    /// The [`line_number`](Directive::line_number) of the directives following it are updated,
+    /// so that they match the text once the edit is applied.
+    ///
+    /// Returns `None` (and leaves the directive unchanged) if there is no such directive,
+    /// or if it does not come from `source` (like a directive without line number).
+    /// Returns `None` as well if the change leaves the text of the directive unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::{BeancountFile, DirectiveContent};
+    /// let mut source = String::from("; Food\n2023-05-27 * \"Coffee\"\n  Expenses:Food  3 CHF\n  Assets:Cash\n");
+    /// let mut beancount: BeancountFile<f64> = source.parse().unwrap();
+    /// let id = beancount.directive_ids().next().unwrap();
+    /// let edit = beancount
+    ///     .edit(&source, id, |directive| {
+    ///         let DirectiveContent::Transaction(trx) = &mut directive.content else { unreachable!() };
+    ///         trx.narration = Some("Tea".into());
+    ///     })
+    ///     .unwrap();
+    /// assert_eq!(edit.range, 7..65);
+    /// edit.apply(&mut source);
+    /// assert_eq!(source, "; Food\n2023-05-27 * \"Tea\"\n  Expenses:Food  3 CHF\n  Assets:Cash\n");
+    /// ```
+    pub fn edit(
+        &mut self,
+        source: &str,
+        id: DirectiveId,
+        f: impl FnOnce(&mut Directive<D>),
+    ) -> Option<TextEdit>
+    where
+        D: Display,
+    {
+        let mut f = Some(f);
+        let mut edits = edit::edit(self, source, [id], |directive| {
+            if let Some(f) = f.take() {
+                f(directive);
+            }
+        });
+        edits.pop()
+    }
+
+    /// Change the directives identified by `ids`, and return the edits of `source` writing the changed directives
+    ///
+    /// This is the same as [`BeancountFile::edit`] for several directives at once:
+    /// the closure is called once for every directive, in the order of [`Self::directives`].
+    /// The ids of the directives that are not found (or do not come from `source`) are ignored,
+    /// and there is no edit for the directives whose text is unchanged.
+    ///
+    /// The edits are sorted by position and do not overlap.
+    /// Their ranges are all in `source`: apply them from the last one to the first.
+    pub fn edit_batch(
+        &mut self,
+        source: &str,
+        ids: impl IntoIterator<Item = DirectiveId>,
+        f: impl FnMut(&mut Directive<D>),
+    ) -> Vec<TextEdit>
+    where
+        D: Display,
+    {
+        edit::edit(self, source, ids, f)
+    }
+
+    /// Returns, for every link (sorted by name), the directives having that link (in the order of [`Self::directives`])
+    ///
+    /// The index is computed on every call. [`Snapshot::links_index`] computes it once and reuses it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::{BeancountFile, DirectiveContent};
+    /// let input = r#"
+    /// 2014-02-05 * "Invoice for January" ^invoice-pepe-studios-jan14
+    ///   Income:Clients:PepeStudios  -8450.00 USD
+    ///   Assets:AccountsReceivable
+    ///
+    /// 2014-02-20 * "Check deposit - payment from Pepe" ^invoice-pepe-studios-jan14
+    ///   Assets:BofA:Checking  8450.00 USD
+    ///   Assets:AccountsReceivable
+    /// "#;
+    /// let beancount: BeancountFile<f64> = input.parse().unwrap();
+    /// let index = beancount.links_index();
+    /// let ids = &index["invoice-pepe-studios-jan14"];
+    /// assert_eq!(ids.len(), 2);
+    /// assert_eq!(beancount.directive(ids[1]).unwrap().date.day, 20);
+    ///
+    /// // Or more simply, using `linked_group`
+    /// let received: f64 = beancount
+    ///     .linked_group("invoice-pepe-studios-jan14")
+    ///     .filter_map(|directive| match &directive.content {
+    ///         DirectiveContent::Transaction(trx) => Some(&trx.postings),
+    ///         _ => None,
+    ///     })
+    ///     .flatten()
+    ///     .filter(|posting| posting.account.as_str() == "Assets:BofA:Checking")
+    ///     .filter_map(|posting| posting.amount.as_ref())
+    ///     .map(|amount| amount.value)
+    ///     .sum();
+    /// assert_eq!(received, 8450.0);
+    /// ```
+    #[must_use]
+    pub fn links_index(&self) -> BTreeMap<Link, Vec<DirectiveId>> {
+        index::links(&self.directives)
+    }
+
+    /// Returns, for every tag (sorted by name), the directives having that tag (in the order of [`Self::directives`])
+    ///
+    /// Tags added with `pushtag` are included.
+    ///
+    /// See [`Self::links_index`] for an example.
+    #[must_use]
+    pub fn tags_index(&self) -> BTreeMap<Tag, Vec<DirectiveId>> {
+        index::tags(&self.directives)
+    }
+
+    /// Returns a summary of the directives of every period, in chronological order
+    ///
+    /// There is an entry for every period from the one of the first directive to the one of the last directive,
+    /// including the periods without any directive. The directives are read once, in any order.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::{lint::DirectiveKind, period::Period, BeancountFile};
+    /// let input = r#"
+    /// 2023-01-01 open Assets:Cash CHF
+    /// 2023-01-10 * "Groceries"
+    ///   Expenses:Food  30 CHF
+    ///   Assets:Cash
+    /// 2023-03-02 * "Groceries"
+    ///   Expenses:Food  20 EUR @ 1 CHF
+    ///   Assets:Cash
+    /// "#;
+    /// let beancount: BeancountFile<f64> = input.parse().unwrap();
+    /// let activity = beancount.activity(Period::Month);
+    ///
+    /// let months: Vec<String> = activity.iter().map(|(month, _)| month.to_string()).collect();
+    /// assert_eq!(months, ["2023-01", "2023-02", "2023-03"]);
+    /// let january = &activity[0].1;
+    /// assert_eq!(january.directives[&DirectiveKind::Transaction], 1);
+    /// assert_eq!(january.postings, 2);
+    /// assert_eq!(january.accounts, 2);
+    /// assert_eq!(january.currencies, 1);
+    /// assert_eq!(activity[2].1.currencies, 2);
+    /// ```
+    #[must_use]
+    pub fn activity(&self, period: Period) -> Vec<(PeriodKey, ActivityStats)> {
+        period::activity(&self.directives, period)
+    }
+
+    /// Returns the paths of the documents attached to the directive, and to the directives sharing a link with it
+    ///
+    /// The documents of the directive come first (see [`Directive::attached_documents`]),
+    /// followed by the ones of the linked directives, in the order of [`Self::directives`].
+    /// A path is only returned once. Returns an empty list if there is no such directive.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::BeancountFile;
+    /// let input = r#"
+    /// 2023-05-20 * "Invoice" ^invoice-42
+    ///   document: "invoices/42.pdf"
+    ///   Assets:Receivable  100 CHF
+    ///   Income:Consulting
+    /// 2023-06-01 * "Payment" ^invoice-42
+    ///   document: "bank/statement-june.pdf"
+    ///   Assets:Bank  100 CHF
+    ///   Assets:Receivable
+    /// "#;
+    /// let beancount: BeancountFile<f64> = input.parse().unwrap();
+    /// let payment = beancount.links_index()["invoice-42"][1];
+    /// assert_eq!(beancount.documents_for(payment), ["bank/statement-june.pdf", "invoices/42.pdf"]);
+    /// ```
+    #[must_use]
+    pub fn documents_for(&self, id: DirectiveId) -> Vec<&str> {
+        attachment::documents_for(self, id)
+    }
+
+    /// Returns the directives having the given link, in the order of [`Self::directives`]
+    ///
+    /// See [`Self::links_index`] for an example.
+    pub fn linked_group<'a>(&'a self, link: &'a str) -> impl Iterator<Item = &'a Directive<D>> {
+        self.directives.iter().filter(move |directive| {
+            index::tags_and_links(&directive.content).is_some_and(|(_, links)| links.contains(link))
+        })
+    }
+
+    /// Returns the accounts whose balance may be changed by the directives dated in the range
+    ///
+    /// That is the accounts of the postings, of the `pad`, `balance`, `open` and `close` directives in the range.
+    /// The source account of a `pad` is included if the pad or the `balance` assertion resolving it is in the range,
+    /// and the `account_rounding` account (if set) if a transaction in the range does not balance.
+    /// The balances of the other accounts are the same as without the directives of the range.
+    ///
+    /// The directives are read once, in any order.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::{BeancountFile, Date};
+    /// let input = r#"
+    /// 2023-01-01 pad Assets:Checking Equity:Opening-Balances
+    /// 2023-01-10 * "Coffee"
+    ///   Expenses:Food   4 CHF
+    ///   Assets:Cash
+    /// "#;
+    /// let mut beancount: BeancountFile<f64> = input.parse().unwrap();
+    /// beancount.extend_from_str("2023-02-01 balance Assets:Checking 100 CHF").unwrap();
+    ///
+    /// let touched = beancount.accounts_touched_in(Date::new(2023, 2, 1)..);
+    /// let mut touched: Vec<&str> = touched.iter().map(|account| account.as_str()).collect();
+    /// touched.sort_unstable();
+    /// assert_eq!(touched, ["Assets:Checking", "Equity:Opening-Balances"]);
+    /// ```
+    #[must_use]
+    pub fn accounts_touched_in(&self, range: impl RangeBounds<Date>) -> HashSet<Account>
+    where
+        D: Evaluate,
+    {
+        booking::accounts_touched(self, &range)
+    }
+
+    /// Returns the number of decimals with which the amounts of each currency are displayed
+    ///
+    /// The precision of a currency is the most common number of decimals among its amounts in the file
+    /// (the larger one on a tie). The decimals are counted on the literals as written in the file,
+    /// including the trailing zeros (`1.50` has 2 decimals, whatever the number type).
+    /// The directives built by a program or deserialized, and those whose amounts were added or removed, are not counted.
+    ///
+    /// See [`DisplayContext`] for an example.
+    #[must_use]
+    pub fn display_context(&self) -> DisplayContext {
+        display::display_context(self)
+    }
+
+    /// Returns the options to render the amounts of the file in human-facing reports
+    ///
+    /// The thousands are separated if the option `render_commas` is true,
+    /// and the number of decimals of each currency is the one of the [`display_context`](Self::display_context).
+    ///
+    /// See [`AmountDisplay`] for an example.
+    #[must_use]
+    pub fn amount_display(&self) -> AmountDisplay {
+        display::amount_display(self)
+    }
+
+    /// Returns the balance of every account (sorted by name) at the beginning of the day `as_of`
+    ///
+    /// Only the directives dated before `as_of` are taken into account. If `as_of` is `None`, all directives are.
+    ///
+    /// The balances are computed in a single chronological pass, following the beancount rules:
+    ///
+    /// * The amount of a posting without amount is interpolated so that the transaction balances
+    ///   (using the cost or the price of the other postings, if any)
+    /// * A `pad` directive moves the amount needed to satisfy the next `balance` assertion of the padded account,
+    ///   and that amount is accounted at the date of the `pad`
+    /// * If the option `account_rounding` is set, the unbalanced remainder of transactions is posted to that account
+    ///
+    /// Only the quantities are tracked: lots held at cost are not distinguished.
+    /// The balance of parent accounts can be obtained with [`AccountTree::rollup`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::BeancountFile;
+    /// let input = r#"
+    /// 2023-01-01 pad Assets:Checking Equity:Opening-Balances
+    /// 2023-01-02 balance Assets:Checking 100 CHF
+    /// 2023-01-10 * "Coffee"
+    ///   Expenses:Food   4 CHF
+    ///   Assets:Checking
+    /// "#;
+    /// let beancount: BeancountFile<f64> = input.parse().unwrap();
+    ///
+    /// let balances = beancount.balances(None);
+    /// assert_eq!(balances["Assets:Checking"].get("CHF"), Some(&96.0));
+    /// assert_eq!(balances["Equity:Opening-Balances"].get("CHF"), Some(&-100.0));
+    ///
+    /// let balances = beancount.balances(Some("2023-01-10".parse().unwrap()));
+    /// assert_eq!(balances["Assets:Checking"].get("CHF"), Some(&100.0));
+    /// assert!(!balances.contains_key("Expenses:Food"));
+    /// ```
+    #[must_use]
+    pub fn balances(&self, as_of: Option<Date>) -> BTreeMap<Account, Inventory<D>>
+    where
+        D: Evaluate,
+    {
+        booking::balances(self, as_of)
+    }
+
+    /// Check the `balance` assertions, and return an error (with code [`Code::BalanceMismatch`]) for each one that fails
+    ///
+    /// The balances are computed like [`Self::balances`], and an assertion passes if the difference
+    /// is within its tolerance (if any). By default, an assertion only checks the asserted account itself.
+    /// It also covers the sub-accounts if the file was parsed with
+    /// [`ParseOptions::aggregate_balance_assertions`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::{parse_with_options, BeancountFile, Code, ParseOptions};
+    /// let input = r#"
+    /// 2023-01-01 * "Salary"
+    ///   Assets:Bank:Checking  100 CHF
+    ///   Income:Salary
+    /// 2023-01-02 balance Assets:Bank 100 CHF
+    /// "#;
+    /// let beancount: BeancountFile<f64> = input.parse().unwrap();
+    /// let errors = beancount.verify_balances();
+    /// assert_eq!(errors[0].code(), Code::BalanceMismatch);
+    /// assert_eq!(errors[0].line_number(), 5);
+    ///
+    /// let options = ParseOptions::default().aggregate_balance_assertions(true);
+    /// let beancount: BeancountFile<f64> = parse_with_options(input, &options).unwrap();
+    /// assert!(beancount.verify_balances().is_empty());
+    /// ```
+    #[must_use]
+    pub fn verify_balances(&self) -> Vec<Error>
+    where
+        D: Evaluate,
+    {
+        booking::verify_balances(self)
+    }
+
+    /// Returns the transactions closing the income and expense accounts into the `target` account, as of the beginning of the day `as_of`
+    ///
+    /// There is one transaction (flagged `*`, with the narration `Closing entry`) for each income and expense account
+    /// whose [balance](Self::balances) is not zero, with one posting per currency transferring the balance to `target`.
+    /// If `close_accounts` is true, a `close` directive follows for each of those accounts.
+    /// The root accounts are named by the options `name_income` and `name_expenses` (`Income` and `Expenses` by default).
+    ///
+    /// The directives are dated the day before `as_of`, so that they are accounted in the balances as of `as_of`,
+    /// and have a [provenance](Directive::provenance) labelled `closing entries`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::{BeancountFile, Date, DirectiveContent};
+    /// let input = r#"
+    /// 2023-03-01 * "Salary"
+    ///   Assets:Bank     1000 CHF
+    ///   Income:Salary
+    /// 2023-06-01 * "Rent"
+    ///   Expenses:Rent  400 CHF
+    ///   Assets:Bank
+    /// "#;
+    /// let mut beancount: BeancountFile<f64> = input.parse().unwrap();
+    /// let as_of = Date::new(2024, 1, 1);
+    /// let entries = beancount.closing_entries(as_of, &"Equity:Earnings:Previous".parse().unwrap(), true);
+    /// assert_eq!(entries.len(), 4);
+    /// assert_eq!(entries[0].date, Date::new(2023, 12, 31));
+    /// assert!(matches!(entries[3].content, DirectiveContent::Close(_)));
+    ///
+    /// beancount.directives.extend(entries);
+    /// let balances = beancount.balances(Some(as_of));
+    /// assert!(balances["Income:Salary"].is_empty());
+    /// assert_eq!(balances["Equity:Earnings:Previous"].get("CHF"), Some(&-600.0));
+    /// ```
+    #[must_use]
+    pub fn closing_entries(
+        &self,
+        as_of: Date,
+        target: &Account,
+        close_accounts: bool,
+    ) -> Vec<Directive<D>>
+    where
+        D: Evaluate,
+    {
+        closing::closing_entries(self, as_of, target, close_accounts)
+    }
+
+    /// Compare the balance of an account observed at the beginning of the day `date` (like in a bank statement)
+    /// with the balance computed from the file
+    ///
+    /// The computed balance is the one a `balance` assertion dated `date` checks
+    /// (see [`Self::verify_balances`]), for the currency of `observed` only:
+    /// the other currencies held by the account are ignored.
+    /// Build the assertion with [`Directive::balance`], preceded by a [`Directive::balance_adjustment`]
+    /// if the balances differ.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::{Amount, BalanceAdjustment, BalanceSuggestion, BeancountFile, Directive};
+    /// let input = r#"
+    /// 2023-01-01 open Assets:Bank
+    /// 2023-01-05 * "Salary"
+    ///   Assets:Bank     1000 CHF
+    ///   Income:Salary
+    /// "#;
+    /// let mut beancount: BeancountFile<f64> = input.parse().unwrap();
+    /// let account = "Assets:Bank".parse().unwrap();
+    /// let date = "2023-02-01".parse().unwrap();
+    /// let observed = Amount { value: 990.0, currency: "CHF".parse().unwrap() };
+    ///
+    /// let BalanceSuggestion::Differs { delta } = beancount.suggest_balance(&account, date, &observed) else {
+    ///     unreachable!()
+    /// };
+    /// assert_eq!(delta.value, -10.0);
+    /// let fees = BalanceAdjustment::Transaction("Expenses:Fees".parse().unwrap());
+    /// beancount.directives.push(Directive::balance_adjustment(date, account.clone(), delta, fees));
+    /// beancount.directives.push(Directive::balance(date, account.clone(), observed.clone()));
+    /// assert!(beancount.verify_balances().is_empty());
+    /// assert_eq!(beancount.suggest_balance(&account, date, &observed), BalanceSuggestion::Matches);
+    /// ```
+    #[must_use]
+    pub fn suggest_balance(
+        &self,
+        account: &Account,
+        date: Date,
+        observed: &Amount<D>,
+    ) -> BalanceSuggestion<D>
+    where
+        D: Evaluate,
+    {
+        suggestion::suggest_balance(self, account, date, observed)
+    }
+
+    /// Returns the content of the file as an indented tree, intended for humans and snapshot tests
+    ///
+    /// The options, includes, directives (see [`Directive::debug_tree`]) and warnings are written one per line,
+    /// in the order of the file.
+    ///
+    /// # Example
+    /// ```
+    /// # use beancount_parser::BeancountFile;
+    /// let input = "option \"title\" \"Demo\"\n2023-05-20 open Assets:Cash CHF";
+    /// let file: BeancountFile<f64> = input.parse().unwrap();
+    /// assert_eq!(
+    ///     file.debug_tree(),
+    ///     "option title \"Demo\"\n2023-05-20 open\n  account: Assets:Cash\n  currencies: CHF\n"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn debug_tree(&self) -> String
+    where
+        D: Display,
+    {
+        debug_tree::FileTree(self).to_string()
+    }
+
+    /// Returns every posting with its implicit fields resolved
+    ///
+    /// It is a flat view of the postings, as needed to export or analyze them:
+    /// * the amount of the posting without amount is interpolated (once per currency needed to balance the transaction)
+    /// * the cost without date is dated at the date of the transaction
+    /// * the cost without amount of a posting adding units (like `10 HOOL {}`) is interpolated
+    /// * the tags, links, payee and narration of the transaction are copied to each posting
+    /// * the metadata of the transaction completes the metadata of each posting
+    /// * the `pad` directives are expanded into postings (see [`ResolveOptions::expand_pads`])
+    ///
+    /// A transaction with more than one posting without amount cannot be resolved. It is reported
+    /// in [`ResolvedLedger::errors`] (with the code [`Code::TooManyMissingAmounts`]), and its postings are skipped.
+    /// So is a transaction whose cost cannot be interpolated (with the code [`Code::UnresolvableCost`]).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::{BeancountFile, ResolveOptions};
+    /// let input = r#"
+    /// 2023-01-01 pad Assets:Checking Equity:Opening-Balances
+    /// 2023-01-02 balance Assets:Checking 100 CHF
+    /// 2023-01-10 * "Coffee" #morning
+    ///   Expenses:Food   4 CHF
+    ///   Assets:Checking
+    /// "#;
+    /// let beancount: BeancountFile<f64> = input.parse().unwrap();
+    /// let ledger = beancount.resolve(&ResolveOptions::default());
+    /// assert!(ledger.errors.is_empty());
+    /// let postings: Vec<_> = ledger.postings.iter()
+    ///     .map(|p| (p.date.to_string(), p.account.as_str(), p.amount.value, p.flag))
+    ///     .collect();
+    /// assert_eq!(postings, [
+    ///     ("2023-01-01".into(), "Assets:Checking", 100.0, Some('P')),
+    ///     ("2023-01-01".into(), "Equity:Opening-Balances", -100.0, Some('P')),
+    ///     ("2023-01-10".into(), "Expenses:Food", 4.0, Some('*')),
+    ///     ("2023-01-10".into(), "Assets:Checking", -4.0, Some('*')),
+    /// ]);
+    /// assert!(ledger.postings[3].tags.contains("morning"));
+    /// ```
+    #[must_use]
+    pub fn resolve(&self, options: &ResolveOptions) -> ResolvedLedger<D>
+    where
+        D: Evaluate,
+    {
+        resolve::resolve(self, options)
+    }
+
+    /// Rewrite the file in a canonical form
+    ///
+    /// By default, the directives are sorted in the order they take effect, and the payees and narrations are trimmed.
+    /// See [`NormalizeOptions`] for the other normalizations. Normalizing a normalized file leaves it unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::{BeancountFile, NormalizeOptions};
+    /// let input = r#"
+    /// 2023-05-21 close Assets:Cash
+    /// 2023-05-20 * " Coffee "
+    ///   Expenses:Food  3 CHF
+    ///   Assets:Cash
+    /// 2023-05-20 open Assets:Cash
+    /// 2023-05-20 open Assets:Cash
+    /// "#;
+    /// let mut beancount: BeancountFile<f64> = input.parse().unwrap();
+    /// beancount.normalize(&NormalizeOptions::default().remove_duplicates(true));
+    /// let lines: Vec<u32> = beancount.directives.iter().map(|d| d.line_number).collect();
+    /// assert_eq!(lines, [6, 3, 2]);
+    /// ```
+    pub fn normalize(&mut self, options: &NormalizeOptions)
+    where
+        D: PartialEq,
+    {
+        normalize::normalize(self, options);
+    }
+
+    /// Shift all the dates of the file by the number of days (negative to shift them to the past)
+    ///
+    /// That is the dates of the directives, the dates of the costs and the dates of the `custom` directives values.
+    /// The shifted dates are valid dates (a day past the end of its month counts as a day of the next month),
+    /// and a date that would be out of the range supported by [`Date`] is clamped to the first or last day of that range.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::{BeancountFile, Date};
+    /// let input = r#"
+    /// 2023-12-30 * "Stock"
+    ///   Assets:Stock   1 HOOL {100 USD, 2023-12-30}
+    ///   Assets:Cash
+    /// "#;
+    /// let mut beancount: BeancountFile<f64> = input.parse().unwrap();
+    /// beancount.shift_dates(3);
+    /// assert_eq!(beancount.directives[0].date, Date::new(2024, 1, 2));
+    /// ```
+    pub fn shift_dates(&mut self, days: i32) {
+        anonymize::shift_dates(self, days);
+    }
 
-/// Main struct representing a parsed beancount file.
-///
-/// To get an instance of this, use [`parse`].
-///
-/// For an example, look at the root crate documentation.
-#[derive(Debug, Clone)]
-#[non_exhaustive]
-pub struct BeancountFile<D> {
-    /// List of beancount options
+    /// Multiply the amounts of the file by the factor
     ///
-    /// See: <https://beancount.github.io/docs/beancount_language_syntax.html#options>
-    pub options: Vec<BeanOption>,
-    /// Paths of include directives
+    /// That is the amounts of the postings, their total prices (`@@`), and the amounts and tolerances of the `balance` assertions.
+    /// The per-unit costs and prices, and the `price` directives, are rates that are left unchanged.
+    /// So the transactions balanced before remain balanced, and the balance assertions keep passing,
+    /// up to the rounding of the number type.
+    /// Like for any transaction, such a rounding residual is booked to the `account_rounding` account (if the option is set)
+    /// when computing the [balances](Self::balances).
     ///
-    /// See: <https://beancount.github.io/docs/beancount_language_syntax.html#includes>
-    pub includes: Vec<PathBuf>,
-    /// List of [`Directive`] found in the file
-    pub directives: Vec<Directive<D>>,
-}
-
-impl<D> Default for BeancountFile<D> {
-    fn default() -> Self {
-        Self {
-            options: Vec::new(),
-            includes: Vec::new(),
-            directives: Vec::new(),
-        }
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::BeancountFile;
+    /// let input = r#"
+    /// 2023-01-01 * "Salary"
+    ///   Assets:Bank     1000 CHF
+    ///   Income:Salary  -1000 CHF
+    /// 2023-01-02 balance Assets:Bank 1000 CHF
+    /// "#;
+    /// let mut beancount: BeancountFile<f64> = input.parse().unwrap();
+    /// beancount.scale_amounts(&0.5);
+    /// assert_eq!(beancount.balances(None)["Assets:Bank"].get("CHF"), Some(&500.0));
+    /// assert!(beancount.verify_balances().is_empty());
+    /// ```
+    pub fn scale_amounts(&mut self, factor: &D)
+    where
+        D: Evaluate,
+    {
+        anonymize::scale_amounts(self, factor);
     }
-}
 
-impl<D> BeancountFile<D> {
-    /// Returns the first value found for the option
+    /// Replace the names of the file by pseudonyms, to share a file without revealing its content
     ///
-    /// If the option is declared multiple times, this function returns the first one found.
+    /// The pseudonyms are derived from the seed and the name by a keyed hash:
+    /// the same name gets the same pseudonym for the same seed (on every platform and run, and in every file),
+    /// and distinct names get distinct pseudonyms. The pseudonyms are valid names (like `Expenses:Dorapikemu`),
+    /// so that the file still parses and balances. See [`PseudonymizeOptions`] for what is replaced (and what is kept).
     ///
-    /// See [`Self::options`] to get all declared options.
+    /// Two names may have the same hash. Then, within a file, the name met last gets another pseudonym, which
+    /// depends on the names met before it: only in this unlikely case, a name may get different pseudonyms
+    /// in different files.
     ///
-    /// Syntax: <https://beancount.github.io/docs/beancount_language_syntax.html#options>
+    /// The hash is not cryptographic: keep the seed private, as short names may be guessed by someone knowing it.
     ///
     /// # Example
     ///
     /// ```
-    /// use beancount_parser::BeancountFile;
+    /// use beancount_parser::{BeancountFile, DirectiveContent, PseudonymizeOptions};
     /// let input = r#"
-    /// option "favorite_color" "blue"
-    /// option "operating_currency" "CHF"
-    /// option "operating_currency" "PLN"
+    /// 2023-01-01 * "Employer" "Salary"
+    ///   Assets:Bank:Checking  1000 CHF
+    ///   Income:Salary
     /// "#;
-    /// let beancount: BeancountFile<f64> = input.parse().unwrap();
-    /// assert_eq!(beancount.option("favorite_color"), Some("blue"));
-    /// assert_eq!(beancount.option("operating_currency"), Some("CHF"));
-    /// assert_eq!(beancount.option("foo"), None);
+    /// let mut beancount: BeancountFile<f64> = input.parse().unwrap();
+    /// beancount.pseudonymize(42, &PseudonymizeOptions::default());
+    /// let DirectiveContent::Transaction(trx) = &beancount.directives[0].content else { unreachable!() };
+    /// assert_ne!(trx.payee.as_deref(), Some("Employer"));
+    /// let account = trx.postings[0].account.as_str();
+    /// assert!(account.starts_with("Assets:"));
+    /// assert_eq!(account.split(':').count(), 3);
     /// ```
-    #[must_use]
-    pub fn option(&self, key: &str) -> Option<&str> {
-        self.options
-            .iter()
-            .find(|opt| opt.name == key)
-            .map(|opt| &opt.value[..])
+    pub fn pseudonymize(&mut self, seed: u64, options: &PseudonymizeOptions) {
+        anonymize::pseudonymize(self, seed, options);
     }
 }
 
@@ -229,6 +1413,14 @@ impl<D> Extend<Entry<D>> for BeancountFile<D> {
                 Entry::Directive(d) => self.directives.push(d),
                 Entry::Option(o) => self.options.push(o),
                 Entry::Include(p) => self.includes.push(p),
+                Entry::Plugin(p) => self.plugins.push(p),
+                // The tags and metadata are already added to the directives
+                Entry::PushTag(_)
+                | Entry::PopTag(_)
+                | Entry::PushMeta(..)
+                | Entry::PopMeta(_)
+                | Entry::Ignored(_) => {}
+                Entry::Warning(w) => self.warnings.push(w),
             }
         }
     }
@@ -267,7 +1459,12 @@ impl<D> FromIterator<Entry<D>> for BeancountFile<D> {
 ///    }
 /// }
 /// ```
-#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields, bound(deserialize = "D: serde::Deserialize<'de>"))
+)]
+#[derive(Debug, Clone)]
 #[non_exhaustive]
 pub struct Directive<D> {
     /// Date of the directive
@@ -277,23 +1474,260 @@ pub struct Directive<D> {
     /// Metadata associated to the directive
     ///
     /// See the [`metadata`] module for more
+    #[cfg_attr(feature = "serde", serde(default))]
     pub metadata: metadata::Map<D>,
-    /// Line number where the directive was found in the input file
+    /// Line number where the directive was found in the input file (the line of its date)
+    ///
+    /// There is no column: a directive always starts at the beginning of its line.
+    /// It is `0` for a directive built by a program (see [`Directive::provenance`] for those).
+    /// See the [line numbers](crate#line-numbers) section for an example.
+    #[cfg_attr(feature = "serde", serde(default))]
     pub line_number: u32,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) metadata_duplicates: metadata::Duplicates<D>,
+    /// Keyword of the directive, if it was misspelled (rare, so it is boxed)
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) misspelled_keyword: Option<Box<MisspelledKeyword>>,
+    /// Indented lines of the directive that are neither metadata nor postings, reported by the iterator
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) invalid_lines: Option<Box<[InvalidLine]>>,
+    /// Origin of the directive, if it was built by a program
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) provenance: Option<Arc<Provenance>>,
+    /// Number of decimals of the literals of the amounts, as written in the file (see [`BeancountFile::display_context`])
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) decimals: display::Decimals,
+}
+
+/// The decimals of the amounts are ignored, as they only tell how the amounts were written (like the line numbers of the postings)
+impl<D: PartialEq> PartialEq for Directive<D> {
+    fn eq(&self, other: &Self) -> bool {
+        self.date == other.date
+            && self.content == other.content
+            && self.metadata == other.metadata
+            && self.line_number == other.line_number
+            && self.metadata_duplicates == other.metadata_duplicates
+            && self.misspelled_keyword == other.misspelled_keyword
+            && self.invalid_lines == other.invalid_lines
+            && self.provenance == other.provenance
+    }
+}
+
+impl<D> Directive<D> {
+    /// Directive built by a program, without metadata
+    pub(crate) fn new(date: Date, content: DirectiveContent<D>) -> Self {
+        Self {
+            date,
+            content,
+            metadata: metadata::Map::default(),
+            line_number: 0,
+            metadata_duplicates: metadata::Duplicates::default(),
+            misspelled_keyword: None,
+            invalid_lines: None,
+            provenance: None,
+            decimals: display::Decimals::default(),
+        }
+    }
+
+    /// Create a `balance` assertion of the account (without tolerance)
+    ///
+    /// See [`BeancountFile::suggest_balance`] for an example.
+    #[must_use]
+    pub fn balance(date: Date, account: Account, amount: Amount<D>) -> Self {
+        suggestion::balance(date, account, amount)
+    }
+
+    /// Create the directive changing the balance of the account by `delta`, before a `balance` assertion dated `date`
+    ///
+    /// It is a `pad` directive or a transaction flagged `!` (with the narration `Balance adjustment`),
+    /// depending on the `adjustment`. It is dated the day before `date`, so that the assertion accounts it.
+    /// The `delta` is typically the one of [`BalanceSuggestion::Differs`] (a `pad` does not need it).
+    ///
+    /// See [`BeancountFile::suggest_balance`] for an example.
+    #[must_use]
+    pub fn balance_adjustment(
+        date: Date,
+        account: Account,
+        delta: Amount<D>,
+        adjustment: BalanceAdjustment,
+    ) -> Self
+    where
+        D: Evaluate,
+    {
+        suggestion::adjustment(date, account, delta, adjustment)
+    }
+
+    /// Returns the values of all the occurrences of the metadata key, in declaration order
+    ///
+    /// The first one is the value found in [`Directive::metadata`].
+    /// See the [`metadata`] module for more about duplicate keys.
+    pub fn metadata_all<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> impl Iterator<Item = &'a metadata::Value<D>> {
+        metadata::all(&self.metadata, &self.metadata_duplicates, key)
+    }
+
+    /// Attach the document at `path` to the directive, with a `document` metadata key
+    ///
+    /// If the directive already has documents, the next numbered key is used (`document-2`, `document-3`, etc.).
+    /// See the [`attachment`] module for an example.
+    pub fn attach_document(&mut self, path: &str) {
+        attachment::attach(self, path);
+    }
+
+    /// Returns the paths of the documents attached to the directive, in the order of their metadata keys
+    ///
+    /// See the [`attachment`] module for an example.
+    #[must_use]
+    pub fn attached_documents(&self) -> Vec<&str> {
+        attachment::documents(self)
+    }
+
+    /// Attach a [`Provenance`] with the given label, located at the caller
+    ///
+    /// It is intended for the directives built by a program (like an importer),
+    /// so that the errors about them report their origin instead of a meaningless line number.
+    /// See [`Provenance`] for an example.
+    #[must_use]
+    #[track_caller]
+    pub fn with_provenance(mut self, label: impl Into<String>) -> Self {
+        self.provenance = Some(Arc::new(Provenance::new(label)));
+        self
+    }
+
+    /// Returns the origin of the directive, if it was built by a program (see [`Directive::with_provenance`])
+    #[must_use]
+    pub fn provenance(&self) -> Option<&Provenance> {
+        self.provenance.as_deref()
+    }
+
+    /// Returns the repeated metadata keys of the directive and of its postings
+    pub(crate) fn duplicates(&self) -> impl Iterator<Item = &metadata::Duplicate<D>> {
+        let postings: &[Posting<D>] = match &self.content {
+            DirectiveContent::Transaction(trx) => &trx.postings,
+            _ => &[],
+        };
+        self.metadata_duplicates
+            .iter()
+            .chain(postings.iter().flat_map(|p| p.metadata_duplicates.iter()))
+    }
+
+    /// Returns the directive as an indented tree, intended for humans and snapshot tests
+    ///
+    /// The first line is the date and the kind of directive, followed by one line per field,
+    /// metadata entry and posting. Missing fields are omitted, amounts are written with their currency,
+    /// and tags, links, currencies and metadata keys are sorted, so that the output is stable across runs.
+    /// The line number is not included, so that the tree does not change when unrelated lines are added.
+    ///
+    /// # Example
+    /// ```
+    /// # use beancount_parser::Directive;
+    /// let input = r#"2023-05-20 * "Coffee" #morning
+    ///   Expenses:Coffee  3 CHF
+    ///   Assets:Cash
+    ///     id: 42"#;
+    /// let directive: Directive<f64> = input.parse().unwrap();
+    /// assert_eq!(directive.debug_tree(), r#"2023-05-20 transaction
+    ///   flag: *
+    ///   narration: "Coffee"
+    ///   tags: #morning
+    ///   posting Expenses:Coffee
+    ///     amount: 3 CHF
+    ///   posting Assets:Cash
+    ///     id: 42
+    /// "#);
+    /// ```
+    #[must_use]
+    pub fn debug_tree(&self) -> String
+    where
+        D: Display,
+    {
+        debug_tree::DirectiveTree(self).to_string()
+    }
+
+    /// Removes and returns the tags and links found at the end of the posting lines
+    pub(crate) fn take_misplaced_tags(&mut self) -> Vec<transaction::MisplacedTag> {
+        let DirectiveContent::Transaction(trx) = &mut self.content else {
+            return Vec::new();
+        };
+        trx.postings
+            .iter_mut()
+            .filter_map(|p| p.misplaced_tags.take())
+            .flat_map(Vec::from)
+            .collect()
+    }
+
+    /// Removes and returns the tokens found on the posting lines that are not part of the postings
+    pub(crate) fn take_stray_tokens(&mut self) -> Vec<transaction::StrayToken> {
+        let DirectiveContent::Transaction(trx) = &mut self.content else {
+            return Vec::new();
+        };
+        trx.postings
+            .iter_mut()
+            .filter_map(|p| p.stray_tokens.take())
+            .flat_map(Vec::from)
+            .collect()
+    }
+
+    /// Removes and returns the costs and prices written in a syntax that is not supported
+    pub(crate) fn take_unsupported_costs(&mut self) -> Vec<transaction::UnsupportedCost> {
+        let DirectiveContent::Transaction(trx) = &mut self.content else {
+            return Vec::new();
+        };
+        trx.postings
+            .iter_mut()
+            .filter_map(|p| p.unsupported_cost.take())
+            .map(|cost| *cost)
+            .collect()
+    }
+
+    /// Removes and returns the indented lines that are neither metadata nor postings
+    pub(crate) fn take_invalid_lines(&mut self) -> Vec<InvalidLine> {
+        self.invalid_lines.take().map(Vec::from).unwrap_or_default()
+    }
 }
 
-impl<D: Decimal> FromStr for Directive<D> {
+impl<D: Number> FromStr for Directive<D> {
     type Err = Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match all_consuming(directive)(Span::new(s)).finish() {
-            Ok((_, d)) => Ok(d),
-            Err(err) => Err(Error::new(s, err.input)),
+        match all_consuming(terminated(directive, many0_count(empty_line)))(span(s)).finish() {
+            Ok((_, mut d)) => {
+                if let Some(cost) = d.take_unsupported_costs().first() {
+                    return Err(cost.error(s));
+                }
+                match d.take_misplaced_tags().first() {
+                    None => match d.misspelled_keyword.take() {
+                        None => match d.take_stray_tokens().first() {
+                            None => match d.take_invalid_lines().first() {
+                                None => Ok(d),
+                                Some(line) => Err(line.error(s)),
+                            },
+                            Some(token) => Err(token.error(s)),
+                        },
+                        Some(keyword) => Err(Error::at(
+                            s,
+                            keyword.offset,
+                            keyword.line_number,
+                            Code::MisspelledKeyword,
+                        )
+                        .with_hint(keyword.hint())),
+                    },
+                    Some(tag) => Err(tag.error(s)),
+                }
+            }
+            Err(err) => Err(Error::from_nom(s, &err)),
         }
     }
 }
 
 /// Directive specific content
 #[allow(missing_docs)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 #[derive(Debug, Clone, PartialEq)]
 #[non_exhaustive]
 pub enum DirectiveContent<D> {
@@ -305,35 +1739,200 @@ pub enum DirectiveContent<D> {
     Pad(Pad),
     Commodity(Currency),
     Event(Event),
+    Note(Note),
+    Document(Document),
+    Query(Query),
+    Custom(Custom<D>),
+}
+
+impl<D> DirectiveContent<D> {
+    /// Call `f` for each account referenced by the directive content
+    pub(crate) fn for_each_account<'a>(&'a self, mut f: impl FnMut(&'a Account)) {
+        match self {
+            DirectiveContent::Transaction(trx) => trx.postings.iter().for_each(|p| f(&p.account)),
+            DirectiveContent::Balance(balance) => f(&balance.account),
+            DirectiveContent::Open(open) => f(&open.account),
+            DirectiveContent::Close(close) => f(&close.account),
+            DirectiveContent::Note(note) => f(&note.account),
+            DirectiveContent::Document(document) => f(&document.account),
+            DirectiveContent::Pad(pad) => {
+                f(&pad.account);
+                f(&pad.source_account);
+            }
+            DirectiveContent::Custom(custom) => custom.values.iter().for_each(|value| {
+                if let CustomValue::Account(account) = value {
+                    f(account);
+                }
+            }),
+            DirectiveContent::Price(_)
+            | DirectiveContent::Commodity(_)
+            | DirectiveContent::Event(_)
+            | DirectiveContent::Query(_) => (),
+        }
+    }
+
+    /// Call `f` for each amount of the directive content
+    pub(crate) fn for_each_amount<'a>(&'a self, mut f: impl FnMut(&'a Amount<D>)) {
+        match self {
+            DirectiveContent::Transaction(trx) => {
+                for posting in &trx.postings {
+                    let cost = posting.cost.as_ref().and_then(|c| c.amount.as_ref());
+                    let price = posting.price.as_ref().map(|price| match price {
+                        PostingPrice::Unit(amount) | PostingPrice::Total(amount) => amount,
+                    });
+                    posting
+                        .amount
+                        .iter()
+                        .chain(cost)
+                        .chain(price)
+                        .for_each(&mut f);
+                }
+            }
+            DirectiveContent::Price(price) => f(&price.amount),
+            DirectiveContent::Balance(balance) => f(&balance.amount),
+            DirectiveContent::Custom(custom) => custom.values.iter().for_each(|value| {
+                if let CustomValue::Amount(amount) = value {
+                    f(amount);
+                }
+            }),
+            DirectiveContent::Open(_)
+            | DirectiveContent::Close(_)
+            | DirectiveContent::Commodity(_)
+            | DirectiveContent::Pad(_)
+            | DirectiveContent::Event(_)
+            | DirectiveContent::Note(_)
+            | DirectiveContent::Document(_)
+            | DirectiveContent::Query(_) => {}
+        }
+    }
+
+    /// Call `f` for each currency referenced by the directive content
+    ///
+    /// A currency may be passed more than once.
+    pub(crate) fn for_each_currency<'a>(&'a self, mut f: impl FnMut(&'a Currency)) {
+        match self {
+            DirectiveContent::Transaction(trx) => {
+                for posting in &trx.postings {
+                    let cost = posting.cost.as_ref().and_then(|c| c.amount.as_ref());
+                    let price = posting.price.as_ref().map(|price| match price {
+                        PostingPrice::Unit(amount) | PostingPrice::Total(amount) => amount,
+                    });
+                    posting
+                        .amount
+                        .iter()
+                        .chain(cost)
+                        .chain(price)
+                        .for_each(|amount| f(&amount.currency));
+                }
+            }
+            DirectiveContent::Price(price) => {
+                f(&price.currency);
+                f(&price.amount.currency);
+            }
+            DirectiveContent::Balance(balance) => f(&balance.amount.currency),
+            DirectiveContent::Open(open) => open.currencies.iter().for_each(f),
+            DirectiveContent::Commodity(currency) => f(currency),
+            DirectiveContent::Custom(custom) => custom.values.iter().for_each(|value| {
+                if let CustomValue::Amount(amount) = value {
+                    f(&amount.currency);
+                }
+            }),
+            DirectiveContent::Close(_)
+            | DirectiveContent::Pad(_)
+            | DirectiveContent::Event(_)
+            | DirectiveContent::Note(_)
+            | DirectiveContent::Document(_)
+            | DirectiveContent::Query(_) => {}
+        }
+    }
 }
 
-type Span<'a> = nom_locate::LocatedSpan<&'a str>;
+type Span<'a> = nom_locate::LocatedSpan<&'a str, amount::Literals>;
+
+/// Span of the whole `input`
+///
+/// The number literals are parsed with the default options, unless the parser sets them (like [`entry_with_options`]).
+fn span(input: &str) -> Span<'_> {
+    Span::new_extra(input, amount::Literals::default())
+}
 type IResult<'a, O> = nom::IResult<Span<'a>, O>;
 
 /// Entry in the beancount syntax
 ///
-/// It is more general than `Directive` as an entry can also be option or an include.
-#[allow(missing_docs)]
+/// It is more general than `Directive` as an entry can also be option, an include, a plugin
+/// or an operation of the tag or metadata stack.
+// Most entries are directives, boxing them would only add an allocation per directive
+#[allow(missing_docs, clippy::large_enum_variant)]
 #[non_exhaustive]
 #[derive(Debug, Clone)]
 pub enum Entry<D> {
     Directive(Directive<D>),
     Option(BeanOption),
     Include(PathBuf),
+    Plugin(Plugin),
+    /// Tag pushed with `pushtag`
+    ///
+    /// It is informative: the parser already adds the pushed tags to the transactions that follow, until they are popped.
+    PushTag(Tag),
+    /// Tag popped with `poptag` (see [`Entry::PushTag`])
+    PopTag(Tag),
+    /// Metadata pushed with `pushmeta`
+    ///
+    /// It is informative: the parser already adds the pushed metadata to the directives that follow, until it is popped.
+    /// A directive declaring the same key keeps its own value.
+    PushMeta(metadata::Key, metadata::Value<D>),
+    /// Metadata key popped with `popmeta` (see [`Entry::PushMeta`])
+    PopMeta(metadata::Key),
+    /// Line ignored by the parser, only emitted with [`ParseOptions::capture_ignored_lines`]
+    Ignored(IgnoredLine),
+    Warning(Warning),
 }
 
 enum RawEntry<D> {
     Directive(Directive<D>),
     Option(BeanOption),
-    Include(PathBuf),
+    /// Path, offset and line number of an `include` directive
+    Include(PathBuf, usize, u32),
+    Plugin(Plugin),
+    /// Directive whose date is followed by a time (which is invalid)
+    TimedDirective(Directive<D>, Time),
     PushTag(Tag),
-    PopTag(Tag),
+    /// Tag and line number of a `poptag` directive
+    PopTag(Tag, u32),
+    PushMeta(metadata::Key, metadata::Value<D>),
+    PopMeta(metadata::Key),
+    /// Kind, offset and line number of a line ignored by the parser
+    Ignored(LineKind, usize, u32),
+    /// Content skipped by the parser (like a directive outside of [`ParseOptions::date_range`])
     Comment,
 }
 
+/// Time found after the date of a directive
+struct Time {
+    offset: usize,
+    line_number: u32,
+    /// Text to remove, including the separator from the date (like ` 12:30:00`)
+    text: String,
+}
+
 /// An beancount option
 ///
+/// Any name and value is accepted, without validation, so that the options of newer beancount versions can be parsed.
+/// Well-known options include:
+///
+/// * `title`: title of the ledger
+/// * `operating_currency`: currency in which the reports are rendered (may be given more than once)
+/// * `name_assets`, `name_liabilities`, `name_equity`, `name_income`, `name_expenses`: names of the root accounts
+/// * `account_previous_balances`, `account_previous_earnings`, `account_current_earnings`, etc.: accounts used when closing a period
+/// * `booking_method`: default booking method of the accounts (like `FIFO`)
+/// * `render_commas`: whether the thousands are separated (see [`BeancountFile::amount_display`])
+///
 /// See: <https://beancount.github.io/docs/beancount_language_syntax.html#options>
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
 #[derive(Debug, Clone)]
 #[non_exhaustive]
 pub struct BeanOption {
@@ -343,72 +1942,296 @@ pub struct BeanOption {
     pub value: String,
 }
 
-fn entry<D: Decimal>(input: Span<'_>) -> IResult<'_, RawEntry<D>> {
-    alt((
-        directive.map(RawEntry::Directive),
-        option.map(|(name, value)| RawEntry::Option(BeanOption { name, value })),
-        include.map(|p| RawEntry::Include(p)),
-        tag_stack_operation,
-        line.map(|()| RawEntry::Comment),
-    ))(input)
+/// A beancount plugin, to run on the directives of the file
+///
+/// # Example
+/// ```
+/// use beancount_parser::BeancountFile;
+/// let input = r#"
+/// plugin "beancount.plugins.auto_accounts"
+/// plugin "beancount.plugins.check_closing" "strict"
+/// "#;
+/// let beancount: BeancountFile<f64> = input.parse().unwrap();
+/// assert_eq!(beancount.plugins[0].name, "beancount.plugins.auto_accounts");
+/// assert_eq!(beancount.plugins[0].config, None);
+/// assert_eq!(beancount.plugins[1].config.as_deref(), Some("strict"));
+/// ```
+///
+/// See: <https://beancount.github.io/docs/beancount_language_syntax.html#plugins>
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Plugin {
+    /// Name of the plugin, which is the python module implementing it (like `beancount.plugins.auto_accounts`)
+    pub name: String,
+    /// Configuration string passed to the plugin, if any
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub config: Option<String>,
+}
+
+/// Parse the entry starting at the beginning of the input, chosen by the [`LineKind`] of its first line
+fn entry<D: Number>(input: Span<'_>) -> IResult<'_, RawEntry<D>> {
+    let kind = LineKind::of(input.fragment().lines().next().unwrap_or_default());
+    let (rest, entry) = match kind {
+        LineKind::Directive => opt(alt((
+            timed_directive.map(|(directive, time)| RawEntry::TimedDirective(directive, time)),
+            directive.map(RawEntry::Directive),
+        )))(input)?,
+        LineKind::FileEntry => opt(alt((
+            option.map(|(name, value)| RawEntry::Option(BeanOption { name, value })),
+            include,
+            plugin.map(RawEntry::Plugin),
+            tag_stack_operation,
+            metadata_stack_operation,
+        )))(input)?,
+        LineKind::Comment | LineKind::Ignorable | LineKind::Blank | LineKind::Unrecognized => {
+            (input, None)
+        }
+    };
+    match entry {
+        Some(entry) => Ok((rest, entry)),
+        None => ignored_line(input, kind),
+    }
+}
+
+/// Consume a line ignored by the parser
+///
+/// A directive or file entry that could not be parsed is [`LineKind::Unrecognized`].
+fn ignored_line<D>(input: Span<'_>, kind: LineKind) -> IResult<'_, RawEntry<D>> {
+    let kind = match kind {
+        LineKind::Directive | LineKind::FileEntry => LineKind::Unrecognized,
+        kind => kind,
+    };
+    let (rest, ()) = line(input)?;
+    Ok((
+        rest,
+        RawEntry::Ignored(kind, input.location_offset(), input.location_line()),
+    ))
+}
+
+fn entry_with_options<'a, D: Number>(
+    options: &ParseOptions,
+) -> impl FnMut(Span<'a>) -> IResult<'a, RawEntry<D>> {
+    let date_range = options.date_range.clone();
+    let keep_declarations = options.keep_declarations;
+    let literals = amount::Literals::from(options);
+    move |input| {
+        if let Some(range) = &date_range {
+            if let Ok((input, ())) = skipped_directive(input, range, keep_declarations) {
+                return Ok((input, RawEntry::Comment));
+            }
+        }
+        entry(input.map_extra(|_| literals))
+    }
+}
+
+/// Consume a directive dated outside of the range, with its indented lines, without parsing its content
+fn skipped_directive<'a>(
+    input: Span<'a>,
+    range: &Range<Date>,
+    keep_declarations: bool,
+) -> IResult<'a, ()> {
+    let (input, _) = verify(date::parse, |date| !range.contains(date))(input)?;
+    let (input, _) = space1(input)?;
+    if keep_declarations {
+        not(peek(alt((tag("open"), tag("close"), tag("commodity")))))(input)?;
+    }
+    let (input, _) = not_line_ending(input)?;
+    let (input, _) = alt((line_ending, eof))(input)?;
+    let (input, _) = many0_count(tuple((space1, not_line_ending, alt((line_ending, eof)))))(input)?;
+    Ok((input, ()))
 }
 
-fn directive<D: Decimal>(input: Span<'_>) -> IResult<'_, Directive<D>> {
+fn directive<D: Number>(input: Span<'_>) -> IResult<'_, Directive<D>> {
     let (input, position) = position(input)?;
     let (input, date) = date::parse(input)?;
     let (input, _) = cut(space1)(input)?;
-    let (input, (content, metadata)) = alt((
-        map(transaction::parse, |(t, m)| {
-            (DirectiveContent::Transaction(t), m)
-        }),
-        tuple((
-            terminated(
-                alt((
-                    map(
-                        preceded(tag("price"), cut(preceded(space1, amount::price))),
-                        DirectiveContent::Price,
-                    ),
-                    map(
-                        preceded(tag("balance"), cut(preceded(space1, account::balance))),
-                        DirectiveContent::Balance,
-                    ),
-                    map(
-                        preceded(tag("open"), cut(preceded(space1, account::open))),
-                        DirectiveContent::Open,
-                    ),
-                    map(
-                        preceded(tag("close"), cut(preceded(space1, account::close))),
-                        DirectiveContent::Close,
-                    ),
-                    map(
-                        preceded(tag("pad"), cut(preceded(space1, account::pad))),
-                        DirectiveContent::Pad,
-                    ),
-                    map(
-                        preceded(tag("commodity"), cut(preceded(space1, amount::currency))),
-                        DirectiveContent::Commodity,
-                    ),
-                    map(
-                        preceded(tag("event"), cut(preceded(space1, event::parse))),
-                        DirectiveContent::Event,
-                    ),
-                )),
-                end_of_line,
+    directive_after_date(input, date, position.location_line())
+}
+
+/// Parse a directive whose date is followed by a time (like `2023-05-22 12:30:00 * "Coffee"`)
+///
+/// To avoid false positives, the time is only recognized if the rest of the line is a valid directive.
+fn timed_directive<D: Number>(input: Span<'_>) -> IResult<'_, (Directive<D>, Time)> {
+    let (input, position) = position(input)?;
+    let (input, date) = date::parse(input)?;
+    let (input, time) = recognize(tuple((alt((tag("T"), space1)), time)))(input)?;
+    let (input, _) = space1(input)?;
+    let (input, directive) = directive_after_date(input, date, position.location_line())?;
+    let time = Time {
+        offset: time.location_offset(),
+        line_number: time.location_line(),
+        text: (*time.fragment()).into(),
+    };
+    Ok((input, (directive, time)))
+}
+
+/// Time of the day, like `9:30`, `12:30:00` or `12:30:00.123`
+fn time(input: Span<'_>) -> IResult<'_, ()> {
+    let two_digits = |max: u8| {
+        verify(
+            map_res(
+                take_while_m_n(2, 2, |c: char| c.is_ascii_digit()),
+                |s: Span<'_>| s.fragment().parse::<u8>(),
             ),
-            metadata::parse,
-        )),
+            move |n| *n <= max,
+        )
+    };
+    let hour = verify(
+        map_res(
+            take_while_m_n(1, 2, |c: char| c.is_ascii_digit()),
+            |s: Span<'_>| s.fragment().parse::<u8>(),
+        ),
+        |n| *n <= 23,
+    );
+    let (input, _) = tuple((
+        hour,
+        char(':'),
+        two_digits(59),
+        opt(tuple((
+            char(':'),
+            two_digits(60),
+            opt(tuple((char('.'), digit1))),
+        ))),
     ))(input)?;
+    Ok((input, ()))
+}
+
+fn directive_after_date<D: Number>(
+    input: Span<'_>,
+    date: Date,
+    line_number: u32,
+) -> IResult<'_, Directive<D>> {
+    let start = input.map_extra(amount::Literals::without_amounts);
+    let (input, ((content, (metadata, metadata_duplicates), invalid_lines), misspelled_keyword)) =
+        alt((
+            misspelled_directive,
+            map(transaction::parse, |(t, m, invalid_lines)| {
+                ((DirectiveContent::Transaction(t), m, invalid_lines), None)
+            }),
+            map(with_metadata(directive_content), |parsed| (parsed, None)),
+        ))(start)?;
+    let decimals = input.extra.amounts;
+    let input = without_trailing_lines(start, input);
     Ok((
         input,
         Directive {
             date,
             content,
             metadata,
-            line_number: position.location_line(),
+            line_number,
+            metadata_duplicates,
+            misspelled_keyword,
+            invalid_lines: (!invalid_lines.is_empty()).then(|| invalid_lines.into()),
+            provenance: None,
+            decimals,
         },
     ))
 }
 
+/// Returns the input following the directive parsed from `start` to `rest`, without its trailing blank and comment lines
+///
+/// The parsing of the metadata and postings consumes the blank and comment lines following them.
+/// Those at the end are given back, so that they are entries of the file (see [`LineKind`]) rather than part of the directive.
+fn without_trailing_lines<'a>(start: Span<'a>, rest: Span<'a>) -> Span<'a> {
+    let consumed = &start.fragment()[..rest.location_offset() - start.location_offset()];
+    let header = consumed
+        .find('\n')
+        .map_or(consumed.len(), |index| index + 1);
+    let mut end = consumed.len();
+    for line in consumed[header..].split_inclusive('\n').rev() {
+        if !matches!(LineKind::of(line), LineKind::Blank | LineKind::Comment) {
+            break;
+        }
+        end -= line.len();
+    }
+    start.take_split(end).0
+}
+
+type ContentWithMetadata<D> = (DirectiveContent<D>, metadata::Block<D>, Vec<InvalidLine>);
+
+/// Parse a directive whose keyword is misspelled (like `Open` or `blance`), as if it had the suggested keyword
+fn misspelled_directive<D: Number>(
+    input: Span<'_>,
+) -> IResult<'_, (ContentWithMetadata<D>, Option<Box<MisspelledKeyword>>)> {
+    let (input, misspelled) = keyword::misspelled(input)?;
+    let (input, parsed) = match misspelled.keyword {
+        "txn" => map(transaction::parse_after_keyword, |(t, m, invalid_lines)| {
+            (DirectiveContent::Transaction(t), m, invalid_lines)
+        })(input)?,
+        keyword => with_metadata(|input| content_after_keyword(keyword, input))(input)?,
+    };
+    Ok((input, (parsed, Some(Box::new(misspelled)))))
+}
+
+fn directive_content<D: Number>(input: Span<'_>) -> IResult<'_, DirectiveContent<D>> {
+    let (rest, keyword) = alpha1(input)?;
+    content_after_keyword(keyword.fragment(), rest)
+}
+
+/// Parse the content of the directive following its keyword
+fn content_after_keyword<'a, D: Number>(
+    keyword: &str,
+    input: Span<'a>,
+) -> IResult<'a, DirectiveContent<D>> {
+    match keyword {
+        "price" => map(
+            cut(preceded(space1, amount::price)),
+            DirectiveContent::Price,
+        )(input),
+        "balance" => map(
+            cut(preceded(space1, account::balance)),
+            DirectiveContent::Balance,
+        )(input),
+        "open" => map(cut(preceded(space1, account::open)), DirectiveContent::Open)(input),
+        "close" => map(
+            cut(preceded(space1, account::close)),
+            DirectiveContent::Close,
+        )(input),
+        "pad" => map(cut(preceded(space1, account::pad)), DirectiveContent::Pad)(input),
+        "commodity" => map(
+            cut(preceded(space1, amount::currency)),
+            DirectiveContent::Commodity,
+        )(input),
+        "event" => map(cut(preceded(space1, event::parse)), DirectiveContent::Event)(input),
+        "note" => map(cut(preceded(space1, note::parse)), DirectiveContent::Note)(input),
+        "document" => map(
+            cut(preceded(space1, document::parse)),
+            DirectiveContent::Document,
+        )(input),
+        "query" => map(cut(preceded(space1, query::parse)), DirectiveContent::Query)(input),
+        "custom" => map(
+            cut(preceded(space1, custom::parse)),
+            DirectiveContent::Custom,
+        )(input),
+        _ => Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::Tag,
+        ))),
+    }
+}
+
+/// Parse the end of the directive line, followed by the indented metadata lines
+///
+/// Every directive kind (except transactions, which have postings after the metadata) goes through it,
+/// so that metadata is consumed the same way for all of them.
+/// The indented lines that are not metadata are skipped and returned, so that no line of the directive goes unnoticed.
+fn with_metadata<'a, O, D: Number>(
+    content: impl FnMut(Span<'a>) -> IResult<'a, O>,
+) -> impl FnMut(Span<'a>) -> IResult<'a, (O, metadata::Block<D>, Vec<InvalidLine>)> {
+    map(
+        tuple((
+            terminated(content, cut(end_of_line)),
+            metadata::parse_skipping_invalid_lines,
+        )),
+        |(content, (block, invalid_lines))| (content, block, invalid_lines),
+    )
+}
+
 fn option(input: Span<'_>) -> IResult<'_, (String, String)> {
     let (input, _) = tag("option")(input)?;
     let (input, key) = preceded(space1, string)(input)?;
@@ -417,16 +2240,53 @@ fn option(input: Span<'_>) -> IResult<'_, (String, String)> {
     Ok((input, (key, value)))
 }
 
-fn include(input: Span<'_>) -> IResult<'_, PathBuf> {
+fn include<D>(input: Span<'_>) -> IResult<'_, RawEntry<D>> {
+    let (input, position) = position(input)?;
     let (input, _) = tag("include")(input)?;
     let (input, path) = cut(delimited(space1, string, end_of_line))(input)?;
-    Ok((input, path.into()))
+    Ok((
+        input,
+        RawEntry::Include(
+            path.into(),
+            position.location_offset(),
+            position.location_line(),
+        ),
+    ))
+}
+
+fn plugin(input: Span<'_>) -> IResult<'_, Plugin> {
+    let (input, _) = tag("plugin")(input)?;
+    let (input, (name, config)) = cut(terminated(
+        tuple((preceded(space1, string), opt(preceded(space1, string)))),
+        end_of_line,
+    ))(input)?;
+    Ok((input, Plugin { name, config }))
 }
 
 fn tag_stack_operation<D>(input: Span<'_>) -> IResult<'_, RawEntry<D>> {
     alt((
         preceded(tuple((tag("pushtag"), space1)), transaction::parse_tag).map(RawEntry::PushTag),
-        preceded(tuple((tag("poptag"), space1)), transaction::parse_tag).map(RawEntry::PopTag),
+        tuple((
+            position,
+            preceded(tuple((tag("poptag"), space1)), transaction::parse_tag),
+        ))
+        .map(|(position, tag)| RawEntry::PopTag(tag, position.location_line())),
+    ))(input)
+}
+
+fn metadata_stack_operation<D: Number>(input: Span<'_>) -> IResult<'_, RawEntry<D>> {
+    alt((
+        preceded(
+            tuple((tag("pushmeta"), space1)),
+            cut(terminated(metadata::key_value, end_of_line)),
+        )
+        .map(|(key, value)| RawEntry::PushMeta(key, value)),
+        // Beancount writes the key followed by `:`, which is optional here
+        preceded(
+            tuple((tag("popmeta"), space1)),
+            cut(terminated(metadata::key, pair(opt(char(':')), end_of_line))),
+        )
+        .map(RawEntry::PopMeta),
     ))(input)
 }
 
@@ -454,25 +2314,91 @@ fn empty_line(input: Span<'_>) -> IResult<'_, ()> {
     end_of_line(input)
 }
 
+/// Indented line of a directive that is neither metadata nor a posting (like a posting with an invalid flag)
+///
+/// Without it, the directive would end before that line, and the line (with the ones following it)
+/// would be ignored like any other line that is not an entry.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct InvalidLine {
+    /// Content of the line, without the indentation
+    pub(crate) text: String,
+    pub(crate) line_number: u32,
+    pub(crate) offset: usize,
+}
+
+impl InvalidLine {
+    /// Returns the hint quoting the line
+    pub(crate) fn hint(&self) -> String {
+        format!("`{}` is neither metadata nor a posting", self.text)
+    }
+
+    /// Returns the error reporting it in the source
+    pub(crate) fn error(&self, source: &str) -> Error {
+        Error::at(
+            source,
+            self.offset,
+            self.line_number,
+            Code::InvalidIndentedLine,
+        )
+        .with_hint(self.hint())
+    }
+}
+
+/// Parse any indented line that is not empty
+///
+/// It must be tried after the parsers of the lines it may be (metadata, postings and empty lines).
+fn invalid_line(input: Span<'_>) -> IResult<'_, InvalidLine> {
+    let (input, _) = space1(input)?;
+    let (input, position) = position(input)?;
+    let (input, text) = verify(not_line_ending, |text: &Span<'_>| !text.is_empty())(input)?;
+    let (input, _) = alt((line_ending, eof))(input)?;
+    Ok((
+        input,
+        InvalidLine {
+            text: text.trim_end().into(),
+            line_number: position.location_line(),
+            offset: position.location_offset(),
+        },
+    ))
+}
+
 fn string(input: Span<'_>) -> IResult<'_, String> {
     let (input, _) = char('"')(input)?;
     let mut string = String::new();
     let take_data = take_while(|c: char| c != '"' && c != '\\');
-    let (mut input, mut part) = take_data(input)?;
-    while !part.fragment().is_empty() {
-        string.push_str(part.fragment());
+    let (mut input, part) = take_data(input)?;
+    string.push_str(part.fragment());
+    loop {
         let (new_input, escaped) =
             opt(alt((value('"', tag("\\\"")), value('\\', tag("\\\\")))))(input)?;
         let Some(escaped) = escaped else { break };
         string.push(escaped);
-        let (new_input, new_part) = take_data(new_input)?;
+        let (new_input, part) = take_data(new_input)?;
+        string.push_str(part.fragment());
         input = new_input;
-        part = new_part;
     }
     let (input, _) = char('"')(input)?;
     Ok((input, string))
 }
 
+/// Display adapter writing the string as a quoted beancount string, escaping quotes and backslashes
+///
+/// This is the inverse of [`string`].
+pub(crate) struct Quoted<'a>(pub(crate) &'a str);
+
+impl Display for Quoted<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_char('"')?;
+        for c in self.0.chars() {
+            if c == '"' || c == '\\' {
+                f.write_char('\\')?;
+            }
+            f.write_char(c)?;
+        }
+        f.write_char('"')
+    }
+}
+
 #[cfg(test)]
 type ChumskyError = chumsky::error::Simple<char>;
 
@@ -0,0 +1,147 @@
+/// Keywords of the entries that are not dated (like `option` or `pushtag`)
+const FILE_KEYWORDS: [&str; 7] = [
+    "option", "include", "plugin", "pushtag", "poptag", "pushmeta", "popmeta",
+];
+
+/// First characters of the lines that beancount ignores (like the headings of an org-mode file)
+const IGNORABLE: [char; 7] = ['*', ':', '!', '&', '#', '?', '%'];
+
+/// Kind of a line found outside of the directives, decided by its first characters
+///
+/// It is the only rule deciding how such a line is parsed:
+///
+/// | First characters                                          | Kind                        |
+/// |-----------------------------------------------------------|-----------------------------|
+/// | a digit                                                   | [`LineKind::Directive`]     |
+/// | `option`, `include`, `plugin`, `pushtag`, `poptag`, `pushmeta` or `popmeta` | [`LineKind::FileEntry`] |
+/// | `;` (possibly indented)                                   | [`LineKind::Comment`]       |
+/// | one of `*`, `:`, `!`, `&`, `#`, `?` or `%`                | [`LineKind::Ignorable`]     |
+/// | only whitespace                                           | [`LineKind::Blank`]         |
+/// | anything else                                             | [`LineKind::Unrecognized`]  |
+///
+/// In particular, a line starting with `#` is ignorable: it is never a tag (tags only follow a transaction header).
+///
+/// A directive or file entry that cannot be parsed is ignored as an [`LineKind::Unrecognized`] line
+/// (unless its keyword is recognized and its content is invalid, which is an error).
+/// See [`IgnoredLine`] to capture the ignored lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum LineKind {
+    /// A dated directive (like `2023-05-27 open Assets:Cash`)
+    Directive,
+    /// An entry that is not dated (like `option "title" "Ledger"` or `pushtag #trip`)
+    FileEntry,
+    /// A comment (like `; Groceries`)
+    Comment,
+    /// A line that beancount ignores (like an org-mode heading `* Expenses`, or `#comment`)
+    Ignorable,
+    /// A line made of whitespace only
+    Blank,
+    /// A line that is none of the above, or a directive or file entry that could not be parsed
+    ///
+    /// It is ignored with a warning (with code [`Code::UnrecognizedLine`](crate::Code::UnrecognizedLine)).
+    Unrecognized,
+}
+
+impl LineKind {
+    /// Returns the kind of the line
+    ///
+    /// Only the beginning of the line is looked at: the kind tells how the line is parsed,
+    /// not whether it is valid.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::LineKind;
+    /// assert_eq!(LineKind::of("2023-05-27 open Assets:Cash"), LineKind::Directive);
+    /// assert_eq!(LineKind::of("pushtag #trip"), LineKind::FileEntry);
+    /// assert_eq!(LineKind::of("#trip"), LineKind::Ignorable);
+    /// assert_eq!(LineKind::of("* Expenses"), LineKind::Ignorable);
+    /// assert_eq!(LineKind::of("Expenses"), LineKind::Unrecognized);
+    /// ```
+    #[must_use]
+    pub fn of(line: &str) -> Self {
+        let trimmed = line.trim_start();
+        if trimmed.trim_end().is_empty() {
+            Self::Blank
+        } else if trimmed.starts_with(';') {
+            Self::Comment
+        } else if trimmed.len() < line.len() {
+            Self::Unrecognized
+        } else if line.starts_with(|c: char| c.is_ascii_digit()) {
+            Self::Directive
+        } else if line.starts_with(IGNORABLE) {
+            Self::Ignorable
+        } else if FILE_KEYWORDS.iter().any(|keyword| {
+            line.strip_prefix(keyword)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\t', '\r', '\n']))
+        }) {
+            Self::FileEntry
+        } else {
+            Self::Unrecognized
+        }
+    }
+}
+
+/// Line ignored by the parser, emitted as [`Entry::Ignored`](crate::Entry::Ignored)
+///
+/// The lines are only emitted with [`ParseOptions::capture_ignored_lines`](crate::ParseOptions::capture_ignored_lines),
+/// so that a formatter can write them back as they are. The blank lines are never emitted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct IgnoredLine {
+    /// Content of the line, without the line ending
+    pub text: String,
+    /// Kind of the line
+    pub kind: LineKind,
+    /// Line number in the input
+    pub line_number: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::date("2023-05-27 open Assets:Cash", LineKind::Directive)]
+    #[case::invalid_date("2023-13-45 oops", LineKind::Directive)]
+    #[case::digit("1 thing", LineKind::Directive)]
+    #[case::option("option \"title\" \"Ledger\"", LineKind::FileEntry)]
+    #[case::include("include \"2023.beancount\"", LineKind::FileEntry)]
+    #[case::plugin("plugin \"beancount.plugins.auto\"", LineKind::FileEntry)]
+    #[case::pushtag("pushtag #trip", LineKind::FileEntry)]
+    #[case::poptag("poptag\t#trip", LineKind::FileEntry)]
+    #[case::pushmeta("pushmeta location: \"Paris\"", LineKind::FileEntry)]
+    #[case::popmeta("popmeta location:", LineKind::FileEntry)]
+    #[case::keyword_alone("pushtag", LineKind::FileEntry)]
+    #[case::keyword_before_line_ending("poptag\r\n", LineKind::FileEntry)]
+    #[case::keyword_prefix("options \"title\"", LineKind::Unrecognized)]
+    #[case::keyword_case("Option \"title\" \"Ledger\"", LineKind::Unrecognized)]
+    #[case::comment("; Groceries", LineKind::Comment)]
+    #[case::empty_comment(";", LineKind::Comment)]
+    #[case::indented_comment("  ; Groceries", LineKind::Comment)]
+    #[case::org_heading("* Expenses", LineKind::Ignorable)]
+    #[case::org_property(":PROPERTIES:", LineKind::Ignorable)]
+    #[case::bang("!important", LineKind::Ignorable)]
+    #[case::ampersand("& more", LineKind::Ignorable)]
+    #[case::hash_comment("#comment", LineKind::Ignorable)]
+    #[case::hash_tag_like("#trip", LineKind::Ignorable)]
+    #[case::question("? maybe", LineKind::Ignorable)]
+    #[case::percent("% latex", LineKind::Ignorable)]
+    #[case::empty("", LineKind::Blank)]
+    #[case::spaces("   ", LineKind::Blank)]
+    #[case::line_ending("\n", LineKind::Blank)]
+    #[case::crlf(" \t\r\n", LineKind::Blank)]
+    #[case::word("Expenses:Food 10 CHF", LineKind::Unrecognized)]
+    #[case::txn_without_date("* \"Coffee\"", LineKind::Ignorable)]
+    #[case::indented("  Assets:Cash", LineKind::Unrecognized)]
+    #[case::indented_date("  2023-05-27 open Assets:Cash", LineKind::Unrecognized)]
+    #[case::indented_heading("  * Expenses", LineKind::Unrecognized)]
+    #[case::plus("+ item", LineKind::Unrecognized)]
+    #[case::quote("\"Coffee\"", LineKind::Unrecognized)]
+    fn should_classify_the_line(#[case] line: &str, #[case] expected: LineKind) {
+        assert_eq!(LineKind::of(line), expected);
+    }
+}
@@ -0,0 +1,378 @@
+//! Checks of likely mistakes in a parsed file
+//!
+//! The lints are opt-in, and run on a [`BeancountFile`] after parsing with [`LintSet::check`].
+//! They report [`Warning`]s, each with its own [`Code`](crate::Code).
+//!
+//! Some lints depend on the current date. It is given by a [`Clock`],
+//! so that the checks can be tested with a [`FixedClock`].
+//!
+//! # Example
+//! ```
+//! use beancount_parser::{BeancountFile, Code, Date, lint::{FixedClock, FutureDatedDirective, LintSet}};
+//! let input = r#"
+//! 2023-05-20 * "Coffee"
+//! 2032-05-21 * "Groceries"
+//! "#;
+//! let beancount: BeancountFile<f64> = input.parse().unwrap();
+//! let lints = LintSet::new(FixedClock::new(Date::new(2023, 5, 21)))
+//!     .future_dated_directive(FutureDatedDirective::new(0));
+//! let warnings = lints.check(&beancount);
+//! assert_eq!(warnings.len(), 1);
+//! assert_eq!(warnings[0].code(), Code::FutureDatedDirective);
+//! assert_eq!(warnings[0].line_number(), 3);
+//! ```
+
+#[cfg(feature = "unicode-normalization")]
+use std::collections::HashMap;
+use std::{
+    collections::HashSet,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[cfg(feature = "unicode-normalization")]
+use crate::{unicode, Account};
+use crate::{BeancountFile, Date, Directive, DirectiveContent, Warning, WarningKind};
+
+/// Source of the current date
+pub trait Clock {
+    /// Returns the current date
+    fn today(&self) -> Date;
+}
+
+/// [`Clock`] returning the current date (in UTC) of the system
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn today(&self) -> Date {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let days = i32::try_from(seconds / 86_400).unwrap_or(i32::MAX);
+        Date::from_epoch_days(days).unwrap_or(Date::new(u16::MAX, 12, 31))
+    }
+}
+
+/// [`Clock`] always returning the same date
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedClock(Date);
+
+impl FixedClock {
+    /// Create a clock for which today is always `date`
+    #[must_use]
+    pub fn new(date: Date) -> Self {
+        Self(date)
+    }
+}
+
+impl Clock for FixedClock {
+    fn today(&self) -> Date {
+        self.0
+    }
+}
+
+/// Kind of directive, used to configure the lints
+///
+/// The kinds are ordered like the variants of [`DirectiveContent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+#[allow(missing_docs)]
+pub enum DirectiveKind {
+    Transaction,
+    Price,
+    Balance,
+    Open,
+    Close,
+    Pad,
+    Commodity,
+    Event,
+    Note,
+    Document,
+    Query,
+    Custom,
+}
+
+impl DirectiveKind {
+    /// Every kind of directive, in the order of [`DirectiveContent`]
+    pub const ALL: [Self; 12] = [
+        Self::Transaction,
+        Self::Price,
+        Self::Balance,
+        Self::Open,
+        Self::Close,
+        Self::Pad,
+        Self::Commodity,
+        Self::Event,
+        Self::Note,
+        Self::Document,
+        Self::Query,
+        Self::Custom,
+    ];
+
+    /// Returns the name of the kind (like `transaction` or `open`)
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Transaction => "transaction",
+            Self::Price => "price",
+            Self::Balance => "balance",
+            Self::Open => "open",
+            Self::Close => "close",
+            Self::Pad => "pad",
+            Self::Commodity => "commodity",
+            Self::Event => "event",
+            Self::Note => "note",
+            Self::Document => "document",
+            Self::Query => "query",
+            Self::Custom => "custom",
+        }
+    }
+
+    /// Returns the kind of the directive content
+    #[must_use]
+    pub fn of<D>(content: &DirectiveContent<D>) -> Self {
+        match content {
+            DirectiveContent::Transaction(_) => Self::Transaction,
+            DirectiveContent::Price(_) => Self::Price,
+            DirectiveContent::Balance(_) => Self::Balance,
+            DirectiveContent::Open(_) => Self::Open,
+            DirectiveContent::Close(_) => Self::Close,
+            DirectiveContent::Pad(_) => Self::Pad,
+            DirectiveContent::Commodity(_) => Self::Commodity,
+            DirectiveContent::Event(_) => Self::Event,
+            DirectiveContent::Note(_) => Self::Note,
+            DirectiveContent::Document(_) => Self::Document,
+            DirectiveContent::Query(_) => Self::Query,
+            DirectiveContent::Custom(_) => Self::Custom,
+        }
+    }
+}
+
+/// With the `serde` feature, the kind is serialized as its name (see [`DirectiveKind::as_str`])
+#[cfg(feature = "serde")]
+impl serde::Serialize for DirectiveKind {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+/// Lint reporting the directives dated after today (likely a typo, like `2032` instead of `2023`)
+///
+/// Reported with the code [`Code::FutureDatedDirective`](crate::Code::FutureDatedDirective).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FutureDatedDirective {
+    /// Number of days after today still allowed
+    pub allow_days_ahead: u16,
+    /// Kinds of directive that may be in the future (like scheduled `balance` assertions)
+    pub exempt: HashSet<DirectiveKind>,
+}
+
+impl FutureDatedDirective {
+    /// Create the lint, allowing directives up to `allow_days_ahead` days after today
+    #[must_use]
+    pub fn new(allow_days_ahead: u16) -> Self {
+        Self {
+            allow_days_ahead,
+            exempt: HashSet::new(),
+        }
+    }
+
+    /// Do not report the directives of the given kind
+    #[must_use]
+    pub fn exempt(mut self, kind: DirectiveKind) -> Self {
+        self.exempt.insert(kind);
+        self
+    }
+
+    fn check<D>(&self, today: Date, directive: &Directive<D>) -> Option<Warning> {
+        if self.exempt.contains(&DirectiveKind::of(&directive.content)) {
+            return None;
+        }
+        let days_ahead = days_between(today, directive.date);
+        (days_ahead > i64::from(self.allow_days_ahead)).then(|| {
+            Warning::new(
+                WarningKind::FutureDated(saturating_u32(days_ahead)),
+                directive.line_number,
+            )
+        })
+    }
+}
+
+/// Lint reporting the transactions flagged `!` for too long (likely forgotten follow-ups)
+///
+/// Reported with the code [`Code::StaleIncompleteFlag`](crate::Code::StaleIncompleteFlag).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct StaleIncompleteFlag {
+    /// Maximum number of days a transaction may stay flagged `!`
+    pub max_age_days: u32,
+}
+
+impl StaleIncompleteFlag {
+    /// Create the lint, allowing transactions to be flagged `!` for up to `max_age_days` days
+    #[must_use]
+    pub fn new(max_age_days: u32) -> Self {
+        Self { max_age_days }
+    }
+
+    fn check<D>(&self, today: Date, directive: &Directive<D>) -> Option<Warning> {
+        let DirectiveContent::Transaction(trx) = &directive.content else {
+            return None;
+        };
+        if trx.flag != Some('!') {
+            return None;
+        }
+        let age = days_between(directive.date, today);
+        (age > i64::from(self.max_age_days)).then(|| {
+            Warning::new(
+                WarningKind::StaleIncompleteFlag(saturating_u32(age)),
+                directive.line_number,
+            )
+        })
+    }
+}
+
+/// Lint reporting the accounts whose names only differ by their Unicode normalization
+///
+/// An account like `Assets:Bücher` can be written with a composed `ü` (NFC, like most systems do)
+/// or with a `u` followed by a combining diaeresis (NFD, like some macOS applications do).
+/// Both look the same, but are different accounts, unless the file is parsed with
+/// [`ParseOptions::normalize_nfc`](crate::ParseOptions::normalize_nfc).
+///
+/// Each spelling other than the first one is reported once, at the first directive using it,
+/// with the code [`Code::SimilarAccounts`](crate::Code::SimilarAccounts).
+#[cfg(feature = "unicode-normalization")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SimilarAccounts;
+
+#[cfg(feature = "unicode-normalization")]
+impl SimilarAccounts {
+    /// Create the lint
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Spellings of the accounts found by [`SimilarAccounts`] in the directives checked so far
+#[cfg(feature = "unicode-normalization")]
+#[derive(Default)]
+struct Spellings {
+    /// Every spelling found
+    seen: HashSet<Account>,
+    /// First spelling found of each account in NFC
+    first: HashMap<String, Account>,
+}
+
+#[cfg(feature = "unicode-normalization")]
+impl Spellings {
+    /// Add the spellings of the accounts of the directive, and report the new spellings of known accounts
+    fn check<D>(&mut self, directive: &Directive<D>) -> Vec<Warning> {
+        let mut warnings = Vec::new();
+        directive.content.for_each_account(|account| {
+            if !self.seen.insert(account.clone()) {
+                return;
+            }
+            let first = self
+                .first
+                .entry(unicode::nfc(account.as_str()).into_owned())
+                .or_insert_with(|| account.clone());
+            if first != account {
+                warnings.push(Warning::new(
+                    WarningKind::SimilarAccounts(account.clone(), first.clone()),
+                    directive.line_number,
+                ));
+            }
+        });
+        warnings
+    }
+}
+
+/// Set of lints to run on a file
+///
+/// No lint is enabled by default. See the [module documentation](self) for an example.
+#[derive(Debug, Clone)]
+pub struct LintSet<C> {
+    clock: C,
+    future_dated_directive: Option<FutureDatedDirective>,
+    stale_incomplete_flag: Option<StaleIncompleteFlag>,
+    #[cfg(feature = "unicode-normalization")]
+    similar_accounts: Option<SimilarAccounts>,
+}
+
+impl<C: Clock> LintSet<C> {
+    /// Create an empty set of lints, using `clock` to get the current date
+    #[must_use]
+    pub fn new(clock: C) -> Self {
+        Self {
+            clock,
+            future_dated_directive: None,
+            stale_incomplete_flag: None,
+            #[cfg(feature = "unicode-normalization")]
+            similar_accounts: None,
+        }
+    }
+
+    /// Enable the [`FutureDatedDirective`] lint
+    #[must_use]
+    pub fn future_dated_directive(mut self, lint: FutureDatedDirective) -> Self {
+        self.future_dated_directive = Some(lint);
+        self
+    }
+
+    /// Enable the [`StaleIncompleteFlag`] lint
+    #[must_use]
+    pub fn stale_incomplete_flag(mut self, lint: StaleIncompleteFlag) -> Self {
+        self.stale_incomplete_flag = Some(lint);
+        self
+    }
+
+    /// Enable the [`SimilarAccounts`] lint
+    #[cfg(feature = "unicode-normalization")]
+    #[must_use]
+    pub fn similar_accounts(mut self, lint: SimilarAccounts) -> Self {
+        self.similar_accounts = Some(lint);
+        self
+    }
+
+    /// Run the lints on the directives of the file, and return the warnings in the order of the directives
+    #[must_use]
+    pub fn check<D>(&self, file: &BeancountFile<D>) -> Vec<Warning> {
+        let today = self.clock.today();
+        #[cfg(feature = "unicode-normalization")]
+        let mut spellings = Spellings::default();
+        file.directives
+            .iter()
+            .flat_map(|directive| {
+                let warnings = [
+                    self.future_dated_directive
+                        .as_ref()
+                        .and_then(|lint| lint.check(today, directive)),
+                    self.stale_incomplete_flag
+                        .as_ref()
+                        .and_then(|lint| lint.check(today, directive)),
+                ]
+                .into_iter()
+                .flatten();
+                #[cfg(feature = "unicode-normalization")]
+                let warnings = warnings.chain(
+                    self.similar_accounts
+                        .iter()
+                        .flat_map(|_| spellings.check(directive)),
+                );
+                warnings.collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+/// Number of days from `from` to `to` (negative if `to` is before `from`)
+fn days_between(from: Date, to: Date) -> i64 {
+    i64::from(to.to_epoch_days()) - i64::from(from.to_epoch_days())
+}
+
+fn saturating_u32(days: i64) -> u32 {
+    u32::try_from(days).unwrap_or(u32::MAX)
+}
@@ -0,0 +1,139 @@
+use crate::{booking, metadata, Account, Amount, Evaluate, MergeError, Posting, Transaction};
+
+/// Options of [`Transaction::merge`]
+///
+/// # Example
+/// ```
+/// # use beancount_parser::MergePolicy;
+/// let policy = MergePolicy::default().narration_separator("; ").force(true);
+/// ```
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct MergePolicy {
+    pub(crate) narration_separator: String,
+    pub(crate) force: bool,
+}
+
+impl Default for MergePolicy {
+    fn default() -> Self {
+        Self {
+            narration_separator: " / ".into(),
+            force: false,
+        }
+    }
+}
+
+impl MergePolicy {
+    /// Text inserted between the two narrations (` / ` by default)
+    #[must_use]
+    pub fn narration_separator(mut self, separator: impl Into<String>) -> Self {
+        self.narration_separator = separator.into();
+        self
+    }
+
+    /// Keep the payee of the first transaction when the payees conflict, instead of failing (disabled by default)
+    #[must_use]
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+}
+
+pub(crate) fn split<D: Evaluate>(
+    mut transaction: Transaction<D>,
+    posting_indices: &[usize],
+    transfer_account: &Account,
+) -> (Transaction<D>, Transaction<D>) {
+    let (moved, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut transaction.postings)
+        .into_iter()
+        .enumerate()
+        .partition(|(index, _)| posting_indices.contains(index));
+    let side = |postings: Vec<(usize, Posting<D>)>| Transaction {
+        postings: postings.into_iter().map(|(_, posting)| posting).collect(),
+        ..transaction.clone()
+    };
+    let (mut first, mut second) = (side(kept), side(moved));
+    let first_booked = booking::book(&first);
+    let second_booked = booking::book(&second);
+    // A side with a posting without amount balances itself, so its transfer is the opposite of the other side's.
+    // This keeps the interpolated amount of that posting, and the transfers sum to zero.
+    let (first_transfer, second_transfer) = match (
+        first_booked.missing.is_empty(),
+        second_booked.missing.is_empty(),
+    ) {
+        (true, false) => {
+            let transfer = negated(first_booked.residual.to_amounts());
+            (transfer.clone(), negated(transfer))
+        }
+        (false, true) => {
+            let transfer = negated(second_booked.residual.to_amounts());
+            (negated(transfer.clone()), transfer)
+        }
+        _ => (
+            negated(first_booked.residual.to_amounts()),
+            negated(second_booked.residual.to_amounts()),
+        ),
+    };
+    for (side, transfer) in [(&mut first, first_transfer), (&mut second, second_transfer)] {
+        side.postings.extend(
+            transfer
+                .into_iter()
+                .map(|amount| transfer_posting(transfer_account, amount)),
+        );
+    }
+    (first, second)
+}
+
+fn negated<D: Evaluate>(amounts: Vec<Amount<D>>) -> Vec<Amount<D>> {
+    amounts
+        .into_iter()
+        .map(|amount| Amount {
+            value: -amount.value,
+            currency: amount.currency,
+        })
+        .collect()
+}
+
+pub(crate) fn transfer_posting<D>(account: &Account, amount: Amount<D>) -> Posting<D> {
+    Posting {
+        flag: None,
+        account: account.clone(),
+        amount: Some(amount),
+        cost: None,
+        price: None,
+        metadata: metadata::Map::default(),
+        line_number: 0,
+        metadata_duplicates: metadata::Duplicates::default(),
+        misplaced_tags: None,
+        stray_tokens: None,
+        unsupported_cost: None,
+    }
+}
+
+pub(crate) fn merge<D>(
+    mut first: Transaction<D>,
+    second: Transaction<D>,
+    policy: &MergePolicy,
+) -> Result<Transaction<D>, MergeError> {
+    match (&first.payee, second.payee) {
+        (Some(payee), Some(other)) if *payee != other && !policy.force => {
+            return Err(MergeError::ConflictingPayees(payee.clone(), other));
+        }
+        (None, other) => first.payee = other,
+        _ => (),
+    }
+    first.narration = match (first.narration, second.narration) {
+        (Some(narration), Some(other)) if narration != other => {
+            Some(format!("{narration}{}{other}", policy.narration_separator))
+        }
+        (narration, other) => narration.or(other),
+    };
+    first.flag = match (first.flag, second.flag) {
+        (Some('!'), _) | (_, Some('!')) => Some('!'),
+        (flag, other) => flag.or(other),
+    };
+    first.tags.extend(second.tags);
+    first.links.extend(second.links);
+    first.postings.extend(second.postings);
+    Ok(first)
+}
@@ -13,10 +13,34 @@
 //! let directive_metadata = &beancount.directives[0].metadata;
 //! assert_eq!(directive_metadata.get("title"), Some(&Value::String("Swiss Franc".into())));
 //! ```
+//!
+//! # Duplicate keys
+//!
+//! Like in beancount, when a key is declared more than once on the same directive (or posting),
+//! the first value is kept in the [`Map`] and a [`Warning`](crate::Warning) is emitted for each repeated occurrence.
+//! With [`ParseOptions::strict_metadata`](crate::ParseOptions::strict_metadata), a repeated key is an error instead.
+//!
+//! All the occurrences of a key, in declaration order, remain available via
+//! [`Directive::metadata_all`](crate::Directive::metadata_all) and [`Posting::metadata_all`](crate::Posting::metadata_all).
+//!
+//! ```
+//! # use beancount_parser::{BeancountFile, metadata::Value};
+//! let input = r#"
+//! 2023-05-27 commodity CHF
+//!     title: "Swiss Franc"
+//!     title: "Franc suisse"
+//! "#;
+//! let beancount: BeancountFile<f64> = input.parse().unwrap();
+//! let directive = &beancount.directives[0];
+//! assert_eq!(directive.metadata.get("title"), Some(&Value::String("Swiss Franc".into())));
+//! let titles: Vec<_> = directive.metadata_all("title").collect();
+//! assert_eq!(titles, [&Value::String("Swiss Franc".into()), &Value::String("Franc suisse".into())]);
+//! assert_eq!(beancount.warnings.len(), 1);
+//! ```
 
 use std::{
     borrow::Borrow,
-    collections::HashMap,
+    collections::{hash_map, HashMap},
     fmt::{Debug, Display, Formatter},
     str::FromStr,
     sync::Arc,
@@ -30,8 +54,12 @@ use nom::{
     sequence::preceded,
     Parser,
 };
+use nom_locate::position;
 
-use crate::{amount, empty_line, end_of_line, string, Currency, Decimal, IResult, Span};
+use crate::{
+    amount, empty_line, end_of_line, invalid_line, string, unicode, Currency, IResult, InvalidLine,
+    Number, Span,
+};
 
 /// Metadata map
 ///
@@ -41,9 +69,19 @@ pub type Map<D> = HashMap<Key, Value<D>>;
 /// Metadata key
 ///
 /// See the [`metadata`](crate::metadata) module for an example
+///
+/// With the `serde` feature, the key is (de)serialized as a plain string.
+/// Deserialization fails if the string is not a valid metadata key.
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct Key(Arc<str>);
 
+impl Key {
+    /// Create a key from a name known to be valid
+    pub(crate) fn new_unchecked(name: &str) -> Self {
+        Self(name.into())
+    }
+}
+
 impl Display for Key {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         Display::fmt(&self.0, f)
@@ -65,7 +103,7 @@ impl Borrow<str> for Key {
 impl FromStr for Key {
     type Err = crate::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let span = Span::new(s);
+        let span = crate::span(s);
         match all_consuming(key)(span) {
             Ok((_, key)) => Ok(key),
             Err(_) => Err(crate::Error::new(s, span)),
@@ -73,9 +111,30 @@ impl FromStr for Key {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for Key {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_ref())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Key {
+    fn deserialize<De: serde::Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse()
+            .map_err(|_| serde::de::Error::custom(format!("invalid metadata key: {name}")))
+    }
+}
+
 /// Metadata value
 ///
 /// See the [`metadata`](crate::metadata) module for an example
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 #[derive(Debug, Clone, PartialEq)]
 #[non_exhaustive]
 pub enum Value<D> {
@@ -87,15 +146,123 @@ pub enum Value<D> {
     Currency(Currency),
 }
 
-pub(crate) fn parse<D: Decimal>(input: Span<'_>) -> IResult<'_, Map<D>> {
+/// Repeated occurrence of a metadata key, that is not part of the [`Map`]
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Duplicate<D> {
+    pub(crate) key: Key,
+    pub(crate) value: Value<D>,
+    pub(crate) line_number: u32,
+    pub(crate) offset: usize,
+}
+
+/// Repeated occurrences of metadata keys of a directive or posting
+///
+/// They are rare, so they are boxed to not increase the size of every directive and posting.
+#[allow(clippy::box_collection)]
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Duplicates<D>(Option<Box<Vec<Duplicate<D>>>>);
+
+impl<D> Default for Duplicates<D> {
+    fn default() -> Self {
+        Self(None)
+    }
+}
+
+impl<D> Duplicates<D> {
+    pub(crate) fn iter(&self) -> std::slice::Iter<'_, Duplicate<D>> {
+        self.as_vec()
+            .map_or([].iter(), |duplicates| duplicates.iter())
+    }
+
+    pub(crate) fn as_vec(&self) -> Option<&Vec<Duplicate<D>>> {
+        self.0.as_deref()
+    }
+
+    pub(crate) fn as_mut_vec(&mut self) -> Option<&mut Vec<Duplicate<D>>> {
+        self.0.as_deref_mut()
+    }
+
+    fn push(&mut self, duplicate: Duplicate<D>) {
+        self.0.get_or_insert_with(Box::default).push(duplicate);
+    }
+}
+
+/// Metadata of a directive or posting: the map and the repeated keys
+pub(crate) type Block<D> = (Map<D>, Duplicates<D>);
+
+/// Returns the values of all occurrences of the key: the one in the map first, then the repeated ones in order
+pub(crate) fn all<'a, D>(
+    map: &'a Map<D>,
+    duplicates: &'a Duplicates<D>,
+    key: &'a str,
+) -> impl Iterator<Item = &'a Value<D>> {
+    map.get(key).into_iter().chain(
+        duplicates
+            .iter()
+            .filter(move |duplicate| duplicate.key.as_ref() == key)
+            .map(|duplicate| &duplicate.value),
+    )
+}
+
+pub(crate) fn parse<D: Number>(input: Span<'_>) -> IResult<'_, Block<D>> {
     let mut iter = iterator(input, alt((entry.map(Some), empty_line.map(|()| None))));
-    let map: HashMap<_, _> = iter.flatten().collect();
+    let block = collect(iter.flatten());
     let (input, ()) = iter.finish()?;
-    Ok((input, map))
+    Ok((input, block))
+}
+
+/// Parse the metadata of a directive, skipping and returning the indented lines that are not metadata
+///
+/// It must not be used for the metadata of transactions and postings, as it would skip the postings that follow.
+pub(crate) fn parse_skipping_invalid_lines<D: Number>(
+    input: Span<'_>,
+) -> IResult<'_, (Block<D>, Vec<InvalidLine>)> {
+    let mut invalid_lines = Vec::new();
+    let mut iter = iterator(
+        input,
+        alt((
+            entry.map(|entry| Some(Ok(entry))),
+            empty_line.map(|()| None),
+            invalid_line.map(|line| Some(Err(line))),
+        )),
+    );
+    let block = collect(
+        iter.flatten()
+            .filter_map(|line| line.map_err(|line| invalid_lines.push(line)).ok()),
+    );
+    let (input, ()) = iter.finish()?;
+    Ok((input, (block, invalid_lines)))
+}
+
+fn collect<'a, D>(entries: impl Iterator<Item = (Span<'a>, Key, Value<D>)>) -> Block<D> {
+    let mut map = HashMap::new();
+    let mut duplicates = Duplicates::default();
+    for (position, key, value) in entries {
+        match map.entry(key) {
+            hash_map::Entry::Occupied(entry) => duplicates.push(Duplicate {
+                key: entry.key().clone(),
+                value,
+                line_number: position.location_line(),
+                offset: position.location_offset(),
+            }),
+            hash_map::Entry::Vacant(entry) => {
+                entry.insert(value);
+            }
+        }
+    }
+    (map, duplicates)
 }
 
-fn entry<D: Decimal>(input: Span<'_>) -> IResult<'_, (Key, Value<D>)> {
+fn entry<D: Number>(input: Span<'_>) -> IResult<'_, (Span<'_>, Key, Value<D>)> {
     let (input, _) = space1(input)?;
+    let (input, position) = position(input)?;
+    let (input, (key, value)) = key_value(input)?;
+    let (input, ()) = end_of_line(input)?;
+    Ok((input, (position, key, value)))
+}
+
+/// Key and value of a metadata entry, separated by `:`
+pub(crate) fn key_value<D: Number>(input: Span<'_>) -> IResult<'_, (Key, Value<D>)> {
     let (input, key) = key(input)?;
     let (input, _) = char(':')(input)?;
     let (input, _) = space1(input)?;
@@ -104,15 +271,16 @@ fn entry<D: Decimal>(input: Span<'_>) -> IResult<'_, (Key, Value<D>)> {
         amount::expression.map(Value::Number),
         amount::currency.map(Value::Currency),
     ))(input)?;
-    let (input, ()) = end_of_line(input)?;
     Ok((input, (key, value)))
 }
 
-fn key(input: Span<'_>) -> IResult<'_, Key> {
+pub(crate) fn key(input: Span<'_>) -> IResult<'_, Key> {
     map(
         recognize(preceded(
             satisfy(char::is_lowercase),
-            take_while(|c: char| c.is_alphanumeric() || c == '-' || c == '_'),
+            take_while(|c: char| {
+                c.is_alphanumeric() || c == '-' || c == '_' || unicode::is_combining_mark(c)
+            }),
         )),
         |s: Span<'_>| Key((*s.fragment()).into()),
     )(input)
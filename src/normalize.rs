@@ -0,0 +1,131 @@
+use std::collections::BTreeMap;
+
+use crate::{booking, BeancountFile, Date, Directive, DirectiveContent};
+
+/// Options of [`BeancountFile::normalize`]
+///
+/// Every normalization can be enabled or disabled individually.
+///
+/// # Example
+/// ```
+/// # use beancount_parser::NormalizeOptions;
+/// let options = NormalizeOptions::default()
+///     .drop_redundant_cost_dates(true)
+///     .remove_duplicates(true);
+/// ```
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct NormalizeOptions {
+    pub(crate) sort_directives: bool,
+    pub(crate) trim_text: bool,
+    pub(crate) drop_redundant_cost_dates: bool,
+    pub(crate) remove_duplicates: bool,
+}
+
+impl Default for NormalizeOptions {
+    fn default() -> Self {
+        Self {
+            sort_directives: true,
+            trim_text: true,
+            drop_redundant_cost_dates: false,
+            remove_duplicates: false,
+        }
+    }
+}
+
+impl NormalizeOptions {
+    /// Sort the directives in the order they take effect (enabled by default)
+    ///
+    /// Directives are sorted by date. On the same day, `open` comes first, then `balance`,
+    /// then the other directives, and `close` comes last.
+    /// Directives of the same day and kind keep their relative order.
+    #[must_use]
+    pub fn sort_directives(mut self, sort: bool) -> Self {
+        self.sort_directives = sort;
+        self
+    }
+
+    /// Trim the leading and trailing whitespace of the payees and narrations (enabled by default)
+    ///
+    /// The case and the inner whitespace are left unchanged.
+    #[must_use]
+    pub fn trim_text(mut self, trim: bool) -> Self {
+        self.trim_text = trim;
+        self
+    }
+
+    /// Remove the dates of the costs that are the date of their transaction (disabled by default)
+    ///
+    /// Such a date is the default one (`{10 USD, 2023-05-20}` on a transaction of `2023-05-20` is the same as `{10 USD}`).
+    #[must_use]
+    pub fn drop_redundant_cost_dates(mut self, drop: bool) -> Self {
+        self.drop_redundant_cost_dates = drop;
+        self
+    }
+
+    /// Remove the directives that are exact duplicates of a previous directive (disabled by default)
+    ///
+    /// Two directives are duplicates if they have the same date, content and metadata.
+    /// Their line numbers are not compared. The first directive is kept.
+    #[must_use]
+    pub fn remove_duplicates(mut self, remove: bool) -> Self {
+        self.remove_duplicates = remove;
+        self
+    }
+}
+
+pub(crate) fn normalize<D: PartialEq>(file: &mut BeancountFile<D>, options: &NormalizeOptions) {
+    if options.trim_text || options.drop_redundant_cost_dates {
+        for directive in &mut file.directives {
+            let DirectiveContent::Transaction(trx) = &mut directive.content else {
+                continue;
+            };
+            if options.trim_text {
+                trx.payee
+                    .iter_mut()
+                    .chain(&mut trx.narration)
+                    .for_each(trim);
+            }
+            if options.drop_redundant_cost_dates {
+                for cost in trx.postings.iter_mut().filter_map(|p| p.cost.as_mut()) {
+                    if cost.date == Some(directive.date) {
+                        cost.date = None;
+                    }
+                }
+            }
+        }
+    }
+    if options.remove_duplicates {
+        remove_duplicates(&mut file.directives);
+    }
+    if options.sort_directives {
+        file.directives
+            .sort_by_key(|directive| (directive.date, booking::kind_order(&directive.content)));
+    }
+}
+
+fn trim(text: &mut String) {
+    if text.trim().len() != text.len() {
+        *text = text.trim().to_owned();
+    }
+}
+
+fn remove_duplicates<D: PartialEq>(directives: &mut Vec<Directive<D>>) {
+    // Duplicates have the same date, so each directive is only compared to the kept directives of its day
+    let mut kept_by_date: BTreeMap<Date, Vec<usize>> = BTreeMap::new();
+    let mut keep = Vec::with_capacity(directives.len());
+    for (index, directive) in directives.iter().enumerate() {
+        let kept = kept_by_date.entry(directive.date).or_default();
+        let duplicate = kept.iter().any(|&other| {
+            let other = &directives[other];
+            other.content == directive.content && other.metadata == directive.metadata
+        });
+        if !duplicate {
+            kept.push(index);
+        }
+        keep.push(!duplicate);
+    }
+    let mut keep = keep.into_iter();
+    directives.retain(|_| keep.next().unwrap_or(true));
+}
@@ -0,0 +1,52 @@
+use std::collections::HashSet;
+
+use nom::character::complete::space1;
+
+use crate::{account, string, transaction, Account, IResult, Link, Span, Tag};
+
+/// A note attached to an account
+///
+/// Beancount does not allow tags and links after the description, but they are accepted and kept.
+///
+/// # Example
+/// ```
+/// # use beancount_parser::{BeancountFile, DirectiveContent};
+/// let input = r#"2023-06-15 note Assets:Cash "Transferred to savings""#;
+/// let beancount: BeancountFile<f64> = input.parse().unwrap();
+/// let DirectiveContent::Note(ref note) = beancount.directives[0].content else { unreachable!() };
+/// assert_eq!(note.account.as_str(), "Assets:Cash");
+/// assert_eq!(note.description, "Transferred to savings");
+/// ```
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Note {
+    /// Account of the note
+    pub account: Account,
+    /// Text of the note
+    pub description: String,
+    /// Tags following the description
+    pub tags: HashSet<Tag>,
+    /// Links following the description
+    pub links: HashSet<Link>,
+}
+
+pub(super) fn parse(input: Span<'_>) -> IResult<'_, Note> {
+    let (input, account) = account::parse(input)?;
+    let (input, _) = space1(input)?;
+    let (input, description) = string(input)?;
+    let (input, (tags, links)) = transaction::tags_and_links(input)?;
+    Ok((
+        input,
+        Note {
+            account,
+            description,
+            tags,
+            links,
+        },
+    ))
+}
@@ -0,0 +1,496 @@
+use std::{collections::HashSet, ops::Range, path::PathBuf};
+
+use crate::{Code, Date};
+
+/// Options altering how the input is parsed
+///
+/// The default options follow the behavior of beancount.
+///
+/// # Example
+///
+/// ```
+/// use beancount_parser::{parse_with_options, BeancountFile, ParseOptions};
+/// let input = r#"
+/// 2023-05-27 open Assets:Cash
+///   id: 1
+///   id: 2
+/// "#;
+/// let options = ParseOptions::default().strict_metadata(true);
+/// let result = parse_with_options::<f64>(input, &options);
+/// assert_eq!(result.unwrap_err().line_number(), 4);
+/// ```
+#[derive(Debug, Clone, Default)]
+#[allow(clippy::struct_excessive_bools)]
+#[non_exhaustive]
+pub struct ParseOptions {
+    pub(crate) strict_metadata: bool,
+    pub(crate) date_range: Option<Range<Date>>,
+    pub(crate) keep_declarations: bool,
+    pub(crate) suppressed: HashSet<Code>,
+    pub(crate) strip_times: bool,
+    pub(crate) lenient_accounts: bool,
+    pub(crate) source_name: Option<PathBuf>,
+    pub(crate) aggregate_balance_assertions: bool,
+    pub(crate) strip_posting_tags: bool,
+    pub(crate) case_insensitive_keywords: bool,
+    pub(crate) drop_stray_tokens: bool,
+    pub(crate) allow_scientific_notation: bool,
+    pub(crate) round_excess_precision: bool,
+    pub(crate) skip_invalid_lines: bool,
+    pub(crate) capture_ignored_lines: bool,
+    #[cfg(feature = "unicode-normalization")]
+    pub(crate) normalize_nfc: bool,
+    #[cfg(feature = "unicode-normalization")]
+    pub(crate) normalize_nfc_text: bool,
+}
+
+impl ParseOptions {
+    /// Identifiers of the options (the names of the builder methods), listed by [`capabilities`](crate::capabilities)
+    pub(crate) const NAMES: &'static [&'static str] = &[
+        "strict_metadata",
+        "date_range",
+        "keep_declarations",
+        "suppress",
+        "strip_times",
+        "lenient_accounts",
+        "source_name",
+        "aggregate_balance_assertions",
+        "strip_posting_tags",
+        "case_insensitive_keywords",
+        "drop_stray_tokens",
+        "allow_scientific_notation",
+        "round_excess_precision",
+        "skip_invalid_lines",
+        "capture_ignored_lines",
+        #[cfg(feature = "unicode-normalization")]
+        "normalize_nfc",
+        #[cfg(feature = "unicode-normalization")]
+        "normalize_nfc_text",
+    ];
+
+    /// Reject metadata keys declared more than once on the same directive or posting
+    ///
+    /// By default, the first value is kept and a [`Warning`](crate::Warning) is emitted.
+    #[must_use]
+    pub fn strict_metadata(mut self, strict: bool) -> Self {
+        self.strict_metadata = strict;
+        self
+    }
+
+    /// Only parse the directives dated from `start` (inclusive) to `end` (exclusive)
+    ///
+    /// The other directives are skipped without parsing more than their date:
+    /// the rest of their line and the indented lines that follow (postings and metadata) are ignored.
+    /// This is much faster than parsing everything when the range is small compared to the file.
+    ///
+    /// Options, includes, `pushtag`/`poptag` and `pushmeta`/`popmeta` are still processed as usual,
+    /// so that tags and metadata pushed before the range are applied to the directives in the range.
+    ///
+    /// Note that syntax errors and warnings in the skipped directives are not reported,
+    /// and that no balance can be computed from the result for accounts that existed before `start`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::{parse_with_options, BeancountFile, ParseOptions};
+    /// let input = r#"
+    /// 2023-01-01 open Assets:Cash
+    /// 2023-02-10 * "Coffee"
+    ///   Expenses:Coffee  3 CHF
+    ///   Assets:Cash
+    /// 2023-03-10 * "Tea"
+    ///   Expenses:Tea  2 CHF
+    ///   Assets:Cash
+    /// "#;
+    /// let options = ParseOptions::default().date_range(
+    ///     "2023-03-01".parse().unwrap(),
+    ///     "2023-04-01".parse().unwrap(),
+    /// );
+    /// let beancount: BeancountFile<f64> = parse_with_options(input, &options).unwrap();
+    /// assert_eq!(beancount.directives.len(), 1);
+    /// assert_eq!(beancount.directives[0].date.month, 3);
+    /// ```
+    #[must_use]
+    pub fn date_range(mut self, start: Date, end: Date) -> Self {
+        self.date_range = Some(start..end);
+        self
+    }
+
+    /// Keep the `open`, `close` and `commodity` directives, even outside of the [`date_range`](Self::date_range)
+    ///
+    /// This is useful as consumers usually need to know all the accounts and commodities.
+    #[must_use]
+    pub fn keep_declarations(mut self, keep: bool) -> Self {
+        self.keep_declarations = keep;
+        self
+    }
+
+    /// Do not emit the warnings with the given [`Code`]
+    ///
+    /// It has no effect on errors.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::{parse_with_options, BeancountFile, Code, ParseOptions};
+    /// let input = "2023-05-27 open Assets:Cash\n  id: 1\n  id: 2";
+    /// let options = ParseOptions::default().suppress(Code::DuplicateMetadataKey);
+    /// let beancount: BeancountFile<f64> = parse_with_options(input, &options).unwrap();
+    /// assert!(beancount.warnings.is_empty());
+    /// ```
+    #[must_use]
+    pub fn suppress(mut self, code: Code) -> Self {
+        self.suppressed.insert(code);
+        self
+    }
+
+    /// Remove the time following the date of a directive (like in `2023-05-22 12:30:00 * "Coffee"`)
+    ///
+    /// By default, this is an error (with code [`Code::DateWithTime`]), as beancount dates cannot include a time.
+    /// With this option, the time is removed and a [`Warning`](crate::Warning) is emitted instead.
+    ///
+    /// A time is `H:MM`, `HH:MM`, `HH:MM:SS` or `HH:MM:SS.fff`, separated from the date by spaces or by `T`.
+    /// It is only recognized as such if it is followed by a valid directive.
+    /// So `2023-05-22 10:00` alone on its line is not reported
+    /// (like any other line that is not a directive, it is ignored).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::{parse, parse_with_options, BeancountFile, ParseOptions};
+    /// let input = "2023-05-22 12:30:00 open Assets:Cash";
+    /// let error = parse::<f64>(input).unwrap_err();
+    /// assert_eq!(
+    ///     error.to_string(),
+    ///     "error[E002]: Beancount dates cannot include a time at line: 1 (remove ` 12:30:00`)"
+    /// );
+    ///
+    /// let options = ParseOptions::default().strip_times(true);
+    /// let beancount: BeancountFile<f64> = parse_with_options(input, &options).unwrap();
+    /// assert_eq!(beancount.directives.len(), 1);
+    /// assert_eq!(beancount.warnings.len(), 1);
+    /// ```
+    #[must_use]
+    pub fn strip_times(mut self, strip: bool) -> Self {
+        self.strip_times = strip;
+        self
+    }
+
+    /// Keep the directives having an invalid account name (like `Assets:Bank:` or `Assets::Bank`)
+    ///
+    /// By default, this is an error (with code [`Code::InvalidAccount`]).
+    /// With this option, the account is kept as written and a [`Warning`](crate::Warning) is emitted instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::{parse, parse_with_options, BeancountFile, ParseOptions};
+    /// let input = "2023-05-22 open Assets:Bank:";
+    /// let error = parse::<f64>(input).unwrap_err();
+    /// assert_eq!(
+    ///     error.to_string(),
+    ///     "error[E005]: Invalid account name at line: 1 (empty account component)"
+    /// );
+    ///
+    /// let options = ParseOptions::default().lenient_accounts(true);
+    /// let beancount: BeancountFile<f64> = parse_with_options(input, &options).unwrap();
+    /// assert_eq!(beancount.directives.len(), 1);
+    /// assert_eq!(
+    ///     beancount.warnings[0].to_string(),
+    ///     "warning[E005]: Invalid account `Assets:Bank:` at line: 1 (empty account component)"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn lenient_accounts(mut self, lenient: bool) -> Self {
+        self.lenient_accounts = lenient;
+        self
+    }
+
+    /// Name of the input, used as the path of [`FileId::MAIN`](crate::FileId::MAIN) in the [`SourceMap`](crate::SourceMap) of a [`ParseReport`](crate::ParseReport)
+    ///
+    /// By default, the input is named `<input>`.
+    #[must_use]
+    pub fn source_name(mut self, name: impl Into<PathBuf>) -> Self {
+        self.source_name = Some(name.into());
+        self
+    }
+
+    /// Make the `balance` assertions cover the sub-accounts of the asserted account
+    ///
+    /// By default, like beancount, a `balance` assertion only checks the balance of the asserted account itself.
+    /// With this option, the balances of all its sub-accounts are summed in (like some beancount forks and plugins do).
+    /// So `balance Assets:Bank 1000 USD` asserts the combined balance of `Assets:Bank`, `Assets:Bank:Checking`, etc.
+    ///
+    /// It does not change the parsing, only [`BeancountFile::verify_balances`](crate::BeancountFile::verify_balances)
+    /// and the amounts of the `pad` directives.
+    #[must_use]
+    pub fn aggregate_balance_assertions(mut self, aggregate: bool) -> Self {
+        self.aggregate_balance_assertions = aggregate;
+        self
+    }
+
+    /// Remove the tags and links found at the end of posting lines (like in `Expenses:Food  10 USD #lunch`)
+    ///
+    /// By default, this is an error (with code [`Code::TagOnPosting`]), as beancount only allows them in the transaction header.
+    /// With this option, they are removed and a [`Warning`](crate::Warning) is emitted for each of them instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::{parse, parse_with_options, BeancountFile, ParseOptions};
+    /// let input = "2023-05-22 * \"Lunch\"\n  Expenses:Food  10 USD #lunch\n  Assets:Cash";
+    /// let error = parse::<f64>(input).unwrap_err();
+    /// assert_eq!(
+    ///     error.to_string(),
+    ///     "error[E007]: Tag or link on a posting at line: 2 (tags are not allowed on postings; move #lunch to the transaction header)"
+    /// );
+    ///
+    /// let options = ParseOptions::default().strip_posting_tags(true);
+    /// let beancount: BeancountFile<f64> = parse_with_options(input, &options).unwrap();
+    /// assert_eq!(beancount.directives.len(), 1);
+    /// assert_eq!(beancount.warnings.len(), 1);
+    /// ```
+    #[must_use]
+    pub fn strip_posting_tags(mut self, strip: bool) -> Self {
+        self.strip_posting_tags = strip;
+        self
+    }
+
+    /// Accept the directive keywords written with another case (like `Open` or `TXN`)
+    ///
+    /// By default, like beancount, the keywords must be lowercase, and a misspelled keyword is an error
+    /// (with code [`Code::MisspelledKeyword`]) hinting at the keyword it likely is.
+    /// With this option, the keywords only differing by their case are read as the lowercase keyword,
+    /// and a [`Warning`](crate::Warning) is emitted instead. The other misspellings (like `blance`) are still errors.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::{parse, parse_with_options, BeancountFile, DirectiveContent, ParseOptions};
+    /// let input = "2023-05-22 Open Assets:Cash";
+    /// let error = parse::<f64>(input).unwrap_err();
+    /// assert_eq!(
+    ///     error.to_string(),
+    ///     "error[E009]: Misspelled directive keyword at line: 1 (did you mean `open`?)"
+    /// );
+    ///
+    /// let options = ParseOptions::default().case_insensitive_keywords(true);
+    /// let beancount: BeancountFile<f64> = parse_with_options(input, &options).unwrap();
+    /// assert!(matches!(beancount.directives[0].content, DirectiveContent::Open(_)));
+    /// assert_eq!(beancount.warnings.len(), 1);
+    /// ```
+    #[must_use]
+    pub fn case_insensitive_keywords(mut self, case_insensitive: bool) -> Self {
+        self.case_insensitive_keywords = case_insensitive;
+        self
+    }
+
+    /// Remove the tokens of posting lines that are not part of the posting (like the `CHF` of `10 USD CHF`)
+    ///
+    /// By default, this is an error (with code [`Code::StrayPostingToken`]) naming the unexpected token.
+    /// With this option, the tokens are removed and a [`Warning`](crate::Warning) is emitted for each of them instead.
+    ///
+    /// A cost or a price that cannot be parsed (like the lot label of `{2 USD, "lot"}`) is never removed:
+    /// it is always an error (with code [`Code::UnsupportedCostSyntax`]), as the posting would silently lose its cost.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::{parse, parse_with_options, BeancountFile, ParseOptions};
+    /// let input = "2023-05-22 * \"Lunch\"\n  Expenses:Food  10 USD CHF\n  Assets:Cash";
+    /// let error = parse::<f64>(input).unwrap_err();
+    /// assert_eq!(
+    ///     error.to_string(),
+    ///     "error[E010]: Unexpected token in a posting at line: 2 (unexpected second currency `CHF`)"
+    /// );
+    ///
+    /// let options = ParseOptions::default().drop_stray_tokens(true);
+    /// let beancount: BeancountFile<f64> = parse_with_options(input, &options).unwrap();
+    /// assert_eq!(beancount.directives.len(), 1);
+    /// assert_eq!(beancount.warnings.len(), 1);
+    /// ```
+    #[must_use]
+    pub fn drop_stray_tokens(mut self, drop: bool) -> Self {
+        self.drop_stray_tokens = drop;
+        self
+    }
+
+    /// Accept numbers written in scientific notation (like `2.5E-7`)
+    ///
+    /// By default, this is an error (with code [`Code::ScientificNotation`]) whose hint gives the number as a plain decimal.
+    /// With this option, the number is converted to that plain decimal before being given to the number type,
+    /// so it is as exact as any other number (for example with `rust_decimal`).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::{parse, parse_with_options, BeancountFile, ParseOptions};
+    /// let input = "2023-05-22 price BTC 2.5E-7 USD";
+    /// let error = parse::<f64>(input).unwrap_err();
+    /// assert_eq!(
+    ///     error.to_string(),
+    ///     "error[E012]: Number in scientific notation at line: 1 (write `0.00000025`)"
+    /// );
+    ///
+    /// let options = ParseOptions::default().allow_scientific_notation(true);
+    /// let beancount: BeancountFile<f64> = parse_with_options(input, &options).unwrap();
+    /// # let beancount_parser::DirectiveContent::Price(price) = &beancount.directives[0].content else { unreachable!() };
+    /// assert_eq!(price.amount.value, 0.000_000_25);
+    /// ```
+    #[must_use]
+    pub fn allow_scientific_notation(mut self, allow: bool) -> Self {
+        self.allow_scientific_notation = allow;
+        self
+    }
+
+    /// Round the numbers that have more digits than the number type can represent
+    ///
+    /// A number has too many digits if the number type does not distinguish it from the number
+    /// whose last non-zero digit is changed by one (like `0.123456789012345678` and `0.123456789012345679` for `f64`),
+    /// unless the number type tells it otherwise (see [`FromLiteral::exceeds_precision`](crate::FromLiteral::exceeds_precision)).
+    /// By default, this is an error (with code [`Code::ExcessPrecision`]) instead of a silent rounding.
+    /// With this option, the number is rounded by the number type.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::{parse, parse_with_options, BeancountFile, ParseOptions};
+    /// let input = "2023-05-22 price ETH 0.123456789012345678 USD";
+    /// let error = parse::<f64>(input).unwrap_err();
+    /// assert_eq!(error.to_string(), "error[E013]: Number too precise for the number type at line: 1");
+    ///
+    /// let options = ParseOptions::default().round_excess_precision(true);
+    /// let beancount: BeancountFile<f64> = parse_with_options(input, &options).unwrap();
+    /// assert_eq!(beancount.directives.len(), 1);
+    /// ```
+    #[must_use]
+    pub fn round_excess_precision(mut self, round: bool) -> Self {
+        self.round_excess_precision = round;
+        self
+    }
+
+    /// Skip the indented lines of the directives that are neither metadata nor postings
+    ///
+    /// By default, such a line is an error (with code [`Code::InvalidIndentedLine`]), as ignoring it
+    /// would silently drop a posting (like one with an invalid flag) or a metadata entry.
+    /// With this option, the line is skipped with a warning, and the lines following it are parsed as usual.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::{parse, parse_with_options, BeancountFile, DirectiveContent, ParseOptions};
+    /// let input = "2023-05-22 * \"Lunch\"\n  Expenses:Food  10 USD\n  ?? Assets:Card  -4 USD\n  Assets:Cash";
+    /// let error = parse::<f64>(input).unwrap_err();
+    /// assert_eq!(
+    ///     error.to_string(),
+    ///     "error[E014]: Invalid line in a directive at line: 3 (`?? Assets:Card  -4 USD` is neither metadata nor a posting)"
+    /// );
+    ///
+    /// let options = ParseOptions::default().skip_invalid_lines(true);
+    /// let beancount: BeancountFile<f64> = parse_with_options(input, &options).unwrap();
+    /// # let DirectiveContent::Transaction(trx) = &beancount.directives[0].content else { unreachable!() };
+    /// assert_eq!(trx.postings.len(), 2);
+    /// assert_eq!(beancount.warnings.len(), 1);
+    /// ```
+    #[must_use]
+    pub fn skip_invalid_lines(mut self, skip: bool) -> Self {
+        self.skip_invalid_lines = skip;
+        self
+    }
+
+    /// Emit the lines ignored by the parser (like comments and org-mode headings) as [`Entry::Ignored`](crate::Entry::Ignored)
+    ///
+    /// By default, they are dropped. Capturing them allows a formatter to write them back as they are.
+    /// The blank lines, and the lines inside the directives (like indented comments), are not emitted.
+    /// See [`LineKind`](crate::LineKind) for how the lines are classified.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::{parse_iter_with_options, Entry, LineKind, ParseOptions};
+    /// let input = "* Accounts\n#todo\n2023-05-27 open Assets:Cash\n; end\n";
+    /// let options = ParseOptions::default().capture_ignored_lines(true);
+    /// let ignored: Vec<(LineKind, String)> = parse_iter_with_options::<f64>(input, &options)
+    ///     .filter_map(|entry| match entry.unwrap() {
+    ///         Entry::Ignored(line) => Some((line.kind, line.text)),
+    ///         _ => None,
+    ///     })
+    ///     .collect();
+    /// assert_eq!(
+    ///     ignored,
+    ///     [
+    ///         (LineKind::Ignorable, "* Accounts".into()),
+    ///         (LineKind::Ignorable, "#todo".into()),
+    ///         (LineKind::Comment, "; end".into()),
+    ///     ]
+    /// );
+    /// ```
+    #[must_use]
+    pub fn capture_ignored_lines(mut self, capture: bool) -> Self {
+        self.capture_ignored_lines = capture;
+        self
+    }
+
+    /// Normalize the accounts, tags, links and metadata keys to the Unicode composed form (NFC)
+    ///
+    /// The same name can be written with different characters: `ü` is a single character in composed form (NFC),
+    /// but a `u` followed by a combining diaeresis in decomposed form (NFD), which is what some macOS applications produce.
+    /// By default, the names are kept as written, so `Assets:Bücher` typed in both forms are two different accounts
+    /// (see [`lint::SimilarAccounts`](crate::lint::SimilarAccounts) to find them).
+    /// With this option, the names are normalized to NFC, and equal names share their allocation.
+    ///
+    /// The payees, narrations and string metadata values are left as written, unless [`normalize_nfc_text`](Self::normalize_nfc_text) is set.
+    /// The currencies are made of ASCII characters, so they never need to be normalized.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use beancount_parser::{parse_with_options, BeancountFile, DirectiveContent, ParseOptions};
+    /// let input = "2023-05-22 open Assets:Bu\u{308}cher\n2023-05-23 close Assets:B\u{fc}cher";
+    /// let options = ParseOptions::default().normalize_nfc(true);
+    /// let beancount: BeancountFile<f64> = parse_with_options(input, &options).unwrap();
+    /// # let DirectiveContent::Open(open) = &beancount.directives[0].content else { unreachable!() };
+    /// # let DirectiveContent::Close(close) = &beancount.directives[1].content else { unreachable!() };
+    /// assert_eq!(open.account, close.account);
+    /// assert_eq!(open.account.as_str(), "Assets:B\u{fc}cher");
+    /// ```
+    #[cfg(feature = "unicode-normalization")]
+    #[must_use]
+    pub fn normalize_nfc(mut self, normalize: bool) -> Self {
+        self.normalize_nfc = normalize;
+        self
+    }
+
+    /// Also normalize the payees, narrations and string metadata values to NFC
+    ///
+    /// It has no effect unless [`normalize_nfc`](Self::normalize_nfc) is set.
+    #[cfg(feature = "unicode-normalization")]
+    #[must_use]
+    pub fn normalize_nfc_text(mut self, normalize: bool) -> Self {
+        self.normalize_nfc_text = normalize;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_should_list_every_option() {
+        let debug = format!("{:?}", ParseOptions::default());
+        let fields: Vec<&str> = debug
+            .trim_start_matches("ParseOptions { ")
+            .split(", ")
+            .filter_map(|field| field.split_once(": ").map(|(name, _)| name))
+            .map(|name| {
+                if name == "suppressed" {
+                    "suppress"
+                } else {
+                    name
+                }
+            })
+            .collect();
+        assert_eq!(fields, ParseOptions::NAMES);
+    }
+}
@@ -0,0 +1,322 @@
+//! Grouping of directives and postings by calendar period (like months or ISO weeks)
+//!
+//! [`group_by_period`] puts anything [`Dated`] in ordered buckets, keyed by a [`PeriodKey`],
+//! and [`fill_gaps`] adds the empty buckets between the first and the last one.
+//! [`BeancountFile::activity`](crate::BeancountFile::activity) summarizes the directives of every period
+//! with [`ActivityStats`].
+//!
+//! # Example
+//!
+//! An expense-by-month report.
+//!
+//! ```
+//! use beancount_parser::{period::{self, Period}, BeancountFile, ResolveOptions};
+//! let input = r#"
+//! 2023-01-10 * "Groceries"
+//!   Expenses:Food  30 CHF
+//!   Assets:Cash
+//! 2023-01-25 * "Restaurant"
+//!   Expenses:Food  50 CHF
+//!   Assets:Cash
+//! 2023-03-02 * "Groceries"
+//!   Expenses:Food  20 CHF
+//!   Assets:Cash
+//! "#;
+//! let beancount: BeancountFile<f64> = input.parse().unwrap();
+//! let ledger = beancount.resolve(&ResolveOptions::default());
+//! let expenses = ledger.postings.iter().filter(|p| p.account.as_str().starts_with("Expenses:"));
+//!
+//! let mut months = period::group_by_period(expenses, Period::Month);
+//! period::fill_gaps(&mut months);
+//! let report: Vec<(String, f64)> = months
+//!     .iter()
+//!     .map(|(month, postings)| (month.to_string(), postings.iter().map(|p| p.amount.value).sum()))
+//!     .collect();
+//! assert_eq!(report, [("2023-01".into(), 80.0), ("2023-02".into(), 0.0), ("2023-03".into(), 20.0)]);
+//! ```
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::{Display, Formatter},
+};
+
+use crate::{
+    lint::DirectiveKind, Account, Currency, Date, Directive, DirectiveContent, ResolvedPosting,
+};
+
+/// Anything having a date, that can be grouped by [`group_by_period`]
+pub trait Dated {
+    /// Returns the date of the item
+    fn date(&self) -> Date;
+}
+
+impl Dated for Date {
+    fn date(&self) -> Date {
+        *self
+    }
+}
+
+impl<D> Dated for Directive<D> {
+    fn date(&self) -> Date {
+        self.date
+    }
+}
+
+impl<D> Dated for ResolvedPosting<D> {
+    fn date(&self) -> Date {
+        self.date
+    }
+}
+
+impl<T: Dated + ?Sized> Dated for &T {
+    fn date(&self) -> Date {
+        (**self).date()
+    }
+}
+
+/// Day of the week
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    /// Returns the day of the week of the date
+    ///
+    /// # Example
+    /// ```
+    /// # use beancount_parser::{period::Weekday, Date};
+    /// assert_eq!(Weekday::of(Date::new(2023, 5, 20)), Weekday::Saturday);
+    /// ```
+    #[must_use]
+    pub fn of(date: Date) -> Self {
+        // 1970-01-01 was a Thursday
+        Self::from_index((date.to_epoch_days() + 3).rem_euclid(7))
+    }
+
+    /// Number of days since monday (from `0` to `6`)
+    fn index(self) -> i32 {
+        self as i32
+    }
+
+    fn from_index(index: i32) -> Self {
+        match index {
+            0 => Self::Monday,
+            1 => Self::Tuesday,
+            2 => Self::Wednesday,
+            3 => Self::Thursday,
+            4 => Self::Friday,
+            5 => Self::Saturday,
+            _ => Self::Sunday,
+        }
+    }
+}
+
+/// Calendar period used to group the items by [`group_by_period`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum Period {
+    /// Weeks starting on the given day
+    ///
+    /// They are numbered like ISO-8601 weeks (which start on monday): a week belongs to the year of its fourth day,
+    /// and the first week of a year is the one containing its fourth day of January.
+    Week(Weekday),
+    /// Calendar months
+    Month,
+    /// Quarters of a year (January to March, April to June, etc.)
+    Quarter,
+    /// Calendar years
+    Year,
+}
+
+/// Identifier of a calendar period (like the month `2023-03`)
+///
+/// The keys are ordered by the start of the period.
+/// It is displayed as `2023-03` for a month, `2023-Q1` for a quarter, `2023-W09` for a week and `2023` for a year,
+/// and serialized (with the `serde` feature) as displayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PeriodKey {
+    start: Date,
+    period: Period,
+}
+
+impl PeriodKey {
+    /// Returns the key of the period containing the date
+    ///
+    /// # Example
+    /// ```
+    /// # use beancount_parser::{period::{Period, PeriodKey, Weekday}, Date};
+    /// let date = Date::new(2023, 5, 20);
+    /// assert_eq!(PeriodKey::of(date, Period::Quarter).to_string(), "2023-Q2");
+    /// assert_eq!(PeriodKey::of(date, Period::Week(Weekday::Monday)).start(), Date::new(2023, 5, 15));
+    /// ```
+    #[must_use]
+    pub fn of(date: Date, period: Period) -> Self {
+        let start = match period {
+            Period::Week(first_day) => {
+                let days = date.to_epoch_days();
+                let offset = (Weekday::of(date).index() - first_day.index()).rem_euclid(7);
+                Date::from_epoch_days(days - offset).unwrap_or(date)
+            }
+            Period::Month => Date::new(date.year, date.month, 1),
+            Period::Quarter => Date::new(date.year, date.month.saturating_sub(1) / 3 * 3 + 1, 1),
+            Period::Year => Date::new(date.year, 1, 1),
+        };
+        Self { start, period }
+    }
+
+    /// First day of the period
+    #[must_use]
+    pub fn start(self) -> Date {
+        self.start
+    }
+
+    /// Kind of period
+    #[must_use]
+    pub fn period(self) -> Period {
+        self.period
+    }
+
+    /// Returns the key of the following period
+    ///
+    /// Returns `None` if the period starts after the last year supported by [`Date`].
+    #[must_use]
+    pub fn next(self) -> Option<Self> {
+        let Date { year, month, .. } = self.start;
+        let start = match self.period {
+            Period::Week(_) => Date::from_epoch_days(self.start.to_epoch_days() + 7)?,
+            Period::Month if month >= 12 => Date::new(year.checked_add(1)?, 1, 1),
+            Period::Month => Date::new(year, month + 1, 1),
+            Period::Quarter if month >= 10 => Date::new(year.checked_add(1)?, 1, 1),
+            Period::Quarter => Date::new(year, month + 3, 1),
+            Period::Year => Date::new(year.checked_add(1)?, 1, 1),
+        };
+        Some(Self { start, ..self })
+    }
+
+    /// Returns the year the week belongs to, and its number (starting at 1)
+    fn week(self) -> (u16, u32) {
+        let days = self.start.to_epoch_days() + 3;
+        let Some(fourth_day) = Date::from_epoch_days(days) else {
+            return (self.start.year, 1);
+        };
+        let day_of_year = days - Date::new(fourth_day.year, 1, 1).to_epoch_days();
+        (fourth_day.year, day_of_year.unsigned_abs() / 7 + 1)
+    }
+}
+
+impl Display for PeriodKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let Date { year, month, .. } = self.start;
+        match self.period {
+            Period::Week(_) => {
+                let (year, week) = self.week();
+                write!(f, "{year:04}-W{week:02}")
+            }
+            Period::Month => write!(f, "{year:04}-{month:02}"),
+            Period::Quarter => write!(f, "{year:04}-Q{}", month.saturating_sub(1) / 3 + 1),
+            Period::Year => write!(f, "{year:04}"),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for PeriodKey {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Group the items by the period containing their date
+///
+/// The buckets are ordered by period, and the items of a bucket keep their order.
+/// Only the periods containing at least one item have a bucket, see [`fill_gaps`] to add the empty ones.
+///
+/// See the [module documentation](self) for an example.
+pub fn group_by_period<T: Dated>(
+    items: impl IntoIterator<Item = T>,
+    period: Period,
+) -> BTreeMap<PeriodKey, Vec<T>> {
+    let mut groups: BTreeMap<PeriodKey, Vec<T>> = BTreeMap::new();
+    for item in items {
+        groups
+            .entry(PeriodKey::of(item.date(), period))
+            .or_default()
+            .push(item);
+    }
+    groups
+}
+
+/// Add an empty bucket for every period between the first and the last bucket that has none
+///
+/// See the [module documentation](self) for an example.
+pub fn fill_gaps<V: Default>(groups: &mut BTreeMap<PeriodKey, V>) {
+    let (Some(&first), Some(&last)) = (groups.keys().next(), groups.keys().next_back()) else {
+        return;
+    };
+    let mut key = Some(first);
+    while let Some(current) = key.filter(|key| *key < last) {
+        groups.entry(current).or_default();
+        key = current.next();
+    }
+}
+
+/// Summary of the directives of a period, returned by [`BeancountFile::activity`](crate::BeancountFile::activity)
+///
+/// It is serializable with the `serde` feature.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct ActivityStats {
+    /// Number of directives of each kind (the kinds without directive are absent)
+    pub directives: BTreeMap<DirectiveKind, usize>,
+    /// Number of postings of the transactions
+    pub postings: usize,
+    /// Number of distinct accounts referenced by the directives
+    pub accounts: usize,
+    /// Number of distinct currencies referenced by the directives
+    pub currencies: usize,
+}
+
+pub(crate) fn activity<D>(
+    directives: &[Directive<D>],
+    period: Period,
+) -> Vec<(PeriodKey, ActivityStats)> {
+    // The distinct accounts and currencies are only counted once the directives of the period are all seen
+    let mut buckets: BTreeMap<PeriodKey, (ActivityStats, HashSet<&Account>, HashSet<&Currency>)> =
+        BTreeMap::new();
+    for directive in directives {
+        let (stats, accounts, currencies) = buckets
+            .entry(PeriodKey::of(directive.date, period))
+            .or_default();
+        *stats
+            .directives
+            .entry(DirectiveKind::of(&directive.content))
+            .or_default() += 1;
+        if let DirectiveContent::Transaction(trx) = &directive.content {
+            stats.postings += trx.postings.len();
+        }
+        directive.content.for_each_account(|account| {
+            accounts.insert(account);
+        });
+        directive.content.for_each_currency(|currency| {
+            currencies.insert(currency);
+        });
+    }
+    fill_gaps(&mut buckets);
+    buckets
+        .into_iter()
+        .map(|(key, (mut stats, accounts, currencies))| {
+            stats.accounts = accounts.len();
+            stats.currencies = currencies.len();
+            (key, stats)
+        })
+        .collect()
+}
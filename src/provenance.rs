@@ -0,0 +1,50 @@
+use std::{
+    fmt::{Display, Formatter},
+    panic::Location,
+};
+
+/// Origin of a directive built by a program (like an importer), instead of being parsed from a file
+///
+/// Use [`Directive::with_provenance`](crate::Directive::with_provenance) to attach it to a directive.
+/// The errors about the directive (like [`Code::BalanceMismatch`](crate::Code::BalanceMismatch)) report it
+/// instead of a line number, which is meaningless for those directives.
+///
+/// # Example
+///
+/// ```
+/// use beancount_parser::{BeancountFile, Directive};
+/// let mut beancount: BeancountFile<f64> = "2023-05-20 open Assets:Bank".parse().unwrap();
+/// let balance: Directive<f64> = "2023-05-21 balance Assets:Bank 10 CHF".parse().unwrap();
+/// beancount.directives.push(balance.with_provenance("bank statement"));
+///
+/// let errors = beancount.verify_balances();
+/// assert_eq!(errors[0].provenance().unwrap().label, "bank statement");
+/// assert!(errors[0].to_string().starts_with("error[E006]: Balance assertion failed in `bank statement` (built at "));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Provenance {
+    /// Description of what built the directive (like the name of an importer rule)
+    pub label: String,
+    /// Location in the source code where the provenance was attached
+    pub location: &'static Location<'static>,
+}
+
+impl Provenance {
+    /// Create a provenance with the given label, located at the caller
+    #[must_use]
+    #[track_caller]
+    pub fn new(label: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            location: Location::caller(),
+        }
+    }
+}
+
+impl Display for Provenance {
+    /// Formats the provenance as `` `label` (built at file:line:column) ``
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{}` (built at {})", self.label, self.location)
+    }
+}
@@ -0,0 +1,35 @@
+use nom::character::complete::space1;
+
+use crate::{string, IResult, Span};
+
+/// A query (in the beancount query language) embedded in the file
+///
+/// # Example
+/// ```
+/// # use beancount_parser::{BeancountFile, DirectiveContent};
+/// let input = r#"2023-01-01 query "cash" "SELECT account, sum(position) WHERE account ~ 'Cash'; ""#;
+/// let beancount: BeancountFile<f64> = input.parse().unwrap();
+/// let DirectiveContent::Query(ref query) = beancount.directives[0].content else { unreachable!() };
+/// assert_eq!(query.name, "cash");
+/// assert_eq!(query.query_string, "SELECT account, sum(position) WHERE account ~ 'Cash'; ");
+/// ```
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Query {
+    /// Name of the query
+    pub name: String,
+    /// Text of the query
+    pub query_string: String,
+}
+
+pub(super) fn parse(input: Span<'_>) -> IResult<'_, Query> {
+    let (input, name) = string(input)?;
+    let (input, _) = space1(input)?;
+    let (input, query_string) = string(input)?;
+    Ok((input, Query { name, query_string }))
+}
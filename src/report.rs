@@ -0,0 +1,211 @@
+use std::{
+    collections::HashSet,
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    parse_iter_with_options, Account, BeancountFile, DirectiveContent, Error, Indent, Number,
+    ParseOptions, SourceMap, Warning,
+};
+
+/// Result of [`parse_report`](crate::parse_report): the parsed file, and everything found while parsing it
+///
+/// # Example
+/// ```
+/// use beancount_parser::{parse_report, ParseOptions, ParseReport};
+/// let input = "2023-05-27 open Assets:Cash\n  id: 1\n  id: 2";
+/// let report: ParseReport<f64> = parse_report(input, &ParseOptions::default());
+/// assert!(report.errors.is_empty());
+/// assert_eq!(report.warnings.len(), 1);
+/// assert_eq!(report.stats.directives, 1);
+///
+/// let mut summary = Vec::new();
+/// report.print_summary(&mut summary).unwrap();
+/// let summary = String::from_utf8(summary).unwrap();
+/// assert!(summary.contains("1 warning\n"));
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct ParseReport<D> {
+    /// The parsed file
+    ///
+    /// In case of error, it contains what was parsed before the error.
+    /// Its warnings are moved to [`ParseReport::warnings`].
+    pub file: BeancountFile<D>,
+    /// Errors found while parsing
+    ///
+    /// The parsing stops at the first error, so there is at most one.
+    pub errors: Vec<Error>,
+    /// Warnings found while parsing
+    pub warnings: Vec<Warning>,
+    /// Statistics about the parsed file
+    pub stats: Stats,
+    /// Time spent parsing
+    pub elapsed: Duration,
+    /// Paths of the parsed files, to locate the errors and warnings
+    ///
+    /// See [`ParseOptions::source_name`]
+    pub sources: SourceMap,
+}
+
+/// Statistics of a [`ParseReport`]
+///
+/// They serialize (with the `serde` feature) as a flat object, to feed them to a metrics pipeline.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Stats {
+    /// Number of lines parsed
+    pub lines: usize,
+    /// Size of the parsed input, in bytes
+    pub bytes: usize,
+    /// Number of directives (including transactions)
+    pub directives: usize,
+    /// Number of transactions
+    pub transactions: usize,
+    /// Number of postings of all transactions
+    pub postings: usize,
+    /// Number of distinct accounts referenced by the directives
+    pub accounts: usize,
+    /// Number of options
+    pub options: usize,
+    /// Number of includes
+    pub includes: usize,
+    /// Indentation of the postings used by the most transactions (see [`Transaction::indent`](crate::Transaction::indent))
+    ///
+    /// If several indentations are used by the same number of transactions,
+    /// the one of the first of those transactions in the file is chosen.
+    /// It is `None` if no transaction has an indentation.
+    pub indent: Option<Indent>,
+}
+
+impl Stats {
+    fn of<D>(file: &BeancountFile<D>, input: &str) -> Self {
+        let mut accounts: HashSet<Account> = HashSet::new();
+        // Number of transactions using each indentation, in order of first use
+        let mut indents: Vec<(Indent, usize)> = Vec::new();
+        let mut stats = Self {
+            lines: input.lines().count(),
+            bytes: input.len(),
+            directives: file.directives.len(),
+            options: file.options.len(),
+            includes: file.includes.len(),
+            ..Self::default()
+        };
+        for directive in &file.directives {
+            directive.content.for_each_account(|account| {
+                accounts.insert(account.clone());
+            });
+            if let DirectiveContent::Transaction(trx) = &directive.content {
+                stats.transactions += 1;
+                stats.postings += trx.postings.len();
+                if let Some(indent) = trx.indent() {
+                    match indents.iter_mut().find(|(other, _)| *other == indent) {
+                        Some((_, count)) => *count += 1,
+                        None => indents.push((indent, 1)),
+                    }
+                }
+            }
+        }
+        stats.accounts = accounts.len();
+        stats.indent = indents
+            .iter()
+            .rev()
+            .max_by_key(|(_, count)| *count)
+            .map(|(indent, _)| *indent);
+        stats
+    }
+}
+
+impl<D> ParseReport<D> {
+    /// Returns true if there is no error
+    #[must_use]
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Convert the errors and the warnings into [`Diagnostic`](crate::diagnostic::Diagnostic)s (requires the `serde` feature)
+    ///
+    /// See [`diagnostic::diagnostics`](crate::diagnostic::diagnostics) to also include the findings of lints.
+    #[cfg(feature = "serde")]
+    #[must_use]
+    pub fn diagnostics(&self) -> Vec<crate::diagnostic::Diagnostic> {
+        crate::diagnostic::diagnostics(&self.sources, &self.errors, &self.warnings)
+    }
+
+    /// Write a human-readable digest of the report
+    ///
+    /// It contains the statistics, followed by every warning and error.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the writer, if any
+    pub fn print_summary(&self, mut w: impl Write) -> io::Result<()> {
+        let stats = &self.stats;
+        writeln!(
+            w,
+            "Parsed {} in {:?}",
+            plural(stats.lines, "line"),
+            self.elapsed
+        )?;
+        writeln!(
+            w,
+            "  {} ({}, {})",
+            plural(stats.directives, "directive"),
+            plural(stats.transactions, "transaction"),
+            plural(stats.postings, "posting")
+        )?;
+        writeln!(
+            w,
+            "  {}, {}, {}",
+            plural(stats.accounts, "account"),
+            plural(stats.options, "option"),
+            plural(stats.includes, "include")
+        )?;
+        writeln!(w, "{}", plural(self.warnings.len(), "warning"))?;
+        for warning in &self.warnings {
+            writeln!(w, "  {warning}")?;
+        }
+        writeln!(w, "{}", plural(self.errors.len(), "error"))?;
+        for error in &self.errors {
+            writeln!(w, "  {error}")?;
+        }
+        Ok(())
+    }
+}
+
+fn plural(count: usize, name: &str) -> String {
+    if count == 1 {
+        format!("{count} {name}")
+    } else {
+        format!("{count} {name}s")
+    }
+}
+
+pub(crate) fn parse<D: Number>(input: &str, options: &ParseOptions) -> ParseReport<D> {
+    let start = Instant::now();
+    let mut file = BeancountFile::default();
+    file.state.options = options.clone();
+    let mut errors = Vec::new();
+    for entry in parse_iter_with_options(input, options) {
+        match entry {
+            Ok(entry) => file.extend([entry]),
+            Err(err) => errors.push(err),
+        }
+    }
+    let elapsed = start.elapsed();
+    let warnings = std::mem::take(&mut file.warnings);
+    let stats = Stats::of(&file, input);
+    ParseReport {
+        file,
+        errors,
+        warnings,
+        stats,
+        elapsed,
+        sources: options
+            .source_name
+            .clone()
+            .map_or_else(SourceMap::default, SourceMap::new),
+    }
+}
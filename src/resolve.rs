@@ -0,0 +1,224 @@
+use std::{collections::BTreeSet, sync::Arc};
+
+use crate::{
+    booking::{self, Padding, Tracker},
+    metadata, Account, Amount, BeancountFile, Code, Cost, Date, DirectiveContent, Error, Evaluate,
+    Link, PostingPrice, Provenance, Tag, Warning,
+};
+
+/// Options of [`BeancountFile::resolve`]
+///
+/// # Example
+/// ```
+/// # use beancount_parser::ResolveOptions;
+/// let options = ResolveOptions::default().expand_pads(false);
+/// ```
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ResolveOptions {
+    pub(crate) expand_pads: bool,
+}
+
+impl Default for ResolveOptions {
+    fn default() -> Self {
+        Self { expand_pads: true }
+    }
+}
+
+impl ResolveOptions {
+    /// Add the postings moving the amounts of the `pad` directives (enabled by default)
+    ///
+    /// The amount of a pad is determined by the next balance assertion of the padded account,
+    /// for each currency asserted. The two postings of the padding (one for the padded account and one for the source account)
+    /// are dated at the date of the `pad` directive, flagged `P` and marked [`synthetic`](ResolvedPosting::synthetic).
+    ///
+    /// If several pads of an account come before its next balance assertion, the last one is used.
+    /// The pads that are not used are reported in [`ResolvedLedger::warnings`] (with the code [`Code::UnusedPad`]).
+    #[must_use]
+    pub fn expand_pads(mut self, expand_pads: bool) -> Self {
+        self.expand_pads = expand_pads;
+        self
+    }
+}
+
+/// Postings of a file with all their implicit fields resolved
+///
+/// See [`BeancountFile::resolve`]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ResolvedLedger<D> {
+    /// Resolved postings, sorted by date
+    pub postings: Vec<ResolvedPosting<D>>,
+    /// Transactions that could not be resolved
+    ///
+    /// Their postings are not part of [`ResolvedLedger::postings`].
+    pub errors: Vec<Error>,
+    /// Problems that did not prevent the resolution, like unused pads
+    pub warnings: Vec<Warning>,
+}
+
+/// A posting with the fields it inherits from its transaction
+///
+/// See [`BeancountFile::resolve`]
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct ResolvedPosting<D> {
+    /// Date of the transaction
+    pub date: Date,
+    /// Line number of the transaction (or of the `pad` directive)
+    pub line_number: u32,
+    /// Provenance of the transaction (or of the `pad` directive), if it was built by a program
+    ///
+    /// See [`Directive::with_provenance`](crate::Directive::with_provenance)
+    pub provenance: Option<Arc<Provenance>>,
+    /// Flag of the posting if any, otherwise the flag of the transaction (`P` for a pad)
+    pub flag: Option<char>,
+    /// Payee of the transaction
+    pub payee: Option<String>,
+    /// Narration of the transaction
+    pub narration: Option<String>,
+    /// Account modified by the posting
+    pub account: Account,
+    /// Amount of the posting, interpolated if the posting has no amount
+    pub amount: Amount<D>,
+    /// Cost of the posting, dated at the date of the transaction if the cost has no date
+    ///
+    /// The cost amount of a posting adding units at an unknown cost (like `10 HOOL {}`) is interpolated,
+    /// so that the transaction balances.
+    pub cost: Option<Cost<D>>,
+    /// Price of the posting
+    pub price: Option<PostingPrice<D>>,
+    /// Tags of the transaction (including the ones pushed with `pushtag`)
+    pub tags: BTreeSet<Tag>,
+    /// Links of the transaction
+    pub links: BTreeSet<Link>,
+    /// Metadata of the posting, completed by the metadata of the transaction
+    ///
+    /// See [`Posting::effective_metadata`](crate::Posting::effective_metadata)
+    pub metadata: metadata::Map<D>,
+    /// True if the posting does not come from a transaction of the file, but was generated (like the postings of a pad)
+    ///
+    /// The postings of the `P` transactions written in the file (like the ones produced by beancount tools)
+    /// have the same flag as the generated ones, but are not synthetic.
+    pub synthetic: bool,
+}
+
+pub(crate) fn resolve<D: Evaluate>(
+    file: &BeancountFile<D>,
+    options: &ResolveOptions,
+) -> ResolvedLedger<D> {
+    let mut ledger = ResolvedLedger {
+        postings: Vec::new(),
+        errors: Vec::new(),
+        warnings: Vec::new(),
+    };
+    let mut tracker = Tracker::new(file);
+    for directive in booking::chronological(&file.directives) {
+        if let Some(padding) = tracker.apply(directive) {
+            if options.expand_pads {
+                ledger.postings.extend(pad_postings(padding));
+            }
+        }
+        let DirectiveContent::Transaction(trx) = &directive.content else {
+            continue;
+        };
+        let booked = booking::book(trx);
+        if !booked.missing_costs.is_empty() {
+            let unknowns = booked.missing.len() + booked.missing_costs.len();
+            let hint = if unknowns > 1 {
+                format!("{unknowns} postings without amount or cost")
+            } else {
+                "the other postings do not weigh a single currency".into()
+            };
+            ledger.errors.push(
+                Error::at("", 0, directive.line_number, Code::UnresolvableCost)
+                    .with_hint(hint)
+                    .with_provenance(directive.provenance.clone()),
+            );
+            continue;
+        }
+        if booked.missing.len() > 1 {
+            ledger.errors.push(
+                Error::at("", 0, directive.line_number, Code::TooManyMissingAmounts)
+                    .with_hint(format!("{} postings without amount", booked.missing.len()))
+                    .with_provenance(directive.provenance.clone()),
+            );
+            continue;
+        }
+        let mut amounts = booked.amounts;
+        let interpolated = amounts.split_off(trx.postings.len() - booked.missing.len());
+        let mut amounts = amounts.into_iter();
+        for (position, posting) in trx.postings.iter().enumerate() {
+            let posting_amounts = if booked.missing.contains(&position) {
+                interpolated.clone()
+            } else {
+                amounts.next().into_iter().collect()
+            };
+            for (_, amount) in posting_amounts {
+                ledger.postings.push(ResolvedPosting {
+                    date: directive.date,
+                    line_number: directive.line_number,
+                    provenance: directive.provenance.clone(),
+                    flag: posting.flag.or(trx.flag),
+                    payee: trx.payee.clone(),
+                    narration: trx.narration.clone(),
+                    account: posting.account.clone(),
+                    amount,
+                    cost: posting.cost.clone().map(|cost| Cost {
+                        amount: match &booked.cost {
+                            Some((interpolated, amount)) if *interpolated == position => {
+                                Some(amount.clone())
+                            }
+                            _ => cost.amount,
+                        },
+                        date: cost.date.or(Some(directive.date)),
+                    }),
+                    price: posting.price.clone(),
+                    tags: trx.tags.iter().cloned().collect(),
+                    links: trx.links.iter().cloned().collect(),
+                    metadata: posting
+                        .effective_metadata(&directive.metadata)
+                        .map(|(key, value)| (key.clone(), value.clone()))
+                        .collect(),
+                    synthetic: false,
+                });
+            }
+        }
+    }
+    ledger.postings.sort_by_key(|posting| posting.date);
+    ledger.warnings = tracker.unused_pads();
+    ledger
+}
+
+fn pad_postings<D: Evaluate>(padding: Padding<D>) -> [ResolvedPosting<D>; 2] {
+    let Padding {
+        date,
+        line_number,
+        provenance,
+        account,
+        source_account,
+        amount,
+    } = padding;
+    let narration = format!("(Padding inserted for the balance of {account})");
+    let posting = |account: Account, amount: Amount<D>| ResolvedPosting {
+        date,
+        line_number,
+        provenance: provenance.clone(),
+        flag: Some('P'),
+        payee: None,
+        narration: Some(narration.clone()),
+        account,
+        amount,
+        cost: None,
+        price: None,
+        tags: BTreeSet::new(),
+        links: BTreeSet::new(),
+        metadata: metadata::Map::new(),
+        synthetic: true,
+    };
+    let negated = Amount {
+        value: -amount.value.clone(),
+        currency: amount.currency.clone(),
+    };
+    [posting(account, amount), posting(source_account, negated)]
+}
@@ -0,0 +1,153 @@
+use std::{ops::ControlFlow, path::PathBuf};
+
+use crate::{
+    metadata, parse_iter_with_options, BeanOption, Directive, Entry, Error, IgnoredLine, Number,
+    ParseOptions, Plugin, Tag, Warning,
+};
+
+/// Receiver of the entries parsed by [`parse_with_sink`](crate::parse_with_sink)
+///
+/// Every hook returns a [`ControlFlow`]: returning [`ControlFlow::Break`] stops the parsing.
+/// Only [`DirectiveSink::directive`] is required, the other hooks ignore their entry by default.
+///
+/// # Example
+///
+/// Insert the transactions in a table (like a database would), and stop after 2 rows.
+///
+/// ```
+/// use std::ops::ControlFlow;
+/// use beancount_parser::{parse_with_sink, Date, Directive, DirectiveContent, DirectiveSink, ParseOptions, Warning};
+///
+/// struct Table {
+///     rows: Vec<(Date, String, usize)>,
+///     max_rows: usize,
+///     warnings: usize,
+/// }
+///
+/// impl DirectiveSink<f64> for Table {
+///     fn directive(&mut self, directive: Directive<f64>) -> ControlFlow<()> {
+///         if let DirectiveContent::Transaction(trx) = directive.content {
+///             let narration = trx.narration.unwrap_or_default();
+///             self.rows.push((directive.date, narration, trx.postings.len()));
+///         }
+///         if self.rows.len() < self.max_rows {
+///             ControlFlow::Continue(())
+///         } else {
+///             ControlFlow::Break(())
+///         }
+///     }
+///
+///     fn warning(&mut self, _: Warning) -> ControlFlow<()> {
+///         self.warnings += 1;
+///         ControlFlow::Continue(())
+///     }
+/// }
+///
+/// let input = r#"
+/// 2023-05-20 open Assets:Cash
+/// 2023-05-21 * "Coffee"
+///   Expenses:Coffee  3 CHF
+///   Assets:Cash
+/// 2023-05-22 * "Groceries"
+///   Expenses:Food  30 CHF
+///   Assets:Cash
+/// 2023-05-23 * "Not inserted"
+///   Expenses:Food  10 CHF
+///   Assets:Cash
+/// "#;
+/// let mut table = Table { rows: Vec::new(), max_rows: 2, warnings: 0 };
+/// parse_with_sink(input, &ParseOptions::default(), &mut table).unwrap();
+/// assert_eq!(table.rows.len(), 2);
+/// assert_eq!(table.rows[1].1, "Groceries");
+/// assert_eq!(table.warnings, 0);
+/// ```
+pub trait DirectiveSink<D> {
+    /// Receive a directive
+    fn directive(&mut self, directive: Directive<D>) -> ControlFlow<()>;
+
+    /// Receive an option
+    fn option(&mut self, option: BeanOption) -> ControlFlow<()> {
+        let _ = option;
+        ControlFlow::Continue(())
+    }
+
+    /// Receive the path of an `include` directive
+    fn include(&mut self, path: PathBuf) -> ControlFlow<()> {
+        let _ = path;
+        ControlFlow::Continue(())
+    }
+
+    /// Receive a plugin
+    fn plugin(&mut self, plugin: Plugin) -> ControlFlow<()> {
+        let _ = plugin;
+        ControlFlow::Continue(())
+    }
+
+    /// Receive the tag of a `pushtag`
+    ///
+    /// The parser already adds the pushed tags to the transactions that follow, until they are popped.
+    fn push_tag(&mut self, tag: Tag) -> ControlFlow<()> {
+        let _ = tag;
+        ControlFlow::Continue(())
+    }
+
+    /// Receive the tag of a `poptag`
+    fn pop_tag(&mut self, tag: Tag) -> ControlFlow<()> {
+        let _ = tag;
+        ControlFlow::Continue(())
+    }
+
+    /// Receive the key and value of a `pushmeta`
+    ///
+    /// The parser already adds the pushed metadata to the directives that follow, until it is popped.
+    fn push_meta(&mut self, key: metadata::Key, value: metadata::Value<D>) -> ControlFlow<()> {
+        let _ = (key, value);
+        ControlFlow::Continue(())
+    }
+
+    /// Receive the key of a `popmeta`
+    fn pop_meta(&mut self, key: metadata::Key) -> ControlFlow<()> {
+        let _ = key;
+        ControlFlow::Continue(())
+    }
+
+    /// Receive a line ignored by the parser
+    ///
+    /// Only called with [`ParseOptions::capture_ignored_lines`].
+    fn ignored_line(&mut self, line: IgnoredLine) -> ControlFlow<()> {
+        let _ = line;
+        ControlFlow::Continue(())
+    }
+
+    /// Receive a warning
+    fn warning(&mut self, warning: Warning) -> ControlFlow<()> {
+        let _ = warning;
+        ControlFlow::Continue(())
+    }
+}
+
+/// Push the entries of [`parse_iter_with_options`] to the sink, until the end of the input or until the sink breaks
+pub(crate) fn parse<D: Number>(
+    input: &str,
+    options: &ParseOptions,
+    sink: &mut dyn DirectiveSink<D>,
+) -> Result<(), Error> {
+    for entry in parse_iter_with_options(input, options) {
+        let flow = match entry? {
+            Entry::Directive(directive) => sink.directive(directive),
+            Entry::Option(option) => sink.option(option),
+            Entry::Include(path) => sink.include(path),
+            Entry::Plugin(plugin) => sink.plugin(plugin),
+            Entry::PushTag(tag) => sink.push_tag(tag),
+            Entry::PopTag(tag) => sink.pop_tag(tag),
+            Entry::PushMeta(key, value) => sink.push_meta(key, value),
+            Entry::PopMeta(key) => sink.pop_meta(key),
+            Entry::Ignored(line) => sink.ignored_line(line),
+            Entry::Warning(warning) => sink.warning(warning),
+        };
+        if flow.is_break() {
+            break;
+        }
+    }
+    Ok(())
+}
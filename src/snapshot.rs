@@ -0,0 +1,319 @@
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    sync::{Arc, OnceLock},
+};
+
+use crate::{
+    account_tree, index, iterator::ParseState, AccountTree, BeanOption, BeancountFile, Directive,
+    DirectiveId, Link, Plugin, Tag, Warning,
+};
+
+/// View of a [`BeancountFile`] that is cheap to clone
+///
+/// Use [`BeancountFile::into_snapshot`] to get an instance of this.
+///
+/// Every directive is behind an [`Arc`], so cloning a snapshot only copies one pointer per directive,
+/// instead of the postings, strings and metadata of the directives (like an undo stack of an editor would).
+///
+/// The directives can still be modified, with copy-on-write semantics:
+/// [`Snapshot::directive_mut`] only copies the directive if it is shared with another snapshot.
+///
+/// # Example
+///
+/// ```
+/// use beancount_parser::{BeancountFile, DirectiveContent};
+/// let input = "2023-05-20 open Assets:Cash\n2023-05-21 close Assets:Cash";
+/// let beancount: BeancountFile<f64> = input.parse().unwrap();
+/// let mut snapshot = beancount.into_snapshot();
+///
+/// let undo = snapshot.clone();
+/// let id = snapshot.directives().nth(1).map(|(id, _)| id).unwrap();
+/// snapshot.directive_mut(id).unwrap().date = "2023-12-31".parse().unwrap();
+///
+/// assert_eq!(snapshot.directive(id).unwrap().date.to_string(), "2023-12-31");
+/// assert_eq!(undo.directive(id).unwrap().date.to_string(), "2023-05-21");
+/// ```
+///
+/// # Derived data
+///
+/// The indexes and the account tree ([`Snapshot::links_index`], [`Snapshot::tags_index`] and [`Snapshot::account_tree`])
+/// are computed on first use, and reused by the following calls, including the calls from other threads
+/// and from the clones of the snapshot.
+/// The methods modifying the directives ([`Snapshot::directive_mut`], [`Snapshot::push`], [`Snapshot::remove`]
+/// and [`Snapshot::retain`]) discard them, so that they are computed again from the modified directives.
+///
+/// ```
+/// use beancount_parser::BeancountFile;
+/// let input = "2023-05-20 * \"Invoice\" ^invoice-42\n2023-06-01 * \"Payment\" ^invoice-42";
+/// let beancount: BeancountFile<f64> = input.parse().unwrap();
+/// let snapshot = beancount.into_snapshot();
+///
+/// let index = std::thread::scope(|s| s.spawn(|| snapshot.links_index()).join().unwrap());
+/// assert!(std::ptr::eq(index, snapshot.links_index()));
+/// assert_eq!(index["invoice-42"].len(), 2);
+/// ```
+#[derive(Debug)]
+pub struct Snapshot<D> {
+    options: Arc<[BeanOption]>,
+    includes: Arc<[PathBuf]>,
+    plugins: Arc<[Plugin]>,
+    directives: Vec<Arc<Directive<D>>>,
+    warnings: Arc<[Warning]>,
+    state: Arc<ParseState<D>>,
+    derived: Arc<Derived>,
+}
+
+/// Data computed from the directives of a snapshot on first use
+///
+/// It is shared by the clones of the snapshot, and replaced when the directives are modified.
+#[derive(Debug, Default)]
+struct Derived {
+    links_index: OnceLock<BTreeMap<Link, Vec<DirectiveId>>>,
+    tags_index: OnceLock<BTreeMap<Tag, Vec<DirectiveId>>>,
+    account_tree: OnceLock<AccountTree>,
+    /// Number of structures computed, to check that each one is only computed once
+    #[cfg(test)]
+    computations: std::sync::atomic::AtomicUsize,
+}
+
+impl Derived {
+    #[cfg_attr(not(test), allow(clippy::unused_self))]
+    fn get<'a, T>(&self, cell: &'a OnceLock<T>, compute: impl FnOnce() -> T) -> &'a T {
+        cell.get_or_init(|| {
+            #[cfg(test)]
+            self.computations
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            compute()
+        })
+    }
+}
+
+impl<D> Clone for Snapshot<D> {
+    fn clone(&self) -> Self {
+        Self {
+            options: Arc::clone(&self.options),
+            includes: Arc::clone(&self.includes),
+            plugins: Arc::clone(&self.plugins),
+            directives: self.directives.clone(),
+            warnings: Arc::clone(&self.warnings),
+            state: Arc::clone(&self.state),
+            derived: Arc::clone(&self.derived),
+        }
+    }
+}
+
+impl<D> From<BeancountFile<D>> for Snapshot<D> {
+    fn from(file: BeancountFile<D>) -> Self {
+        Self {
+            options: file.options.into(),
+            includes: file.includes.into(),
+            plugins: file.plugins.into(),
+            directives: file.directives.into_iter().map(Arc::new).collect(),
+            warnings: file.warnings.into(),
+            state: Arc::new(file.state),
+            derived: Arc::default(),
+        }
+    }
+}
+
+impl<D> Snapshot<D> {
+    /// Options of the file (see [`BeancountFile::options`])
+    #[must_use]
+    pub fn options(&self) -> &[BeanOption] {
+        &self.options
+    }
+
+    /// Paths of the include directives (see [`BeancountFile::includes`])
+    #[must_use]
+    pub fn includes(&self) -> &[PathBuf] {
+        &self.includes
+    }
+
+    /// Plugins of the file (see [`BeancountFile::plugins`])
+    #[must_use]
+    pub fn plugins(&self) -> &[Plugin] {
+        &self.plugins
+    }
+
+    /// Warnings emitted while parsing the file (see [`BeancountFile::warnings`])
+    #[must_use]
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// Number of directives
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.directives.len()
+    }
+
+    /// Returns true if there is no directive
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.directives.is_empty()
+    }
+
+    /// Returns the directives, with their ids (in the order of [`BeancountFile::directives`])
+    pub fn directives(&self) -> impl Iterator<Item = (DirectiveId, &Directive<D>)> {
+        self.directives
+            .iter()
+            .enumerate()
+            .map(|(index, directive)| (DirectiveId::new(index), &**directive))
+    }
+
+    /// Returns the directive identified by `id`
+    ///
+    /// Returns `None` if there is no such directive.
+    #[must_use]
+    pub fn directive(&self, id: DirectiveId) -> Option<&Directive<D>> {
+        self.directives
+            .get(id.index())
+            .map(|directive| &**directive)
+    }
+
+    /// Returns, for every link (sorted by name), the directives having that link
+    ///
+    /// Computed on first use (see [`BeancountFile::links_index`] and [derived data](Snapshot#derived-data)).
+    #[must_use]
+    pub fn links_index(&self) -> &BTreeMap<Link, Vec<DirectiveId>> {
+        self.derived.get(&self.derived.links_index, || {
+            index::links(self.directives.iter().map(|d| &**d))
+        })
+    }
+
+    /// Returns, for every tag (sorted by name), the directives having that tag
+    ///
+    /// Computed on first use (see [`BeancountFile::tags_index`] and [derived data](Snapshot#derived-data)).
+    #[must_use]
+    pub fn tags_index(&self) -> &BTreeMap<Tag, Vec<DirectiveId>> {
+        self.derived.get(&self.derived.tags_index, || {
+            index::tags(self.directives.iter().map(|d| &**d))
+        })
+    }
+
+    /// Returns the hierarchy of all the accounts referenced in the directives
+    ///
+    /// Computed on first use (see [`BeancountFile::account_tree`] and [derived data](Snapshot#derived-data)).
+    #[must_use]
+    pub fn account_tree(&self) -> &AccountTree {
+        self.derived.get(&self.derived.account_tree, || {
+            account_tree::build(self.directives.iter().map(|d| &**d))
+        })
+    }
+
+    /// Returns the directive identified by `id` for modification
+    ///
+    /// If the directive is shared with another snapshot, it is copied first, leaving the other snapshots unchanged.
+    /// Returns `None` if there is no such directive.
+    ///
+    /// Discards the [derived data](Snapshot#derived-data), even if the directive is not modified.
+    pub fn directive_mut(&mut self, id: DirectiveId) -> Option<&mut Directive<D>>
+    where
+        D: Clone,
+    {
+        self.invalidate();
+        self.directives.get_mut(id.index()).map(Arc::make_mut)
+    }
+
+    /// Add a directive at the end
+    ///
+    /// Discards the [derived data](Snapshot#derived-data).
+    pub fn push(&mut self, directive: Directive<D>) {
+        self.invalidate();
+        self.directives.push(Arc::new(directive));
+    }
+
+    /// Remove the directive identified by `id`, shifting the ids of the directives after it
+    ///
+    /// Returns `None` if there is no such directive.
+    /// Discards the [derived data](Snapshot#derived-data) if a directive is removed.
+    pub fn remove(&mut self, id: DirectiveId) -> Option<Arc<Directive<D>>> {
+        (id.index() < self.directives.len()).then(|| {
+            self.invalidate();
+            self.directives.remove(id.index())
+        })
+    }
+
+    /// Keep only the directives for which `keep` returns true
+    ///
+    /// Discards the [derived data](Snapshot#derived-data).
+    pub fn retain(&mut self, mut keep: impl FnMut(&Directive<D>) -> bool) {
+        self.invalidate();
+        self.directives.retain(|directive| keep(directive));
+    }
+
+    /// Discard the derived data of this snapshot, leaving the one of the other snapshots unchanged
+    fn invalidate(&mut self) {
+        self.derived = Arc::default();
+    }
+
+    /// Convert back to a [`BeancountFile`]
+    ///
+    /// Only the directives that are shared with another snapshot are copied.
+    #[must_use]
+    pub fn into_file(self) -> BeancountFile<D>
+    where
+        D: Clone,
+    {
+        BeancountFile {
+            options: self.options.to_vec(),
+            includes: self.includes.to_vec(),
+            plugins: self.plugins.to_vec(),
+            directives: self
+                .directives
+                .into_iter()
+                .map(|directive| Arc::try_unwrap(directive).unwrap_or_else(|d| (*d).clone()))
+                .collect(),
+            warnings: self.warnings.to_vec(),
+            state: Arc::try_unwrap(self.state).unwrap_or_else(|state| (*state).clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use super::*;
+
+    const INPUT: &str = r#"
+2023-05-20 open Assets:Cash
+2023-05-21 * "Coffee" ^receipt-1 #food
+  Expenses:Food  3 CHF
+  Assets:Cash
+"#;
+
+    fn snapshot() -> Snapshot<f64> {
+        INPUT.parse::<BeancountFile<f64>>().unwrap().into_snapshot()
+    }
+
+    #[test]
+    fn derived_data_is_computed_once_across_threads() {
+        let snapshot = snapshot();
+        std::thread::scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|| {
+                    for _ in 0..100 {
+                        assert_eq!(snapshot.links_index().len(), 1);
+                        assert_eq!(snapshot.tags_index().len(), 1);
+                        assert!(snapshot.account_tree().get("Expenses:Food").is_some());
+                    }
+                });
+            }
+        });
+        assert_eq!(snapshot.derived.computations.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn clones_share_derived_data_until_modified() {
+        let snapshot = snapshot();
+        let mut clone = snapshot.clone();
+        let _ = snapshot.links_index();
+        assert!(std::ptr::eq(snapshot.links_index(), clone.links_index()));
+        clone.retain(|directive| directive.date.day == 20);
+        assert!(clone.links_index().is_empty());
+        assert_eq!(snapshot.links_index().len(), 1);
+        assert_eq!(snapshot.derived.computations.load(Ordering::SeqCst), 1);
+    }
+}
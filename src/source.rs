@@ -0,0 +1,161 @@
+use std::{
+    fmt::{Display, Formatter},
+    path::{Path, PathBuf},
+};
+
+use crate::{Error, Warning};
+
+/// Identifier of a source file, in a [`SourceMap`]
+///
+/// Every [`Error`] and [`Warning`] carries the identifier of the file it was found in,
+/// so that it can be attributed to a path without storing the path in every diagnostic.
+///
+/// The input of single-file parsing (like [`parse`](crate::parse)) is always [`FileId::MAIN`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FileId(u32);
+
+impl FileId {
+    /// Identifier of the input given to the single-file parsing functions
+    pub const MAIN: Self = Self(0);
+
+    /// Returns the index of the file in its [`SourceMap`]
+    #[must_use]
+    pub fn index(self) -> u32 {
+        self.0
+    }
+}
+
+/// Table of the paths of the source files, indexed by [`FileId`]
+///
+/// # Example
+/// ```
+/// use beancount_parser::{parse_report, ParseOptions, ParseReport};
+/// let options = ParseOptions::default().source_name("main.beancount");
+/// let report: ParseReport<f64> = parse_report("2023-05-27 open oops", &options);
+/// let error = &report.errors[0];
+/// assert_eq!(report.sources.resolve(error.file_id()).to_str(), Some("main.beancount"));
+/// assert_eq!(
+///     report.sources.display(error).to_string(),
+///     "main.beancount:1:17: error[E001]: Invalid beancount syntax at line: 1"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceMap {
+    paths: Vec<PathBuf>,
+}
+
+impl Default for SourceMap {
+    fn default() -> Self {
+        Self::new("<input>")
+    }
+}
+
+impl SourceMap {
+    /// Create a table whose [`FileId::MAIN`] is the file at `main`
+    #[must_use]
+    pub fn new(main: impl Into<PathBuf>) -> Self {
+        Self {
+            paths: vec![main.into()],
+        }
+    }
+
+    /// Returns the path of the file
+    ///
+    /// # Panics
+    ///
+    /// Panics if the identifier does not belong to this table
+    #[must_use]
+    pub fn resolve(&self, file_id: FileId) -> &Path {
+        &self.paths[file_id.0 as usize]
+    }
+
+    /// Returns the path of the file, or `None` if the identifier does not belong to this table
+    pub(crate) fn path(&self, file_id: FileId) -> Option<&Path> {
+        self.paths.get(file_id.0 as usize).map(PathBuf::as_path)
+    }
+
+    /// Returns the identifier of the file at `path`, adding it to the table if needed
+    pub fn insert(&mut self, path: impl Into<PathBuf>) -> FileId {
+        let path = path.into();
+        let index = self
+            .paths
+            .iter()
+            .position(|p| *p == path)
+            .unwrap_or_else(|| {
+                self.paths.push(path);
+                self.paths.len() - 1
+            });
+        FileId(u32::try_from(index).unwrap_or(u32::MAX))
+    }
+
+    /// Display the diagnostic prefixed with its location (`path:line:column`, or `path:line` if the column is unknown)
+    #[must_use]
+    pub fn display<'a, T: Locate>(&'a self, diagnostic: &'a T) -> Located<'a, T> {
+        Located {
+            sources: self,
+            diagnostic,
+        }
+    }
+}
+
+/// Location of a diagnostic in the source files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Location {
+    /// File of the diagnostic
+    pub file_id: FileId,
+    /// Line number (starting at 1)
+    pub line_number: u32,
+    /// Column (in characters, starting at 1), if known
+    pub column: Option<u32>,
+}
+
+/// A diagnostic ([`Error`] or [`Warning`]) that can be located in the source files
+pub trait Locate: Display {
+    /// Returns the location of the diagnostic
+    fn location(&self) -> Location;
+}
+
+impl Locate for Error {
+    fn location(&self) -> Location {
+        Location {
+            file_id: self.file_id(),
+            line_number: self.line_number(),
+            column: self.column(),
+        }
+    }
+}
+
+impl Locate for Warning {
+    fn location(&self) -> Location {
+        Location {
+            file_id: self.file_id(),
+            line_number: self.line_number(),
+            column: None,
+        }
+    }
+}
+
+/// Diagnostic displayed with its location
+///
+/// See [`SourceMap::display`]
+#[derive(Debug, Clone, Copy)]
+pub struct Located<'a, T> {
+    sources: &'a SourceMap,
+    diagnostic: &'a T,
+}
+
+impl<T: Locate> Display for Located<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let location = self.diagnostic.location();
+        let path = self
+            .sources
+            .path(location.file_id)
+            .unwrap_or(Path::new("<unknown>"));
+        write!(f, "{}:{}", path.display(), location.line_number)?;
+        if let Some(column) = location.column {
+            write!(f, ":{column}")?;
+        }
+        write!(f, ": {}", self.diagnostic)
+    }
+}
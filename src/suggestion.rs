@@ -0,0 +1,122 @@
+use std::collections::HashSet;
+
+use crate::{
+    booking, merge::transfer_posting, Account, Amount, Balance, BeancountFile, Date, Directive,
+    DirectiveContent, Evaluate, Pad, Transaction,
+};
+
+/// Comparison of an observed balance with the balance computed from a file
+///
+/// See [`BeancountFile::suggest_balance`] for an example.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum BalanceSuggestion<D> {
+    /// The observed amount is the computed balance: a `balance` assertion of it passes as is
+    Matches,
+    /// The observed amount is not the computed balance
+    ///
+    /// A `balance` assertion of it fails, unless it is preceded by an adjustment (see [`BalanceAdjustment`]).
+    Differs {
+        /// Observed amount minus the computed balance
+        delta: Amount<D>,
+    },
+    /// The account is not open at the date
+    ///
+    /// It is neither opened by an `open` directive nor used by a posting before the date, or it is closed.
+    UnknownAccount,
+}
+
+/// Directive making a `balance` assertion pass, when the observed balance [differs](BalanceSuggestion::Differs)
+///
+/// See [`Directive::balance_adjustment`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum BalanceAdjustment {
+    /// A `pad` directive, filling the account from the given source account
+    Pad(Account),
+    /// A transaction flagged `!`, moving the difference from the given source account
+    Transaction(Account),
+}
+
+pub(crate) fn suggest_balance<D: Evaluate>(
+    file: &BeancountFile<D>,
+    account: &Account,
+    date: Date,
+    observed: &Amount<D>,
+) -> BalanceSuggestion<D> {
+    let (mut opened, mut closed) = (false, false);
+    for directive in &file.directives {
+        match &directive.content {
+            DirectiveContent::Open(open) if open.account == *account => {
+                opened = opened || directive.date <= date;
+            }
+            DirectiveContent::Close(close) if close.account == *account => {
+                closed = closed || directive.date < date;
+            }
+            _ => (),
+        }
+    }
+    let computed = booking::asserted_balance(file, account, &observed.currency, date);
+    let computed = match computed {
+        _ if closed => return BalanceSuggestion::UnknownAccount,
+        Some(computed) => computed,
+        None if opened => D::default(),
+        None => return BalanceSuggestion::UnknownAccount,
+    };
+    if computed == observed.value {
+        BalanceSuggestion::Matches
+    } else {
+        BalanceSuggestion::Differs {
+            delta: Amount {
+                value: observed.value.clone() - computed,
+                currency: observed.currency.clone(),
+            },
+        }
+    }
+}
+
+pub(crate) fn balance<D>(date: Date, account: Account, amount: Amount<D>) -> Directive<D> {
+    Directive::new(
+        date,
+        DirectiveContent::Balance(Balance {
+            account,
+            amount,
+            tolerance: None,
+        }),
+    )
+}
+
+pub(crate) fn adjustment<D: Evaluate>(
+    date: Date,
+    account: Account,
+    delta: Amount<D>,
+    adjustment: BalanceAdjustment,
+) -> Directive<D> {
+    // The adjustment must take effect before the assertion, which checks the balance at the beginning of its day
+    let date = Date::from_epoch_days(date.to_epoch_days() - 1).unwrap_or(date);
+    let content = match adjustment {
+        BalanceAdjustment::Pad(source_account) => DirectiveContent::Pad(Pad {
+            account,
+            source_account,
+        }),
+        BalanceAdjustment::Transaction(source_account) => {
+            let source = transfer_posting(
+                &source_account,
+                Amount {
+                    value: -delta.value.clone(),
+                    currency: delta.currency.clone(),
+                },
+            );
+            DirectiveContent::Transaction(Transaction {
+                flag: Some('!'),
+                payee: None,
+                narration: Some("Balance adjustment".into()),
+                tags: HashSet::new(),
+                links: HashSet::new(),
+                postings: vec![transfer_posting(&account, delta), source],
+                indent: None,
+            })
+        }
+    };
+    Directive::new(date, content)
+}
@@ -0,0 +1,83 @@
+use std::{
+    collections::HashSet,
+    fmt::{Display, Formatter, Result, Write},
+};
+
+use crate::{metadata, Amount, Cost, CustomValue, Link, Quoted, Tag};
+
+/// Display adapter writing an amount as `value currency`
+pub(crate) struct AmountText<'a, D>(pub(crate) &'a Amount<D>);
+
+impl<D: Display> Display for AmountText<'_, D> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{} {}", self.0.value, self.0.currency)
+    }
+}
+
+/// Display adapter writing a cost between braces, like `{1.1 CHF, 2023-01-01}`
+pub(crate) struct CostText<'a, D>(pub(crate) &'a Cost<D>);
+
+impl<D: Display> Display for CostText<'_, D> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.write_char('{')?;
+        match (&self.0.amount, &self.0.date) {
+            (Some(amount), Some(date)) => write!(f, "{}, {date}", AmountText(amount))?,
+            (Some(amount), None) => write!(f, "{}", AmountText(amount))?,
+            (None, Some(date)) => write!(f, "{date}")?,
+            (None, None) => (),
+        }
+        f.write_char('}')
+    }
+}
+
+/// Display adapter writing a value of a custom directive
+pub(crate) struct CustomValueText<'a, D>(pub(crate) &'a CustomValue<D>);
+
+impl<D: Display> Display for CustomValueText<'_, D> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self.0 {
+            CustomValue::String(s) => write!(f, "{}", Quoted(s)),
+            CustomValue::Date(date) => write!(f, "{date}"),
+            CustomValue::Bool(true) => f.write_str("TRUE"),
+            CustomValue::Bool(false) => f.write_str("FALSE"),
+            CustomValue::Amount(amount) => write!(f, "{}", AmountText(amount)),
+            CustomValue::Number(n) => write!(f, "{n}"),
+            CustomValue::Account(account) => write!(f, "{account}"),
+        }
+    }
+}
+
+/// Display adapter writing the value of a metadata entry
+pub(crate) struct MetadataValueText<'a, D>(pub(crate) &'a metadata::Value<D>);
+
+impl<D: Display> Display for MetadataValueText<'_, D> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self.0 {
+            metadata::Value::String(s) => write!(f, "{}", Quoted(s)),
+            metadata::Value::Number(n) => write!(f, "{n}"),
+            metadata::Value::Currency(c) => write!(f, "{c}"),
+        }
+    }
+}
+
+/// Display adapter writing the tags and then the links, sorted by name and each preceded by a space
+pub(crate) struct TagsAndLinks<'a> {
+    pub(crate) tags: &'a HashSet<Tag>,
+    pub(crate) links: &'a HashSet<Link>,
+}
+
+impl Display for TagsAndLinks<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let mut tags: Vec<&Tag> = self.tags.iter().collect();
+        tags.sort();
+        for tag in tags {
+            write!(f, " #{tag}")?;
+        }
+        let mut links: Vec<&Link> = self.links.iter().collect();
+        links.sort();
+        for link in links {
+            write!(f, " ^{link}")?;
+        }
+        Ok(())
+    }
+}
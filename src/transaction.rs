@@ -1,23 +1,25 @@
 use std::borrow::Borrow;
 use std::collections::HashSet;
-use std::fmt::{Display, Formatter};
+use std::fmt::{Display, Formatter, Write};
 use std::sync::Arc;
 
 use nom::{
     branch::alt,
-    bytes::complete::{tag, take_while},
+    bytes::complete::{tag, take, take_till, take_till1, take_while},
     character::complete::satisfy,
-    character::complete::{char as char_tag, space0, space1},
-    combinator::{cut, iterator, map, opt, success, value},
+    character::complete::{char as char_tag, one_of, space0, space1},
+    combinator::{cond, cut, iterator, map, not, opt, recognize, success, value},
+    multi::fold_many0,
     sequence::{delimited, preceded, separated_pair, terminated, tuple},
     Parser,
 };
 
-use crate::string;
 use crate::{
-    account, account::Account, amount, amount::Amount, date, empty_line, end_of_line, metadata,
-    Date, Decimal, IResult, Span,
+    account, account::Account, amount, amount::Amount, amount::Currency, date, empty_line,
+    end_of_line, invalid_line, merge, metadata, Code, Date, Error, Evaluate, IResult, InvalidLine,
+    MergeError, MergePolicy, Number, Span,
 };
+use crate::{string, syntax::TagsAndLinks, unicode, Quoted};
 
 /// A transaction
 ///
@@ -42,6 +44,11 @@ use crate::{
 /// assert!(trx.tags.contains("food"));
 /// assert_eq!(trx.postings.len(), 2);
 /// ```
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
 #[derive(Debug, Clone, PartialEq)]
 #[non_exhaustive]
 pub struct Transaction<D> {
@@ -57,6 +64,239 @@ pub struct Transaction<D> {
     pub links: HashSet<Link>,
     /// Postings
     pub postings: Vec<Posting<D>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) indent: Option<Indent>,
+}
+
+impl<D> Transaction<D> {
+    /// Returns the indentation of the first posting, as found in the parsed input
+    ///
+    /// Returns `None` if the transaction has no posting, if the indentation mixes spaces and tabs,
+    /// or if the transaction was not parsed. See [`Stats::indent`](crate::Stats::indent) for the style of a whole file.
+    #[must_use]
+    pub fn indent(&self) -> Option<Indent> {
+        self.indent
+    }
+
+    /// Returns the header line of the transaction (date, flag, payee, narration, tags and links)
+    ///
+    /// It is in canonical form: payee and narration are quoted and escaped, tags and links are sorted,
+    /// and a transaction without flag uses the `txn` keyword.
+    /// The postings and the metadata are not included.
+    ///
+    /// The result is a valid beancount header line that parses back to the same header.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use beancount_parser::{BeancountFile, DirectiveContent};
+    /// let input = r#"
+    /// 2023-05-22 * "Coop" "Groceries \"bio\"" #food ^receipt-42 #shop
+    ///   Expenses:Groceries   10 CHF
+    ///   Assets:Cash
+    /// "#;
+    /// let beancount: BeancountFile<f64> = input.parse().unwrap();
+    /// let directive = &beancount.directives[0];
+    /// let DirectiveContent::Transaction(trx) = &directive.content else { unreachable!() };
+    /// assert_eq!(
+    ///     trx.summary(directive.date),
+    ///     r#"2023-05-22 * "Coop" "Groceries \"bio\"" #food #shop ^receipt-42"#
+    /// );
+    /// ```
+    #[must_use]
+    pub fn summary(&self, date: Date) -> String {
+        self.header(date).to_string()
+    }
+
+    /// Returns a value implementing [`Display`] to write the header line of the transaction
+    ///
+    /// It writes the same as [`Transaction::summary`] without allocating a string.
+    #[must_use]
+    pub fn header(&self, date: Date) -> TransactionHeader<'_, D> {
+        TransactionHeader {
+            date,
+            transaction: self,
+            hidden_tags: None,
+        }
+    }
+
+    /// Split the transaction in two, moving the postings at the given positions (starting at 0) to the second one
+    ///
+    /// Both transactions keep the flag, payee, narration, tags and links, and the postings keep their order,
+    /// flags and metadata. Each transaction is rebalanced with postings to `transfer_account` (one per currency),
+    /// that sum to zero across the two transactions if this one is balanced.
+    /// A transaction keeping the posting without amount (if any) balances itself.
+    /// Positions out of range are ignored.
+    ///
+    /// # Example
+    /// ```
+    /// # use beancount_parser::{Account, BeancountFile, DirectiveContent};
+    /// let input = r#"
+    /// 2023-05-20 * "Shop" "Food and drinks"
+    ///   Expenses:Food    30 CHF
+    ///   Expenses:Drinks  10 CHF
+    ///   Assets:Cash     -40 CHF
+    /// "#;
+    /// let beancount: BeancountFile<f64> = input.parse().unwrap();
+    /// let DirectiveContent::Transaction(trx) = beancount.directives[0].content.clone() else { unreachable!() };
+    /// let transfer: Account = "Equity:Transfer".parse().unwrap();
+    /// let (food, drinks) = trx.split(&[1], &transfer);
+    /// let postings: Vec<(&str, f64)> = drinks
+    ///     .postings
+    ///     .iter()
+    ///     .map(|p| (p.account.as_str(), p.amount.as_ref().unwrap().value))
+    ///     .collect();
+    /// assert_eq!(postings, [("Expenses:Drinks", 10.0), ("Equity:Transfer", -10.0)]);
+    /// assert_eq!(food.postings.len(), 3);
+    /// ```
+    #[must_use]
+    pub fn split(self, posting_indices: &[usize], transfer_account: &Account) -> (Self, Self)
+    where
+        D: Evaluate,
+    {
+        merge::split(self, posting_indices, transfer_account)
+    }
+
+    /// Merge the other transaction into this one
+    ///
+    /// The postings of the other transaction are appended (keeping their flags and metadata),
+    /// the tags and links are the union of both, and the narrations are joined with
+    /// the [separator](MergePolicy::narration_separator) (unless they are equal).
+    /// The merged transaction is flagged `!` if one of them is, and otherwise has the flag of this transaction.
+    /// The date is not part of the transaction: merging transactions of the same day is up to the caller.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MergeError::ConflictingPayees`] if both transactions have a payee and they differ,
+    /// unless the policy is [forced](MergePolicy::force).
+    ///
+    /// # Example
+    /// ```
+    /// # use beancount_parser::{BeancountFile, DirectiveContent, MergePolicy};
+    /// let input = r#"
+    /// 2023-05-20 * "Shop" "Food" #trip
+    ///   Expenses:Food  30 CHF
+    ///   Assets:Cash
+    /// 2023-05-20 * "Shop" "Drinks" #trip #bar
+    ///   Expenses:Drinks  10 CHF
+    ///   Assets:Cash
+    /// "#;
+    /// let beancount: BeancountFile<f64> = input.parse().unwrap();
+    /// let [DirectiveContent::Transaction(food), DirectiveContent::Transaction(drinks)] =
+    ///     [0, 1].map(|i| beancount.directives[i].content.clone()) else { unreachable!() };
+    /// let merged = food.merge(drinks, &MergePolicy::default()).unwrap();
+    /// assert_eq!(merged.narration.as_deref(), Some("Food / Drinks"));
+    /// assert_eq!(merged.tags.len(), 2);
+    /// assert_eq!(merged.postings.len(), 4);
+    /// ```
+    pub fn merge(self, other: Self, policy: &MergePolicy) -> Result<Self, MergeError> {
+        merge::merge(self, other, policy)
+    }
+}
+
+/// Indentation of the postings of a transaction
+///
+/// See [`Transaction::indent`]. It is displayed as the whitespace it stands for.
+///
+/// # Example
+/// ```
+/// # use beancount_parser::{Directive, DirectiveContent, Indent};
+/// let directive: Directive<f64> = "2023-05-20 * \"Coffee\"\n    Expenses:Food  3 CHF\n    Assets:Cash".parse().unwrap();
+/// let DirectiveContent::Transaction(trx) = &directive.content else { unreachable!() };
+/// assert_eq!(trx.indent(), Some(Indent::Spaces(4)));
+/// assert_eq!(trx.indent().unwrap().to_string(), "    ");
+/// ```
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Indent {
+    /// Number of spaces
+    Spaces(u8),
+    /// Number of tabs
+    Tabs(u8),
+}
+
+impl Indent {
+    /// Returns the indentation made of the whitespace, or `None` if it mixes spaces and tabs
+    fn of(whitespace: &str) -> Option<Self> {
+        let count = u8::try_from(whitespace.len()).unwrap_or(u8::MAX);
+        if whitespace.bytes().all(|c| c == b' ') {
+            Some(Self::Spaces(count))
+        } else if whitespace.bytes().all(|c| c == b'\t') {
+            Some(Self::Tabs(count))
+        } else {
+            None
+        }
+    }
+}
+
+impl Display for Indent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (c, count) = match self {
+            Self::Spaces(count) => (' ', count),
+            Self::Tabs(count) => ('\t', count),
+        };
+        (0..*count).try_for_each(|_| f.write_char(c))
+    }
+}
+
+/// Header line of a transaction
+///
+/// See [`Transaction::header`]
+#[derive(Debug)]
+pub struct TransactionHeader<'a, D> {
+    date: Date,
+    transaction: &'a Transaction<D>,
+    hidden_tags: Option<&'a HashSet<Tag>>,
+}
+
+impl<'a, D> TransactionHeader<'a, D> {
+    /// Leave out the given tags (like the ones added by `pushtag`)
+    pub(crate) fn without_tags(mut self, tags: &'a HashSet<Tag>) -> Self {
+        self.hidden_tags = Some(tags);
+        self
+    }
+}
+
+impl<D> Display for TransactionHeader<'_, D> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let trx = self.transaction;
+        write!(f, "{}", self.date)?;
+        match trx.flag {
+            Some(flag) => write!(f, " {flag}")?,
+            None => f.write_str(" txn")?,
+        }
+        match (&trx.payee, &trx.narration) {
+            (Some(payee), narration) => write!(
+                f,
+                " {} {}",
+                Quoted(payee),
+                Quoted(narration.as_deref().unwrap_or_default())
+            )?,
+            (None, Some(narration)) => write!(f, " {}", Quoted(narration))?,
+            (None, None) => (),
+        }
+        let shown_tags: HashSet<Tag>;
+        let tags = match self.hidden_tags {
+            Some(hidden) => {
+                shown_tags = trx.tags.difference(hidden).cloned().collect();
+                &shown_tags
+            }
+            None => &trx.tags,
+        };
+        write!(
+            f,
+            "{}",
+            TagsAndLinks {
+                tags,
+                links: &trx.links,
+            }
+        )
+    }
 }
 
 /// A transaction posting
@@ -86,7 +326,12 @@ pub struct Transaction<D> {
 /// assert_eq!(price.value, 3.0);
 /// assert_eq!(price.currency.as_str(), "EUR");
 /// ```
-#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields, bound(deserialize = "D: serde::Deserialize<'de>"))
+)]
+#[derive(Debug, Clone)]
 #[non_exhaustive]
 pub struct Posting<D> {
     /// Transaction flag (`*` or `!` or `None` when absent)
@@ -100,12 +345,185 @@ pub struct Posting<D> {
     /// Price (`@` or `@@`) syntax
     pub price: Option<PostingPrice<D>>,
     /// The metadata attached to the posting
+    #[cfg_attr(feature = "serde", serde(default))]
     pub metadata: metadata::Map<D>,
+    /// Line number where the posting was found in the input file (`0` for a posting built by a program)
+    ///
+    /// It is not compared by `==`: two postings with the same content are equal wherever they are.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub line_number: u32,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) metadata_duplicates: metadata::Duplicates<D>,
+    /// Tags and links found at the end of the posting line (they are rare, so they are boxed)
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) misplaced_tags: Option<Box<[MisplacedTag]>>,
+    /// Tokens found on the posting line that are not part of the posting (they are rare, so they are boxed)
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) stray_tokens: Option<Box<[StrayToken]>>,
+    /// Cost or price written in a syntax that is not supported (it is rare, so it is boxed)
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) unsupported_cost: Option<Box<UnsupportedCost>>,
+}
+
+impl<D: PartialEq> PartialEq for Posting<D> {
+    fn eq(&self, other: &Self) -> bool {
+        self.flag == other.flag
+            && self.account == other.account
+            && self.amount == other.amount
+            && self.cost == other.cost
+            && self.price == other.price
+            && self.metadata == other.metadata
+            && self.metadata_duplicates == other.metadata_duplicates
+            && self.misplaced_tags == other.misplaced_tags
+            && self.stray_tokens == other.stray_tokens
+            && self.unsupported_cost == other.unsupported_cost
+    }
+}
+
+/// Tag or link found at the end of a posting line, where beancount does not allow them
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct MisplacedTag {
+    /// The tag or link, with its `#` or `^` prefix
+    pub(crate) text: String,
+    pub(crate) line_number: u32,
+    pub(crate) offset: usize,
+}
+
+impl MisplacedTag {
+    /// Returns the hint explaining how to fix it
+    pub(crate) fn hint(&self) -> String {
+        let kind = if self.text.starts_with('^') {
+            "links"
+        } else {
+            "tags"
+        };
+        format!(
+            "{kind} are not allowed on postings; move {} to the transaction header",
+            self.text
+        )
+    }
+
+    /// Returns the error reporting it in the source
+    pub(crate) fn error(&self, source: &str) -> Error {
+        Error::at(source, self.offset, self.line_number, Code::TagOnPosting).with_hint(self.hint())
+    }
+}
+
+/// Token found on a posting line, that is not part of the posting (like the `CHF` of `10 USD CHF`)
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct StrayToken {
+    pub(crate) text: String,
+    /// What the token looks like (like `second currency`), if it is recognized
+    pub(crate) kind: Option<&'static str>,
+    pub(crate) line_number: u32,
+    pub(crate) offset: usize,
+}
+
+impl StrayToken {
+    /// Returns the hint naming what was found
+    pub(crate) fn hint(&self) -> String {
+        match self.kind {
+            Some(kind) => format!("unexpected {kind} `{}`", self.text),
+            None => format!("unexpected `{}`", self.text),
+        }
+    }
+
+    /// Returns the error reporting it in the source
+    pub(crate) fn error(&self, source: &str) -> Error {
+        Error::at(
+            source,
+            self.offset,
+            self.line_number,
+            Code::StrayPostingToken,
+        )
+        .with_hint(self.hint())
+    }
+}
+
+/// Cost or price of a posting written in a syntax that is not supported (like `{2 USD, "lot"}`)
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct UnsupportedCost {
+    /// The text from the opening `{` or `@` to the end of the line
+    pub(crate) text: String,
+    pub(crate) line_number: u32,
+    pub(crate) offset: usize,
+}
+
+impl UnsupportedCost {
+    /// Returns the error reporting it in the source
+    pub(crate) fn error(&self, source: &str) -> Error {
+        Error::at(
+            source,
+            self.offset,
+            self.line_number,
+            Code::UnsupportedCostSyntax,
+        )
+        .with_hint(format!("unsupported cost or price syntax `{}`", self.text))
+    }
+}
+
+impl<D> Posting<D> {
+    /// Returns the values of all the occurrences of the metadata key, in declaration order
+    ///
+    /// The first one is the value found in [`Posting::metadata`].
+    /// See the [`metadata`] module for more about duplicate keys.
+    pub fn metadata_all<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> impl Iterator<Item = &'a metadata::Value<D>> {
+        metadata::all(&self.metadata, &self.metadata_duplicates, key)
+    }
+
+    /// Returns the metadata of the posting, completed by the metadata of its transaction
+    ///
+    /// The entries of the posting come first. Then come the entries of `transaction_metadata`
+    /// for the keys the posting doesn't define: a key defined on the posting always takes precedence.
+    /// Neither map is cloned, and no allocation is made.
+    ///
+    /// # Example
+    /// ```
+    /// # use beancount_parser::{BeancountFile, DirectiveContent, metadata::Value};
+    /// let input = r#"
+    /// 2023-05-27 * "Invoice"
+    ///   document: "invoice.pdf"
+    ///   statement: "2023-05.pdf"
+    ///   Assets:Cash  10 CHF
+    ///     document: "receipt.pdf"
+    ///   Income:Sales
+    /// "#;
+    /// let beancount: BeancountFile<f64> = input.parse().unwrap();
+    /// let directive = &beancount.directives[0];
+    /// let DirectiveContent::Transaction(trx) = &directive.content else { unreachable!() };
+    /// let mut metadata: Vec<_> = trx.postings[0]
+    ///     .effective_metadata(&directive.metadata)
+    ///     .map(|(key, value)| (key.as_ref(), value))
+    ///     .collect();
+    /// metadata.sort_by_key(|(key, _)| *key);
+    /// assert_eq!(metadata, [
+    ///     ("document", &Value::String("receipt.pdf".into())),
+    ///     ("statement", &Value::String("2023-05.pdf".into())),
+    /// ]);
+    /// ```
+    pub fn effective_metadata<'a>(
+        &'a self,
+        transaction_metadata: &'a metadata::Map<D>,
+    ) -> impl Iterator<Item = (&'a metadata::Key, &'a metadata::Value<D>)> {
+        self.metadata.iter().chain(
+            transaction_metadata
+                .iter()
+                .filter(|(key, _)| !self.metadata.contains_key(*key)),
+        )
+    }
 }
 
 /// Cost of a posting
 ///
 /// It is the amount within `{` and `}`.
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
 #[derive(Debug, Default, Clone, PartialEq)]
 #[non_exhaustive]
 pub struct Cost<D> {
@@ -115,9 +533,53 @@ pub struct Cost<D> {
     pub date: Option<Date>,
 }
 
+impl<D> Cost<D> {
+    /// Returns the problems of the cost, for a posting whose units are in `posting_currency`
+    ///
+    /// The parser accepts any cost it can read. This checks that the cost also makes sense for the posting.
+    /// The parser reports the issues as [`Warning`](crate::Warning)s.
+    ///
+    /// # Example
+    /// ```
+    /// # use beancount_parser::{Cost, CostIssue, Currency, Directive, DirectiveContent};
+    /// let input = "2023-01-01 *\n  Assets:Stock  10 HOOL {10 HOOL}\n  Assets:Cash";
+    /// let directive: Directive<f64> = input.parse().unwrap();
+    /// let DirectiveContent::Transaction(trx) = &directive.content else { unreachable!() };
+    /// let posting = &trx.postings[0];
+    /// let currency = &posting.amount.as_ref().unwrap().currency;
+    /// let issues = posting.cost.as_ref().unwrap().validate(currency);
+    /// assert_eq!(issues, [CostIssue::SameCurrency]);
+    /// ```
+    #[must_use]
+    pub fn validate(&self, posting_currency: &Currency) -> Vec<CostIssue> {
+        let mut issues = Vec::new();
+        if self
+            .amount
+            .as_ref()
+            .is_some_and(|amount| &amount.currency == posting_currency)
+        {
+            issues.push(CostIssue::SameCurrency);
+        }
+        issues
+    }
+}
+
+/// Problem found by [`Cost::validate`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum CostIssue {
+    /// The cost is in the currency of the units of the posting (like `10 HOOL {10 HOOL}`)
+    SameCurrency,
+}
+
 /// Price of a posting
 ///
 /// It is the amount following the `@` or `@@` symbols
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 #[derive(Debug, Clone, PartialEq)]
 pub enum PostingPrice<D> {
     /// Unit cost (`@`)
@@ -143,10 +605,25 @@ pub enum PostingPrice<D> {
 /// };
 /// assert!(trx.tags.contains("food"));
 /// ```
+///
+/// # Equality, ordering and hashing
+///
+/// Equality, ordering and hashing are defined by the string content only (without the `#` prefix).
+/// Two tags with the same name are equal and hash identically no matter which parse created them.
+///
+/// # Serde
+///
+/// With the `serde` feature, the tag is (de)serialized as a plain string without the `#` prefix.
+/// Deserialization fails if the string is not a valid tag name.
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct Tag(Arc<str>);
 
 impl Tag {
+    /// Create a tag from a name known to be valid
+    pub(crate) fn new_unchecked(name: &str) -> Self {
+        Self(name.into())
+    }
+
     /// Returns underlying string representation
     #[must_use]
     pub fn as_str(&self) -> &str {
@@ -189,10 +666,25 @@ impl Borrow<str> for Tag {
 /// };
 /// assert!(trx.links.contains("invoice-pepe-studios-jan14"));
 /// ```
+///
+/// # Equality, ordering and hashing
+///
+/// Equality, ordering and hashing are defined by the string content only (without the `^` prefix).
+/// Two links with the same name are equal and hash identically no matter which parse created them.
+///
+/// # Serde
+///
+/// With the `serde` feature, the link is (de)serialized as a plain string without the `^` prefix.
+/// Deserialization fails if the string is not a valid link name.
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct Link(Arc<str>);
 
 impl Link {
+    /// Create a link from a name known to be valid
+    pub(crate) fn new_unchecked(name: &str) -> Self {
+        Self(name.into())
+    }
+
     /// Returns underlying string representation
     #[must_use]
     pub fn as_str(&self) -> &str {
@@ -218,28 +710,92 @@ impl Borrow<str> for Link {
     }
 }
 
-#[allow(clippy::type_complexity)]
-pub(crate) fn parse<D: Decimal>(
-    input: Span<'_>,
-) -> IResult<'_, (Transaction<D>, metadata::Map<D>)> {
+#[cfg(feature = "serde")]
+impl serde::Serialize for Tag {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Tag {
+    fn deserialize<De: serde::Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+        let name = String::deserialize(deserializer)?;
+        if name.is_empty() || !name.chars().all(is_tag_char) {
+            return Err(serde::de::Error::custom(format!("invalid tag: {name}")));
+        }
+        Ok(Self(name.into()))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Link {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Link {
+    fn deserialize<De: serde::Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+        let name = String::deserialize(deserializer)?;
+        if name.is_empty() || !name.chars().all(is_link_char) {
+            return Err(serde::de::Error::custom(format!("invalid link: {name}")));
+        }
+        Ok(Self(name.into()))
+    }
+}
+
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_' || unicode::is_combining_mark(c)
+}
+
+fn is_link_char(c: char) -> bool {
+    is_tag_char(c) || c == '.'
+}
+
+/// Transaction, with its metadata and the indented lines that are neither metadata nor postings
+pub(crate) type Parsed<D> = (Transaction<D>, metadata::Block<D>, Vec<InvalidLine>);
+
+pub(crate) fn parse<D: Number>(input: Span<'_>) -> IResult<'_, Parsed<D>> {
     let (input, flag) = alt((map(flag, Some), value(None, tag("txn"))))(input)?;
     cut(do_parse(flag))(input)
 }
 
+/// Parse the rest of a transaction after its keyword (like `txn`), that is a transaction without flag
+pub(crate) fn parse_after_keyword<D: Number>(input: Span<'_>) -> IResult<'_, Parsed<D>> {
+    cut(do_parse(None))(input)
+}
+
 fn flag(input: Span<'_>) -> IResult<'_, char> {
     satisfy(|c: char| !c.is_ascii_lowercase())(input)
 }
 
-fn do_parse<D: Decimal>(
-    flag: Option<char>,
-) -> impl Fn(Span<'_>) -> IResult<'_, (Transaction<D>, metadata::Map<D>)> {
+fn do_parse<D: Number>(flag: Option<char>) -> impl Fn(Span<'_>) -> IResult<'_, Parsed<D>> {
     move |input| {
         let (input, payee_and_narration) = opt(preceded(space1, payee_and_narration))(input)?;
         let (input, (tags, links)) = tags_and_links(input)?;
         let (input, ()) = end_of_line(input)?;
         let (input, metadata) = metadata::parse(input)?;
-        let mut iter = iterator(input, alt((posting.map(Some), empty_line.map(|()| None))));
-        let postings = iter.flatten().collect();
+        // A line that is not a posting is skipped (and returned), instead of ending the transaction
+        let mut invalid_lines = Vec::new();
+        let mut iter = iterator(
+            input,
+            alt((
+                posting.map(|posting| Some(Ok(posting))),
+                empty_line.map(|()| None),
+                invalid_line.map(|line| Some(Err(line))),
+            )),
+        );
+        let mut indent = None;
+        let postings = iter
+            .flatten()
+            .filter_map(|line| line.map_err(|line| invalid_lines.push(line)).ok())
+            .map(|(posting_indent, posting)| {
+                indent.get_or_insert(posting_indent);
+                posting
+            })
+            .collect();
         let (input, ()) = iter.finish()?;
         let (payee, narration) = match payee_and_narration {
             Some((payee, narration)) => (payee, Some(narration)),
@@ -255,8 +811,10 @@ fn do_parse<D: Decimal>(
                     tags,
                     links,
                     postings,
+                    indent: indent.flatten(),
                 },
                 metadata,
+                invalid_lines,
             ),
         ))
     }
@@ -269,20 +827,14 @@ pub(super) enum TagOrLink {
 
 pub(super) fn parse_tag(input: Span<'_>) -> IResult<'_, Tag> {
     map(
-        preceded(
-            char_tag('#'),
-            take_while(|c: char| c.is_alphanumeric() || c == '-' || c == '_'),
-        ),
+        preceded(char_tag('#'), take_while(is_tag_char)),
         |s: Span<'_>| Tag((*s.fragment()).into()),
     )(input)
 }
 
 pub(super) fn parse_link(input: Span<'_>) -> IResult<'_, Link> {
     map(
-        preceded(
-            char_tag('^'),
-            take_while(|c: char| c.is_alphanumeric() || c == '-' || c == '_' || c == '.'),
-        ),
+        preceded(char_tag('^'), take_while(is_link_char)),
         |s: Span<'_>| Link((*s.fragment()).into()),
     )(input)
 }
@@ -294,7 +846,7 @@ pub(super) fn parse_tag_or_link(input: Span<'_>) -> IResult<'_, TagOrLink> {
     ))(input)
 }
 
-fn tags_and_links(input: Span<'_>) -> IResult<'_, (HashSet<Tag>, HashSet<Link>)> {
+pub(super) fn tags_and_links(input: Span<'_>) -> IResult<'_, (HashSet<Tag>, HashSet<Link>)> {
     let mut tags_and_links_iter = iterator(input, preceded(space0, parse_tag_or_link));
     let (tags, links) = tags_and_links_iter.fold(
         (HashSet::new(), HashSet::new()),
@@ -322,12 +874,31 @@ fn payee_and_narration(input: Span<'_>) -> IResult<'_, (Option<String>, String)>
     ))
 }
 
-fn posting<D: Decimal>(input: Span<'_>) -> IResult<'_, Posting<D>> {
-    let (input, _) = space1(input)?;
+/// Like `many0`, but does not allocate when nothing is found (which is the common case)
+fn many0_lazy<'a, O>(
+    parser: impl Parser<Span<'a>, O, nom::error::Error<Span<'a>>>,
+) -> impl FnMut(Span<'a>) -> IResult<'a, Vec<O>> {
+    fold_many0(parser, Vec::new, |mut items, item| {
+        items.push(item);
+        items
+    })
+}
+
+/// Parse a posting, with its indentation
+fn posting<D: Number>(input: Span<'_>) -> IResult<'_, (Option<Indent>, Posting<D>)> {
+    let (input, indent) = space1(input)?;
     let (input, flag) = opt(terminated(flag, space1))(input)?;
     let (input, account) = account::parse(input)?;
     let (input, amounts) = opt(tuple((
-        preceded(space1, amount::parse),
+        preceded(
+            space1,
+            alt((
+                map(amount::parse, |amount| (amount, None)),
+                map(amount_with_second_number, |(amount, token)| {
+                    (amount, Some(token))
+                }),
+            )),
+        ),
         opt(preceded(space1, cost)),
         opt(preceded(
             space1,
@@ -343,26 +914,115 @@ fn posting<D: Decimal>(input: Span<'_>) -> IResult<'_, Posting<D>> {
             )),
         )),
     )))(input)?;
+    let (input, misplaced_tags) = many0_lazy(preceded(space1, misplaced_tag))(input)?;
+    let (input, stray_tokens) =
+        cond(amounts.is_some(), many0_lazy(preceded(space1, stray_token)))(input)?;
+    let mut stray_tokens = stray_tokens.unwrap_or_default();
+    let (input, unsupported_cost) =
+        cond(amounts.is_some(), opt(preceded(space1, unsupported_cost)))(input)?;
     let (input, ()) = end_of_line(input)?;
-    let (input, metadata) = metadata::parse(input)?;
+    let (input, (metadata, metadata_duplicates)) = metadata::parse(input)?;
     let (amount, cost, price) = match amounts {
-        Some((a, l, p)) => (Some(a), l, p),
+        Some(((a, second_number), l, p)) => {
+            stray_tokens.splice(0..0, second_number);
+            (Some(a), l, p)
+        }
         None => (None, None, None),
     };
     Ok((
         input,
-        Posting {
-            flag,
-            account,
-            amount,
-            cost,
-            price,
-            metadata,
+        (
+            Indent::of(indent.fragment()),
+            Posting {
+                flag,
+                account,
+                amount,
+                cost,
+                price,
+                metadata,
+                line_number: indent.location_line(),
+                metadata_duplicates,
+                misplaced_tags: (!misplaced_tags.is_empty()).then(|| misplaced_tags.into()),
+                stray_tokens: (!stray_tokens.is_empty()).then(|| stray_tokens.into()),
+                unsupported_cost: unsupported_cost.flatten().map(Box::new),
+            },
+        ),
+    ))
+}
+
+/// Parse an amount with a second number before the currency (like `10 20 USD`), returning the second number as a stray token
+fn amount_with_second_number<D: Number>(input: Span<'_>) -> IResult<'_, (Amount<D>, StrayToken)> {
+    let (input, value) = amount::expression(input)?;
+    let decimals = amount::decimals(input);
+    let (input, _) = space1(input)?;
+    let (input, second) = recognize(amount::expression::<D>)(input)?;
+    let (input, _) = space1(input)?;
+    let (input, currency) = amount::currency(input)?;
+    let token = StrayToken {
+        text: (*second.fragment()).into(),
+        kind: Some("second number"),
+        line_number: second.location_line(),
+        offset: second.location_offset(),
+    };
+    Ok((
+        amount::with_amount(input, decimals),
+        (Amount { value, currency }, token),
+    ))
+}
+
+/// Parse any token up to the next space or comment following the amount of a posting, as a stray token
+///
+/// A currency (like the `CHF` of `10 USD CHF`) is reported as a second currency.
+/// A token starting with `{` or `@` is not a stray token: it is a cost or a price (see [`unsupported_cost`]).
+fn stray_token(input: Span<'_>) -> IResult<'_, StrayToken> {
+    let (input, ()) = not(one_of("{@"))(input)?;
+    let (rest, span) = take_till1(|c: char| c.is_whitespace() || c == ';')(input)?;
+    let kind = span
+        .fragment()
+        .parse::<Currency>()
+        .is_ok()
+        .then_some("second currency");
+    Ok((
+        rest,
+        StrayToken {
+            text: (*span.fragment()).into(),
+            kind,
+            line_number: span.location_line(),
+            offset: span.location_offset(),
+        },
+    ))
+}
+
+/// Parse a cost or a price that could not be parsed as such, up to the comment or the end of the line
+fn unsupported_cost(input: Span<'_>) -> IResult<'_, UnsupportedCost> {
+    let (_, span) = recognize(preceded(
+        one_of("{@"),
+        take_till(|c: char| matches!(c, ';' | '\r' | '\n')),
+    ))(input)?;
+    let (rest, span) = take(span.fragment().trim_end().len())(input)?;
+    Ok((
+        rest,
+        UnsupportedCost {
+            text: (*span.fragment()).into(),
+            line_number: span.location_line(),
+            offset: span.location_offset(),
+        },
+    ))
+}
+
+fn misplaced_tag(input: Span<'_>) -> IResult<'_, MisplacedTag> {
+    let (rest, span) = recognize(parse_tag_or_link)(input)?;
+    Ok((
+        rest,
+        MisplacedTag {
+            text: (*span.fragment()).into(),
+            line_number: span.location_line(),
+            offset: span.location_offset(),
         },
     ))
 }
 
-fn cost<D: Decimal>(input: Span<'_>) -> IResult<'_, Cost<D>> {
+fn cost<D: Number>(input: Span<'_>) -> IResult<'_, Cost<D>> {
     let (input, _) = terminated(char_tag('{'), space0)(input)?;
     let (input, (cost, date)) = alt((
         map(
@@ -411,6 +1071,7 @@ mod chumsky {
                     tags,
                     links,
                     postings,
+                    indent: None,
                 },
             )
     }
@@ -498,6 +1159,11 @@ mod chumsky {
                     cost,
                     price,
                     metadata,
+                    line_number: 0,
+                    metadata_duplicates: crate::metadata::Duplicates::default(),
+                    misplaced_tags: None,
+                    stray_tokens: None,
+                    unsupported_cost: None,
                 },
             )
     }
@@ -0,0 +1,212 @@
+/// Returns true if the character is a combining mark (like the diaeresis of `ü` written in decomposed form)
+///
+/// Only the blocks of combining marks are recognized, which cover the decomposition of the latin, greek and cyrillic letters.
+pub(crate) fn is_combining_mark(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0300}'..='\u{036F}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{FE20}'..='\u{FE2F}'
+    )
+}
+
+#[cfg(feature = "unicode-normalization")]
+pub(crate) use self::nfc::{nfc, Normalizer};
+
+/// Without the `unicode-normalization` feature, the names are never normalized
+#[cfg(not(feature = "unicode-normalization"))]
+#[derive(Debug)]
+pub(crate) enum Normalizer {}
+
+#[cfg(not(feature = "unicode-normalization"))]
+impl Normalizer {
+    pub(crate) fn from_options(_: &crate::ParseOptions) -> Option<Self> {
+        None
+    }
+
+    pub(crate) fn directive<D>(&mut self, _: &mut crate::Directive<D>) {
+        match *self {}
+    }
+
+    pub(crate) fn tag(&mut self, _: &mut crate::Tag) {
+        match *self {}
+    }
+
+    pub(crate) fn key(&mut self, _: &mut crate::metadata::Key) {
+        match *self {}
+    }
+
+    pub(crate) fn value<D>(&self, _: &mut crate::metadata::Value<D>) {
+        match *self {}
+    }
+}
+
+#[cfg(feature = "unicode-normalization")]
+mod nfc {
+    use std::{
+        borrow::{Borrow, Cow},
+        collections::HashSet,
+        hash::Hash,
+        mem,
+    };
+
+    use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+    use crate::{
+        metadata, Account, CustomValue, Directive, DirectiveContent, Link, ParseOptions, Tag,
+    };
+
+    /// Returns the name in the Unicode composed form (NFC)
+    pub(crate) fn nfc(name: &str) -> Cow<'_, str> {
+        if is_nfc(name) {
+            Cow::Borrowed(name)
+        } else {
+            Cow::Owned(name.nfc().collect())
+        }
+    }
+
+    /// Normalizes the names of the parsed directives to NFC (see [`ParseOptions::normalize_nfc`](crate::ParseOptions::normalize_nfc))
+    ///
+    /// The names are interned after being normalized,
+    /// so that the names only differing by their normalization share the same allocation.
+    #[derive(Debug, Default)]
+    pub(crate) struct Normalizer {
+        /// Whether the payees, narrations and string metadata values are normalized too
+        text: bool,
+        accounts: HashSet<Account>,
+        tags: HashSet<Tag>,
+        links: HashSet<Link>,
+        keys: HashSet<metadata::Key>,
+    }
+
+    impl Normalizer {
+        /// Returns a normalizer if [`ParseOptions::normalize_nfc`] is set
+        pub(crate) fn from_options(options: &ParseOptions) -> Option<Self> {
+            options.normalize_nfc.then(|| Self {
+                text: options.normalize_nfc_text,
+                ..Self::default()
+            })
+        }
+
+        pub(crate) fn directive<D>(&mut self, directive: &mut Directive<D>) {
+            self.metadata(&mut directive.metadata, &mut directive.metadata_duplicates);
+            match &mut directive.content {
+                DirectiveContent::Transaction(trx) => {
+                    for text in trx.payee.iter_mut().chain(&mut trx.narration) {
+                        self.text(text);
+                    }
+                    self.tags_and_links(&mut trx.tags, &mut trx.links);
+                    for posting in &mut trx.postings {
+                        self.account(&mut posting.account);
+                        self.metadata(&mut posting.metadata, &mut posting.metadata_duplicates);
+                    }
+                }
+                DirectiveContent::Balance(balance) => self.account(&mut balance.account),
+                DirectiveContent::Open(open) => self.account(&mut open.account),
+                DirectiveContent::Close(close) => self.account(&mut close.account),
+                DirectiveContent::Pad(pad) => {
+                    self.account(&mut pad.account);
+                    self.account(&mut pad.source_account);
+                }
+                DirectiveContent::Note(note) => {
+                    self.account(&mut note.account);
+                    self.tags_and_links(&mut note.tags, &mut note.links);
+                }
+                DirectiveContent::Document(document) => {
+                    self.account(&mut document.account);
+                    self.tags_and_links(&mut document.tags, &mut document.links);
+                }
+                DirectiveContent::Custom(custom) => {
+                    for value in &mut custom.values {
+                        if let CustomValue::Account(account) = value {
+                            self.account(account);
+                        }
+                    }
+                }
+                DirectiveContent::Price(_)
+                | DirectiveContent::Commodity(_)
+                | DirectiveContent::Event(_)
+                | DirectiveContent::Query(_) => (),
+            }
+        }
+
+        pub(crate) fn tag(&mut self, tag: &mut Tag) {
+            intern(&mut self.tags, tag, Tag::new_unchecked);
+        }
+
+        pub(crate) fn key(&mut self, key: &mut metadata::Key) {
+            intern(&mut self.keys, key, metadata::Key::new_unchecked);
+        }
+
+        fn account(&mut self, account: &mut Account) {
+            intern(&mut self.accounts, account, Account::new_unchecked);
+        }
+
+        fn tags_and_links(&mut self, tags: &mut HashSet<Tag>, links: &mut HashSet<Link>) {
+            *tags = mem::take(tags)
+                .into_iter()
+                .map(|mut tag| {
+                    self.tag(&mut tag);
+                    tag
+                })
+                .collect();
+            *links = mem::take(links)
+                .into_iter()
+                .map(|mut link| {
+                    intern(&mut self.links, &mut link, Link::new_unchecked);
+                    link
+                })
+                .collect();
+        }
+
+        /// Normalize the keys (and the string values) of the metadata
+        ///
+        /// If two keys of the map become equal, the value of only one of them is kept.
+        fn metadata<D>(
+            &mut self,
+            map: &mut metadata::Map<D>,
+            duplicates: &mut metadata::Duplicates<D>,
+        ) {
+            for (mut key, mut value) in mem::take(map) {
+                self.key(&mut key);
+                self.value(&mut value);
+                map.entry(key).or_insert(value);
+            }
+            for duplicate in duplicates.as_mut_vec().into_iter().flatten() {
+                self.key(&mut duplicate.key);
+                self.value(&mut duplicate.value);
+            }
+        }
+
+        /// Normalize the string value (if the text is normalized)
+        pub(crate) fn value<D>(&self, value: &mut metadata::Value<D>) {
+            if let metadata::Value::String(text) = value {
+                self.text(text);
+            }
+        }
+
+        fn text(&self, text: &mut String) {
+            if self.text && !is_nfc(text) {
+                *text = text.nfc().collect();
+            }
+        }
+    }
+
+    /// Replace the name by its NFC form, shared with the equal names already interned
+    fn intern<T>(names: &mut HashSet<T>, name: &mut T, new: fn(&str) -> T)
+    where
+        T: Borrow<str> + Clone + Eq + Hash,
+    {
+        if let Cow::Owned(normalized) = nfc(Borrow::<str>::borrow(&*name)) {
+            *name = new(&normalized);
+        }
+        match names.get::<T>(name) {
+            Some(shared) => *name = shared.clone(),
+            None => {
+                names.insert(name.clone());
+            }
+        }
+    }
+}
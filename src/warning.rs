@@ -0,0 +1,269 @@
+use std::fmt::{Display, Formatter};
+
+use crate::{account, metadata, Account, Code, CostIssue, FileId, Tag};
+
+/// Non-fatal problem found while parsing
+///
+/// The input is still parsed, but it may not mean what the author intended.
+/// Warnings are collected in [`BeancountFile::warnings`](crate::BeancountFile::warnings),
+/// or emitted as [`Entry::Warning`](crate::Entry::Warning) by [`parse_iter`](crate::parse_iter).
+///
+/// # Example
+/// ```
+/// # use beancount_parser::{BeancountFile, WarningKind};
+/// let input = r#"
+/// 2023-05-27 open Assets:Cash
+///   id: 1
+///   id: 2
+/// "#;
+/// let beancount: BeancountFile<f64> = input.parse().unwrap();
+/// let warning = &beancount.warnings[0];
+/// assert_eq!(warning.line_number(), 4);
+/// assert!(matches!(warning.kind(), WarningKind::DuplicateMetadataKey(key) if key.as_ref() == "id"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    kind: WarningKind,
+    file_id: FileId,
+    line_number: u32,
+}
+
+/// Kind of [`Warning`]
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum WarningKind {
+    /// A metadata key is declared more than once on the same directive or posting
+    ///
+    /// The first value is kept. See the [`metadata`] module.
+    DuplicateMetadataKey(metadata::Key),
+    /// The time following the date of a directive was removed (like `12:30:00`)
+    ///
+    /// See [`ParseOptions::strip_times`](crate::ParseOptions::strip_times)
+    TimeRemoved(String),
+    /// A `custom "budget"` directive could not be interpreted as a fava budget
+    ///
+    /// The directive is ignored by [`fava::budgets`](crate::fava::budgets). The value is the reason.
+    MalformedBudget(String),
+    /// The cost of a posting does not make sense for the posting
+    ///
+    /// The line number is the one of the transaction. See [`Cost::validate`](crate::Cost::validate)
+    InvalidCost(CostIssue),
+    /// The directive is dated after today
+    ///
+    /// The value is the number of days after today. See [`lint::FutureDatedDirective`](crate::lint::FutureDatedDirective)
+    FutureDated(u32),
+    /// The transaction has been flagged `!` for too long
+    ///
+    /// The value is the age of the transaction in days. See [`lint::StaleIncompleteFlag`](crate::lint::StaleIncompleteFlag)
+    StaleIncompleteFlag(u32),
+    /// The account name is not valid, and was kept as written
+    ///
+    /// See [`ParseOptions::lenient_accounts`](crate::ParseOptions::lenient_accounts)
+    InvalidAccount(Account),
+    /// A tag or link at the end of a posting line was removed (like `#lunch`)
+    ///
+    /// See [`ParseOptions::strip_posting_tags`](crate::ParseOptions::strip_posting_tags)
+    PostingTagRemoved(String),
+    /// The glob pattern of an `include` directive matches no file
+    ///
+    /// The value is the pattern as written. See [`read_files`](crate::read_files)
+    EmptyIncludeGlob(String),
+    /// The `pad` directive of the account was not used by any balance assertion
+    ///
+    /// See [`BeancountFile::resolve`](crate::BeancountFile::resolve)
+    UnusedPad(Account),
+    /// The keyword of a directive was written with another case (like `Open` or `TXN`)
+    ///
+    /// The value is the keyword as written. See [`ParseOptions::case_insensitive_keywords`](crate::ParseOptions::case_insensitive_keywords)
+    KeywordCase(String),
+    /// A token that is not part of the posting was removed from a posting line (like the `CHF` of `10 USD CHF`)
+    ///
+    /// The value is the hint naming the token. See [`ParseOptions::drop_stray_tokens`](crate::ParseOptions::drop_stray_tokens)
+    StrayTokenDropped(String),
+    /// An indented line of a directive that is neither metadata nor a posting was skipped
+    ///
+    /// The value is the line without its indentation. See [`ParseOptions::skip_invalid_lines`](crate::ParseOptions::skip_invalid_lines)
+    InvalidLineSkipped(String),
+    /// The account only differs from another account by its Unicode normalization
+    ///
+    /// The values are the account and the first spelling of the other account.
+    /// See `lint::SimilarAccounts` (requires the `unicode-normalization` feature)
+    SimilarAccounts(Account, Account),
+    /// A `poptag` pops a tag that is not pushed
+    ///
+    /// The `poptag` has no effect. See [`Entry::PopTag`](crate::Entry::PopTag)
+    UnpushedTagPopped(Tag),
+    /// A line outside of the directives is not recognized, and was ignored
+    ///
+    /// The value is the line as written. See [`LineKind::Unrecognized`](crate::LineKind::Unrecognized)
+    UnrecognizedLine(String),
+}
+
+impl WarningKind {
+    fn code(&self) -> Code {
+        match self {
+            Self::DuplicateMetadataKey(_) => Code::DuplicateMetadataKey,
+            Self::TimeRemoved(_) => Code::DateWithTime,
+            Self::MalformedBudget(_) => Code::MalformedBudget,
+            Self::InvalidCost(issue) => match issue {
+                CostIssue::SameCurrency => Code::CostInUnitsCurrency,
+            },
+            Self::FutureDated(_) => Code::FutureDatedDirective,
+            Self::StaleIncompleteFlag(_) => Code::StaleIncompleteFlag,
+            Self::InvalidAccount(_) => Code::InvalidAccount,
+            Self::PostingTagRemoved(_) => Code::TagOnPosting,
+            Self::EmptyIncludeGlob(_) => Code::EmptyIncludeGlob,
+            Self::UnusedPad(_) => Code::UnusedPad,
+            Self::KeywordCase(_) => Code::MisspelledKeyword,
+            Self::StrayTokenDropped(_) => Code::StrayPostingToken,
+            Self::InvalidLineSkipped(_) => Code::InvalidIndentedLine,
+            Self::SimilarAccounts(_, _) => Code::SimilarAccounts,
+            Self::UnpushedTagPopped(_) => Code::UnbalancedTag,
+            Self::UnrecognizedLine(_) => Code::UnrecognizedLine,
+        }
+    }
+}
+
+impl Warning {
+    pub(crate) fn new(kind: WarningKind, line_number: u32) -> Self {
+        Self {
+            kind,
+            file_id: FileId::MAIN,
+            line_number,
+        }
+    }
+
+    /// Attribute the warning to another file
+    pub(crate) fn in_file(mut self, file_id: FileId) -> Self {
+        self.file_id = file_id;
+        self
+    }
+
+    /// Add `lines` to the line number, for a warning found in a part of a larger input
+    pub(crate) fn shift_lines(&mut self, lines: u32) {
+        self.line_number += lines;
+    }
+
+    /// Kind of the warning
+    #[must_use]
+    pub fn kind(&self) -> &WarningKind {
+        &self.kind
+    }
+
+    /// Code identifying the kind of warning
+    ///
+    /// See [`Code`] for the list of codes.
+    #[must_use]
+    pub fn code(&self) -> Code {
+        self.kind.code()
+    }
+
+    /// Line number at which the warning was found in the input
+    #[must_use]
+    pub fn line_number(&self) -> u32 {
+        self.line_number
+    }
+
+    /// File in which the warning was found
+    ///
+    /// See [`SourceMap`](crate::SourceMap) to get its path.
+    #[must_use]
+    pub fn file_id(&self) -> FileId {
+        self.file_id
+    }
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "warning[{}]: ", self.code())?;
+        match &self.kind {
+            WarningKind::DuplicateMetadataKey(key) => write!(
+                f,
+                "Duplicate metadata key `{key}` at line: {} (the first value is kept)",
+                self.line_number
+            ),
+            WarningKind::TimeRemoved(time) => write!(
+                f,
+                "Time `{time}` removed from the date at line: {}",
+                self.line_number
+            ),
+            WarningKind::MalformedBudget(reason) => write!(
+                f,
+                "Malformed budget at line: {} ({reason})",
+                self.line_number
+            ),
+            WarningKind::InvalidCost(CostIssue::SameCurrency) => write!(
+                f,
+                "Cost in the currency of the units in the transaction at line: {}",
+                self.line_number
+            ),
+            WarningKind::FutureDated(days) => write!(
+                f,
+                "Directive dated {days} day(s) in the future at line: {}",
+                self.line_number
+            ),
+            WarningKind::StaleIncompleteFlag(days) => write!(
+                f,
+                "Transaction flagged `!` for {days} day(s) at line: {}",
+                self.line_number
+            ),
+            WarningKind::PostingTagRemoved(tag) => write!(
+                f,
+                "`{tag}` removed from the posting at line: {} (only transactions can have tags and links)",
+                self.line_number
+            ),
+            WarningKind::EmptyIncludeGlob(pattern) => write!(
+                f,
+                "Include pattern `{pattern}` matches no file at line: {}",
+                self.line_number
+            ),
+            WarningKind::UnusedPad(account) => write!(
+                f,
+                "Unused pad of `{account}` at line: {} (no balance assertion of the account needs it)",
+                self.line_number
+            ),
+            WarningKind::InvalidLineSkipped(line) => write!(
+                f,
+                "Line skipped at line: {} (`{line}` is neither metadata nor a posting)",
+                self.line_number
+            ),
+            WarningKind::StrayTokenDropped(hint) => write!(
+                f,
+                "Token removed from the posting at line: {} ({hint})",
+                self.line_number
+            ),
+            WarningKind::SimilarAccounts(account, other) => write!(
+                f,
+                "Account `{account}` at line: {} only differs from `{other}` by its Unicode normalization",
+                self.line_number
+            ),
+            WarningKind::UnpushedTagPopped(tag) => write!(
+                f,
+                "Tag `#{tag}` popped at line: {} without being pushed",
+                self.line_number
+            ),
+            WarningKind::UnrecognizedLine(line) => write!(
+                f,
+                "Line ignored at line: {} (`{line}` is neither a directive nor a comment)",
+                self.line_number
+            ),
+            WarningKind::KeywordCase(keyword) => write!(
+                f,
+                "Keyword `{keyword}` read as `{}` at line: {} (beancount keywords are lowercase)",
+                keyword.to_ascii_lowercase(),
+                self.line_number
+            ),
+            WarningKind::InvalidAccount(account) => {
+                write!(
+                    f,
+                    "Invalid account `{account}` at line: {}",
+                    self.line_number
+                )?;
+                match account::invalid_reason(account.as_str()) {
+                    Some((_, reason)) => write!(f, " ({reason})"),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,112 @@
+#![allow(missing_docs)]
+
+use rstest::rstest;
+
+use beancount_parser::{parse, AccountNode, AccountTree, Date};
+
+const OFFICIAL: &str = include_str!("samples/official.beancount");
+
+const INPUT: &str = r#"
+2023-01-01 open Assets:Bank:UBS
+2023-01-02 open Assets:Bank:Postfinance
+2023-01-01 open Expenses:Food
+2023-02-01 * "Groceries"
+  Expenses:Food:Groceries  10 CHF
+  Assets:Bank:UBS
+2023-12-31 close Assets:Bank:UBS
+"#;
+
+fn tree(input: &str) -> AccountTree {
+    parse::<f64>(input).unwrap().account_tree()
+}
+
+#[rstest]
+#[case::root("Assets")]
+#[case::implied_parent("Assets:Bank")]
+#[case::only_referenced_in_posting("Expenses:Food:Groceries")]
+fn implied_accounts_are_implicit(#[case] account: &str) {
+    let tree = tree(INPUT);
+    let node = tree.get(account).unwrap();
+    assert!(node.is_implicit());
+    assert_eq!(node.account.as_str(), account);
+}
+
+#[rstest]
+#[case(
+    "Assets:Bank:UBS",
+    Date::new(2023, 1, 1),
+    Some(Date::new(2023, 12, 31))
+)]
+#[case("Assets:Bank:Postfinance", Date::new(2023, 1, 2), None)]
+#[case("Expenses:Food", Date::new(2023, 1, 1), None)]
+fn opened_accounts_are_explicit(
+    #[case] account: &str,
+    #[case] open: Date,
+    #[case] close: Option<Date>,
+) {
+    let tree = tree(INPUT);
+    let node = tree.get(account).unwrap();
+    assert!(!node.is_implicit());
+    assert_eq!(node.open, Some(open));
+    assert_eq!(node.close, close);
+}
+
+#[rstest]
+fn node_name_is_last_component() {
+    let tree = tree(INPUT);
+    assert_eq!(tree.get("Assets").unwrap().name(), "Assets");
+    assert_eq!(tree.get("Assets:Bank:UBS").unwrap().name(), "UBS");
+}
+
+#[rstest]
+fn children_are_sorted_by_name() {
+    let tree = tree(INPUT);
+    let children: Vec<&str> = tree
+        .get("Assets:Bank")
+        .unwrap()
+        .children()
+        .map(AccountNode::name)
+        .collect();
+    assert_eq!(children, ["Postfinance", "UBS"]);
+    let roots: Vec<&str> = tree.roots().map(AccountNode::name).collect();
+    assert_eq!(roots, ["Assets", "Expenses"]);
+}
+
+#[rstest]
+fn iterate_depth_first() {
+    let tree = tree(INPUT);
+    let accounts: Vec<&str> = tree.iter().map(|node| node.account.as_str()).collect();
+    assert_eq!(
+        accounts,
+        [
+            "Assets",
+            "Assets:Bank",
+            "Assets:Bank:Postfinance",
+            "Assets:Bank:UBS",
+            "Expenses",
+            "Expenses:Food",
+            "Expenses:Food:Groceries",
+        ]
+    );
+}
+
+#[rstest]
+#[case::unknown("Assets:Unknown")]
+#[case::empty("")]
+#[case::trailing_colon("Assets:Bank:")]
+fn get_unknown_account(#[case] account: &str) {
+    assert!(tree(INPUT).get(account).is_none());
+}
+
+#[rstest]
+fn empty_file_has_empty_tree() {
+    assert_eq!(tree("").iter().count(), 0);
+}
+
+#[rstest]
+fn every_opened_account_of_example_file_is_explicit() {
+    let file = parse::<f64>(OFFICIAL).unwrap();
+    let tree = file.account_tree();
+    let explicit_count = tree.iter().filter(|node| !node.is_implicit()).count();
+    assert_eq!(explicit_count, 63);
+}
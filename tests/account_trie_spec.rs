@@ -0,0 +1,122 @@
+#![allow(missing_docs)]
+#![cfg(feature = "trie")]
+
+use rstest::rstest;
+
+use beancount_parser::{parse, AccountTrie};
+
+const OFFICIAL: &str = include_str!("samples/official.beancount");
+
+const INPUT: &str = r#"
+2023-01-01 open Assets:Bank:Checking
+2023-01-01 open Assets:Bank:Savings
+2023-01-01 open Assets:Broker
+2023-01-01 open Equity:Opening-Balances
+2023-01-01 open Expenses:Food:Groceries
+2023-01-01 open Expenses:Food:Restaurant
+2023-01-01 open Expenses:Fees
+2023-01-01 open Expenses:Furniture
+2023-01-01 open Expenses:Transport
+2023-01-02 * "Groceries"
+  Expenses:Food:Groceries  10 CHF
+  Assets:Bank:Checking
+2023-01-03 * "Groceries"
+  Expenses:Food:Groceries  20 CHF
+  Assets:Bank:Checking
+2023-01-04 * "Restaurant"
+  Expenses:Food:Restaurant  30 CHF
+  Assets:Bank:Checking
+"#;
+
+fn trie(input: &str) -> AccountTrie {
+    parse::<f64>(input).unwrap().account_trie()
+}
+
+fn complete(query: &str, limit: usize) -> Vec<String> {
+    trie(INPUT)
+        .complete(query, limit)
+        .into_iter()
+        .map(|account| account.as_str().to_owned())
+        .collect()
+}
+
+#[rstest]
+#[case::full_name("Expenses:Food:Groceries", &["Expenses:Food:Groceries"])]
+#[case::full_prefix("Expenses:Food:R", &["Expenses:Food:Restaurant"])]
+#[case::abbreviation("Ex:Fo:Gr", &["Expenses:Food:Groceries"])]
+#[case::one_letter_per_component("E:F:R", &["Expenses:Food:Restaurant"])]
+#[case::mixed("Expenses:Fo:Re", &["Expenses:Food:Restaurant"])]
+#[case::lowercase("ex:fo:gr", &["Expenses:Food:Groceries"])]
+#[case::with_dash("Eq:Opening-", &["Equity:Opening-Balances"])]
+fn should_complete_unambiguous_queries(#[case] query: &str, #[case] expected: &[&str]) {
+    assert_eq!(complete(query, 10), expected);
+}
+
+#[rstest]
+#[case::same_first_letter_of_component(
+    "Ex:F",
+    &["Expenses:Food:Groceries", "Expenses:Food:Restaurant", "Expenses:Fees", "Expenses:Furniture"]
+)]
+#[case::same_first_letters_of_root("E:F", &[
+    "Expenses:Food:Groceries",
+    "Expenses:Food:Restaurant",
+    "Expenses:Fees",
+    "Expenses:Furniture",
+])]
+#[case::same_first_letter_of_leaf("A:B", &["Assets:Bank:Checking", "Assets:Bank:Savings", "Assets:Broker"])]
+#[case::longer_component_disambiguates("A:Br", &["Assets:Broker"])]
+#[case::parent_and_descendants("Ex:Food", &["Expenses:Food:Groceries", "Expenses:Food:Restaurant"])]
+#[case::trailing_colon("Assets:Bank:", &["Assets:Bank:Checking", "Assets:Bank:Savings"])]
+#[case::root_only("E", &[
+    "Expenses:Food:Groceries",
+    "Expenses:Food:Restaurant",
+    "Equity:Opening-Balances",
+    "Expenses:Fees",
+    "Expenses:Furniture",
+    "Expenses:Transport",
+])]
+fn should_order_ambiguous_matches_by_frequency_then_name(
+    #[case] query: &str,
+    #[case] expected: &[&str],
+) {
+    assert_eq!(complete(query, 10), expected);
+}
+
+#[rstest]
+#[case::not_a_prefix("Ex:oo")]
+#[case::skipped_component("Ex:Gr")]
+#[case::too_many_components("Ex:Fees:Bank")]
+#[case::uppercase_is_exact("EX:FO")]
+#[case::uppercase_in_component("Expenses:FOod")]
+#[case::unknown_root("Income")]
+fn should_not_complete_unmatched_queries(#[case] query: &str) {
+    assert_eq!(complete(query, 10), Vec::<String>::new());
+}
+
+#[rstest]
+fn limit_should_keep_the_most_referenced_accounts() {
+    assert_eq!(
+        complete("", 2),
+        ["Assets:Bank:Checking", "Expenses:Food:Groceries"]
+    );
+    assert_eq!(complete("Ex", 0), Vec::<String>::new());
+}
+
+#[rstest]
+fn empty_query_should_match_every_account() {
+    let trie = trie(OFFICIAL);
+    let mut accounts = parse::<f64>(OFFICIAL).unwrap().balances(None).into_keys();
+    assert!(!trie.is_empty());
+    assert_eq!(trie.complete("", usize::MAX).len(), trie.len());
+    assert!(accounts.all(|account| trie
+        .complete(account.as_str(), usize::MAX)
+        .contains(&&account)));
+}
+
+#[rstest]
+fn trie_of_empty_file_should_be_empty() {
+    let trie = trie("");
+    assert!(trie.is_empty());
+    assert_eq!(trie.len(), 0);
+    assert!(trie.complete("", 10).is_empty());
+}
@@ -0,0 +1,79 @@
+#![allow(missing_docs, unsafe_code)]
+
+//! Guard against regressions of the number of heap allocations made to parse a directive
+//!
+//! The allocations are counted per thread, so that the other threads of the test harness are not counted.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use beancount_parser::{parse_iter, DirectiveContent, Entry};
+
+const FIXTURE: &str = include_str!("samples/allocations.beancount");
+
+/// Maximum number of allocations made to parse one transaction of the fixture
+///
+/// Intentional increases require editing the budget file.
+const BUDGET: &str = include_str!("allocation_budget.txt");
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn count() {
+    // Fails to access the thread local while the thread is being destroyed, which is not a parse
+    let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+}
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count();
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count();
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+#[test]
+fn parsing_a_transaction_should_stay_within_the_allocation_budget() {
+    let budget: usize = BUDGET.trim().parse().unwrap();
+    let mut entries = parse_iter::<f64>(FIXTURE);
+    let mut transactions = 0;
+    loop {
+        let before = allocations();
+        let Some(entry) = entries.next() else { break };
+        let allocated = allocations() - before;
+        let Entry::Directive(directive) = entry.unwrap() else {
+            panic!("the fixture should only contain directives");
+        };
+        assert!(matches!(
+            directive.content,
+            DirectiveContent::Transaction(_)
+        ));
+        transactions += 1;
+        assert!(
+            allocated <= budget,
+            "parsing the transaction at line {} made {allocated} allocations, more than the budget of {budget} (see tests/allocation_budget.txt)",
+            directive.line_number,
+        );
+    }
+    assert_eq!(transactions, 4);
+}
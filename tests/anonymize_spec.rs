@@ -0,0 +1,330 @@
+#![allow(missing_docs)]
+#![cfg(feature = "rust_decimal")]
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use rstest::rstest;
+use rust_decimal::Decimal;
+
+use beancount_parser::{
+    Account, BeancountFile, Currency, CustomValue, Date, DirectiveContent, Inventory,
+    PseudonymizeOptions,
+};
+
+const OFFICIAL: &str = include_str!("samples/official.beancount");
+
+fn official() -> BeancountFile<Decimal> {
+    OFFICIAL.parse().unwrap()
+}
+
+fn errors(file: &BeancountFile<Decimal>) -> Vec<String> {
+    file.verify_balances()
+        .iter()
+        .map(ToString::to_string)
+        .collect()
+}
+
+fn accounts(file: &BeancountFile<Decimal>) -> BTreeSet<Account> {
+    file.balances(None).into_keys().collect()
+}
+
+fn currencies(file: &BeancountFile<Decimal>) -> HashSet<Currency> {
+    file.balances(None)
+        .into_values()
+        .flat_map(Vec::from)
+        .map(|amount| amount.currency)
+        .collect()
+}
+
+fn inventories(file: &BeancountFile<Decimal>) -> Vec<String> {
+    let mut inventories: Vec<String> = file
+        .balances(None)
+        .into_values()
+        .map(|inventory: Inventory<Decimal>| format!("{:?}", Vec::from(inventory)))
+        .collect();
+    inventories.sort();
+    inventories
+}
+
+fn payees(file: &BeancountFile<Decimal>) -> Vec<Option<&str>> {
+    file.directives
+        .iter()
+        .filter_map(|d| match &d.content {
+            DirectiveContent::Transaction(trx) => Some(trx.payee.as_deref()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[rstest]
+fn shift_dates_should_shift_every_directive_by_the_same_number_of_days(
+    #[values(-400, 3, 10_000)] days: i32,
+) {
+    let original = official();
+    let mut shifted = official();
+    shifted.shift_dates(days);
+    for (before, after) in original.directives.iter().zip(&shifted.directives) {
+        assert_eq!(
+            after.date.to_epoch_days() - before.date.to_epoch_days(),
+            days
+        );
+    }
+    assert_eq!(errors(&shifted), Vec::<String>::new());
+    assert_eq!(shifted.balances(None), original.balances(None));
+}
+
+#[rstest]
+fn shift_dates_should_shift_the_dates_of_the_costs_and_custom_values() {
+    let input = r#"
+2023-12-30 * "Stock"
+  Assets:Stock   1 HOOL {100 USD, 2023-12-30}
+  Assets:Cash
+2023-12-31 custom "budget" 2023-12-31 "yearly"
+"#;
+    let mut file: BeancountFile<f64> = input.parse().unwrap();
+    file.shift_dates(1);
+    let DirectiveContent::Transaction(trx) = &file.directives[0].content else {
+        panic!("was not a transaction");
+    };
+    let cost = trx.postings[0].cost.as_ref().unwrap();
+    assert_eq!(cost.date, Some(Date::new(2023, 12, 31)));
+    let DirectiveContent::Custom(custom) = &file.directives[1].content else {
+        panic!("was not a custom directive");
+    };
+    assert_eq!(file.directives[1].date, Date::new(2024, 1, 1));
+    assert_eq!(custom.values[0], CustomValue::Date(Date::new(2024, 1, 1)));
+}
+
+#[rstest]
+fn shift_dates_should_clamp_the_dates_out_of_range() {
+    let mut file: BeancountFile<f64> = "0001-01-10 open Assets:Cash".parse().unwrap();
+    file.shift_dates(-1000);
+    assert_eq!(file.directives[0].date, Date::new(0, 1, 1));
+}
+
+#[rstest]
+fn scale_amounts_should_keep_the_file_balanced() {
+    let factor = Decimal::new(15, 1);
+    let original = official();
+    let mut scaled = official();
+    scaled.scale_amounts(&factor);
+    assert_eq!(errors(&scaled), Vec::<String>::new());
+    let balances = scaled.balances(None);
+    for (account, inventory) in original.balances(None) {
+        for amount in Vec::from(inventory) {
+            assert_eq!(
+                balances[&account].get(&amount.currency),
+                Some(&(amount.value * factor)),
+                "{account} {}",
+                amount.currency
+            );
+        }
+    }
+}
+
+#[rstest]
+fn scale_amounts_should_keep_the_per_unit_prices() {
+    let input = r#"
+2023-01-01 * "Exchange"
+  Assets:Euro   10 EUR @ 1.1 USD
+  Assets:Dollar  -11 USD
+2023-01-01 price EUR 1.1 USD
+"#;
+    let mut file: BeancountFile<Decimal> = input.parse().unwrap();
+    file.scale_amounts(&Decimal::from(3));
+    let balances = file.balances(None);
+    assert_eq!(balances["Assets:Euro"].get("EUR"), Some(&Decimal::from(30)));
+    assert_eq!(
+        balances["Assets:Dollar"].get("USD"),
+        Some(&Decimal::from(-33))
+    );
+    let DirectiveContent::Price(price) = &file.directives[1].content else {
+        panic!("was not a price");
+    };
+    assert_eq!(price.amount.value, Decimal::new(11, 1));
+}
+
+#[rstest]
+fn pseudonymize_should_be_deterministic() {
+    let mut first = official();
+    let mut second = official();
+    let mut other_seed = official();
+    first.pseudonymize(42, &PseudonymizeOptions::default());
+    second.pseudonymize(42, &PseudonymizeOptions::default());
+    other_seed.pseudonymize(43, &PseudonymizeOptions::default());
+    assert_eq!(first.directives, second.directives);
+    assert_ne!(first.directives, other_seed.directives);
+}
+
+#[rstest]
+fn pseudonymize_should_preserve_the_structure_and_the_balances() {
+    let original = official();
+    let mut file = official();
+    file.pseudonymize(42, &PseudonymizeOptions::default());
+    let (before, after) = (accounts(&original), accounts(&file));
+    assert_eq!(after.len(), before.len());
+    assert!(before.is_disjoint(&after));
+    for account in &after {
+        assert!(Account::try_new(account.as_str()).is_ok(), "{account}");
+    }
+    let depths = |accounts: &BTreeSet<Account>| -> Vec<(String, usize)> {
+        let mut depths: Vec<(String, usize)> = accounts
+            .iter()
+            .map(|a| {
+                let root = a.as_str().split(':').next().unwrap().to_owned();
+                (root, a.as_str().split(':').count())
+            })
+            .collect();
+        depths.sort();
+        depths
+    };
+    assert_eq!(depths(&after), depths(&before));
+    assert_eq!(inventories(&file), inventories(&original));
+    assert_eq!(currencies(&file), currencies(&original));
+    assert_eq!(errors(&file), Vec::<String>::new());
+}
+
+#[rstest]
+fn pseudonymize_should_give_the_same_pseudonym_to_the_same_name() {
+    let mut file = official();
+    file.pseudonymize(7, &PseudonymizeOptions::default());
+    let original = official();
+    let mut pseudonyms = HashMap::new();
+    for (before, after) in payees(&original).into_iter().zip(payees(&file)) {
+        assert_eq!(*pseudonyms.entry(before).or_insert(after), after);
+    }
+    let distinct: HashSet<_> = pseudonyms.values().collect();
+    assert_eq!(distinct.len(), pseudonyms.len());
+    assert_eq!(pseudonyms.get(&None), Some(&None));
+}
+
+#[rstest]
+fn pseudonymize_should_replace_the_currencies_when_requested() {
+    let original = official();
+    let mut file = official();
+    file.pseudonymize(42, &PseudonymizeOptions::default().currencies(true));
+    let (before, after) = (currencies(&original), currencies(&file));
+    assert_eq!(after.len(), before.len());
+    assert!(before.is_disjoint(&after));
+    for currency in &after {
+        assert!(currency.as_str().parse::<Currency>().is_ok(), "{currency}");
+    }
+    assert_eq!(
+        file.option_values("operating_currency").count(),
+        original.option_values("operating_currency").count()
+    );
+    assert_ne!(file.option("operating_currency"), Some("USD"));
+    assert_eq!(errors(&file), Vec::<String>::new());
+}
+
+#[rstest]
+fn pseudonymize_should_replace_the_rounding_account_option() {
+    let input = r#"
+option "account_rounding" "Equity:Rounding"
+2023-01-01 open Equity:Rounding
+"#;
+    let mut file: BeancountFile<f64> = input.parse().unwrap();
+    file.pseudonymize(1, &PseudonymizeOptions::default());
+    let DirectiveContent::Open(open) = &file.directives[0].content else {
+        panic!("was not an open directive");
+    };
+    assert_eq!(file.option("account_rounding"), Some(open.account.as_str()));
+    assert_ne!(open.account.as_str(), "Equity:Rounding");
+}
+
+#[rstest]
+fn pseudonymize_should_keep_what_is_disabled() {
+    let original = official();
+    let mut file = official();
+    file.pseudonymize(
+        42,
+        &PseudonymizeOptions::default()
+            .payees(false)
+            .narrations(false)
+            .accounts(false)
+            .tags_and_links(false)
+            .metadata(false)
+            .notes(false)
+            .documents(false)
+            .events(false)
+            .queries(false),
+    );
+    assert_eq!(file.directives, original.directives);
+}
+
+#[rstest]
+fn pseudonymize_should_replace_every_identifying_text() {
+    let input = r#"
+pushtag #trip-paris
+pushmeta traveler: "Alice"
+2023-01-01 open Assets:Bank
+  iban: "CH93 0076 2011 6238 5295 7"
+2023-01-02 * "Shop" "Gift" #for-bob ^invoice-acme-42
+  Assets:Bank  -10 CHF
+    receipt: "Acme receipt"
+  Expenses:Gifts
+2023-01-03 note Assets:Bank "Called Alice about the loan" #loan ^contract-7
+2023-01-04 document Assets:Bank "documents/Assets/Bank/alice-statement.pdf"
+2023-01-05 event "location" "Paris, rue de Rivoli 12"
+2023-01-06 query "cash" "SELECT account WHERE account ~ 'Bank'"
+popmeta traveler:
+"#;
+    let mut file: BeancountFile<f64> = input.parse().unwrap();
+    file.pseudonymize(42, &PseudonymizeOptions::default());
+    let debug = format!("{:?}", file.directives);
+    for text in [
+        "Alice",
+        "Bob",
+        "bob",
+        "trip-paris",
+        "invoice-acme",
+        "contract-7",
+        "loan",
+        "CH93",
+        "Acme",
+        "Paris",
+        "statement",
+        "Bank",
+        "SELECT",
+    ] {
+        assert!(!debug.contains(text), "{text} found in {debug}");
+    }
+    assert!(!file.pushed_tags().any(|tag| tag.as_str() == "trip-paris"));
+    let DirectiveContent::Document(document) = &file.directives[3].content else {
+        panic!("was not a document");
+    };
+    let DirectiveContent::Open(open) = &file.directives[0].content else {
+        panic!("was not an open directive");
+    };
+    let components: Vec<&str> = document.path.split('/').collect();
+    assert_eq!(components.len(), 4);
+    assert_eq!(components[1], "Assets");
+    assert_eq!(
+        components[2],
+        open.account.as_str().split(':').nth(1).unwrap()
+    );
+    assert_eq!(components[3].rsplit_once('.').unwrap().1, "pdf");
+    let DirectiveContent::Event(event) = &file.directives[4].content else {
+        panic!("was not an event");
+    };
+    assert_eq!(event.name, "location");
+    let DirectiveContent::Query(query) = &file.directives[5].content else {
+        panic!("was not a query");
+    };
+    assert_eq!(query.name, "cash");
+}
+
+#[rstest]
+fn pseudonym_should_not_depend_on_the_other_names_of_the_file() {
+    let pseudonymized = |input: &str| {
+        let mut file: BeancountFile<f64> = input.parse().unwrap();
+        file.pseudonymize(42, &PseudonymizeOptions::default());
+        let DirectiveContent::Transaction(trx) = &file.directives.last().unwrap().content else {
+            panic!("was not a transaction");
+        };
+        trx.payee.clone().unwrap()
+    };
+    let alone = pseudonymized("2023-01-02 * \"Shop\" \"Gift\"");
+    let after_others = pseudonymized(&format!("{OFFICIAL}\n2023-12-31 * \"Shop\" \"Gift\"\n"));
+    assert_eq!(alone, after_others);
+}
@@ -0,0 +1,161 @@
+#![allow(missing_docs)]
+
+use rstest::rstest;
+
+use beancount_parser::{
+    attachment::{document_index, document_key, DOCUMENT_KEY},
+    metadata::Value,
+    BeancountFile, Directive,
+};
+
+const FAVA: &str = r#"
+2023-05-20 * "Hardware store" "Drill" ^drill
+  document: "documents/Expenses/Tools/2023-05-20 receipt.pdf"
+  document-2: "documents/Expenses/Tools/2023-05-20 warranty.pdf"
+  Expenses:Tools  80 CHF
+  Assets:Cash
+2023-05-21 * "Hardware store" "Drill bits"
+  document-3: "third.pdf"
+  document: "first.pdf"
+  documents: "not-a-document.pdf"
+  document-1: "not-a-document.pdf"
+  Expenses:Tools  10 CHF
+  Assets:Cash
+2023-06-01 * "Insurance" "Drill coverage" ^drill ^insurance
+  document: "documents/insurance.pdf"
+  Expenses:Insurance  5 CHF
+  Assets:Cash
+2023-06-02 * "Insurance" "Refund" ^insurance
+  document: "documents/refund.pdf"
+  edited: TRUE
+  Assets:Cash  5 CHF
+  Expenses:Insurance
+"#;
+
+fn directive(input: &str) -> Directive<f64> {
+    input.parse().unwrap()
+}
+
+#[rstest]
+#[case(1, "document")]
+#[case(2, "document-2")]
+#[case(10, "document-10")]
+fn document_key_should_be_numbered_after_the_first(#[case] n: usize, #[case] key: &str) {
+    assert_eq!(document_key(n), key);
+    assert_eq!(document_index(key), Some(n));
+}
+
+#[rstest]
+#[case::first_numbered("document-1")]
+#[case::leading_zero("document-02")]
+#[case::plural("documents")]
+#[case::no_number("document-")]
+#[case::other("receipt")]
+fn document_index_should_reject_other_keys(#[case] key: &str) {
+    assert_eq!(document_index(key), None);
+}
+
+#[rstest]
+fn attach_document_should_use_the_next_free_key() {
+    let mut directive = directive("2023-05-20 * \"Drill\"\n  document-2: \"second.pdf\"");
+    directive.attach_document("first.pdf");
+    directive.attach_document("third.pdf");
+    directive.attach_document("fourth.pdf");
+    assert_eq!(
+        directive.metadata.get(DOCUMENT_KEY),
+        Some(&Value::String("first.pdf".into()))
+    );
+    assert_eq!(
+        directive.metadata.get("document-4"),
+        Some(&Value::String("fourth.pdf".into()))
+    );
+    assert_eq!(
+        directive.attached_documents(),
+        ["first.pdf", "second.pdf", "third.pdf", "fourth.pdf"]
+    );
+}
+
+#[rstest]
+fn attached_documents_should_read_files_written_by_fava() {
+    let file: BeancountFile<f64> = FAVA.parse().unwrap();
+    let documents: Vec<Vec<&str>> = file
+        .directives
+        .iter()
+        .map(Directive::attached_documents)
+        .collect();
+    assert_eq!(
+        documents,
+        [
+            vec![
+                "documents/Expenses/Tools/2023-05-20 receipt.pdf",
+                "documents/Expenses/Tools/2023-05-20 warranty.pdf"
+            ],
+            vec!["first.pdf", "third.pdf"],
+            vec!["documents/insurance.pdf"],
+            vec!["documents/refund.pdf"],
+        ]
+    );
+}
+
+#[rstest]
+fn attached_documents_should_ignore_values_that_are_not_strings() {
+    let directive = directive("2023-05-20 * \"Drill\"\n  document: 42\n  document-2: \"real.pdf\"");
+    assert_eq!(directive.attached_documents(), ["real.pdf"]);
+}
+
+#[rstest]
+#[case::shared_link(0, &[
+    "documents/Expenses/Tools/2023-05-20 receipt.pdf",
+    "documents/Expenses/Tools/2023-05-20 warranty.pdf",
+    "documents/insurance.pdf",
+])]
+#[case::without_link(1, &["first.pdf", "third.pdf"])]
+#[case::several_links(2, &[
+    "documents/insurance.pdf",
+    "documents/Expenses/Tools/2023-05-20 receipt.pdf",
+    "documents/Expenses/Tools/2023-05-20 warranty.pdf",
+    "documents/refund.pdf",
+])]
+fn documents_for_should_include_the_documents_of_linked_directives(
+    #[case] index: usize,
+    #[case] expected: &[&str],
+) {
+    let file: BeancountFile<f64> = FAVA.parse().unwrap();
+    let (id, _) = file
+        .clone()
+        .into_snapshot()
+        .directives()
+        .nth(index)
+        .unwrap();
+    let documents = file.documents_for(id);
+    assert_eq!(documents, expected);
+}
+
+#[rstest]
+fn documents_for_should_only_return_a_path_once() {
+    let input = r#"
+2023-05-20 * "Invoice" ^a ^b
+  document: "shared.pdf"
+2023-05-21 * "Payment" ^a
+  document: "shared.pdf"
+  document-2: "payment.pdf"
+2023-05-22 * "Reminder" ^b
+  document: "payment.pdf"
+"#;
+    let file: BeancountFile<f64> = input.parse().unwrap();
+    let invoice = file.links_index()["a"][0];
+    assert_eq!(file.documents_for(invoice), ["shared.pdf", "payment.pdf"]);
+}
+
+#[cfg(feature = "serde")]
+#[rstest]
+fn attached_documents_should_survive_a_serialization_round_trip() {
+    let mut file: BeancountFile<f64> = FAVA.parse().unwrap();
+    file.directives[1].attach_document("second.pdf");
+    let json = serde_json::to_string(&file).unwrap();
+    let restored: BeancountFile<f64> = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        restored.directives[1].attached_documents(),
+        ["first.pdf", "second.pdf", "third.pdf"]
+    );
+}
@@ -0,0 +1,301 @@
+#![allow(missing_docs)]
+#![cfg(feature = "rust_decimal")]
+
+use rstest::rstest;
+use rust_decimal::Decimal;
+
+use beancount_parser::{
+    parse_with_options, Amount, BeancountFile, Code, Date, DirectiveContent, ParseOptions,
+};
+
+const OFFICIAL: &str = include_str!("samples/official.beancount");
+
+fn date(input: &str) -> Date {
+    input.parse().unwrap()
+}
+
+fn balance(
+    file: &BeancountFile<Decimal>,
+    as_of: Option<&str>,
+    account: &str,
+) -> Vec<Amount<Decimal>> {
+    file.balances(as_of.map(date))
+        .remove(account)
+        .map(Into::into)
+        .unwrap_or_default()
+}
+
+fn amounts(input: &[(i64, u32, &str)]) -> Vec<Amount<Decimal>> {
+    input
+        .iter()
+        .map(|&(num, scale, currency)| Amount {
+            value: Decimal::new(num, scale),
+            currency: currency.parse().unwrap(),
+        })
+        .collect()
+}
+
+#[rstest]
+fn every_balance_assertion_of_the_official_example_holds() {
+    let file: BeancountFile<Decimal> = OFFICIAL.parse().unwrap();
+    let tree = file.account_tree();
+    let mut checked = 0;
+    for directive in &file.directives {
+        let DirectiveContent::Balance(assertion) = &directive.content else {
+            continue;
+        };
+        let totals = tree.rollup(&file.balances(Some(directive.date)));
+        let actual = totals[&assertion.account]
+            .get(&assertion.amount.currency)
+            .copied()
+            .unwrap_or_default();
+        assert_eq!(
+            actual, assertion.amount.value,
+            "balance of {} on {:?}",
+            assertion.account, directive.date
+        );
+        checked += 1;
+    }
+    assert!(checked > 50, "only {checked} assertions");
+}
+
+#[rstest]
+#[case::explicit_amounts(
+    "2023-01-01 * \"Pay\"\n  Assets:Cash  -10 CHF\n  Expenses:Food  10 CHF",
+    &[(-10, 0, "CHF")]
+)]
+#[case::interpolated(
+    "2023-01-01 * \"Pay\"\n  Expenses:Food  10 CHF\n  Assets:Cash",
+    &[(-10, 0, "CHF")]
+)]
+#[case::interpolated_multiple_currencies(
+    "2023-01-01 * \"Pay\"\n  Expenses:Food  10 CHF\n  Expenses:Food  3 EUR\n  Assets:Cash",
+    &[(-10, 0, "CHF"), (-3, 0, "EUR")]
+)]
+#[case::interpolated_from_cost(
+    "2023-01-01 * \"Buy\"\n  Assets:Stock  2 ACME {10.5 CHF}\n  Assets:Cash",
+    &[(-21, 0, "CHF")]
+)]
+#[case::interpolated_from_unit_price(
+    "2023-01-01 * \"Exchange\"\n  Assets:Bank  -10 EUR @ 1.1 CHF\n  Assets:Cash",
+    &[(11, 0, "CHF")]
+)]
+#[case::interpolated_from_total_price(
+    "2023-01-01 * \"Exchange\"\n  Assets:Bank  -10 EUR @@ 12 CHF\n  Assets:Cash",
+    &[(12, 0, "CHF")]
+)]
+#[case::cost_has_precedence_over_price(
+    "2023-01-01 * \"Sell\"\n  Assets:Stock  -2 ACME {10 CHF} @ 12 CHF\n  Income:Gains  -4 CHF\n  Assets:Cash",
+    &[(24, 0, "CHF")]
+)]
+#[case::pad(
+    "2023-01-01 pad Assets:Cash Equity:Opening\n2023-01-05 balance Assets:Cash 42 CHF",
+    &[(42, 0, "CHF")]
+)]
+#[case::pad_consider_existing_amount(
+    "2023-01-01 * \"Withdraw\"\n  Assets:Cash  10 CHF\n  Assets:Bank\n2023-01-02 pad Assets:Cash Equity:Opening\n2023-01-05 balance Assets:Cash 42 CHF",
+    &[(42, 0, "CHF")]
+)]
+#[case::pad_once_per_currency(
+    "2023-01-01 pad Assets:Cash Equity:Opening\n2023-01-05 balance Assets:Cash 42 CHF\n2023-01-06 * \"Pay\"\n  Assets:Cash  -2 CHF\n  Expenses:Food\n2023-01-07 balance Assets:Cash 42 CHF",
+    &[(40, 0, "CHF")]
+)]
+#[case::pad_multiple_currencies(
+    "2023-01-01 pad Assets:Cash Equity:Opening\n2023-01-05 balance Assets:Cash 42 CHF\n2023-01-05 balance Assets:Cash 3 EUR",
+    &[(42, 0, "CHF"), (3, 0, "EUR")]
+)]
+#[case::pad_within_tolerance(
+    "2023-01-01 * \"Withdraw\"\n  Assets:Cash  10 CHF\n  Assets:Bank\n2023-01-02 pad Assets:Cash Equity:Opening\n2023-01-05 balance Assets:Cash 10.01 ~ 0.02 CHF",
+    &[(10, 0, "CHF")]
+)]
+fn should_compute_balance_of_cash_account(
+    #[case] input: &str,
+    #[case] expected: &[(i64, u32, &str)],
+) {
+    let file: BeancountFile<Decimal> = input.parse().unwrap();
+    assert_eq!(balance(&file, None, "Assets:Cash"), amounts(expected));
+}
+
+#[rstest]
+#[case::before_everything(Some("2023-01-01"), &[])]
+#[case::before_the_pad(Some("2023-01-02"), &[(10, 0, "CHF")])]
+#[case::between_pad_and_assertion(Some("2023-01-03"), &[(100, 0, "CHF")])]
+#[case::on_assertion_day(Some("2023-01-05"), &[(100, 0, "CHF")])]
+#[case::after_withdrawal(Some("2023-01-07"), &[(80, 0, "CHF")])]
+#[case::end(None, &[(80, 0, "CHF")])]
+fn should_compute_balance_as_of_date(
+    #[case] as_of: Option<&str>,
+    #[case] expected: &[(i64, u32, &str)],
+) {
+    let input = r#"
+2023-01-01 * "Deposit"
+  Assets:Bank  10 CHF
+  Income:Salary
+2023-01-02 pad Assets:Bank Equity:Opening
+2023-01-05 balance Assets:Bank 100 CHF
+2023-01-06 * "Withdraw"
+  Assets:Bank  -20 CHF
+  Assets:Cash
+"#;
+    let file: BeancountFile<Decimal> = input.parse().unwrap();
+    assert_eq!(balance(&file, as_of, "Assets:Bank"), amounts(expected));
+}
+
+#[rstest]
+fn unbalanced_remainder_goes_to_the_rounding_account() {
+    let input = r#"
+option "account_rounding" "Equity:Rounding"
+2023-01-01 * "Exchange"
+  Assets:Bank  -10 EUR @ 1.1111 CHF
+  Assets:Cash   11.11 CHF
+"#;
+    let file: BeancountFile<Decimal> = input.parse().unwrap();
+    assert_eq!(
+        balance(&file, None, "Equity:Rounding"),
+        amounts(&[(1, 3, "CHF")])
+    );
+}
+
+#[rstest]
+fn without_rounding_account_the_remainder_is_not_posted() {
+    let input = r#"
+2023-01-01 * "Exchange"
+  Assets:Bank  -10 EUR @ 1.1111 CHF
+  Assets:Cash   11.11 CHF
+"#;
+    let file: BeancountFile<Decimal> = input.parse().unwrap();
+    let balances = file.balances(None);
+    assert_eq!(balances.len(), 2, "{balances:?}");
+}
+
+#[rstest]
+fn rollup_sums_descendants() {
+    let input = r#"
+2023-01-01 * "Shopping"
+  Expenses:Food:Groceries   10 CHF
+  Expenses:Food:Restaurant  20 CHF
+  Expenses:Food             1 CHF
+  Expenses:Transport         5 EUR
+  Assets:Cash
+"#;
+    let file: BeancountFile<Decimal> = input.parse().unwrap();
+    let totals = file.account_tree().rollup(&file.balances(None));
+    let total = |account: &str| -> Vec<Amount<Decimal>> { totals[account].clone().into() };
+    assert_eq!(total("Expenses:Food:Groceries"), amounts(&[(10, 0, "CHF")]));
+    assert_eq!(total("Expenses:Food"), amounts(&[(31, 0, "CHF")]));
+    assert_eq!(total("Expenses"), amounts(&[(31, 0, "CHF"), (5, 0, "EUR")]));
+    assert_eq!(total("Assets"), amounts(&[(-31, 0, "CHF"), (-5, 0, "EUR")]));
+}
+
+#[rstest]
+fn official_example_should_pass_balance_verification(#[values(false, true)] aggregate: bool) {
+    let options = ParseOptions::default().aggregate_balance_assertions(aggregate);
+    let file: BeancountFile<Decimal> = parse_with_options(OFFICIAL, &options).unwrap();
+    assert_eq!(
+        file.verify_balances()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>(),
+        Vec::<String>::new()
+    );
+}
+
+const PARENT_ASSERTION: &str = r#"
+2023-01-01 * "Salary"
+  Assets:Bank:Checking  600 USD
+  Assets:Bank:Savings   400 USD
+  Income:Salary
+2023-01-02 balance Assets:Bank 1000 USD
+2023-01-02 balance Assets:Bank:Checking 600 USD
+2023-01-02 balance Assets:Bank:Check 0 USD
+"#;
+
+#[rstest]
+fn aggregate_balance_assertion_should_sum_the_sub_accounts() {
+    let options = ParseOptions::default().aggregate_balance_assertions(true);
+    let file: BeancountFile<Decimal> = parse_with_options(PARENT_ASSERTION, &options).unwrap();
+    let errors = file.verify_balances();
+    assert!(errors.is_empty(), "{errors:?}");
+}
+
+#[rstest]
+fn balance_assertion_should_only_check_the_asserted_account_by_default() {
+    let file: BeancountFile<Decimal> = PARENT_ASSERTION.parse().unwrap();
+    let errors = file.verify_balances();
+    let [error] = &errors[..] else {
+        panic!("expected one error: {errors:?}");
+    };
+    assert_eq!(error.code(), Code::BalanceMismatch);
+    assert_eq!(error.line_number(), 6);
+    assert_eq!(
+        error.to_string(),
+        "error[E006]: Balance assertion failed at line: 6 (expected 1000 USD in Assets:Bank, found 0 USD)"
+    );
+}
+
+#[rstest]
+#[case::within_tolerance("99.99 ~ 0.02 USD", 0)]
+#[case::outside_tolerance("99.90 ~ 0.02 USD", 1)]
+#[case::without_tolerance("99.99 USD", 1)]
+fn balance_verification_should_apply_the_tolerance(
+    #[case] amount: &str,
+    #[case] expected_errors: usize,
+) {
+    let input = format!("2023-01-01 * \"Salary\"\n  Assets:Bank  100 USD\n  Income:Salary\n2023-01-02 balance Assets:Bank {amount}");
+    let file: BeancountFile<Decimal> = input.parse().unwrap();
+    assert_eq!(file.verify_balances().len(), expected_errors);
+}
+
+#[rstest]
+#[case::first_year(date("2022-01-01")..date("2023-01-01"))]
+#[case::one_month(date("2023-06-01")..date("2023-07-01"))]
+#[case::one_day(date("2023-06-23")..date("2023-06-24"))]
+fn accounts_touched_in_should_contain_every_account_whose_balance_depends_on_the_range(
+    #[case] range: std::ops::Range<Date>,
+) {
+    let file: BeancountFile<Decimal> = OFFICIAL.parse().unwrap();
+    let touched = file.accounts_touched_in(range.clone());
+    assert!(!touched.is_empty());
+    let mut without = file.clone();
+    without.directives.retain(|d| !range.contains(&d.date));
+    let before = without.balances(None);
+    for (account, inventory) in file.balances(None) {
+        if !touched.contains(&account) {
+            assert_eq!(before.get(&account), Some(&inventory), "{account}");
+        }
+    }
+}
+
+#[rstest]
+fn accounts_touched_in_should_contain_the_source_of_a_pad_resolved_in_the_range() {
+    let input = r"
+2023-01-01 pad Assets:Checking Equity:Opening-Balances
+2023-01-01 pad Assets:Savings Equity:Opening-Balances
+2023-01-15 pad Assets:Checking Income:Gifts
+2023-02-01 balance Assets:Checking 100 CHF
+";
+    let file: BeancountFile<Decimal> = input.parse().unwrap();
+    let mut touched: Vec<String> = file
+        .accounts_touched_in(date("2023-02-01")..=date("2023-02-01"))
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    touched.sort_unstable();
+    assert_eq!(touched, ["Assets:Checking", "Income:Gifts"]);
+}
+
+#[rstest]
+#[case::unbalanced("  Expenses:Food  4.001 CHF\n  Assets:Cash  -4 CHF", true)]
+#[case::balanced("  Expenses:Food  4 CHF\n  Assets:Cash  -4 CHF", false)]
+fn accounts_touched_in_should_contain_the_rounding_account_if_a_transaction_does_not_balance(
+    #[case] postings: &str,
+    #[case] expected: bool,
+) {
+    let input = format!(
+        "option \"account_rounding\" \"Equity:Rounding\"\n2023-01-10 * \"Coffee\"\n{postings}"
+    );
+    let file: BeancountFile<Decimal> = input.parse().unwrap();
+    let touched = file.accounts_touched_in(..);
+    assert_eq!(touched.len(), 2 + usize::from(expected));
+    assert_eq!(touched.contains("Equity:Rounding"), expected);
+}
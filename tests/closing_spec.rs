@@ -0,0 +1,151 @@
+#![allow(missing_docs)]
+
+use rstest::rstest;
+
+use beancount_parser::{
+    Account, BeancountFile, Date, Directive, DirectiveContent, Inventory, Transaction,
+};
+
+const OFFICIAL: &str = include_str!("samples/official.beancount");
+
+const LEDGER: &str = r#"
+2023-03-01 * "Salary"
+  Assets:Bank     1000 CHF
+  Income:Salary
+2023-04-01 * "Bonus"
+  Assets:Bank:EUR  200 EUR
+  Income:Salary
+2023-05-01 * "Refund"
+  Expenses:Food   -10 CHF
+  Assets:Bank
+2023-05-02 * "Lunch"
+  Expenses:Food    10 CHF
+  Assets:Bank
+"#;
+
+fn target() -> Account {
+    "Equity:Earnings:Previous".parse().unwrap()
+}
+
+fn transaction(directive: &Directive<f64>) -> &Transaction<f64> {
+    match &directive.content {
+        DirectiveContent::Transaction(trx) => trx,
+        _ => panic!("was not a transaction: {directive:?}"),
+    }
+}
+
+fn is_income_or_expense(account: &Account) -> bool {
+    account.as_str().starts_with("Income:") || account.as_str().starts_with("Expenses:")
+}
+
+#[rstest]
+fn closing_entries_should_zero_the_income_statement_of_the_example_ledger() {
+    let mut file: BeancountFile<f64> = OFFICIAL.parse().unwrap();
+    let as_of = Date::new(2024, 1, 1);
+    let before = file.balances(Some(as_of));
+    let entries = file.closing_entries(as_of, &target(), false);
+    assert!(!entries.is_empty());
+    for entry in &entries {
+        let trx = transaction(entry);
+        assert_eq!(trx.flag, Some('*'));
+        assert_eq!(trx.narration.as_deref(), Some("Closing entry"));
+        assert_eq!(entry.date, Date::new(2023, 12, 31));
+        assert_eq!(entry.provenance().unwrap().label, "closing entries");
+        let mut residual = Inventory::new();
+        for posting in &trx.postings {
+            residual.add(posting.amount.clone().unwrap());
+        }
+        assert!(residual.is_empty(), "{residual:?}");
+    }
+    file.directives.extend(entries);
+    let after = file.balances(Some(as_of));
+    for (account, inventory) in &after {
+        if is_income_or_expense(account) {
+            assert!(inventory.is_empty(), "{account}: {inventory:?}");
+        } else if *account != target() {
+            assert_eq!(Some(inventory), before.get(account), "{account}");
+        }
+    }
+    let mut earnings = Inventory::new();
+    for (account, inventory) in before.iter().filter(|(a, _)| is_income_or_expense(a)) {
+        earnings.merge(inventory);
+        assert!(after[account].is_empty(), "{account}");
+    }
+    assert_eq!(after[&target()], earnings);
+}
+
+#[rstest]
+fn closing_entries_should_post_each_currency_of_an_account() {
+    let file: BeancountFile<f64> = LEDGER.parse().unwrap();
+    let entries = file.closing_entries(Date::new(2024, 1, 1), &target(), false);
+    assert_eq!(
+        entries.len(),
+        1,
+        "the zero balance of Expenses:Food is not closed"
+    );
+    let postings: Vec<(&str, f64, &str)> = transaction(&entries[0])
+        .postings
+        .iter()
+        .map(|p| {
+            let amount = p.amount.as_ref().unwrap();
+            (p.account.as_str(), amount.value, amount.currency.as_str())
+        })
+        .collect();
+    assert_eq!(
+        postings,
+        [
+            ("Income:Salary", 1000.0, "CHF"),
+            ("Income:Salary", 200.0, "EUR"),
+            ("Equity:Earnings:Previous", -1000.0, "CHF"),
+            ("Equity:Earnings:Previous", -200.0, "EUR"),
+        ]
+    );
+}
+
+#[rstest]
+#[case::without_close(false, &[])]
+#[case::with_close(true, &["Expenses:Rent", "Revenus:Salary"])]
+fn closing_entries_should_close_the_zeroed_accounts_on_demand(
+    #[case] close_accounts: bool,
+    #[case] expected: &[&str],
+) {
+    let input = r#"
+option "name_income" "Revenus"
+2023-03-01 * "Salary"
+  Assets:Bank      1000 CHF
+  Revenus:Salary
+2023-06-01 * "Rent"
+  Expenses:Rent  400 CHF
+  Assets:Bank
+2024-01-01 * "Rent"
+  Expenses:Rent  400 CHF
+  Assets:Bank
+"#;
+    let file: BeancountFile<f64> = input.parse().unwrap();
+    let entries = file.closing_entries(Date::new(2024, 1, 1), &target(), close_accounts);
+    let closed: Vec<&str> = entries
+        .iter()
+        .filter_map(|entry| match &entry.content {
+            DirectiveContent::Close(close) => Some(close.account.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(closed, expected);
+    assert_eq!(entries.len(), 2 + expected.len());
+}
+
+#[cfg(feature = "serde")]
+#[rstest]
+fn closing_entries_should_round_trip_through_json() {
+    let file: BeancountFile<f64> = LEDGER.parse().unwrap();
+    let entries = file.closing_entries(Date::new(2024, 1, 1), &target(), true);
+    let json = serde_json::to_string(&entries).unwrap();
+    let deserialized: Vec<Directive<f64>> = serde_json::from_str(&json).unwrap();
+    let contents = |directives: &[Directive<f64>]| -> Vec<(Date, DirectiveContent<f64>)> {
+        directives
+            .iter()
+            .map(|d| (d.date, d.content.clone()))
+            .collect()
+    };
+    assert_eq!(contents(&deserialized), contents(&entries));
+}
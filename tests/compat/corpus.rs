@@ -0,0 +1,38 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Returns the paths of all the `.beancount` files found (recursively) in the directory, sorted
+pub(crate) fn files(directory: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![directory.to_path_buf()];
+    while let Some(directory) = pending.pop() {
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.extension().is_some_and(|ext| ext == "beancount") {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_samples() {
+        let samples = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/samples");
+        let files = files(&samples).unwrap();
+        assert!(
+            files.contains(&samples.join("official.beancount")),
+            "{files:?}"
+        );
+        assert!(files.windows(2).all(|w| w[0] < w[1]));
+    }
+}
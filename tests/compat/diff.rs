@@ -0,0 +1,286 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use rust_decimal::Decimal;
+
+use crate::record::{Parsed, Record};
+
+/// A difference between the output of the Python parser (expected) and this crate (actual)
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Divergence {
+    pub(crate) line: u32,
+    pub(crate) field: &'static str,
+    pub(crate) expected: String,
+    pub(crate) actual: String,
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: python={} rust={}",
+            self.line, self.field, self.expected, self.actual
+        )
+    }
+}
+
+/// Compare the records of both parsers, matching them by line number
+///
+/// Amounts are compared by value (`1.0` and `1.00` are equal), and may differ by at most `tolerance`.
+pub(crate) fn diff(expected: &Parsed, actual: &Parsed, tolerance: Decimal) -> Vec<Divergence> {
+    let mut divergences = Vec::new();
+    for &line in &actual.errors {
+        if !expected.errors.contains(&line) {
+            divergences.push(Divergence {
+                line,
+                field: "error",
+                expected: "ok".into(),
+                actual: "syntax error".into(),
+            });
+        }
+    }
+    if !actual.errors.is_empty() {
+        // The crate stops at the first error, the records cannot be compared
+        return divergences;
+    }
+    for &line in &expected.errors {
+        divergences.push(Divergence {
+            line,
+            field: "error",
+            expected: "syntax error".into(),
+            actual: "ok".into(),
+        });
+    }
+    let expected = by_line(&expected.records);
+    let actual = by_line(&actual.records);
+    let lines = expected.keys().chain(actual.keys()).copied();
+    let mut lines: Vec<u32> = lines.collect();
+    lines.sort_unstable();
+    lines.dedup();
+    for line in lines {
+        match (expected.get(&line), actual.get(&line)) {
+            (Some(expected), Some(actual)) => {
+                diff_record(expected, actual, tolerance, &mut divergences);
+            }
+            (expected, actual) => divergences.push(Divergence {
+                line,
+                field: "directive",
+                expected: expected.map_or_else(|| "-".into(), |r| r.kind.clone()),
+                actual: actual.map_or_else(|| "-".into(), |r| r.kind.clone()),
+            }),
+        }
+    }
+    divergences
+}
+
+fn by_line(records: &[Record]) -> BTreeMap<u32, &Record> {
+    records.iter().map(|record| (record.line, record)).collect()
+}
+
+fn diff_record(
+    expected: &Record,
+    actual: &Record,
+    tolerance: Decimal,
+    divergences: &mut Vec<Divergence>,
+) {
+    let line = actual.line;
+    let mut push = |field: &'static str, python: String, rust: String| {
+        if python != rust {
+            divergences.push(Divergence {
+                line,
+                field,
+                expected: python,
+                actual: rust,
+            });
+        }
+    };
+    push("kind", expected.kind.clone(), actual.kind.clone());
+    push("date", expected.date.clone(), actual.date.clone());
+    push(
+        "accounts",
+        format!("{:?}", expected.accounts),
+        format!("{:?}", actual.accounts),
+    );
+    push("payee", expected.payee.clone(), actual.payee.clone());
+    push(
+        "narration",
+        expected.narration.clone(),
+        actual.narration.clone(),
+    );
+    if !amounts_match(&expected.amounts, &actual.amounts, tolerance) {
+        push(
+            "amounts",
+            format!("{:?}", expected.amounts),
+            format!("{:?}", actual.amounts),
+        );
+    }
+}
+
+fn amounts_match(
+    expected: &[(Decimal, String)],
+    actual: &[(Decimal, String)],
+    tolerance: Decimal,
+) -> bool {
+    expected.len() == actual.len()
+        && expected
+            .iter()
+            .zip(actual)
+            .all(|((v1, c1), (v2, c2))| c1 == c2 && (*v1 - *v2).abs() <= tolerance)
+}
+
+/// Known divergences that do not fail the test
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Allowlist(Vec<Pattern>);
+
+#[derive(Debug, Clone)]
+struct Pattern {
+    file: String,
+    line: Option<u32>,
+    field: Option<String>,
+}
+
+impl Allowlist {
+    pub(crate) fn allows(&self, file: &str, divergence: &Divergence) -> bool {
+        self.0.iter().any(|pattern| {
+            (pattern.file == "*" || pattern.file == file)
+                && pattern.line.map_or(true, |line| line == divergence.line)
+                && pattern
+                    .field
+                    .as_ref()
+                    .map_or(true, |field| field == divergence.field)
+        })
+    }
+}
+
+impl FromStr for Allowlist {
+    type Err = String;
+
+    /// Parse the allowlist, with one `<file>:<line>:<field>` entry per line
+    ///
+    /// Empty lines and lines starting with `#` are ignored.
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        input
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|entry| {
+                let mut parts = entry.rsplitn(3, ':');
+                let (Some(field), Some(line), Some(file)) =
+                    (parts.next(), parts.next(), parts.next())
+                else {
+                    return Err(format!("invalid allowlist entry: {entry}"));
+                };
+                let line = match line {
+                    "*" => None,
+                    line => Some(
+                        line.parse()
+                            .map_err(|_| format!("invalid line in allowlist entry: {entry}"))?,
+                    ),
+                };
+                Ok(Pattern {
+                    file: file.into(),
+                    line,
+                    field: (field != "*").then(|| field.into()),
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(Allowlist)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(line: u32, narration: &str, amount: Decimal) -> Record {
+        Record {
+            line,
+            date: "2023-01-01".into(),
+            kind: "transaction".into(),
+            accounts: vec!["Assets:Cash".into()],
+            amounts: vec![(amount, "CHF".into())],
+            payee: String::new(),
+            narration: narration.into(),
+        }
+    }
+
+    fn parsed(records: Vec<Record>) -> Parsed {
+        Parsed {
+            records,
+            errors: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn equal_records_have_no_divergence() {
+        let expected = parsed(vec![record(1, "a", Decimal::new(10, 0))]);
+        let actual = parsed(vec![record(1, "a", Decimal::new(100, 1))]);
+        assert_eq!(diff(&expected, &actual, Decimal::ZERO), vec![]);
+    }
+
+    #[test]
+    fn amounts_are_compared_with_tolerance() {
+        let expected = parsed(vec![record(1, "a", Decimal::new(1000, 3))]);
+        let actual = parsed(vec![record(1, "a", Decimal::new(1001, 3))]);
+        assert_eq!(diff(&expected, &actual, Decimal::ZERO).len(), 1);
+        assert_eq!(diff(&expected, &actual, Decimal::new(1, 3)), vec![]);
+    }
+
+    #[test]
+    fn different_fields_and_missing_directives_are_reported() {
+        let expected = parsed(vec![
+            record(1, "a", Decimal::ONE),
+            record(5, "b", Decimal::ONE),
+        ]);
+        let actual = parsed(vec![record(1, "c", Decimal::ONE)]);
+        let divergences: Vec<String> = diff(&expected, &actual, Decimal::ZERO)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            divergences,
+            vec![
+                "1:narration: python=a rust=c",
+                "5:directive: python=transaction rust=-"
+            ]
+        );
+    }
+
+    #[test]
+    fn syntax_errors_are_compared() {
+        let ok = parsed(vec![]);
+        let error = Parsed {
+            records: vec![],
+            errors: vec![3],
+        };
+        assert_eq!(diff(&error, &error, Decimal::ZERO), vec![]);
+        assert_eq!(diff(&ok, &error, Decimal::ZERO)[0].field, "error");
+        assert_eq!(diff(&error, &ok, Decimal::ZERO)[0].field, "error");
+    }
+
+    #[test]
+    fn allowlist_matches_with_wildcards() {
+        let allowlist: Allowlist = "# comment\n\nold.beancount:*:narration\n*:12:*\n"
+            .parse()
+            .unwrap();
+        let divergence = |line, field| Divergence {
+            line,
+            field,
+            expected: String::new(),
+            actual: String::new(),
+        };
+        assert!(allowlist.allows("old.beancount", &divergence(3, "narration")));
+        assert!(!allowlist.allows("old.beancount", &divergence(3, "amounts")));
+        assert!(!allowlist.allows("new.beancount", &divergence(3, "narration")));
+        assert!(allowlist.allows("new.beancount", &divergence(12, "amounts")));
+    }
+
+    #[test]
+    fn invalid_allowlist_entry_is_rejected() {
+        assert!("foo.beancount:narration".parse::<Allowlist>().is_err());
+        assert!("foo.beancount:one:narration".parse::<Allowlist>().is_err());
+    }
+}
@@ -0,0 +1,68 @@
+"""Dump the directives found by the beancount Python parser as JSON.
+
+Used by the compat test harness (see `tests/compat/main.rs`).
+Only the parser is invoked: no plugin, booking or validation is applied.
+
+Usage: python3 dump_entries.py FILE
+"""
+
+import json
+import sys
+from decimal import Decimal
+
+from beancount.core import data
+from beancount.parser import parser
+
+
+def amount(value):
+    if value is None or not isinstance(value.number, Decimal):
+        return None
+    return {"number": str(value.number), "currency": value.currency}
+
+
+def record(entry):
+    accounts = []
+    amounts = []
+    payee = None
+    narration = None
+    if isinstance(entry, data.Transaction):
+        payee = entry.payee
+        narration = entry.narration
+        for posting in entry.postings:
+            accounts.append(posting.account)
+            amounts.append(amount(posting.units))
+    elif isinstance(entry, data.Pad):
+        accounts = [entry.account, entry.source_account]
+    elif hasattr(entry, "account"):
+        accounts = [entry.account]
+    if isinstance(entry, (data.Balance, data.Price)):
+        amounts.append(amount(entry.amount))
+    return {
+        "line": entry.meta["lineno"],
+        "date": entry.date.isoformat(),
+        "kind": type(entry).__name__.lower(),
+        "accounts": accounts,
+        "amounts": [a for a in amounts if a is not None],
+        "payee": payee,
+        "narration": narration,
+    }
+
+
+def error(err):
+    source = err.source or {}
+    return {"line": source.get("lineno", 0), "message": str(err.message)}
+
+
+def main(path):
+    entries, errors, _ = parser.parse_file(path)
+    json.dump(
+        {
+            "entries": [record(entry) for entry in entries],
+            "errors": [error(err) for err in errors],
+        },
+        sys.stdout,
+    )
+
+
+if __name__ == "__main__":
+    main(sys.argv[1])
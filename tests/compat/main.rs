@@ -0,0 +1,82 @@
+//! Compatibility harness comparing the output of this crate with the beancount Python parser
+//!
+//! It is disabled unless the environment variable `BEANCOUNT_COMPAT_CORPUS` is set to a directory.
+//! All the `.beancount` files found (recursively) in that directory are parsed by both parsers,
+//! and the directives (dates, kinds, accounts, amounts, payees and narrations) are compared.
+//! The test fails on any divergence that is not listed in the allowlist.
+//!
+//! It requires a Python installation with beancount installed (`pip install beancount`).
+//!
+//! Other environment variables:
+//!
+//! * `BEANCOUNT_COMPAT_PYTHON`: Python interpreter to use (default: `python3`)
+//! * `BEANCOUNT_COMPAT_TOLERANCE`: Maximum difference between two amounts considered equal (default: `0`)
+//! * `BEANCOUNT_COMPAT_ALLOWLIST`: Path of the allowlist file (default: `tests/compat/allowlist.txt`)
+//!
+//! Example: `BEANCOUNT_COMPAT_CORPUS=tests/samples cargo test --features rust_decimal --test compat`
+
+#![allow(missing_docs)]
+#![cfg(feature = "rust_decimal")]
+
+use std::{env, fs, path::Path};
+
+use rust_decimal::Decimal;
+
+use crate::{corpus::files, diff::Allowlist};
+
+mod corpus;
+mod diff;
+mod python;
+mod record;
+
+const DEFAULT_ALLOWLIST: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/compat/allowlist.txt");
+
+#[test]
+fn corpus_is_parsed_like_the_python_parser() {
+    let Some(corpus) = env::var_os("BEANCOUNT_COMPAT_CORPUS") else {
+        println!("BEANCOUNT_COMPAT_CORPUS is not set, skipping the compatibility tests");
+        return;
+    };
+    let corpus = Path::new(&corpus);
+    let python = env::var("BEANCOUNT_COMPAT_PYTHON").unwrap_or_else(|_| "python3".into());
+    let tolerance: Decimal = env::var("BEANCOUNT_COMPAT_TOLERANCE")
+        .map(|t| t.parse().expect("invalid BEANCOUNT_COMPAT_TOLERANCE"))
+        .unwrap_or_default();
+    let allowlist_path =
+        env::var("BEANCOUNT_COMPAT_ALLOWLIST").unwrap_or_else(|_| DEFAULT_ALLOWLIST.into());
+    let allowlist: Allowlist = fs::read_to_string(&allowlist_path)
+        .unwrap_or_else(|err| panic!("cannot read allowlist {allowlist_path}: {err}"))
+        .parse()
+        .expect("invalid allowlist");
+
+    let mut divergences = Vec::new();
+    let files = files(corpus).expect("cannot read corpus directory");
+    assert!(
+        !files.is_empty(),
+        "no beancount file found in {}",
+        corpus.display()
+    );
+    for path in &files {
+        let name = path
+            .strip_prefix(corpus)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        let input = fs::read_to_string(path).expect("cannot read beancount file");
+        let expected = python::parse(&python, path);
+        let actual = record::parse(&input);
+        divergences.extend(
+            diff::diff(&expected, &actual, tolerance)
+                .into_iter()
+                .filter(|divergence| !allowlist.allows(&name, divergence))
+                .map(|divergence| format!("{name}:{divergence}")),
+        );
+    }
+    assert!(
+        divergences.is_empty(),
+        "{} divergence(s) found in {} file(s):\n{}",
+        divergences.len(),
+        files.len(),
+        divergences.join("\n")
+    );
+}
@@ -0,0 +1,27 @@
+use std::{path::Path, process::Command};
+
+use crate::record::{self, Parsed};
+
+const SCRIPT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/compat/dump_entries.py");
+
+/// Parse the file with the beancount Python parser
+///
+/// # Panics
+///
+/// Panics if the interpreter cannot be run or if beancount is not installed
+pub(crate) fn parse(python: &str, path: &Path) -> Parsed {
+    let output = Command::new(python)
+        .arg(SCRIPT)
+        .arg(path)
+        .output()
+        .unwrap_or_else(|err| panic!("cannot run {python}: {err}"));
+    assert!(
+        output.status.success(),
+        "{python} failed on {} (is beancount installed?):\n{}",
+        path.display(),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let json = serde_json::from_slice(&output.stdout)
+        .unwrap_or_else(|err| panic!("invalid output of {SCRIPT}: {err}"));
+    record::from_json(&json).unwrap_or_else(|err| panic!("invalid output of {SCRIPT}: {err}"))
+}
@@ -0,0 +1,186 @@
+use beancount_parser::{Amount, Directive, DirectiveContent};
+use rust_decimal::Decimal;
+use serde_json::Value;
+
+/// The fields of a directive that are compared between the two parsers
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Record {
+    pub(crate) line: u32,
+    pub(crate) date: String,
+    pub(crate) kind: String,
+    pub(crate) accounts: Vec<String>,
+    pub(crate) amounts: Vec<(Decimal, String)>,
+    pub(crate) payee: String,
+    pub(crate) narration: String,
+}
+
+/// Result of parsing a file with one of the parsers
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Parsed {
+    pub(crate) records: Vec<Record>,
+    /// Lines at which syntax errors were reported
+    pub(crate) errors: Vec<u32>,
+}
+
+/// Parse the input with this crate
+pub(crate) fn parse(input: &str) -> Parsed {
+    match beancount_parser::parse::<Decimal>(input) {
+        Ok(file) => Parsed {
+            records: file.directives.iter().filter_map(from_directive).collect(),
+            errors: Vec::new(),
+        },
+        Err(err) => Parsed {
+            records: Vec::new(),
+            errors: vec![err.line_number()],
+        },
+    }
+}
+
+fn from_directive(directive: &Directive<Decimal>) -> Option<Record> {
+    let mut record = Record {
+        line: directive.line_number,
+        date: directive.date.to_string(),
+        ..Record::default()
+    };
+    let (kind, accounts, amounts): (_, Vec<_>, Vec<&Amount<Decimal>>) = match &directive.content {
+        DirectiveContent::Transaction(trx) => {
+            record.payee = trx.payee.clone().unwrap_or_default();
+            record.narration = trx.narration.clone().unwrap_or_default();
+            (
+                "transaction",
+                trx.postings.iter().map(|p| &p.account).collect(),
+                trx.postings
+                    .iter()
+                    .filter_map(|p| p.amount.as_ref())
+                    .collect(),
+            )
+        }
+        DirectiveContent::Price(price) => ("price", vec![], vec![&price.amount]),
+        DirectiveContent::Balance(balance) => {
+            ("balance", vec![&balance.account], vec![&balance.amount])
+        }
+        DirectiveContent::Open(open) => ("open", vec![&open.account], vec![]),
+        DirectiveContent::Close(close) => ("close", vec![&close.account], vec![]),
+        DirectiveContent::Pad(pad) => ("pad", vec![&pad.account, &pad.source_account], vec![]),
+        DirectiveContent::Commodity(_) => ("commodity", vec![], vec![]),
+        DirectiveContent::Event(_) => ("event", vec![], vec![]),
+        DirectiveContent::Note(note) => ("note", vec![&note.account], vec![]),
+        DirectiveContent::Document(document) => ("document", vec![&document.account], vec![]),
+        DirectiveContent::Query(_) => ("query", vec![], vec![]),
+        _ => return None,
+    };
+    record.kind = kind.into();
+    record.accounts = accounts.into_iter().map(ToString::to_string).collect();
+    record.amounts = amounts
+        .into_iter()
+        .map(|a| (a.value, a.currency.to_string()))
+        .collect();
+    Some(record)
+}
+
+/// Read the output of the `dump_entries.py` script
+pub(crate) fn from_json(json: &Value) -> Result<Parsed, String> {
+    let records = array(json, "entries")?
+        .iter()
+        .map(record_from_json)
+        .collect::<Result<_, _>>()?;
+    let errors = array(json, "errors")?
+        .iter()
+        .map(line)
+        .collect::<Result<_, _>>()?;
+    Ok(Parsed { records, errors })
+}
+
+fn record_from_json(json: &Value) -> Result<Record, String> {
+    Ok(Record {
+        line: line(json)?,
+        date: string(json, "date")?,
+        kind: string(json, "kind")?,
+        accounts: array(json, "accounts")?
+            .iter()
+            .map(|account| {
+                account
+                    .as_str()
+                    .map(ToOwned::to_owned)
+                    .ok_or_else(|| format!("invalid account: {account}"))
+            })
+            .collect::<Result<_, _>>()?,
+        amounts: array(json, "amounts")?
+            .iter()
+            .map(|amount| {
+                let number = string(amount, "number")?;
+                let number = number
+                    .parse::<Decimal>()
+                    .or_else(|_| Decimal::from_scientific(&number))
+                    .map_err(|err| format!("invalid number {number}: {err}"))?;
+                Ok((number, string(amount, "currency")?))
+            })
+            .collect::<Result<_, String>>()?,
+        payee: string(json, "payee")?,
+        narration: string(json, "narration")?,
+    })
+}
+
+fn line(json: &Value) -> Result<u32, String> {
+    json["line"]
+        .as_u64()
+        .and_then(|line| line.try_into().ok())
+        .ok_or_else(|| format!("invalid line in {json}"))
+}
+
+fn array<'a>(json: &'a Value, key: &str) -> Result<&'a Vec<Value>, String> {
+    json[key]
+        .as_array()
+        .ok_or_else(|| format!("missing array `{key}` in {json}"))
+}
+
+/// Returns the string value of the key, or an empty string if it is `null`
+fn string(json: &Value, key: &str) -> Result<String, String> {
+    match &json[key] {
+        Value::Null => Ok(String::new()),
+        Value::String(s) => Ok(s.clone()),
+        other => Err(format!("invalid `{key}`: {other}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rust_and_json_records_of_same_input_are_equal() {
+        let input = r#"
+2023-01-01 open Assets:Cash
+2023-01-02 * "Shop" "Groceries"
+  Expenses:Food  10.50 CHF
+  Assets:Cash
+2023-01-03 balance Assets:Cash -10.5 CHF
+"#;
+        let json = serde_json::json!({
+            "entries": [
+                {"line": 2, "date": "2023-01-01", "kind": "open", "accounts": ["Assets:Cash"],
+                 "amounts": [], "payee": null, "narration": null},
+                {"line": 3, "date": "2023-01-02", "kind": "transaction",
+                 "accounts": ["Expenses:Food", "Assets:Cash"],
+                 "amounts": [{"number": "10.50", "currency": "CHF"}],
+                 "payee": "Shop", "narration": "Groceries"},
+                {"line": 6, "date": "2023-01-03", "kind": "balance", "accounts": ["Assets:Cash"],
+                 "amounts": [{"number": "-10.5", "currency": "CHF"}], "payee": null, "narration": null},
+            ],
+            "errors": [],
+        });
+        assert_eq!(parse(input), from_json(&json).unwrap());
+    }
+
+    #[test]
+    fn syntax_error_is_recorded_with_its_line() {
+        let parsed = parse("2023-01-01 open Assets:Cash\n2023-01-02 txn\"oops\"\n");
+        assert_eq!(parsed.errors, vec![2]);
+    }
+
+    #[test]
+    fn invalid_json_is_rejected() {
+        let json = serde_json::json!({"entries": [{"line": "one"}], "errors": []});
+        assert!(from_json(&json).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+#![allow(missing_docs, clippy::float_cmp)]
+
+//! Envelope of the cost syntax (`{...}`) accepted by the parser
+//!
+//! Any change to the set of accepted costs must be reflected in these tables.
+
+use rstest::rstest;
+
+use beancount_parser::{
+    BeancountFile, Code, Cost, CostIssue, Date, DirectiveContent, ParseOptions, Warning,
+    WarningKind,
+};
+
+fn parse(cost: &str) -> Result<BeancountFile<f64>, beancount_parser::Error> {
+    format!("2023-01-01 *\n  Assets:Stock  10 HOOL {cost}\n  Assets:Cash")
+        .parse::<BeancountFile<f64>>()
+}
+
+fn parse_cost(cost: &str) -> Cost<f64> {
+    let file = parse(cost).unwrap_or_else(|err| panic!("`{cost}` should be accepted: {err}"));
+    let DirectiveContent::Transaction(trx) = &file.directives[0].content else {
+        unreachable!("was not a transaction");
+    };
+    trx.postings[0].cost.clone().expect("should have a cost")
+}
+
+#[rstest]
+#[case::empty("{}", None, None)]
+#[case::empty_with_space("{ }", None, None)]
+#[case::amount("{500 USD}", Some((500.0, "USD")), None)]
+#[case::amount_with_spaces("{  500 USD  }", Some((500.0, "USD")), None)]
+#[case::amount_expression("{1000 / 2 USD}", Some((500.0, "USD")), None)]
+#[case::date("{2022-12-31}", None, Some(Date::new(2022, 12, 31)))]
+#[case::amount_and_date(
+    "{500 USD, 2022-12-31}",
+    Some((500.0, "USD")),
+    Some(Date::new(2022, 12, 31))
+)]
+#[case::amount_and_date_without_space(
+    "{500 USD,2022-12-31}",
+    Some((500.0, "USD")),
+    Some(Date::new(2022, 12, 31))
+)]
+#[case::date_and_amount(
+    "{2022-12-31, 500 USD}",
+    Some((500.0, "USD")),
+    Some(Date::new(2022, 12, 31))
+)]
+fn should_accept_cost(
+    #[case] input: &str,
+    #[case] amount: Option<(f64, &str)>,
+    #[case] date: Option<Date>,
+) {
+    let cost = parse_cost(input);
+    let actual_amount = cost
+        .amount
+        .as_ref()
+        .map(|amount| (amount.value, amount.currency.as_str()));
+    assert_eq!(actual_amount, amount);
+    assert_eq!(cost.date, date);
+}
+
+#[rstest]
+#[case::number_without_currency("{500}")]
+#[case::currency_without_number("{USD}")]
+#[case::label(r#"{"lot-1"}"#)]
+#[case::amount_and_label(r#"{500 USD, "lot-1"}"#)]
+#[case::two_labels(r#"{"lot-1", "lot-2"}"#)]
+#[case::merge("{*}")]
+#[case::total("{# 500 USD}")]
+#[case::per_unit_and_total("{500 # 10 USD}")]
+#[case::double_braces("{{5000 USD}}")]
+#[case::two_amounts("{500 USD, 600 USD}")]
+#[case::two_dates("{2022-12-31, 2022-12-30}")]
+#[case::trailing_comma("{500 USD,}")]
+#[case::unclosed("{500 USD")]
+fn should_reject_cost(#[case] input: &str) {
+    assert!(parse(input).is_err(), "`{input}` should be rejected");
+}
+
+#[rstest]
+#[case::same_currency("{500 HOOL}", &[CostIssue::SameCurrency])]
+#[case::other_currency("{500 USD}", &[])]
+#[case::date_only("{2022-12-31}", &[])]
+#[case::empty("{}", &[])]
+fn validate_should_return_issues(#[case] input: &str, #[case] expected: &[CostIssue]) {
+    let cost = parse_cost(input);
+    assert_eq!(cost.validate(&"HOOL".parse().unwrap()), expected);
+}
+
+#[rstest]
+fn should_warn_about_cost_in_units_currency() {
+    let file = parse("{500 HOOL}").unwrap();
+    let [warning]: &[Warning] = &file.warnings[..] else {
+        panic!("expected one warning: {:?}", file.warnings);
+    };
+    assert_eq!(warning.code(), Code::CostInUnitsCurrency);
+    assert_eq!(warning.line_number(), 1);
+    assert_eq!(
+        warning.kind(),
+        &WarningKind::InvalidCost(CostIssue::SameCurrency)
+    );
+    assert_eq!(
+        warning.to_string(),
+        "warning[W003]: Cost in the currency of the units in the transaction at line: 1"
+    );
+}
+
+#[rstest]
+fn cost_warning_can_be_suppressed() {
+    let input = "2023-01-01 *\n  Assets:Stock  10 HOOL {500 HOOL}\n  Assets:Cash";
+    let options = ParseOptions::default().suppress(Code::CostInUnitsCurrency);
+    let file: BeancountFile<f64> = beancount_parser::parse_with_options(input, &options).unwrap();
+    assert_eq!(file.warnings, []);
+}
@@ -0,0 +1,105 @@
+#![allow(missing_docs)]
+
+use rstest::rstest;
+
+use beancount_parser::{BeancountFile, Directive};
+
+#[rstest]
+#[case::transaction(
+    r#"2023-05-20 ! "Shop" "Groceries" #travel #food ^trip
+  id: "42"
+  Expenses:Food  10.5 CHF {2 EUR, 2023-01-01} @ 3 USD
+    note: "bio"
+  ! Expenses:Travel  1 CHF @@ 7 USD
+  Assets:Cash  2 HOOL {}"#,
+    r#"2023-05-20 transaction
+  flag: !
+  payee: "Shop"
+  narration: "Groceries"
+  tags: #food #travel
+  links: ^trip
+  id: "42"
+  posting Expenses:Food
+    amount: 10.5 CHF
+    cost: {2 EUR, 2023-01-01}
+    price: @ 3 USD
+    note: "bio"
+  posting Expenses:Travel
+    flag: !
+    amount: 1 CHF
+    price: @@ 7 USD
+  posting Assets:Cash
+    amount: 2 HOOL
+    cost: {}
+"#
+)]
+#[case::price(
+    "2023-05-20 price HOOL 600 USD",
+    "2023-05-20 price\n  currency: HOOL\n  amount: 600 USD\n"
+)]
+#[case::balance(
+    "2023-05-20 balance Assets:Cash 10 ~ 0.01 CHF",
+    "2023-05-20 balance\n  account: Assets:Cash\n  amount: 10 CHF\n  tolerance: 0.01\n"
+)]
+#[case::open(
+    "2023-05-20 open Assets:Cash USD,CHF \"FIFO\"\n  b: CHF\n  a: 1",
+    "2023-05-20 open\n  account: Assets:Cash\n  currencies: CHF,USD\n  booking_method: FIFO\n  a: 1\n  b: CHF\n"
+)]
+#[case::close(
+    "2023-05-20 close Assets:Cash",
+    "2023-05-20 close\n  account: Assets:Cash\n"
+)]
+#[case::pad(
+    "2023-05-20 pad Assets:Cash Equity:Opening-Balances",
+    "2023-05-20 pad\n  account: Assets:Cash\n  source_account: Equity:Opening-Balances\n"
+)]
+#[case::commodity("2023-05-20 commodity CHF", "2023-05-20 commodity\n  currency: CHF\n")]
+fn directive_debug_tree(#[case] input: &str, #[case] expected: &str) {
+    let directive: Directive<f64> = input.parse().unwrap();
+    assert_eq!(directive.debug_tree(), expected);
+}
+
+#[rstest]
+fn debug_tree_should_be_stable() {
+    let input =
+        "2023-05-20 * #e #d #c #b #a ^e ^d ^c ^b ^a\n  e: 1\n  d: 1\n  c: 1\n  b: 1\n  a: 1";
+    let directive: Directive<f64> = input.parse().unwrap();
+    let expected = directive.debug_tree();
+    for _ in 0..10 {
+        let directive: Directive<f64> = input.parse().unwrap();
+        assert_eq!(directive.debug_tree(), expected);
+    }
+}
+
+#[rstest]
+fn file_debug_tree() {
+    let input = r#"
+option "title" "Demo"
+include "other.beancount"
+
+2023-05-20 open Assets:Cash
+  id: 1
+  id: 2
+
+2023-05-21 * "Coffee"
+  Expenses:Coffee  3 CHF
+  Assets:Cash
+"#;
+    let file: BeancountFile<f64> = input.parse().unwrap();
+    assert_eq!(
+        file.debug_tree(),
+        r#"option title "Demo"
+include "other.beancount"
+2023-05-20 open
+  account: Assets:Cash
+  id: 1
+2023-05-21 transaction
+  flag: *
+  narration: "Coffee"
+  posting Expenses:Coffee
+    amount: 3 CHF
+  posting Assets:Cash
+warning[W001]: Duplicate metadata key `id` at line: 7 (the first value is kept)
+"#
+    );
+}
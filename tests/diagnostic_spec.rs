@@ -0,0 +1,104 @@
+#![allow(missing_docs)]
+#![cfg(feature = "serde")]
+
+use rstest::rstest;
+
+use beancount_parser::{
+    diagnostic::{self, Diagnostic, Severity},
+    lint::{FixedClock, LintSet, StaleIncompleteFlag},
+    parse_report, Code, Date, ParseOptions, ParseReport,
+};
+
+const FIXTURE: &str = include_str!("samples/diagnostics.beancount");
+const SARIF: &str = include_str!("samples/diagnostics.sarif.json");
+
+fn fixture_diagnostics() -> Vec<Diagnostic> {
+    let options = ParseOptions::default().source_name("tests/samples/diagnostics.beancount");
+    let report: ParseReport<f64> = parse_report(FIXTURE, &options);
+    let lints = LintSet::new(FixedClock::new(Date::new(2023, 12, 1)))
+        .stale_incomplete_flag(StaleIncompleteFlag::new(30))
+        .check(&report.file);
+    diagnostic::diagnostics(
+        &report.sources,
+        &report.errors,
+        report.warnings.iter().chain(&lints),
+    )
+}
+
+#[rstest]
+fn sarif_output_should_match_golden_file() {
+    let sarif = serde_json::to_string_pretty(&diagnostic::sarif(&fixture_diagnostics())).unwrap();
+    assert_eq!(sarif, SARIF.trim_end());
+}
+
+#[rstest]
+fn should_convert_errors_warnings_and_lints() {
+    let diagnostics = fixture_diagnostics();
+    let summary: Vec<(Code, Severity, u32)> = diagnostics
+        .iter()
+        .map(|d| (d.code, d.severity, d.line))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (Code::InvalidSyntax, Severity::Error, 11),
+            (Code::DuplicateMetadataKey, Severity::Warning, 3),
+            (Code::StaleIncompleteFlag, Severity::Warning, 7),
+        ]
+    );
+    assert!(diagnostics
+        .iter()
+        .all(|d| d.file.to_str() == Some("tests/samples/diagnostics.beancount")));
+}
+
+#[rstest]
+fn json_lines_should_have_one_flat_object_per_diagnostic() {
+    let lines: Vec<String> = fixture_diagnostics()
+        .iter()
+        .map(|d| serde_json::to_string(d).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines.iter().all(|line| !line.contains('\n')));
+    assert_eq!(
+        lines[0],
+        r#"{"code":"E001","severity":"error","message":"Invalid beancount syntax at line: 11","file":"tests/samples/diagnostics.beancount","line":11,"column":17,"suggestion":null}"#
+    );
+}
+
+#[rstest]
+fn error_hint_should_be_the_suggestion() {
+    let report: ParseReport<f64> = parse_report(
+        "2023-05-20 * \"Coffee\"\n  Expenses:Food  3 CHF 4",
+        &ParseOptions::default(),
+    );
+    let diagnostics = report.diagnostics();
+    assert_eq!(diagnostics.len(), 1);
+    let suggestion = diagnostics[0].suggestion.as_deref().unwrap();
+    assert!(diagnostics[0].message.ends_with(&format!("({suggestion})")));
+}
+
+#[rstest]
+fn warning_turned_into_error_should_keep_its_code() {
+    let report: ParseReport<f64> = parse_report(
+        "2023-05-20 open Assets:Cash\n  id: 1\n  id: 2",
+        &ParseOptions::default().strict_metadata(true),
+    );
+    let diagnostics = report.diagnostics();
+    assert_eq!(diagnostics[0].code, Code::DuplicateMetadataKey);
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+}
+
+#[rstest]
+fn error_relaxed_into_warning_should_keep_its_code() {
+    let report: ParseReport<f64> = parse_report(
+        "2023-05-20 * \"Coffee\"\n  oops\n  Expenses:Food  3 CHF\n  Assets:Cash",
+        &ParseOptions::default().skip_invalid_lines(true),
+    );
+    let diagnostics = report.diagnostics();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, Code::InvalidIndentedLine);
+    assert_eq!(diagnostics[0].severity, Severity::Warning);
+    let sarif = serde_json::to_value(diagnostic::sarif(&diagnostics)).unwrap();
+    assert_eq!(sarif["runs"][0]["results"][0]["ruleId"], "E014");
+    assert_eq!(sarif["runs"][0]["results"][0]["level"], "warning");
+}
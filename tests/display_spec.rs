@@ -0,0 +1,153 @@
+#![allow(missing_docs)]
+#![cfg(feature = "rust_decimal")]
+
+use rstest::rstest;
+use rust_decimal::Decimal;
+
+use beancount_parser::{Amount, AmountDisplay, BeancountFile, DirectiveContent, DisplayContext};
+
+fn amount<D>(file: &BeancountFile<D>, index: usize) -> &Amount<D> {
+    match &file.directives[index].content {
+        DirectiveContent::Balance(balance) => &balance.amount,
+        _ => panic!(
+            "was not a balance: {:?}",
+            file.directives[index].line_number
+        ),
+    }
+}
+
+const LEDGER: &str = r#"
+option "render_commas" "TRUE"
+2023-05-20 * "Sushi"
+  Expenses:Food   1234 JPY
+  Assets:Card     -1234 JPY
+2023-05-21 * "Dinner"
+  Expenses:Food   12.50 EUR
+  Expenses:Food   2.25 EUR
+  Assets:Card
+2023-05-22 balance Assets:Card -98765.4 JPY
+2023-05-22 balance Assets:Card -1234.5 EUR
+"#;
+
+#[rstest]
+fn should_render_negative_amounts_in_accounting_style() {
+    let file: BeancountFile<Decimal> = LEDGER.parse().unwrap();
+    let display = file.amount_display().accounting_negatives(true);
+    assert_eq!(amount(&file, 2).display_with(&display), "(98,765 JPY)");
+    assert_eq!(
+        amount(&file, 2).display_with(&display.accounting_negatives(false)),
+        "-98,765 JPY"
+    );
+}
+
+#[rstest]
+fn should_render_a_symbol_and_a_decimal_comma() {
+    let file: BeancountFile<Decimal> = LEDGER.parse().unwrap();
+    let display = file
+        .amount_display()
+        .symbol("EUR".parse().unwrap(), "€")
+        .decimal_comma(true);
+    assert_eq!(amount(&file, 3).display_with(&display), "-1.234,50 €");
+    assert_eq!(
+        amount(&file, 3).display_with(&display.symbol_before(true)),
+        "-€1.234,50"
+    );
+}
+
+#[rstest]
+fn should_infer_the_precision_of_each_currency() {
+    let file: BeancountFile<Decimal> = LEDGER.parse().unwrap();
+    let context = file.display_context();
+    assert_eq!(context.precision("JPY"), Some(0));
+    assert_eq!(context.precision("EUR"), Some(2));
+    assert_eq!(context.precision("USD"), None);
+}
+
+#[rstest]
+#[case::none(None, "-98765.4 JPY")]
+#[case::rounded_down(Some(0), "-98765 JPY")]
+#[case::padded(Some(3), "-98765.400 JPY")]
+fn should_use_the_precision_of_the_context(
+    #[case] precision: Option<usize>,
+    #[case] expected: &str,
+) {
+    let file: BeancountFile<f64> = LEDGER.parse().unwrap();
+    let context = precision.map_or_else(DisplayContext::default, |precision| {
+        DisplayContext::default().with_precision("JPY".parse().unwrap(), precision)
+    });
+    let display = AmountDisplay::default().context(context);
+    assert_eq!(amount(&file, 2).display_with(&display), expected);
+}
+
+#[rstest]
+#[case::half_up("1.995", 2, "2.00 USD")]
+#[case::carry("999.5", 0, "1,000 USD")]
+#[case::zero("-0.004", 2, "0.00 USD")]
+fn should_round_half_away_from_zero(
+    #[case] value: &str,
+    #[case] precision: usize,
+    #[case] expected: &str,
+) {
+    let amount = Amount {
+        value: value.parse::<Decimal>().unwrap(),
+        currency: "USD".parse().unwrap(),
+    };
+    let display = AmountDisplay::default()
+        .render_commas(true)
+        .context(DisplayContext::default().with_precision("USD".parse().unwrap(), precision));
+    assert_eq!(amount.display_with(&display), expected);
+}
+
+#[rstest]
+#[case::absent("", "-1234.5 EUR")]
+#[case::disabled("option \"render_commas\" \"FALSE\"\n", "-1234.5 EUR")]
+#[case::numeric("option \"render_commas\" \"1\"\n", "-1,234.5 EUR")]
+fn should_separate_the_thousands_if_render_commas_is_true(
+    #[case] option: &str,
+    #[case] expected: &str,
+) {
+    let input = format!("{option}2023-05-22 balance Assets:Card -1234.5 EUR");
+    let file: BeancountFile<f64> = input.parse().unwrap();
+    assert_eq!(
+        amount(&file, 0).display_with(&file.amount_display()),
+        expected
+    );
+}
+
+#[rstest]
+fn should_count_the_trailing_zeros_of_the_literals_whatever_the_number_type() {
+    let input = r#"
+2023-05-20 * "Groceries"
+  Expenses:Food   12.50 USD
+  Expenses:Food   100.00 USD
+  Expenses:Food   3.10 USD
+  Assets:Card
+2023-05-21 balance Assets:Card -116.10 USD
+"#;
+    let file: BeancountFile<f64> = input.parse().unwrap();
+    assert_eq!(file.display_context().precision("USD"), Some(2));
+    assert_eq!(
+        amount(&file, 1).display_with(&file.amount_display()),
+        "-116.10 USD"
+    );
+    let DirectiveContent::Transaction(transaction) = &file.directives[0].content else {
+        unreachable!("was not a transaction")
+    };
+    let hundred = transaction.postings[1].amount.as_ref().unwrap();
+    assert_eq!(hundred.display_with(&file.amount_display()), "100.00 USD");
+}
+
+#[rstest]
+fn should_count_the_costs_and_prices_but_not_the_tolerances() {
+    let input = r#"
+2023-05-20 * "Buy"
+  Assets:Stock   10 STOCK {1.500 USD} @ 1.6000 USD
+  Assets:Cash    -15.00 USD
+2023-05-21 balance Assets:Cash -15.00 ~ 0.001 USD
+2023-05-22 price STOCK 1.62 USD
+"#;
+    let file: BeancountFile<f64> = input.parse().unwrap();
+    let context = file.display_context();
+    assert_eq!(context.precision("STOCK"), Some(0));
+    assert_eq!(context.precision("USD"), Some(2));
+}
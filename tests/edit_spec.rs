@@ -0,0 +1,293 @@
+#![allow(missing_docs)]
+
+use rstest::rstest;
+
+use beancount_parser::{
+    metadata, BeancountFile, Directive, DirectiveContent, DirectiveId, TextEdit,
+};
+
+const LEDGER: &str = r#"option "operating_currency" "CHF"
+
+2023-01-01 open Assets:Bank CHF,EUR "FIFO"
+
+; Groceries
+2023-01-05 * "Shop" "Groceries" #food ^receipt-1
+  receipt: "scan.pdf"
+    Expenses:Food  42.5 CHF
+    Assets:Bank
+      bank-id: 1234
+    ; trailing comment
+
+2023-01-10 ! "Trip"
+    Expenses:Travel  100 EUR {1.1 CHF, 2023-01-01} @ 1.2 CHF
+    Assets:Bank
+
+2023-01-31 balance Assets:Bank -144.5 CHF
+2023-01-31 note Assets:Bank "Checked" #audit
+2023-02-01 custom "budget" Expenses:Food "monthly" 200 CHF TRUE
+"#;
+
+fn ledger() -> BeancountFile<f64> {
+    LEDGER.parse().unwrap()
+}
+
+fn id_of(file: &BeancountFile<f64>, index: usize) -> DirectiveId {
+    file.directive_ids().nth(index).unwrap()
+}
+
+fn patched(source: &str, edits: &[TextEdit]) -> String {
+    let mut text = source.to_owned();
+    for edit in edits.iter().rev() {
+        edit.apply(&mut text);
+    }
+    text
+}
+
+fn set_first_amount(directive: &mut Directive<f64>, value: f64) {
+    let DirectiveContent::Transaction(trx) = &mut directive.content else {
+        panic!("was not a transaction");
+    };
+    trx.postings[0].amount.as_mut().unwrap().value = value;
+}
+
+fn set_narration(directive: &mut Directive<f64>, narration: &str) {
+    let DirectiveContent::Transaction(trx) = &mut directive.content else {
+        panic!("was not a transaction");
+    };
+    trx.narration = Some(narration.into());
+}
+
+fn assert_reparsed(file: &BeancountFile<f64>, source: &str) {
+    let reparsed: BeancountFile<f64> = source.parse().unwrap();
+    assert_eq!(reparsed.directives, file.directives);
+    assert_eq!(posting_lines(&reparsed), posting_lines(file));
+}
+
+fn posting_lines(file: &BeancountFile<f64>) -> Vec<u32> {
+    file.directives
+        .iter()
+        .filter_map(|directive| match &directive.content {
+            DirectiveContent::Transaction(trx) => Some(trx.postings.iter().map(|p| p.line_number)),
+            _ => None,
+        })
+        .flatten()
+        .collect()
+}
+
+#[rstest]
+fn amount_change_should_only_replace_the_lines_of_the_directive() {
+    let mut file = ledger();
+    let id = id_of(&file, 1);
+    let edit = file
+        .edit(LEDGER, id, |directive| set_first_amount(directive, 40.0))
+        .unwrap();
+    let start = LEDGER.find("2023-01-05").unwrap();
+    let end = LEDGER.find("bank-id: 1234").unwrap() + "bank-id: 1234".len();
+    assert_eq!(edit.range, start..end);
+    assert_eq!(
+        edit.replacement,
+        "2023-01-05 * \"Shop\" \"Groceries\" #food ^receipt-1\n  receipt: \"scan.pdf\"\n    Expenses:Food  40 CHF\n    Assets:Bank\n      bank-id: 1234"
+    );
+    let source = patched(LEDGER, &[edit]);
+    assert!(source.contains("; Groceries\n2023-01-05"));
+    assert!(source.contains("bank-id: 1234\n    ; trailing comment\n"));
+    assert_reparsed(&file, &source);
+}
+
+#[rstest]
+fn unchanged_lines_should_be_kept_as_written() {
+    let mut source = String::from(
+        "2023-01-05 * \"Shop\"\n  ; food\n  Expenses:Food      42.5 CHF ; approx\n     ; bank\n  Assets:Bank\n",
+    );
+    let mut file: BeancountFile<f64> = source.parse().unwrap();
+    let id = id_of(&file, 0);
+    let edit = file
+        .edit(&source, id, |directive| set_first_amount(directive, 40.0))
+        .unwrap();
+    edit.apply(&mut source);
+    assert_eq!(
+        source,
+        "2023-01-05 * \"Shop\"\n  ; food\n  Expenses:Food      40 CHF ; approx\n     ; bank\n  Assets:Bank\n"
+    );
+    assert_reparsed(&file, &source);
+}
+
+#[rstest]
+fn added_metadata_should_follow_the_indentation_of_the_other_entries() {
+    let mut file = ledger();
+    let id = id_of(&file, 1);
+    let edit = file
+        .edit(LEDGER, id, |directive| {
+            let DirectiveContent::Transaction(trx) = &mut directive.content else {
+                panic!("was not a transaction");
+            };
+            trx.postings[0].metadata.insert(
+                "category".parse().unwrap(),
+                metadata::Value::String("food".into()),
+            );
+        })
+        .unwrap();
+    assert_eq!(
+        edit.replacement,
+        "2023-01-05 * \"Shop\" \"Groceries\" #food ^receipt-1\n  receipt: \"scan.pdf\"\n    Expenses:Food  42.5 CHF\n      category: \"food\"\n    Assets:Bank\n      bank-id: 1234"
+    );
+    assert_reparsed(&file, &patched(LEDGER, &[edit]));
+}
+
+#[rstest]
+fn removed_and_added_postings_should_only_change_their_lines() {
+    let mut file = ledger();
+    let id = id_of(&file, 1);
+    let edit = file
+        .edit(LEDGER, id, |directive| {
+            let DirectiveContent::Transaction(trx) = &mut directive.content else {
+                panic!("was not a transaction");
+            };
+            let bank = trx.postings.remove(1);
+            let mut cash = bank.clone();
+            cash.account = "Assets:Cash".parse().unwrap();
+            cash.metadata.clear();
+            cash.line_number = 0;
+            trx.postings.push(cash);
+            trx.postings.push(bank);
+        })
+        .unwrap();
+    assert_eq!(
+        edit.replacement,
+        "2023-01-05 * \"Shop\" \"Groceries\" #food ^receipt-1\n  receipt: \"scan.pdf\"\n    Expenses:Food  42.5 CHF\n    Assets:Cash\n    Assets:Bank\n      bank-id: 1234"
+    );
+    let source = patched(LEDGER, &[edit]);
+    assert_reparsed(&file, &source);
+    let edit = file
+        .edit(&source, id, |directive| {
+            let DirectiveContent::Transaction(trx) = &mut directive.content else {
+                panic!("was not a transaction");
+            };
+            trx.postings.pop();
+        })
+        .unwrap();
+    assert_eq!(
+        edit.replacement,
+        "2023-01-05 * \"Shop\" \"Groceries\" #food ^receipt-1\n  receipt: \"scan.pdf\"\n    Expenses:Food  42.5 CHF\n    Assets:Cash"
+    );
+    assert_reparsed(&file, &patched(&source, &[edit]));
+}
+
+#[rstest]
+fn narration_change_should_be_applied_to_the_source() {
+    let mut file = ledger();
+    let id = id_of(&file, 2);
+    let edit = file
+        .edit(LEDGER, id, |directive| set_narration(directive, "Holidays"))
+        .unwrap();
+    assert_eq!(
+        edit.replacement,
+        "2023-01-10 ! \"Holidays\"\n    Expenses:Travel  100 EUR {1.1 CHF, 2023-01-01} @ 1.2 CHF\n    Assets:Bank"
+    );
+    assert_reparsed(&file, &patched(LEDGER, &[edit]));
+}
+
+#[rstest]
+#[case::open(0)]
+#[case::balance(3)]
+#[case::note(4)]
+#[case::custom(5)]
+fn unchanged_directive_should_be_parsed_the_same(#[case] index: usize) {
+    let mut file = ledger();
+    let id = id_of(&file, index);
+    assert_eq!(file.edit(LEDGER, id, |_| ()), None);
+    assert_reparsed(&file, LEDGER);
+}
+
+#[rstest]
+fn pushed_tags_and_metadata_should_not_be_written() {
+    let source = "pushtag #trip\npushmeta foo: \"bar\"\n2023-05-27 open Assets:Cash\n2023-05-28 * \"Coffee\" #food\n  Expenses:Food  3 CHF\n  Assets:Cash\n";
+    let mut file: BeancountFile<f64> = source.parse().unwrap();
+    assert_eq!(file.edit(source, id_of(&file, 0), |_| ()), None);
+    assert_eq!(file.edit(source, id_of(&file, 1), |_| ()), None);
+    let edit = file
+        .edit(source, id_of(&file, 1), |directive| {
+            set_narration(directive, "Tea");
+        })
+        .unwrap();
+    assert_eq!(
+        edit.replacement,
+        "2023-05-28 * \"Tea\" #food\n  Expenses:Food  3 CHF\n  Assets:Cash"
+    );
+    let source = patched(source, &[edit]);
+    assert_reparsed(&file, &source);
+    let edit = file
+        .edit(&source, id_of(&file, 0), |directive| {
+            directive.metadata.insert(
+                "foo".parse().unwrap(),
+                metadata::Value::String("baz".into()),
+            );
+        })
+        .unwrap();
+    assert_eq!(
+        edit.replacement,
+        "2023-05-27 open Assets:Cash\n  foo: \"baz\""
+    );
+    assert_reparsed(&file, &patched(&source, &[edit]));
+}
+
+#[rstest]
+fn batch_edits_should_be_sorted_and_not_overlap() {
+    let mut file = ledger();
+    let ids = [id_of(&file, 2), id_of(&file, 1), id_of(&file, 2)];
+    let mut calls = 0;
+    let edits = file.edit_batch(LEDGER, ids, |directive| {
+        calls += 1;
+        set_first_amount(directive, 1.0);
+    });
+    assert_eq!(calls, 2);
+    assert_eq!(edits.len(), 2);
+    assert!(edits[0].range.end <= edits[1].range.start);
+    assert_reparsed(&file, &patched(LEDGER, &edits));
+}
+
+#[rstest]
+fn line_numbers_should_follow_the_added_lines() {
+    let mut file = ledger();
+    let id = id_of(&file, 2);
+    let balance_line = file.directives[3].line_number;
+    let edit = file
+        .edit(LEDGER, id, |directive| {
+            directive.metadata.insert(
+                "ref".parse().unwrap(),
+                metadata::Value::String("T-1".into()),
+            );
+        })
+        .unwrap();
+    assert_eq!(file.directives[2].line_number, 13);
+    let DirectiveContent::Transaction(trx) = &file.directives[2].content else {
+        panic!("was not a transaction");
+    };
+    assert_eq!(trx.postings[0].line_number, 15);
+    assert_eq!(file.directives[3].line_number, balance_line + 1);
+    let mut source = patched(LEDGER, &[edit]);
+    assert_reparsed(&file, &source);
+    let appended = "2023-02-02 close Assets:Bank\n";
+    file.extend_from_str(appended).unwrap();
+    source.push_str(appended);
+    assert_reparsed(&file, &source);
+}
+
+#[rstest]
+fn should_return_none_for_an_unknown_id() {
+    let mut other = ledger();
+    other.directives.push(other.directives[0].clone());
+    let id = other.directive_ids().last().unwrap();
+    let mut file = ledger();
+    assert_eq!(file.edit(LEDGER, id, |_| unreachable!()), None);
+}
+
+#[rstest]
+fn should_return_none_for_a_directive_without_line_number() {
+    let mut file = ledger();
+    let mut directive = file.directives[0].clone();
+    directive.line_number = 0;
+    file.directives.push(directive);
+    let id = file.directive_ids().last().unwrap();
+    assert_eq!(file.edit(LEDGER, id, |_| unreachable!()), None);
+}
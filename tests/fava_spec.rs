@@ -0,0 +1,72 @@
+#![allow(missing_docs, clippy::float_cmp)]
+
+use rstest::rstest;
+
+use beancount_parser::{
+    fava::{self, Interval},
+    BeancountFile, Code, Date, WarningKind,
+};
+
+#[rstest]
+#[case("daily", Interval::Daily)]
+#[case("weekly", Interval::Weekly)]
+#[case("monthly", Interval::Monthly)]
+#[case("quarterly", Interval::Quarterly)]
+#[case("yearly", Interval::Yearly)]
+fn should_return_budget(#[case] name: &str, #[case] expected: Interval) {
+    let input = format!("2023-01-01 custom \"budget\" Expenses:Food \"{name}\" 12.5 CHF");
+    let file: BeancountFile<f64> = input.parse().unwrap();
+    let (budgets, warnings) = fava::budgets(&file);
+    assert_eq!(warnings, []);
+    let [budget] = &budgets[..] else {
+        panic!("expected one budget: {budgets:?}");
+    };
+    assert_eq!(budget.date, Date::new(2023, 1, 1));
+    assert_eq!(budget.account.as_str(), "Expenses:Food");
+    assert_eq!(budget.interval, expected);
+    assert_eq!(budget.interval.to_string(), name);
+    assert_eq!(budget.amount.value, 12.5);
+    assert_eq!(budget.amount.currency.as_str(), "CHF");
+}
+
+#[rstest]
+#[case::no_value(r#"custom "budget""#, "expected an account")]
+#[case::no_account(r#"custom "budget" "monthly" 10 CHF"#, "expected an account")]
+#[case::no_interval(r#"custom "budget" Expenses:Food 10 CHF"#, "expected an interval")]
+#[case::unknown_interval(
+    r#"custom "budget" Expenses:Food "hourly" 10 CHF"#,
+    "unknown interval `hourly`"
+)]
+#[case::no_amount(r#"custom "budget" Expenses:Food "monthly""#, "expected an amount")]
+#[case::no_currency(r#"custom "budget" Expenses:Food "monthly" 10"#, "expected an amount")]
+fn should_warn_about_malformed_budget(#[case] directive: &str, #[case] expected: &str) {
+    let input = format!("2023-01-01 open Expenses:Food\n2023-01-01 {directive}");
+    let file: BeancountFile<f64> = input.parse().unwrap();
+    let (budgets, warnings) = fava::budgets(&file);
+    assert_eq!(budgets, []);
+    let [warning] = &warnings[..] else {
+        panic!("expected one warning: {warnings:?}");
+    };
+    assert_eq!(warning.line_number(), 2);
+    assert_eq!(warning.code(), Code::MalformedBudget);
+    assert!(matches!(warning.kind(), WarningKind::MalformedBudget(reason) if reason == expected));
+    assert_eq!(
+        warning.to_string(),
+        format!("warning[W002]: Malformed budget at line: 2 ({expected})")
+    );
+}
+
+#[rstest]
+fn should_ignore_other_directives() {
+    let input = r#"
+2023-01-01 open Expenses:Food
+2023-01-01 custom "fava-option" "language" "en"
+2023-01-01 event "budget" "monthly"
+2023-01-02 custom "budget" Expenses:Food "weekly" 100 CHF
+"#;
+    let file: BeancountFile<f64> = input.parse().unwrap();
+    let (budgets, warnings) = fava::budgets(&file);
+    assert_eq!(warnings, []);
+    assert_eq!(budgets.len(), 1);
+    assert_eq!(budgets[0].date, Date::new(2023, 1, 2));
+}
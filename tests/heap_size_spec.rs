@@ -0,0 +1,54 @@
+#![allow(missing_docs, unsafe_code)]
+
+//! This test file contains a single test, because the global allocator counts the allocations
+//! of all the threads, and tests of the same file run in parallel.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use beancount_parser::BeancountFile;
+
+const OFFICIAL: &str = include_str!("samples/official.beancount");
+
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::SeqCst);
+        System.dealloc(ptr, layout);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+#[test]
+fn estimated_heap_size_is_close_to_allocated_size() {
+    let before = ALLOCATED.load(Ordering::SeqCst);
+    let mut file: BeancountFile<f64> = OFFICIAL.parse().unwrap();
+    let allocated = ALLOCATED.load(Ordering::SeqCst) - before;
+    let estimated = file.estimated_heap_size();
+    assert!(
+        estimated > allocated / 2 && estimated < allocated * 2,
+        "estimated: {estimated}, allocated: {allocated}"
+    );
+
+    file.shrink_to_fit();
+    let shrunk_allocated = ALLOCATED.load(Ordering::SeqCst) - before;
+    let shrunk_estimated = file.estimated_heap_size();
+    assert!(shrunk_allocated <= allocated);
+    assert!(shrunk_estimated <= estimated);
+    assert!(
+        shrunk_estimated > shrunk_allocated / 2 && shrunk_estimated < shrunk_allocated * 2,
+        "estimated: {shrunk_estimated}, allocated: {shrunk_allocated}"
+    );
+}
@@ -0,0 +1,158 @@
+#![allow(missing_docs, clippy::float_cmp)]
+#![cfg(feature = "import")]
+
+use std::path::Path;
+
+use rstest::rstest;
+
+use beancount_parser::{
+    import::{self, ImportError, Importer, ImporterRegistry},
+    Account, BeancountFile, Directive, DirectiveContent,
+};
+
+/// Importer of CSV exports with the columns `date,payee,amount,currency`
+struct BankCsv {
+    account: Account,
+    header: &'static str,
+}
+
+impl BankCsv {
+    fn new(account: &str, header: &'static str) -> Self {
+        Self {
+            account: account.parse().unwrap(),
+            header,
+        }
+    }
+
+    fn transaction(&self, line: &str) -> Result<Directive<f64>, ImportError> {
+        let [date, payee, amount, currency] =
+            line.split(',').map(str::trim).collect::<Vec<_>>()[..]
+        else {
+            return Err(ImportError::invalid(format!(
+                "expected 4 columns in `{line}`"
+            )));
+        };
+        let counter_account = if amount.starts_with('-') {
+            "Expenses:Unknown"
+        } else {
+            "Income:Unknown"
+        };
+        let input = format!(
+            "{date} * \"{payee}\" \"\"\n  {}  {amount} {currency}\n  {counter_account}",
+            self.account
+        );
+        Ok(input.parse()?)
+    }
+}
+
+impl Importer<f64> for BankCsv {
+    fn identify(&self, input: &[u8], name: &str) -> bool {
+        Path::new(name)
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"))
+            && input.starts_with(self.header.as_bytes())
+    }
+
+    fn extract(&self, input: &[u8]) -> Result<Vec<Directive<f64>>, ImportError> {
+        let input =
+            std::str::from_utf8(input).map_err(|err| ImportError::invalid(err.to_string()))?;
+        input
+            .lines()
+            .skip(1)
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| self.transaction(line))
+            .collect()
+    }
+
+    fn account(&self) -> Account {
+        self.account.clone()
+    }
+}
+
+fn registry() -> ImporterRegistry<f64> {
+    ImporterRegistry::new()
+        .with(BankCsv::new(
+            "Assets:Checking",
+            "date,payee,amount,currency",
+        ))
+        .with(BankCsv::new(
+            "Liabilities:Card",
+            "Date,Payee,Amount,Currency",
+        ))
+}
+
+const CHECKING: &[u8] =
+    b"date,payee,amount,currency\n2023-05-20,Coffee shop,-3.5,CHF\n2023-05-25,Employer,5000,CHF\n";
+const CARD: &[u8] = b"Date,Payee,Amount,Currency\n2023-05-21,Grocery store,-42,EUR\n";
+
+#[rstest]
+fn registry_should_extract_each_file_with_the_importer_identifying_it() {
+    let extracted = registry().extract([
+        ("checking.csv", CHECKING),
+        ("notes.txt", &b"date,payee,amount,currency"[..]),
+        ("card.csv", CARD),
+    ]);
+    let summary: Vec<(&str, &str, usize)> = extracted
+        .iter()
+        .map(|e| {
+            (
+                e.name.as_str(),
+                e.account.as_str(),
+                e.directives.as_ref().unwrap().len(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("checking.csv", "Assets:Checking", 2),
+            ("card.csv", "Liabilities:Card", 1)
+        ]
+    );
+    let directive = &extracted[1].directives.as_ref().unwrap()[0];
+    let DirectiveContent::Transaction(trx) = &directive.content else {
+        panic!("was not a transaction: {directive:?}");
+    };
+    assert_eq!(trx.payee.as_deref(), Some("Grocery store"));
+    assert_eq!(trx.postings[0].amount.as_ref().unwrap().value, -42.0);
+    assert_eq!(trx.postings[1].account.as_str(), "Expenses:Unknown");
+}
+
+#[rstest]
+#[case::missing_column(
+    b"date,payee,amount,currency\n2023-05-20,Coffee,-3\n",
+    "Invalid input: expected 4 columns in `2023-05-20,Coffee,-3`"
+)]
+#[case::invalid_date(
+    b"date,payee,amount,currency\n2023-13-20,Coffee,-3,CHF\n",
+    "error[E001]: Invalid beancount syntax at line: 1"
+)]
+fn registry_should_report_extraction_errors(#[case] input: &[u8], #[case] expected: &str) {
+    let extracted = registry().extract([("checking.csv", input)]);
+    let error = extracted[0].directives.as_ref().unwrap_err();
+    assert_eq!(error.to_string(), expected);
+}
+
+#[rstest]
+fn merge_should_skip_the_directives_already_in_the_ledger() {
+    let mut ledger: BeancountFile<f64> = r#"
+2023-05-01 open Assets:Checking
+2023-05-20 * "Coffee shop" ""
+  note: "already reviewed"
+  Assets:Checking  -3.5 CHF
+  Expenses:Unknown
+"#
+    .parse()
+    .unwrap();
+    let extracted = registry().extract([("checking.csv", CHECKING)]);
+    let directives = extracted.into_iter().next().unwrap().directives.unwrap();
+    let duplicates = import::merge(&mut ledger, directives);
+    assert_eq!(duplicates.len(), 1);
+    assert_eq!(duplicates[0].date.to_string(), "2023-05-20");
+    let dates: Vec<String> = ledger
+        .directives
+        .iter()
+        .map(|d| d.date.to_string())
+        .collect();
+    assert_eq!(dates, ["2023-05-01", "2023-05-20", "2023-05-25"]);
+}
@@ -0,0 +1,57 @@
+#![allow(missing_docs)]
+
+use rstest::rstest;
+
+use beancount_parser::BeancountFile;
+
+const INPUT: &str = r#"
+2023-01-01 open Assets:Cash
+2023-01-05 * "Invoice" #work ^invoice-1
+  Income:Clients  -100 CHF
+  Assets:Receivable
+pushtag #trip
+2023-01-10 * "Hotel" ^trip-2023
+  Expenses:Hotel  50 CHF
+  Assets:Cash
+poptag #trip
+2023-01-20 * "Payment" #work ^invoice-1 ^bank
+  Assets:Cash  100 CHF
+  Assets:Receivable
+"#;
+
+fn lines(file: &BeancountFile<f64>, ids: Option<&Vec<beancount_parser::DirectiveId>>) -> Vec<u32> {
+    ids.into_iter()
+        .flatten()
+        .map(|&id| file.directive(id).unwrap().line_number)
+        .collect()
+}
+
+#[rstest]
+#[case("invoice-1", &[3, 11])]
+#[case("trip-2023", &[7])]
+#[case("bank", &[11])]
+#[case("unknown", &[])]
+fn links_index_should_list_directives_by_link(#[case] link: &str, #[case] expected: &[u32]) {
+    let file: BeancountFile<f64> = INPUT.parse().unwrap();
+    assert_eq!(lines(&file, file.links_index().get(link)), expected);
+    let group: Vec<u32> = file.linked_group(link).map(|d| d.line_number).collect();
+    assert_eq!(group, expected);
+}
+
+#[rstest]
+#[case("work", &[3, 11])]
+#[case::pushed_tag("trip", &[7])]
+#[case("unknown", &[])]
+fn tags_index_should_list_directives_by_tag(#[case] tag: &str, #[case] expected: &[u32]) {
+    let file: BeancountFile<f64> = INPUT.parse().unwrap();
+    assert_eq!(lines(&file, file.tags_index().get(tag)), expected);
+}
+
+#[rstest]
+fn directive_id_of_another_file_may_not_exist() {
+    let file: BeancountFile<f64> = INPUT.parse().unwrap();
+    let id = file.links_index()["invoice-1"][1];
+    let other: BeancountFile<f64> = "2023-01-01 open Assets:Cash".parse().unwrap();
+    assert_eq!(id.index(), 3);
+    assert!(other.directive(id).is_none());
+}
@@ -0,0 +1,136 @@
+#![allow(missing_docs)]
+
+use rstest::rstest;
+
+use beancount_parser::{
+    lint::{
+        Clock, DirectiveKind, FixedClock, FutureDatedDirective, LintSet, StaleIncompleteFlag,
+        SystemClock,
+    },
+    BeancountFile, Code, Date, WarningKind,
+};
+
+fn check(
+    input: &str,
+    today: Date,
+    lints: impl Fn(LintSet<FixedClock>) -> LintSet<FixedClock>,
+) -> Vec<(u32, WarningKind)> {
+    let file: BeancountFile<f64> = input.parse().unwrap();
+    lints(LintSet::new(FixedClock::new(today)))
+        .check(&file)
+        .into_iter()
+        .map(|warning| (warning.line_number(), warning.kind().clone()))
+        .collect()
+}
+
+#[rstest]
+#[case::same_day(Date::new(2023, 12, 31), 0, None)]
+#[case::next_day_not_allowed(Date::new(2024, 1, 1), 0, Some(1))]
+#[case::next_day_allowed(Date::new(2024, 1, 1), 1, None)]
+#[case::next_year(Date::new(2024, 1, 31), 30, Some(31))]
+#[case::typo_in_year(Date::new(2032, 12, 31), 365, Some(3288))]
+#[case::past(Date::new(2023, 1, 1), 0, None)]
+fn future_dated_directive_should_be_reported(
+    #[case] date: Date,
+    #[case] allow_days_ahead: u16,
+    #[case] expected_days: Option<u32>,
+) {
+    let input = format!("{date} * \"Coffee\"");
+    let warnings = check(&input, Date::new(2023, 12, 31), |lints| {
+        lints.future_dated_directive(FutureDatedDirective::new(allow_days_ahead))
+    });
+    let expected: Vec<(u32, WarningKind)> = expected_days
+        .map(|days| (1, WarningKind::FutureDated(days)))
+        .into_iter()
+        .collect();
+    assert_eq!(warnings, expected);
+}
+
+#[rstest]
+fn future_dated_directive_should_not_report_exempted_kinds() {
+    let input = r#"
+2024-01-01 * "Coffee"
+2024-01-01 balance Assets:Cash 10 CHF
+2024-01-01 open Assets:Bank
+"#;
+    let warnings = check(input, Date::new(2023, 12, 31), |lints| {
+        lints.future_dated_directive(FutureDatedDirective::new(0).exempt(DirectiveKind::Balance))
+    });
+    let lines: Vec<u32> = warnings.iter().map(|(line, _)| *line).collect();
+    assert_eq!(lines, [2, 4]);
+}
+
+#[rstest]
+#[case::same_day(Date::new(2024, 1, 2), 0, None)]
+#[case::within_max_age(Date::new(2023, 12, 31), 2, None)]
+#[case::over_year_boundary(Date::new(2023, 12, 31), 1, Some(2))]
+#[case::no_age_allowed(Date::new(2023, 12, 31), 0, Some(2))]
+#[case::long_ago(Date::new(2022, 1, 2), 365, Some(730))]
+fn stale_incomplete_flag_should_be_reported(
+    #[case] date: Date,
+    #[case] max_age_days: u32,
+    #[case] expected_days: Option<u32>,
+) {
+    let input = format!("{date} ! \"Check this\"\n{date} * \"Fine\"");
+    let warnings = check(&input, Date::new(2024, 1, 2), |lints| {
+        lints.stale_incomplete_flag(StaleIncompleteFlag::new(max_age_days))
+    });
+    let expected: Vec<(u32, WarningKind)> = expected_days
+        .map(|days| (1, WarningKind::StaleIncompleteFlag(days)))
+        .into_iter()
+        .collect();
+    assert_eq!(warnings, expected);
+}
+
+#[rstest]
+fn stale_incomplete_flag_should_count_leap_days() {
+    let input = "2024-02-28 ! \"Check this\"";
+    let warnings = check(input, Date::new(2024, 3, 1), |lints| {
+        lints.stale_incomplete_flag(StaleIncompleteFlag::new(0))
+    });
+    assert_eq!(warnings, [(1, WarningKind::StaleIncompleteFlag(2))]);
+}
+
+#[rstest]
+fn lint_set_should_report_warnings_in_directive_order_with_codes() {
+    let input = r#"
+2032-01-01 * "Typo"
+2023-01-01 ! "Forgotten"
+2032-01-02 ! "Typo and forgotten"
+"#;
+    let file: BeancountFile<f64> = input.parse().unwrap();
+    let warnings = LintSet::new(FixedClock::new(Date::new(2023, 6, 1)))
+        .future_dated_directive(FutureDatedDirective::new(0))
+        .stale_incomplete_flag(StaleIncompleteFlag::new(30))
+        .check(&file);
+    let codes: Vec<(u32, Code)> = warnings
+        .iter()
+        .map(|warning| (warning.line_number(), warning.code()))
+        .collect();
+    assert_eq!(
+        codes,
+        [
+            (2, Code::FutureDatedDirective),
+            (3, Code::StaleIncompleteFlag),
+            (4, Code::FutureDatedDirective),
+        ]
+    );
+    assert_eq!(
+        warnings[1].to_string(),
+        "warning[W005]: Transaction flagged `!` for 151 day(s) at line: 3"
+    );
+}
+
+#[rstest]
+fn empty_lint_set_should_not_report_anything() {
+    let input = "2032-01-01 ! \"Typo\"";
+    assert_eq!(check(input, Date::new(2023, 1, 1), |lints| lints), []);
+}
+
+#[rstest]
+fn system_clock_should_return_a_plausible_date() {
+    let today = SystemClock.today();
+    assert!(today >= Date::new(2023, 1, 1), "{today}");
+    assert!((1..=12).contains(&today.month), "{today}");
+    assert!((1..=31).contains(&today.day), "{today}");
+}
@@ -0,0 +1,227 @@
+#![allow(missing_docs)]
+
+use rstest::rstest;
+
+use beancount_parser::{
+    metadata, Account, BeancountFile, DirectiveContent, MergeError, MergePolicy, Tag, Transaction,
+};
+
+fn transactions(input: &str) -> (BeancountFile<f64>, Vec<Transaction<f64>>) {
+    let file: BeancountFile<f64> = input.parse().unwrap();
+    let transactions = file
+        .directives
+        .iter()
+        .map(|d| match &d.content {
+            DirectiveContent::Transaction(trx) => trx.clone(),
+            _ => panic!("was not a transaction: {d:?}"),
+        })
+        .collect();
+    (file, transactions)
+}
+
+/// Returns the file with its single transaction replaced by the given ones
+fn replaced(
+    mut file: BeancountFile<f64>,
+    transactions: Vec<Transaction<f64>>,
+) -> BeancountFile<f64> {
+    let directive = file.directives.remove(0);
+    for trx in transactions {
+        let mut directive = directive.clone();
+        directive.content = DirectiveContent::Transaction(trx);
+        file.directives.push(directive);
+    }
+    file
+}
+
+fn postings(trx: &Transaction<f64>) -> Vec<(&str, Option<f64>, String)> {
+    trx.postings
+        .iter()
+        .map(|p| {
+            (
+                p.account.as_str(),
+                p.amount.as_ref().map(|a| a.value),
+                p.amount
+                    .as_ref()
+                    .map(|a| a.currency.to_string())
+                    .unwrap_or_default(),
+            )
+        })
+        .collect()
+}
+
+fn transfer() -> Account {
+    "Equity:Transfer".parse().unwrap()
+}
+
+const TRIP: &str = r#"
+2023-05-20 * "Travel agency" "Trip" #trip
+  Expenses:Food      30 CHF
+  ! Expenses:Hotel  100 EUR
+    receipt: "hotel.pdf"
+  Assets:Cash       -30 CHF
+  Assets:Card      -100 EUR
+"#;
+
+#[rstest]
+fn split_should_rebalance_both_sides_of_a_multicurrency_transaction() {
+    let (file, trx) = transactions(TRIP);
+    let (first, second) = trx[0].clone().split(&[1, 2], &transfer());
+    assert_eq!(
+        postings(&first),
+        [
+            ("Expenses:Food", Some(30.0), "CHF".into()),
+            ("Assets:Card", Some(-100.0), "EUR".into()),
+            ("Equity:Transfer", Some(-30.0), "CHF".into()),
+            ("Equity:Transfer", Some(100.0), "EUR".into()),
+        ]
+    );
+    assert_eq!(
+        postings(&second),
+        [
+            ("Expenses:Hotel", Some(100.0), "EUR".into()),
+            ("Assets:Cash", Some(-30.0), "CHF".into()),
+            ("Equity:Transfer", Some(30.0), "CHF".into()),
+            ("Equity:Transfer", Some(-100.0), "EUR".into()),
+        ]
+    );
+    for side in [&first, &second] {
+        assert_eq!(side.payee.as_deref(), Some("Travel agency"));
+        assert_eq!(side.narration.as_deref(), Some("Trip"));
+        assert!(side.tags.contains("trip"));
+    }
+    let hotel = &second.postings[0];
+    assert_eq!(hotel.flag, Some('!'));
+    assert_eq!(
+        hotel.metadata.get("receipt"),
+        Some(&metadata::Value::String("hotel.pdf".into()))
+    );
+
+    let balances = file.balances(None);
+    let split = replaced(file, vec![first, second]).balances(None);
+    assert!(split[&transfer()].is_empty(), "{split:?}");
+    assert_eq!(split.len(), balances.len() + 1);
+    for (account, inventory) in &balances {
+        assert_eq!(&split[account], inventory, "{account}");
+    }
+}
+
+#[rstest]
+fn split_should_not_add_transfers_to_balanced_sides() {
+    let (_, trx) = transactions(TRIP);
+    let (first, second) = trx[0].clone().split(&[1, 3], &transfer());
+    assert_eq!(first.postings.len(), 2);
+    assert_eq!(second.postings.len(), 2);
+}
+
+#[rstest]
+#[case::missing_amount_kept(&[1])]
+#[case::missing_amount_moved(&[0, 2])]
+fn split_should_keep_the_interpolated_amount(#[case] moved: &[usize]) {
+    let input = r#"
+2023-05-20 * "Groceries"
+  Expenses:Food    30 CHF
+  Expenses:Drinks  10 CHF
+  Assets:Cash
+"#;
+    let (file, trx) = transactions(input);
+    let (first, second) = trx[0].clone().split(moved, &transfer());
+    let balances = file.balances(None);
+    let split = replaced(file, vec![first, second]).balances(None);
+    assert_eq!(
+        split[&"Assets:Cash".parse::<Account>().unwrap()].get("CHF"),
+        Some(&-40.0)
+    );
+    assert!(split[&transfer()].is_empty(), "{split:?}");
+    for (account, inventory) in &balances {
+        assert_eq!(&split[account], inventory, "{account}");
+    }
+}
+
+#[rstest]
+fn merge_should_union_the_tags_and_links_and_concatenate_the_postings() {
+    let input = r#"
+2023-05-20 * "Shop" "Food" #trip #food ^receipt-1
+  Expenses:Food  30 CHF
+  Assets:Cash
+2023-05-20 ! "Drinks" #trip #bar ^receipt-1 ^receipt-2
+  Expenses:Drinks  10 CHF
+    id: 2
+  Assets:Cash
+"#;
+    let (_, trx) = transactions(input);
+    let [food, drinks] = [trx[0].clone(), trx[1].clone()];
+    let merged = food.merge(drinks, &MergePolicy::default()).unwrap();
+    assert_eq!(merged.flag, Some('!'));
+    assert_eq!(merged.payee.as_deref(), Some("Shop"));
+    assert_eq!(merged.narration.as_deref(), Some("Food / Drinks"));
+    let mut tags: Vec<&str> = merged.tags.iter().map(Tag::as_str).collect();
+    tags.sort_unstable();
+    assert_eq!(tags, ["bar", "food", "trip"]);
+    assert_eq!(merged.links.len(), 2);
+    let accounts: Vec<&str> = merged.postings.iter().map(|p| p.account.as_str()).collect();
+    assert_eq!(
+        accounts,
+        [
+            "Expenses:Food",
+            "Assets:Cash",
+            "Expenses:Drinks",
+            "Assets:Cash"
+        ]
+    );
+    assert_eq!(
+        merged.postings[2].metadata.get("id"),
+        Some(&metadata::Value::Number(2.0))
+    );
+}
+
+#[rstest]
+#[case::default(MergePolicy::default(), "Food / Coffee")]
+#[case::separator(MergePolicy::default().narration_separator(", "), "Food, Coffee")]
+fn merge_should_join_the_narrations(#[case] policy: MergePolicy, #[case] expected: &str) {
+    let (_, trx) = transactions("2023-05-20 * \"Food\"\n2023-05-20 * \"Coffee\"");
+    let merged = trx[0].clone().merge(trx[1].clone(), &policy).unwrap();
+    assert_eq!(merged.narration.as_deref(), Some(expected));
+}
+
+#[rstest]
+#[case::equal("2023-05-20 * \"Food\"\n2023-05-20 * \"Food\"")]
+#[case::only_first("2023-05-20 * \"Food\"\n2023-05-20 *")]
+#[case::only_second("2023-05-20 *\n2023-05-20 * \"Food\"")]
+fn merge_should_not_repeat_the_narration(#[case] input: &str) {
+    let (_, trx) = transactions(input);
+    let merged = trx[0]
+        .clone()
+        .merge(trx[1].clone(), &MergePolicy::default())
+        .unwrap();
+    assert_eq!(merged.narration.as_deref(), Some("Food"));
+}
+
+#[rstest]
+fn merge_should_fail_on_conflicting_payees_unless_forced() {
+    let input = "2023-05-20 * \"Shop\" \"Food\"\n2023-05-20 * \"Bar\" \"Drinks\"";
+    let (_, trx) = transactions(input);
+    let error = trx[0]
+        .clone()
+        .merge(trx[1].clone(), &MergePolicy::default())
+        .unwrap_err();
+    assert_eq!(
+        error,
+        MergeError::ConflictingPayees("Shop".into(), "Bar".into())
+    );
+    let merged = trx[0]
+        .clone()
+        .merge(trx[1].clone(), &MergePolicy::default().force(true))
+        .unwrap();
+    assert_eq!(merged.payee.as_deref(), Some("Shop"));
+}
+
+#[rstest]
+fn merge_should_keep_the_payee_of_either_transaction() {
+    let input = "2023-05-20 * \"Food\"\n2023-05-20 * \"Bar\" \"Drinks\"";
+    let (_, trx) = transactions(input);
+    let merged = trx[0]
+        .clone()
+        .merge(trx[1].clone(), &MergePolicy::default())
+        .unwrap();
+    assert_eq!(merged.payee.as_deref(), Some("Bar"));
+}
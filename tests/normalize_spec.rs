@@ -0,0 +1,136 @@
+#![allow(missing_docs)]
+
+use rstest::rstest;
+
+use beancount_parser::{BeancountFile, Directive, DirectiveContent, NormalizeOptions};
+
+const OFFICIAL: &str = include_str!("samples/official.beancount");
+
+fn all() -> NormalizeOptions {
+    NormalizeOptions::default()
+        .drop_redundant_cost_dates(true)
+        .remove_duplicates(true)
+}
+
+fn normalized(input: &str, options: &NormalizeOptions) -> BeancountFile<f64> {
+    let mut file: BeancountFile<f64> = input.parse().unwrap();
+    file.normalize(options);
+    file
+}
+
+fn debug_trees(file: &BeancountFile<f64>) -> String {
+    file.directives.iter().map(Directive::debug_tree).collect()
+}
+
+fn narrations(file: &BeancountFile<f64>) -> Vec<(Option<&str>, Option<&str>)> {
+    file.directives
+        .iter()
+        .filter_map(|d| match &d.content {
+            DirectiveContent::Transaction(trx) => {
+                Some((trx.payee.as_deref(), trx.narration.as_deref()))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[rstest]
+#[case::default(NormalizeOptions::default())]
+#[case::all(all())]
+fn normalize_should_be_idempotent(#[case] options: NormalizeOptions) {
+    let mut file = normalized(OFFICIAL, &options);
+    let once = debug_trees(&file);
+    let directives = file.directives.clone();
+    file.normalize(&options);
+    assert_eq!(file.directives, directives);
+    assert_eq!(debug_trees(&file), once);
+}
+
+#[rstest]
+fn normalize_should_sort_the_directives_in_the_order_they_take_effect() {
+    let input = r#"
+2023-05-21 * "Second"
+2023-05-20 close Assets:Cash
+2023-05-20 * "First"
+2023-05-20 balance Assets:Cash 0 CHF
+2023-05-20 open Assets:Cash
+"#;
+    let file = normalized(input, &NormalizeOptions::default());
+    let lines: Vec<u32> = file.directives.iter().map(|d| d.line_number).collect();
+    assert_eq!(lines, [6, 5, 4, 3, 2]);
+    let unsorted = normalized(input, &NormalizeOptions::default().sort_directives(false));
+    let lines: Vec<u32> = unsorted.directives.iter().map(|d| d.line_number).collect();
+    assert_eq!(lines, [2, 3, 4, 5, 6]);
+}
+
+#[rstest]
+fn normalize_should_trim_payees_and_narrations() {
+    let input = "2023-05-20 * \" Shop \" \"  Coffee  and  cake \"\n2023-05-21 * \"\tTea\"";
+    let file = normalized(input, &NormalizeOptions::default());
+    assert_eq!(
+        narrations(&file),
+        [
+            (Some("Shop"), Some("Coffee  and  cake")),
+            (None, Some("Tea"))
+        ]
+    );
+    let untrimmed = normalized(input, &NormalizeOptions::default().trim_text(false));
+    assert_eq!(narrations(&untrimmed)[1], (None, Some("\tTea")));
+}
+
+#[rstest]
+#[case::same_date("2023-05-20", None)]
+#[case::other_date("2023-05-01", Some("2023-05-01"))]
+fn drop_redundant_cost_dates_should_only_drop_the_date_of_the_transaction(
+    #[case] cost_date: &str,
+    #[case] expected: Option<&str>,
+) {
+    let input = format!(
+        "2023-05-20 * \"Buy\"\n  Assets:Stock  1 HOOL {{10 USD, {cost_date}}}\n  Assets:Cash"
+    );
+    let date_of_cost = |file: &BeancountFile<f64>| {
+        let DirectiveContent::Transaction(trx) = &file.directives[0].content else {
+            panic!("was not a transaction: {:?}", file.directives[0]);
+        };
+        trx.postings[0]
+            .cost
+            .as_ref()
+            .and_then(|cost| cost.date)
+            .map(|date| date.to_string())
+    };
+    let file = normalized(&input, &all());
+    assert_eq!(date_of_cost(&file).as_deref(), expected);
+    let kept = normalized(&input, &NormalizeOptions::default());
+    assert_eq!(date_of_cost(&kept).as_deref(), Some(cost_date));
+}
+
+#[rstest]
+fn remove_duplicates_should_keep_the_first_of_exact_duplicates() {
+    let input = r#"
+2023-05-20 * "Coffee"
+  Expenses:Food  3 CHF
+  Assets:Cash
+2023-05-20 open Assets:Cash
+2023-05-20 * "Coffee"
+  Expenses:Food  3 CHF
+  Assets:Cash
+2023-05-20 * "Coffee"
+  id: 2
+  Expenses:Food  3 CHF
+  Assets:Cash
+2023-05-21 * "Coffee"
+  Expenses:Food  3 CHF
+  Assets:Cash
+2023-05-20 * "Coffee"
+  Expenses:Food  4 CHF
+  Assets:Cash
+"#;
+    let options = NormalizeOptions::default()
+        .sort_directives(false)
+        .remove_duplicates(true);
+    let file = normalized(input, &options);
+    let lines: Vec<u32> = file.directives.iter().map(|d| d.line_number).collect();
+    assert_eq!(lines, [2, 5, 9, 13, 16]);
+    let kept = normalized(input, &NormalizeOptions::default().sort_directives(false));
+    assert_eq!(kept.directives.len(), 6);
+}
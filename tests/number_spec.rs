@@ -0,0 +1,312 @@
+#![allow(missing_docs, clippy::float_cmp)]
+#![cfg(feature = "rust_decimal")]
+
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use rstest::rstest;
+
+use rust_decimal::Decimal;
+
+use beancount_parser::{
+    parse_with_options, BeancountFile, Code, DirectiveContent, FromLiteral, Literal, Number,
+    Operation, ParseOptions,
+};
+
+/// Fixed-point number with 4 implied decimals, which does not implement `FromStr`
+#[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd)]
+struct Fixed(i128);
+
+const SCALE: i128 = 10_000;
+
+impl FromLiteral for Fixed {
+    type Err = String;
+    fn from_literal(literal: Literal<'_>) -> Result<Self, Self::Err> {
+        if literal.fraction.len() > 4 {
+            return Err(format!("too many decimals: {literal}"));
+        }
+        let digits = format!("{}{:0<4}", literal.integer, literal.fraction);
+        let value: i128 = digits.parse().map_err(|_| literal.to_string())?;
+        Ok(Fixed(if literal.negative { -value } else { value }))
+    }
+}
+
+impl Number for Fixed {
+    fn evaluate(operation: Operation<Self>) -> Option<Self> {
+        Some(operation.evaluate())
+    }
+}
+
+impl Add for Fixed {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Fixed(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Fixed {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Fixed(self.0 - rhs.0)
+    }
+}
+
+impl Mul for Fixed {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        Fixed(self.0 * rhs.0 / SCALE)
+    }
+}
+
+impl Div for Fixed {
+    type Output = Self;
+    fn div(self, rhs: Self) -> Self {
+        Fixed(self.0 * SCALE / rhs.0)
+    }
+}
+
+impl Neg for Fixed {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Fixed(-self.0)
+    }
+}
+
+fn parse_price(input: &str) -> Result<Fixed, beancount_parser::Error> {
+    let file: BeancountFile<Fixed> = format!("2023-05-27 price CHF {input} PLN").parse()?;
+    let DirectiveContent::Price(price) = &file.directives[0].content else {
+        unreachable!("was not a price: {file:?}")
+    };
+    Ok(price.amount.value)
+}
+
+#[rstest]
+#[case::integer("10", 100_000)]
+#[case::decimal("10.25", 102_500)]
+#[case::negative("-10.25", -102_500)]
+#[case::negative_with_space("- 10.25", -102_500)]
+#[case::thousands_separators("1,000,000.5", 10_000_005_000)]
+#[case::no_integer_part(".5", 5_000)]
+#[case::no_fraction_part("5.", 50_000)]
+#[case::all_decimals("0.0001", 1)]
+#[case::expression("10 / 4", 25_000)]
+#[case::negated_group("-(1 + 2)", -30_000)]
+fn should_parse_literal_parts(#[case] input: &str, #[case] expected: i128) {
+    assert_eq!(parse_price(input).unwrap(), Fixed(expected));
+}
+
+#[rstest]
+#[case::too_many_decimals("0.00001")]
+#[case::no_digit(".")]
+fn should_report_error_of_from_literal(#[case] input: &str) {
+    assert!(parse_price(input).is_err());
+}
+
+/// Number without arithmetic, which does not support expressions
+#[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd)]
+struct Cents(i64);
+
+impl FromLiteral for Cents {
+    type Err = String;
+    fn from_literal(literal: Literal<'_>) -> Result<Self, Self::Err> {
+        let value: i64 = format!("{}{:0<2}", literal.integer, literal.fraction)
+            .parse()
+            .map_err(|_| literal.to_string())?;
+        Ok(Cents(if literal.negative { -value } else { value }))
+    }
+}
+
+impl Number for Cents {}
+
+#[rstest]
+fn number_without_arithmetic_should_parse_literals() {
+    let file: BeancountFile<Cents> = "2023-05-27 price CHF -1,234.5 PLN".parse().unwrap();
+    let DirectiveContent::Price(price) = &file.directives[0].content else {
+        unreachable!("was not a price: {file:?}")
+    };
+    assert_eq!(price.amount.value, Cents(-123_450));
+}
+
+#[rstest]
+#[case::sum("1 + 2")]
+#[case::product("10 / 4")]
+#[case::negated_group("-(1)")]
+fn number_without_arithmetic_should_reject_expressions(#[case] input: &str) {
+    let error = format!("2023-05-27 price CHF {input} PLN")
+        .parse::<BeancountFile<Cents>>()
+        .unwrap_err();
+    assert_eq!(error.code(), Code::InvalidSyntax);
+    assert_eq!(error.column(), Some(22));
+    assert!(error
+        .hint()
+        .is_some_and(|hint| hint.contains("expressions")));
+}
+
+#[rstest]
+#[case::integer("42")]
+#[case::tenth("0.1")]
+#[case::negative("-0.3")]
+#[case::fifteen_digits("123456789.012345")]
+#[case::many_decimals("0.0000000000000000000123")]
+#[case::long("123456789012345678901234567890.5")]
+fn float_literals_should_equal_the_parsed_text(#[case] input: &str) {
+    let options = ParseOptions::default().round_excess_precision(true);
+    assert_eq!(
+        parse_price_with::<f64>(input, &options).unwrap(),
+        input.parse::<f64>().unwrap()
+    );
+    assert_eq!(
+        parse_price_with::<f32>(input, &options).unwrap(),
+        input.parse::<f32>().unwrap()
+    );
+}
+
+#[rstest]
+#[case::integer("42", 42)]
+#[case::zero_decimals("-42.00", -42)]
+fn integer_literals_should_be_parsed(#[case] input: &str, #[case] expected: i64) {
+    let options = ParseOptions::default();
+    assert_eq!(parse_price_with::<i64>(input, &options).unwrap(), expected);
+    assert!(parse_price_with::<i64>("4.2", &options).is_err());
+}
+
+#[rstest]
+fn balances_of_fixed_point_type_are_exact() {
+    let input = r#"
+2023-01-01 * "Coffee"
+  Expenses:Coffee  3.1 CHF
+  Assets:Cash
+2023-01-02 * "Coffee"
+  Expenses:Coffee  0.2 CHF
+  Assets:Cash
+"#;
+    let file: BeancountFile<Fixed> = input.parse().unwrap();
+    let balances = file.balances(None);
+    assert_eq!(balances["Expenses:Coffee"].get("CHF"), Some(&Fixed(33_000)));
+    assert_eq!(balances["Assets:Cash"].get("CHF"), Some(&Fixed(-33_000)));
+}
+
+const THIRTY_DECIMALS: &str = "0.123456789012345678901234567890";
+
+fn parse_price_with<D: Number>(
+    input: &str,
+    options: &ParseOptions,
+) -> Result<D, beancount_parser::Error> {
+    let file: BeancountFile<D> =
+        parse_with_options(&format!("2023-05-27 price BTC {input} USD"), options)?;
+    let DirectiveContent::Price(price) = &file.directives[0].content else {
+        unreachable!("was not a price: {file:?}")
+    };
+    Ok(price.amount.value.clone())
+}
+
+#[rstest]
+#[case::integer_exponent("1e3", "1000")]
+#[case::negative_exponent("2.5E-7", "0.00000025")]
+#[case::positive_exponent("1.25E+2", "125")]
+#[case::fraction_left("1.2345e2", "123.45")]
+fn scientific_notation_should_be_rejected_by_default(#[case] input: &str, #[case] plain: &str) {
+    for error in [
+        parse_price_with::<f64>(input, &ParseOptions::default()).unwrap_err(),
+        parse_price_with::<Decimal>(input, &ParseOptions::default()).unwrap_err(),
+    ] {
+        assert_eq!(error.code(), Code::ScientificNotation);
+        assert_eq!(error.column(), Some(22));
+        assert_eq!(error.hint(), Some(format!("write `{plain}`").as_str()));
+    }
+}
+
+#[rstest]
+#[case::integer_exponent("1e3", 1000.0, Decimal::new(1000, 0))]
+#[case::negative_exponent("2.5E-7", 2.5e-7, Decimal::new(25, 8))]
+#[case::negative_number("-2.5E-7", -2.5e-7, Decimal::new(-25, 8))]
+#[case::thousands_separator("1,000.5e1", 10_005.0, Decimal::new(10_005, 0))]
+fn scientific_notation_should_be_converted_to_plain_decimal_when_allowed(
+    #[case] input: &str,
+    #[case] expected_f64: f64,
+    #[case] expected_decimal: Decimal,
+) {
+    let options = ParseOptions::default().allow_scientific_notation(true);
+    assert_eq!(
+        parse_price_with::<f64>(input, &options).unwrap(),
+        expected_f64
+    );
+    assert_eq!(
+        parse_price_with::<Decimal>(input, &options).unwrap(),
+        expected_decimal
+    );
+}
+
+#[rstest]
+fn excess_precision_should_be_rejected_by_default() {
+    for error in [
+        parse_price_with::<f64>(THIRTY_DECIMALS, &ParseOptions::default()).unwrap_err(),
+        parse_price_with::<Decimal>(THIRTY_DECIMALS, &ParseOptions::default()).unwrap_err(),
+    ] {
+        assert_eq!(error.code(), Code::ExcessPrecision);
+        assert_eq!(error.column(), Some(22));
+    }
+}
+
+#[rstest]
+fn excess_precision_should_be_rounded_when_requested() {
+    let options = ParseOptions::default().round_excess_precision(true);
+    assert_eq!(
+        parse_price_with::<f64>(THIRTY_DECIMALS, &options).unwrap(),
+        0.123_456_789_012_345_68
+    );
+    assert_eq!(
+        parse_price_with::<Decimal>(THIRTY_DECIMALS, &options).unwrap(),
+        "0.1234567890123456789012345679".parse().unwrap()
+    );
+}
+
+#[rstest]
+fn literals_of_any_length_should_be_parsed() {
+    let input = format!("-0.{}", "1".repeat(100));
+    let options = ParseOptions::default().round_excess_precision(true);
+    assert_eq!(
+        parse_price_with::<f64>(&input, &options).unwrap(),
+        -0.111_111_111_111_111_1
+    );
+    let error = parse_price_with::<f64>(&input, &ParseOptions::default()).unwrap_err();
+    assert_eq!(error.code(), Code::ExcessPrecision);
+}
+
+#[rstest]
+#[case::eighteen_decimals("0.123456789012345678")]
+#[case::large_integer("123456789012345678")]
+fn precision_limit_should_depend_on_the_number_type(#[case] input: &str) {
+    let f64_error = parse_price_with::<f64>(input, &ParseOptions::default()).unwrap_err();
+    assert_eq!(f64_error.code(), Code::ExcessPrecision);
+    assert_eq!(
+        parse_price_with::<Decimal>(input, &ParseOptions::default()).unwrap(),
+        input.parse().unwrap()
+    );
+}
+
+#[rstest]
+#[case::trailing_zeros("1.50000000000000000000", 1.5)]
+#[case::round_number("100000000000000000000000", 1e23)]
+#[case::zero("0.000000000000000000000000000000", 0.0)]
+fn precision_should_ignore_trailing_zeros(#[case] input: &str, #[case] expected: f64) {
+    assert_eq!(
+        parse_price_with::<f64>(input, &ParseOptions::default()).unwrap(),
+        expected
+    );
+    assert!(parse_price_with::<Decimal>(input, &ParseOptions::default()).is_ok());
+}
+
+#[rstest]
+fn scientific_notation_and_excess_precision_should_combine() {
+    let input = "1.23456789012345678901234567890E5";
+    let allowed = ParseOptions::default().allow_scientific_notation(true);
+    assert_eq!(
+        parse_price_with::<f64>(input, &allowed).unwrap_err().code(),
+        Code::ExcessPrecision
+    );
+    let rounded = allowed.round_excess_precision(true);
+    assert_eq!(
+        parse_price_with::<f64>(input, &rounded).unwrap(),
+        123_456.789_012_345_68
+    );
+}
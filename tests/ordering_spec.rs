@@ -0,0 +1,138 @@
+#![allow(missing_docs)]
+
+use rstest::rstest;
+
+use beancount_parser::{
+    parse_report, Account, BeancountFile, Link, ParseOptions, ResolveOptions, Tag,
+};
+
+const FIXTURE: &str = r#"
+2023-01-01 open Assets:Bank:Checking
+2023-01-01 open Assets:Bank:Savings
+2023-01-01 open Assets:Cash
+2023-01-01 open Expenses:Food:Groceries
+2023-01-01 open Expenses:Food:Restaurant
+2023-01-01 open Expenses:Travel
+2023-01-01 open Income:Salary
+
+2023-01-05 * "Salary" #work ^pay-jan
+  Assets:Bank:Checking  3000 CHF
+  Income:Salary
+
+2023-01-06 * "Savings" ^pay-jan ^savings
+  Assets:Bank:Savings  1000 CHF
+  Assets:Bank:Checking
+
+2023-01-07 * "Groceries" #food
+  Expenses:Food:Groceries  80 CHF
+  Assets:Cash
+
+2023-01-08 * "Dinner in Paris" #food #travel ^trip
+  Expenses:Food:Restaurant  60 EUR
+  Assets:Bank:Checking  -65 CHF @@ 60 EUR
+
+2023-01-09 * "Train" #travel ^trip
+  Expenses:Travel  40 EUR
+  Expenses:Travel  12 USD
+  Assets:Cash  -40 EUR
+  Assets:Cash  -12 USD
+
+2023-01-10 balance Assets:Bank:Savings 1000 CHF
+"#;
+
+/// Returns the blocks of the fixture (separated by blank lines) in a different order
+fn shuffle(input: &str, permutation: &[usize]) -> String {
+    let blocks: Vec<&str> = input.trim().split("\n\n").collect();
+    assert_eq!(blocks.len(), permutation.len());
+    permutation
+        .iter()
+        .map(|&index| blocks[index])
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Serialize every aggregate of the file, so that two runs can be compared
+fn aggregates(input: &str) -> Vec<String> {
+    let file: BeancountFile<f64> = input.parse().unwrap();
+    // The directives of an index are in the order of the file, which is shuffled
+    let directive_dates = |ids: &Vec<beancount_parser::DirectiveId>| -> Vec<String> {
+        let mut dates: Vec<String> = ids
+            .iter()
+            .map(|id| file.directive(*id).unwrap().date.to_string())
+            .collect();
+        dates.sort();
+        dates
+    };
+    let balances = file.balances(None);
+    let rollup = file.account_tree().rollup(&balances);
+    let resolved = file.resolve(&ResolveOptions::default());
+    let report = parse_report::<f64>(input, &ParseOptions::default());
+    vec![
+        format!("{balances:?}"),
+        format!("{rollup:?}"),
+        format!(
+            "{:?}",
+            file.links_index()
+                .iter()
+                .map(|(link, ids)| (link, directive_dates(ids)))
+                .collect::<Vec<_>>()
+        ),
+        format!(
+            "{:?}",
+            file.tags_index()
+                .iter()
+                .map(|(tag, ids)| (tag, directive_dates(ids)))
+                .collect::<Vec<_>>()
+        ),
+        format!(
+            "{:?}",
+            resolved
+                .postings
+                .iter()
+                .map(|p| (p.date, &p.account, &p.amount, &p.tags, &p.links))
+                .collect::<Vec<_>>()
+        ),
+        format!("{:?}", report.stats),
+        format!(
+            "{:?}",
+            file.account_tree()
+                .iter()
+                .map(|node| node.account.as_str())
+                .collect::<Vec<_>>()
+        ),
+    ]
+}
+
+#[rstest]
+#[case::rotated(&[1, 2, 3, 4, 5, 6, 0])]
+#[case::reversed(&[6, 5, 4, 3, 2, 1, 0])]
+#[case::interleaved(&[3, 0, 5, 2, 6, 1, 4])]
+fn aggregates_should_not_depend_on_the_run_nor_on_the_order_of_the_input(
+    #[case] permutation: &[usize],
+) {
+    let input = shuffle(FIXTURE, permutation);
+    let expected = aggregates(&shuffle(FIXTURE, &[0, 1, 2, 3, 4, 5, 6]));
+    assert_eq!(aggregates(&input), expected);
+    assert_eq!(aggregates(&input), expected);
+}
+
+#[rstest]
+fn aggregates_should_be_sorted() {
+    let file: BeancountFile<f64> = FIXTURE.parse().unwrap();
+    let balances = file.balances(None);
+    let accounts: Vec<&str> = balances.keys().map(Account::as_str).collect();
+    let mut sorted = accounts.clone();
+    sorted.sort_unstable();
+    assert_eq!(accounts, sorted);
+    let links = file.links_index();
+    let links: Vec<&str> = links.keys().map(Link::as_str).collect();
+    assert_eq!(links, ["pay-jan", "savings", "trip"]);
+    let tags = file.tags_index();
+    let tags: Vec<&str> = tags.keys().map(Tag::as_str).collect();
+    assert_eq!(tags, ["food", "travel", "work"]);
+    let currencies: Vec<&str> = balances["Expenses:Travel"]
+        .iter()
+        .map(|(currency, _)| currency.as_str())
+        .collect();
+    assert_eq!(currencies, ["EUR", "USD"]);
+}
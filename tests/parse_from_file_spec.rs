@@ -1,8 +1,13 @@
+#![allow(missing_docs)]
+
 use std::path::PathBuf;
 
 use rstest::rstest;
 
-use beancount_parser::BeancountFile;
+use beancount_parser::{
+    read_files_with_sources, BeancountFile, Code, Entry, FileId, ReadFileError, SourceMap,
+    WarningKind,
+};
 
 #[rstest]
 #[case("comments.beancount", 0)]
@@ -16,3 +21,165 @@ fn can_parse_example_files(#[case] file_name: &str, #[case] expected_directive_c
     beancount_parser::read_files([path], |entry| file.extend(Some(entry))).unwrap();
     assert_eq!(file.directives.len(), expected_directive_count);
 }
+
+fn write_files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("beancount-parser-{name}-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    for (file_name, content) in files {
+        let path = dir.join(file_name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+    dir
+}
+
+#[rstest]
+fn read_files_with_sources_should_attribute_diagnostics_to_their_file() {
+    let dir = write_files(
+        "sources",
+        &[
+            (
+                "main.beancount",
+                "include \"other.beancount\"\n2023-01-01 open Assets:Cash\n  id: 1\n  id: 2\n",
+            ),
+            (
+                "other.beancount",
+                "\n2023-01-02 open Assets:Bank\n2023-01-03 open oops\n",
+            ),
+        ],
+    );
+    let mut sources = SourceMap::default();
+    let mut warnings = Vec::new();
+    let result =
+        read_files_with_sources::<f64, _>([dir.join("main.beancount")], &mut sources, |entry| {
+            if let Entry::Warning(warning) = entry {
+                warnings.push(warning);
+            }
+        });
+    let Err(ReadFileError::Syntax(error)) = result else {
+        panic!("expected a syntax error: {result:?}");
+    };
+    let main = dir.join("main.beancount").canonicalize().unwrap();
+    let other = dir.join("other.beancount").canonicalize().unwrap();
+    assert_ne!(error.file_id(), FileId::MAIN);
+    assert_eq!(sources.resolve(error.file_id()), other);
+    assert_eq!(
+        sources.display(&error).to_string(),
+        format!(
+            "{}:3:17: error[E001]: Invalid beancount syntax at line: 3",
+            other.display()
+        )
+    );
+    let [warning] = &warnings[..] else {
+        panic!("expected one warning: {warnings:?}");
+    };
+    assert_eq!(sources.resolve(warning.file_id()), main);
+    assert_eq!(
+        sources.display(warning).to_string(),
+        format!(
+            "{}:4: warning[W001]: Duplicate metadata key `id` at line: 4 (the first value is kept)",
+            main.display()
+        )
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+fn read_dates(path: PathBuf) -> Result<(Vec<String>, BeancountFile<f64>), ReadFileError> {
+    let mut file = BeancountFile::<f64>::default();
+    beancount_parser::read_files([path], |entry| file.extend(Some(entry)))?;
+    let dates = file.directives.iter().map(|d| d.date.to_string()).collect();
+    Ok((dates, file))
+}
+
+#[rstest]
+fn read_files_should_expand_include_globs_in_lexicographic_order() {
+    let dir = write_files(
+        "globs",
+        &[
+            (
+                "main.beancount",
+                "include \"2023/*.bean\"\ninclude \"accounts\\\\**\\\\*.bean\"\n",
+            ),
+            ("2023/02.bean", "2023-02-01 open Assets:B"),
+            ("2023/01.bean", "2023-01-01 open Assets:A"),
+            ("2023/10.bean", "2023-10-01 open Assets:C"),
+            ("2023/.hidden.bean", "2000-01-01 open Assets:Hidden"),
+            ("2023/notes.txt", "2000-01-01 open Assets:Txt"),
+            ("accounts/b/c/deep.bean", "2021-03-01 open Assets:Deep"),
+            ("accounts/a/x.bean", "2021-01-01 open Assets:X"),
+            ("accounts/top.bean", "2021-02-01 open Assets:Top"),
+        ],
+    );
+    let (dates, _) = read_dates(dir.join("main.beancount")).unwrap();
+    assert_eq!(
+        dates,
+        [
+            "2023-01-01",
+            "2023-02-01",
+            "2023-10-01",
+            "2021-01-01",
+            "2021-03-01",
+            "2021-02-01"
+        ]
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[rstest]
+fn read_files_should_load_a_file_once_when_included_with_different_spellings() {
+    let dir = write_files(
+        "spellings",
+        &[
+            (
+                "main.beancount",
+                "include \"sub/../other.beancount\"\ninclude \"./other.beancount\"\ninclude \"sub\\\\o*.beancount\"\n2023-01-01 open Assets:Main",
+            ),
+            ("other.beancount", "include \"main.beancount\"\n2023-01-02 open Assets:Other"),
+            ("sub/other.beancount", "include \"../*.beancount\"\n2023-01-03 open Assets:Sub"),
+        ],
+    );
+    let (dates, _) = read_dates(dir.join("main.beancount")).unwrap();
+    assert_eq!(dates, ["2023-01-01", "2023-01-02", "2023-01-03"]);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[rstest]
+fn read_files_should_fail_if_an_included_file_does_not_exist() {
+    let dir = write_files(
+        "missing",
+        &[(
+            "main.beancount",
+            "2023-01-01 open Assets:Cash\ninclude \"missing.beancount\"\n",
+        )],
+    );
+    let Err(ReadFileError::Syntax(error)) = read_dates(dir.join("main.beancount")) else {
+        panic!("expected an error");
+    };
+    assert_eq!(error.code(), Code::IncludeNotFound);
+    assert_eq!(error.line_number(), 2);
+    assert_eq!(error.column(), Some(1));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[rstest]
+fn read_files_should_warn_if_an_include_glob_matches_nothing() {
+    let dir = write_files(
+        "empty-glob",
+        &[(
+            "main.beancount",
+            "2023-01-01 open Assets:Cash\ninclude \"2024/*.bean\"\n",
+        )],
+    );
+    let (dates, file) = read_dates(dir.join("main.beancount")).unwrap();
+    assert_eq!(dates, ["2023-01-01"]);
+    let [warning] = &file.warnings[..] else {
+        panic!("expected one warning: {:?}", file.warnings);
+    };
+    assert_eq!(warning.code(), Code::EmptyIncludeGlob);
+    assert_eq!(warning.line_number(), 2);
+    assert_eq!(
+        warning.kind(),
+        &WarningKind::EmptyIncludeGlob("2024/*.bean".into())
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -1,10 +1,17 @@
-#![allow(clippy::items_after_test_module, clippy::pedantic)]
+#![allow(missing_docs, clippy::items_after_test_module, clippy::pedantic)]
 
-use std::{collections::HashSet, path::Path};
+use std::{
+    collections::{BTreeSet, HashSet},
+    path::Path,
+};
 
 use rstest::rstest;
 
-use beancount_parser::{metadata, parse, Account, BeancountFile, Directive, DirectiveContent};
+use beancount_parser::{
+    metadata, parse, parse_iter, parse_iter_with_options, parse_lenient,
+    parse_lenient_with_options, parse_with_options, Account, BeancountFile, Code, CustomValue,
+    Date, Directive, DirectiveContent, Entry, Indent, LineKind, ParseOptions, Tag, WarningKind,
+};
 
 const COMMENTS: &str = include_str!("samples/comments.beancount");
 const SIMPLE: &str = include_str!("samples/simple.beancount");
@@ -83,6 +90,17 @@ fn should_parse_pad_source_account(#[case] input: &str, #[case] expected: &str)
     assert_eq!(pad.source_account.as_str(), expected);
 }
 
+#[rstest]
+fn should_report_the_missing_source_account_of_pad() {
+    let input =
+        "2014-05-01 open Assets:Checking\n2014-06-01 pad Assets:Checking\n2014-06-02 * \"Next\"";
+    let error = parse::<f64>(input).unwrap_err();
+    assert_eq!(error.code(), Code::InvalidSyntax);
+    assert_eq!(error.line_number(), 2);
+    assert_eq!(error.column(), Some(31));
+    assert_eq!(error.snippet(), Some("2014-06-01 pad Assets:Checking"));
+}
+
 #[rstest]
 #[case(
     "2013-09-10 balance Assets:US:Vanguard  305.205 RGAGX",
@@ -128,11 +146,25 @@ fn should_parse_balance_assertion_amount(
 #[case::num_at_end("Assets:Cash2")]
 #[case::num_at_start("Assets:2Cash")]
 #[case::non_standard_name("Ausgaben:A")]
+#[case::composed("Assets:B\u{fc}cher")]
+#[case::decomposed("Assets:Bu\u{308}cher")]
 fn account_from_str_should_parse_valid_account(#[case] input: &str) {
     let account: Account = input.parse().unwrap();
     assert_eq!(account.as_str(), input);
 }
 
+#[rstest]
+fn should_parse_names_written_in_decomposed_form() {
+    let input = "2023-05-22 * \"B\u{fc}cher\" #bu\u{308}cher ^rechnung-bu\u{308}cher\n  Expenses:Bu\u{308}cher  10 EUR\n    de\u{301}ja\u{300}: 1\n  Assets:Cash";
+    let DirectiveContent::Transaction(trx) = parse_single_directive(input).content else {
+        panic!("was not a transaction");
+    };
+    assert!(trx.tags.contains("bu\u{308}cher"));
+    assert!(trx.links.contains("rechnung-bu\u{308}cher"));
+    assert_eq!(trx.postings[0].account.as_str(), "Expenses:Bu\u{308}cher");
+    assert!(trx.postings[0].metadata.contains_key("de\u{301}ja\u{300}"));
+}
+
 #[rstest]
 #[case("oops")]
 #[case("Assets:")]
@@ -225,6 +257,26 @@ fn should_parse_close_account(#[case] input: &str, #[case] expected_account: &st
     assert_eq!(close.account.as_str(), expected_account);
 }
 
+#[rstest]
+fn should_parse_open_and_close_of_the_same_account() {
+    let input = r#"
+2014-05-01 open Assets:Cash CHF
+  institution: "Home"
+2014-06-01 * "Coffee"
+  Expenses:Food  3 CHF
+  Assets:Cash
+2016-12-31 close Assets:Cash ; Moved abroad
+  reason: "moved"
+"#;
+    let file = parse::<f64>(input).unwrap();
+    assert_eq!(file.directives.len(), 3);
+    assert!(file.directives[2].metadata.contains_key("reason"));
+    let tree = file.account_tree();
+    let cash = tree.get("Assets:Cash").unwrap();
+    assert_eq!(cash.open, Some("2014-05-01".parse().unwrap()));
+    assert_eq!(cash.close, Some("2016-12-31".parse().unwrap()));
+}
+
 #[rstest]
 fn should_parse_option() {
     let beancount = parse::<f64>(r#"option "He\"llo" "world\"!\"""#).unwrap();
@@ -251,6 +303,41 @@ option "operating_currency" "PLN"
     );
 }
 
+#[rstest]
+fn should_return_all_the_values_of_an_option() {
+    let input = r#"
+option "operating_currency" "CHF"
+2023-01-01 open Assets:Cash
+option "title" "My ledger"
+option "operating_currency" "PLN"
+"#;
+    let beancount = parse::<f64>(input).unwrap();
+    let currencies: Vec<&str> = beancount.option_values("operating_currency").collect();
+    assert_eq!(currencies, ["CHF", "PLN"]);
+    assert_eq!(beancount.option("operating_currency"), Some("CHF"));
+}
+
+#[rstest]
+fn should_collect_the_options_between_the_directives() {
+    let beancount = parse::<f64>(
+        r#"
+option "title" "Ledger"
+2023-05-20 open Assets:Cash
+option "account_previous_balances" "Opening-Balances"
+2023-05-21 close Assets:Cash
+option "some_future_option" "any value"
+"#,
+    )
+    .unwrap();
+    assert_eq!(beancount.directives.len(), 2);
+    let names: Vec<&str> = beancount.options.iter().map(|opt| &opt.name[..]).collect();
+    assert_eq!(
+        names,
+        ["title", "account_previous_balances", "some_future_option"]
+    );
+    assert_eq!(beancount.option("some_future_option"), Some("any value"));
+}
+
 #[rstest]
 fn should_parse_option_with_comment() {
     let beancount = parse::<f64>(r#"option "Hello" "world!" ; This is great"#).unwrap();
@@ -276,6 +363,257 @@ fn should_parse_include_with_comment() {
     assert_eq!(&includes, expected);
 }
 
+#[rstest]
+fn should_keep_the_include_paths_as_written() {
+    let input = r#"
+include "does/not/exist.beancount"
+include "../accounts/*.beancount"
+"#;
+    let includes = parse::<f64>(input).unwrap().includes;
+    let expected = [
+        Path::new("does/not/exist.beancount"),
+        Path::new("../accounts/*.beancount"),
+    ];
+    assert_eq!(includes, expected);
+}
+
+#[rstest]
+#[case::relative(r#"include "2023/january.beancount""#, "2023/january.beancount")]
+#[case::absolute(
+    r#"include "/home/me/ledger/main.beancount""#,
+    "/home/me/ledger/main.beancount"
+)]
+#[case::windows(
+    r#"include "C:\\ledger\\main.beancount""#,
+    "C:\\ledger\\main.beancount"
+)]
+#[case::with_spaces(
+    r#"include "my ledger/2023 january.beancount""#,
+    "my ledger/2023 january.beancount"
+)]
+#[case::with_comment(
+    r#"include "2023/january.beancount"; January"#,
+    "2023/january.beancount"
+)]
+#[case::with_trailing_spaces("include \"2023/january.beancount\"  \t", "2023/january.beancount")]
+fn should_parse_include_path(#[case] input: &str, #[case] expected: &str) {
+    let includes = parse::<f64>(input).unwrap().includes;
+    assert_eq!(includes, [Path::new(expected)]);
+}
+
+#[rstest]
+fn should_parse_plugin() {
+    let plugins = parse::<f64>(r#"plugin "beancount.plugins.auto_accounts""#)
+        .unwrap()
+        .plugins;
+    assert_eq!(plugins.len(), 1);
+    assert_eq!(plugins[0].name, "beancount.plugins.auto_accounts");
+    assert_eq!(plugins[0].config, None);
+}
+
+#[rstest]
+fn should_parse_plugin_with_config() {
+    let input = r#"
+plugin "first" "some \"config\"" ; with a comment
+plugin "second"
+"#;
+    let plugins = parse::<f64>(input).unwrap().plugins;
+    let names: Vec<&str> = plugins.iter().map(|p| &p.name[..]).collect();
+    assert_eq!(names, ["first", "second"]);
+    assert_eq!(plugins[0].config.as_deref(), Some("some \"config\""));
+    assert_eq!(plugins[1].config, None);
+}
+
+#[rstest]
+fn should_emit_the_operations_of_the_tag_stack() {
+    let input = "pushtag #trip\n2023-01-01 * \"Hotel\"\npoptag #trip\n";
+    let entries: Vec<String> = parse_iter::<f64>(input)
+        .map(|entry| match entry.unwrap() {
+            Entry::PushTag(tag) => format!("pushtag {tag}"),
+            Entry::PopTag(tag) => format!("poptag {tag}"),
+            Entry::Directive(d) => format!("directive {}", d.line_number),
+            _ => unreachable!(),
+        })
+        .collect();
+    assert_eq!(entries, ["pushtag trip", "directive 2", "poptag trip"]);
+}
+
+#[rstest]
+fn should_compose_nested_pushed_tags() {
+    let input = r#"
+pushtag #trip
+2023-01-01 * "Train"
+pushtag #work
+2023-01-02 * "Hotel" #paid
+poptag #trip
+2023-01-03 * "Meeting"
+poptag #work
+2023-01-04 * "Home"
+"#;
+    let tags: Vec<BTreeSet<String>> = parse::<f64>(input)
+        .unwrap()
+        .directives
+        .into_iter()
+        .map(|d| match d.content {
+            DirectiveContent::Transaction(trx) => {
+                trx.tags.iter().map(ToString::to_string).collect()
+            }
+            _ => unreachable!(),
+        })
+        .collect();
+    let expected: Vec<BTreeSet<String>> =
+        [&["trip"][..], &["paid", "trip", "work"], &["work"], &[]]
+            .iter()
+            .map(|tags| tags.iter().map(ToString::to_string).collect())
+            .collect();
+    assert_eq!(tags, expected);
+}
+
+#[rstest]
+fn should_warn_when_popping_a_tag_that_is_not_pushed() {
+    let input = "pushtag #trip\npoptag #tirp\n2023-01-01 * \"Hotel\"\n";
+    let file = parse::<f64>(input).unwrap();
+    assert_eq!(file.warnings.len(), 1);
+    assert_eq!(file.warnings[0].code(), Code::UnbalancedTag);
+    assert_eq!(file.warnings[0].line_number(), 2);
+    assert!(
+        matches!(file.warnings[0].kind(), WarningKind::UnpushedTagPopped(tag) if tag.as_str() == "tirp")
+    );
+    let DirectiveContent::Transaction(trx) = &file.directives[0].content else {
+        panic!("was not a transaction");
+    };
+    assert!(trx.tags.contains("trip"));
+    let options = ParseOptions::default().suppress(Code::UnbalancedTag);
+    assert!(parse_with_options::<f64>(input, &options)
+        .unwrap()
+        .warnings
+        .is_empty());
+}
+
+#[rstest]
+fn should_apply_the_tags_still_pushed_until_the_end_of_the_input() {
+    let input = "pushtag #trip\npushtag #work\n2023-01-01 * \"Hotel\"\npoptag #work\n2023-01-02 * \"Train\"";
+    let file = parse::<f64>(input).unwrap();
+    assert!(file.warnings.is_empty());
+    let DirectiveContent::Transaction(trx) = &file.directives[1].content else {
+        panic!("was not a transaction");
+    };
+    assert_eq!(trx.tags.len(), 1);
+    assert!(trx.tags.contains("trip"));
+    let pushed: Vec<&str> = file.pushed_tags().map(Tag::as_str).collect();
+    assert_eq!(pushed, ["trip"]);
+}
+
+#[rstest]
+fn should_apply_the_pushed_metadata_to_the_following_directives() {
+    let input = r#"
+pushmeta location: "Paris"
+2023-01-01 open Assets:Cash
+pushmeta location: "Lyon"
+2023-01-02 * "Train"
+  Expenses:Transport  10 EUR
+  Assets:Cash
+2023-01-03 commodity EUR
+  location: "Brussels"
+popmeta location:
+2023-01-04 close Assets:Cash
+popmeta location
+2023-01-05 commodity CHF
+"#;
+    let locations: Vec<Option<String>> = parse::<f64>(input)
+        .unwrap()
+        .directives
+        .iter()
+        .map(|d| match d.metadata.get("location") {
+            Some(metadata::Value::String(location)) => Some(location.clone()),
+            None => None,
+            Some(value) => panic!("unexpected value: {value:?}"),
+        })
+        .collect();
+    assert_eq!(
+        locations,
+        [
+            Some("Paris".into()),
+            Some("Lyon".into()),
+            Some("Brussels".into()),
+            Some("Paris".into()),
+            None
+        ]
+    );
+}
+
+#[rstest]
+fn should_not_add_the_pushed_metadata_to_the_postings() {
+    let input = "pushmeta trip: \"Paris\"\n2023-01-01 *\n  Expenses:Food  10 EUR\n  Assets:Cash";
+    let file = parse::<f64>(input).unwrap();
+    let DirectiveContent::Transaction(trx) = &file.directives[0].content else {
+        panic!("was not a transaction");
+    };
+    assert!(file.directives[0].metadata.contains_key("trip"));
+    assert!(trx.postings.iter().all(|p| p.metadata.is_empty()));
+}
+
+#[rstest]
+fn should_emit_the_operations_of_the_metadata_stack() {
+    let input = "pushmeta rate: 2 * 3\n2023-01-01 open Assets:Cash\npopmeta rate:\n";
+    let entries: Vec<String> = parse_iter::<f64>(input)
+        .map(|entry| match entry.unwrap() {
+            Entry::PushMeta(key, metadata::Value::Number(value)) => {
+                format!("pushmeta {key} {value}")
+            }
+            Entry::PopMeta(key) => format!("popmeta {key}"),
+            Entry::Directive(d) => format!("directive {:?}", d.metadata.get("rate")),
+            entry => panic!("unexpected entry: {entry:?}"),
+        })
+        .collect();
+    assert_eq!(
+        entries,
+        [
+            "pushmeta rate 6",
+            "directive Some(Number(6.0))",
+            "popmeta rate"
+        ]
+    );
+}
+
+#[rstest]
+#[case::push_without_value("pushmeta location:")]
+#[case::push_without_colon("pushmeta location \"Paris\"")]
+#[case::push_invalid_key("pushmeta Location: \"Paris\"")]
+#[case::pop_with_value("popmeta location: \"Paris\"")]
+fn should_reject_invalid_metadata_stack_operations(#[case] input: &str) {
+    assert!(parse::<f64>(input).is_err());
+}
+
+#[rstest]
+fn extend_from_str_should_continue_with_the_pushed_metadata() {
+    let mut file: BeancountFile<f64> = "pushmeta trip: \"Paris\"\n".parse().unwrap();
+    file.extend_from_str("2023-01-01 open Assets:Cash\npopmeta trip:\n2023-01-02 open Assets:Bank")
+        .unwrap();
+    let trips: Vec<bool> = file
+        .directives
+        .iter()
+        .map(|d| d.metadata.contains_key("trip"))
+        .collect();
+    assert_eq!(trips, [true, false]);
+}
+
+#[rstest]
+fn should_find_a_plugin_by_name() {
+    let input = r#"
+plugin "beancount.plugins.auto_accounts"
+2023-01-01 open Assets:Cash
+plugin "beancount.plugins.check_commodity" "first"
+plugin "beancount.plugins.check_commodity" "second"
+"#;
+    let beancount = parse::<f64>(input).unwrap();
+    let plugin = beancount
+        .plugin("beancount.plugins.check_commodity")
+        .unwrap();
+    assert_eq!(plugin.config.as_deref(), Some("first"));
+    assert!(beancount.plugin("beancount.plugins").is_none());
+}
+
 #[rstest]
 fn should_parse_commodity() {
     let input = "1792-01-01 commodity USD";
@@ -285,6 +623,24 @@ fn should_parse_commodity() {
     assert_eq!(commodity.as_str(), "USD");
 }
 
+#[rstest]
+fn should_parse_metadata_of_commodity() {
+    let input = "2012-01-01 commodity HOOL\n  name: \"Google Inc.\"\n  asset-class: \"stock\"";
+    let directive = parse_single_directive(input);
+    let DirectiveContent::Commodity(commodity) = &directive.content else {
+        panic!("was not an commodity directive");
+    };
+    assert_eq!(commodity.as_str(), "HOOL");
+    assert_eq!(
+        directive.metadata.get("name"),
+        Some(&metadata::Value::String("Google Inc.".into()))
+    );
+    assert_eq!(
+        directive.metadata.get("asset-class"),
+        Some(&metadata::Value::String("stock".into()))
+    );
+}
+
 #[rstest]
 fn should_parse_commodity_that_ends_with_number() {
     let input = "1792-01-01 commodity A1";
@@ -297,30 +653,325 @@ fn should_parse_commodity_that_ends_with_number() {
 #[rstest]
 fn should_parse_event() {
     let input = "2020-12-09 event \"location\" \"New \\\"Metropolis\\\"\"";
-    let DirectiveContent::Event(event) = parse_single_directive(input).content else {
-        panic!("was not an commodity directive");
+    assert_eq!(
+        parse_single_directive(input).debug_tree(),
+        r#"2020-12-09 event
+  name: "location"
+  value: "New \"Metropolis\""
+"#
+    );
+}
+
+#[rstest]
+#[case::plain("\"Transferred to savings\"", "Transferred to savings")]
+#[case::unicode("\"Überweisung ✓ 💸\"", "Überweisung ✓ 💸")]
+#[case::escaped_quotes(r#""Called \"the bank\"""#, "Called \"the bank\"")]
+#[case::escaped_backslash(r#""C:\\Users""#, "C:\\Users")]
+#[case::comment("\"Checked\" ; by hand", "Checked")]
+fn should_parse_note(#[case] description: &str, #[case] expected: &str) {
+    let input = format!("2023-06-15 note Assets:Cash {description}");
+    let DirectiveContent::Note(note) = parse_single_directive(&input).content else {
+        panic!("was not a note");
     };
-    assert_eq!(event.name, "location");
-    assert_eq!(event.value, "New \"Metropolis\"");
+    assert_eq!(note.account.as_str(), "Assets:Cash");
+    assert_eq!(note.description, expected);
 }
 
 #[rstest]
-fn should_parse_price_commodity() {
-    let input = "2022-08-26 price VHT          121.03 USD";
+fn should_parse_note_with_metadata() {
+    let input = "2023-06-15 note Assets:Cash \"Transferred\"\n  ticket: 42";
+    assert_eq!(
+        parse_single_directive(input).debug_tree(),
+        r#"2023-06-15 note
+  account: Assets:Cash
+  description: "Transferred"
+  ticket: 42
+"#
+    );
+}
+
+#[rstest]
+fn should_parse_tags_and_links_after_note() {
+    let input = "2013-11-03 note Assets:Checking \"Called about the fees\" #bank ^fees-2013 #phone\n2013-11-04 close Assets:Checking";
+    let file = parse::<f64>(input).unwrap();
+    assert_eq!(
+        file.directives[0].debug_tree(),
+        r#"2013-11-03 note
+  account: Assets:Checking
+  description: "Called about the fees"
+  tags: #bank #phone
+  links: ^fees-2013
+"#
+    );
+    assert!(matches!(
+        file.directives[1].content,
+        DirectiveContent::Close(_)
+    ));
+    assert_eq!(file.links_index()["fees-2013"].len(), 1);
+}
+
+#[rstest]
+#[case::no_description("2023-06-15 note Assets:Cash")]
+#[case::unquoted_description("2023-06-15 note Assets:Cash Transferred")]
+#[case::no_account(r#"2023-06-15 note "Transferred""#)]
+fn should_reject_invalid_note(#[case] input: &str) {
+    assert!(parse::<f64>(input).is_err());
+}
+
+#[rstest]
+#[case::absolute("\"/home/user/stmt.pdf\"", "/home/user/stmt.pdf")]
+#[case::relative("\"statements/2023/april.pdf\"", "statements/2023/april.pdf")]
+#[case::spaces(
+    "\"/home/user/My Statements/april.pdf\"",
+    "/home/user/My Statements/april.pdf"
+)]
+#[case::unicode("\"relevés/avril ✓.pdf\"", "relevés/avril ✓.pdf")]
+#[case::comment("\"stmt.pdf\" ; from the bank", "stmt.pdf")]
+fn should_parse_document(#[case] path: &str, #[case] expected: &str) {
+    let input = format!("2023-04-01 document Assets:Cash {path}");
+    let DirectiveContent::Document(document) = parse_single_directive(&input).content else {
+        panic!("was not a document");
+    };
+    assert_eq!(document.account.as_str(), "Assets:Cash");
+    assert_eq!(document.path, expected);
+}
+
+#[rstest]
+fn should_parse_document_with_metadata() {
+    let input = "2023-04-01 document Assets:Cash \"stmt.pdf\"\n  pages: 2";
+    assert_eq!(
+        parse_single_directive(input).debug_tree(),
+        r#"2023-04-01 document
+  account: Assets:Cash
+  path: "stmt.pdf"
+  pages: 2
+"#
+    );
+}
+
+#[rstest]
+fn should_parse_tags_and_links_after_document() {
+    let input = "2013-11-03 document Assets:Checking \"/path/to/statement.pdf\" #bank ^stmt-2013-11\n  pages: 2\n2013-11-04 close Assets:Checking";
+    let file = parse::<f64>(input).unwrap();
+    assert_eq!(
+        file.directives[0].debug_tree(),
+        r#"2013-11-03 document
+  account: Assets:Checking
+  path: "/path/to/statement.pdf"
+  tags: #bank
+  links: ^stmt-2013-11
+  pages: 2
+"#
+    );
+    assert!(matches!(
+        file.directives[1].content,
+        DirectiveContent::Close(_)
+    ));
+    assert_eq!(file.tags_index()["bank"].len(), 1);
+}
+
+#[rstest]
+#[case::no_path("2023-04-01 document Assets:Cash")]
+#[case::unquoted_path("2023-04-01 document Assets:Cash /home/user/stmt.pdf")]
+#[case::unterminated_path(r#"2023-04-01 document Assets:Cash "stmt.pdf"#)]
+#[case::no_account(r#"2023-04-01 document "stmt.pdf""#)]
+fn should_reject_invalid_document(#[case] input: &str) {
+    assert!(parse::<f64>(input).is_err());
+}
+
+#[rstest]
+#[case::simple(
+    r#""cash" "SELECT account, sum(position)""#,
+    "cash",
+    "SELECT account, sum(position)"
+)]
+#[case::semicolons(
+    r#""balances" "SELECT account; SELECT date;" ; comment"#,
+    "balances",
+    "SELECT account; SELECT date;"
+)]
+#[case::escaped_quotes(
+    r#""payees" "SELECT payee WHERE payee ~ \"Shop\"""#,
+    "payees",
+    r#"SELECT payee WHERE payee ~ "Shop""#
+)]
+#[case::multiline(
+    "\"food\" \"SELECT date, narration\n  WHERE account ~ 'Food'\"",
+    "food",
+    "SELECT date, narration\n  WHERE account ~ 'Food'"
+)]
+fn should_parse_query(#[case] args: &str, #[case] name: &str, #[case] query_string: &str) {
+    let input = format!("2023-01-01 query {args}\n2023-01-02 close Assets:Cash");
+    let file = parse::<f64>(&input).unwrap();
+    let DirectiveContent::Query(query) = &file.directives[0].content else {
+        panic!("was not a query");
+    };
+    assert_eq!(query.name, name);
+    assert_eq!(query.query_string, query_string);
+    assert!(matches!(
+        file.directives[1].content,
+        DirectiveContent::Close(_)
+    ));
+}
+
+#[rstest]
+fn should_parse_long_query() {
+    let columns = vec!["account"; 5_000].join(", ");
+    let input = format!("2023-01-01 query \"wide\" \"SELECT {columns}\"");
+    let DirectiveContent::Query(query) = parse_single_directive(&input).content else {
+        panic!("was not a query");
+    };
+    assert_eq!(query.query_string.len(), "SELECT ".len() + columns.len());
+}
+
+#[rstest]
+fn should_parse_query_with_metadata() {
+    let input = "2023-01-01 query \"cash\" \"SELECT account\"\n  author: \"me\"";
+    assert_eq!(
+        parse_single_directive(input).debug_tree(),
+        r#"2023-01-01 query
+  name: "cash"
+  query_string: "SELECT account"
+  author: "me"
+"#
+    );
+}
+
+#[rstest]
+#[case::no_query_string(r#"2023-01-01 query "cash""#)]
+#[case::unquoted_query_string(r#"2023-01-01 query "cash" SELECT account"#)]
+#[case::unterminated_query_string(r#"2023-01-01 query "cash" "SELECT account"#)]
+#[case::unquoted_name(r#"2023-01-01 query cash "SELECT account""#)]
+fn should_reject_invalid_query(#[case] input: &str) {
+    assert!(parse::<f64>(input).is_err());
+}
+
+#[rstest]
+#[case::simple(r#""location" "New York""#, "location", "New York")]
+#[case::inner_spaces(r#""home  office" " Zürich  West ""#, "home  office", " Zürich  West ")]
+#[case::several_spaces(r#""employer"    "ACME Corp""#, "employer", "ACME Corp")]
+#[case::comma(r#""location" "Paris, France""#, "location", "Paris, France")]
+#[case::escaped_quotes(r#""\"employer\"" "ACME \"Corp\"""#, r#""employer""#, r#"ACME "Corp""#)]
+#[case::escaped_backslashes(r#""share" "\\\\server\\home""#, "share", r"\\server\home")]
+fn should_separate_the_name_and_value_of_event(
+    #[case] strings: &str,
+    #[case] name: &str,
+    #[case] value: &str,
+) {
+    let input = format!("2023-01-01 event {strings}");
+    let DirectiveContent::Event(event) = parse_single_directive(&input).content else {
+        panic!("was not an event");
+    };
+    assert_eq!(event.name, name);
+    assert_eq!(event.value, value);
+}
+
+#[rstest]
+fn should_report_the_missing_value_of_event() {
+    let input = "2023-01-01 event \"location\"\n2023-01-02 event \"location\" \"Paris\"";
+    let error = parse::<f64>(input).unwrap_err();
+    assert_eq!(error.code(), Code::InvalidSyntax);
+    assert_eq!(error.line_number(), 1);
+}
+
+#[rstest]
+fn should_parse_custom() {
+    let input =
+        r#"2020-12-09 custom "fava-option" "locale" 2020-01-01 TRUE FALSE 12.5 USD 3 Assets:Cash"#;
+    assert_eq!(
+        parse_single_directive(input).debug_tree(),
+        r#"2020-12-09 custom
+  name: "fava-option"
+  value: "locale"
+  value: 2020-01-01
+  value: TRUE
+  value: FALSE
+  value: 12.5 USD
+  value: 3
+  value: Assets:Cash
+"#
+    );
+}
+
+#[rstest]
+fn should_collect_the_values_of_custom_until_the_end_of_line() {
+    let input = r#"2023-01-01 custom "name" "arg1" 42 USD 2023-01-01
+2023-01-02 custom "autobean.xcheck" "statements.csv"   Assets:Bank
+  tolerance: 0.01
+2023-01-03 close Assets:Bank"#;
+    let file = parse::<f64>(input).unwrap();
+    assert_eq!(file.directives.len(), 3);
+    let DirectiveContent::Custom(custom) = &file.directives[0].content else {
+        panic!("was not a custom directive");
+    };
+    assert_eq!(custom.name, "name");
+    assert!(matches!(
+        custom.values.as_slice(),
+        [
+            CustomValue::String(arg),
+            CustomValue::Amount(amount),
+            CustomValue::Date(date),
+        ] if arg == "arg1"
+            && amount.value == 42.0
+            && amount.currency.as_str() == "USD"
+            && *date == Date::new(2023, 1, 1)
+    ));
+    let DirectiveContent::Custom(custom) = &file.directives[1].content else {
+        panic!("was not a custom directive");
+    };
+    assert_eq!(custom.values.len(), 2);
+    assert!(file.directives[1].metadata.contains_key("tolerance"));
+}
+
+#[rstest]
+fn should_report_the_location_of_an_invalid_custom_value() {
+    let input = "2023-01-01 open Expenses:Food\n2023-01-02 custom \"budget\" Expenses:Food \"monthly\" 45.30 USD oops";
+    let error = parse::<f64>(input).unwrap_err();
+    assert_eq!(error.code(), Code::InvalidSyntax);
+    assert_eq!(error.line_number(), 2);
+    assert_eq!(error.column(), Some(62));
+}
+
+#[rstest]
+#[case::no_value(r#"2020-12-09 custom "autobean""#)]
+#[case::comment(r#"2020-12-09 custom "autobean" ; comment"#)]
+#[case::metadata("2020-12-09 custom \"autobean\"\n  key: \"value\"")]
+fn should_parse_custom_without_values(#[case] input: &str) {
+    let DirectiveContent::Custom(custom) = parse_single_directive(input).content else {
+        panic!("was not a custom directive");
+    };
+    assert_eq!(custom.name, "autobean");
+    assert!(custom.values.is_empty());
+}
+
+#[rstest]
+#[case::no_name("2020-12-09 custom")]
+#[case::unquoted_name("2020-12-09 custom budget")]
+#[case::invalid_value(r#"2020-12-09 custom "budget" oops"#)]
+fn should_reject_invalid_custom(#[case] input: &str) {
+    assert!(parse::<f64>(input).is_err());
+}
+
+#[rstest]
+#[case("2022-08-26 price VHT          121.03 USD", "VHT")]
+#[case("2014-05-01 price AAPL-2014 720.50 USD", "AAPL-2014")]
+#[case("2014-05-01 price HOOL 720.50 USD ; From the broker", "HOOL")]
+fn should_parse_price_commodity(#[case] input: &str, #[case] expected: &str) {
     let DirectiveContent::Price(price) = parse_single_directive(input).content else {
         panic!("was not an price directive");
     };
-    assert_eq!(price.currency.as_str(), "VHT");
+    assert_eq!(price.currency.as_str(), expected);
 }
 
 #[rstest]
-fn should_parse_price_amount() {
-    let input = "2022-08-26 price VHT          121.03 USD";
+#[case("2022-08-26 price VHT          121.03 USD", 121.03, "USD")]
+#[case("2023-01-01 price USD 1.25 EUR", 1.25, "EUR")]
+fn should_parse_price_amount(#[case] input: &str, #[case] value: f64, #[case] currency: &str) {
     let DirectiveContent::Price(price) = parse_single_directive(input).content else {
         panic!("was not an price directive");
     };
-    assert_eq!(price.amount.value, 121.03);
-    assert_eq!(price.amount.currency.as_str(), "USD");
+    assert_eq!(price.amount.value, value);
+    assert_eq!(price.amount.currency.as_str(), currency);
 }
 
 #[rstest]
@@ -403,6 +1054,28 @@ fn should_parse_metadata_entry(
     assert_eq!(metadata.get(key), Some(&expected_value));
 }
 
+#[rstest]
+#[case::balance("2014-06-01 balance Assets:Checking 10 CHF ; A comment")]
+#[case::price("2014-06-01 price HOOL 10 CHF ; A comment")]
+#[case::pad("2014-06-01 pad Assets:Checking Equity:Opening-Balances ; A comment")]
+#[case::close("2014-06-01 close Assets:Checking ; A comment")]
+fn should_parse_metadata_of_directive(#[case] directive: &str) {
+    let input = format!(
+        "{directive}\n  title: \"hello\"\n  value: 42 ; The answer\n2014-06-02 open Assets:Cash"
+    );
+    let directives = parse::<f64>(&input).unwrap().directives;
+    assert_eq!(directives.len(), 2, "{directives:#?}");
+    let metadata = &directives[0].metadata;
+    assert_eq!(metadata.len(), 2, "{metadata:#?}");
+    assert_eq!(
+        metadata.get("title"),
+        Some(&metadata::Value::String("hello".into()))
+    );
+    assert_eq!(metadata.get("value"), Some(&metadata::Value::Number(42.0)));
+    assert_eq!(directives[1].line_number, 4);
+    assert!(matches!(directives[1].content, DirectiveContent::Open(_)));
+}
+
 #[rstest]
 fn should_parse_metadata_currency() {
     let metadata = parse_single_directive("2023-05-27 *\n foo: CHF").metadata;
@@ -412,6 +1085,978 @@ fn should_parse_metadata_currency() {
     assert_eq!(currency.as_str(), "CHF");
 }
 
+#[rstest]
+#[case::identical_values(
+    "2023-05-27 open Assets:Cash\n  id: 1\n  id: 1",
+    &[metadata::Value::Number(1.0), metadata::Value::Number(1.0)],
+    &[3]
+)]
+#[case::different_types(
+    "2023-05-27 open Assets:Cash\n  id: \"one\"\n  id: CHF",
+    &[
+        metadata::Value::String("one".into()),
+        metadata::Value::Currency("CHF".parse().unwrap()),
+    ],
+    &[3]
+)]
+#[case::triplicate(
+    "2023-05-27 open Assets:Cash\n  id: 1\n  other: 0\n  id: 2\n  id: 3",
+    &[
+        metadata::Value::Number(1.0),
+        metadata::Value::Number(2.0),
+        metadata::Value::Number(3.0),
+    ],
+    &[4, 5]
+)]
+#[case::on_posting(
+    "2023-05-27 *\n  Assets:Cash 1 CHF\n    id: 1\n    id: 2\n  Income:Gift",
+    &[],
+    &[4]
+)]
+fn should_keep_first_duplicate_metadata_and_warn(
+    #[case] input: &str,
+    #[case] expected_values: &[metadata::Value<f64>],
+    #[case] expected_warning_lines: &[u32],
+) {
+    let file = parse::<f64>(input).unwrap();
+    let directive = &file.directives[0];
+    assert_eq!(directive.metadata.get("id"), expected_values.first());
+    assert_eq!(
+        directive.metadata_all("id").cloned().collect::<Vec<_>>(),
+        expected_values
+    );
+    let warning_lines: Vec<u32> = file.warnings.iter().map(|w| w.line_number()).collect();
+    assert_eq!(warning_lines, expected_warning_lines);
+    for warning in &file.warnings {
+        assert!(
+            matches!(warning.kind(), WarningKind::DuplicateMetadataKey(key) if key.as_ref() == "id"),
+            "{warning:?}"
+        );
+    }
+}
+
+#[rstest]
+fn should_give_all_duplicate_metadata_of_posting() {
+    let file =
+        parse::<f64>("2023-05-27 *\n  Assets:Cash 1 CHF\n    id: 1\n    id: 2\n  Income:Gift")
+            .unwrap();
+    let DirectiveContent::Transaction(trx) = &file.directives[0].content else {
+        unreachable!()
+    };
+    let values: Vec<_> = trx.postings[0].metadata_all("id").collect();
+    assert_eq!(
+        values,
+        [&metadata::Value::Number(1.0), &metadata::Value::Number(2.0)]
+    );
+    assert_eq!(trx.postings[0].metadata_all("other").count(), 0);
+}
+
+#[rstest]
+#[case::posting_only("  Assets:Cash 1 CHF\n    a: 1\n  Income:Gift", &[("a", 1.0)])]
+#[case::transaction_only("  a: 1\n  Assets:Cash 1 CHF\n  Income:Gift", &[("a", 1.0)])]
+#[case::posting_first("  b: 2\n  Assets:Cash 1 CHF\n    a: 1\n  Income:Gift", &[("a", 1.0), ("b", 2.0)])]
+#[case::posting_takes_precedence("  a: 2\n  Assets:Cash 1 CHF\n    a: 1\n  Income:Gift", &[("a", 1.0)])]
+#[case::none("  Assets:Cash 1 CHF\n  Income:Gift", &[])]
+fn should_give_effective_metadata_of_posting(#[case] body: &str, #[case] expected: &[(&str, f64)]) {
+    let file = parse::<f64>(&format!("2023-05-27 *\n{body}")).unwrap();
+    let directive = &file.directives[0];
+    let DirectiveContent::Transaction(trx) = &directive.content else {
+        unreachable!()
+    };
+    let actual: Vec<(&str, f64)> = trx.postings[0]
+        .effective_metadata(&directive.metadata)
+        .map(|(key, value)| match value {
+            metadata::Value::Number(n) => (key.as_ref(), *n),
+            _ => unreachable!(),
+        })
+        .collect();
+    assert_eq!(actual, expected);
+}
+
+#[rstest]
+fn should_emit_duplicate_metadata_warnings_after_the_directive() {
+    let entries: Vec<Entry<f64>> =
+        parse_iter("2023-05-27 open Assets:Cash\n  id: 1\n  id: 2\n2023-05-28 close Assets:Cash")
+            .collect::<Result<_, _>>()
+            .unwrap();
+    assert!(matches!(entries[0], Entry::Directive(_)), "{entries:?}");
+    assert!(matches!(entries[1], Entry::Warning(_)), "{entries:?}");
+    assert!(matches!(entries[2], Entry::Directive(_)), "{entries:?}");
+    assert_eq!(entries.len(), 3);
+}
+
+#[rstest]
+#[case("2023-05-27 open Assets:Cash\n  id: 1\n  id: 1", 3)]
+#[case("2023-05-27 open Assets:Cash\n  id: 1\n  id: \"one\"", 3)]
+#[case(
+    "2023-05-27 *\n  id: 1\n  id: 2\n  Assets:Cash 1 CHF\n  Income:Gift",
+    3
+)]
+#[case(
+    "2023-05-27 *\n  Assets:Cash 1 CHF\n    id: 1\n    id: 2\n  Income:Gift",
+    4
+)]
+fn strict_metadata_should_reject_duplicate_keys(#[case] input: &str, #[case] expected_line: u32) {
+    let options = ParseOptions::default().strict_metadata(true);
+    let result = parse_with_options::<f64>(input, &options);
+    let error = result.expect_err("should be rejected");
+    assert_eq!(error.line_number(), expected_line);
+}
+
+#[rstest]
+fn strict_metadata_should_accept_distinct_keys() {
+    let options = ParseOptions::default().strict_metadata(true);
+    let file =
+        parse_with_options::<f64>("2023-05-27 open Assets:Cash\n  id: 1\n  other: 1", &options)
+            .unwrap();
+    assert!(file.warnings.is_empty());
+}
+
+#[rstest]
+fn errors_and_warnings_should_have_a_code() {
+    let input = "2023-05-27 open Assets:Cash\n  id: 1\n  id: 2";
+    let warning = &parse::<f64>(input).unwrap().warnings[0];
+    assert_eq!(warning.code(), Code::DuplicateMetadataKey);
+    assert_eq!(
+        warning.to_string(),
+        "warning[W001]: Duplicate metadata key `id` at line: 3 (the first value is kept)"
+    );
+
+    let options = ParseOptions::default().strict_metadata(true);
+    let error = parse_with_options::<f64>(input, &options).unwrap_err();
+    assert_eq!(error.code(), Code::DuplicateMetadataKey);
+    assert_eq!(
+        error.to_string(),
+        "error[W001]: Duplicate metadata key at line: 3"
+    );
+
+    let error = parse::<f64>("2023-05-27 open oops").unwrap_err();
+    assert_eq!(error.code(), Code::InvalidSyntax);
+}
+
+#[rstest]
+fn suppressed_warnings_should_not_be_emitted() {
+    let input = "2023-05-27 open Assets:Cash\n  id: 1\n  id: 2";
+    let options = ParseOptions::default().suppress(Code::DuplicateMetadataKey);
+    let entries: Vec<Entry<f64>> = beancount_parser::parse_iter_with_options(input, &options)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(entries.len(), 1);
+    assert!(matches!(entries[0], Entry::Directive(_)));
+}
+
+#[rstest]
+#[case::seconds("2023-05-22 12:30:00 * \"Coffee\"", " 12:30:00")]
+#[case::minutes("2023-05-22 12:30 open Assets:Cash", " 12:30")]
+#[case::single_digit_hour("2023-05-22 9:30 open Assets:Cash", " 9:30")]
+#[case::fraction_of_seconds("2023-05-22 12:30:00.123 open Assets:Cash", " 12:30:00.123")]
+#[case::iso_separator("2023-05-22T12:30:00 open Assets:Cash", "T12:30:00")]
+#[case::many_spaces("2023-05-22   12:30:00  open Assets:Cash", "   12:30:00")]
+fn should_reject_time_after_date(#[case] input: &str, #[case] time: &str) {
+    let error = parse::<f64>(input).unwrap_err();
+    assert_eq!(error.code(), Code::DateWithTime);
+    assert_eq!(error.line_number(), 1);
+    assert!(
+        error.to_string().ends_with(&format!("(remove `{time}`)")),
+        "{error}"
+    );
+}
+
+#[rstest]
+#[case::time_alone("2023-05-22 10:00")]
+#[case::invalid_hour("2023-05-22 24:00 open Assets:Cash")]
+#[case::invalid_minutes("2023-05-22 10:60 open Assets:Cash")]
+#[case::not_followed_by_directive("2023-05-22 10:00 oops")]
+fn should_not_detect_time_if_not_a_timed_directive(#[case] input: &str) {
+    let result = parse::<f64>(input);
+    if let Err(error) = result {
+        assert_ne!(error.code(), Code::DateWithTime, "{error}");
+    }
+}
+
+#[rstest]
+fn strip_times_should_remove_the_time_with_a_warning() {
+    let input = r#"
+2023-05-22 12:30:00 * "Coffee"
+  Expenses:Coffee  3 CHF
+  Assets:Cash
+2023-05-23 open Assets:Bank
+"#;
+    let options = ParseOptions::default().strip_times(true);
+    let file = parse_with_options::<f64>(input, &options).unwrap();
+    assert_eq!(file.directives.len(), 2);
+    assert_eq!(file.directives[0].date, "2023-05-22".parse().unwrap());
+    let DirectiveContent::Transaction(trx) = &file.directives[0].content else {
+        panic!("was not a transaction: {:?}", file.directives[0]);
+    };
+    assert_eq!(trx.postings.len(), 2);
+    assert_eq!(file.warnings.len(), 1);
+    assert_eq!(file.warnings[0].code(), Code::DateWithTime);
+    assert_eq!(file.warnings[0].line_number(), 2);
+    assert!(
+        matches!(file.warnings[0].kind(), WarningKind::TimeRemoved(time) if time == "12:30:00"),
+        "{:?}",
+        file.warnings[0]
+    );
+}
+
+#[rstest]
+#[case::trailing_colon("2023-05-22 open Assets:Bank:", 1)]
+#[case::double_colon("2023-05-22 open Assets::Bank", 1)]
+#[case::leading_colon("2023-05-22 open :Assets:Bank", 1)]
+#[case::in_balance("2023-05-22 balance Assets:Bank: 10 CHF", 1)]
+#[case::in_pad("2023-05-22 pad Assets:Bank Equity::Opening", 1)]
+#[case::in_posting("2023-05-22 * \"Coffee\"\n  Expenses:Coffee  3 CHF\n  Assets:Cash:", 3)]
+fn should_reject_empty_account_component(#[case] input: &str, #[case] line_number: u32) {
+    let error = parse::<f64>(input).unwrap_err();
+    assert_eq!(error.code(), Code::InvalidAccount);
+    assert_eq!(error.line_number(), line_number);
+    assert!(
+        error.to_string().ends_with("(empty account component)"),
+        "{error}"
+    );
+}
+
+#[rstest]
+fn lenient_accounts_should_keep_the_account_as_written_with_a_warning() {
+    let input = r#"
+2023-05-22 open Assets:Bank:
+2023-05-23 * "Coffee"
+  Expenses:Coffee  3 CHF
+  Assets::Cash
+"#;
+    let options = ParseOptions::default().lenient_accounts(true);
+    let file = parse_with_options::<f64>(input, &options).unwrap();
+    assert_eq!(file.directives.len(), 2);
+    let DirectiveContent::Open(open) = &file.directives[0].content else {
+        panic!("was not an open: {:?}", file.directives[0]);
+    };
+    assert_eq!(open.account.as_str(), "Assets:Bank:");
+    let warnings: Vec<(u32, &str)> = file
+        .warnings
+        .iter()
+        .map(|warning| match warning.kind() {
+            WarningKind::InvalidAccount(account) => (warning.line_number(), account.as_str()),
+            kind => panic!("unexpected warning: {kind:?}"),
+        })
+        .collect();
+    assert_eq!(warnings, [(2, "Assets:Bank:"), (3, "Assets::Cash")]);
+    assert_eq!(file.warnings[1].code(), Code::InvalidAccount);
+}
+
+#[rstest]
+#[case::valid("Assets:Bank", None)]
+#[case::digits("Assets:2023:Bank-Account", None)]
+#[case::trailing_colon("Assets:Bank:", Some("empty account component"))]
+#[case::double_colon("Assets::Bank", Some("empty account component"))]
+#[case::leading_colon(":Assets:Bank", Some("empty account component"))]
+#[case::lone_root("Assets", Some("missing account component after the account type"))]
+#[case::lowercase(
+    "Assets:bank",
+    Some("account component must start with an uppercase letter or a digit")
+)]
+#[case::space("Assets:My Bank", Some("invalid character in account name"))]
+#[case::empty("", Some("empty account name"))]
+fn account_try_new_should_validate_the_name(#[case] name: &str, #[case] reason: Option<&str>) {
+    match (Account::try_new(name), reason) {
+        (Ok(account), None) => assert_eq!(account.as_str(), name),
+        (Err(error), Some(reason)) => {
+            assert_eq!(error.code(), Code::InvalidAccount);
+            assert!(
+                error.to_string().ends_with(&format!("({reason})")),
+                "{error}"
+            );
+        }
+        (result, _) => panic!("unexpected result for {name:?}: {result:?}"),
+    }
+}
+
+#[rstest]
+#[case::after_amount("  Expenses:Food  10 USD #lunch", "#lunch", 25)]
+#[case::after_cost("  Assets:Stock  1 HOOL {10 USD} #lunch", "#lunch", 33)]
+#[case::after_price("  Expenses:Food  10 EUR @ 1.1 USD #lunch", "#lunch", 35)]
+#[case::after_total_price("  Expenses:Food  10 EUR @@ 11 USD   #lunch", "#lunch", 37)]
+#[case::without_amount("  Expenses:Food #lunch", "#lunch", 17)]
+#[case::link("  Expenses:Food  10 USD ^invoice", "^invoice", 25)]
+#[case::followed_by_comment("  Expenses:Food  10 USD #lunch ; with Bob", "#lunch", 25)]
+fn should_reject_tags_on_postings(#[case] posting: &str, #[case] tag: &str, #[case] column: u32) {
+    let input = format!("2023-05-22 * \"Lunch\"\n{posting}\n  Assets:Cash");
+    let error = parse::<f64>(&input).unwrap_err();
+    assert_eq!(error.code(), Code::TagOnPosting);
+    assert_eq!(error.line_number(), 2);
+    assert_eq!(error.column(), Some(column));
+    let kind = if tag.starts_with('#') {
+        "tags"
+    } else {
+        "links"
+    };
+    assert!(
+        error.to_string().ends_with(&format!(
+            "({kind} are not allowed on postings; move {tag} to the transaction header)"
+        )),
+        "{error}"
+    );
+    assert_eq!(
+        input.parse::<Directive<f64>>().unwrap_err().code(),
+        Code::TagOnPosting
+    );
+}
+
+#[rstest]
+#[case::in_comment("  Expenses:Food  10 USD ; #lunch")]
+#[case::in_comment_without_amount("  Expenses:Food ;#lunch")]
+#[case::in_metadata_string("  Expenses:Food  10 USD\n    note: \"#lunch\"")]
+fn should_accept_tag_like_text_in_comments_and_strings(#[case] posting: &str) {
+    let input = format!("2023-05-22 * \"Lunch\"\n{posting}\n  Assets:Cash");
+    let file = parse::<f64>(&input).unwrap();
+    assert!(file.warnings.is_empty(), "{:?}", file.warnings);
+}
+
+#[rstest]
+fn strip_posting_tags_should_remove_them_with_a_warning() {
+    let input = r#"
+2023-05-22 * "Lunch" #work
+  Expenses:Food  10 USD #lunch ^receipt
+  Assets:Cash
+"#;
+    let options = ParseOptions::default().strip_posting_tags(true);
+    let file = parse_with_options::<f64>(input, &options).unwrap();
+    let expected = parse::<f64>(&input.replace(" #lunch ^receipt", "")).unwrap();
+    assert_eq!(file.directives, expected.directives);
+    let warnings: Vec<String> = file.warnings.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        warnings,
+        [
+            "warning[E007]: `#lunch` removed from the posting at line: 3 (only transactions can have tags and links)",
+            "warning[E007]: `^receipt` removed from the posting at line: 3 (only transactions can have tags and links)",
+        ]
+    );
+}
+
+#[rstest]
+#[case::capitalized("2023-05-22 Open Assets:Cash", 1, "open")]
+#[case::uppercase(
+    "2023-05-22 TXN \"Lunch\"\n  Assets:Cash  -10 USD\n  Expenses:Food",
+    1,
+    "txn"
+)]
+#[case::missing_letter("2023-05-22 blance Assets:Cash 10 USD", 1, "balance")]
+#[case::extra_letter("2023-05-22 closee Assets:Cash", 1, "close")]
+#[case::on_second_line(
+    "2023-05-21 open Assets:Cash\n2023-05-22 Pad Assets:Cash Equity:Opening",
+    2,
+    "pad"
+)]
+fn should_hint_at_the_likely_keyword_of_a_misspelled_one(
+    #[case] input: &str,
+    #[case] expected_line: u32,
+    #[case] expected_keyword: &str,
+) {
+    let error = parse::<f64>(input).unwrap_err();
+    assert_eq!(error.code(), Code::MisspelledKeyword);
+    assert_eq!(error.line_number(), expected_line);
+    assert!(
+        error
+            .to_string()
+            .ends_with(&format!("(did you mean `{expected_keyword}`?)")),
+        "{error}"
+    );
+}
+
+#[rstest]
+fn should_not_hint_at_a_keyword_for_an_unrelated_word() {
+    let error = parse::<f64>("2023-05-22 foobar Assets:Cash").unwrap_err();
+    assert_eq!(error.code(), Code::InvalidSyntax);
+    assert_eq!(
+        error.to_string(),
+        "error[E001]: Invalid beancount syntax at line: 1"
+    );
+}
+
+#[rstest]
+fn case_insensitive_keywords_should_accept_keywords_with_another_case() {
+    let input = r#"
+2023-05-22 Open Assets:Cash
+2023-05-23 TXN "Lunch"
+  Assets:Cash  -10 USD
+  Expenses:Food
+"#;
+    let options = ParseOptions::default().case_insensitive_keywords(true);
+    let file = parse_with_options::<f64>(input, &options).unwrap();
+    let expected = parse::<f64>(&input.replace("Open", "open").replace("TXN", "txn")).unwrap();
+    assert_eq!(file.directives, expected.directives);
+    let warnings: Vec<String> = file.warnings.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        warnings,
+        [
+            "warning[E009]: Keyword `Open` read as `open` at line: 2 (beancount keywords are lowercase)",
+            "warning[E009]: Keyword `TXN` read as `txn` at line: 3 (beancount keywords are lowercase)",
+        ]
+    );
+}
+
+#[rstest]
+fn case_insensitive_keywords_should_still_reject_other_misspellings() {
+    let options = ParseOptions::default().case_insensitive_keywords(true);
+    let error =
+        parse_with_options::<f64>("2023-05-22 blance Assets:Cash 10 USD", &options).unwrap_err();
+    assert_eq!(error.code(), Code::MisspelledKeyword);
+    assert_eq!(
+        "2023-05-22 Open Assets:Cash"
+            .parse::<Directive<f64>>()
+            .unwrap_err()
+            .code(),
+        Code::MisspelledKeyword
+    );
+}
+
+#[rstest]
+#[case::second_currency("  Assets:Cash  10 USD CHF", "unexpected second currency `CHF`", 23)]
+#[case::second_number("  Assets:Cash  10 20 USD", "unexpected second number `20`", 19)]
+#[case::after_price(
+    "  Assets:Cash  10 USD @ 3 EUR CHF",
+    "unexpected second currency `CHF`",
+    31
+)]
+#[case::after_cost(
+    "  Assets:Cash  10 USD {2 PLN} CHF",
+    "unexpected second currency `CHF`",
+    31
+)]
+#[case::followed_by_comment(
+    "  Assets:Cash  10 USD CHF ; note",
+    "unexpected second currency `CHF`",
+    23
+)]
+fn should_reject_stray_tokens_on_postings(
+    #[case] posting: &str,
+    #[case] hint: &str,
+    #[case] column: u32,
+) {
+    let input = format!("2023-05-20 *\n{posting}\n  Income:Gift");
+    let error = parse::<f64>(&input).unwrap_err();
+    assert_eq!(error.code(), Code::StrayPostingToken);
+    assert_eq!(error.line_number(), 2);
+    assert_eq!(error.column(), Some(column));
+    assert!(error.to_string().ends_with(&format!("({hint})")), "{error}");
+    assert_eq!(
+        input.parse::<Directive<f64>>().unwrap_err().code(),
+        Code::StrayPostingToken
+    );
+}
+
+#[rstest]
+#[case::cost_price_and_comment("  Assets:Cash  10 USD {2 PLN} @ 3 EUR ; comment")]
+#[case::total_price("  Assets:Cash  10 USD @@ 30 EUR")]
+#[case::expression("  Assets:Cash  (10 + 20) USD")]
+fn should_accept_postings_without_stray_tokens(#[case] posting: &str) {
+    let input = format!("2023-05-20 *\n{posting}\n  Income:Gift");
+    let file = parse::<f64>(&input).unwrap();
+    assert!(file.warnings.is_empty(), "{:?}", file.warnings);
+}
+
+#[rstest]
+fn drop_stray_tokens_should_remove_them_with_a_warning() {
+    let input = r#"
+2023-05-20 * "Gift"
+  Assets:Cash  10 USD CHF
+  Assets:Bank  10 20 USD
+  Income:Gift
+"#;
+    let options = ParseOptions::default().drop_stray_tokens(true);
+    let file = parse_with_options::<f64>(input, &options).unwrap();
+    let expected = parse::<f64>(&input.replace(" CHF", "").replace(" 20", "")).unwrap();
+    assert_eq!(file.directives, expected.directives);
+    let warnings: Vec<String> = file.warnings.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        warnings,
+        [
+            "warning[E010]: Token removed from the posting at line: 3 (unexpected second currency `CHF`)",
+            "warning[E010]: Token removed from the posting at line: 4 (unexpected second number `20`)",
+        ]
+    );
+}
+
+#[rstest]
+#[case::lot_label("  Assets:Cash  10 HOOL {2 USD, \"lot\"}", "{2 USD, \"lot\"}", 24)]
+#[case::total_cost("  Assets:Cash  10 HOOL {{20 PLN}}", "{{20 PLN}}", 24)]
+#[case::compound_cost("  Assets:Cash  10 HOOL {2 # 3 PLN}", "{2 # 3 PLN}", 24)]
+#[case::followed_by_comment("  Assets:Cash  10 HOOL {{20 PLN}}  ; note", "{{20 PLN}}", 24)]
+#[case::invalid_price("  Assets:Cash  10 HOOL @ oops", "@ oops", 24)]
+#[case::after_stray_token("  Assets:Cash  10 HOOL CHF {{20 PLN}}", "{{20 PLN}}", 28)]
+fn should_reject_unsupported_cost_syntax_even_when_dropping_stray_tokens(
+    #[case] posting: &str,
+    #[case] text: &str,
+    #[case] column: u32,
+    #[values(false, true)] drop_stray_tokens: bool,
+) {
+    let input = format!("2023-05-20 *\n{posting}\n  Income:Gift");
+    let options = ParseOptions::default().drop_stray_tokens(drop_stray_tokens);
+    let error = parse_with_options::<f64>(&input, &options).unwrap_err();
+    assert_eq!(error.code(), Code::UnsupportedCostSyntax);
+    assert_eq!(error.line_number(), 2);
+    assert_eq!(error.column(), Some(column));
+    assert!(
+        error
+            .to_string()
+            .ends_with(&format!("(unsupported cost or price syntax `{text}`)")),
+        "{error}"
+    );
+    assert_eq!(
+        input.parse::<Directive<f64>>().unwrap_err().code(),
+        Code::UnsupportedCostSyntax
+    );
+}
+
+const BROKEN_IN_THE_MIDDLE: &str = r#"2023-05-20 open Assets:Cash
+  id: 1
+  oops
+  owner: "me"
+2023-05-21 * "Groceries"
+  Expenses:Food    10 CHF
+  ?? Expenses:Drinks  5 CHF
+  Assets:Cash
+2023-05-22 close Assets:Cash
+"#;
+
+#[rstest]
+#[case::posting_flag(
+    "  Expenses:Food  10 CHF\n  !! Expenses:Drinks  5 CHF\n  Assets:Cash",
+    3,
+    "`!! Expenses:Drinks  5 CHF` is neither metadata nor a posting"
+)]
+#[case::posting_account(
+    "  Expenses:Food  10 CHF\n  expenses:drinks  5 CHF\n  Assets:Cash",
+    3,
+    "`expenses:drinks  5 CHF` is neither metadata nor a posting"
+)]
+#[case::posting_metadata(
+    "  Expenses:Food  10 CHF\n    receipt: ???\n  Assets:Cash",
+    3,
+    "`receipt: ???` is neither metadata nor a posting"
+)]
+#[case::transaction_metadata(
+    "  id: 1\n  id 2\n  Expenses:Food  10 CHF\n  Assets:Cash",
+    3,
+    "`id 2` is neither metadata nor a posting"
+)]
+fn should_reject_invalid_lines_in_the_middle_of_a_transaction(
+    #[case] body: &str,
+    #[case] line_number: u32,
+    #[case] hint: &str,
+) {
+    let directive = format!("2023-05-20 * \"Groceries\"\n{body}\n");
+    let input = format!("{directive}2023-05-21 open Assets:Cash\n");
+    let error = parse::<f64>(&input).unwrap_err();
+    assert_eq!(error.code(), Code::InvalidIndentedLine);
+    assert_eq!(error.line_number(), line_number);
+    assert!(error.to_string().ends_with(&format!("({hint})")), "{error}");
+    assert_eq!(
+        directive.parse::<Directive<f64>>().unwrap_err().code(),
+        Code::InvalidIndentedLine
+    );
+}
+
+#[rstest]
+fn should_reject_invalid_lines_in_the_middle_of_a_file() {
+    let error = parse::<f64>(BROKEN_IN_THE_MIDDLE).unwrap_err();
+    assert_eq!(error.code(), Code::InvalidIndentedLine);
+    assert_eq!(error.line_number(), 3);
+    assert_eq!(error.column(), Some(3));
+    let entries: Vec<_> = parse_iter::<f64>(BROKEN_IN_THE_MIDDLE).collect();
+    assert!(
+        matches!(&entries[..], [Err(error)] if error.line_number() == 3),
+        "{entries:?}"
+    );
+}
+
+#[rstest]
+fn skip_invalid_lines_should_skip_them_with_a_warning() {
+    let options = ParseOptions::default().skip_invalid_lines(true);
+    let file = parse_with_options::<f64>(BROKEN_IN_THE_MIDDLE, &options).unwrap();
+    let expected = parse::<f64>(
+        &BROKEN_IN_THE_MIDDLE
+            .replace("  oops\n", "")
+            .replace("  ?? Expenses:Drinks  5 CHF\n", ""),
+    )
+    .unwrap();
+    let contents = |file: &BeancountFile<f64>| -> Vec<_> {
+        file.directives
+            .iter()
+            .map(|d| (d.content.clone(), d.metadata.clone()))
+            .collect()
+    };
+    assert_eq!(contents(&file), contents(&expected));
+    assert_eq!(file.directives[0].metadata.len(), 2);
+    let warnings: Vec<String> = file.warnings.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        warnings,
+        [
+            "warning[E014]: Line skipped at line: 3 (`oops` is neither metadata nor a posting)",
+            "warning[E014]: Line skipped at line: 7 (`?? Expenses:Drinks  5 CHF` is neither metadata nor a posting)",
+        ]
+    );
+}
+
+#[rstest]
+#[case::comment("  ; comment")]
+#[case::blank("   ")]
+#[case::empty("")]
+fn should_accept_empty_indented_lines_in_a_transaction(#[case] line: &str) {
+    let input = format!("2023-05-20 *\n  Expenses:Food  3 CHF\n{line}\n  Assets:Cash\n");
+    let DirectiveContent::Transaction(trx) = parse_single_directive(&input).content else {
+        panic!("was not a transaction");
+    };
+    assert_eq!(trx.postings.len(), 2);
+}
+
+#[rstest]
+#[case::two_spaces("  Expenses:Food  3 CHF\n  Assets:Cash", Some(Indent::Spaces(2)))]
+#[case::four_spaces("    Expenses:Food  3 CHF\n    Assets:Cash", Some(Indent::Spaces(4)))]
+#[case::tab("\tExpenses:Food  3 CHF\n\tAssets:Cash", Some(Indent::Tabs(1)))]
+#[case::first_posting("   Expenses:Food  3 CHF\n\tAssets:Cash", Some(Indent::Spaces(3)))]
+#[case::after_metadata(
+    "\tid: 1\n  Expenses:Food  3 CHF\n  Assets:Cash",
+    Some(Indent::Spaces(2))
+)]
+#[case::mixed(" \tExpenses:Food  3 CHF\n  Assets:Cash", None)]
+#[case::no_posting("  id: 1", None)]
+fn transaction_should_record_the_indent_of_its_postings(
+    #[case] body: &str,
+    #[case] expected: Option<Indent>,
+) {
+    let directive: Directive<f64> = format!("2023-05-20 * \"Coffee\"\n{body}").parse().unwrap();
+    let DirectiveContent::Transaction(trx) = &directive.content else {
+        panic!("was not a transaction: {directive:?}");
+    };
+    assert_eq!(trx.indent(), expected);
+}
+
+#[rstest]
+fn extend_from_str_should_continue_with_the_state_of_the_original_input() {
+    let mut file: BeancountFile<f64> =
+        "2023-01-01 open Assets:Cash\npushtag #trip\n2023-01-02 * \"Hotel\"\n"
+            .parse()
+            .unwrap();
+    file.extend_from_str("2023-01-03 * \"Restaurant\"\n  Expenses:Food  10 CHF\n  Assets:Cash\n")
+        .unwrap();
+    file.extend_from_str("poptag #trip\n2023-01-04 * \"Home\"")
+        .unwrap();
+    let summary: Vec<(u32, usize)> = file
+        .directives
+        .iter()
+        .map(|d| {
+            let tags = match &d.content {
+                DirectiveContent::Transaction(trx) => trx.tags.len(),
+                _ => 0,
+            };
+            (d.line_number, tags)
+        })
+        .collect();
+    assert_eq!(summary, [(1, 0), (3, 1), (4, 1), (8, 0)]);
+}
+
+#[rstest]
+fn extend_from_str_should_leave_the_file_unchanged_on_error() {
+    let mut file: BeancountFile<f64> = "pushtag #trip\n2023-01-01 open Assets:Cash\n"
+        .parse()
+        .unwrap();
+    let error = file
+        .extend_from_str("2023-01-02 * \"Hotel\"\npoptag #trip\n2023-01-03 open oops\n")
+        .unwrap_err();
+    assert_eq!(error.line_number(), 5);
+    assert_eq!(file.directives.len(), 1);
+    file.extend_from_str("2023-01-04 * \"Tagged\"").unwrap();
+    let DirectiveContent::Transaction(trx) = &file.directives[1].content else {
+        panic!("was not a transaction: {:?}", file.directives[1]);
+    };
+    assert!(trx.tags.contains("trip"));
+    assert_eq!(file.directives[1].line_number, 3);
+}
+
+#[rstest]
+fn extend_from_str_should_count_the_posting_lines_from_the_start_of_the_file() {
+    let mut file: BeancountFile<f64> = "2023-01-01 open Assets:Cash\n\n\n".parse().unwrap();
+    file.extend_from_str("2023-01-02 * \"Coffee\"\n  Expenses:Food  3 CHF\n  Assets:Cash\n")
+        .unwrap();
+    assert_eq!(file.directives[1].line_number, 4);
+    let DirectiveContent::Transaction(trx) = &file.directives[1].content else {
+        panic!("was not a transaction: {:?}", file.directives[1]);
+    };
+    let lines: Vec<u32> = trx.postings.iter().map(|p| p.line_number).collect();
+    assert_eq!(lines, [5, 6]);
+}
+
+#[rstest]
+fn extend_from_str_should_use_the_options_of_the_original_parse() {
+    let options = ParseOptions::default().strict_metadata(true);
+    let mut file = parse_with_options::<f64>("2023-01-01 open Assets:Cash\n", &options).unwrap();
+    let error = file
+        .extend_from_str("2023-01-02 open Assets:Bank\n  id: 1\n  id: 2\n")
+        .unwrap_err();
+    assert_eq!(error.code(), Code::DuplicateMetadataKey);
+    assert_eq!(error.line_number(), 4);
+}
+
+#[rstest]
+fn parse_lenient_should_return_the_valid_directives_and_all_the_errors() {
+    let input = r#"
+2023-01-01 open Assets:Cash
+2023-01-02 * "Invalid posting"
+  Expenses:Food  10 CHF
+  Assets:Cash    oops
+
+2023-01-03 open Assets:Bank
+2023-01-04 * oops
+  Assets:Bank  10 CHF
+2023-01-05 close Assets:Bank
+
+  garbage
+2023-01-06 open Assets:"#
+        .trim();
+    let (file, errors) = parse_lenient::<f64>(input);
+    let directives: Vec<(u32, Date)> = file
+        .directives
+        .iter()
+        .map(|d| (d.line_number, d.date))
+        .collect();
+    assert_eq!(
+        directives,
+        [
+            (1, Date::new(2023, 1, 1)),
+            (6, Date::new(2023, 1, 3)),
+            (9, Date::new(2023, 1, 5))
+        ]
+    );
+    let errors: Vec<(u32, Code)> = errors.iter().map(|e| (e.line_number(), e.code())).collect();
+    assert_eq!(
+        errors,
+        [
+            (4, Code::InvalidIndentedLine),
+            (7, Code::InvalidSyntax),
+            (11, Code::InvalidIndentedLine),
+            (12, Code::InvalidAccount)
+        ]
+    );
+    let (file, errors) =
+        parse_lenient::<f64>("2023-01-01 open Assets:A\n\n  garbage\n2023-01-03 open Assets:B\n");
+    let lines: Vec<u32> = file.directives.iter().map(|d| d.line_number).collect();
+    assert_eq!(lines, [1, 4]);
+    assert_eq!(errors.len(), 1);
+}
+
+#[rstest]
+fn parse_lenient_should_count_the_posting_lines_from_the_start_of_the_input() {
+    let input =
+        "2023-01-01 open oops\n2023-01-02 * \"Coffee\"\n  Expenses:Food  3 CHF\n  Assets:Cash\n";
+    let (file, errors) = parse_lenient::<f64>(input);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(file.directives[0].line_number, 2);
+    let DirectiveContent::Transaction(trx) = &file.directives[0].content else {
+        panic!("was not a transaction: {:?}", file.directives[0]);
+    };
+    let lines: Vec<u32> = trx.postings.iter().map(|p| p.line_number).collect();
+    assert_eq!(lines, [3, 4]);
+}
+
+#[rstest]
+fn parse_lenient_should_be_the_same_as_parse_for_valid_input(
+    #[values("", COMMENTS, SIMPLE, OFFICIAL)] input: &str,
+) {
+    let (file, errors) = parse_lenient::<f64>(input);
+    assert!(errors.is_empty(), "{errors:?}");
+    assert_eq!(file.directives, parse::<f64>(input).unwrap().directives);
+}
+
+#[rstest]
+fn parse_lenient_should_keep_the_tags_pushed_before_an_error() {
+    let input = "pushtag #trip\n2023-01-01 open oops\n2023-01-02 * \"Hotel\"\npoptag #trip\n";
+    let (mut file, errors) = parse_lenient::<f64>(input);
+    assert_eq!(errors.len(), 1);
+    let DirectiveContent::Transaction(trx) = &file.directives[0].content else {
+        panic!("was not a transaction: {:?}", file.directives[0]);
+    };
+    assert!(trx.tags.contains("trip"));
+    file.extend_from_str("2023-01-03 close Assets:Cash")
+        .unwrap();
+    assert_eq!(file.directives[1].line_number, 5);
+}
+
+#[rstest]
+fn parse_lenient_should_skip_the_entries_rejected_by_the_options() {
+    let input = "2023-01-01 open Assets:Cash\n  id: 1\n  id: 2\n2023-01-02 open Assets:Bank\n";
+    let options = ParseOptions::default().strict_metadata(true);
+    let (file, errors) = parse_lenient_with_options::<f64>(input, &options);
+    assert_eq!(file.directives.len(), 1);
+    assert_eq!(file.directives[0].line_number, 4);
+    assert_eq!(errors[0].code(), Code::DuplicateMetadataKey);
+    assert_eq!(errors[0].line_number(), 3);
+}
+
+fn ignored_lines(input: &str) -> Vec<(u32, LineKind, String)> {
+    let options = ParseOptions::default().capture_ignored_lines(true);
+    parse_iter_with_options::<f64>(input, &options)
+        .filter_map(|entry| match entry.unwrap() {
+            Entry::Ignored(line) => Some((line.line_number, line.kind, line.text)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[rstest]
+fn should_capture_the_ignored_lines_with_their_kind() {
+    let input = "* Banking\n:PROPERTIES:\n#comment\n; Cash\n2023-01-01 open Assets:Cash\n  ; inside\n  id: 1\n\n; after\n  ; indented after\nHello World\n2023-01-02 oops\noptions \"title\"\n";
+    assert_eq!(
+        ignored_lines(input),
+        [
+            (1, LineKind::Ignorable, "* Banking".into()),
+            (2, LineKind::Ignorable, ":PROPERTIES:".into()),
+            (3, LineKind::Ignorable, "#comment".into()),
+            (4, LineKind::Comment, "; Cash".into()),
+            (9, LineKind::Comment, "; after".into()),
+            (10, LineKind::Comment, "  ; indented after".into()),
+            (11, LineKind::Unrecognized, "Hello World".into()),
+            (12, LineKind::Unrecognized, "2023-01-02 oops".into()),
+            (13, LineKind::Unrecognized, "options \"title\"".into()),
+        ]
+    );
+}
+
+#[rstest]
+fn should_not_capture_the_ignored_lines_by_default() {
+    let input = "* Banking\n; Cash\n2023-01-01 open Assets:Cash\n";
+    let entries: Vec<Entry<f64>> = parse_iter(input).collect::<Result<_, _>>().unwrap();
+    assert_eq!(entries.len(), 1);
+}
+
+#[rstest]
+fn trailing_comments_should_not_change_the_directive() {
+    let input = "2023-01-01 * \"Coffee\"\n  Expenses:Food  3 CHF\n  Assets:Cash\n; end\n\n";
+    let with_comment = parse_single_directive(input);
+    let without_comment = parse_single_directive(&input[..input.find(';').unwrap()]);
+    assert_eq!(with_comment, without_comment);
+    assert_eq!(
+        ignored_lines(input),
+        [(4, LineKind::Comment, "; end".into())]
+    );
+    let directive: Directive<f64> = input.parse().unwrap();
+    assert_eq!(directive, with_comment);
+}
+
+#[rstest]
+fn hash_lines_should_never_be_tags(#[values("#trip", "#trip\n", "# trip")] line: &str) {
+    let input = format!("pushtag #work\n{line}\n2023-01-01 * \"Hotel\"\npoptag #work\n");
+    let file: BeancountFile<f64> = input.parse().unwrap();
+    assert!(file.warnings.is_empty(), "{:?}", file.warnings);
+    let DirectiveContent::Transaction(trx) = &file.directives[0].content else {
+        panic!("was not a transaction: {:?}", file.directives[0]);
+    };
+    let tags: Vec<&str> = trx.tags.iter().map(|tag| tag.as_str()).collect();
+    assert_eq!(tags, ["work"]);
+}
+
+#[rstest]
+fn should_warn_about_unrecognized_lines() {
+    let input = "* Banking\nHello World\n2023-01-01 open Assets:Cash\n";
+    let file: BeancountFile<f64> = input.parse().unwrap();
+    assert_eq!(file.directives.len(), 1);
+    assert_eq!(file.warnings.len(), 1);
+    let warning = &file.warnings[0];
+    assert_eq!(warning.code(), Code::UnrecognizedLine);
+    assert_eq!(warning.line_number(), 2);
+    assert!(matches!(warning.kind(), WarningKind::UnrecognizedLine(line) if line == "Hello World"));
+    let options = ParseOptions::default().suppress(Code::UnrecognizedLine);
+    let file = parse_with_options::<f64>(input, &options).unwrap();
+    assert!(file.warnings.is_empty());
+}
+
+fn date_range(start: &str, end: &str) -> ParseOptions {
+    ParseOptions::default().date_range(start.parse().unwrap(), end.parse().unwrap())
+}
+
+#[rstest]
+#[case::one_month("2023-03-01", "2023-04-01")]
+#[case::everything("2000-01-01", "2100-01-01")]
+#[case::nothing("2030-01-01", "2031-01-01")]
+fn date_range_should_give_same_directives_as_filtering_after_parsing(
+    #[case] start: &str,
+    #[case] end: &str,
+) {
+    let options = date_range(start, end);
+    let range = start.parse().unwrap()..end.parse().unwrap();
+    let expected: Vec<Directive<f64>> = parse::<f64>(OFFICIAL)
+        .unwrap()
+        .directives
+        .into_iter()
+        .filter(|d| range.contains(&d.date))
+        .collect();
+    let actual = parse_with_options::<f64>(OFFICIAL, &options)
+        .unwrap()
+        .directives;
+    assert_eq!(actual, expected);
+}
+
+#[rstest]
+fn date_range_should_skip_indented_lines_of_skipped_directives() {
+    let input = r#"
+2023-01-01 * "Before" #trip
+  id: 1
+  Expenses:Food  10 CHF
+    id: 2
+  ; comment
+  Assets:Cash
+2023-02-01 * "In range"
+  Expenses:Food  10 CHF
+  Assets:Cash
+2023-03-01 open Assets:Cash
+  id: 3"#;
+    let file = parse_with_options::<f64>(input, &date_range("2023-02-01", "2023-03-01")).unwrap();
+    assert_eq!(file.directives.len(), 1, "{:?}", file.directives);
+    assert_eq!(file.directives[0].line_number, 8);
+}
+
+#[rstest]
+fn date_range_should_still_apply_tags_pushed_before_the_range() {
+    let input = r#"
+pushtag #trip
+2023-01-01 * "Before"
+2023-02-01 * "In range"
+poptag #trip
+2023-02-02 * "Untagged"
+"#;
+    let file = parse_with_options::<f64>(input, &date_range("2023-02-01", "2023-03-01")).unwrap();
+    let tags: Vec<usize> = file
+        .directives
+        .iter()
+        .map(|d| match &d.content {
+            DirectiveContent::Transaction(trx) => trx.tags.len(),
+            _ => unreachable!(),
+        })
+        .collect();
+    assert_eq!(tags, vec![1, 0]);
+}
+
+#[rstest]
+fn date_range_should_keep_declarations_when_requested(#[values(false, true)] keep: bool) {
+    let input = r#"
+2023-01-01 open Assets:Cash
+2023-01-01 commodity CHF
+2023-01-01 * "Before"
+2023-02-01 * "In range"
+2023-12-31 close Assets:Cash
+2023-12-31 event "location" "home"
+"#;
+    let options = date_range("2023-02-01", "2023-03-01").keep_declarations(keep);
+    let lines: Vec<u32> = parse_with_options::<f64>(input, &options)
+        .unwrap()
+        .directives
+        .iter()
+        .map(|d| d.line_number)
+        .collect();
+    let expected = if keep { vec![2, 3, 5, 6] } else { vec![5] };
+    assert_eq!(lines, expected);
+}
+
 #[rstest]
 fn should_reject_invalid_input(
     #[values(
@@ -452,6 +2097,8 @@ fn should_reject_invalid_input(
         "2022-05-18 open Assets:Cash\n  Title: \"hello\"",
         "2020-04-10 balance Assets:US:BofA:Checking2473.33 USD",
         "2020-04-10 balance Assets:US:BofA:Checking",
+        "2023-01-01 balance Assets:Cash 100.00",
+        "2023-01-01 balance Assets:Cash 100.00 ~ 0.05",
         "2020-12-09 event \"location\"\"New Metropolis\"",
         "2020-12-09 event\"location\" \"New Metropolis\"",
         "2020-12-09 event \"location\"",
@@ -464,7 +2111,13 @@ fn should_reject_invalid_input(
         "2022-08-26 price 121.03 USD",
         "2014-06-01 pad Assets:BofA:CheckingEquity:Opening-Balances",
         "2014-06-01 padAssets:BofA:Checking Equity:Opening-Balances",
-        r#"include"./a/path/to/file.beancount""#
+        "2014-06-01 pad Assets:BofA:Checking",
+        "2014-06-01 pad Assets:BofA:Checking ",
+        r#"include"./a/path/to/file.beancount""#,
+        "plugin",
+        "plugin\"auto_accounts\"",
+        "plugin \"auto_accounts\"\"config\"",
+        "plugin \"auto_accounts\" config"
     )]
     input: &str,
 ) {
@@ -0,0 +1,216 @@
+#![allow(missing_docs)]
+
+use rstest::rstest;
+
+use beancount_parser::{
+    lint::DirectiveKind,
+    period::{self, ActivityStats, Period, PeriodKey, Weekday},
+    BeancountFile, Date,
+};
+
+fn date(input: &str) -> Date {
+    input.parse().unwrap()
+}
+
+#[rstest]
+#[case::middle_of_year("2023-03-01", "2023-W09")]
+#[case::last_week_of_long_year("2020-12-31", "2020-W53")]
+#[case::sunday_in_previous_year_week("2021-01-03", "2020-W53")]
+#[case::first_monday_of_year("2021-01-04", "2021-W01")]
+#[case::december_in_next_year_week("2019-12-30", "2020-W01")]
+#[case::january_in_previous_year_week("2022-01-01", "2021-W52")]
+#[case::last_day_of_year("2024-12-31", "2025-W01")]
+fn iso_week_should_be_numbered_like_iso_8601(#[case] input: &str, #[case] expected: &str) {
+    let key = PeriodKey::of(date(input), Period::Week(Weekday::Monday));
+    assert_eq!(key.to_string(), expected);
+    assert_eq!(Weekday::of(key.start()), Weekday::Monday);
+}
+
+#[rstest]
+#[case::sunday_start(Weekday::Sunday, "2023-01-07", "2023-01-01", "2023-W01")]
+#[case::saturday_start(Weekday::Saturday, "2023-01-07", "2023-01-07", "2023-W02")]
+#[case::sunday_start_at_year_boundary(Weekday::Sunday, "2022-01-01", "2021-12-26", "2021-W52")]
+fn week_should_start_on_the_given_day(
+    #[case] first_day: Weekday,
+    #[case] input: &str,
+    #[case] expected_start: &str,
+    #[case] expected: &str,
+) {
+    let key = PeriodKey::of(date(input), Period::Week(first_day));
+    assert_eq!(key.start(), date(expected_start));
+    assert_eq!(key.to_string(), expected);
+}
+
+#[rstest]
+#[case::month(Period::Month, "2023-03-15", "2023-03", "2023-03-01", "2023-04")]
+#[case::december(Period::Month, "2023-12-31", "2023-12", "2023-12-01", "2024-01")]
+#[case::quarter(Period::Quarter, "2023-05-20", "2023-Q2", "2023-04-01", "2023-Q3")]
+#[case::last_quarter(Period::Quarter, "2023-11-02", "2023-Q4", "2023-10-01", "2024-Q1")]
+#[case::year(Period::Year, "2023-05-20", "2023", "2023-01-01", "2024")]
+#[case::week(
+    Period::Week(Weekday::Monday),
+    "2020-12-31",
+    "2020-W53",
+    "2020-12-28",
+    "2021-W01"
+)]
+fn period_key_should_have_a_start_a_label_and_a_next_period(
+    #[case] period: Period,
+    #[case] input: &str,
+    #[case] expected: &str,
+    #[case] expected_start: &str,
+    #[case] expected_next: &str,
+) {
+    let key = PeriodKey::of(date(input), period);
+    assert_eq!(key.to_string(), expected);
+    assert_eq!(key.start(), date(expected_start));
+    assert_eq!(key.period(), period);
+    assert_eq!(key.next().unwrap().to_string(), expected_next);
+}
+
+#[rstest]
+fn group_by_period_should_return_ordered_buckets_keeping_the_order_of_the_items() {
+    let input = r#"
+2023-03-02 open Assets:Cash
+2022-12-31 open Assets:Bank
+2023-01-15 event "location" "Paris"
+2023-03-01 event "location" "Rome"
+"#;
+    let file: BeancountFile<f64> = input.parse().unwrap();
+    let groups = period::group_by_period(&file.directives, Period::Month);
+    let summary: Vec<(String, Vec<u32>)> = groups
+        .iter()
+        .map(|(key, directives)| {
+            (
+                key.to_string(),
+                directives.iter().map(|d| d.line_number).collect(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("2022-12".into(), vec![3]),
+            ("2023-01".into(), vec![4]),
+            ("2023-03".into(), vec![2, 5]),
+        ]
+    );
+}
+
+#[rstest]
+fn fill_gaps_should_add_the_empty_periods_across_years() {
+    let dates = [date("2022-11-20"), date("2023-02-01")];
+    let mut groups = period::group_by_period(dates, Period::Month);
+    period::fill_gaps(&mut groups);
+    let summary: Vec<(String, usize)> = groups
+        .iter()
+        .map(|(key, dates)| (key.to_string(), dates.len()))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("2022-11".into(), 1),
+            ("2022-12".into(), 0),
+            ("2023-01".into(), 0),
+            ("2023-02".into(), 1),
+        ]
+    );
+}
+
+#[rstest]
+fn fill_gaps_should_leave_empty_and_single_groups_unchanged(
+    #[values(&[][..], &["2023-05-20"][..])] dates: &[&str],
+) {
+    let mut groups =
+        period::group_by_period(dates.iter().map(|d| date(d)), Period::Week(Weekday::Monday));
+    period::fill_gaps(&mut groups);
+    assert_eq!(groups.len(), dates.len());
+}
+
+const YEAR: &str = r#"
+2023-01-01 open Assets:Cash CHF
+2023-01-01 open Expenses:Food
+2023-01-15 * "Groceries"
+  Expenses:Food  30 CHF
+  Assets:Cash
+2023-02-01 price EUR 1.02 CHF
+2023-05-10 * "Restaurant"
+  Expenses:Food  20 EUR @ 1 CHF
+  Assets:Cash
+2023-05-20 * "Groceries"
+  Expenses:Food  10 CHF
+  Assets:Cash
+2023-06-30 balance Assets:Cash -60 CHF
+2023-04-01 event "location" "Zürich"
+2023-12-31 close Expenses:Food
+"#;
+
+#[rstest]
+fn activity_should_summarize_every_month_including_the_empty_ones() {
+    let file: BeancountFile<f64> = YEAR.parse().unwrap();
+    let activity = file.activity(Period::Month);
+    let months: Vec<String> = activity.iter().map(|(key, _)| key.to_string()).collect();
+    assert_eq!(
+        months,
+        [
+            "2023-01", "2023-02", "2023-03", "2023-04", "2023-05", "2023-06", "2023-07", "2023-08",
+            "2023-09", "2023-10", "2023-11", "2023-12"
+        ]
+    );
+    let summary = |index: usize| {
+        let stats = &activity[index].1;
+        let directives: Vec<(&str, usize)> = stats
+            .directives
+            .iter()
+            .map(|(kind, count)| (kind.as_str(), *count))
+            .collect();
+        (directives, stats.postings, stats.accounts, stats.currencies)
+    };
+    assert_eq!(summary(0), (vec![("transaction", 1), ("open", 2)], 2, 2, 1));
+    assert_eq!(summary(1), (vec![("price", 1)], 0, 0, 2));
+    assert_eq!(activity[2].1, ActivityStats::default());
+    assert_eq!(summary(3), (vec![("event", 1)], 0, 0, 0));
+    assert_eq!(summary(4), (vec![("transaction", 2)], 4, 2, 2));
+    assert_eq!(summary(5), (vec![("balance", 1)], 0, 1, 1));
+    assert_eq!(activity[6].1, ActivityStats::default());
+    assert_eq!(summary(11), (vec![("close", 1)], 0, 1, 0));
+    let empty = activity
+        .iter()
+        .filter(|(_, stats)| stats.directives.is_empty());
+    assert_eq!(empty.count(), 6);
+}
+
+#[rstest]
+fn activity_should_not_depend_on_the_order_of_the_directives() {
+    let mut file: BeancountFile<f64> = YEAR.parse().unwrap();
+    let expected = file.activity(Period::Quarter);
+    file.directives.reverse();
+    assert_eq!(file.activity(Period::Quarter), expected);
+    assert_eq!(expected.len(), 4);
+    assert_eq!(expected[0].1.directives[&DirectiveKind::Open], 2);
+}
+
+#[rstest]
+fn activity_of_an_empty_file_should_be_empty() {
+    let file = BeancountFile::<f64>::default();
+    assert!(file.activity(Period::Year).is_empty());
+}
+
+#[cfg(feature = "serde")]
+#[rstest]
+fn activity_should_serialize_with_the_period_labels() {
+    let file: BeancountFile<f64> = YEAR.parse().unwrap();
+    let json = serde_json::to_value(file.activity(Period::Quarter)).unwrap();
+    assert_eq!(
+        json[1],
+        serde_json::json!([
+            "2023-Q2",
+            {
+                "directives": {"transaction": 2, "balance": 1, "event": 1},
+                "postings": 4,
+                "accounts": 2,
+                "currencies": 2
+            }
+        ])
+    );
+}
@@ -0,0 +1,85 @@
+#![allow(missing_docs)]
+
+use rstest::rstest;
+
+use beancount_parser::{BeancountFile, Code, Directive, ResolveOptions};
+
+fn built(input: &str, label: &str) -> Directive<f64> {
+    input
+        .parse::<Directive<f64>>()
+        .unwrap()
+        .with_provenance(label)
+}
+
+#[rstest]
+fn with_provenance_should_record_the_label_and_the_caller() {
+    let directive = built("2023-05-20 open Assets:Cash", "opening rule");
+    let provenance = directive.provenance().unwrap();
+    assert_eq!(provenance.label, "opening rule");
+    assert_eq!(provenance.location.file(), "tests/provenance_spec.rs");
+    assert!(provenance.location.line() < line!());
+}
+
+#[rstest]
+fn parsed_directives_should_not_have_a_provenance() {
+    let file: BeancountFile<f64> = "2023-05-20 open Assets:Cash".parse().unwrap();
+    assert!(file.directives[0].provenance().is_none());
+}
+
+#[rstest]
+fn balance_error_should_report_the_provenance_instead_of_the_line() {
+    let mut file: BeancountFile<f64> = "2023-05-20 open Assets:Cash\n".parse().unwrap();
+    file.directives.push(built(
+        "2023-05-21 balance Assets:Cash 10 CHF",
+        "bank balance",
+    ));
+    let errors = file.verify_balances();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].code(), Code::BalanceMismatch);
+    assert_eq!(errors[0].provenance().unwrap().label, "bank balance");
+    let message = errors[0].to_string();
+    let location = file.directives[1].provenance().unwrap().location;
+    assert_eq!(
+        message,
+        format!("error[E006]: Balance assertion failed in `bank balance` (built at {location}) (expected 10.0 CHF in Assets:Cash, found 0.0 CHF)")
+    );
+}
+
+#[rstest]
+fn resolve_error_should_report_the_provenance() {
+    let mut file: BeancountFile<f64> = "".parse().unwrap();
+    file.directives.push(built(
+        "2023-05-21 *\n  Assets:Cash\n  Expenses:Food",
+        "card rule",
+    ));
+    let ledger = file.resolve(&ResolveOptions::default());
+    assert_eq!(ledger.errors[0].code(), Code::TooManyMissingAmounts);
+    assert_eq!(ledger.errors[0].provenance().unwrap().label, "card rule");
+}
+
+#[rstest]
+fn resolved_postings_should_carry_the_provenance() {
+    let mut file: BeancountFile<f64> = r"
+2023-05-01 open Assets:Cash
+2023-05-01 open Equity:Opening
+2023-05-01 pad Assets:Cash Equity:Opening
+"
+    .parse()
+    .unwrap();
+    file.directives
+        .push(built("2023-05-02 balance Assets:Cash 10 CHF", "statement"));
+    file.directives.push(built(
+        "2023-05-03 *\n  Assets:Cash  -3 CHF\n  Expenses:Food",
+        "card rule",
+    ));
+    let ledger = file.resolve(&ResolveOptions::default());
+    let provenances: Vec<Option<&str>> = ledger
+        .postings
+        .iter()
+        .map(|p| p.provenance.as_ref().map(|p| p.label.as_str()))
+        .collect();
+    assert_eq!(
+        provenances,
+        [None, None, Some("card rule"), Some("card rule")]
+    );
+}
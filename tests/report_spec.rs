@@ -0,0 +1,138 @@
+#![allow(missing_docs)]
+
+use rstest::rstest;
+
+use beancount_parser::{
+    parse, parse_report, Code, FileId, Indent, ParseOptions, ParseReport, Stats,
+};
+
+const OFFICIAL: &str = include_str!("samples/official.beancount");
+
+const INPUT: &str = r#"option "title" "Test"
+include "other.beancount"
+2023-01-01 open Assets:Cash
+2023-01-01 open Expenses:Food
+2023-01-02 * "Groceries"
+  id: 1
+  id: 2
+  Expenses:Food  10 CHF
+  Assets:Cash
+"#;
+
+fn summary<D>(report: &ParseReport<D>) -> String {
+    let mut summary = Vec::new();
+    report.print_summary(&mut summary).unwrap();
+    String::from_utf8(summary).unwrap()
+}
+
+#[rstest]
+fn should_contain_file_and_warnings() {
+    let report: ParseReport<f64> = parse_report(INPUT, &ParseOptions::default());
+    assert!(report.is_ok());
+    let expected = parse::<f64>(INPUT).unwrap();
+    assert_eq!(report.file.directives, expected.directives);
+    assert_eq!(report.warnings, expected.warnings);
+    assert!(report.file.warnings.is_empty());
+}
+
+#[rstest]
+fn should_compute_stats() {
+    let report: ParseReport<f64> = parse_report(INPUT, &ParseOptions::default());
+    let mut expected = Stats::default();
+    expected.lines = 9;
+    expected.bytes = INPUT.len();
+    expected.directives = 3;
+    expected.transactions = 1;
+    expected.postings = 2;
+    expected.accounts = 2;
+    expected.options = 1;
+    expected.includes = 1;
+    expected.indent = Some(Indent::Spaces(2));
+    assert_eq!(report.stats, expected);
+}
+
+fn transaction(indent: &str) -> String {
+    format!("2023-01-02 * \"Groceries\"\n{indent}Expenses:Food  10 CHF\n{indent}Assets:Cash\n")
+}
+
+#[rstest]
+#[case::majority(&["  ", "\t", "    ", "\t"], Some(Indent::Tabs(1)))]
+#[case::tie_first_used(&["    ", "  ", "  ", "    "], Some(Indent::Spaces(4)))]
+#[case::tie_other_order(&["  ", "    ", "    ", "  "], Some(Indent::Spaces(2)))]
+#[case::tie_of_three(&["\t", "  ", "    "], Some(Indent::Tabs(1)))]
+#[case::mixed_are_ignored(&["\t  ", " \t", "    "], Some(Indent::Spaces(4)))]
+#[case::only_mixed(&[" \t"], None)]
+#[case::no_transaction(&[], None)]
+fn stats_should_give_the_majority_indent_of_postings(
+    #[case] indents: &[&str],
+    #[case] expected: Option<Indent>,
+) {
+    let input: String = indents.iter().map(|indent| transaction(indent)).collect();
+    let report: ParseReport<f64> = parse_report(&input, &ParseOptions::default());
+    assert!(report.is_ok(), "{:?}", report.errors);
+    assert_eq!(report.stats.indent, expected);
+}
+
+#[rstest]
+fn should_keep_what_was_parsed_before_error() {
+    let input = "2023-01-01 open Assets:Cash\n  id: 1\n  id: 2\n2023-01-02 open oops\n2023-01-03 open Assets:Bank";
+    let report: ParseReport<f64> = parse_report(input, &ParseOptions::default());
+    assert!(!report.is_ok());
+    assert_eq!(report.errors.len(), 1);
+    assert_eq!(report.errors[0].code(), Code::InvalidSyntax);
+    assert_eq!(report.errors[0].line_number(), 4);
+    assert_eq!(report.file.directives.len(), 1);
+    assert_eq!(report.warnings.len(), 1);
+}
+
+#[rstest]
+fn should_apply_options() {
+    let options = ParseOptions::default().strict_metadata(true);
+    let report: ParseReport<f64> = parse_report(INPUT, &options);
+    assert_eq!(report.errors[0].code(), Code::DuplicateMetadataKey);
+}
+
+#[rstest]
+fn summary_should_list_stats_warnings_and_errors() {
+    let input = "2023-01-01 open Assets:Cash\n  id: 1\n  id: 2\n2023-01-02 open oops";
+    let report: ParseReport<f64> = parse_report(input, &ParseOptions::default());
+    let summary = summary(&report);
+    let lines: Vec<&str> = summary.lines().collect();
+    assert!(lines[0].starts_with("Parsed 4 lines in "), "{summary}");
+    assert_eq!(
+        lines[1..],
+        [
+            "  1 directive (0 transactions, 0 postings)",
+            "  1 account, 0 options, 0 includes",
+            "1 warning",
+            "  warning[W001]: Duplicate metadata key `id` at line: 3 (the first value is kept)",
+            "1 error",
+            "  error[E001]: Invalid beancount syntax at line: 4",
+        ]
+    );
+}
+
+#[rstest]
+fn summary_of_official_sample_should_have_no_warning_nor_error() {
+    let report: ParseReport<f64> = parse_report(OFFICIAL, &ParseOptions::default());
+    let summary = summary(&report);
+    assert!(summary.ends_with("\n0 warnings\n0 errors\n"), "{summary}");
+}
+
+#[rstest]
+#[case::default(ParseOptions::default(), "<input>")]
+#[case::named(ParseOptions::default().source_name("main.beancount"), "main.beancount")]
+fn sources_should_locate_errors_and_warnings(#[case] options: ParseOptions, #[case] name: &str) {
+    let input = "2023-01-01 open Assets:Cash\n  id: 1\n  id: 2\n2023-01-02 * \"Coffee\"\n  Assets:Cash  3 chf";
+    let report: ParseReport<f64> = parse_report(input, &options);
+    assert_eq!(report.errors[0].file_id(), FileId::MAIN);
+    assert_eq!(report.sources.resolve(FileId::MAIN).to_str(), Some(name));
+    assert_eq!(
+        report.sources.display(&report.errors[0]).to_string(),
+        format!("{name}:5:3: error[E014]: Invalid line in a directive at line: 5 (`Assets:Cash  3 chf` is neither metadata nor a posting)")
+    );
+    assert_eq!(
+        report.sources.display(&report.warnings[0]).to_string(),
+        format!("{name}:3: warning[W001]: Duplicate metadata key `id` at line: 3 (the first value is kept)")
+    );
+}
@@ -0,0 +1,423 @@
+#![allow(missing_docs, clippy::float_cmp)]
+
+use std::collections::HashMap;
+
+use rstest::rstest;
+
+use beancount_parser::{
+    metadata::Value, Account, BeancountFile, Code, Date, DirectiveContent, Inventory,
+    ResolveOptions, ResolvedLedger, WarningKind,
+};
+
+const OFFICIAL: &str = include_str!("samples/official.beancount");
+
+fn resolve(input: &str, options: &ResolveOptions) -> ResolvedLedger<f64> {
+    let file: BeancountFile<f64> = input.parse().unwrap();
+    file.resolve(options)
+}
+
+#[rstest]
+fn official_sample_should_resolve_to_the_balances() {
+    let file: BeancountFile<f64> = OFFICIAL.parse().unwrap();
+    let ledger = file.resolve(&ResolveOptions::default());
+    assert!(ledger.errors.is_empty(), "{:?}", ledger.errors);
+    assert!(ledger.warnings.is_empty(), "{:?}", ledger.warnings);
+    let mut balances: HashMap<Account, Inventory<f64>> = HashMap::new();
+    for posting in &ledger.postings {
+        balances
+            .entry(posting.account.clone())
+            .or_default()
+            .add(posting.amount.clone());
+    }
+    let expected = file.balances(None);
+    assert_eq!(balances.len(), expected.len());
+    for (account, inventory) in &expected {
+        for (currency, value) in inventory.iter() {
+            let actual = balances[account].get(currency).copied().unwrap_or_default();
+            assert!(
+                (actual - value).abs() < 1e-6,
+                "{account} {currency}: {actual} != {value}"
+            );
+        }
+    }
+}
+
+#[rstest]
+fn official_sample_postings_should_be_sorted_by_date() {
+    let ledger = resolve(OFFICIAL, &ResolveOptions::default());
+    assert!(ledger.postings.windows(2).all(|w| w[0].date <= w[1].date));
+}
+
+#[rstest]
+fn should_interpolate_one_posting_per_currency() {
+    let input = r#"
+2023-01-01 * "Exchange"
+  Assets:Cash  -10 CHF
+  Assets:Cash  -5 EUR
+  Assets:Bank
+"#;
+    let ledger = resolve(input, &ResolveOptions::default());
+    let mut bank: Vec<(f64, &str)> = ledger
+        .postings
+        .iter()
+        .filter(|p| p.account.as_str() == "Assets:Bank")
+        .map(|p| (p.amount.value, p.amount.currency.as_str()))
+        .collect();
+    bank.sort_by(|a, b| a.1.cmp(b.1));
+    assert_eq!(bank, [(10.0, "CHF"), (5.0, "EUR")]);
+}
+
+#[rstest]
+fn should_keep_the_order_of_the_postings() {
+    let input = r#"
+2023-01-01 * "Groceries"
+  Assets:Cash
+  Expenses:Food  10 CHF
+  Expenses:Drinks  5 CHF
+"#;
+    let ledger = resolve(input, &ResolveOptions::default());
+    let accounts: Vec<&str> = ledger.postings.iter().map(|p| p.account.as_str()).collect();
+    assert_eq!(
+        accounts,
+        ["Assets:Cash", "Expenses:Food", "Expenses:Drinks"]
+    );
+    assert_eq!(ledger.postings[0].amount.value, -15.0);
+}
+
+#[rstest]
+#[case::without_date("{500 USD}", Date::new(2023, 3, 1))]
+#[case::with_date("{500 USD, 2022-12-31}", Date::new(2022, 12, 31))]
+fn should_default_the_date_of_costs(#[case] cost: &str, #[case] expected: Date) {
+    let input = format!("2023-03-01 *\n  Assets:Stock  1 HOOL {cost}\n  Assets:Cash");
+    let ledger = resolve(&input, &ResolveOptions::default());
+    assert_eq!(
+        ledger.postings[0].cost.as_ref().unwrap().date,
+        Some(expected)
+    );
+    assert_eq!(ledger.postings[1].amount.value, -500.0);
+    assert!(ledger.postings[1].cost.is_none());
+}
+
+#[rstest]
+fn should_copy_transaction_fields_to_postings() {
+    let input = r#"
+pushtag #trip
+2023-01-01 ! "Shop" "Souvenirs" #gift ^receipt
+  document: "receipt.pdf"
+  Expenses:Gifts  10 CHF
+    document: "photo.jpg"
+  * Assets:Cash
+poptag #trip
+"#;
+    let ledger = resolve(input, &ResolveOptions::default());
+    let [gifts, cash] = &ledger.postings[..] else {
+        panic!("expected 2 postings: {:?}", ledger.postings);
+    };
+    for posting in [gifts, cash] {
+        assert_eq!(posting.date, Date::new(2023, 1, 1));
+        assert_eq!(posting.line_number, 3);
+        assert_eq!(posting.payee.as_deref(), Some("Shop"));
+        assert_eq!(posting.narration.as_deref(), Some("Souvenirs"));
+        assert!(posting.tags.contains("trip"));
+        assert!(posting.tags.contains("gift"));
+        assert!(posting.links.contains("receipt"));
+    }
+    assert_eq!(gifts.flag, Some('!'));
+    assert_eq!(cash.flag, Some('*'));
+    assert_eq!(
+        gifts.metadata.get("document"),
+        Some(&Value::String("photo.jpg".into()))
+    );
+    assert_eq!(
+        cash.metadata.get("document"),
+        Some(&Value::String("receipt.pdf".into()))
+    );
+}
+
+#[rstest]
+fn should_report_transactions_with_too_many_missing_amounts() {
+    let input = r#"
+2023-01-01 * "Ambiguous"
+  Assets:Cash  -10 CHF
+  Expenses:Food
+  Expenses:Drinks
+2023-01-02 * "Fine"
+  Assets:Cash  -5 CHF
+  Expenses:Food
+"#;
+    let ledger = resolve(input, &ResolveOptions::default());
+    let [error] = &ledger.errors[..] else {
+        panic!("expected one error: {:?}", ledger.errors);
+    };
+    assert_eq!(error.code(), Code::TooManyMissingAmounts);
+    assert_eq!(error.line_number(), 2);
+    assert!(error.to_string().ends_with("(2 postings without amount)"));
+    let dates: Vec<Date> = ledger.postings.iter().map(|p| p.date).collect();
+    assert_eq!(dates, [Date::new(2023, 1, 2), Date::new(2023, 1, 2)]);
+}
+
+#[rstest]
+#[case::expanded(true, &[("Assets:Checking", 100.0), ("Equity:Opening", -100.0), ("Assets:Checking", -4.0), ("Expenses:Food", 4.0)])]
+#[case::not_expanded(false, &[("Assets:Checking", -4.0), ("Expenses:Food", 4.0)])]
+fn should_expand_pads(#[case] expand_pads: bool, #[case] expected: &[(&str, f64)]) {
+    let input = r#"
+2023-01-01 pad Assets:Checking Equity:Opening
+2023-01-05 * "Coffee"
+  Assets:Checking  -4 CHF
+  Expenses:Food
+2023-01-10 balance Assets:Checking 96 CHF
+"#;
+    let ledger = resolve(input, &ResolveOptions::default().expand_pads(expand_pads));
+    let postings: Vec<(&str, f64)> = ledger
+        .postings
+        .iter()
+        .map(|p| (p.account.as_str(), p.amount.value))
+        .collect();
+    assert_eq!(postings, expected);
+    if expand_pads {
+        assert_eq!(ledger.postings[0].date, Date::new(2023, 1, 1));
+        assert_eq!(ledger.postings[0].line_number, 2);
+        assert_eq!(ledger.postings[0].flag, Some('P'));
+    }
+}
+
+fn pad_postings(ledger: &ResolvedLedger<f64>) -> Vec<(String, &str, f64, &str)> {
+    ledger
+        .postings
+        .iter()
+        .filter(|p| p.synthetic)
+        .map(|p| {
+            assert_eq!(p.flag, Some('P'));
+            (
+                p.date.to_string(),
+                p.account.as_str(),
+                p.amount.value,
+                p.amount.currency.as_str(),
+            )
+        })
+        .collect()
+}
+
+#[rstest]
+fn should_expand_a_pad_for_each_asserted_currency() {
+    let input = r#"
+2023-01-01 pad Assets:Checking Equity:Opening
+2023-01-05 * "Coffee"
+  Assets:Checking  -4 CHF
+  Expenses:Food
+2023-01-10 balance Assets:Checking 96 CHF
+2023-01-10 balance Assets:Checking 50 EUR
+2023-01-20 balance Assets:Checking 50 EUR
+"#;
+    let file: BeancountFile<f64> = input.parse().unwrap();
+    let ledger = file.resolve(&ResolveOptions::default());
+    assert_eq!(
+        pad_postings(&ledger),
+        [
+            ("2023-01-01".into(), "Assets:Checking", 100.0, "CHF"),
+            ("2023-01-01".into(), "Equity:Opening", -100.0, "CHF"),
+            ("2023-01-01".into(), "Assets:Checking", 50.0, "EUR"),
+            ("2023-01-01".into(), "Equity:Opening", -50.0, "EUR"),
+        ]
+    );
+    assert!(ledger.warnings.is_empty(), "{:?}", ledger.warnings);
+    assert!(file.verify_balances().is_empty());
+}
+
+#[rstest]
+fn should_warn_for_a_pad_without_following_assertion() {
+    let input = "
+2023-01-01 balance Assets:Checking 0 CHF
+2023-01-02 pad Assets:Checking Equity:Opening
+2023-01-02 pad Assets:Savings Equity:Opening
+2023-01-03 balance Assets:Savings 10 CHF
+";
+    let ledger = resolve(input, &ResolveOptions::default());
+    assert_eq!(pad_postings(&ledger).len(), 2);
+    let [warning] = &ledger.warnings[..] else {
+        panic!("expected one warning: {:?}", ledger.warnings);
+    };
+    assert_eq!(warning.code(), Code::UnusedPad);
+    assert_eq!(warning.line_number(), 3);
+    assert_eq!(
+        warning.kind(),
+        &WarningKind::UnusedPad("Assets:Checking".parse().unwrap())
+    );
+}
+
+#[rstest]
+fn should_use_the_last_of_two_pads_before_an_assertion() {
+    let input = "
+2023-01-01 pad Assets:Checking Equity:Opening
+2023-01-05 pad Assets:Checking Income:Gifts
+2023-01-10 balance Assets:Checking 100 CHF
+";
+    let file: BeancountFile<f64> = input.parse().unwrap();
+    let ledger = file.resolve(&ResolveOptions::default());
+    assert_eq!(
+        pad_postings(&ledger),
+        [
+            ("2023-01-05".into(), "Assets:Checking", 100.0, "CHF"),
+            ("2023-01-05".into(), "Income:Gifts", -100.0, "CHF"),
+        ]
+    );
+    let [warning] = &ledger.warnings[..] else {
+        panic!("expected one warning: {:?}", ledger.warnings);
+    };
+    assert_eq!(warning.code(), Code::UnusedPad);
+    assert_eq!(warning.line_number(), 2);
+    assert!(file.verify_balances().is_empty());
+}
+
+#[rstest]
+fn pad_should_be_used_by_subsequent_balance_checks() {
+    let input = r#"
+2023-01-01 pad Assets:Checking Equity:Opening
+2023-01-10 balance Assets:Checking 100 CHF
+2023-01-15 * "Coffee"
+  Assets:Checking  -4 CHF
+  Expenses:Food
+2023-01-20 balance Assets:Checking 96 CHF
+2023-01-20 balance Equity:Opening -100 CHF
+"#;
+    let file: BeancountFile<f64> = input.parse().unwrap();
+    assert!(file.verify_balances().is_empty());
+    let ledger = file.resolve(&ResolveOptions::default());
+    let synthetic = ledger.postings.iter().filter(|p| p.synthetic).count();
+    assert_eq!(synthetic, 2);
+    assert_eq!(ledger.postings.len(), 4);
+}
+
+#[rstest]
+fn padding_transactions_of_the_file_should_not_be_synthetic() {
+    let input = r#"
+2023-01-01 P "(Padding inserted for Balance of 100 CHF for difference 100 CHF)"
+  Assets:Checking  100 CHF
+  Equity:Opening  -100 CHF
+2023-01-02 pad Assets:Savings Equity:Opening
+2023-01-10 balance Assets:Savings 50 CHF
+"#;
+    let file: BeancountFile<f64> = input.parse().unwrap();
+    let DirectiveContent::Transaction(trx) = &file.directives[0].content else {
+        panic!("was not a transaction: {:?}", file.directives[0]);
+    };
+    assert_eq!(trx.flag, Some('P'));
+    let ledger = file.resolve(&ResolveOptions::default());
+    let postings: Vec<(&str, Option<char>, bool)> = ledger
+        .postings
+        .iter()
+        .map(|p| (p.account.as_str(), p.flag, p.synthetic))
+        .collect();
+    assert_eq!(
+        postings,
+        [
+            ("Assets:Checking", Some('P'), false),
+            ("Equity:Opening", Some('P'), false),
+            ("Assets:Savings", Some('P'), true),
+            ("Equity:Opening", Some('P'), true),
+        ]
+    );
+}
+
+fn resolved_costs(postings: &str) -> Vec<(String, Option<(f64, String)>)> {
+    let ledger = resolve(
+        &format!("2023-05-20 * \"Buy\"\n{postings}"),
+        &ResolveOptions::default(),
+    );
+    assert!(ledger.errors.is_empty(), "{:?}", ledger.errors);
+    ledger
+        .postings
+        .iter()
+        .map(|p| {
+            let cost = p.cost.as_ref().and_then(|cost| cost.amount.as_ref());
+            (
+                p.account.as_str().to_owned(),
+                cost.map(|cost| (cost.value, cost.currency.as_str().to_owned())),
+            )
+        })
+        .collect()
+}
+
+#[rstest]
+#[case::empty_cost("  Assets:Stock  10 HOOL {}\n  Assets:Cash  -1100 USD", 110.0)]
+#[case::with_price(
+    "  Assets:Stock  10 HOOL {} @ 120 USD\n  Assets:Cash  -1100 USD",
+    110.0
+)]
+#[case::with_date(
+    "  Assets:Stock  10 HOOL {2023-05-01}\n  Assets:Cash  -1100 USD",
+    110.0
+)]
+#[case::several_postings(
+    "  Assets:Stock  4 HOOL {}\n  Expenses:Fees  10 USD\n  Assets:Cash  -90 USD\n  Assets:Bank  -20 USD",
+    25.0
+)]
+#[case::cost_posting_last("  Assets:Cash  -100 USD\n  Assets:Stock  8 HOOL {}", 12.5)]
+fn resolve_should_interpolate_the_missing_cost_of_an_augmentation(
+    #[case] postings: &str,
+    #[case] expected: f64,
+) {
+    let costs = resolved_costs(postings);
+    let stock = costs
+        .iter()
+        .find(|(account, _)| account == "Assets:Stock")
+        .unwrap();
+    assert_eq!(stock.1, Some((expected, "USD".into())));
+    assert!(costs
+        .iter()
+        .filter(|(account, _)| account != "Assets:Stock")
+        .all(|(_, cost)| cost.is_none()));
+}
+
+#[rstest]
+fn resolve_should_keep_the_date_of_an_interpolated_cost() {
+    let ledger = resolve(
+        "2023-05-20 *\n  Assets:Stock  10 HOOL {2023-05-01}\n  Assets:Cash  -1100 USD",
+        &ResolveOptions::default(),
+    );
+    let cost = ledger.postings[0].cost.as_ref().unwrap();
+    assert_eq!(cost.date, Some(Date::new(2023, 5, 1)));
+}
+
+#[rstest]
+fn resolve_should_not_interpolate_the_cost_of_a_reduction() {
+    let costs = resolved_costs("  Assets:Stock  -10 HOOL {}\n  Assets:Cash  1100 USD");
+    assert_eq!(
+        costs,
+        [("Assets:Stock".into(), None), ("Assets:Cash".into(), None)]
+    );
+}
+
+#[rstest]
+#[case::with_missing_amount(
+    "  Assets:Stock  10 HOOL {}\n  Assets:Cash",
+    "2 postings without amount or cost"
+)]
+#[case::two_missing_costs(
+    "  Assets:Stock  10 HOOL {}\n  Assets:Fund  5 FUND {}\n  Assets:Cash  -1100 USD",
+    "2 postings without amount or cost"
+)]
+#[case::several_currencies(
+    "  Assets:Stock  10 HOOL {}\n  Assets:Cash  -1100 USD\n  Assets:Card  -10 EUR",
+    "the other postings do not weigh a single currency"
+)]
+#[case::no_other_posting(
+    "  Assets:Stock  10 HOOL {}",
+    "the other postings do not weigh a single currency"
+)]
+fn resolve_should_report_the_costs_that_cannot_be_interpolated(
+    #[case] postings: &str,
+    #[case] hint: &str,
+) {
+    let input = format!("2023-05-19 open Assets:Stock\n2023-05-20 * \"Buy\"\n{postings}");
+    let ledger = resolve(&input, &ResolveOptions::default());
+    assert!(ledger.postings.is_empty(), "{:?}", ledger.postings);
+    assert_eq!(ledger.errors.len(), 1);
+    assert_eq!(ledger.errors[0].code(), Code::UnresolvableCost);
+    assert_eq!(ledger.errors[0].line_number(), 2);
+    assert!(
+        ledger.errors[0].to_string().ends_with(&format!("({hint})")),
+        "{}",
+        ledger.errors[0]
+    );
+}
@@ -1,4 +1,5 @@
-#![allow(clippy::items_after_test_module)]
+#![allow(missing_docs, clippy::items_after_test_module)]
+#![cfg(feature = "rust_decimal")]
 
 use rstest::rstest;
 use rust_decimal::Decimal;
@@ -23,6 +24,21 @@ fn should_parse_amount(
     assert_eq!(amount.currency.as_str(), expected_currency);
 }
 
+#[rstest]
+#[case("1.50 CHF", 150, 2)]
+#[case("-0.001 CHF", -1, 3)]
+#[case("1,000. CHF", 1000, 0)]
+fn should_keep_the_scale_of_the_literal(
+    #[case] input: &str,
+    #[case] mantissa: i64,
+    #[case] scale: u32,
+) {
+    let input = format!("2023-05-17 *\n  Assets:Cash {input}");
+    let value = parse_single_posting(&input).amount.unwrap().value;
+    assert_eq!(value.mantissa(), i128::from(mantissa));
+    assert_eq!(value.scale(), scale);
+}
+
 fn parse_single_directive(input: &str) -> Directive<Decimal> {
     let directives = parse(input).expect("parsing should succeed").directives;
     assert_eq!(
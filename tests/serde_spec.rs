@@ -0,0 +1,226 @@
+#![allow(missing_docs)]
+#![cfg(feature = "serde")]
+
+use std::collections::HashMap;
+
+use rstest::rstest;
+
+use beancount_parser::{
+    parse_report, Account, BeancountFile, Directive, DirectiveContent, Link, ParseOptions,
+    ParseReport, Tag, Transaction,
+};
+
+const SIMPLE: &str = include_str!("samples/simple.beancount");
+const OFFICIAL: &str = include_str!("samples/official.beancount");
+const ALL_KINDS: &str = r#"
+option "operating_currency" "CHF"
+include "other.beancount"
+2023-05-20 open Assets:Cash CHF,EUR "FIFO"
+2023-05-20 commodity HOOL
+  name: "Hooli"
+2023-05-21 price HOOL 10 CHF
+2023-05-22 * "Shop" "Shares" #trip ^invoice
+  id: 42
+  Assets:Stock  2 HOOL {10 CHF, 2023-05-21} @ 11 CHF
+    lot: HOOL
+  ! Assets:Cash
+2023-05-23 txn "Fees"
+  Assets:Cash  -1 CHF @@ 1 EUR
+  Expenses:Fees
+2023-05-24 balance Assets:Cash 10 ~ 0.01 CHF
+2023-05-25 pad Assets:Cash Equity:Opening
+2023-05-26 event "location" "Paris"
+2023-05-27 custom "budget" Expenses:Food "monthly" 45.3 CHF TRUE 2023-05-01 12
+2023-05-27 note Assets:Cash "Counted the coins"
+2023-05-27 document Assets:Cash "statements/2023-05.pdf" #bank
+2023-05-27 query "cash" "SELECT account, sum(position) WHERE account ~ \"Cash\"; "
+  id: 7
+2023-05-28 close Assets:Cash
+"#;
+
+const INPUT: &str =
+    "2023-07-09 * \"hello\" #trip ^invoice\n  Assets:Bank:Checking 10 CHF\n  Income:Gifts";
+
+fn parse_transaction() -> Transaction<f64> {
+    let file: BeancountFile<f64> = INPUT.parse().unwrap();
+    let DirectiveContent::Transaction(trx) = file.directives.into_iter().next().unwrap().content
+    else {
+        unreachable!("was not a transaction")
+    };
+    trx
+}
+
+#[rstest]
+fn account_is_serialized_as_plain_string() {
+    let account = parse_transaction().postings.remove(0).account;
+    let json = serde_json::to_string(&account).unwrap();
+    assert_eq!(json, "\"Assets:Bank:Checking\"");
+}
+
+#[rstest]
+fn tag_and_link_are_serialized_as_plain_strings() {
+    let trx = parse_transaction();
+    let tag = trx.tags.iter().next().unwrap();
+    let link = trx.links.iter().next().unwrap();
+    assert_eq!(serde_json::to_string(tag).unwrap(), "\"trip\"");
+    assert_eq!(serde_json::to_string(link).unwrap(), "\"invoice\"");
+}
+
+#[rstest]
+fn accounts_from_different_parses_serialize_identically() {
+    let first = parse_transaction().postings.remove(0).account;
+    let second = parse_transaction().postings.remove(0).account;
+    assert_eq!(
+        serde_json::to_string(&first).unwrap(),
+        serde_json::to_string(&second).unwrap()
+    );
+}
+
+#[rstest]
+fn map_keyed_by_account_round_trips() {
+    let mut map = HashMap::new();
+    map.insert(parse_transaction().postings.remove(0).account, 1);
+    let json = serde_json::to_string(&map).unwrap();
+    assert_eq!(json, "{\"Assets:Bank:Checking\":1}");
+    let deserialized: HashMap<Account, i32> = serde_json::from_str(&json).unwrap();
+    assert_eq!(deserialized, map);
+    assert_eq!(deserialized.get("Assets:Bank:Checking"), Some(&1));
+}
+
+#[rstest]
+fn tag_and_link_round_trip() {
+    let trx = parse_transaction();
+    let tag: Tag = serde_json::from_str("\"trip\"").unwrap();
+    let link: Link = serde_json::from_str("\"invoice\"").unwrap();
+    assert!(trx.tags.contains(&tag));
+    assert!(trx.links.contains(&link));
+}
+
+#[rstest]
+fn reject_invalid_account(
+    #[values("\"\"", "\"Assets\"", "\"Assets::Cash\"", "\"oops\"")] json: &str,
+) {
+    let result: Result<Account, _> = serde_json::from_str(json);
+    assert!(result.is_err(), "{result:?}");
+}
+
+#[rstest]
+fn reject_invalid_tag_and_link(#[values("\"\"", "\"hello world\"", "\"#tag\"")] json: &str) {
+    let tag: Result<Tag, _> = serde_json::from_str(json);
+    assert!(tag.is_err(), "{tag:?}");
+    let link: Result<Link, _> = serde_json::from_str(json);
+    assert!(link.is_err(), "{link:?}");
+}
+
+#[rstest]
+fn capabilities_should_serialize_to_lists_of_strings() {
+    let json = serde_json::to_value(beancount_parser::capabilities()).unwrap();
+    assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(json["directives"][0], "transaction");
+    assert_eq!(json["directives"].as_array().unwrap().len(), 12);
+    assert!(json["options"]
+        .as_array()
+        .unwrap()
+        .contains(&"strict_metadata".into()));
+    assert!(json["features"]
+        .as_array()
+        .unwrap()
+        .contains(&"serde".into()));
+}
+
+#[rstest]
+fn stats_should_serialize_to_a_flat_object() {
+    let report: ParseReport<f64> = parse_report(INPUT, &ParseOptions::default());
+    assert_eq!(
+        serde_json::to_value(&report.stats).unwrap(),
+        serde_json::json!({
+            "lines": 3,
+            "bytes": INPUT.len(),
+            "directives": 1,
+            "transactions": 1,
+            "postings": 2,
+            "accounts": 2,
+            "options": 0,
+            "includes": 0,
+            "indent": { "spaces": 2 },
+        })
+    );
+}
+
+#[rstest]
+fn file_should_round_trip_through_json(#[values(SIMPLE, OFFICIAL, ALL_KINDS)] input: &str) {
+    let file: BeancountFile<f64> = input.parse().unwrap();
+    let json = serde_json::to_string(&file).unwrap();
+    let deserialized: BeancountFile<f64> = serde_json::from_str(&json).unwrap();
+    assert_eq!(deserialized.directives, file.directives);
+    assert_eq!(deserialized.includes, file.includes);
+    let options = |file: &BeancountFile<f64>| -> Vec<(String, String)> {
+        file.options
+            .iter()
+            .map(|option| (option.name.clone(), option.value.clone()))
+            .collect()
+    };
+    assert_eq!(options(&deserialized), options(&file));
+    assert_eq!(
+        serde_json::to_string(&deserialized).unwrap().len(),
+        json.len()
+    );
+}
+
+#[rstest]
+fn directive_should_serialize_with_the_field_names_and_snake_case_variants() {
+    let directive: Directive<f64> = "2023-05-21 price HOOL 10 CHF".parse().unwrap();
+    assert_eq!(
+        serde_json::to_value(&directive).unwrap(),
+        serde_json::json!({
+            "date": "2023-05-21",
+            "content": { "price": { "currency": "HOOL", "amount": { "value": 10.0, "currency": "CHF" } } },
+            "metadata": {},
+            "line_number": 1,
+        })
+    );
+}
+
+#[rstest]
+fn directive_should_deserialize_without_metadata_and_line_number() {
+    let json = r#"{"date": "2023-05-20", "content": {"close": {"account": "Assets:Cash"}}}"#;
+    let directive: Directive<f64> = serde_json::from_str(json).unwrap();
+    let parsed: Directive<f64> = "2023-05-20 close Assets:Cash".parse().unwrap();
+    assert_eq!(directive.content, parsed.content);
+    assert!(directive.metadata.is_empty());
+    assert_eq!(directive.line_number, 0);
+}
+
+#[rstest]
+#[case::unknown_directive_field(
+    r#"{"date": "2023-05-20", "content": {"close": {"account": "Assets:Cash"}}, "flag": "*"}"#
+)]
+#[case::unknown_content_field(
+    r#"{"date": "2023-05-20", "content": {"close": {"account": "Assets:Cash", "reason": "moved"}}}"#
+)]
+#[case::unknown_directive_kind(
+    r#"{"date": "2023-05-20", "content": {"budget": {"name": "cash"}}}"#
+)]
+#[case::invalid_date(r#"{"date": "2023-02-30", "content": {"close": {"account": "Assets:Cash"}}}"#)]
+#[case::malformed_date(
+    r#"{"date": "20/05/2023", "content": {"close": {"account": "Assets:Cash"}}}"#
+)]
+#[case::invalid_account(
+    r#"{"date": "2023-05-20", "content": {"close": {"account": "assets:cash"}}}"#
+)]
+#[case::invalid_currency(r#"{"date": "2023-05-20", "content": {"commodity": "chf"}}"#)]
+#[case::invalid_metadata_key(
+    r#"{"date": "2023-05-20", "content": {"commodity": "CHF"}, "metadata": {"Name": {"string": "Franc"}}}"#
+)]
+fn reject_invalid_directive(#[case] json: &str) {
+    let result: Result<Directive<f64>, _> = serde_json::from_str(json);
+    assert!(result.is_err(), "{result:?}");
+}
+
+#[rstest]
+fn reject_unknown_file_field() {
+    let result: Result<BeancountFile<f64>, _> = serde_json::from_str(
+        r#"{"options": [], "includes": [], "directives": [], "warnings": []}"#,
+    );
+    assert!(result.is_err());
+}
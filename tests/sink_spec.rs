@@ -0,0 +1,153 @@
+#![allow(missing_docs)]
+
+use std::{ops::ControlFlow, path::PathBuf};
+
+use rstest::rstest;
+
+use beancount_parser::{
+    metadata, parse_iter_with_options, parse_with_sink, BeanOption, Code, Directive, DirectiveSink,
+    Entry, IgnoredLine, ParseOptions, Plugin, Tag, Warning,
+};
+
+const OFFICIAL: &str = include_str!("samples/official.beancount");
+
+#[derive(Default)]
+struct Recorder {
+    entries: Vec<Entry<f64>>,
+    max_directives: Option<usize>,
+}
+
+impl Recorder {
+    fn record(&mut self, entry: Entry<f64>) -> ControlFlow<()> {
+        self.entries.push(entry);
+        ControlFlow::Continue(())
+    }
+}
+
+impl DirectiveSink<f64> for Recorder {
+    fn directive(&mut self, directive: Directive<f64>) -> ControlFlow<()> {
+        self.record(Entry::Directive(directive))?;
+        let directives = self
+            .entries
+            .iter()
+            .filter(|entry| matches!(entry, Entry::Directive(_)))
+            .count();
+        match self.max_directives {
+            Some(max) if directives >= max => ControlFlow::Break(()),
+            _ => ControlFlow::Continue(()),
+        }
+    }
+
+    fn option(&mut self, option: BeanOption) -> ControlFlow<()> {
+        self.record(Entry::Option(option))
+    }
+
+    fn include(&mut self, path: PathBuf) -> ControlFlow<()> {
+        self.record(Entry::Include(path))
+    }
+
+    fn plugin(&mut self, plugin: Plugin) -> ControlFlow<()> {
+        self.record(Entry::Plugin(plugin))
+    }
+
+    fn push_tag(&mut self, tag: Tag) -> ControlFlow<()> {
+        self.record(Entry::PushTag(tag))
+    }
+
+    fn pop_tag(&mut self, tag: Tag) -> ControlFlow<()> {
+        self.record(Entry::PopTag(tag))
+    }
+
+    fn push_meta(&mut self, key: metadata::Key, value: metadata::Value<f64>) -> ControlFlow<()> {
+        self.record(Entry::PushMeta(key, value))
+    }
+
+    fn pop_meta(&mut self, key: metadata::Key) -> ControlFlow<()> {
+        self.record(Entry::PopMeta(key))
+    }
+
+    fn ignored_line(&mut self, line: IgnoredLine) -> ControlFlow<()> {
+        self.record(Entry::Ignored(line))
+    }
+
+    fn warning(&mut self, warning: Warning) -> ControlFlow<()> {
+        self.record(Entry::Warning(warning))
+    }
+}
+
+/// Kind and line of every entry (the metadata of the directives is not ordered)
+fn summary(entries: &[Entry<f64>]) -> Vec<String> {
+    entries
+        .iter()
+        .map(|entry| match entry {
+            Entry::Directive(d) => format!("directive {} {}", d.line_number, d.date),
+            Entry::Option(o) => format!("option {} {}", o.name, o.value),
+            Entry::Include(path) => format!("include {}", path.display()),
+            Entry::Plugin(p) => format!("plugin {}", p.name),
+            Entry::PushTag(tag) => format!("pushtag {tag}"),
+            Entry::PopTag(tag) => format!("poptag {tag}"),
+            Entry::PushMeta(key, value) => format!("pushmeta {key} {value:?}"),
+            Entry::PopMeta(key) => format!("popmeta {key}"),
+            Entry::Ignored(line) => format!("ignored {} {:?}", line.line_number, line.text),
+            Entry::Warning(w) => format!("warning {w}"),
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+#[rstest]
+#[case::official(OFFICIAL)]
+#[case::with_warnings(
+    "option \"title\" \"Test\"\ninclude \"other.beancount\"\nplugin \"auto_accounts\"\npushtag #trip\npushmeta city: \"Paris\"\n2023-01-01 open Assets:Cash\n  id: 1\n  id: 2\npopmeta city:\npoptag #trip\n"
+)]
+#[case::with_ignored_lines("* Cash\n2023-01-01 open Assets:Cash\n; end\nHello\n")]
+fn sink_should_receive_the_entries_of_parse_iter(#[case] input: &str) {
+    let options = ParseOptions::default().capture_ignored_lines(true);
+    let mut recorder = Recorder::default();
+    parse_with_sink(input, &options, &mut recorder).unwrap();
+    let expected: Vec<Entry<f64>> = parse_iter_with_options(input, &options)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(summary(&recorder.entries), summary(&expected));
+}
+
+#[rstest]
+fn sink_should_stop_parsing_on_break() {
+    let input = "2023-01-01 open Assets:Cash\n2023-01-02 open Assets:Bank\n2023-01-03 open oops";
+    let mut recorder = Recorder {
+        max_directives: Some(2),
+        ..Recorder::default()
+    };
+    parse_with_sink(input, &ParseOptions::default(), &mut recorder).unwrap();
+    assert_eq!(recorder.entries.len(), 2);
+}
+
+#[rstest]
+fn sink_should_receive_the_entries_before_an_error() {
+    let input = "2023-01-01 open Assets:Cash\n2023-01-02 open oops\n2023-01-03 open Assets:Bank";
+    let mut recorder = Recorder::default();
+    let error = parse_with_sink(input, &ParseOptions::default(), &mut recorder).unwrap_err();
+    assert_eq!(error.code(), Code::InvalidSyntax);
+    assert_eq!(error.line_number(), 2);
+    assert_eq!(recorder.entries.len(), 1);
+}
+
+#[rstest]
+fn sink_should_apply_the_options() {
+    struct Count(usize);
+    impl DirectiveSink<f64> for Count {
+        fn directive(&mut self, _: Directive<f64>) -> ControlFlow<()> {
+            self.0 += 1;
+            ControlFlow::Continue(())
+        }
+    }
+    let options = ParseOptions::default()
+        .date_range("2023-01-01".parse().unwrap(), "2024-01-01".parse().unwrap());
+    let mut count = Count(0);
+    parse_with_sink(OFFICIAL, &options, &mut count).unwrap();
+    let expected = parse_iter_with_options::<f64>(OFFICIAL, &options)
+        .filter(|entry| matches!(entry, Ok(Entry::Directive(_))))
+        .count();
+    assert_eq!(count.0, expected);
+    assert!(count.0 > 0);
+}
@@ -0,0 +1,150 @@
+#![allow(missing_docs)]
+
+use std::ptr;
+
+use rstest::rstest;
+
+use beancount_parser::{
+    AccountNode, BeancountFile, Directive, DirectiveContent, DirectiveId, Snapshot,
+};
+
+const OFFICIAL: &str = include_str!("samples/official.beancount");
+
+fn snapshot(input: &str) -> Snapshot<f64> {
+    input.parse::<BeancountFile<f64>>().unwrap().into_snapshot()
+}
+
+#[rstest]
+fn clone_should_share_the_directives() {
+    let original = snapshot(OFFICIAL);
+    let clone = original.clone();
+    assert_eq!(clone.len(), original.len());
+    for ((_, a), (_, b)) in original.directives().zip(clone.directives()) {
+        assert!(ptr::eq(a, b));
+    }
+}
+
+#[rstest]
+fn directive_mut_should_only_copy_the_modified_directive() {
+    let original = snapshot("2023-05-20 open Assets:Cash\n2023-05-21 close Assets:Cash");
+    let mut modified = original.clone();
+    let (first, second) = {
+        let mut ids = modified.directives().map(|(id, _)| id);
+        (ids.next().unwrap(), ids.next().unwrap())
+    };
+    modified.directive_mut(second).unwrap().date = "2023-12-31".parse().unwrap();
+    assert_eq!(
+        modified.directive(second).unwrap().date.to_string(),
+        "2023-12-31"
+    );
+    assert_eq!(
+        original.directive(second).unwrap().date.to_string(),
+        "2023-05-21"
+    );
+    assert!(ptr::eq(
+        original.directive(first).unwrap(),
+        modified.directive(first).unwrap()
+    ));
+    assert!(!ptr::eq(
+        original.directive(second).unwrap(),
+        modified.directive(second).unwrap()
+    ));
+}
+
+#[rstest]
+fn push_remove_and_retain_should_leave_other_snapshots_unchanged() {
+    let original = snapshot("2023-05-20 open Assets:Cash\n2023-05-21 open Assets:Bank");
+    let mut modified = original.clone();
+    let first = modified.directives().next().unwrap().0;
+    let removed = modified.remove(first).unwrap();
+    assert!(
+        matches!(&removed.content, DirectiveContent::Open(open) if open.account.as_str() == "Assets:Cash")
+    );
+    modified.push(
+        "2023-05-22 close Assets:Bank"
+            .parse::<BeancountFile<f64>>()
+            .unwrap()
+            .directives
+            .remove(0),
+    );
+    modified.retain(|d| !matches!(d.content, DirectiveContent::Open(_)));
+    let dates: Vec<String> = modified
+        .directives()
+        .map(|(_, d)| d.date.to_string())
+        .collect();
+    assert_eq!(dates, ["2023-05-22"]);
+    assert_eq!(original.len(), 2);
+}
+
+#[rstest]
+fn remove_should_return_none_for_an_unknown_id() {
+    let mut empty = snapshot("");
+    let id = snapshot("2023-05-20 open Assets:Cash")
+        .directives()
+        .next()
+        .unwrap()
+        .0;
+    assert!(empty.is_empty());
+    assert!(empty.remove(id).is_none());
+    assert!(empty.directive_mut(id).is_none());
+}
+
+#[rstest]
+fn into_file_should_restore_the_file() {
+    let input = "option \"title\" \"Test\"\ninclude \"other.beancount\"\npushtag #trip\n2023-05-20 * \"Hotel\"";
+    let file: BeancountFile<f64> = input.parse().unwrap();
+    let snapshot = file.clone().into_snapshot();
+    let _shared = snapshot.clone();
+    assert_eq!(snapshot.options()[0].value, "Test");
+    assert_eq!(snapshot.includes(), file.includes);
+    let mut restored = snapshot.into_file();
+    assert_eq!(restored.directives, file.directives);
+    restored
+        .extend_from_str("2023-05-21 * \"Restaurant\"")
+        .unwrap();
+    let DirectiveContent::Transaction(trx) = &restored.directives[1].content else {
+        unreachable!("was not a transaction")
+    };
+    assert!(trx.tags.contains("trip"));
+}
+
+#[rstest]
+fn derived_data_should_match_the_one_of_the_file() {
+    let file: BeancountFile<f64> = OFFICIAL.parse().unwrap();
+    let snapshot = file.clone().into_snapshot();
+    assert_eq!(snapshot.links_index(), &file.links_index());
+    assert_eq!(snapshot.tags_index(), &file.tags_index());
+    let tree = file.account_tree();
+    let names: Vec<&str> = tree.iter().map(AccountNode::name).collect();
+    assert_eq!(account_names(&snapshot), names);
+}
+
+fn open_bank() -> Directive<f64> {
+    let mut file: BeancountFile<f64> = "2023-05-22 open Assets:Bank".parse().unwrap();
+    file.directives.remove(0)
+}
+
+fn first_id(snapshot: &Snapshot<f64>) -> DirectiveId {
+    snapshot.directives().next().unwrap().0
+}
+
+fn account_names(snapshot: &Snapshot<f64>) -> Vec<String> {
+    snapshot
+        .account_tree()
+        .iter()
+        .map(|node| node.name().to_owned())
+        .collect()
+}
+
+#[rstest]
+#[case::directive_mut(|s: &mut Snapshot<f64>| s.directive_mut(first_id(s)).unwrap().content = open_bank().content)]
+#[case::push(|s: &mut Snapshot<f64>| s.push(open_bank()))]
+#[case::remove(|s: &mut Snapshot<f64>| drop(s.remove(first_id(s))))]
+#[case::retain(|s: &mut Snapshot<f64>| s.retain(|d| d.date.day != 20))]
+fn modifications_should_discard_the_derived_data(#[case] modify: fn(&mut Snapshot<f64>)) {
+    let mut snapshot = snapshot("2023-05-20 open Assets:Cash\n2023-05-21 * \"Coffee\" ^receipt");
+    let before = account_names(&snapshot);
+    assert_eq!(snapshot.links_index().len(), 1);
+    modify(&mut snapshot);
+    assert_ne!(account_names(&snapshot), before);
+}
@@ -0,0 +1,203 @@
+#![allow(missing_docs)]
+
+use rstest::rstest;
+
+use beancount_parser::{
+    parse_with_options, Account, Amount, BalanceAdjustment, BalanceSuggestion, BeancountFile, Date,
+    Directive, DirectiveContent, ParseOptions,
+};
+
+const LEDGER: &str = r#"
+2023-01-01 open Assets:Bank
+2023-01-01 open Assets:Empty
+2023-01-05 * "Salary"
+  Assets:Bank     1000 CHF
+  Income:Salary
+2023-01-10 * "Bonus"
+  Assets:Bank      200 EUR
+  Income:Salary
+2023-01-20 * "Rent"
+  Expenses:Rent    400 CHF
+  Assets:Bank
+2023-01-31 close Assets:Empty
+"#;
+
+fn ledger() -> BeancountFile<f64> {
+    LEDGER.parse().unwrap()
+}
+
+fn account(name: &str) -> Account {
+    name.parse().unwrap()
+}
+
+fn amount(value: f64, currency: &str) -> Amount<f64> {
+    Amount {
+        value,
+        currency: currency.parse().unwrap(),
+    }
+}
+
+#[rstest]
+#[case::before_the_rent(Date::new(2023, 1, 20), 1000.0)]
+#[case::after_the_rent(Date::new(2023, 1, 21), 600.0)]
+fn should_match_the_computed_balance_of_the_asserted_currency(
+    #[case] date: Date,
+    #[case] value: f64,
+) {
+    let observed = amount(value, "CHF");
+    assert_eq!(
+        ledger().suggest_balance(&account("Assets:Bank"), date, &observed),
+        BalanceSuggestion::Matches
+    );
+}
+
+#[rstest]
+fn should_ignore_the_other_currencies_of_the_account() {
+    let mut file = ledger();
+    let (bank, date) = (account("Assets:Bank"), Date::new(2023, 2, 1));
+    let observed = amount(200.0, "EUR");
+    assert_eq!(
+        file.suggest_balance(&bank, date, &observed),
+        BalanceSuggestion::Matches
+    );
+    file.directives
+        .push(Directive::balance(date, bank.clone(), observed));
+    assert!(file.verify_balances().is_empty());
+    assert_eq!(file.balances(None)[&bank].get("CHF"), Some(&600.0));
+}
+
+#[rstest]
+#[case::less(amount(590.0, "CHF"), Some(-10.0))]
+#[case::more(amount(650.0, "CHF"), Some(50.0))]
+#[case::currency_never_held(amount(0.0, "USD"), None)]
+fn should_return_the_difference_with_the_computed_balance(
+    #[case] observed: Amount<f64>,
+    #[case] delta: Option<f64>,
+) {
+    let expected = match delta {
+        Some(delta) => BalanceSuggestion::Differs {
+            delta: amount(delta, observed.currency.as_str()),
+        },
+        None => BalanceSuggestion::Matches,
+    };
+    assert_eq!(
+        ledger().suggest_balance(&account("Assets:Bank"), Date::new(2023, 2, 1), &observed),
+        expected
+    );
+}
+
+#[rstest]
+#[case::never_opened("Assets:Savings", Date::new(2023, 2, 1))]
+#[case::before_the_open("Assets:Bank", Date::new(2022, 12, 31))]
+#[case::after_the_close("Assets:Empty", Date::new(2023, 2, 1))]
+fn should_report_the_accounts_not_open_at_the_date(#[case] name: &str, #[case] date: Date) {
+    let observed = amount(0.0, "CHF");
+    assert_eq!(
+        ledger().suggest_balance(&account(name), date, &observed),
+        BalanceSuggestion::UnknownAccount
+    );
+}
+
+#[rstest]
+#[case::on_the_open_date(Date::new(2023, 1, 1))]
+#[case::on_the_close_date(Date::new(2023, 1, 31))]
+fn should_accept_an_open_account_without_postings(#[case] date: Date) {
+    let empty = account("Assets:Empty");
+    assert_eq!(
+        ledger().suggest_balance(&empty, date, &amount(0.0, "CHF")),
+        BalanceSuggestion::Matches
+    );
+    assert_eq!(
+        ledger().suggest_balance(&empty, date, &amount(5.0, "CHF")),
+        BalanceSuggestion::Differs {
+            delta: amount(5.0, "CHF")
+        }
+    );
+}
+
+#[rstest]
+fn should_accept_an_account_used_without_open_directive() {
+    let observed = amount(-1000.0, "CHF");
+    assert_eq!(
+        ledger().suggest_balance(&account("Income:Salary"), Date::new(2023, 2, 1), &observed),
+        BalanceSuggestion::Matches
+    );
+}
+
+#[rstest]
+fn should_include_the_sub_accounts_with_aggregate_balance_assertions() {
+    let input = "2023-01-01 * \"Salary\"\n  Assets:Bank:Checking  100 CHF\n  Income:Salary\n";
+    let observed = amount(100.0, "CHF");
+    let (bank, date) = (account("Assets:Bank"), Date::new(2023, 1, 2));
+    let file: BeancountFile<f64> = input.parse().unwrap();
+    assert_eq!(
+        file.suggest_balance(&bank, date, &observed),
+        BalanceSuggestion::UnknownAccount
+    );
+    let options = ParseOptions::default().aggregate_balance_assertions(true);
+    let file: BeancountFile<f64> = parse_with_options(input, &options).unwrap();
+    assert_eq!(
+        file.suggest_balance(&bank, date, &observed),
+        BalanceSuggestion::Matches
+    );
+}
+
+#[rstest]
+#[case::pad(BalanceAdjustment::Pad(account("Equity:Opening-Balances")))]
+#[case::transaction(BalanceAdjustment::Transaction(account("Equity:Opening-Balances")))]
+fn adjustment_should_make_the_balance_assertion_pass(#[case] adjustment: BalanceAdjustment) {
+    let mut file = ledger();
+    let (bank, date) = (account("Assets:Bank"), Date::new(2023, 2, 1));
+    let observed = amount(590.0, "CHF");
+    let BalanceSuggestion::Differs { delta } = file.suggest_balance(&bank, date, &observed) else {
+        panic!("the balances should differ");
+    };
+    let directive = Directive::balance_adjustment(date, bank.clone(), delta, adjustment);
+    assert_eq!(directive.date, Date::new(2023, 1, 31));
+    file.directives.push(directive);
+    file.directives
+        .push(Directive::balance(date, bank.clone(), observed.clone()));
+    assert!(file.verify_balances().is_empty());
+    assert_eq!(
+        file.suggest_balance(&bank, date, &observed),
+        BalanceSuggestion::Matches
+    );
+    assert_eq!(
+        file.balances(None)["Equity:Opening-Balances"].get("CHF"),
+        Some(&10.0)
+    );
+}
+
+#[rstest]
+fn adjustment_transaction_should_be_flagged() {
+    let directive = Directive::balance_adjustment(
+        Date::new(2023, 2, 1),
+        account("Assets:Bank"),
+        amount(-10.0, "CHF"),
+        BalanceAdjustment::Transaction(account("Expenses:Fees")),
+    );
+    let DirectiveContent::Transaction(trx) = &directive.content else {
+        panic!("was not a transaction");
+    };
+    assert_eq!(trx.flag, Some('!'));
+    assert_eq!(trx.narration.as_deref(), Some("Balance adjustment"));
+    let postings: Vec<(&str, f64)> = trx
+        .postings
+        .iter()
+        .map(|p| (p.account.as_str(), p.amount.as_ref().unwrap().value))
+        .collect();
+    assert_eq!(postings, [("Assets:Bank", -10.0), ("Expenses:Fees", 10.0)]);
+}
+
+#[rstest]
+fn balance_directive_should_be_a_plain_assertion() {
+    let directive = Directive::balance(
+        Date::new(2023, 2, 1),
+        account("Assets:Bank"),
+        amount(590.0, "CHF"),
+    );
+    assert_eq!(
+        directive.debug_tree(),
+        "2023-02-01 balance\n  account: Assets:Bank\n  amount: 590 CHF\n"
+    );
+}
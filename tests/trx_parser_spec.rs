@@ -1,4 +1,4 @@
-#![allow(clippy::items_after_test_module, clippy::pedantic)]
+#![allow(missing_docs, clippy::items_after_test_module, clippy::pedantic)]
 
 use std::collections::HashSet;
 
@@ -55,6 +55,8 @@ fn should_find_all_postings(#[case] input: &str, #[case] expected_count: usize)
 #[case("2023-05-15 txn \"Hello world!\"", Some("Hello world!"))]
 #[case::escaped_double_quotes("2023-05-15 txn \"Hello \\\"world\\\"!\"", Some("Hello \"world\"!"))]
 #[case::escaped_backslash("2023-05-15 txn \"Hello \\\\world!\"", Some("Hello \\world!"))]
+#[case::leading_escape("2023-05-15 txn \"\\\"Hello\\\" world\"", Some("\"Hello\" world"))]
+#[case::consecutive_escapes("2023-05-15 txn \"a\\\\\\\"b\"", Some("a\\\"b"))]
 #[case("2023-05-15 txn \"payee\" \"narration\"", Some("narration"))]
 #[case(
     "2023-05-15 txn \"Hello world!\" ; And a comment",
@@ -342,6 +344,28 @@ fn should_parse_cost_date(
     assert_eq!(date.day, expected_day);
 }
 
+#[rstest]
+fn postings_should_have_their_line_number() {
+    let input = r#"
+2023-05-15 * "Groceries"
+  Expenses:Food  10 CHF
+    receipt: "42"
+  ; comment
+  ! Assets:Cash
+"#;
+    let trx = parse_single_transaction(input);
+    let lines: Vec<u32> = trx.postings.iter().map(|p| p.line_number).collect();
+    assert_eq!(lines, [3, 6]);
+}
+
+#[rstest]
+fn postings_at_different_lines_should_be_equal() {
+    let input = "2023-05-15 *\n  Assets:Cash  1 CHF\n  Assets:Cash  1 CHF";
+    let trx = parse_single_transaction(input);
+    assert_ne!(trx.postings[0].line_number, trx.postings[1].line_number);
+    assert_eq!(trx.postings[0], trx.postings[1]);
+}
+
 #[rstest]
 fn should_include_tag_stack() {
     let input = r"
@@ -382,6 +406,56 @@ poptag #foo
     );
 }
 
+#[rstest]
+#[case::txn("2023-05-15 txn", "2023-05-15 txn")]
+#[case::flag("2023-05-15 !", "2023-05-15 !")]
+#[case::narration("2023-05-15 * \"Hello\"", "2023-05-15 * \"Hello\"")]
+#[case::payee_and_narration(
+    "2023-05-15 *   \"Coop\"   \"Groceries\"",
+    "2023-05-15 * \"Coop\" \"Groceries\""
+)]
+#[case::escaped(
+    "2023-05-15 * \"a \\\"quote\\\"\" \"back\\\\slash\"",
+    "2023-05-15 * \"a \\\"quote\\\"\" \"back\\\\slash\""
+)]
+#[case::sorted_tags_and_links(
+    "2023-05-15 * \"Hello\" ^b #y ^a #x",
+    "2023-05-15 * \"Hello\" #x #y ^a ^b"
+)]
+#[case::without_postings_and_metadata(
+    "2023-05-15 * \"Hello\" ; comment\n  foo: \"bar\"\n  Assets:Cash 1 CHF\n  Income:Gift",
+    "2023-05-15 * \"Hello\""
+)]
+fn should_render_transaction_summary(#[case] input: &str, #[case] expected: &str) {
+    let directive = parse_single_directive(input);
+    let DirectiveContent::Transaction(trx) = &directive.content else {
+        panic!("was not a transaction: {directive:?}");
+    };
+    assert_eq!(trx.summary(directive.date), expected);
+}
+
+#[rstest]
+fn transaction_summary_should_parse_back_to_the_same_header(
+    #[values(SIMPLE, OFFICIAL, "2023-05-15 * \"\\\\\\\"\" \"\\\"\\\\\" #a-b_c ^x.y")] input: &str,
+) {
+    for directive in parse::<f64>(input).unwrap().directives {
+        let DirectiveContent::Transaction(trx) = &directive.content else {
+            continue;
+        };
+        let summary = trx.summary(directive.date);
+        let reparsed = parse_single_directive(&summary);
+        let DirectiveContent::Transaction(reparsed_trx) = &reparsed.content else {
+            panic!("was not a transaction: {reparsed:?}");
+        };
+        assert_eq!(reparsed.date, directive.date, "{summary}");
+        assert_eq!(reparsed_trx.flag, trx.flag, "{summary}");
+        assert_eq!(reparsed_trx.payee, trx.payee, "{summary}");
+        assert_eq!(reparsed_trx.narration, trx.narration, "{summary}");
+        assert_eq!(reparsed_trx.tags, trx.tags, "{summary}");
+        assert_eq!(reparsed_trx.links, trx.links, "{summary}");
+    }
+}
+
 #[rstest]
 fn should_reject_invalid_input(
     #[values(
@@ -0,0 +1,145 @@
+#![allow(missing_docs)]
+#![cfg(feature = "unicode-normalization")]
+
+use std::collections::HashSet;
+
+use rstest::rstest;
+
+use beancount_parser::{
+    lint::{FixedClock, LintSet, SimilarAccounts},
+    metadata, parse, parse_with_options, Account, BeancountFile, Code, Date, DirectiveContent,
+    ParseOptions, Tag, Transaction, WarningKind,
+};
+
+const NFC: &str = "Assets:B\u{fc}cher";
+const NFD: &str = "Assets:Bu\u{308}cher";
+
+fn input() -> String {
+    format!(
+        "
+2023-01-01 open {NFC}
+pushtag #bu\u{308}cher
+2023-01-02 * \"Cafe\u{301}\" \"Bu\u{308}cher\" #b\u{fc}cher ^rechnung-b\u{fc}cher
+  {NFD}  10 EUR
+    de\u{301}ja\u{300}: \"vu\u{308}\"
+  Income:Salary
+poptag #b\u{fc}cher
+2023-01-03 * \"Library\"
+  {NFD}  5 EUR
+  Income:Salary
+"
+    )
+}
+
+fn accounts(file: &BeancountFile<f64>) -> HashSet<&str> {
+    file.directives
+        .iter()
+        .flat_map(|d| match &d.content {
+            DirectiveContent::Open(open) => vec![open.account.as_str()],
+            DirectiveContent::Transaction(trx) => {
+                trx.postings.iter().map(|p| p.account.as_str()).collect()
+            }
+            _ => Vec::new(),
+        })
+        .filter(|account| account.starts_with("Assets"))
+        .collect()
+}
+
+fn transaction(file: &BeancountFile<f64>, index: usize) -> &Transaction<f64> {
+    let DirectiveContent::Transaction(trx) = &file.directives[index].content else {
+        panic!("was not a transaction");
+    };
+    trx
+}
+
+#[rstest]
+fn names_should_be_kept_as_written_by_default() {
+    let file = parse::<f64>(&input()).unwrap();
+    assert_eq!(accounts(&file), HashSet::from([NFC, NFD]));
+    let tags: HashSet<&str> = transaction(&file, 1).tags.iter().map(Tag::as_str).collect();
+    assert_eq!(tags, HashSet::from(["b\u{fc}cher", "bu\u{308}cher"]));
+    assert_eq!(transaction(&file, 2).tags.len(), 1);
+}
+
+#[rstest]
+fn normalize_nfc_should_merge_the_names_written_in_both_forms() {
+    let options = ParseOptions::default().normalize_nfc(true);
+    let file = parse_with_options::<f64>(&input(), &options).unwrap();
+    assert_eq!(accounts(&file), HashSet::from([NFC]));
+    let trx = transaction(&file, 1);
+    let tags: Vec<&str> = trx.tags.iter().map(Tag::as_str).collect();
+    assert_eq!(tags, ["b\u{fc}cher"]);
+    assert!(trx.links.contains("rechnung-b\u{fc}cher"));
+    assert!(trx.postings[0].metadata.contains_key("d\u{e9}j\u{e0}"));
+    assert!(
+        transaction(&file, 2).tags.is_empty(),
+        "the tag should have been popped"
+    );
+}
+
+#[rstest]
+fn normalize_nfc_should_share_the_allocation_of_the_merged_accounts() {
+    let options = ParseOptions::default().normalize_nfc(true);
+    let heap_size = |first: &str, second: &str, options: &ParseOptions| {
+        let input = format!("2023-01-01 open {first}\n2023-12-31 close {second}");
+        parse_with_options::<f64>(&input, options)
+            .unwrap()
+            .estimated_heap_size()
+    };
+    let merged = heap_size(NFC, NFD, &options);
+    assert_eq!(merged, heap_size(NFC, NFC, &options));
+    assert!(merged < heap_size(NFC, NFC, &ParseOptions::default()));
+}
+
+#[rstest]
+fn normalize_nfc_should_leave_the_text_as_written_by_default(
+    #[values(false, true)] normalize_text: bool,
+) {
+    let options = ParseOptions::default()
+        .normalize_nfc(true)
+        .normalize_nfc_text(normalize_text);
+    let file = parse_with_options::<f64>(&input(), &options).unwrap();
+    let trx = transaction(&file, 1);
+    let value = &trx.postings[0].metadata["d\u{e9}j\u{e0}"];
+    if normalize_text {
+        assert_eq!(trx.payee.as_deref(), Some("Caf\u{e9}"));
+        assert_eq!(trx.narration.as_deref(), Some("B\u{fc}cher"));
+        assert_eq!(value, &metadata::Value::String("v\u{fc}".into()));
+    } else {
+        assert_eq!(trx.payee.as_deref(), Some("Cafe\u{301}"));
+        assert_eq!(trx.narration.as_deref(), Some("Bu\u{308}cher"));
+        assert_eq!(value, &metadata::Value::String("vu\u{308}".into()));
+    }
+}
+
+#[rstest]
+fn similar_accounts_should_report_the_other_spellings_once() {
+    let file = parse::<f64>(&input()).unwrap();
+    let warnings = LintSet::new(FixedClock::new(Date::new(2023, 12, 31)))
+        .similar_accounts(SimilarAccounts::new())
+        .check(&file);
+    let warnings: Vec<(u32, Code, WarningKind)> = warnings
+        .into_iter()
+        .map(|w| (w.line_number(), w.code(), w.kind().clone()))
+        .collect();
+    let nfc: Account = NFC.parse().unwrap();
+    let nfd: Account = NFD.parse().unwrap();
+    assert_eq!(
+        warnings,
+        [(
+            4,
+            Code::SimilarAccounts,
+            WarningKind::SimilarAccounts(nfd, nfc)
+        )]
+    );
+}
+
+#[rstest]
+fn similar_accounts_should_not_report_normalized_accounts() {
+    let options = ParseOptions::default().normalize_nfc(true);
+    let file = parse_with_options::<f64>(&input(), &options).unwrap();
+    let warnings = LintSet::new(FixedClock::new(Date::new(2023, 12, 31)))
+        .similar_accounts(SimilarAccounts::new())
+        .check(&file);
+    assert!(warnings.is_empty(), "{warnings:?}");
+}
@@ -1,4 +1,10 @@
-use std::fmt::Debug;
+#![allow(missing_docs)]
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    fmt::Debug,
+    hash::{Hash, Hasher},
+};
 
 use rstest::rstest;
 
@@ -32,6 +38,105 @@ fn error_debug_impl_is_succinct() {
     assert!(!debug.contains("; end comment"), "{}", debug);
 }
 
+#[rstest]
+fn error_debug_output_should_be_bounded_by_the_size_of_the_snippet(
+    #[values(10, 200_000)] lines: usize,
+) {
+    let mut input = String::from("2023-06-10 open Assets:Cash\n2023-06-11 * Oops\n");
+    input.push_str(&"; padding\n".repeat(lines));
+    let err = parse::<f64>(&input).unwrap_err();
+    assert_eq!(err.line_number(), 2);
+    assert_eq!(err.snippet(), Some("2023-06-11 * Oops"));
+    let debug = format!("{err:?}");
+    assert!(
+        debug.contains(r#"snippet: Some("2023-06-11 * Oops")"#),
+        "{debug}"
+    );
+    assert!(!debug.contains("padding"), "{debug}");
+    assert!(debug.len() < 300, "{debug}");
+}
+
+#[rstest]
+#[case::default(format!("{:?}", long_line_error()), 120)]
+#[case::precision(format!("{:.20?}", long_line_error()), 20)]
+fn error_debug_output_should_truncate_long_lines(#[case] debug: String, #[case] shown: usize) {
+    assert!(
+        debug.contains(&format!("{}…\"", "a".repeat(shown - 11))),
+        "{debug}"
+    );
+    assert!(!debug.contains(&"a".repeat(shown - 10)), "{debug}");
+}
+
+fn long_line_error() -> Error {
+    let input = format!("2023-06-11 {}", "a".repeat(10_000));
+    parse::<f64>(&input).unwrap_err()
+}
+
+#[rstest]
+fn redacted_error_should_not_contain_the_input() {
+    let input = "2023-06-11 * \"Salary\" Oops\n";
+    let err = parse::<f64>(input).unwrap_err();
+    let redacted = err.clone().redacted();
+    assert_eq!(redacted.line_number(), err.line_number());
+    assert_eq!(redacted.code(), err.code());
+    assert_eq!(redacted.to_string(), err.to_string());
+    assert_eq!(redacted.snippet(), None);
+    let debug = format!("{redacted:?}");
+    assert!(!debug.contains("Salary"), "{debug}");
+    assert!(
+        debug.contains("snippet: Some(<redacted: 26 chars>)"),
+        "{debug}"
+    );
+}
+
+#[rstest]
+#[case::syntax(
+    "2023-06-10 open Assets:Cash\n2023-06-11 * Oops\n",
+    "error[E001]: Invalid beancount syntax at line: 2\n  |\n2 | 2023-06-11 * Oops\n  |              ^"
+)]
+#[case::hint("2023-06-11 blance Assets:Cash 1 USD", "error[E009]: Misspelled directive keyword at line: 1 (did you mean `balance`?)\n  |\n1 | 2023-06-11 blance Assets:Cash 1 USD\n  |            ^")]
+#[case::tab(
+    "2023-06-11 *\n\tAssets:Cash  1 USD #tag",
+    "error[E007]: Tag or link on a posting at line: 2 (tags are not allowed on postings; move #tag to the transaction header)\n  |\n2 | \tAssets:Cash  1 USD #tag\n  | \t                   ^"
+)]
+fn error_alternate_display_should_show_the_line_of_the_error(
+    #[case] input: &str,
+    #[case] expected: &str,
+) {
+    let err = parse::<f64>(input).unwrap_err();
+    assert_eq!(format!("{err:#}"), expected);
+    assert_eq!(
+        format!("{err:#}").lines().next(),
+        Some(err.to_string().as_str())
+    );
+}
+
+#[rstest]
+fn redacted_error_alternate_display_should_not_contain_the_input() {
+    let err = parse::<f64>("2023-06-11 * \"Salary\" Oops\n")
+        .unwrap_err()
+        .redacted();
+    assert_eq!(format!("{err:#}"), err.to_string());
+}
+
+#[rstest]
+fn error_should_implement_std_error() {
+    let err: Box<dyn std::error::Error + Send + Sync> = Box::new(parse::<f64>("oops").unwrap_err());
+    assert!(err.to_string().starts_with("error[E001]"));
+}
+
+#[rstest]
+fn error_should_outlive_the_input() {
+    fn parse_local_copy(input: &str) -> Result<BeancountFile<f64>, Error> {
+        let local = input.to_owned();
+        parse(&local)
+    }
+    fn is_static<T: 'static>(_: &T) {}
+    let err = parse_local_copy("2023-06-11 * Oops").unwrap_err();
+    is_static(&err);
+    assert_eq!(err.snippet(), Some("2023-06-11 * Oops"));
+}
+
 #[rstest]
 fn accounts_implements_display() {
     let account = "Expenses:Taxes:Y2021:US:Federal:PreTax401k";
@@ -97,3 +202,38 @@ fn directive_from_str(
     let from_str: Directive<f64> = input.parse().unwrap();
     assert_eq!(from_file, from_str);
 }
+
+fn hash<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[rstest]
+fn account_tag_and_link_identity_is_defined_by_content() {
+    let input =
+        "2023-07-09 * \"hello\" #trip ^invoice\n  Assets:Bank:Checking 10 CHF\n  Income:Gifts";
+    let first: BeancountFile<f64> = input.parse().unwrap();
+    let second: BeancountFile<f64> = input.parse().unwrap();
+    let DirectiveContent::Transaction(ref first) = first.directives[0].content else {
+        unreachable!("was not a transaction")
+    };
+    let DirectiveContent::Transaction(ref second) = second.directives[0].content else {
+        unreachable!("was not a transaction")
+    };
+
+    let (a, b) = (&first.postings[0].account, &second.postings[0].account);
+    assert_eq!(a, b);
+    assert_eq!(hash(a), hash(b));
+    assert_eq!(hash(a), hash("Assets:Bank:Checking"));
+
+    let (a, b) = (first.tags.iter().next(), second.tags.iter().next());
+    assert_eq!(a, b);
+    assert_eq!(hash(a.unwrap()), hash(b.unwrap()));
+    assert_eq!(hash(a.unwrap()), hash("trip"));
+
+    let (a, b) = (first.links.iter().next(), second.links.iter().next());
+    assert_eq!(a, b);
+    assert_eq!(hash(a.unwrap()), hash(b.unwrap()));
+    assert_eq!(hash(a.unwrap()), hash("invoice"));
+}